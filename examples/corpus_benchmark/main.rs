@@ -0,0 +1,50 @@
+use liquid_memory::testing::corpus::{self, CorpusGenerator};
+use liquid_memory::testing::hash_embedder::HashEmbedder;
+use liquid_memory::vectorstore::in_memory::InMemoryVectorStore;
+use liquid_memory::vectorstore::vector_store::VectorStore;
+use std::time::{Duration, Instant};
+
+#[tokio::main]
+async fn main() {
+    let generator = CorpusGenerator::new(42)
+        .with_documents(10_000)
+        .with_length(20, 120)
+        .with_duplicate_ratio(0.05);
+    let documents = generator.generate();
+    let queries = generator.queries(200);
+    let embedder = HashEmbedder::new(256);
+    let store = InMemoryVectorStore::new();
+
+    let started = Instant::now();
+    corpus::load_into(&store, "corpus", &documents, &embedder)
+        .await
+        .unwrap();
+    println!(
+        "Ingested {} documents in {:?}",
+        documents.len(),
+        started.elapsed()
+    );
+
+    let mut latencies: Vec<Duration> = Vec::with_capacity(queries.len());
+    let mut on_topic = 0;
+    for query in &queries {
+        let started = Instant::now();
+        let hits = store
+            .query("corpus", embedder.embed_one(&query.text), 10)
+            .await
+            .unwrap();
+        latencies.push(started.elapsed());
+        on_topic += hits
+            .iter()
+            .filter(|hit| hit.payload["topic"] == query.topic.as_str())
+            .count();
+    }
+    latencies.sort();
+    println!(
+        "{} queries: p50 {:?}, p95 {:?}, topic precision@10 {:.3}",
+        queries.len(),
+        latencies[latencies.len() / 2],
+        latencies[latencies.len() * 95 / 100],
+        on_topic as f64 / (queries.len() * 10) as f64
+    );
+}
@@ -1,7 +1,5 @@
 use liquid_memory::llm::llm_client::LlmClientChat;
 use liquid_memory::llm::openai::OpenAIClient;
-use liquid_memory::vectorstore::qdrant_client::QdrantClient;
-use tokio;
 
 #[tokio::main]
 async fn main() {
@@ -1,11 +1,17 @@
+use liquid_memory::devstack::{DevStack, Service};
 use liquid_memory::embeddings::text_embedding_inference::TextEmbeddingInference;
 use liquid_memory::vectorstore::qdrant_client::{texts_to_payload, QdrantClient};
-use tokio;
 
 use qdrant_client::qdrant::{Distance, VectorParamsBuilder};
 
 #[tokio::main]
 async fn main() {
+    // Starts Qdrant and Text Embedding Inference in Docker unless they are already running
+    DevStack::new(vec![Service::qdrant(), Service::tei_text()])
+        .up()
+        .await
+        .unwrap();
+
     let client = QdrantClient::new("http://localhost:6334");
 
     let collection_name = "test_collection";
@@ -34,13 +40,14 @@ async fn main() {
     let tei_client = TextEmbeddingInference::new(Some("http://localhost:8888"));
     let embeddings = tei_client.embed(sentences.clone()).await.unwrap();
 
+    let query = embeddings[0].clone();
     client
-        .upsert_points(collection_name, embeddings.clone(), payload)
+        .upsert_points(collection_name, embeddings, payload)
         .await
         .unwrap();
 
     let response = client
-        .query_points(collection_name, embeddings[0].clone(), 5)
+        .query_points(collection_name, query, 5)
         .await
         .unwrap();
 
@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use liquid_memory::testing::invariants::check_chunk_text;
+
+fuzz_target!(|input: (u8, &str)| {
+    let (max_tokens, text) = input;
+    check_chunk_text(text, max_tokens as usize);
+});
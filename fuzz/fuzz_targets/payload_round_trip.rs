@@ -0,0 +1,25 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use liquid_memory::testing::invariants::check_payload_round_trip;
+use liquid_memory::vectorstore::interop::{Document, PayloadConvention};
+use serde_json::Value as JsonValue;
+
+// Input: convention selector, then `text\0{metadata json}`
+fuzz_target!(|input: (u8, &str)| {
+    let (selector, data) = input;
+    let convention = match selector % 3 {
+        0 => PayloadConvention::LiquidMemory,
+        1 => PayloadConvention::LangChain,
+        _ => PayloadConvention::LlamaIndex,
+    };
+    let (text, metadata) = data.split_once('\0').unwrap_or((data, "{}"));
+    let Ok(JsonValue::Object(metadata)) = serde_json::from_str(metadata) else {
+        return;
+    };
+    let mut document = Document::new(text).with_metadata(metadata);
+    if convention == PayloadConvention::LlamaIndex {
+        document = document.with_node_id(text);
+    }
+    check_payload_round_trip(&document, convention);
+});
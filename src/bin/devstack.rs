@@ -0,0 +1,53 @@
+use liquid_memory::devstack::{DevStack, Service};
+use std::env;
+use std::process::ExitCode;
+
+const USAGE: &str =
+    "usage: liquid-memory-devstack <up|down|status> [qdrant|tei-text|tei-image|ollama]...";
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    liquid_memory::logging::init_stderr();
+    let args: Vec<String> = env::args().skip(1).collect();
+    let Some((command, names)) = args.split_first() else {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    };
+    let stack = if names.is_empty() {
+        DevStack::all()
+    } else {
+        let mut services = Vec::with_capacity(names.len());
+        for name in names {
+            match Service::by_name(name) {
+                Some(service) => services.push(service),
+                None => {
+                    eprintln!("Unknown service {name}\n{USAGE}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        DevStack::new(services)
+    };
+
+    let result = match command.as_str() {
+        "up" => stack.up().await,
+        "down" => stack.down().await,
+        "status" => {
+            for (name, healthy) in stack.status().await {
+                println!("{name}: {}", if healthy { "healthy" } else { "down" });
+            }
+            Ok(())
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use liquid_memory::embeddings::text_embedding_inference::TextEmbeddingInference;
+use liquid_memory::mcp::server::McpServer;
+use liquid_memory::memory::embedder_binding::EmbedderBinding;
+use liquid_memory::memory::memory_store::MemoryStore;
+use liquid_memory::memory::ownership::AgentIdentity;
+use liquid_memory::memory::sensitivity::Sensitivity;
+use liquid_memory::vectorstore::hedging::RequestHedging;
+use liquid_memory::vectorstore::qdrant_client::QdrantClient;
+use std::env;
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    liquid_memory::logging::init_stderr();
+    let qdrant_url = env::var("QDRANT_URL").unwrap_or("http://localhost:6334".to_string());
+    let read_urls = env::var("QDRANT_READ_URLS").unwrap_or_default();
+    let read_urls: Vec<&str> = read_urls.split(',').filter(|url| !url.is_empty()).collect();
+    let embedding_url = env::var("EMBEDDING_URL").unwrap_or("http://localhost:8888".to_string());
+    let collection_name = env::var("MEMORY_COLLECTION").unwrap_or("memories".to_string());
+    let access_level = match env::var("MEMORY_ACCESS_LEVEL") {
+        Ok(level) => serde_json::from_value(serde_json::json!(level))
+            .expect("MEMORY_ACCESS_LEVEL must be public, internal or secret"),
+        Err(_) => Sensitivity::Secret,
+    };
+
+    let embedder = TextEmbeddingInference::new(Some(&embedding_url));
+    let binding = EmbedderBinding::from_tei(&embedder).await;
+    let mut qdrant = QdrantClient::new(&qdrant_url)
+        .with_read_replicas(&read_urls)
+        .expect("QDRANT_READ_URLS must be comma-separated URLs");
+    if let Ok(percentile) = env::var("QDRANT_HEDGE_PERCENTILE") {
+        let percentile = percentile
+            .parse()
+            .expect("QDRANT_HEDGE_PERCENTILE must be a number between 0 and 1");
+        qdrant = qdrant.with_hedging(RequestHedging::new().with_percentile(percentile));
+    }
+    let mut store =
+        MemoryStore::new(qdrant, embedder, collection_name).with_access_level(access_level);
+    if let Ok(agent_id) = env::var("MEMORY_AGENT_ID") {
+        let mut agent = AgentIdentity::new(agent_id);
+        if let Ok(team) = env::var("MEMORY_AGENT_TEAM") {
+            agent = agent.with_team(team);
+        }
+        store = store.with_agent(agent);
+    }
+    match binding {
+        Ok(binding) => store = store.with_embedder_binding(binding),
+        Err(e) => eprintln!("Could not read embedding model, collections are not bound: {e}"),
+    }
+    if let Err(e) = store.warm_up().await {
+        eprintln!("Warm-up failed: {e}");
+    }
+    McpServer::new(store).serve_stdio().await
+}
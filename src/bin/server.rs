@@ -0,0 +1,30 @@
+use liquid_memory::embeddings::text_embedding_inference::TextEmbeddingInference;
+use liquid_memory::metrics::ClientMetrics;
+use liquid_memory::server::embeddings::{router, EmbeddingProxy};
+use std::env;
+use std::time::Duration;
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    liquid_memory::logging::init_stderr();
+    let bind_addr = env::var("BIND_ADDR").unwrap_or("0.0.0.0:8080".to_string());
+    let embedding_url = env::var("EMBEDDING_URL").unwrap_or("http://localhost:8888".to_string());
+    let max_batch_size = env::var("EMBEDDING_MAX_BATCH_SIZE")
+        .ok()
+        .and_then(|size| size.parse().ok());
+
+    let mut metrics = ClientMetrics::new("tei");
+    if let Some(slow_ms) = env::var("SLOW_REQUEST_MS")
+        .ok()
+        .and_then(|ms| ms.parse().ok())
+    {
+        metrics = metrics.with_slow_threshold(Duration::from_millis(slow_ms));
+    }
+
+    let proxy = EmbeddingProxy::new(
+        TextEmbeddingInference::new(Some(&embedding_url)).with_metrics(metrics),
+        max_batch_size,
+    );
+    let listener = tokio::net::TcpListener::bind(&bind_addr).await?;
+    axum::serve(listener, router(proxy)).await
+}
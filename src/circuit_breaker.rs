@@ -0,0 +1,446 @@
+use crate::llm::llm_client::{ChatMessage, LlmClientChat};
+use qdrant_client::QdrantError;
+use reqwest::StatusCode;
+use serde::Serialize;
+use std::convert::Infallible;
+use std::future::Future;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// Requests go through.
+    Closed,
+    /// Requests fail fast until `open_for` has passed.
+    Open,
+    /// One probe request goes through; its outcome closes or reopens the circuit.
+    HalfOpen,
+}
+
+#[derive(Debug, Clone, Error)]
+#[error("Circuit of {name} is open, retry in {retry_in:?}")]
+pub struct CircuitOpenError {
+    pub name: String,
+    pub retry_in: Duration,
+}
+
+impl From<CircuitOpenError> for QdrantError {
+    fn from(e: CircuitOpenError) -> Self {
+        QdrantError::Io(std::io::Error::other(e))
+    }
+}
+
+/// Whether an error means the provider is unhealthy: unreachable, timing out, failing with
+/// a 5xx or rate limiting. Only such errors count towards opening a circuit; a rejected
+/// request (another 4xx, a model without vision support) is the caller's mistake and
+/// says nothing about the provider.
+pub trait TransientError {
+    fn is_transient(&self) -> bool;
+}
+
+/// Statuses of a provider that is down or overloaded: 5xx and 429.
+pub fn is_transient_status(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+impl TransientError for reqwest::Error {
+    fn is_transient(&self) -> bool {
+        match self.status() {
+            Some(status) => is_transient_status(status),
+            None => self.is_timeout() || self.is_connect() || self.is_request(),
+        }
+    }
+}
+
+impl TransientError for std::io::Error {
+    fn is_transient(&self) -> bool {
+        true
+    }
+}
+
+// Errors of the embedding client, transient if the request itself failed
+impl TransientError for Box<dyn std::error::Error> {
+    fn is_transient(&self) -> bool {
+        if let Some(e) = self.downcast_ref::<reqwest::Error>() {
+            return e.is_transient();
+        }
+        self.is::<std::io::Error>()
+    }
+}
+
+impl TransientError for QdrantError {
+    fn is_transient(&self) -> bool {
+        match self {
+            // The gRPC codes are the same across tonic versions
+            QdrantError::ResponseError { status } => matches!(
+                tonic::Code::from(status.code() as i32),
+                tonic::Code::Unavailable
+                    | tonic::Code::DeadlineExceeded
+                    | tonic::Code::ResourceExhausted
+                    | tonic::Code::Aborted
+                    | tonic::Code::Internal
+                    | tonic::Code::Unknown
+            ),
+            QdrantError::ResourceExhaustedError { .. } | QdrantError::Io(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl TransientError for Infallible {
+    fn is_transient(&self) -> bool {
+        match *self {}
+    }
+}
+
+type StateCallback = Box<dyn Fn(&str, CircuitState, CircuitState) + Send + Sync>;
+
+struct BreakerState {
+    state: CircuitState,
+    consecutive_failures: u32,
+    opened_at: Instant,
+    probing: bool,
+}
+
+/// Fails requests to a provider fast once it looks down: the circuit opens after
+/// `failure_threshold` consecutive failures, rejects requests for `open_for`, then lets
+/// one probe through and closes again if it succeeds.
+pub struct CircuitBreaker {
+    name: String,
+    failure_threshold: u32,
+    open_for: Duration,
+    state: Mutex<BreakerState>,
+    on_state_change: Option<StateCallback>,
+}
+
+// Clears the probe flag of a half-open circuit if the probe is dropped before finishing
+struct Attempt<'a> {
+    breaker: &'a CircuitBreaker,
+    finished: bool,
+}
+
+impl Drop for Attempt<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.breaker.state.lock().unwrap().probing = false;
+        }
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new(name: impl Into<String>, failure_threshold: u32, open_for: Duration) -> Self {
+        Self {
+            name: name.into(),
+            failure_threshold: failure_threshold.max(1),
+            open_for,
+            state: Mutex::new(BreakerState {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                opened_at: Instant::now(),
+                probing: false,
+            }),
+            on_state_change: None,
+        }
+    }
+
+    /// Called with the breaker name, the previous and the new state on every transition,
+    /// e.g. to log or page when a provider goes down.
+    pub fn with_on_state_change(
+        mut self,
+        on_state_change: impl Fn(&str, CircuitState, CircuitState) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_state_change = Some(Box::new(on_state_change));
+        self
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.state.lock().unwrap().state
+    }
+
+    fn transition(&self, from: CircuitState, to: CircuitState) {
+        if from == to {
+            return;
+        }
+        if let Some(on_state_change) = &self.on_state_change {
+            on_state_change(&self.name, from, to);
+        }
+    }
+
+    fn acquire(&self) -> Result<(), CircuitOpenError> {
+        let (from, to) = {
+            let mut state = self.state.lock().unwrap();
+            let from = state.state;
+            match state.state {
+                CircuitState::Closed => return Ok(()),
+                CircuitState::Open => {
+                    let elapsed = state.opened_at.elapsed();
+                    if elapsed < self.open_for {
+                        return Err(CircuitOpenError {
+                            name: self.name.clone(),
+                            retry_in: self.open_for - elapsed,
+                        });
+                    }
+                    state.state = CircuitState::HalfOpen;
+                }
+                CircuitState::HalfOpen if state.probing => {
+                    return Err(CircuitOpenError {
+                        name: self.name.clone(),
+                        retry_in: Duration::ZERO,
+                    });
+                }
+                CircuitState::HalfOpen => {}
+            }
+            state.probing = true;
+            (from, state.state)
+        };
+        self.transition(from, to);
+        Ok(())
+    }
+
+    fn record(&self, success: bool) {
+        let (from, to) = {
+            let mut state = self.state.lock().unwrap();
+            let from = state.state;
+            state.probing = false;
+            if success {
+                state.consecutive_failures = 0;
+                state.state = CircuitState::Closed;
+            } else {
+                state.consecutive_failures += 1;
+                if from == CircuitState::HalfOpen
+                    || state.consecutive_failures >= self.failure_threshold
+                {
+                    state.state = CircuitState::Open;
+                    state.opened_at = Instant::now();
+                }
+            }
+            (from, state.state)
+        };
+        self.transition(from, to);
+    }
+
+    /// Runs `request` unless the circuit is open, in which case it fails with
+    /// [`CircuitOpenError`] without being sent. Only [transient](TransientError) errors
+    /// count as failures; any other outcome counts as a success.
+    pub async fn call<T, E: From<CircuitOpenError> + TransientError>(
+        &self,
+        request: impl Future<Output = Result<T, E>>,
+    ) -> Result<T, E> {
+        self.acquire()?;
+        let mut attempt = Attempt {
+            breaker: self,
+            finished: false,
+        };
+        let result = request.await;
+        attempt.finished = true;
+        self.record(!result.as_ref().is_err_and(E::is_transient));
+        result
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum GuardedError<E: std::error::Error + 'static> {
+    #[error(transparent)]
+    Open(#[from] CircuitOpenError),
+    #[error(transparent)]
+    Provider(E),
+}
+
+impl<E: std::error::Error + TransientError + 'static> TransientError for GuardedError<E> {
+    fn is_transient(&self) -> bool {
+        match self {
+            Self::Open(_) => false,
+            Self::Provider(e) => e.is_transient(),
+        }
+    }
+}
+
+/// Chat client behind a [`CircuitBreaker`]. Clients made with [`LlmClientChat::new`] open
+/// after 5 consecutive failures for 30 seconds.
+pub struct GuardedChat<C: LlmClientChat> {
+    client: C,
+    circuit_breaker: CircuitBreaker,
+}
+
+impl<C: LlmClientChat> GuardedChat<C> {
+    pub fn wrap(client: C, circuit_breaker: CircuitBreaker) -> Self {
+        Self {
+            client,
+            circuit_breaker,
+        }
+    }
+
+    pub fn client(&self) -> &C {
+        &self.client
+    }
+
+    pub fn circuit_breaker(&self) -> &CircuitBreaker {
+        &self.circuit_breaker
+    }
+}
+
+impl<C: LlmClientChat> LlmClientChat for GuardedChat<C>
+where
+    C::Error: TransientError,
+{
+    type Error = GuardedError<C::Error>;
+
+    fn new(base_url: Option<&str>, api_key: Option<&str>) -> Self {
+        Self::wrap(
+            C::new(base_url, api_key),
+            CircuitBreaker::new("llm", 5, Duration::from_secs(30)),
+        )
+    }
+
+    async fn send_message(
+        &self,
+        model: impl Into<String>,
+        text: impl AsRef<str>,
+        image_path: Option<impl AsRef<Path>>,
+        temperature: Option<f32>,
+    ) -> Result<String, Self::Error> {
+        self.circuit_breaker
+            .call(async {
+                self.client
+                    .send_message(model, text, image_path, temperature)
+                    .await
+                    .map_err(GuardedError::Provider)
+            })
+            .await
+    }
+
+    async fn send_message_json(
+        &self,
+        model: impl Into<String>,
+        text: impl AsRef<str>,
+        temperature: Option<f32>,
+    ) -> Result<String, Self::Error> {
+        self.circuit_breaker
+            .call(async {
+                self.client
+                    .send_message_json(model, text, temperature)
+                    .await
+                    .map_err(GuardedError::Provider)
+            })
+            .await
+    }
+
+    async fn send_conversation(
+        &self,
+        model: impl Into<String>,
+        system: Option<&str>,
+        messages: &[ChatMessage],
+        temperature: Option<f32>,
+    ) -> Result<String, Self::Error> {
+        self.circuit_breaker
+            .call(async {
+                self.client
+                    .send_conversation(model, system, messages, temperature)
+                    .await
+                    .map_err(GuardedError::Provider)
+            })
+            .await
+    }
+
+    async fn warm_up(&self) -> Result<(), Self::Error> {
+        self.circuit_breaker
+            .call(async { self.client.warm_up().await.map_err(GuardedError::Provider) })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[derive(Debug, PartialEq)]
+    enum TestError {
+        Failed,
+        Open,
+    }
+
+    impl From<CircuitOpenError> for TestError {
+        fn from(_: CircuitOpenError) -> Self {
+            TestError::Open
+        }
+    }
+
+    impl TransientError for TestError {
+        fn is_transient(&self) -> bool {
+            *self == TestError::Failed
+        }
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker() {
+        let transitions = Arc::new(Mutex::new(Vec::new()));
+        let recorded = transitions.clone();
+        let breaker = CircuitBreaker::new("tei", 2, Duration::from_millis(20))
+            .with_on_state_change(move |_, _, to| recorded.lock().unwrap().push(to));
+
+        for _ in 0..2 {
+            let failed = breaker
+                .call(async { Err::<(), _>(TestError::Failed) })
+                .await;
+            assert_eq!(failed, Err(TestError::Failed));
+        }
+        assert_eq!(breaker.state(), CircuitState::Open);
+        let rejected = breaker.call(async { Ok::<_, TestError>(()) }).await;
+        assert_eq!(rejected, Err(TestError::Open));
+
+        tokio::time::sleep(Duration::from_millis(25)).await;
+        assert_eq!(breaker.call(async { Ok::<_, TestError>(1) }).await, Ok(1));
+        assert_eq!(
+            *transitions.lock().unwrap(),
+            vec![
+                CircuitState::Open,
+                CircuitState::HalfOpen,
+                CircuitState::Closed
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_probe_reopens() {
+        let breaker = CircuitBreaker::new("qdrant", 1, Duration::ZERO);
+        let _ = breaker
+            .call(async { Err::<(), _>(TestError::Failed) })
+            .await;
+        assert_eq!(breaker.state(), CircuitState::Open);
+        let _ = breaker
+            .call(async { Err::<(), _>(TestError::Failed) })
+            .await;
+        assert_eq!(breaker.state(), CircuitState::Open);
+    }
+
+    #[tokio::test]
+    async fn test_client_errors_do_not_open() {
+        use crate::llm::openai::OpenAIError;
+
+        let api_error = |status| {
+            Err::<(), _>(GuardedError::Provider(OpenAIError::ApiError {
+                status,
+                message: String::new(),
+            }))
+        };
+        let breaker = CircuitBreaker::new("openai", 2, Duration::from_secs(60));
+        for _ in 0..3 {
+            let _ = breaker
+                .call(async { api_error(StatusCode::BAD_REQUEST) })
+                .await;
+        }
+        assert_eq!(breaker.state(), CircuitState::Closed);
+
+        for status in [
+            StatusCode::TOO_MANY_REQUESTS,
+            StatusCode::SERVICE_UNAVAILABLE,
+        ] {
+            let _ = breaker.call(async { api_error(status) }).await;
+        }
+        assert_eq!(breaker.state(), CircuitState::Open);
+    }
+}
@@ -0,0 +1,340 @@
+use crate::models::ModelRegistry;
+use crate::utils::estimate_tokens;
+use crate::vectorstore::ingestion::IngestReport;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+// Tokens billed for one image at high detail by OpenAI-style vision models (512px tiles)
+const IMAGE_INPUT_TOKENS: usize = 765;
+
+// Payload overhead per point besides the text: timestamp, source, field names
+const PAYLOAD_OVERHEAD_BYTES: u64 = 128;
+
+// Bytes of text per estimated token, the inverse of `estimate_tokens`
+const BYTES_PER_TOKEN: u64 = 4;
+
+/// USD per million tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ModelPrice {
+    pub input_per_mtok: f64,
+    pub output_per_mtok: f64,
+}
+
+impl ModelPrice {
+    pub fn new(input_per_mtok: f64, output_per_mtok: f64) -> Self {
+        Self {
+            input_per_mtok,
+            output_per_mtok,
+        }
+    }
+
+    pub fn cost(&self, input_tokens: usize, output_tokens: usize) -> f64 {
+        (input_tokens as f64 * self.input_per_mtok + output_tokens as f64 * self.output_per_mtok)
+            / 1_000_000.0
+    }
+}
+
+/// Prices by model name, falling back to the longest priced prefix of a name like
+/// [`ModelRegistry`] lookups. The default table has the prices of the default registry;
+/// override them with [`with_price`](Self::with_price), and give self-hosted models
+/// (e.g. behind TEI) a zero price so they are not reported unpriced.
+#[derive(Debug, Clone)]
+pub struct PriceTable {
+    prices: HashMap<String, ModelPrice>,
+}
+
+impl Default for PriceTable {
+    fn default() -> Self {
+        ModelRegistry::default().price_table()
+    }
+}
+
+impl PriceTable {
+    pub fn empty() -> Self {
+        Self {
+            prices: HashMap::new(),
+        }
+    }
+
+    pub fn with_price(mut self, model: impl Into<String>, price: ModelPrice) -> Self {
+        self.prices.insert(model.into(), price);
+        self
+    }
+
+    pub fn price(&self, model: &str) -> Option<ModelPrice> {
+        if let Some(price) = self.prices.get(model) {
+            return Some(*price);
+        }
+        self.prices
+            .iter()
+            .filter(|(name, _)| model.starts_with(name.as_str()))
+            .max_by_key(|(name, _)| name.len())
+            .map(|(_, price)| *price)
+    }
+}
+
+/// Size of the corpus a pipeline will run over.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CorpusStats {
+    pub documents: usize,
+    /// Estimated tokens of all documents.
+    pub tokens: usize,
+    pub images: usize,
+}
+
+impl CorpusStats {
+    pub fn from_texts(texts: &[String]) -> Self {
+        Self {
+            documents: texts.len(),
+            tokens: texts.iter().map(|text| estimate_tokens(text)).sum(),
+            images: 0,
+        }
+    }
+
+    pub fn with_images(mut self, images: usize) -> Self {
+        self.images = images;
+        self
+    }
+}
+
+impl From<&IngestReport> for CorpusStats {
+    /// Stats of a dry run, with its points as documents so chunking is not applied twice.
+    fn from(report: &IngestReport) -> Self {
+        Self {
+            documents: report.points,
+            tokens: report.embedding_tokens,
+            images: 0,
+        }
+    }
+}
+
+/// What one LLM call is made for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum StepUnit {
+    Document,
+    Chunk,
+    Image,
+}
+
+/// An LLM call made for every document, chunk or image, e.g. captioning or summarizing.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LlmStep {
+    pub name: String,
+    pub model: String,
+    pub per: StepUnit,
+    /// Tokens of the prompt around the unit's content.
+    pub prompt_tokens: usize,
+    /// Expected tokens of each answer.
+    pub output_tokens: usize,
+}
+
+/// The model calls and storage of an ingestion pipeline.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PipelineSpec {
+    pub embedding_model: String,
+    /// Summed dimensions of the vectors stored per point.
+    pub embedding_dimensions: u64,
+    /// Chunk size in estimated tokens, documents are embedded whole if `None`.
+    pub chunk_tokens: Option<usize>,
+    pub llm_steps: Vec<LlmStep>,
+}
+
+impl PipelineSpec {
+    pub fn new(embedding_model: impl Into<String>, embedding_dimensions: u64) -> Self {
+        Self {
+            embedding_model: embedding_model.into(),
+            embedding_dimensions,
+            chunk_tokens: None,
+            llm_steps: Vec::new(),
+        }
+    }
+
+    pub fn with_chunk_tokens(mut self, chunk_tokens: usize) -> Self {
+        self.chunk_tokens = Some(chunk_tokens);
+        self
+    }
+
+    pub fn with_llm_step(
+        mut self,
+        name: impl Into<String>,
+        model: impl Into<String>,
+        per: StepUnit,
+        prompt_tokens: usize,
+        output_tokens: usize,
+    ) -> Self {
+        self.llm_steps.push(LlmStep {
+            name: name.into(),
+            model: model.into(),
+            per,
+            prompt_tokens,
+            output_tokens,
+        });
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StepCost {
+    pub name: String,
+    pub model: String,
+    pub calls: usize,
+    pub input_tokens: usize,
+    pub output_tokens: usize,
+    /// `None` if the model is not in the price table.
+    pub cost_usd: Option<f64>,
+}
+
+/// Projected cost and storage of running a pipeline over a corpus.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CostReport {
+    pub chunks: usize,
+    /// Stored points: chunks and images.
+    pub points: usize,
+    pub embedding_tokens: usize,
+    pub embedding_cost_usd: Option<f64>,
+    pub llm_steps: Vec<StepCost>,
+    /// Sum of the priced costs.
+    pub total_cost_usd: f64,
+    /// Models missing from the price table, whose cost is not in the total.
+    pub unpriced_models: Vec<String>,
+    /// Estimated bytes of vectors and payloads, without index overhead.
+    pub storage_bytes: u64,
+}
+
+impl CostReport {
+    /// Plain-text summary for budgeting.
+    pub fn report(&self) -> String {
+        let mut report = format!(
+            "{} points ({} chunks), ~{:.1} MiB, ~${:.2}\n",
+            self.points,
+            self.chunks,
+            self.storage_bytes as f64 / (1024.0 * 1024.0),
+            self.total_cost_usd
+        );
+        let usd = |cost: Option<f64>| cost.map_or("unpriced".to_string(), |c| format!("${c:.2}"));
+        let _ = writeln!(
+            report,
+            "  embedding: {} tokens, {}",
+            self.embedding_tokens,
+            usd(self.embedding_cost_usd)
+        );
+        for step in &self.llm_steps {
+            let _ = writeln!(
+                report,
+                "  {} ({}): {} calls, {} in / {} out tokens, {}",
+                step.name,
+                step.model,
+                step.calls,
+                step.input_tokens,
+                step.output_tokens,
+                usd(step.cost_usd)
+            );
+        }
+        if !self.unpriced_models.is_empty() {
+            let _ = writeln!(report, "Unpriced: {}", self.unpriced_models.join(", "));
+        }
+        report
+    }
+}
+
+/// Projects the tokens, cost and storage of running `pipeline` over a corpus, e.g. one
+/// measured with a dry run of the ingestion (see [`CorpusStats`]'s `From<&IngestReport>`).
+pub fn estimate_cost(
+    pipeline: &PipelineSpec,
+    corpus: &CorpusStats,
+    prices: &PriceTable,
+) -> CostReport {
+    let chunks = match pipeline.chunk_tokens {
+        Some(chunk_tokens) => corpus
+            .tokens
+            .div_ceil(chunk_tokens.max(1))
+            .max(corpus.documents),
+        None => corpus.documents,
+    };
+    let points = chunks + corpus.images;
+
+    let mut unpriced_models = Vec::new();
+    let mut price = |model: &str| {
+        let price = prices.price(model);
+        if price.is_none() && !unpriced_models.iter().any(|known| known == model) {
+            unpriced_models.push(model.to_string());
+        }
+        price
+    };
+
+    let embedding_cost_usd =
+        price(&pipeline.embedding_model).map(|price| price.cost(corpus.tokens, 0));
+
+    let llm_steps: Vec<StepCost> = pipeline
+        .llm_steps
+        .iter()
+        .map(|step| {
+            let (calls, content_tokens) = match step.per {
+                StepUnit::Document => (corpus.documents, corpus.tokens),
+                StepUnit::Chunk => (chunks, corpus.tokens),
+                StepUnit::Image => (corpus.images, corpus.images * IMAGE_INPUT_TOKENS),
+            };
+            let input_tokens = content_tokens + calls * step.prompt_tokens;
+            let output_tokens = calls * step.output_tokens;
+            StepCost {
+                name: step.name.clone(),
+                model: step.model.clone(),
+                calls,
+                input_tokens,
+                output_tokens,
+                cost_usd: price(&step.model).map(|price| price.cost(input_tokens, output_tokens)),
+            }
+        })
+        .collect();
+
+    let total_cost_usd = embedding_cost_usd.unwrap_or(0.0)
+        + llm_steps
+            .iter()
+            .filter_map(|step| step.cost_usd)
+            .sum::<f64>();
+    let storage_bytes = points as u64
+        * (pipeline.embedding_dimensions * 4 + PAYLOAD_OVERHEAD_BYTES)
+        + corpus.tokens as u64 * BYTES_PER_TOKEN;
+
+    CostReport {
+        chunks,
+        points,
+        embedding_tokens: corpus.tokens,
+        embedding_cost_usd,
+        llm_steps,
+        total_cost_usd,
+        unpriced_models,
+        storage_bytes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_cost() {
+        let pipeline = PipelineSpec::new("text-embedding-3-small", 1536)
+            .with_chunk_tokens(500)
+            .with_llm_step("summary", "gpt-4o-mini", StepUnit::Document, 100, 200)
+            .with_llm_step("caption", "llava", StepUnit::Image, 50, 100);
+        let corpus = CorpusStats {
+            documents: 10,
+            tokens: 1_000_000,
+            images: 4,
+        };
+
+        let report = estimate_cost(&pipeline, &corpus, &PriceTable::default());
+        assert_eq!(report.chunks, 2000);
+        assert_eq!(report.points, 2004);
+        assert_eq!(report.embedding_cost_usd, Some(0.02));
+        let summary = &report.llm_steps[0];
+        assert_eq!((summary.calls, summary.input_tokens), (10, 1_001_000));
+        assert!((summary.cost_usd.unwrap() - (1.001 * 0.15 + 0.002 * 0.6)).abs() < 1e-9);
+        assert_eq!(report.llm_steps[1].cost_usd, None);
+        assert_eq!(report.unpriced_models, vec!["llava".to_string()]);
+        assert!((report.total_cost_usd - 0.02 - summary.cost_usd.unwrap()).abs() < 1e-9);
+        assert!(report.report().contains("caption (llava): 4 calls"));
+    }
+}
@@ -0,0 +1,3 @@
+#[cfg(feature = "onnx")]
+pub mod onnx;
+pub mod region_detector;
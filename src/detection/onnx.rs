@@ -0,0 +1,94 @@
+use super::region_detector::{
+    decode_yolo_output, non_max_suppression, DetectionError, Region, RegionDetector,
+};
+use image::imageops::FilterType;
+use image::DynamicImage;
+use std::path::Path;
+use tract_onnx::prelude::*;
+
+fn model_error(e: impl std::fmt::Display) -> DetectionError {
+    DetectionError::ModelError(e.to_string())
+}
+
+/// Object detector running a YOLOv8-format ONNX model (`[1, 3, size, size]` RGB input,
+/// `[1, 4 + classes, anchors]` output), e.g. one exported with `yolo export format=onnx`.
+pub struct OnnxDetector {
+    plan: Arc<TypedRunnableModel>,
+    input_size: u32,
+    labels: Vec<String>,
+    score_threshold: f32,
+    iou_threshold: f32,
+}
+
+impl OnnxDetector {
+    /// `labels` are the class names in model order.
+    pub fn load(
+        model_path: impl AsRef<Path>,
+        input_size: Option<u32>,
+        labels: Vec<String>,
+    ) -> Result<Self, DetectionError> {
+        let input_size = input_size.unwrap_or(640);
+        let size = input_size as usize;
+        let plan = tract_onnx::onnx()
+            .model_for_path(model_path)
+            .and_then(|model| model.with_input_fact(0, f32::fact([1, 3, size, size]).into()))
+            .and_then(|model| model.into_optimized())
+            .and_then(|model| model.into_runnable())
+            .map_err(model_error)?;
+
+        Ok(Self {
+            plan,
+            input_size,
+            labels,
+            score_threshold: 0.25,
+            iou_threshold: 0.45,
+        })
+    }
+
+    pub fn with_thresholds(mut self, score_threshold: f32, iou_threshold: f32) -> Self {
+        self.score_threshold = score_threshold;
+        self.iou_threshold = iou_threshold;
+        self
+    }
+
+    fn preprocess(&self, image: &DynamicImage) -> Tensor {
+        let size = self.input_size;
+        let resized = image
+            .resize_exact(size, size, FilterType::Triangle)
+            .to_rgb8();
+        tract_ndarray::Array4::from_shape_fn(
+            (1, 3, size as usize, size as usize),
+            |(_, channel, y, x)| resized.get_pixel(x as u32, y as u32)[channel] as f32 / 255.0,
+        )
+        .into()
+    }
+}
+
+impl RegionDetector for OnnxDetector {
+    fn detect(&self, image: &DynamicImage) -> Result<Vec<Region>, DetectionError> {
+        let outputs = self
+            .plan
+            .run(tvec!(self.preprocess(image).into()))
+            .map_err(model_error)?;
+        let output = outputs[0]
+            .to_plain_array_view::<f32>()
+            .map_err(model_error)?;
+        let num_anchors = *output
+            .shape()
+            .last()
+            .ok_or_else(|| model_error("empty model output"))?;
+        let output: Vec<f32> = output.iter().copied().collect();
+
+        let regions = decode_yolo_output(
+            &output,
+            num_anchors,
+            &self.labels,
+            self.score_threshold,
+            image.width() as f32 / self.input_size as f32,
+            image.height() as f32 / self.input_size as f32,
+            image.width(),
+            image.height(),
+        );
+        Ok(non_max_suppression(regions, self.iou_threshold))
+    }
+}
@@ -0,0 +1,181 @@
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum DetectionError {
+    #[error("Image Error: {0}")]
+    ImageError(#[from] image::ImageError),
+    #[error("Model Error: {0}")]
+    ModelError(String),
+}
+
+/// Pixel coordinates in the original image.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl BoundingBox {
+    fn area(&self) -> f32 {
+        (self.width * self.height) as f32
+    }
+
+    /// Intersection over union.
+    pub fn iou(&self, other: &BoundingBox) -> f32 {
+        let x1 = self.x.max(other.x);
+        let y1 = self.y.max(other.y);
+        let x2 = (self.x + self.width).min(other.x + other.width);
+        let y2 = (self.y + self.height).min(other.y + other.height);
+        if x2 <= x1 || y2 <= y1 {
+            return 0.0;
+        }
+        let intersection = ((x2 - x1) * (y2 - y1)) as f32;
+        intersection / (self.area() + other.area() - intersection)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Region {
+    pub bbox: BoundingBox,
+    pub label: Option<String>,
+    pub score: f32,
+}
+
+/// Finds salient regions (faces, objects) in an image.
+pub trait RegionDetector {
+    fn detect(&self, image: &DynamicImage) -> Result<Vec<Region>, DetectionError>;
+}
+
+pub fn crop_region(image: &DynamicImage, bbox: &BoundingBox) -> DynamicImage {
+    image.crop_imm(bbox.x, bbox.y, bbox.width, bbox.height)
+}
+
+/// Keeps the highest scoring region of every group of regions with the same label
+/// overlapping by more than `iou_threshold`.
+pub fn non_max_suppression(mut regions: Vec<Region>, iou_threshold: f32) -> Vec<Region> {
+    regions.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let mut kept: Vec<Region> = Vec::new();
+    for region in regions {
+        let suppressed = kept
+            .iter()
+            .any(|k| k.label == region.label && k.bbox.iou(&region.bbox) > iou_threshold);
+        if !suppressed {
+            kept.push(region);
+        }
+    }
+    kept
+}
+
+/// Decodes the output of a YOLOv8-style detector: a `[4 + classes, anchors]` row-major
+/// tensor of `(cx, cy, w, h, class scores...)` in model input pixels. Boxes are scaled back
+/// by `scale_x`/`scale_y` and clamped to the `image_width` x `image_height` image.
+#[allow(clippy::too_many_arguments)]
+pub fn decode_yolo_output(
+    output: &[f32],
+    num_anchors: usize,
+    labels: &[String],
+    score_threshold: f32,
+    scale_x: f32,
+    scale_y: f32,
+    image_width: u32,
+    image_height: u32,
+) -> Vec<Region> {
+    let num_classes = output.len() / num_anchors - 4;
+    let at = |row: usize, anchor: usize| output[row * num_anchors + anchor];
+
+    (0..num_anchors)
+        .filter_map(|anchor| {
+            let (class, score) = (0..num_classes)
+                .map(|class| (class, at(4 + class, anchor)))
+                .max_by(|a, b| a.1.total_cmp(&b.1))?;
+            if score < score_threshold {
+                return None;
+            }
+
+            let (cx, cy) = (at(0, anchor) * scale_x, at(1, anchor) * scale_y);
+            let (w, h) = (at(2, anchor) * scale_x, at(3, anchor) * scale_y);
+            let x = (cx - w / 2.0).clamp(0.0, image_width as f32);
+            let y = (cy - h / 2.0).clamp(0.0, image_height as f32);
+            let width = (w.min(image_width as f32 - x)) as u32;
+            let height = (h.min(image_height as f32 - y)) as u32;
+            if width == 0 || height == 0 {
+                return None;
+            }
+
+            Some(Region {
+                bbox: BoundingBox {
+                    x: x as u32,
+                    y: y as u32,
+                    width,
+                    height,
+                },
+                label: labels.get(class).cloned(),
+                score,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(x: u32, score: f32) -> Region {
+        Region {
+            bbox: BoundingBox {
+                x,
+                y: 0,
+                width: 10,
+                height: 10,
+            },
+            label: Some("shoe".to_string()),
+            score,
+        }
+    }
+
+    #[test]
+    fn test_iou() {
+        let a = region(0, 1.0).bbox;
+        assert_eq!(a.iou(&a), 1.0);
+        assert_eq!(a.iou(&region(20, 1.0).bbox), 0.0);
+        assert!((a.iou(&region(5, 1.0).bbox) - 1.0 / 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_non_max_suppression() {
+        let kept = non_max_suppression(vec![region(0, 0.5), region(1, 0.9), region(50, 0.3)], 0.5);
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[0].score, 0.9);
+        assert_eq!(kept[1].bbox.x, 50);
+    }
+
+    #[test]
+    fn test_decode_yolo_output() {
+        // 2 anchors, 2 classes: rows are cx, cy, w, h, class 0, class 1
+        let output = vec![
+            50.0, 10.0, // cx
+            50.0, 10.0, // cy
+            20.0, 4.0, // w
+            40.0, 4.0, // h
+            0.1, 0.2, // class 0
+            0.8, 0.1, // class 1
+        ];
+        let labels = vec!["bag".to_string(), "boot".to_string()];
+        let regions = decode_yolo_output(&output, 2, &labels, 0.5, 2.0, 2.0, 200, 200);
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].label.as_deref(), Some("boot"));
+        assert_eq!(
+            regions[0].bbox,
+            BoundingBox {
+                x: 80,
+                y: 60,
+                width: 40,
+                height: 80
+            }
+        );
+    }
+}
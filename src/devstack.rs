@@ -0,0 +1,312 @@
+use anyhow::{anyhow, bail, Context, Result};
+use std::env;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+
+const CONTAINER_PREFIX: &str = "liquid-memory";
+
+/// A local service the examples talk to, run as a Docker container.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Service {
+    pub name: String,
+    pub image: String,
+    /// `(host, container)` port pairs.
+    pub ports: Vec<(u16, u16)>,
+    pub env: Vec<(String, String)>,
+    pub args: Vec<String>,
+    /// URL answering 2xx once the service is ready.
+    pub health_url: String,
+    /// Directory to build `image` from when it is not available locally.
+    pub build_context: Option<PathBuf>,
+}
+
+impl Service {
+    pub fn new(
+        name: impl Into<String>,
+        image: impl Into<String>,
+        health_url: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            image: image.into(),
+            ports: Vec::new(),
+            env: Vec::new(),
+            args: Vec::new(),
+            health_url: health_url.into(),
+            build_context: None,
+        }
+    }
+
+    pub fn with_port(mut self, host: u16, container: u16) -> Self {
+        self.ports.push((host, container));
+        self
+    }
+
+    pub fn with_env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn with_args(mut self, args: &[&str]) -> Self {
+        self.args.extend(args.iter().map(|arg| arg.to_string()));
+        self
+    }
+
+    pub fn with_build_context(mut self, build_context: impl Into<PathBuf>) -> Self {
+        self.build_context = Some(build_context.into());
+        self
+    }
+
+    /// Qdrant on 6333 (REST) and 6334 (gRPC).
+    pub fn qdrant() -> Self {
+        Self::new("qdrant", "qdrant/qdrant", "http://localhost:6333/healthz")
+            .with_port(6333, 6333)
+            .with_port(6334, 6334)
+    }
+
+    /// Text Embedding Inference serving `BAAI/bge-large-en-v1.5` on 8888.
+    pub fn tei_text() -> Self {
+        Self::new(
+            "tei-text",
+            "ghcr.io/huggingface/text-embeddings-inference:cpu-1.5",
+            "http://localhost:8888/health",
+        )
+        .with_port(8888, 80)
+        .with_args(&["--model-id", "BAAI/bge-large-en-v1.5"])
+    }
+
+    /// Image Embedding Inference on 8000, built from the directory in
+    /// `IMAGE_EMBEDDING_INFERENCE_DIR`, by default `image_embedding_inference/` under the
+    /// current directory (the repository root when run with `cargo run`).
+    pub fn tei_image() -> Self {
+        let build_context = env::var_os("IMAGE_EMBEDDING_INFERENCE_DIR")
+            .map_or_else(|| PathBuf::from("image_embedding_inference"), PathBuf::from);
+        Self::new(
+            "tei-image",
+            "image-embedding-inference",
+            "http://localhost:8000/health",
+        )
+        .with_port(8000, 8000)
+        .with_build_context(build_context)
+    }
+
+    /// Ollama on 11434, with its OpenAI-compatible API under `/v1`.
+    pub fn ollama() -> Self {
+        Self::new("ollama", "ollama/ollama", "http://localhost:11434/api/tags")
+            .with_port(11434, 11434)
+    }
+
+    /// Built-in service by name: `qdrant`, `tei-text`, `tei-image` or `ollama`.
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "qdrant" => Some(Self::qdrant()),
+            "tei-text" => Some(Self::tei_text()),
+            "tei-image" => Some(Self::tei_image()),
+            "ollama" => Some(Self::ollama()),
+            _ => None,
+        }
+    }
+
+    pub fn container_name(&self) -> String {
+        format!("{CONTAINER_PREFIX}-{}", self.name)
+    }
+
+    fn run_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "run".to_string(),
+            "--detach".to_string(),
+            "--rm".to_string(),
+            "--name".to_string(),
+            self.container_name(),
+        ];
+        for (host, container) in &self.ports {
+            args.push("--publish".to_string());
+            args.push(format!("{host}:{container}"));
+        }
+        for (key, value) in &self.env {
+            args.push("--env".to_string());
+            args.push(format!("{key}={value}"));
+        }
+        args.push(self.image.clone());
+        args.extend(self.args.iter().cloned());
+        args
+    }
+}
+
+/// Starts and stops the local services of the examples with the `docker` CLI, waiting
+/// for each one to pass its health check. Services already answering their health check,
+/// e.g. started by hand, are left alone.
+///
+/// Also available as the `liquid-memory-devstack` binary: `up`, `down` or `status`,
+/// followed by service names (all of them if none).
+pub struct DevStack {
+    services: Vec<Service>,
+    health_timeout: Duration,
+    http: reqwest::Client,
+}
+
+impl DevStack {
+    pub fn new(services: Vec<Service>) -> Self {
+        Self {
+            services,
+            // Embedding servers download their model on first start
+            health_timeout: Duration::from_secs(600),
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(2))
+                .build()
+                .expect("failed to build HTTP client"),
+        }
+    }
+
+    /// Qdrant, both embedding servers and Ollama.
+    pub fn all() -> Self {
+        Self::new(vec![
+            Service::qdrant(),
+            Service::tei_text(),
+            Service::tei_image(),
+            Service::ollama(),
+        ])
+    }
+
+    pub fn with_health_timeout(mut self, health_timeout: Duration) -> Self {
+        self.health_timeout = health_timeout;
+        self
+    }
+
+    pub fn services(&self) -> &[Service] {
+        &self.services
+    }
+
+    pub async fn is_healthy(&self, service: &Service) -> bool {
+        self.http
+            .get(&service.health_url)
+            .send()
+            .await
+            .is_ok_and(|response| response.status().is_success())
+    }
+
+    /// Starts the services that are not healthy yet and waits until all of them are.
+    pub async fn up(&self) -> Result<()> {
+        for service in &self.services {
+            if self.is_healthy(service).await {
+                continue;
+            }
+            if !container_running(&service.container_name()).await? {
+                ensure_image(service).await?;
+                log::info!("Starting {}", service.container_name());
+                docker(&service.run_args()).await?;
+            }
+            self.wait_healthy(service).await?;
+        }
+        Ok(())
+    }
+
+    /// Stops and removes the containers of the services. Services not started by
+    /// [`up`](Self::up) keep running.
+    pub async fn down(&self) -> Result<()> {
+        for service in &self.services {
+            let name = service.container_name();
+            if container_running(&name).await? {
+                docker(&["rm".to_string(), "--force".to_string(), name]).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Name and health of each service.
+    pub async fn status(&self) -> Vec<(String, bool)> {
+        let mut status = Vec::with_capacity(self.services.len());
+        for service in &self.services {
+            status.push((service.name.clone(), self.is_healthy(service).await));
+        }
+        status
+    }
+
+    async fn wait_healthy(&self, service: &Service) -> Result<()> {
+        let started = Instant::now();
+        while !self.is_healthy(service).await {
+            if started.elapsed() > self.health_timeout {
+                bail!(
+                    "{} not healthy at {} after {:?}, see `docker logs {}`",
+                    service.name,
+                    service.health_url,
+                    self.health_timeout,
+                    service.container_name()
+                );
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+        Ok(())
+    }
+}
+
+// Runs the docker CLI and returns its standard output
+async fn docker(args: &[String]) -> Result<String> {
+    let output = Command::new("docker")
+        .args(args)
+        .output()
+        .await
+        .context("failed to run docker, is it installed?")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "docker {} failed: {}",
+            args.first().map_or("", String::as_str),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+async fn container_running(name: &str) -> Result<bool> {
+    let ids = docker(&[
+        "ps".to_string(),
+        "--quiet".to_string(),
+        "--filter".to_string(),
+        format!("name=^{name}$"),
+    ])
+    .await?;
+    Ok(!ids.trim().is_empty())
+}
+
+async fn ensure_image(service: &Service) -> Result<()> {
+    let Some(context) = &service.build_context else {
+        // `docker run` pulls missing images
+        return Ok(());
+    };
+    let present = docker(&[
+        "images".to_string(),
+        "--quiet".to_string(),
+        service.image.clone(),
+    ])
+    .await?;
+    if present.trim().is_empty() {
+        log::info!("Building {} from {}", service.image, context.display());
+        docker(&[
+            "build".to_string(),
+            "--tag".to_string(),
+            service.image.clone(),
+            context.display().to_string(),
+        ])
+        .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_args() {
+        let service = Service::tei_text().with_env("RUST_LOG", "info");
+        assert_eq!(
+            service.run_args().join(" "),
+            "run --detach --rm --name liquid-memory-tei-text --publish 8888:80 \
+            --env RUST_LOG=info ghcr.io/huggingface/text-embeddings-inference:cpu-1.5 \
+            --model-id BAAI/bge-large-en-v1.5"
+        );
+        assert_eq!(Service::by_name("ollama"), Some(Service::ollama()));
+        assert!(Service::by_name("redis").is_none());
+    }
+}
@@ -0,0 +1,161 @@
+use crate::embeddings::text_embedding_inference::TextEmbeddingInference;
+use reqwest::StatusCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Embeds large inputs in batches whose size adapts to the embedding server, AIMD-style:
+/// the batch grows by `increase` while requests finish within `target_latency`, and is
+/// halved when a request is slow, rejected as too large (413) or times out. The size is
+/// kept across calls, so a long-lived batcher converges on the deployment's sweet spot.
+pub struct AdaptiveBatcher {
+    min_batch_size: usize,
+    max_batch_size: usize,
+    increase: usize,
+    target_latency: Duration,
+    batch_size: AtomicUsize,
+}
+
+impl Default for AdaptiveBatcher {
+    fn default() -> Self {
+        // TEI's default `--max-client-batch-size` is 32
+        Self::new(1, 32, Duration::from_secs(2))
+    }
+}
+
+fn is_overload(error: &(dyn std::error::Error + 'static)) -> bool {
+    match error.downcast_ref::<reqwest::Error>() {
+        Some(error) => error.is_timeout() || error.status() == Some(StatusCode::PAYLOAD_TOO_LARGE),
+        None => false,
+    }
+}
+
+impl AdaptiveBatcher {
+    pub fn new(min_batch_size: usize, max_batch_size: usize, target_latency: Duration) -> Self {
+        let min_batch_size = min_batch_size.max(1);
+        let max_batch_size = max_batch_size.max(min_batch_size);
+        Self {
+            min_batch_size,
+            max_batch_size,
+            increase: 1,
+            target_latency,
+            // Start in the middle rather than probing from the minimum
+            batch_size: AtomicUsize::new(min_batch_size.max(max_batch_size / 2)),
+        }
+    }
+
+    /// Amount the batch size grows by after each fast request.
+    pub fn with_increase(mut self, increase: usize) -> Self {
+        self.increase = increase.max(1);
+        self
+    }
+
+    pub fn batch_size(&self) -> usize {
+        self.batch_size.load(Ordering::Relaxed)
+    }
+
+    fn on_success(&self, latency: Duration) {
+        let size = self.batch_size();
+        let next = if latency > self.target_latency {
+            size / 2
+        } else {
+            size + self.increase
+        };
+        self.batch_size.store(
+            next.clamp(self.min_batch_size, self.max_batch_size),
+            Ordering::Relaxed,
+        );
+    }
+
+    /// Halves the batch size. Returns `false` if it already was at the minimum.
+    fn on_overload(&self) -> bool {
+        let size = self.batch_size();
+        self.batch_size
+            .store((size / 2).max(self.min_batch_size), Ordering::Relaxed);
+        size > self.min_batch_size
+    }
+
+    /// Embeds `texts`, preserving input order. Batches rejected as too large or timing out
+    /// are retried with a smaller size; other errors are returned immediately.
+    pub async fn embed(
+        &self,
+        embedder: &TextEmbeddingInference,
+        texts: Vec<String>,
+    ) -> Result<Vec<Vec<f32>>, Box<dyn std::error::Error>> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        let mut start = 0;
+        while start < texts.len() {
+            let end = (start + self.batch_size()).min(texts.len());
+            let started = Instant::now();
+            match embedder.embed(texts[start..end].to_vec()).await {
+                Ok(batch) => {
+                    self.on_success(started.elapsed());
+                    embeddings.extend(batch);
+                    start = end;
+                }
+                Err(e) if is_overload(e.as_ref()) && self.on_overload() => {
+                    log::warn!(
+                        "Embedding batch of {} failed ({e}), retrying with {}",
+                        end - start,
+                        self.batch_size()
+                    );
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(embeddings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aimd() {
+        let batcher = AdaptiveBatcher::new(2, 16, Duration::from_millis(100)).with_increase(2);
+        assert_eq!(batcher.batch_size(), 8);
+        batcher.on_success(Duration::from_millis(10));
+        assert_eq!(batcher.batch_size(), 10);
+        batcher.on_success(Duration::from_millis(500));
+        assert_eq!(batcher.batch_size(), 5);
+        assert!(batcher.on_overload());
+        assert!(!batcher.on_overload());
+        assert_eq!(batcher.batch_size(), 2);
+        for _ in 0..20 {
+            batcher.on_success(Duration::ZERO);
+        }
+        assert_eq!(batcher.batch_size(), 16);
+    }
+
+    #[tokio::test]
+    async fn test_embed_shrinks_on_payload_too_large() {
+        let mut server = mockito::Server::new_async().await;
+        // Batches of 4 are rejected, smaller ones accepted
+        let rejected = server
+            .mock("POST", "/embed")
+            .match_body(mockito::Matcher::Regex(
+                r#"^\{"inputs":\["a","b","c","d"\]\}$"#.into(),
+            ))
+            .with_status(413)
+            .create_async()
+            .await;
+        let accepted = server
+            .mock("POST", "/embed")
+            .match_body(mockito::Matcher::Regex(
+                r#"^\{"inputs":\["[a-d]","[a-d]"\]\}$"#.into(),
+            ))
+            .with_body("[[1.0], [2.0]]")
+            .expect(2)
+            .create_async()
+            .await;
+
+        let embedder = TextEmbeddingInference::new(Some(&server.url()));
+        let batcher = AdaptiveBatcher::new(1, 8, Duration::from_secs(10));
+        let texts = ["a", "b", "c", "d"].map(String::from).to_vec();
+        let embeddings = batcher.embed(&embedder, texts).await.unwrap();
+
+        assert_eq!(embeddings.len(), 4);
+        rejected.assert_async().await;
+        accepted.assert_async().await;
+    }
+}
@@ -0,0 +1,13 @@
+use crate::embeddings::text_embedding_inference::TextEmbeddingInference;
+
+/// Turns texts into embeddings, one per input and in input order.
+#[allow(async_fn_in_trait)]
+pub trait Embedder {
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, Box<dyn std::error::Error>>;
+}
+
+impl Embedder for TextEmbeddingInference {
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, Box<dyn std::error::Error>> {
+        TextEmbeddingInference::embed(self, texts).await
+    }
+}
@@ -1 +1,4 @@
+pub mod adaptive_batch;
+pub mod embedder;
+pub mod sparse;
 pub mod text_embedding_inference;
@@ -0,0 +1,111 @@
+use crate::utils::fnv1a;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// BM25 term frequency saturation and length normalization
+const K1: f32 = 1.2;
+const B: f32 = 0.75;
+
+/// Sparse vector as stored in a Qdrant sparse vector: parallel term indices and weights.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SparseEmbedding {
+    pub indices: Vec<u32>,
+    pub values: Vec<f32>,
+}
+
+impl SparseEmbedding {
+    pub(crate) fn from_weights(weights: BTreeMap<u32, f32>) -> Self {
+        let (indices, values) = weights.into_iter().unzip();
+        Self { indices, values }
+    }
+}
+
+/// How [`ingest_documents`](crate::vectorstore::ingestion::ingest_documents) computes
+/// the sparse vector stored next to each chunk's dense embedding.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SparseEncoding {
+    /// Local BM25 term weights, see [`Bm25Encoder`].
+    Bm25,
+    /// SPLADE weights from a TEI server running a SPLADE model, at this URL.
+    Splade { url: String },
+}
+
+/// BM25 document side, computed locally: each lowercased word is hashed to a term index
+/// and weighted by its saturated, length-normalized frequency. The IDF half of BM25 is
+/// left to Qdrant, whose hybrid collections use the `idf` modifier (see
+/// [`create_hybrid_collection`](crate::vectorstore::qdrant_client::QdrantClient::create_hybrid_collection)),
+/// so weights stay valid as the corpus grows.
+#[derive(Debug, Clone, Copy)]
+pub struct Bm25Encoder {
+    avg_doc_len: f32,
+}
+
+impl Default for Bm25Encoder {
+    fn default() -> Self {
+        Self { avg_doc_len: 256.0 }
+    }
+}
+
+fn terms(text: &str) -> impl Iterator<Item = u32> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| fnv1a(word.to_lowercase().as_bytes()) as u32)
+}
+
+impl Bm25Encoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Average words per chunk of the corpus, e.g. about the chunk size in tokens.
+    pub fn with_avg_doc_len(mut self, avg_doc_len: f32) -> Self {
+        self.avg_doc_len = avg_doc_len.max(1.0);
+        self
+    }
+
+    pub fn encode(&self, text: &str) -> SparseEmbedding {
+        let mut counts: BTreeMap<u32, f32> = BTreeMap::new();
+        for term in terms(text) {
+            *counts.entry(term).or_default() += 1.0;
+        }
+        let length: f32 = counts.values().sum();
+        let norm = K1 * (1.0 - B + B * length / self.avg_doc_len);
+        for tf in counts.values_mut() {
+            *tf = *tf * (K1 + 1.0) / (*tf + norm);
+        }
+        SparseEmbedding::from_weights(counts)
+    }
+
+    /// Query side: every distinct query term with weight 1.
+    pub fn encode_query(&self, query: &str) -> SparseEmbedding {
+        SparseEmbedding::from_weights(terms(query).map(|term| (term, 1.0)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bm25_encoder() {
+        let encoder = Bm25Encoder::new().with_avg_doc_len(4.0);
+        let document = encoder.encode("Boots, boots and more BOOTS");
+        assert_eq!(document.indices.len(), 3);
+        assert!(document.indices.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let query = encoder.encode_query("boots");
+        let boots = document
+            .indices
+            .iter()
+            .position(|index| *index == query.indices[0])
+            .unwrap();
+        // Repeated terms weigh more, saturating below k1 + 1
+        assert!(document
+            .values
+            .iter()
+            .all(|value| *value <= document.values[boots]));
+        assert!(document.values[boots] < K1 + 1.0);
+        assert_eq!(encoder.encode("").indices.len(), 0);
+    }
+}
@@ -1,44 +1,366 @@
+use crate::embeddings::sparse::SparseEmbedding;
+use crate::http_transport::{self, default_transport, HttpTransport};
+use crate::metrics::ClientMetrics;
+use crate::models::ModelRegistry;
+use crate::request_id;
+use crate::usage;
+use crate::utils::{estimate_tokens, truncate_to_tokens};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json;
+use std::sync::Arc;
+
+/// What the `inputs` of an embedding request are, so a multimodal server (e.g. CLIP
+/// behind a TEI-compatible API) does not have to guess whether a string is text or an
+/// encoded image.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputKind {
+    #[default]
+    Text,
+    /// Base64-encoded image bytes, as from [`base64_encode`](crate::utils::base64_encode).
+    ImageBase64,
+    /// URLs the server fetches the images from.
+    ImageUrl,
+}
+
+impl InputKind {
+    pub fn is_text(&self) -> bool {
+        *self == Self::Text
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TextEmbeddingRequest {
     pub inputs: Vec<String>,
+    /// Sent as `input_kind` for images only, so text requests stay plain TEI requests.
+    #[serde(default, skip_serializing_if = "InputKind::is_text")]
+    pub input_kind: InputKind,
+}
+
+impl TextEmbeddingRequest {
+    pub fn new(inputs: Vec<String>) -> Self {
+        Self {
+            inputs,
+            input_kind: InputKind::Text,
+        }
+    }
+
+    pub fn with_input_kind(mut self, input_kind: InputKind) -> Self {
+        self.input_kind = input_kind;
+        self
+    }
+}
+
+/// Subset of the `/info` response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextEmbeddingInfo {
+    pub model_id: String,
+    pub max_input_length: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct SparseValue {
+    index: u32,
+    value: f32,
 }
 
 pub struct TextEmbeddingInference {
     pub client: Client,
+    /// Sends the requests built with `client`, see [`with_transport`](Self::with_transport).
+    pub transport: Arc<dyn HttpTransport>,
     pub base_url: String,
+    pub metrics: ClientMetrics,
+    /// Model and token limit inputs are truncated to before embedding, see
+    /// [`with_truncation`](Self::with_truncation).
+    pub truncation: Option<(String, usize)>,
+    /// Kind of the inputs of [`embed`](Self::embed), see
+    /// [`with_input_kind`](Self::with_input_kind).
+    pub input_kind: InputKind,
 }
 
 impl TextEmbeddingInference {
     pub fn new(base_url: Option<&str>) -> Self {
         Self {
             client: Client::new(),
+            transport: default_transport(),
             base_url: base_url.unwrap_or("http://localhost:8888").to_string(),
+            metrics: ClientMetrics::new("tei"),
+            truncation: None,
+            input_kind: InputKind::Text,
+        }
+    }
+
+    /// Declares the inputs of [`embed`](Self::embed) to be of `input_kind`, e.g. for a
+    /// client of an image embedding server.
+    pub fn with_input_kind(mut self, input_kind: InputKind) -> Self {
+        self.input_kind = input_kind;
+        self
+    }
+
+    /// Truncates inputs to `max_tokens` estimated tokens of `model` with
+    /// [`truncate_to_tokens`], so texts over the model limit (512 tokens for most BERT
+    /// models) are cut on a word boundary instead of rejected by a server started without
+    /// `--auto-truncate`.
+    pub fn with_truncation(mut self, model: impl Into<String>, max_tokens: usize) -> Self {
+        self.truncation = Some((model.into(), max_tokens));
+        self
+    }
+
+    // Only text is truncated: a cut image or URL is no longer one
+    fn truncate(&self, text: Vec<String>, input_kind: InputKind) -> Vec<String> {
+        match &self.truncation {
+            Some((model, max_tokens)) if input_kind.is_text() => text
+                .into_iter()
+                .map(|input| truncate_to_tokens(&input, model, *max_tokens).to_string())
+                .collect(),
+            _ => text,
         }
     }
 
+    /// Sends requests through `transport` instead of a plain [`Client`].
+    pub fn with_transport(mut self, transport: impl HttpTransport + 'static) -> Self {
+        self.transport = Arc::new(transport);
+        self
+    }
+
+    // Usage is recorded under the model of the truncation, the only one the client knows
+    fn record_usage(&self, inputs: &[String], input_kind: InputKind) {
+        let model = match &self.truncation {
+            Some((model, _)) => model.as_str(),
+            None => self.base_url.as_str(),
+        };
+        let tokens = if input_kind.is_text() {
+            inputs
+                .iter()
+                .map(|input| estimate_tokens(input) as u64)
+                .sum()
+        } else {
+            0
+        };
+        usage::record(model, tokens, 0);
+    }
+
+    /// Replaces the default metrics, e.g. to log slow requests or cap concurrent requests.
+    pub fn with_metrics(mut self, metrics: ClientMetrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
     pub async fn embed(
         &self,
         text: Vec<String>,
     ) -> Result<Vec<Vec<f32>>, Box<dyn std::error::Error>> {
-        let request = TextEmbeddingRequest { inputs: text };
-        let response = self
-            .client
-            .post(format!("{}/embed", self.base_url))
-            .json(&request)
-            .send()
-            .await?;
+        self.embed_as(text, self.input_kind).await
+    }
 
-        //  Example response:
-        // [[0.1, 0.2, 0.3], [0.4, 0.5, 0.6]]
+    /// Like [`embed`](Self::embed), for inputs of `input_kind` whatever the client default.
+    pub async fn embed_as(
+        &self,
+        text: Vec<String>,
+        input_kind: InputKind,
+    ) -> Result<Vec<Vec<f32>>, Box<dyn std::error::Error>> {
+        let num_inputs = text.len();
+        let request =
+            TextEmbeddingRequest::new(self.truncate(text, input_kind)).with_input_kind(input_kind);
+        let data: Vec<Vec<f32>> = self
+            .metrics
+            .track(
+                "embed",
+                || {
+                    format!(
+                        "url={} inputs={num_inputs} kind={input_kind:?}",
+                        self.base_url
+                    )
+                },
+                async {
+                    let request =
+                        request_id::attach(self.client.post(format!("{}/embed", self.base_url)))
+                            .json(&request);
+                    let response = http_transport::send(self.transport.as_ref(), request)
+                        .await?
+                        .error_for_status()?;
 
-        let text = response.text().await?;
-        let data: Vec<Vec<f32>> = serde_json::from_str(&text)?;
+                    //  Example response:
+                    // [[0.1, 0.2, 0.3], [0.4, 0.5, 0.6]]
+
+                    let text = response.text().await?;
+                    Ok::<_, Box<dyn std::error::Error>>(serde_json::from_str(&text)?)
+                },
+            )
+            .await?;
+        self.record_usage(&request.inputs, input_kind);
         Ok(data)
     }
 }
 
+impl TextEmbeddingInference {
+    /// Returns the model served by the TEI instance.
+    pub async fn info(&self) -> Result<TextEmbeddingInfo, Box<dyn std::error::Error>> {
+        let request = request_id::attach(self.client.get(format!("{}/info", self.base_url)));
+        let response = http_transport::send(self.transport.as_ref(), request)
+            .await?
+            .error_for_status()?;
+        Ok(response.json::<TextEmbeddingInfo>().await?)
+    }
+
+    /// [`with_truncation`](Self::with_truncation) to the limit reported by `/info`, else
+    /// the context window of the model in the default [`ModelRegistry`], else 512 tokens.
+    pub async fn with_truncation_from_info(self) -> Result<Self, Box<dyn std::error::Error>> {
+        let info = self.info().await?;
+        let max_tokens = info
+            .max_input_length
+            .or_else(|| {
+                ModelRegistry::default()
+                    .get(&info.model_id)
+                    .map(|model| model.context_window)
+            })
+            .unwrap_or(512);
+        Ok(self.with_truncation(info.model_id, max_tokens))
+    }
+
+    /// Embeds a short text so the connection is open and the model is loaded before the
+    /// first real request.
+    pub async fn warm_up(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.embed(vec!["warm up".to_string()]).await?;
+        Ok(())
+    }
+}
+
+impl TextEmbeddingInference {
+    /// Sparse embeddings from `/embed_sparse`, served by TEI for SPLADE models.
+    pub async fn embed_sparse(
+        &self,
+        text: Vec<String>,
+    ) -> Result<Vec<SparseEmbedding>, Box<dyn std::error::Error>> {
+        let num_inputs = text.len();
+        let request = TextEmbeddingRequest::new(self.truncate(text, InputKind::Text));
+        let data: Vec<Vec<SparseValue>> = self
+            .metrics
+            .track(
+                "embed_sparse",
+                || format!("url={} inputs={num_inputs}", self.base_url),
+                async {
+                    let request = request_id::attach(
+                        self.client.post(format!("{}/embed_sparse", self.base_url)),
+                    )
+                    .json(&request);
+                    let response = http_transport::send(self.transport.as_ref(), request)
+                        .await?
+                        .error_for_status()?;
+
+                    //  Example response:
+                    // [[{"index": 1996, "value": 0.42}, {"index": 2000, "value": 1.3}]]
+
+                    Ok::<_, Box<dyn std::error::Error>>(response.json().await?)
+                },
+            )
+            .await?;
+        self.record_usage(&request.inputs, InputKind::Text);
+        Ok(data
+            .into_iter()
+            .map(|values| {
+                SparseEmbedding::from_weights(
+                    values
+                        .into_iter()
+                        .map(|value| (value.index, value.value))
+                        .collect(),
+                )
+            })
+            .collect())
+    }
+}
+
 // TODO: /rerank, /predict (classification)
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_info_and_warm_up() {
+        let mut server = mockito::Server::new_async().await;
+        let info = server
+            .mock("GET", "/info")
+            .with_body(r#"{"model_id": "BAAI/bge-small-en-v1.5", "max_input_length": 512}"#)
+            .create_async()
+            .await;
+        let embed = server
+            .mock("POST", "/embed")
+            .with_body("[[0.1, 0.2]]")
+            .create_async()
+            .await;
+
+        let client = TextEmbeddingInference::new(Some(&server.url()));
+        assert_eq!(
+            client.info().await.unwrap().model_id,
+            "BAAI/bge-small-en-v1.5"
+        );
+        client.warm_up().await.unwrap();
+        info.assert_async().await;
+        embed.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_embed_sparse() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/embed_sparse")
+            .with_body(r#"[[{"index": 2000, "value": 1.3}, {"index": 1996, "value": 0.42}]]"#)
+            .create_async()
+            .await;
+
+        let client = TextEmbeddingInference::new(Some(&server.url()));
+        let embeddings = client.embed_sparse(vec!["boots".into()]).await.unwrap();
+        assert_eq!(embeddings[0].indices, vec![1996, 2000]);
+        assert_eq!(embeddings[0].values, vec![0.42, 1.3]);
+    }
+
+    #[tokio::test]
+    async fn test_truncation() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/info")
+            .with_body(r#"{"model_id": "BAAI/bge-small-en-v1.5", "max_input_length": 4}"#)
+            .create_async()
+            .await;
+        let embed = server
+            .mock("POST", "/embed")
+            .match_body(mockito::Matcher::Json(
+                serde_json::json!({"inputs": ["Waterproof", "Boots"]}),
+            ))
+            .with_body("[[0.1], [0.2]]")
+            .create_async()
+            .await;
+
+        let client = TextEmbeddingInference::new(Some(&server.url()))
+            .with_truncation_from_info()
+            .await
+            .unwrap();
+        let inputs = vec!["Waterproof leather boots".to_string(), "Boots".to_string()];
+        client.embed(inputs).await.unwrap();
+        embed.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_input_kind() {
+        let mut server = mockito::Server::new_async().await;
+        let embed = server
+            .mock("POST", "/embed")
+            .match_body(mockito::Matcher::Json(serde_json::json!({
+                "inputs": ["https://example.com/boots.jpg"],
+                "input_kind": "image_url",
+            })))
+            .with_body("[[0.1]]")
+            .create_async()
+            .await;
+
+        let client =
+            TextEmbeddingInference::new(Some(&server.url())).with_input_kind(InputKind::ImageUrl);
+        client
+            .embed(vec!["https://example.com/boots.jpg".to_string()])
+            .await
+            .unwrap();
+        embed.assert_async().await;
+    }
+}
@@ -0,0 +1,67 @@
+use futures::future::BoxFuture;
+use reqwest::{Client, Request, RequestBuilder, Response};
+use std::sync::Arc;
+
+/// Sends the HTTP requests of the LLM and TEI clients, a plain [`reqwest::Client`] by
+/// default. Implement it to put middleware between the clients and the network, e.g.
+/// custom auth or request signing, delegating to a client built with mTLS certificates or
+/// a corporate proxy; such a client can also be passed as is.
+pub trait HttpTransport: Send + Sync {
+    fn execute(&self, request: Request) -> BoxFuture<'_, reqwest::Result<Response>>;
+}
+
+impl HttpTransport for Client {
+    fn execute(&self, request: Request) -> BoxFuture<'_, reqwest::Result<Response>> {
+        Box::pin(Client::execute(self, request))
+    }
+}
+
+pub(crate) fn default_transport() -> Arc<dyn HttpTransport> {
+    Arc::new(Client::new())
+}
+
+/// Builds `request` and sends it through `transport`.
+pub(crate) async fn send(
+    transport: &dyn HttpTransport,
+    request: RequestBuilder,
+) -> reqwest::Result<Response> {
+    transport.execute(request.build()?).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embeddings::text_embedding_inference::TextEmbeddingInference;
+    use reqwest::header::{HeaderValue, AUTHORIZATION};
+
+    // Signs every request before handing it to a plain client
+    struct Signing(Client);
+
+    impl HttpTransport for Signing {
+        fn execute(&self, mut request: Request) -> BoxFuture<'_, reqwest::Result<Response>> {
+            let signature = format!("Signature {}", request.url().path());
+            request
+                .headers_mut()
+                .insert(AUTHORIZATION, HeaderValue::from_str(&signature).unwrap());
+            HttpTransport::execute(&self.0, request)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_transport() {
+        let mut server = mockito::Server::new_async().await;
+        let signed = server
+            .mock("GET", "/info")
+            .match_header("authorization", "Signature /info")
+            .with_body(r#"{"model_id": "BAAI/bge-small-en-v1.5", "max_input_length": 512}"#)
+            .create_async()
+            .await;
+
+        let embedder =
+            TextEmbeddingInference::new(Some(&server.url())).with_transport(Signing(Client::new()));
+        let info = embedder.info().await.unwrap();
+
+        assert_eq!(info.model_id, "BAAI/bge-small-en-v1.5");
+        signed.assert_async().await;
+    }
+}
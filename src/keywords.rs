@@ -0,0 +1,157 @@
+use std::collections::{HashMap, HashSet};
+
+/// Payload field [`ingest_documents`](crate::vectorstore::ingestion::ingest_documents)
+/// stores extracted keywords in, with a keyword index for filters.
+pub const KEYWORDS_FIELD: &str = "keywords";
+
+const STOPWORDS: &[&str] = &[
+    "a", "about", "above", "after", "again", "against", "all", "also", "am", "an", "and", "any",
+    "are", "as", "at", "be", "because", "been", "before", "being", "below", "between", "both",
+    "but", "by", "can", "could", "did", "do", "does", "doing", "down", "during", "each", "few",
+    "for", "from", "further", "had", "has", "have", "having", "he", "her", "here", "hers", "him",
+    "his", "how", "i", "if", "in", "into", "is", "it", "its", "just", "me", "more", "most", "my",
+    "no", "nor", "not", "now", "of", "off", "on", "once", "only", "or", "other", "our", "ours",
+    "out", "over", "own", "same", "she", "should", "so", "some", "such", "than", "that", "the",
+    "their", "them", "then", "there", "these", "they", "this", "those", "through", "to", "too",
+    "under", "until", "up", "us", "very", "was", "we", "were", "what", "when", "where", "which",
+    "while", "who", "whom", "why", "will", "with", "would", "you", "your", "yours",
+];
+
+/// RAKE-style keyword extraction, no model involved: runs of words between stopwords and
+/// punctuation are candidate phrases, each word scores its co-occurrence degree over its
+/// frequency and a phrase scores the sum of its words.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeywordExtractor {
+    /// Keywords kept per text. Defaults to 5.
+    pub max_keywords: usize,
+    /// Longer candidate phrases are dropped, they are usually sentence fragments.
+    /// Defaults to 3.
+    pub max_phrase_words: usize,
+    pub stopwords: HashSet<String>,
+}
+
+impl Default for KeywordExtractor {
+    fn default() -> Self {
+        Self {
+            max_keywords: 5,
+            max_phrase_words: 3,
+            stopwords: STOPWORDS.iter().map(|word| word.to_string()).collect(),
+        }
+    }
+}
+
+impl KeywordExtractor {
+    pub fn with_max_keywords(mut self, max_keywords: usize) -> Self {
+        self.max_keywords = max_keywords;
+        self
+    }
+
+    pub fn with_stopword(mut self, word: impl Into<String>) -> Self {
+        self.stopwords.insert(word.into().to_lowercase());
+        self
+    }
+
+    fn phrases(&self, text: &str) -> Vec<Vec<String>> {
+        let mut phrases = Vec::new();
+        for fragment in text
+            .split(|c: char| !(c.is_alphanumeric() || c.is_whitespace() || c == '-' || c == '\''))
+        {
+            let mut phrase = Vec::new();
+            for word in fragment.split_whitespace() {
+                let word = word.trim_matches(['-', '\'']).to_lowercase();
+                // Numbers alone make poor keywords
+                if word.is_empty()
+                    || self.stopwords.contains(&word)
+                    || word.chars().all(|c| c.is_numeric())
+                {
+                    if !phrase.is_empty() {
+                        phrases.push(std::mem::take(&mut phrase));
+                    }
+                } else {
+                    phrase.push(word);
+                }
+            }
+            if !phrase.is_empty() {
+                phrases.push(phrase);
+            }
+        }
+        phrases.retain(|phrase| phrase.len() <= self.max_phrase_words);
+        phrases
+    }
+
+    /// Top keywords of `text`, best first, lowercased.
+    pub fn extract(&self, text: &str) -> Vec<String> {
+        let phrases = self.phrases(text);
+        let mut frequency: HashMap<&str, f32> = HashMap::new();
+        let mut degree: HashMap<&str, f32> = HashMap::new();
+        for phrase in &phrases {
+            for word in phrase {
+                *frequency.entry(word).or_default() += 1.0;
+                *degree.entry(word).or_default() += phrase.len() as f32;
+            }
+        }
+
+        let mut scored: HashMap<String, f32> = HashMap::new();
+        for phrase in &phrases {
+            let score = phrase
+                .iter()
+                .map(|word| degree[word.as_str()] / frequency[word.as_str()])
+                .sum();
+            scored.insert(phrase.join(" "), score);
+        }
+        let mut keywords: Vec<(String, f32)> = scored.into_iter().collect();
+        // Ties broken alphabetically so extraction is deterministic
+        keywords.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        keywords
+            .into_iter()
+            .take(self.max_keywords)
+            .map(|(keyword, _)| keyword)
+            .collect()
+    }
+
+    /// Share of `keywords` (as stored by [`extract`](Self::extract)) whose words all
+    /// appear in `query`, a cheap signal to blend into hybrid ranking.
+    pub fn overlap(&self, query: &str, keywords: &[String]) -> f32 {
+        if keywords.is_empty() {
+            return 0.0;
+        }
+        let query_words: HashSet<String> = self.phrases(query).into_iter().flatten().collect();
+        let matched = keywords
+            .iter()
+            .filter(|keyword| keyword.split(' ').all(|word| query_words.contains(word)))
+            .count();
+        matched as f32 / keywords.len() as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract() {
+        let text = "Waterproof leather boots are dry. The leather boots are \
+            resoled for free, and waterproof membranes last 5 years.";
+        let extractor = KeywordExtractor::default().with_max_keywords(3);
+        assert_eq!(
+            extractor.extract(text),
+            vec![
+                "waterproof membranes last",
+                "waterproof leather boots",
+                "leather boots"
+            ]
+        );
+        assert!(extractor.extract("It is what it is.").is_empty());
+    }
+
+    #[test]
+    fn test_overlap() {
+        let extractor = KeywordExtractor::default();
+        let keywords = vec!["leather boots".to_string(), "resoling".to_string()];
+        assert_eq!(
+            extractor.overlap("Are the leather boots waterproof?", &keywords),
+            0.5
+        );
+        assert_eq!(extractor.overlap("Sandals", &keywords), 0.0);
+    }
+}
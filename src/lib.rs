@@ -1,4 +1,27 @@
+pub mod circuit_breaker;
+pub mod cost;
+pub mod detection;
+#[cfg(feature = "devstack")]
+pub mod devstack;
 pub mod embeddings;
+pub mod http_transport;
+pub mod injection;
+pub mod keywords;
 pub mod llm;
+pub mod logging;
+#[cfg(feature = "mcp")]
+pub mod mcp;
+pub mod memory;
+pub mod metrics;
+pub mod models;
+pub mod pipelines;
+pub mod request_id;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod similarity;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod text_cleaning;
+pub mod usage;
 pub mod utils;
 pub mod vectorstore;
@@ -1,8 +1,16 @@
-use super::llm_client::LlmClientChat;
-use crate::utils::load_image;
+use super::llm_client::{ChatMessage, ChatRole, LlmClientChat};
+use super::tools::{ToolCall, ToolChoice, ToolDefinition, ToolResponse};
+use crate::circuit_breaker::{is_transient_status, TransientError};
+use crate::http_transport::{self, default_transport, HttpTransport};
+use crate::models::{ModelError, ModelRegistry, ModelRequest};
+use crate::request_id;
+use crate::usage;
+use crate::utils::{estimate_model_tokens, load_image};
 use base64::{engine::general_purpose::STANDARD, Engine};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::sync::Arc;
 use std::{env, path::Path};
 use thiserror::Error;
 
@@ -15,6 +23,13 @@ pub struct ContentItem {
     content_type: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     source: Option<ImageSource>,
+    // Set on `tool_use` blocks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    input: Option<JsonValue>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -49,6 +64,30 @@ pub struct AnthropicResponse {
     usage: Usage,
 }
 
+impl AnthropicResponse {
+    /// All text content, concatenated.
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .filter(|item| item.content_type == "text")
+            .map(|item| item.text.as_str())
+            .collect()
+    }
+
+    /// The `tool_use` blocks of the response, in order.
+    pub fn tool_calls(&self) -> Vec<ToolCall> {
+        self.content
+            .iter()
+            .filter(|item| item.content_type == "tool_use")
+            .map(|item| ToolCall {
+                id: item.id.clone().unwrap_or_default(),
+                name: item.name.clone().unwrap_or_default(),
+                arguments: item.input.clone().unwrap_or(JsonValue::Null),
+            })
+            .collect()
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum AnthropicError {
     #[error("API Error: {status}, {message}")]
@@ -64,6 +103,19 @@ pub enum AnthropicError {
     RequestError(#[from] reqwest::Error),
     #[error("Image Error: {0}")]
     ImageError(String),
+    #[error(transparent)]
+    ModelError(#[from] ModelError),
+}
+
+impl TransientError for AnthropicError {
+    fn is_transient(&self) -> bool {
+        match self {
+            AnthropicError::ApiError { status, .. } => is_transient_status(*status),
+            AnthropicError::RequestError(e) => e.is_transient(),
+            AnthropicError::IoError(_) => true,
+            _ => false,
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -76,15 +128,40 @@ struct Message {
 struct RequestPayload {
     model: String,
     max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
     messages: Vec<Message>,
     temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<AnthropicTool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<JsonValue>,
+}
+
+#[derive(Debug, Serialize)]
+struct AnthropicTool {
+    name: String,
+    description: String,
+    input_schema: JsonValue,
+}
+
+impl From<&ToolDefinition> for AnthropicTool {
+    fn from(tool: &ToolDefinition) -> Self {
+        Self {
+            name: tool.name.clone(),
+            description: tool.description.clone(),
+            input_schema: tool.parameters.clone(),
+        }
+    }
 }
 
 pub struct AnthropicClient {
     client: Client,
+    transport: Arc<dyn HttpTransport>,
     base_url: String,
     api_key: String,
     version: String,
+    models: ModelRegistry,
 }
 
 impl AnthropicClient {
@@ -112,6 +189,9 @@ impl AnthropicClient {
             text: text.to_string(),
             content_type: "text".to_string(),
             source: None,
+            id: None,
+            name: None,
+            input: None,
         }];
 
         if let Some(image_buffer) = image_data {
@@ -124,6 +204,9 @@ impl AnthropicClient {
                     media_type: "image/png".to_string(),
                     data: image_base64,
                 }),
+                id: None,
+                name: None,
+                input: None,
             });
         }
 
@@ -133,12 +216,28 @@ impl AnthropicClient {
     pub fn new(base_url: Option<&str>, api_key: Option<&str>, version: Option<&str>) -> Self {
         Self {
             client: Client::new(),
+            transport: default_transport(),
             base_url: Self::get_or_load_url(base_url),
             api_key: Self::get_or_load_key(api_key),
             version: version.unwrap_or("2023-06-01").to_string(),
+            models: ModelRegistry::default(),
         }
     }
 
+    /// Replaces the default registry used to validate requests and cap their output.
+    pub fn with_models(mut self, models: ModelRegistry) -> Self {
+        self.models = models;
+        self
+    }
+
+    /// Sends requests through `transport` instead of a plain [`Client`].
+    pub fn with_transport(mut self, transport: impl HttpTransport + 'static) -> Self {
+        self.transport = Arc::new(transport);
+        self
+    }
+
+    /// Sends a single user message. The model may call any of `tools`; see
+    /// [`AnthropicResponse::tool_calls`].
     pub async fn create_message(
         &self,
         model: impl Into<String>,
@@ -146,7 +245,17 @@ impl AnthropicClient {
         text: impl AsRef<str>,
         image_path: Option<impl AsRef<Path>>,
         temperature: Option<f32>,
+        tools: &[ToolDefinition],
     ) -> Result<AnthropicResponse, AnthropicError> {
+        let model = model.into();
+        self.models.validate(
+            &model,
+            &ModelRequest {
+                input_tokens: estimate_model_tokens(text.as_ref(), &model),
+                has_image: image_path.is_some(),
+                uses_tools: !tools.is_empty(),
+            },
+        )?;
         let image_data = match image_path {
             Some(path) => Some(load_image(path).await?),
             None => None,
@@ -154,25 +263,132 @@ impl AnthropicClient {
 
         let content = Self::create_content(text.as_ref(), image_data)?;
         let payload = RequestPayload {
-            model: model.into(),
+            model,
             max_tokens,
+            system: None,
             messages: vec![Message {
                 role: "user".to_string(),
                 content,
             }],
             temperature,
+            tools: tools.iter().map(AnthropicTool::from).collect(),
+            tool_choice: None,
         };
+        self.post_messages(&payload).await
+    }
+
+    /// Like [`create_message`](Self::create_message), with the whole conversation so far
+    /// instead of a single user message and an optional system prompt.
+    pub async fn create_conversation(
+        &self,
+        model: impl Into<String>,
+        max_tokens: u32,
+        system: Option<&str>,
+        messages: &[ChatMessage],
+        temperature: Option<f32>,
+    ) -> Result<AnthropicResponse, AnthropicError> {
+        let model = model.into();
+        self.validate_conversation(&model, system, messages, false)?;
+        let payload = Self::conversation_payload(model, max_tokens, system, messages, temperature)?;
+        self.post_messages(&payload).await
+    }
+
+    /// Like [`send_conversation`](LlmClientChat::send_conversation), offering `tools` the
+    /// model may call as `tool_choice` allows. The calls are returned, not run.
+    pub async fn send_with_tools(
+        &self,
+        model: impl Into<String>,
+        system: Option<&str>,
+        messages: &[ChatMessage],
+        tools: &[ToolDefinition],
+        tool_choice: ToolChoice,
+        temperature: Option<f32>,
+    ) -> Result<ToolResponse, AnthropicError> {
+        let model = model.into();
+        self.validate_conversation(&model, system, messages, !tools.is_empty())?;
+        let max_tokens = self.models.max_output_tokens(&model, 4096) as u32;
+        let mut payload =
+            Self::conversation_payload(model, max_tokens, system, messages, temperature)?;
+        if !tools.is_empty() {
+            payload.tools = tools.iter().map(AnthropicTool::from).collect();
+            payload.tool_choice = Some(match tool_choice {
+                ToolChoice::Auto => serde_json::json!({"type": "auto"}),
+                ToolChoice::None => serde_json::json!({"type": "none"}),
+                ToolChoice::Required => serde_json::json!({"type": "any"}),
+                ToolChoice::Tool(name) => serde_json::json!({"type": "tool", "name": name}),
+            });
+        }
+
+        let response = self.post_messages(&payload).await?;
+        Ok(ToolResponse {
+            text: response.text(),
+            tool_calls: response.tool_calls(),
+        })
+    }
+
+    fn validate_conversation(
+        &self,
+        model: &str,
+        system: Option<&str>,
+        messages: &[ChatMessage],
+        uses_tools: bool,
+    ) -> Result<(), ModelError> {
+        let history: String = system
+            .into_iter()
+            .chain(messages.iter().map(|m| m.content.as_str()))
+            .collect();
+        self.models.validate(
+            model,
+            &ModelRequest {
+                input_tokens: estimate_model_tokens(&history, model),
+                has_image: false,
+                uses_tools,
+            },
+        )
+    }
 
+    fn conversation_payload(
+        model: String,
+        max_tokens: u32,
+        system: Option<&str>,
+        messages: &[ChatMessage],
+        temperature: Option<f32>,
+    ) -> Result<RequestPayload, AnthropicError> {
+        let messages = messages
+            .iter()
+            .map(|message| {
+                Ok(Message {
+                    role: match message.role {
+                        ChatRole::User => "user",
+                        ChatRole::Assistant => "assistant",
+                    }
+                    .to_string(),
+                    content: Self::create_content(&message.content, None)?,
+                })
+            })
+            .collect::<Result<_, AnthropicError>>()?;
+        Ok(RequestPayload {
+            model,
+            max_tokens,
+            system: system.map(str::to_string),
+            messages,
+            temperature,
+            tools: Vec::new(),
+            tool_choice: None,
+        })
+    }
+
+    async fn post_messages(
+        &self,
+        payload: &RequestPayload,
+    ) -> Result<AnthropicResponse, AnthropicError> {
         let url = format!("{}/v1/messages", self.base_url);
-        let response = self
-            .client
-            .post(&url)
+        let request = request_id::attach(self.client.post(&url))
             .header("x-api-key", &self.api_key)
             .header("anthropic-version", &self.version)
             .header("content-type", "application/json")
-            .json(&payload)
-            .send()
-            .await?;
+            .json(payload);
+        let response = http_transport::send(self.transport.as_ref(), request).await?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -183,7 +399,13 @@ impl AnthropicClient {
             return Err(AnthropicError::ApiError { status, message });
         }
 
-        Ok(response.json().await?)
+        let response: AnthropicResponse = response.json().await?;
+        usage::record(
+            &response.model,
+            response.usage.input_tokens as u64,
+            response.usage.output_tokens as u64,
+        );
+        Ok(response)
     }
 }
 
@@ -193,9 +415,11 @@ impl LlmClientChat for AnthropicClient {
     fn new(base_url: Option<&str>, api_key: Option<&str>) -> Self {
         Self {
             client: Client::new(),
+            transport: default_transport(),
             base_url: Self::get_or_load_url(base_url),
             api_key: Self::get_or_load_key(api_key),
             version: "2023-01-01".to_string(),
+            models: ModelRegistry::default(),
         }
     }
 
@@ -206,24 +430,41 @@ impl LlmClientChat for AnthropicClient {
         image_path: Option<impl AsRef<Path>>,
         temperature: Option<f32>,
     ) -> Result<String, AnthropicError> {
+        let model = model.into();
+        // The model's output maximum, 4096 tokens for unknown models
+        let max_tokens = self.models.max_output_tokens(&model, 4096) as u32;
         let response = self
-            .create_message(
-                model,
-                4096, // default max tokens
-                text,
-                image_path,
-                temperature,
-            )
+            .create_message(model, max_tokens, text, image_path, temperature, &[])
             .await?;
+        Ok(response.text())
+    }
 
-        // Combine all text content from the response
-        Ok(response
-            .content
-            .into_iter()
-            .filter(|item| item.content_type == "text")
-            .map(|item| item.text)
-            .collect::<Vec<_>>()
-            .join(""))
+    async fn send_conversation(
+        &self,
+        model: impl Into<String>,
+        system: Option<&str>,
+        messages: &[ChatMessage],
+        temperature: Option<f32>,
+    ) -> Result<String, AnthropicError> {
+        let model = model.into();
+        let max_tokens = self.models.max_output_tokens(&model, 4096) as u32;
+        let response = self
+            .create_conversation(model, max_tokens, system, messages, temperature)
+            .await?;
+        Ok(response.text())
+    }
+
+    async fn warm_up(&self) -> Result<(), AnthropicError> {
+        let request = request_id::attach(self.client.get(format!("{}/v1/models", self.base_url)))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", &self.version);
+        let response = http_transport::send(self.transport.as_ref(), request).await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let message = response.text().await.unwrap_or_default();
+            return Err(AnthropicError::ApiError { status, message });
+        }
+        Ok(())
     }
 }
 
@@ -272,3 +513,131 @@ impl LlmClientChat for AnthropicClient {
 //         mock_server.assert();
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Matcher;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_send_conversation_with_system() {
+        let mut server = mockito::Server::new_async().await;
+        let messages_mock = server
+            .mock("POST", "/v1/messages")
+            .match_body(Matcher::PartialJson(json!({
+                "system": "Answer in one word.",
+                "messages": [
+                    {"role": "user", "content": [{"type": "text", "text": "My shoe size is 42."}]},
+                    {"role": "assistant", "content": [{"type": "text", "text": "Noted."}]},
+                    {"role": "user", "content": [{"type": "text", "text": "What is my shoe size?"}]}
+                ]
+            })))
+            .with_body(
+                json!({
+                    "id": "msg_1",
+                    "content": [{"text": "42", "type": "text"}],
+                    "model": "claude-3-5-haiku-latest",
+                    "role": "assistant",
+                    "stop_reason": "end_turn",
+                    "stop_sequence": null,
+                    "type": "message",
+                    "usage": {
+                        "cache_creation_input_tokens": 0,
+                        "cache_read_input_tokens": 0,
+                        "input_tokens": 20,
+                        "output_tokens": 1
+                    }
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let client = AnthropicClient::new(Some(&server.url()), Some("test-key"), None);
+        let messages = [
+            ChatMessage::user("My shoe size is 42."),
+            ChatMessage::assistant("Noted."),
+            ChatMessage::user("What is my shoe size?"),
+        ];
+        let answer = client
+            .send_conversation(
+                "claude-3-5-haiku-latest",
+                Some("Answer in one word."),
+                &messages,
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(answer, "42");
+        messages_mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_create_message_with_tools() {
+        let mut server = mockito::Server::new_async().await;
+        let messages_mock = server
+            .mock("POST", "/v1/messages")
+            .match_body(Matcher::PartialJson(json!({
+                "tools": [{"name": "remember", "description": "Stores a memory"}]
+            })))
+            .with_body(
+                json!({
+                    "id": "msg_2",
+                    "content": [
+                        {"type": "text", "text": "Saving that."},
+                        {
+                            "type": "tool_use",
+                            "id": "toolu_1",
+                            "name": "remember",
+                            "input": {"text": "Shoe size is 42"}
+                        }
+                    ],
+                    "model": "claude-3-5-haiku-latest",
+                    "role": "assistant",
+                    "stop_reason": "tool_use",
+                    "stop_sequence": null,
+                    "type": "message",
+                    "usage": {
+                        "cache_creation_input_tokens": 0,
+                        "cache_read_input_tokens": 0,
+                        "input_tokens": 60,
+                        "output_tokens": 20
+                    }
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let client = AnthropicClient::new(Some(&server.url()), Some("test-key"), None);
+        let remember = ToolDefinition::new(
+            "remember",
+            "Stores a memory",
+            json!({"type": "object", "properties": {"text": {"type": "string"}}}),
+        );
+        let response = client
+            .create_message(
+                "claude-3-5-haiku-latest",
+                1024,
+                "My shoe size is 42, remember it.",
+                None::<&str>,
+                None,
+                &[remember],
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.text(), "Saving that.");
+        assert_eq!(
+            response.tool_calls(),
+            vec![ToolCall {
+                id: "toolu_1".to_string(),
+                name: "remember".to_string(),
+                arguments: json!({"text": "Shoe size is 42"}),
+            }]
+        );
+        messages_mock.assert_async().await;
+    }
+}
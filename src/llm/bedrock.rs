@@ -0,0 +1,561 @@
+use super::llm_client::{ChatMessage, ChatRole, LlmClientChat};
+use crate::circuit_breaker::{is_transient_status, TransientError};
+use crate::http_transport::{default_transport, HttpTransport};
+use crate::models::{ModelError, ModelRegistry, ModelRequest};
+use crate::request_id;
+use crate::usage;
+use crate::utils::{estimate_model_tokens, load_image};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderValue, InvalidHeaderValue, AUTHORIZATION};
+use reqwest::{Client, Request};
+use ring::{digest, hmac};
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use std::sync::Arc;
+use std::{env, path::Path};
+use thiserror::Error;
+
+const SERVICE: &str = "bedrock";
+
+#[derive(Debug, Deserialize)]
+pub struct ContentBlock {
+    #[serde(default)]
+    text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OutputMessage {
+    content: Vec<ContentBlock>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Output {
+    message: OutputMessage,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Usage {
+    #[serde(default)]
+    input_tokens: u64,
+    #[serde(default)]
+    output_tokens: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConverseResponse {
+    output: Output,
+    #[serde(default)]
+    usage: Usage,
+}
+
+#[derive(Debug, Error)]
+pub enum BedrockError {
+    #[error("API Error: {status}, {message}")]
+    ApiError {
+        status: reqwest::StatusCode,
+        message: String,
+    },
+    #[error("Environment variable not found: {0}")]
+    EnvError(#[from] env::VarError),
+    #[error("IO Error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Request Error: {0}")]
+    RequestError(#[from] reqwest::Error),
+    #[error(transparent)]
+    ModelError(#[from] ModelError),
+    #[error("Invalid header value: {0}")]
+    InvalidHeader(#[from] InvalidHeaderValue),
+}
+
+impl TransientError for BedrockError {
+    fn is_transient(&self) -> bool {
+        match self {
+            BedrockError::ApiError { status, .. } => is_transient_status(*status),
+            BedrockError::RequestError(e) => e.is_transient(),
+            BedrockError::IoError(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// AWS credentials requests are signed with (Signature Version 4).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// Set for temporary credentials, e.g. from an assumed role.
+    pub session_token: Option<String>,
+}
+
+impl AwsCredentials {
+    pub fn new(access_key_id: impl Into<String>, secret_access_key: impl Into<String>) -> Self {
+        Self {
+            access_key_id: access_key_id.into(),
+            secret_access_key: secret_access_key.into(),
+            session_token: None,
+        }
+    }
+
+    pub fn with_session_token(mut self, session_token: impl Into<String>) -> Self {
+        self.session_token = Some(session_token.into());
+        self
+    }
+
+    /// Reads `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and `AWS_SESSION_TOKEN`.
+    pub fn from_env() -> Result<Self, env::VarError> {
+        Ok(Self {
+            access_key_id: env::var("AWS_ACCESS_KEY_ID")?,
+            secret_access_key: env::var("AWS_SECRET_ACCESS_KEY")?,
+            session_token: env::var("AWS_SESSION_TOKEN").ok(),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+enum BedrockAuth {
+    SigV4(AwsCredentials),
+    ApiKey(String),
+}
+
+/// Client of the Bedrock Converse API, for Claude, Titan and the other chat models hosted
+/// on AWS. Requests are signed with SigV4, or carry a Bedrock API key if one is set.
+pub struct BedrockClient {
+    client: Client,
+    transport: Arc<dyn HttpTransport>,
+    base_url: String,
+    region: String,
+    auth: BedrockAuth,
+    models: ModelRegistry,
+}
+
+impl BedrockClient {
+    fn get_or_load_region(region: Option<&str>) -> String {
+        match region {
+            Some(val) => val.to_string(),
+            None => env::var("AWS_REGION")
+                .or_else(|_| env::var("AWS_DEFAULT_REGION"))
+                .unwrap_or_else(|_| "us-east-1".to_string()),
+        }
+    }
+
+    /// Calls the public endpoint of `region`, `AWS_REGION` if not given. Credentials are
+    /// read from the environment if not given.
+    pub fn new(region: Option<&str>, credentials: Option<AwsCredentials>) -> Self {
+        let region = Self::get_or_load_region(region);
+        let credentials = credentials.unwrap_or_else(|| {
+            AwsCredentials::from_env()
+                .expect("AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY must be set")
+        });
+        Self {
+            client: Client::new(),
+            transport: default_transport(),
+            base_url: format!("https://bedrock-runtime.{region}.amazonaws.com"),
+            region,
+            auth: BedrockAuth::SigV4(credentials),
+            models: ModelRegistry::default(),
+        }
+    }
+
+    /// Calls another endpoint than the public one of the region, e.g. a VPC endpoint.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Authenticates with a Bedrock API key instead of signing requests.
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.auth = BedrockAuth::ApiKey(api_key.into());
+        self
+    }
+
+    /// Replaces the default registry used to validate requests and cap their output.
+    pub fn with_models(mut self, models: ModelRegistry) -> Self {
+        self.models = models;
+        self
+    }
+
+    /// Sends requests through `transport` instead of a plain [`Client`]. Requests are
+    /// signed before they reach it.
+    pub fn with_transport(mut self, transport: impl HttpTransport + 'static) -> Self {
+        self.transport = Arc::new(transport);
+        self
+    }
+
+    fn user_content(text: &str, image_buffer: Option<Vec<u8>>) -> Vec<JsonValue> {
+        let mut content = vec![serde_json::json!({"text": text})];
+        if let Some(buffer) = image_buffer {
+            content.push(serde_json::json!({
+                "image": {"format": "png", "source": {"bytes": STANDARD.encode(&buffer)}}
+            }));
+        }
+        content
+    }
+
+    fn validate(&self, model: &str, text: &str, has_image: bool) -> Result<(), ModelError> {
+        self.models.validate(
+            model,
+            &ModelRequest {
+                input_tokens: estimate_model_tokens(text, model),
+                has_image,
+                uses_tools: false,
+            },
+        )
+    }
+
+    async fn converse(
+        &self,
+        model: &str,
+        system: Option<&str>,
+        mut messages: Vec<JsonValue>,
+        temperature: Option<f32>,
+    ) -> Result<String, BedrockError> {
+        let mut payload = serde_json::json!({
+            "inferenceConfig": {"maxTokens": self.models.max_output_tokens(model, 4096)}
+        });
+        if let Some(temperature) = temperature {
+            payload["inferenceConfig"]["temperature"] = serde_json::json!(temperature);
+        }
+        if let Some(system) = system {
+            // Titan text models take no system prompt, it goes before the first message
+            if model.contains("amazon.titan") {
+                if let Some(first) = messages.first_mut() {
+                    let text = first["content"][0]["text"].as_str().unwrap_or_default();
+                    first["content"][0]["text"] = serde_json::json!(format!("{system}\n\n{text}"));
+                }
+            } else {
+                payload["system"] = serde_json::json!([{"text": system}]);
+            }
+        }
+        payload["messages"] = JsonValue::Array(messages);
+
+        // Model ids contain `:`, which has to be escaped in the path
+        let url = format!(
+            "{}/model/{}/converse",
+            self.base_url,
+            uri_encode(model, true)
+        );
+        let mut request = request_id::attach(self.client.post(&url))
+            .header("content-type", "application/json")
+            .body(serde_json::to_vec(&payload).map_err(std::io::Error::other)?)
+            .build()?;
+        match &self.auth {
+            BedrockAuth::SigV4(credentials) => {
+                sign_request(&mut request, credentials, &self.region, SERVICE, Utc::now())?
+            }
+            BedrockAuth::ApiKey(api_key) => {
+                let value = HeaderValue::from_str(&format!("Bearer {api_key}"))?;
+                request.headers_mut().insert(AUTHORIZATION, value);
+            }
+        }
+        let response = self.transport.execute(request).await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let message = response.text().await.unwrap_or_default();
+            return Err(BedrockError::ApiError { status, message });
+        }
+
+        let response: ConverseResponse = response.json().await?;
+        usage::record(
+            model,
+            response.usage.input_tokens,
+            response.usage.output_tokens,
+        );
+        Ok(response
+            .output
+            .message
+            .content
+            .into_iter()
+            .filter_map(|block| block.text)
+            .collect())
+    }
+}
+
+impl LlmClientChat for BedrockClient {
+    type Error = BedrockError;
+
+    /// `base_url` replaces the public endpoint of `AWS_REGION`. Requests carry `api_key`, or
+    /// else `AWS_BEARER_TOKEN_BEDROCK`, if set, and are signed with the credentials in the
+    /// environment otherwise.
+    fn new(base_url: Option<&str>, api_key: Option<&str>) -> Self {
+        let region = Self::get_or_load_region(None);
+        let api_key = api_key
+            .map(str::to_string)
+            .or_else(|| env::var("AWS_BEARER_TOKEN_BEDROCK").ok());
+        Self {
+            client: Client::new(),
+            transport: default_transport(),
+            base_url: match base_url {
+                Some(url) => url.to_string(),
+                None => format!("https://bedrock-runtime.{region}.amazonaws.com"),
+            },
+            region,
+            auth: match api_key {
+                Some(key) => BedrockAuth::ApiKey(key),
+                None => BedrockAuth::SigV4(
+                    AwsCredentials::from_env()
+                        .expect("AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY must be set"),
+                ),
+            },
+            models: ModelRegistry::default(),
+        }
+    }
+
+    async fn send_message(
+        &self,
+        model: impl Into<String>,
+        text: impl AsRef<str>,
+        image_path: Option<impl AsRef<Path>>,
+        temperature: Option<f32>,
+    ) -> Result<String, BedrockError> {
+        let model = model.into();
+        self.validate(&model, text.as_ref(), image_path.is_some())?;
+        let image_buffer = match image_path {
+            Some(path) => Some(load_image(path).await?),
+            None => None,
+        };
+        let messages = vec![serde_json::json!({
+            "role": "user",
+            "content": Self::user_content(text.as_ref(), image_buffer)
+        })];
+        self.converse(&model, None, messages, temperature).await
+    }
+
+    async fn send_conversation(
+        &self,
+        model: impl Into<String>,
+        system: Option<&str>,
+        messages: &[ChatMessage],
+        temperature: Option<f32>,
+    ) -> Result<String, BedrockError> {
+        let model = model.into();
+        let history: String = system
+            .into_iter()
+            .chain(messages.iter().map(|m| m.content.as_str()))
+            .collect();
+        self.validate(&model, &history, false)?;
+        let messages = messages
+            .iter()
+            .map(|message| {
+                let role = match message.role {
+                    ChatRole::User => "user",
+                    ChatRole::Assistant => "assistant",
+                };
+                serde_json::json!({"role": role, "content": [{"text": message.content}]})
+            })
+            .collect();
+        self.converse(&model, system, messages, temperature).await
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex(digest::digest(&digest::SHA256, data).as_ref())
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, key);
+    hmac::sign(&key, data.as_bytes()).as_ref().to_vec()
+}
+
+// Percent-encodes all but the unreserved characters, keeping `/` unless `encode_slash`
+fn uri_encode(value: &str, encode_slash: bool) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+/// Signs `request` with AWS Signature Version 4, adding the `x-amz-date`,
+/// `x-amz-security-token` and `authorization` headers. The request must be complete:
+/// headers added afterwards are not covered by the signature.
+fn sign_request(
+    request: &mut Request,
+    credentials: &AwsCredentials,
+    region: &str,
+    service: &str,
+    time: DateTime<Utc>,
+) -> Result<(), BedrockError> {
+    let amz_date = time.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = time.format("%Y%m%d").to_string();
+    let headers = request.headers_mut();
+    headers.insert("x-amz-date", HeaderValue::from_str(&amz_date)?);
+    if let Some(token) = &credentials.session_token {
+        headers.insert("x-amz-security-token", HeaderValue::from_str(token)?);
+    }
+
+    let url = request.url();
+    let host = match url.port() {
+        Some(port) => format!("{}:{port}", url.host_str().unwrap_or_default()),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+    let mut canonical_headers: Vec<(String, String)> = request
+        .headers()
+        .iter()
+        .map(|(name, value)| {
+            let value = String::from_utf8_lossy(value.as_bytes());
+            (name.as_str().to_lowercase(), value.trim().to_string())
+        })
+        .chain([("host".to_string(), host)])
+        .collect();
+    canonical_headers.sort();
+    let signed_headers = canonical_headers
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+
+    // Services other than S3 encode the already encoded path once more
+    let canonical_uri = uri_encode(url.path(), false);
+    let mut query: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(key, value)| (uri_encode(&key, true), uri_encode(&value, true)))
+        .collect();
+    query.sort();
+    let canonical_query = query
+        .iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect::<Vec<_>>()
+        .join("&");
+    let payload = request
+        .body()
+        .and_then(|body| body.as_bytes())
+        .unwrap_or_default();
+    let canonical_request = format!(
+        "{method}\n{canonical_uri}\n{canonical_query}\n{headers}\n{signed_headers}\n{payload}",
+        method = request.method(),
+        headers = canonical_headers
+            .iter()
+            .map(|(name, value)| format!("{name}:{value}\n"))
+            .collect::<String>(),
+        payload = sha256_hex(payload),
+    );
+
+    let scope = format!("{date}/{region}/{service}/aws4_request");
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+        sha256_hex(canonical_request.as_bytes())
+    );
+    let key = hmac_sha256(
+        format!("AWS4{}", credentials.secret_access_key).as_bytes(),
+        &date,
+    );
+    let key = hmac_sha256(&key, region);
+    let key = hmac_sha256(&key, service);
+    let key = hmac_sha256(&key, "aws4_request");
+    let signature = hex(&hmac_sha256(&key, &string_to_sign));
+
+    let authorization = format!(
+        "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+        credentials.access_key_id
+    );
+    request
+        .headers_mut()
+        .insert(AUTHORIZATION, HeaderValue::from_str(&authorization)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use mockito::Matcher;
+    use serde_json::json;
+
+    #[test]
+    fn test_sign_request() {
+        // "get-vanilla" from the AWS SigV4 test suite
+        let credentials =
+            AwsCredentials::new("AKIDEXAMPLE", "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY");
+        let mut request = Client::new()
+            .get("https://example.amazonaws.com/")
+            .build()
+            .unwrap();
+        let time = Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap();
+        sign_request(&mut request, &credentials, "us-east-1", "service", time).unwrap();
+
+        assert_eq!(
+            request.headers()[AUTHORIZATION],
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, \
+             Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+    }
+
+    #[test]
+    fn test_sign_request_invalid_token() {
+        let credentials =
+            AwsCredentials::new("AKID", "secret").with_session_token("token\nwith newline");
+        let mut request = Client::new()
+            .get("https://example.amazonaws.com/")
+            .build()
+            .unwrap();
+        let result = sign_request(
+            &mut request,
+            &credentials,
+            "us-east-1",
+            "bedrock",
+            Utc::now(),
+        );
+        assert!(matches!(result, Err(BedrockError::InvalidHeader(_))));
+    }
+
+    #[tokio::test]
+    async fn test_converse() {
+        let mut server = mockito::Server::new_async().await;
+        let converse = server
+            .mock(
+                "POST",
+                "/model/anthropic.claude-3-haiku-20240307-v1%3A0/converse",
+            )
+            .match_header(
+                "authorization",
+                Matcher::Regex("^AWS4-HMAC-SHA256 Credential=AKID/".to_string()),
+            )
+            .match_body(Matcher::PartialJson(json!({
+                "system": [{"text": "Answer in one word."}],
+                "messages": [{"role": "user", "content": [{"text": "Shoe size 42. Which size?"}]}]
+            })))
+            .with_body(
+                json!({
+                    "output": {"message": {"role": "assistant", "content": [{"text": "42"}]}},
+                    "stopReason": "end_turn",
+                    "usage": {"inputTokens": 12, "outputTokens": 1, "totalTokens": 13}
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let client = BedrockClient::new(
+            Some("eu-west-1"),
+            Some(AwsCredentials::new("AKID", "secret")),
+        )
+        .with_base_url(server.url());
+        let answer = client
+            .send_conversation(
+                "anthropic.claude-3-haiku-20240307-v1:0",
+                Some("Answer in one word."),
+                &[ChatMessage::user("Shoe size 42. Which size?")],
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(answer, "42");
+        converse.assert_async().await;
+    }
+}
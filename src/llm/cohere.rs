@@ -0,0 +1,441 @@
+use super::llm_client::{ChatMessage, LlmClientChat, LlmClientEmbedding};
+use crate::circuit_breaker::{is_transient_status, TransientError};
+use crate::http_transport::{self, default_transport, HttpTransport};
+use crate::models::{ModelError, ModelRegistry, ModelRequest};
+use crate::request_id;
+use crate::usage;
+use crate::utils::{estimate_model_tokens, estimate_tokens, load_image};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::sync::Arc;
+use std::{env, path::Path};
+use thiserror::Error;
+
+/// What embed-v3 models optimise an embedding for; queries and the documents they should
+/// match are embedded differently.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CohereInputType {
+    #[default]
+    SearchDocument,
+    SearchQuery,
+    Classification,
+    Clustering,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ContentItem {
+    #[serde(rename = "type")]
+    content_type: String,
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResponseMessage {
+    role: String,
+    #[serde(default)]
+    content: Vec<ContentItem>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Tokens {
+    #[serde(default)]
+    input_tokens: f64,
+    #[serde(default)]
+    output_tokens: f64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Usage {
+    #[serde(default)]
+    billed_units: Tokens,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CohereChatResponse {
+    id: String,
+    finish_reason: String,
+    message: ResponseMessage,
+    #[serde(default)]
+    usage: Usage,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Embeddings {
+    float: Vec<Vec<f32>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct EmbedMeta {
+    #[serde(default)]
+    billed_units: Tokens,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CohereEmbedResponse {
+    id: String,
+    embeddings: Embeddings,
+    #[serde(default)]
+    meta: EmbedMeta,
+}
+
+#[derive(Debug, Error)]
+pub enum CohereError {
+    #[error("API Error: {status}, {message}")]
+    ApiError {
+        status: reqwest::StatusCode,
+        message: String,
+    },
+    #[error("Environment variable not found: {0}")]
+    EnvError(#[from] env::VarError),
+    #[error("IO Error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Request Error: {0}")]
+    RequestError(#[from] reqwest::Error),
+    #[error("Invalid response: {0}")]
+    InvalidResponse(String),
+    #[error(transparent)]
+    ModelError(#[from] ModelError),
+}
+
+impl TransientError for CohereError {
+    fn is_transient(&self) -> bool {
+        match self {
+            CohereError::ApiError { status, .. } => is_transient_status(*status),
+            CohereError::RequestError(e) => e.is_transient(),
+            CohereError::IoError(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Client of the Cohere v2 API: chat with Command models and embeddings with embed-v3
+/// models (e.g. `embed-english-v3.0`).
+pub struct CohereClient {
+    client: Client,
+    transport: Arc<dyn HttpTransport>,
+    base_url: String,
+    api_key: String,
+    input_type: CohereInputType,
+    models: ModelRegistry,
+}
+
+impl CohereClient {
+    fn get_or_load_key(key: Option<&str>) -> String {
+        match key {
+            Some(val) => val.to_string(),
+            None => env::var("COHERE_API_KEY").expect("COHERE_API_KEY must be set"),
+        }
+    }
+
+    fn get_or_load_url(url: Option<&str>) -> String {
+        match url {
+            Some(val) => val.to_string(),
+            None => {
+                env::var("COHERE_BASE_URL").unwrap_or_else(|_| "https://api.cohere.com".to_string())
+            }
+        }
+    }
+
+    /// Embeds with [`CohereInputType::SearchDocument`] unless
+    /// [`with_input_type`](Self::with_input_type) says otherwise.
+    pub fn new(base_url: Option<&str>, api_key: Option<&str>) -> Self {
+        Self {
+            client: Client::new(),
+            transport: default_transport(),
+            base_url: Self::get_or_load_url(base_url),
+            api_key: Self::get_or_load_key(api_key),
+            input_type: CohereInputType::default(),
+            models: ModelRegistry::default(),
+        }
+    }
+
+    /// Input type of the embeddings made through [`LlmClientEmbedding::embed`], e.g.
+    /// [`CohereInputType::SearchQuery`] for a client that only embeds queries.
+    pub fn with_input_type(mut self, input_type: CohereInputType) -> Self {
+        self.input_type = input_type;
+        self
+    }
+
+    /// Replaces the default registry used to validate requests and cap their output.
+    pub fn with_models(mut self, models: ModelRegistry) -> Self {
+        self.models = models;
+        self
+    }
+
+    /// Sends requests through `transport` instead of a plain [`Client`].
+    pub fn with_transport(mut self, transport: impl HttpTransport + 'static) -> Self {
+        self.transport = Arc::new(transport);
+        self
+    }
+
+    fn user_message(text: &str, image_buffer: Option<Vec<u8>>) -> JsonValue {
+        let mut content = vec![serde_json::json!({"type": "text", "text": text})];
+        if let Some(buffer) = image_buffer {
+            let image_base64 = STANDARD.encode(&buffer);
+            content.push(serde_json::json!({
+                "type": "image_url",
+                "image_url": {"url": format!("data:image/jpeg;base64,{image_base64}")}
+            }));
+        }
+        serde_json::json!({"role": "user", "content": content})
+    }
+
+    async fn post<T: for<'de> Deserialize<'de>>(
+        &self,
+        path: &str,
+        payload: &JsonValue,
+    ) -> Result<T, CohereError> {
+        let url = format!("{}{path}", self.base_url);
+        let request = request_id::attach(self.client.post(&url))
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Content-Type", "application/json")
+            .json(payload);
+        let response = http_transport::send(self.transport.as_ref(), request).await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let message = response.text().await.unwrap_or_default();
+            return Err(CohereError::ApiError { status, message });
+        }
+        Ok(response.json().await?)
+    }
+
+    async fn chat(
+        &self,
+        model: &str,
+        messages: Vec<JsonValue>,
+        temperature: Option<f32>,
+        json_mode: bool,
+    ) -> Result<String, CohereError> {
+        // Output is capped at the model's maximum, 4096 tokens for unknown models
+        let max_tokens = self.models.max_output_tokens(model, 4096);
+        let mut payload = serde_json::json!({
+            "model": model,
+            "messages": messages,
+            "max_tokens": max_tokens
+        });
+        if let Some(temperature) = temperature {
+            payload["temperature"] = serde_json::json!(temperature);
+        }
+        if json_mode {
+            payload["response_format"] = serde_json::json!({"type": "json_object"});
+        }
+
+        let response: CohereChatResponse = self.post("/v2/chat", &payload).await?;
+        usage::record(
+            model,
+            response.usage.billed_units.input_tokens as u64,
+            response.usage.billed_units.output_tokens as u64,
+        );
+        Ok(response
+            .message
+            .content
+            .into_iter()
+            .filter(|item| item.content_type == "text")
+            .map(|item| item.text)
+            .collect())
+    }
+
+    fn validate(&self, model: &str, text: &str, has_image: bool) -> Result<(), ModelError> {
+        self.models.validate(
+            model,
+            &ModelRequest {
+                input_tokens: estimate_model_tokens(text, model),
+                has_image,
+                uses_tools: false,
+            },
+        )
+    }
+
+    /// Embeds `texts` in one request, for `input_type`.
+    pub async fn embed_texts(
+        &self,
+        model: impl Into<String>,
+        texts: &[String],
+        input_type: CohereInputType,
+    ) -> Result<Vec<Vec<f32>>, CohereError> {
+        let model = model.into();
+        let payload = serde_json::json!({
+            "model": model,
+            "texts": texts,
+            "input_type": input_type,
+            "embedding_types": ["float"]
+        });
+        let response: CohereEmbedResponse = self.post("/v2/embed", &payload).await?;
+        if response.embeddings.float.len() != texts.len() {
+            return Err(CohereError::InvalidResponse(format!(
+                "{} embeddings for {} texts",
+                response.embeddings.float.len(),
+                texts.len()
+            )));
+        }
+
+        let input_tokens = match response.meta.billed_units.input_tokens as u64 {
+            0 => texts.iter().map(|text| estimate_tokens(text) as u64).sum(),
+            billed => billed,
+        };
+        usage::record(&model, input_tokens, 0);
+        Ok(response.embeddings.float)
+    }
+}
+
+impl LlmClientChat for CohereClient {
+    type Error = CohereError;
+
+    fn new(base_url: Option<&str>, api_key: Option<&str>) -> Self {
+        CohereClient::new(base_url, api_key)
+    }
+
+    async fn send_message(
+        &self,
+        model: impl Into<String>,
+        text: impl AsRef<str>,
+        image_path: Option<impl AsRef<Path>>,
+        temperature: Option<f32>,
+    ) -> Result<String, CohereError> {
+        let model = model.into();
+        self.validate(&model, text.as_ref(), image_path.is_some())?;
+        let image_buffer = match image_path {
+            Some(path) => Some(load_image(path).await?),
+            None => None,
+        };
+        let messages = vec![Self::user_message(text.as_ref(), image_buffer)];
+        self.chat(&model, messages, temperature, false).await
+    }
+
+    async fn send_message_json(
+        &self,
+        model: impl Into<String>,
+        text: impl AsRef<str>,
+        temperature: Option<f32>,
+    ) -> Result<String, CohereError> {
+        let model = model.into();
+        self.validate(&model, text.as_ref(), false)?;
+        let messages = vec![Self::user_message(text.as_ref(), None)];
+        self.chat(&model, messages, temperature, true).await
+    }
+
+    async fn send_conversation(
+        &self,
+        model: impl Into<String>,
+        system: Option<&str>,
+        messages: &[ChatMessage],
+        temperature: Option<f32>,
+    ) -> Result<String, CohereError> {
+        let model = model.into();
+        let history: String = system
+            .into_iter()
+            .chain(messages.iter().map(|m| m.content.as_str()))
+            .collect();
+        self.validate(&model, &history, false)?;
+        let messages = system
+            .map(|system| serde_json::json!({"role": "system", "content": system}))
+            .into_iter()
+            .chain(messages.iter().map(|message| serde_json::json!(message)))
+            .collect();
+        self.chat(&model, messages, temperature, false).await
+    }
+
+    async fn warm_up(&self) -> Result<(), CohereError> {
+        let request = request_id::attach(self.client.get(format!("{}/v1/models", self.base_url)))
+            .header("Authorization", format!("Bearer {}", self.api_key));
+        let response = http_transport::send(self.transport.as_ref(), request).await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let message = response.text().await.unwrap_or_default();
+            return Err(CohereError::ApiError { status, message });
+        }
+        Ok(())
+    }
+}
+
+impl LlmClientEmbedding for CohereClient {
+    type Error = CohereError;
+
+    fn new(base_url: Option<&str>, api_key: Option<&str>) -> Self {
+        CohereClient::new(base_url, api_key)
+    }
+
+    async fn embed(
+        &self,
+        model: impl Into<String>,
+        text: impl AsRef<str>,
+    ) -> Result<Vec<f32>, CohereError> {
+        let texts = [text.as_ref().to_string()];
+        let mut embeddings = self.embed_texts(model, &texts, self.input_type).await?;
+        Ok(embeddings.remove(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Matcher;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_cohere_chat_and_embed() {
+        let mut server = mockito::Server::new_async().await;
+        let chat = server
+            .mock("POST", "/v2/chat")
+            .match_body(Matcher::PartialJson(json!({
+                "model": "command-r-plus",
+                "messages": [
+                    {"role": "system", "content": "Answer in one word."},
+                    {"role": "user", "content": "What is my shoe size? It is 42."}
+                ]
+            })))
+            .with_body(
+                json!({
+                    "id": "chat-1",
+                    "finish_reason": "COMPLETE",
+                    "message": {"role": "assistant", "content": [{"type": "text", "text": "42"}]},
+                    "usage": {"billed_units": {"input_tokens": 12, "output_tokens": 1}}
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+        let embed = server
+            .mock("POST", "/v2/embed")
+            .match_body(Matcher::PartialJson(json!({
+                "model": "embed-english-v3.0",
+                "input_type": "search_query"
+            })))
+            .with_body(
+                json!({
+                    "id": "embed-1",
+                    "embeddings": {"float": [[0.1, 0.2, 0.3]]},
+                    "meta": {"billed_units": {"input_tokens": 4}}
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let client = CohereClient::new(Some(&server.url()), Some("test-key"))
+            .with_input_type(CohereInputType::SearchQuery);
+        let answer = client
+            .send_conversation(
+                "command-r-plus",
+                Some("Answer in one word."),
+                &[ChatMessage::user("What is my shoe size? It is 42.")],
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(answer, "42");
+
+        let embedding = client
+            .embed("embed-english-v3.0", "shoe size")
+            .await
+            .unwrap();
+        assert_eq!(embedding, vec![0.1, 0.2, 0.3]);
+        chat.assert_async().await;
+        embed.assert_async().await;
+    }
+}
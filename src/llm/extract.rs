@@ -0,0 +1,180 @@
+use crate::llm::llm_client::LlmClientChat;
+use crate::vectorstore::payload_schema::PayloadSchema;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde_json::Value as JsonValue;
+use thiserror::Error;
+
+const EXTRACT_PROMPT: &str = "Extract the information described by the following JSON \
+schema from the text below. Answer with a single JSON object matching the schema, and \
+nothing else. Use null for optional fields the text does not mention.";
+
+#[derive(Debug, Error)]
+pub enum ExtractError {
+    #[error("LLM error: {0}")]
+    LlmError(String),
+    /// Every attempt gave an answer that could not be turned into the target type; holds
+    /// the problem with the last one.
+    #[error("No valid answer after {attempts} attempts: {last_error}")]
+    InvalidResponse { attempts: usize, last_error: String },
+}
+
+/// Extracts typed values from text with an LLM in JSON mode. The JSON schema of the
+/// target type is put in the prompt and checked against the answer before
+/// deserializing; an answer that fails either step is sent back to the LLM with the
+/// error, up to the attempt limit.
+pub struct Extractor<C: LlmClientChat> {
+    llm_client: C,
+    model: String,
+    max_attempts: usize,
+}
+
+impl<C: LlmClientChat> Extractor<C> {
+    /// Makes up to 3 attempts per extraction.
+    pub fn new(llm_client: C, model: impl Into<String>) -> Self {
+        Self {
+            llm_client,
+            model: model.into(),
+            max_attempts: 3,
+        }
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub async fn extract<T: JsonSchema + DeserializeOwned>(
+        &self,
+        text: &str,
+    ) -> Result<T, ExtractError> {
+        let schema = schemars::schema_for!(T).to_value();
+        let prompt = format!("{EXTRACT_PROMPT}\n\nSchema:\n{schema}\n\nText:\n{text}");
+        let schema = PayloadSchema::new(schema);
+
+        let mut last_error = String::new();
+        for attempt in 0..self.max_attempts {
+            let request = if attempt == 0 {
+                prompt.clone()
+            } else {
+                format!(
+                    "{prompt}\n\nYour previous answer was rejected: {last_error}\n\
+                     Answer again with a corrected JSON object."
+                )
+            };
+            let response = self
+                .llm_client
+                .send_message_json(&self.model, request, Some(0.0))
+                .await
+                .map_err(|e| ExtractError::LlmError(e.to_string()))?;
+            match parse_response(&response, &schema) {
+                Ok(value) => return Ok(value),
+                Err(e) => {
+                    log::debug!("Extraction attempt {} rejected: {e}", attempt + 1);
+                    last_error = e;
+                }
+            }
+        }
+        Err(ExtractError::InvalidResponse {
+            attempts: self.max_attempts,
+            last_error,
+        })
+    }
+}
+
+fn parse_response<T: DeserializeOwned>(
+    response: &str,
+    schema: &PayloadSchema,
+) -> Result<T, String> {
+    let start = response.find('{').ok_or("no JSON object in the answer")?;
+    let end = response.rfind('}').ok_or("no JSON object in the answer")?;
+    let value: JsonValue = serde_json::from_str(response.get(start..=end).unwrap_or_default())
+        .map_err(|e| format!("invalid JSON: {e}"))?;
+    schema.validate_json(&value).map_err(|e| e.to_string())?;
+    serde_json::from_value(value).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use std::cell::RefCell;
+    use std::convert::Infallible;
+    use std::path::Path;
+
+    #[derive(Debug, PartialEq, Deserialize, JsonSchema)]
+    struct Product {
+        name: String,
+        price: f64,
+        sizes: Vec<u32>,
+    }
+
+    // Answers with the scripted responses in order, recording the prompts
+    #[derive(Default)]
+    struct Scripted {
+        responses: RefCell<Vec<&'static str>>,
+        prompts: RefCell<Vec<String>>,
+    }
+
+    impl LlmClientChat for Scripted {
+        type Error = Infallible;
+
+        fn new(_base_url: Option<&str>, _api_key: Option<&str>) -> Self {
+            Self::default()
+        }
+
+        async fn send_message(
+            &self,
+            _model: impl Into<String>,
+            text: impl AsRef<str>,
+            _image_path: Option<impl AsRef<Path>>,
+            _temperature: Option<f32>,
+        ) -> Result<String, Infallible> {
+            self.prompts.borrow_mut().push(text.as_ref().to_string());
+            Ok(self.responses.borrow_mut().remove(0).to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_extract_retries() {
+        let llm = Scripted::default();
+        llm.responses.borrow_mut().extend([
+            r#"{"name": "Trail boots", "price": "89.90", "sizes": [41, 42]}"#,
+            r#"Here it is: {"name": "Trail boots", "price": 89.9, "sizes": [41, 42]}"#,
+        ]);
+        let extractor = Extractor::new(llm, "gpt-4o-mini");
+
+        let product: Product = extractor
+            .extract("Trail boots, 89.90 EUR, sizes 41 and 42")
+            .await
+            .unwrap();
+        assert_eq!(
+            product,
+            Product {
+                name: "Trail boots".to_string(),
+                price: 89.9,
+                sizes: vec![41, 42],
+            }
+        );
+        let prompts = extractor.llm_client.prompts.borrow();
+        assert_eq!(prompts.len(), 2);
+        assert!(prompts[0].contains("\"price\""));
+        assert!(prompts[1].contains("`price` should be of type number"));
+    }
+
+    #[tokio::test]
+    async fn test_extract_gives_up() {
+        let llm = Scripted::default();
+        llm.responses
+            .borrow_mut()
+            .extend(["no idea", r#"{"name": "Trail boots"}"#]);
+        let extractor = Extractor::new(llm, "gpt-4o-mini").with_max_attempts(2);
+
+        let result = extractor.extract::<Product>("Trail boots").await;
+        assert!(matches!(
+            result,
+            Err(ExtractError::InvalidResponse { attempts: 2, ref last_error })
+                if last_error.contains("`price` is required")
+        ));
+    }
+}
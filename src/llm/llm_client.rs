@@ -1,6 +1,56 @@
+use serde::{Deserialize, Serialize};
 use std::error::Error;
 use std::path::Path;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatRole {
+    User,
+    Assistant,
+}
+
+/// One message of a conversation sent with
+/// [`send_conversation`](LlmClientChat::send_conversation).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: ChatRole,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: ChatRole::User,
+            content: content.into(),
+        }
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self {
+            role: ChatRole::Assistant,
+            content: content.into(),
+        }
+    }
+}
+
+/// Renders `messages` as a single prompt, one `role: content` line per message, for
+/// clients without multi-turn support.
+pub fn conversation_transcript(messages: &[ChatMessage]) -> String {
+    let mut transcript: String = messages
+        .iter()
+        .map(|message| {
+            let role = match message.role {
+                ChatRole::User => "user",
+                ChatRole::Assistant => "assistant",
+            };
+            format!("{role}: {}\n", message.content)
+        })
+        .collect();
+    transcript.push_str("assistant:");
+    transcript
+}
+
+#[allow(async_fn_in_trait)]
 pub trait LlmClientChat {
     type Error: Error + Send + Sync + 'static;
 
@@ -15,8 +65,48 @@ pub trait LlmClientChat {
         image_path: Option<impl AsRef<Path>>,
         temperature: Option<f32>,
     ) -> Result<String, Self::Error>;
+
+    /// Like [`send_message`](Self::send_message) without an image, asking the provider to
+    /// answer with a single JSON object where it has a JSON mode. The prompt should still
+    /// ask for JSON, since providers without one fall back to `send_message`.
+    async fn send_message_json(
+        &self,
+        model: impl Into<String>,
+        text: impl AsRef<str>,
+        temperature: Option<f32>,
+    ) -> Result<String, Self::Error> {
+        self.send_message(model, text, None::<&str>, temperature)
+            .await
+    }
+
+    /// Answers the last message of a conversation, given every earlier message as history
+    /// and `system` as the instructions for the whole conversation. Clients without
+    /// multi-turn support send the system prompt and the conversation as one
+    /// [`conversation_transcript`] prompt.
+    async fn send_conversation(
+        &self,
+        model: impl Into<String>,
+        system: Option<&str>,
+        messages: &[ChatMessage],
+        temperature: Option<f32>,
+    ) -> Result<String, Self::Error> {
+        let transcript = conversation_transcript(messages);
+        let prompt = match system {
+            Some(system) => format!("{system}\n\n{transcript}"),
+            None => transcript,
+        };
+        self.send_message(model, prompt, None::<&str>, temperature)
+            .await
+    }
+
+    /// Opens the connection to the provider ahead of the first request, without
+    /// generating any tokens. Does nothing unless the client overrides it.
+    async fn warm_up(&self) -> Result<(), Self::Error> {
+        Ok(())
+    }
 }
 
+#[allow(async_fn_in_trait)]
 pub trait LlmClientEmbedding {
     type Error: Error + Send + Sync + 'static;
 
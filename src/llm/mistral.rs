@@ -0,0 +1,409 @@
+use super::llm_client::{ChatMessage, LlmClientChat, LlmClientEmbedding};
+use crate::circuit_breaker::{is_transient_status, TransientError};
+use crate::http_transport::{self, default_transport, HttpTransport};
+use crate::models::{ModelError, ModelRegistry, ModelRequest};
+use crate::request_id;
+use crate::usage;
+use crate::utils::{estimate_model_tokens, load_image};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::sync::Arc;
+use std::{env, path::Path};
+use thiserror::Error;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Message {
+    role: String,
+    #[serde(default)]
+    content: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Choice {
+    index: i32,
+    message: Message,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Usage {
+    #[serde(default)]
+    prompt_tokens: u64,
+    #[serde(default)]
+    completion_tokens: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MistralChatResponse {
+    id: String,
+    model: String,
+    choices: Vec<Choice>,
+    #[serde(default)]
+    usage: Usage,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MistralEmbeddingResponse {
+    id: String,
+    model: String,
+    data: Vec<EmbeddingData>,
+    #[serde(default)]
+    usage: Usage,
+}
+
+#[derive(Debug, Error)]
+pub enum MistralError {
+    #[error("API Error: {status}, {message}")]
+    ApiError {
+        status: reqwest::StatusCode,
+        message: String,
+    },
+    #[error("Environment variable not found: {0}")]
+    EnvError(#[from] env::VarError),
+    #[error("IO Error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Request Error: {0}")]
+    RequestError(#[from] reqwest::Error),
+    #[error("Invalid response: {0}")]
+    InvalidResponse(String),
+    #[error(transparent)]
+    ModelError(#[from] ModelError),
+}
+
+impl TransientError for MistralError {
+    fn is_transient(&self) -> bool {
+        match self {
+            MistralError::ApiError { status, .. } => is_transient_status(*status),
+            MistralError::RequestError(e) => e.is_transient(),
+            MistralError::IoError(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Client of the Mistral API (`api.mistral.ai`, hosted in the EU): chat with Mistral models
+/// and embeddings with `mistral-embed`.
+pub struct MistralClient {
+    client: Client,
+    transport: Arc<dyn HttpTransport>,
+    base_url: String,
+    api_key: String,
+    models: ModelRegistry,
+}
+
+impl MistralClient {
+    fn get_or_load_key(key: Option<&str>) -> String {
+        match key {
+            Some(val) => val.to_string(),
+            None => env::var("MISTRAL_API_KEY").expect("MISTRAL_API_KEY must be set"),
+        }
+    }
+
+    fn get_or_load_url(url: Option<&str>) -> String {
+        match url {
+            Some(val) => val.to_string(),
+            None => env::var("MISTRAL_BASE_URL")
+                .unwrap_or_else(|_| "https://api.mistral.ai".to_string()),
+        }
+    }
+
+    pub fn new(base_url: Option<&str>, api_key: Option<&str>) -> Self {
+        Self {
+            client: Client::new(),
+            transport: default_transport(),
+            base_url: Self::get_or_load_url(base_url),
+            api_key: Self::get_or_load_key(api_key),
+            models: ModelRegistry::default(),
+        }
+    }
+
+    /// Replaces the default registry used to validate requests and cap their output.
+    pub fn with_models(mut self, models: ModelRegistry) -> Self {
+        self.models = models;
+        self
+    }
+
+    /// Sends requests through `transport` instead of a plain [`Client`].
+    pub fn with_transport(mut self, transport: impl HttpTransport + 'static) -> Self {
+        self.transport = Arc::new(transport);
+        self
+    }
+
+    fn user_message(text: &str, image_buffer: Option<Vec<u8>>) -> JsonValue {
+        let Some(buffer) = image_buffer else {
+            return serde_json::json!({"role": "user", "content": text});
+        };
+        let image_base64 = STANDARD.encode(&buffer);
+        serde_json::json!({
+            "role": "user",
+            "content": [
+                {"type": "text", "text": text},
+                {"type": "image_url", "image_url": format!("data:image/jpeg;base64,{image_base64}")}
+            ]
+        })
+    }
+
+    async fn post<T: for<'de> Deserialize<'de>>(
+        &self,
+        path: &str,
+        payload: &JsonValue,
+    ) -> Result<T, MistralError> {
+        let url = format!("{}{path}", self.base_url);
+        let request = request_id::attach(self.client.post(&url))
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Content-Type", "application/json")
+            .json(payload);
+        let response = http_transport::send(self.transport.as_ref(), request).await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let message = response.text().await.unwrap_or_default();
+            return Err(MistralError::ApiError { status, message });
+        }
+        Ok(response.json().await?)
+    }
+
+    async fn chat(
+        &self,
+        model: &str,
+        messages: Vec<JsonValue>,
+        temperature: Option<f32>,
+        json_mode: bool,
+    ) -> Result<String, MistralError> {
+        // Output is capped at the model's maximum, 1024 tokens for unknown models
+        let max_tokens = self.models.max_output_tokens(model, 1024);
+        let mut payload = serde_json::json!({
+            "model": model,
+            "messages": messages,
+            "max_tokens": max_tokens
+        });
+        if let Some(temperature) = temperature {
+            payload["temperature"] = serde_json::json!(temperature);
+        }
+        if json_mode {
+            payload["response_format"] = serde_json::json!({"type": "json_object"});
+        }
+
+        let response: MistralChatResponse = self.post("/v1/chat/completions", &payload).await?;
+        usage::record(
+            &response.model,
+            response.usage.prompt_tokens,
+            response.usage.completion_tokens,
+        );
+        response
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message.content.unwrap_or_default())
+            .ok_or_else(|| MistralError::InvalidResponse("No choices returned".to_string()))
+    }
+
+    fn validate(&self, model: &str, text: &str, has_image: bool) -> Result<(), ModelError> {
+        self.models.validate(
+            model,
+            &ModelRequest {
+                input_tokens: estimate_model_tokens(text, model),
+                has_image,
+                uses_tools: false,
+            },
+        )
+    }
+
+    /// Embeds `texts` in one request, in order.
+    pub async fn embed_texts(
+        &self,
+        model: impl Into<String>,
+        texts: &[String],
+    ) -> Result<Vec<Vec<f32>>, MistralError> {
+        let model = model.into();
+        let payload = serde_json::json!({
+            "model": model,
+            "input": texts
+        });
+        let mut response: MistralEmbeddingResponse = self.post("/v1/embeddings", &payload).await?;
+        if response.data.len() != texts.len() {
+            return Err(MistralError::InvalidResponse(format!(
+                "{} embeddings for {} texts",
+                response.data.len(),
+                texts.len()
+            )));
+        }
+
+        usage::record(&response.model, response.usage.prompt_tokens, 0);
+        response.data.sort_by_key(|data| data.index);
+        Ok(response
+            .data
+            .into_iter()
+            .map(|data| data.embedding)
+            .collect())
+    }
+}
+
+impl LlmClientChat for MistralClient {
+    type Error = MistralError;
+
+    fn new(base_url: Option<&str>, api_key: Option<&str>) -> Self {
+        MistralClient::new(base_url, api_key)
+    }
+
+    async fn send_message(
+        &self,
+        model: impl Into<String>,
+        text: impl AsRef<str>,
+        image_path: Option<impl AsRef<Path>>,
+        temperature: Option<f32>,
+    ) -> Result<String, MistralError> {
+        let model = model.into();
+        self.validate(&model, text.as_ref(), image_path.is_some())?;
+        let image_buffer = match image_path {
+            Some(path) => Some(load_image(path).await?),
+            None => None,
+        };
+        let messages = vec![Self::user_message(text.as_ref(), image_buffer)];
+        self.chat(&model, messages, temperature, false).await
+    }
+
+    async fn send_message_json(
+        &self,
+        model: impl Into<String>,
+        text: impl AsRef<str>,
+        temperature: Option<f32>,
+    ) -> Result<String, MistralError> {
+        let model = model.into();
+        self.validate(&model, text.as_ref(), false)?;
+        let messages = vec![Self::user_message(text.as_ref(), None)];
+        self.chat(&model, messages, temperature, true).await
+    }
+
+    async fn send_conversation(
+        &self,
+        model: impl Into<String>,
+        system: Option<&str>,
+        messages: &[ChatMessage],
+        temperature: Option<f32>,
+    ) -> Result<String, MistralError> {
+        let model = model.into();
+        let history: String = system
+            .into_iter()
+            .chain(messages.iter().map(|m| m.content.as_str()))
+            .collect();
+        self.validate(&model, &history, false)?;
+        let messages = system
+            .map(|system| serde_json::json!({"role": "system", "content": system}))
+            .into_iter()
+            .chain(messages.iter().map(|message| serde_json::json!(message)))
+            .collect();
+        self.chat(&model, messages, temperature, false).await
+    }
+
+    async fn warm_up(&self) -> Result<(), MistralError> {
+        let request = request_id::attach(self.client.get(format!("{}/v1/models", self.base_url)))
+            .header("Authorization", format!("Bearer {}", self.api_key));
+        let response = http_transport::send(self.transport.as_ref(), request).await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let message = response.text().await.unwrap_or_default();
+            return Err(MistralError::ApiError { status, message });
+        }
+        Ok(())
+    }
+}
+
+impl LlmClientEmbedding for MistralClient {
+    type Error = MistralError;
+
+    fn new(base_url: Option<&str>, api_key: Option<&str>) -> Self {
+        MistralClient::new(base_url, api_key)
+    }
+
+    async fn embed(
+        &self,
+        model: impl Into<String>,
+        text: impl AsRef<str>,
+    ) -> Result<Vec<f32>, MistralError> {
+        let texts = [text.as_ref().to_string()];
+        let mut embeddings = self.embed_texts(model, &texts).await?;
+        Ok(embeddings.remove(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Matcher;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_mistral_chat_and_embed() {
+        let mut server = mockito::Server::new_async().await;
+        let chat = server
+            .mock("POST", "/v1/chat/completions")
+            .match_body(Matcher::PartialJson(json!({
+                "model": "mistral-small-latest",
+                "messages": [{"role": "user", "content": "Shoe size 42. Which size?"}]
+            })))
+            .with_body(
+                json!({
+                    "id": "cmpl-1",
+                    "model": "mistral-small-latest",
+                    "choices": [{
+                        "index": 0,
+                        "message": {"role": "assistant", "content": "42"},
+                        "finish_reason": "stop"
+                    }],
+                    "usage": {"prompt_tokens": 10, "completion_tokens": 1, "total_tokens": 11}
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+        let embed = server
+            .mock("POST", "/v1/embeddings")
+            .match_body(Matcher::PartialJson(json!({
+                "model": "mistral-embed",
+                "input": ["shoes", "boots"]
+            })))
+            .with_body(
+                json!({
+                    "id": "embd-1",
+                    "model": "mistral-embed",
+                    "data": [
+                        {"object": "embedding", "index": 1, "embedding": [0.0, 1.0]},
+                        {"object": "embedding", "index": 0, "embedding": [1.0, 0.0]}
+                    ],
+                    "usage": {"prompt_tokens": 4, "total_tokens": 4}
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let client = MistralClient::new(Some(&server.url()), Some("test-key"));
+        let answer = client
+            .send_message(
+                "mistral-small-latest",
+                "Shoe size 42. Which size?",
+                None::<&str>,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(answer, "42");
+
+        let embeddings = client
+            .embed_texts("mistral-embed", &["shoes".to_string(), "boots".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(embeddings, vec![vec![1.0, 0.0], vec![0.0, 1.0]]);
+        chat.assert_async().await;
+        embed.assert_async().await;
+    }
+}
@@ -1,3 +1,10 @@
 pub mod anthropic;
+#[cfg(feature = "bedrock")]
+pub mod bedrock;
+pub mod cohere;
+pub mod extract;
 pub mod llm_client;
+pub mod mistral;
 pub mod openai;
+pub mod sentences;
+pub mod tools;
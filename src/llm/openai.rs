@@ -1,16 +1,54 @@
-use super::llm_client::{LlmClientChat, LlmClientEmbedding};
-use crate::utils::load_image;
+use super::llm_client::{ChatMessage, LlmClientChat, LlmClientEmbedding};
+use super::tools::{ToolCall, ToolChoice, ToolDefinition, ToolResponse};
+use crate::circuit_breaker::{is_transient_status, TransientError};
+use crate::http_transport::{self, default_transport, HttpTransport};
+use crate::models::{ModelError, ModelRegistry, ModelRequest};
+use crate::request_id;
+use crate::usage;
+use crate::utils::{estimate_model_tokens, estimate_tokens, load_image};
 use base64::{engine::general_purpose::STANDARD, Engine};
-use reqwest::{Client, StatusCode};
+use reqwest::{Client, RequestBuilder, StatusCode};
 use serde::{Deserialize, Serialize};
 use std::env;
 use std::path::Path;
+use std::sync::Arc;
 use thiserror::Error;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Message {
     role: String,
-    content: String,
+    // Null when the model only calls tools
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<OpenAIToolCall>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OpenAIToolCall {
+    id: String,
+    #[serde(rename = "type")]
+    call_type: String,
+    function: FunctionCall,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FunctionCall {
+    name: String,
+    // JSON object encoded as a string
+    arguments: String,
+}
+
+impl From<OpenAIToolCall> for ToolCall {
+    fn from(call: OpenAIToolCall) -> Self {
+        let arguments = serde_json::from_str(&call.function.arguments)
+            .unwrap_or(serde_json::Value::String(call.function.arguments));
+        ToolCall {
+            id: call.id,
+            name: call.function.name,
+            arguments,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -24,6 +62,27 @@ pub struct EmbeddingResponse {
     embedding: Vec<f32>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmbeddingData {
+    embedding: Vec<f32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmbeddingsResponse {
+    data: Vec<EmbeddingData>,
+}
+
+/// Which flavour of the API an [`OpenAIClient`] calls.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum OpenAIApi {
+    /// `/v1/...` paths with bearer auth, as served by OpenAI and compatible servers.
+    #[default]
+    OpenAI,
+    /// Azure OpenAI: `/openai/deployments/{deployment}/...?api-version=...` paths with an
+    /// `api-key` header. The model passed to each request names the deployment.
+    Azure { api_version: String },
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CompletionTokensDetails {
     reasoning_tokens: i32,
@@ -57,6 +116,20 @@ pub struct OpenAIResponse {
     choices: Vec<Choice>,
 }
 
+impl OpenAIResponse {
+    fn into_message(self) -> Result<Message, OpenAIError> {
+        self.choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message)
+            .ok_or_else(|| OpenAIError::IoError(std::io::Error::other("No choices returned")))
+    }
+
+    fn into_text(self) -> Result<String, OpenAIError> {
+        Ok(self.into_message()?.content.unwrap_or_default())
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ErrorResponse {
     error: MessageError,
@@ -85,12 +158,28 @@ pub enum OpenAIError {
     RequestError(#[from] reqwest::Error),
     #[error("Image Error: {0}")]
     ImageError(String),
+    #[error(transparent)]
+    ModelError(#[from] ModelError),
+}
+
+impl TransientError for OpenAIError {
+    fn is_transient(&self) -> bool {
+        match self {
+            OpenAIError::ApiError { status, .. } => is_transient_status(*status),
+            OpenAIError::RequestError(e) => e.is_transient(),
+            OpenAIError::IoError(_) => true,
+            _ => false,
+        }
+    }
 }
 
 pub struct OpenAIClient {
     client: Client,
+    transport: Arc<dyn HttpTransport>,
     base_url: String,
     api_key: String,
+    api: OpenAIApi,
+    models: ModelRegistry,
 }
 
 impl OpenAIClient {
@@ -108,13 +197,7 @@ impl OpenAIClient {
         }
     }
 
-    fn create_payload(
-        model: &str,
-        text: &str,
-        image_buffer: Option<Vec<u8>>,
-        temperature: Option<f32>,
-    ) -> serde_json::Value {
-        let mut messages = Vec::new();
+    fn user_message(text: &str, image_buffer: Option<Vec<u8>>) -> serde_json::Value {
         let mut content = Vec::new();
 
         content.push(serde_json::json!({
@@ -148,62 +231,210 @@ impl OpenAIClient {
             content.push(content_img);
         }
 
-        messages.push(serde_json::json!({
+        serde_json::json!({
             "role": "user",
             "content": content
-        }));
+        })
+    }
 
+    fn create_payload(
+        model: &str,
+        messages: Vec<serde_json::Value>,
+        temperature: Option<f32>,
+        max_tokens: usize,
+    ) -> serde_json::Value {
         serde_json::json!({
             "model": model,
             "messages": messages,
             "temperature": temperature.unwrap_or(0.7),
-            "max_tokens": 1024 // TODO!
+            "max_tokens": max_tokens
         })
     }
 
     pub fn new(base_url: Option<&str>, api_key: Option<&str>) -> Self {
         OpenAIClient {
             client: Client::new(),
+            transport: default_transport(),
             base_url: Self::get_or_load_url(base_url),
             api_key: Self::get_or_load_key(api_key),
+            api: OpenAIApi::OpenAI,
+            models: ModelRegistry::default(),
+        }
+    }
+
+    /// Client of an Azure OpenAI resource, e.g. `https://my-resource.openai.azure.com`.
+    /// The key is read from `AZURE_OPENAI_API_KEY` if not given.
+    pub fn azure(endpoint: &str, api_key: Option<&str>, api_version: &str) -> Self {
+        let api_key = match api_key {
+            Some(val) => val.to_string(),
+            None => env::var("AZURE_OPENAI_API_KEY").expect("AZURE_OPENAI_API_KEY must be set"),
+        };
+        Self::new(Some(endpoint), Some(&api_key)).with_api(OpenAIApi::Azure {
+            api_version: api_version.to_string(),
+        })
+    }
+
+    pub fn with_api(mut self, api: OpenAIApi) -> Self {
+        self.api = api;
+        self
+    }
+
+    // URL of an endpoint, e.g. `chat/completions`, for `model`
+    fn endpoint_url(&self, model: &str, endpoint: &str) -> String {
+        match &self.api {
+            OpenAIApi::OpenAI => format!("{}/v1/{endpoint}", self.base_url),
+            OpenAIApi::Azure { api_version } => format!(
+                "{}/openai/deployments/{model}/{endpoint}?api-version={api_version}",
+                self.base_url
+            ),
+        }
+    }
+
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.api {
+            OpenAIApi::OpenAI => {
+                request.header("Authorization", format!("Bearer {}", self.api_key))
+            }
+            OpenAIApi::Azure { .. } => request.header("api-key", &self.api_key),
+        }
+    }
+
+    /// Replaces the default registry used to validate requests and cap their output.
+    pub fn with_models(mut self, models: ModelRegistry) -> Self {
+        self.models = models;
+        self
+    }
+
+    /// Sends requests through `transport` instead of a plain [`Client`].
+    pub fn with_transport(mut self, transport: impl HttpTransport + 'static) -> Self {
+        self.transport = Arc::new(transport);
+        self
+    }
+
+    fn validate(&self, model: &str, text: &str, has_image: bool) -> Result<(), ModelError> {
+        self.models.validate(
+            model,
+            &ModelRequest {
+                input_tokens: estimate_model_tokens(text, model),
+                has_image,
+                uses_tools: false,
+            },
+        )
+    }
+
+    fn conversation_messages(
+        system: Option<&str>,
+        messages: &[ChatMessage],
+    ) -> Vec<serde_json::Value> {
+        system
+            .map(|system| serde_json::json!({"role": "system", "content": system}))
+            .into_iter()
+            .chain(messages.iter().map(|message| serde_json::json!(message)))
+            .collect()
+    }
+
+    fn tool_payload(tool: &ToolDefinition) -> serde_json::Value {
+        serde_json::json!({
+            "type": "function",
+            "function": {
+                "name": tool.name,
+                "description": tool.description,
+                "parameters": tool.parameters
+            }
+        })
+    }
+
+    fn tool_choice_payload(tool_choice: &ToolChoice) -> serde_json::Value {
+        match tool_choice {
+            ToolChoice::Auto => serde_json::json!("auto"),
+            ToolChoice::None => serde_json::json!("none"),
+            ToolChoice::Required => serde_json::json!("required"),
+            ToolChoice::Tool(name) => {
+                serde_json::json!({"type": "function", "function": {"name": name}})
+            }
         }
     }
 
     async fn create_chat_completion(
         &self,
         model: &str,
-        text: &str,
-        image_path: Option<&str>,
+        messages: Vec<serde_json::Value>,
         temperature: Option<f32>,
-    ) -> Result<OpenAIResponse, Box<dyn std::error::Error>> {
-        let image_buffer = match image_path {
-            Some(path) => Some(load_image(path).await?),
-            None => None,
-        };
-
-        let payload = Self::create_payload(model, text, image_buffer, temperature);
-        let url = format!("{}/v1/chat/completions", self.base_url);
+        json_mode: bool,
+        tools: &[ToolDefinition],
+        tool_choice: &ToolChoice,
+    ) -> Result<OpenAIResponse, OpenAIError> {
+        // Output is capped at the model's maximum, 1024 tokens for unknown models
+        let max_tokens = self.models.max_output_tokens(model, 1024);
+        let mut payload = Self::create_payload(model, messages, temperature, max_tokens);
+        if json_mode {
+            payload["response_format"] = serde_json::json!({"type": "json_object"});
+        }
+        if !tools.is_empty() {
+            payload["tools"] = tools.iter().map(Self::tool_payload).collect();
+            payload["tool_choice"] = Self::tool_choice_payload(tool_choice);
+        }
+        let url = self.endpoint_url(model, "chat/completions");
 
-        let response = self
-            .client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
+        let request = self
+            .authorize(request_id::attach(self.client.post(&url)))
             .header("Content-Type", "application/json")
-            .json(&payload)
-            .send()
-            .await?;
+            .json(&payload);
+        let response = http_transport::send(self.transport.as_ref(), request).await?;
 
         if !response.status().is_success() {
-            let error_response = response.json::<ErrorResponse>().await?;
-            return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                error_response.error.message,
-            )));
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            let message = serde_json::from_str::<ErrorResponse>(&body)
+                .map(|error_response| error_response.error.message)
+                .unwrap_or(body);
+            return Err(OpenAIError::ApiError { status, message });
         }
 
         let result = response.json::<OpenAIResponse>().await?;
+        usage::record(
+            &result.model,
+            result.usage.prompt_tokens as u64,
+            result.usage.completion_tokens as u64,
+        );
         Ok(result)
     }
+
+    /// Like [`send_conversation`](LlmClientChat::send_conversation), offering `tools` the
+    /// model may call as `tool_choice` allows. The calls are returned, not run.
+    pub async fn send_with_tools(
+        &self,
+        model: impl Into<String>,
+        system: Option<&str>,
+        messages: &[ChatMessage],
+        tools: &[ToolDefinition],
+        tool_choice: ToolChoice,
+        temperature: Option<f32>,
+    ) -> Result<ToolResponse, OpenAIError> {
+        let model = model.into();
+        let history: String = system
+            .into_iter()
+            .chain(messages.iter().map(|m| m.content.as_str()))
+            .collect();
+        self.models.validate(
+            &model,
+            &ModelRequest {
+                input_tokens: estimate_model_tokens(&history, &model),
+                has_image: false,
+                uses_tools: !tools.is_empty(),
+            },
+        )?;
+        let messages = Self::conversation_messages(system, messages);
+        let response = self
+            .create_chat_completion(&model, messages, temperature, false, tools, &tool_choice)
+            .await?;
+
+        let message = response.into_message()?;
+        Ok(ToolResponse {
+            text: message.content.unwrap_or_default(),
+            tool_calls: message.tool_calls.into_iter().map(ToolCall::from).collect(),
+        })
+    }
 }
 
 impl LlmClientChat for OpenAIClient {
@@ -212,8 +443,11 @@ impl LlmClientChat for OpenAIClient {
     fn new(base_url: Option<&str>, api_key: Option<&str>) -> Self {
         Self {
             client: Client::new(),
+            transport: default_transport(),
             base_url: Self::get_or_load_url(base_url),
             api_key: Self::get_or_load_key(api_key),
+            api: OpenAIApi::OpenAI,
+            models: ModelRegistry::default(),
         }
     }
 
@@ -224,17 +458,71 @@ impl LlmClientChat for OpenAIClient {
         image_path: Option<impl AsRef<Path>>,
         temperature: Option<f32>,
     ) -> Result<String, OpenAIError> {
+        let model = model.into();
+        self.validate(&model, text.as_ref(), image_path.is_some())?;
+        let image_buffer = match image_path {
+            Some(path) => Some(load_image(path).await?),
+            None => None,
+        };
+        let messages = vec![Self::user_message(text.as_ref(), image_buffer)];
         let response = self
-            .create_chat_completion(
-                &model.into(),
-                text.as_ref(),
-                image_path.as_ref().map(|p| p.as_ref().to_str().unwrap()),
-                temperature,
-            )
-            .await
-            .unwrap();
+            .create_chat_completion(&model, messages, temperature, false, &[], &ToolChoice::Auto)
+            .await?;
 
-        Ok(response.choices[0].message.content.clone())
+        response.into_text()
+    }
+
+    async fn send_message_json(
+        &self,
+        model: impl Into<String>,
+        text: impl AsRef<str>,
+        temperature: Option<f32>,
+    ) -> Result<String, OpenAIError> {
+        let model = model.into();
+        self.validate(&model, text.as_ref(), false)?;
+        let messages = vec![Self::user_message(text.as_ref(), None)];
+        let response = self
+            .create_chat_completion(&model, messages, temperature, true, &[], &ToolChoice::Auto)
+            .await?;
+        response.into_text()
+    }
+
+    async fn send_conversation(
+        &self,
+        model: impl Into<String>,
+        system: Option<&str>,
+        messages: &[ChatMessage],
+        temperature: Option<f32>,
+    ) -> Result<String, OpenAIError> {
+        let model = model.into();
+        let history: String = system
+            .into_iter()
+            .chain(messages.iter().map(|m| m.content.as_str()))
+            .collect();
+        self.validate(&model, &history, false)?;
+        let messages = Self::conversation_messages(system, messages);
+        let response = self
+            .create_chat_completion(&model, messages, temperature, false, &[], &ToolChoice::Auto)
+            .await?;
+        response.into_text()
+    }
+
+    async fn warm_up(&self) -> Result<(), OpenAIError> {
+        let url = match &self.api {
+            OpenAIApi::OpenAI => format!("{}/v1/models", self.base_url),
+            OpenAIApi::Azure { api_version } => {
+                format!("{}/openai/models?api-version={api_version}", self.base_url)
+            }
+        };
+        let request = self.authorize(request_id::attach(self.client.get(url)));
+        let response = http_transport::send(self.transport.as_ref(), request).await?;
+        if !response.status().is_success() {
+            return Err(OpenAIError::ApiError {
+                status: response.status(),
+                message: response.text().await.unwrap_or_default(),
+            });
+        }
+        Ok(())
     }
 }
 
@@ -244,8 +532,11 @@ impl LlmClientEmbedding for OpenAIClient {
     fn new(base_url: Option<&str>, api_key: Option<&str>) -> Self {
         Self {
             client: Client::new(),
+            transport: default_transport(),
             base_url: Self::get_or_load_url(base_url),
             api_key: Self::get_or_load_key(api_key),
+            api: OpenAIApi::OpenAI,
+            models: ModelRegistry::default(),
         }
     }
 
@@ -254,20 +545,23 @@ impl LlmClientEmbedding for OpenAIClient {
         model: impl Into<String>,
         text: impl AsRef<str>,
     ) -> Result<Vec<f32>, OpenAIError> {
-        let payload = serde_json::json!({
-            "model": model.into(),
-            "prompt": text.as_ref()
-        });
-        let url = format!("{}/api/embeddings", self.base_url);
+        let model = model.into();
+        let (url, payload) = match &self.api {
+            OpenAIApi::OpenAI => (
+                format!("{}/api/embeddings", self.base_url),
+                serde_json::json!({"model": model, "prompt": text.as_ref()}),
+            ),
+            OpenAIApi::Azure { .. } => (
+                self.endpoint_url(&model, "embeddings"),
+                serde_json::json!({"input": text.as_ref()}),
+            ),
+        };
 
-        let response = self
-            .client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
+        let request = self
+            .authorize(request_id::attach(self.client.post(&url)))
             .header("Content-Type", "application/json")
-            .json(&payload)
-            .send()
-            .await?;
+            .json(&payload);
+        let response = http_transport::send(self.transport.as_ref(), request).await?;
 
         if !response.status().is_success() {
             let error_response = response.json::<ErrorResponse>().await?;
@@ -284,8 +578,208 @@ impl LlmClientEmbedding for OpenAIClient {
             });
         }
 
-        let result: EmbeddingResponse = response.json().await?;
-        let embeddings = result.embedding;
+        let embeddings = match self.api {
+            OpenAIApi::OpenAI => response.json::<EmbeddingResponse>().await?.embedding,
+            OpenAIApi::Azure { .. } => response
+                .json::<EmbeddingsResponse>()
+                .await?
+                .data
+                .into_iter()
+                .next()
+                .map(|data| data.embedding)
+                .unwrap_or_default(),
+        };
+        usage::record(&model, estimate_tokens(text.as_ref()) as u64, 0);
         Ok(embeddings)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Matcher;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_send_conversation() {
+        let mut server = mockito::Server::new_async().await;
+        let completion = server
+            .mock("POST", "/v1/chat/completions")
+            .match_body(Matcher::PartialJson(json!({
+                "messages": [
+                    {"role": "system", "content": "Answer in one word."},
+                    {"role": "user", "content": "My shoe size is 42."},
+                    {"role": "assistant", "content": "Noted."},
+                    {"role": "user", "content": "What is my shoe size?"}
+                ]
+            })))
+            .with_body(
+                json!({
+                    "id": "chatcmpl-1",
+                    "object": "chat.completion",
+                    "created": 0,
+                    "model": "gpt-4o",
+                    "usage": {"prompt_tokens": 20, "completion_tokens": 3, "total_tokens": 23},
+                    "choices": [{
+                        "message": {"role": "assistant", "content": "42"},
+                        "logprobs": null,
+                        "finish_reason": "stop",
+                        "index": 0
+                    }]
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let client = OpenAIClient::new(Some(&server.url()), Some("test-key"));
+        let messages = [
+            ChatMessage::user("My shoe size is 42."),
+            ChatMessage::assistant("Noted."),
+            ChatMessage::user("What is my shoe size?"),
+        ];
+        let answer = client
+            .send_conversation("gpt-4o", Some("Answer in one word."), &messages, None)
+            .await
+            .unwrap();
+
+        assert_eq!(answer, "42");
+        completion.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_send_message_error() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/v1/chat/completions")
+            .with_status(429)
+            .with_body(
+                json!({"error": {"message": "Rate limit reached", "type": "requests", "code": null}})
+                    .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let client = OpenAIClient::new(Some(&server.url()), Some("test-key"));
+        let error = client
+            .send_message("gpt-4o", "Hello", None::<&str>, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            OpenAIError::ApiError { status, message }
+                if status == StatusCode::TOO_MANY_REQUESTS && message == "Rate limit reached"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_azure_deployment_url() {
+        let mut server = mockito::Server::new_async().await;
+        let completion = server
+            .mock("POST", "/openai/deployments/gpt-4o-prod/chat/completions")
+            .match_query(Matcher::UrlEncoded(
+                "api-version".to_string(),
+                "2024-10-21".to_string(),
+            ))
+            .match_header("api-key", "azure-key")
+            .with_body(
+                json!({
+                    "id": "chatcmpl-3",
+                    "object": "chat.completion",
+                    "created": 0,
+                    "model": "gpt-4o",
+                    "usage": {"prompt_tokens": 5, "completion_tokens": 1, "total_tokens": 6},
+                    "choices": [{
+                        "message": {"role": "assistant", "content": "Hi"},
+                        "logprobs": null,
+                        "finish_reason": "stop",
+                        "index": 0
+                    }]
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let client = OpenAIClient::azure(&server.url(), Some("azure-key"), "2024-10-21");
+        let answer = client
+            .send_message("gpt-4o-prod", "Hello", None::<&str>, None)
+            .await
+            .unwrap();
+
+        assert_eq!(answer, "Hi");
+        completion.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_send_with_tools() {
+        let mut server = mockito::Server::new_async().await;
+        let completion = server
+            .mock("POST", "/v1/chat/completions")
+            .match_body(Matcher::PartialJson(json!({
+                "tools": [{
+                    "type": "function",
+                    "function": {"name": "remember", "description": "Stores a memory"}
+                }],
+                "tool_choice": "required"
+            })))
+            .with_body(
+                json!({
+                    "id": "chatcmpl-2",
+                    "object": "chat.completion",
+                    "created": 0,
+                    "model": "gpt-4o",
+                    "usage": {"prompt_tokens": 40, "completion_tokens": 12, "total_tokens": 52},
+                    "choices": [{
+                        "message": {
+                            "role": "assistant",
+                            "content": null,
+                            "tool_calls": [{
+                                "id": "call_1",
+                                "type": "function",
+                                "function": {
+                                    "name": "remember",
+                                    "arguments": "{\"text\": \"Shoe size is 42\"}"
+                                }
+                            }]
+                        },
+                        "logprobs": null,
+                        "finish_reason": "tool_calls",
+                        "index": 0
+                    }]
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let client = OpenAIClient::new(Some(&server.url()), Some("test-key"));
+        let remember = ToolDefinition::new(
+            "remember",
+            "Stores a memory",
+            json!({"type": "object", "properties": {"text": {"type": "string"}}}),
+        );
+        let response = client
+            .send_with_tools(
+                "gpt-4o",
+                None,
+                &[ChatMessage::user("My shoe size is 42, remember it.")],
+                &[remember],
+                ToolChoice::Required,
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.text, "");
+        assert_eq!(
+            response.tool_calls,
+            vec![ToolCall {
+                id: "call_1".to_string(),
+                name: "remember".to_string(),
+                arguments: json!({"text": "Shoe size is 42"}),
+            }]
+        );
+        completion.assert_async().await;
+    }
+}
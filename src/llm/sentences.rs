@@ -0,0 +1,194 @@
+use futures::stream::{self, Stream, StreamExt};
+use std::collections::VecDeque;
+
+const DEFAULT_MIN_CHARS: usize = 10;
+const DEFAULT_MAX_CHARS: usize = 200;
+
+// Full-width marks end a sentence without a following space
+const WIDE_SENTENCE_ENDS: [char; 3] = ['。', '！', '？'];
+const CLAUSE_ENDS: [char; 4] = [',', ';', ':', '—'];
+
+/// Re-chunks streamed text into complete sentences, for text-to-speech or incremental
+/// rendering. A sentence ends at `.`, `!` or `?` followed by whitespace, so a boundary is
+/// only known once the next token arrives, and at every line break. Sentences shorter than
+/// `min_chars`, e.g. "Dr.", are joined with the next one, and longer than `max_chars`
+/// are split at the last clause mark (`,`, `;`, `:` or `—`) or space.
+#[derive(Debug, Clone)]
+pub struct SentenceSegmenter {
+    buffer: String,
+    min_chars: usize,
+    max_chars: usize,
+}
+
+impl Default for SentenceSegmenter {
+    fn default() -> Self {
+        Self {
+            buffer: String::new(),
+            min_chars: DEFAULT_MIN_CHARS,
+            max_chars: DEFAULT_MAX_CHARS,
+        }
+    }
+}
+
+impl SentenceSegmenter {
+    pub fn new(min_chars: usize, max_chars: usize) -> Self {
+        Self {
+            buffer: String::new(),
+            min_chars,
+            max_chars: max_chars.max(min_chars).max(1),
+        }
+    }
+
+    /// Adds a token, returning the segments it completes, trimmed.
+    pub fn push(&mut self, token: &str) -> Vec<String> {
+        self.buffer.push_str(token);
+        let mut segments = Vec::new();
+        while let Some(end) = self.boundary() {
+            let segment: String = self.buffer.drain(..end).collect();
+            if !segment.trim().is_empty() {
+                segments.push(segment.trim().to_string());
+            }
+        }
+        segments
+    }
+
+    /// The remaining text once the stream has ended, if any.
+    pub fn finish(&mut self) -> Option<String> {
+        let rest = std::mem::take(&mut self.buffer);
+        let rest = rest.trim();
+        (!rest.is_empty()).then(|| rest.to_string())
+    }
+
+    // Byte offset just past the first complete segment of the buffer
+    fn boundary(&self) -> Option<usize> {
+        let mut clause = None;
+        let mut space = None;
+        for (chars, (idx, c)) in self.buffer.char_indices().enumerate() {
+            let end = idx + c.len_utf8();
+            if c == '\n' {
+                return Some(end);
+            }
+            if chars >= self.max_chars {
+                return clause.or(space);
+            }
+            let next = self.buffer[end..].chars().next();
+            let spaced = next.is_some_and(char::is_whitespace);
+            let long_enough = chars + 1 >= self.min_chars;
+            if long_enough && WIDE_SENTENCE_ENDS.contains(&c) && next.is_some() {
+                return Some(end);
+            }
+            if long_enough && matches!(c, '.' | '!' | '?') && spaced {
+                return Some(end);
+            }
+            if CLAUSE_ENDS.contains(&c) && spaced {
+                clause = Some(end);
+            } else if c.is_whitespace() && idx > 0 {
+                space = Some(idx);
+            }
+        }
+        None
+    }
+}
+
+/// Combinators splitting a stream of text tokens, such as a streamed chat completion,
+/// into sentences with a [`SentenceSegmenter`]. Errors are passed through in order.
+pub trait SentenceStreamExt<T: AsRef<str>, E>: Stream<Item = Result<T, E>> + Sized {
+    fn sentences(self) -> impl Stream<Item = Result<String, E>> {
+        self.segmented(SentenceSegmenter::default())
+    }
+
+    fn segmented(self, segmenter: SentenceSegmenter) -> impl Stream<Item = Result<String, E>> {
+        let state = (Box::pin(self), segmenter, VecDeque::new(), false);
+        stream::unfold(
+            state,
+            |(mut tokens, mut segmenter, mut pending, mut ended)| async move {
+                loop {
+                    if let Some(segment) = pending.pop_front() {
+                        return Some((Ok(segment), (tokens, segmenter, pending, ended)));
+                    }
+                    if ended {
+                        return None;
+                    }
+                    match tokens.next().await {
+                        Some(Ok(token)) => pending.extend(segmenter.push(token.as_ref())),
+                        Some(Err(e)) => {
+                            return Some((Err(e), (tokens, segmenter, pending, ended)));
+                        }
+                        None => {
+                            pending.extend(segmenter.finish());
+                            ended = true;
+                        }
+                    }
+                }
+            },
+        )
+    }
+}
+
+impl<S, T, E> SentenceStreamExt<T, E> for S
+where
+    S: Stream<Item = Result<T, E>>,
+    T: AsRef<str>,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segmenter() {
+        let mut segmenter = SentenceSegmenter::default();
+        let mut segments = Vec::new();
+        for token in [
+            "Hello",
+            " Dr.",
+            " Smith! Your boo",
+            "ts ship",
+            "ped today.",
+            " Track",
+        ] {
+            segments.extend(segmenter.push(token));
+        }
+        assert_eq!(
+            segments,
+            vec!["Hello Dr. Smith!", "Your boots shipped today."]
+        );
+        segments.extend(segmenter.push(" them online.\n- Item"));
+        segments.extend(segmenter.finish());
+        assert_eq!(
+            segments,
+            vec![
+                "Hello Dr. Smith!",
+                "Your boots shipped today.",
+                "Track them online.",
+                "- Item"
+            ]
+        );
+
+        let mut segmenter = SentenceSegmenter::new(0, 20);
+        assert_eq!(
+            segmenter.push("First, a long clause without an end "),
+            vec!["First,", "a long clause"]
+        );
+        assert_eq!(segmenter.push("好的。谢谢"), vec!["without an end 好的。"]);
+    }
+
+    #[tokio::test]
+    async fn test_sentences() {
+        let tokens = stream::iter(vec![
+            Ok("The boots are red. "),
+            Err("timeout"),
+            Ok("They come in size 42"),
+        ]);
+        let sentences: Vec<Result<String, &str>> = tokens.sentences().collect().await;
+        assert_eq!(
+            sentences,
+            vec![
+                Ok("The boots are red.".to_string()),
+                Err("timeout"),
+                Ok("They come in size 42".to_string()),
+            ]
+        );
+    }
+}
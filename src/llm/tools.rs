@@ -0,0 +1,104 @@
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+/// A function the model may ask to call, with its parameters as a JSON schema.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolDefinition {
+    pub name: String,
+    pub description: String,
+    pub parameters: JsonValue,
+}
+
+impl ToolDefinition {
+    pub fn new(
+        name: impl Into<String>,
+        description: impl Into<String>,
+        parameters: JsonValue,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            parameters,
+        }
+    }
+
+    /// A tool whose parameters are the fields of `T`, so calls can be read back with
+    /// [`ToolCall::parse_arguments`].
+    pub fn for_type<T: JsonSchema>(
+        name: impl Into<String>,
+        description: impl Into<String>,
+    ) -> Self {
+        Self::new(name, description, schemars::schema_for!(T).to_value())
+    }
+}
+
+/// Whether the model may, must or must not call a tool.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ToolChoice {
+    /// The model decides.
+    #[default]
+    Auto,
+    /// Text only, even with tools given.
+    None,
+    /// At least one tool call.
+    Required,
+    /// A call of the tool with this name.
+    Tool(String),
+}
+
+/// A call the model asked for; running it is up to the caller.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    /// Provider id of the call, to match it with its result.
+    pub id: String,
+    pub name: String,
+    /// Arguments as a JSON object. Arguments the model sent as invalid JSON are kept as
+    /// a string.
+    pub arguments: JsonValue,
+}
+
+impl ToolCall {
+    pub fn parse_arguments<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        T::deserialize(&self.arguments)
+    }
+}
+
+/// Answer to a request with tools: text, tool calls or both.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ToolResponse {
+    pub text: String,
+    pub tool_calls: Vec<ToolCall>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Debug, PartialEq, Deserialize, JsonSchema)]
+    struct Remember {
+        text: String,
+        importance: Option<f32>,
+    }
+
+    #[test]
+    fn test_tool_for_type() {
+        let tool = ToolDefinition::for_type::<Remember>("remember", "Stores a memory");
+        assert_eq!(tool.parameters["properties"]["text"]["type"], "string");
+
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            name: "remember".to_string(),
+            arguments: json!({"text": "Size 42"}),
+        };
+        assert_eq!(
+            call.parse_arguments::<Remember>().unwrap(),
+            Remember {
+                text: "Size 42".to_string(),
+                importance: None,
+            }
+        );
+    }
+}
@@ -0,0 +1,35 @@
+use log::{LevelFilter, Log, Metadata, Record};
+use std::env;
+
+// Writes every enabled record to stderr
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        eprintln!("{} {} {}", record.level(), record.target(), record.args());
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: StderrLogger = StderrLogger;
+
+/// Logs the crate's `log` records to stderr, at the level of the `RUST_LOG` environment
+/// variable (`info` if unset), for the binaries and applications without a logger of
+/// their own. Does nothing if a logger is already set.
+pub fn init_stderr() {
+    let level = env::var("RUST_LOG")
+        .ok()
+        .and_then(|level| level.parse().ok())
+        .unwrap_or(LevelFilter::Info);
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(level);
+    }
+}
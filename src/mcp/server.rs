@@ -0,0 +1,286 @@
+use crate::memory::diversity::SourceDiversity;
+use crate::memory::feedback::FeedbackSignal;
+use crate::memory::memory_store::{Memory, MemoryError, MemoryStore};
+use serde_json::{json, Value as JsonValue};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+const PROTOCOL_VERSION: &str = "2024-11-05";
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Model Context Protocol server exposing a [`MemoryStore`] as `remember`, `recall`,
+/// `search_collection` and `feedback` tools. Speaks newline-delimited JSON-RPC over stdio.
+pub struct McpServer {
+    store: MemoryStore,
+}
+
+impl McpServer {
+    pub fn new(store: MemoryStore) -> Self {
+        Self { store }
+    }
+
+    pub async fn serve_stdio(&self) -> std::io::Result<()> {
+        let mut lines = BufReader::new(tokio::io::stdin()).lines();
+        let mut stdout = tokio::io::stdout();
+
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = match serde_json::from_str::<JsonValue>(&line) {
+                Ok(request) => self.handle(request).await,
+                Err(e) => Some(error_response(JsonValue::Null, PARSE_ERROR, &e.to_string())),
+            };
+            if let Some(response) = response {
+                stdout.write_all(response.to_string().as_bytes()).await?;
+                stdout.write_all(b"\n").await?;
+                stdout.flush().await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handles one JSON-RPC message. Notifications (no `id`) get no response.
+    pub async fn handle(&self, request: JsonValue) -> Option<JsonValue> {
+        let id = request.get("id").cloned()?;
+        let method = request["method"].as_str().unwrap_or_default();
+        let params = request.get("params").cloned().unwrap_or(json!({}));
+
+        let response = match method {
+            "initialize" => success_response(
+                id,
+                json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": {"tools": {}},
+                    "serverInfo": {
+                        "name": env!("CARGO_PKG_NAME"),
+                        "version": env!("CARGO_PKG_VERSION"),
+                    },
+                }),
+            ),
+            "ping" => success_response(id, json!({})),
+            "tools/list" => success_response(id, json!({ "tools": tool_definitions() })),
+            "tools/call" => match self.call_tool(&params).await {
+                Ok(result) => success_response(id, result),
+                Err(message) => error_response(id, INVALID_PARAMS, &message),
+            },
+            _ => error_response(id, METHOD_NOT_FOUND, &format!("Unknown method: {method}")),
+        };
+        Some(response)
+    }
+
+    async fn call_tool(&self, params: &JsonValue) -> Result<JsonValue, String> {
+        let name = params["name"].as_str().ok_or("Missing tool name")?;
+        let arguments = &params["arguments"];
+        let limit = arguments["limit"].as_u64().unwrap_or(5);
+
+        let result = match name {
+            "remember" => {
+                let text = required_str(arguments, "text")?;
+                let metadata = arguments["metadata"].as_object().cloned();
+                self.store
+                    .remember(text, metadata)
+                    .await
+                    .map(|id| json!({ "id": id }))
+            }
+            "recall" => {
+                let query = required_str(arguments, "query")?;
+                match arguments["max_per_source"].as_u64() {
+                    Some(max_per_source) => {
+                        let diversity = SourceDiversity::new(max_per_source as usize);
+                        self.store.recall_diverse(query, limit, &diversity).await
+                    }
+                    None => self.store.recall(query, limit).await,
+                }
+                .map(memories_to_json)
+            }
+            "search_collection" => {
+                let collection = required_str(arguments, "collection")?;
+                let query = required_str(arguments, "query")?;
+                self.store
+                    .search_collection(collection, query, limit)
+                    .await
+                    .map(memories_to_json)
+            }
+            "feedback" => {
+                let query = required_str(arguments, "query")?;
+                let id = required_str(arguments, "id")?;
+                let signal = match required_str(arguments, "signal")? {
+                    "up" => FeedbackSignal::Up,
+                    "down" => FeedbackSignal::Down,
+                    signal => return Err(format!("Invalid signal: {signal}")),
+                };
+                self.store
+                    .feedback(query, id, signal)
+                    .await
+                    .map(|()| json!({ "id": id }))
+            }
+            _ => return Err(format!("Unknown tool: {name}")),
+        };
+
+        Ok(tool_result(result))
+    }
+}
+
+fn required_str<'a>(arguments: &'a JsonValue, key: &str) -> Result<&'a str, String> {
+    arguments[key]
+        .as_str()
+        .ok_or_else(|| format!("Missing argument: {key}"))
+}
+
+fn memories_to_json(memories: Vec<Memory>) -> JsonValue {
+    json!(memories)
+}
+
+/// Store failures are reported as tool errors so the model can see them, not as protocol errors.
+fn tool_result(result: Result<JsonValue, MemoryError>) -> JsonValue {
+    match result {
+        Ok(value) => json!({
+            "content": [{"type": "text", "text": value.to_string()}],
+            "isError": false,
+        }),
+        Err(e) => json!({
+            "content": [{"type": "text", "text": e.to_string()}],
+            "isError": true,
+        }),
+    }
+}
+
+fn success_response(id: JsonValue, result: JsonValue) -> JsonValue {
+    json!({"jsonrpc": "2.0", "id": id, "result": result})
+}
+
+fn error_response(id: JsonValue, code: i64, message: &str) -> JsonValue {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+}
+
+fn tool_definitions() -> Vec<JsonValue> {
+    let limit =
+        json!({"type": "integer", "description": "Maximum number of results", "default": 5});
+
+    vec![
+        json!({
+            "name": "remember",
+            "description": "Store a piece of text in long-term memory.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "text": {"type": "string"},
+                    "metadata": {"type": "object"},
+                },
+                "required": ["text"],
+            },
+        }),
+        json!({
+            "name": "recall",
+            "description": "Retrieve the memories most relevant to a query.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": {"type": "string"},
+                    "limit": limit.clone(),
+                    "max_per_source": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": "Most memories returned from the same source or image.",
+                    },
+                },
+                "required": ["query"],
+            },
+        }),
+        json!({
+            "name": "search_collection",
+            "description": "Semantic search over any collection in the vector store.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "collection": {"type": "string"},
+                    "query": {"type": "string"},
+                    "limit": limit,
+                },
+                "required": ["collection", "query"],
+            },
+        }),
+        json!({
+            "name": "feedback",
+            "description": "Mark a recalled memory as relevant (up) or not relevant (down) to a query.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": {"type": "string"},
+                    "id": {"type": "string"},
+                    "signal": {"type": "string", "enum": ["up", "down"]},
+                },
+                "required": ["query", "id", "signal"],
+            },
+        }),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embeddings::text_embedding_inference::TextEmbeddingInference;
+    use crate::vectorstore::qdrant_client::QdrantClient;
+
+    fn server() -> McpServer {
+        let store = MemoryStore::new(
+            QdrantClient::new("http://localhost:6334"),
+            TextEmbeddingInference::new(None),
+            "memories",
+        );
+        McpServer::new(store)
+    }
+
+    #[tokio::test]
+    async fn test_initialize() {
+        let response = server()
+            .handle(json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}))
+            .await
+            .unwrap();
+        assert_eq!(response["result"]["protocolVersion"], PROTOCOL_VERSION);
+    }
+
+    #[tokio::test]
+    async fn test_tools_list() {
+        let response = server()
+            .handle(json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list"}))
+            .await
+            .unwrap();
+        let names: Vec<&str> = response["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tool| tool["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            vec!["remember", "recall", "search_collection", "feedback"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_notification_has_no_response() {
+        let response = server()
+            .handle(json!({"jsonrpc": "2.0", "method": "notifications/initialized"}))
+            .await;
+        assert!(response.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tools_call_missing_argument() {
+        let response = server()
+            .handle(json!({
+                "jsonrpc": "2.0",
+                "id": 3,
+                "method": "tools/call",
+                "params": {"name": "recall", "arguments": {}},
+            }))
+            .await
+            .unwrap();
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+    }
+}
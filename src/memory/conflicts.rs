@@ -0,0 +1,179 @@
+use crate::llm::llm_client::LlmClientChat;
+use crate::memory::memory_store::{Memory, MemoryError, MemoryStore};
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use std::collections::HashSet;
+
+const CONFLICT_PROMPT: &str = "Below are numbered memories retrieved for the same query. \
+Find pairs or groups of memories that contradict each other, i.e. that cannot both be true \
+at the same time. Answer with a single JSON object and nothing else, in the form \
+{\"conflicts\": [{\"memories\": [1, 3], \"description\": \"...\"}]}, \
+with an empty list if there are no contradictions.\n\n";
+
+/// Memories that contradict each other, e.g. two different cities the user lives in.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Conflict {
+    pub memory_ids: Vec<String>,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConflictReport {
+    pub query: String,
+    pub memories: Vec<Memory>,
+    pub conflicts: Vec<Conflict>,
+}
+
+impl ConflictReport {
+    pub fn has_conflicts(&self) -> bool {
+        !self.conflicts.is_empty()
+    }
+}
+
+fn conflict_prompt(query: &str, memories: &[Memory]) -> String {
+    let mut prompt = format!("{CONFLICT_PROMPT}Query: {query}\n\n");
+    for (idx, memory) in memories.iter().enumerate() {
+        let timestamp = memory
+            .metadata
+            .get("timestamp")
+            .and_then(JsonValue::as_str)
+            .unwrap_or("unknown");
+        prompt.push_str(&format!(
+            "[{}] (stored {timestamp}) {}\n",
+            idx + 1,
+            memory.text
+        ));
+    }
+    prompt
+}
+
+/// Maps the 1-based memory numbers of the model response back to ids. Entries referring to
+/// fewer than two known memories are dropped.
+fn parse_conflicts(response: &str, memories: &[Memory]) -> Option<Vec<Conflict>> {
+    let start = response.find('{')?;
+    let end = response.rfind('}')?;
+    let value: JsonValue = serde_json::from_str(response.get(start..=end)?).ok()?;
+
+    let conflicts = value
+        .get("conflicts")?
+        .as_array()?
+        .iter()
+        .filter_map(|conflict| {
+            let mut memory_ids: Vec<String> = conflict
+                .get("memories")?
+                .as_array()?
+                .iter()
+                .filter_map(|number| {
+                    let idx = number.as_u64()?.checked_sub(1)?;
+                    memories.get(idx as usize).map(|memory| memory.id.clone())
+                })
+                .collect();
+            // The model may repeat a memory anywhere in the list, keep its first mention
+            let mut seen = HashSet::new();
+            memory_ids.retain(|id| seen.insert(id.clone()));
+            if memory_ids.len() < 2 {
+                return None;
+            }
+            let description = conflict
+                .get("description")
+                .and_then(JsonValue::as_str)
+                .unwrap_or_default()
+                .to_string();
+            Some(Conflict {
+                memory_ids,
+                description,
+            })
+        })
+        .collect();
+    Some(conflicts)
+}
+
+impl MemoryStore {
+    /// Like [`MemoryStore::recall`], and asks the LLM whether the recalled memories
+    /// contradict each other, so agents can ask a clarifying question instead of citing
+    /// a stale memory.
+    pub async fn recall_with_conflicts<C: LlmClientChat>(
+        &self,
+        query: &str,
+        limit: u64,
+        llm_client: &C,
+        model: &str,
+    ) -> Result<ConflictReport, MemoryError> {
+        let memories = self.recall(query, limit).await?;
+
+        let conflicts = if memories.len() < 2 {
+            Vec::new()
+        } else {
+            let response = llm_client
+                .send_message(
+                    model,
+                    conflict_prompt(query, &memories),
+                    None::<&str>,
+                    Some(0.0),
+                )
+                .await
+                .map_err(|e| MemoryError::LlmError(e.to_string()))?;
+            parse_conflicts(&response, &memories).ok_or_else(|| {
+                MemoryError::LlmError(format!("unparsable conflict check response: {response}"))
+            })?
+        };
+
+        Ok(ConflictReport {
+            query: query.to_string(),
+            memories,
+            conflicts,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Map;
+
+    fn memory(id: &str, text: &str) -> Memory {
+        Memory {
+            id: id.to_string(),
+            text: text.to_string(),
+            metadata: Map::new(),
+            score: 0.8,
+        }
+    }
+
+    #[test]
+    fn test_parse_conflicts() {
+        let memories = vec![
+            memory("a", "User lives in Porto"),
+            memory("b", "User likes hiking"),
+            memory("c", "User lives in Lisbon"),
+        ];
+        let response = "```json\n{\"conflicts\": [\
+            {\"memories\": [1, 3], \"description\": \"Different home cities\"},\
+            {\"memories\": [2, 9], \"description\": \"Out of range\"},\
+            {\"memories\": [1, 2, 1], \"description\": \"Repeated\"}]}\n```";
+        assert_eq!(
+            parse_conflicts(response, &memories),
+            Some(vec![
+                Conflict {
+                    memory_ids: vec!["a".to_string(), "c".to_string()],
+                    description: "Different home cities".to_string(),
+                },
+                Conflict {
+                    memory_ids: vec!["a".to_string(), "b".to_string()],
+                    description: "Repeated".to_string(),
+                }
+            ])
+        );
+        assert_eq!(
+            parse_conflicts("{\"conflicts\": []}", &memories),
+            Some(vec![])
+        );
+        assert_eq!(parse_conflicts("No conflicts.", &memories), None);
+    }
+
+    #[test]
+    fn test_conflict_prompt() {
+        let prompt = conflict_prompt("where does the user live?", &[memory("a", "Porto")]);
+        assert!(prompt.ends_with("[1] (stored unknown) Porto\n"));
+    }
+}
@@ -0,0 +1,269 @@
+use crate::memory::fact_extraction::Turn;
+use crate::memory::memory_store::{Memory, MemoryError, MemoryStore};
+use crate::memory::provenance::derived_from;
+use qdrant_client::qdrant::{Condition, Filter};
+use serde_json::{json, Map};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+const EXCHANGE_KIND: &str = "exchange";
+const WINDOW_KIND: &str = "window";
+
+/// What [`ConversationMemory`] embeds besides the individual turns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Granularity {
+    /// Each turn on its own.
+    Turn,
+    /// Each user turn together with the assistant turn answering it.
+    Exchange,
+    /// `size` consecutive turns, a new window every `stride` turns.
+    Window { size: usize, stride: usize },
+}
+
+impl Granularity {
+    /// Windows of `size` turns overlapping by half.
+    pub fn window(size: usize) -> Self {
+        let size = size.max(2);
+        Self::Window {
+            size,
+            stride: (size / 2).max(1),
+        }
+    }
+
+    /// The `kind` of the memories written at this granularity.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Turn => "turn",
+            Self::Exchange => EXCHANGE_KIND,
+            Self::Window { .. } => WINDOW_KIND,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct BufferedTurn {
+    id: String,
+    role: String,
+    text: String,
+}
+
+#[derive(Debug, Default)]
+struct Session {
+    // The last turns, as many as the largest granularity needs
+    recent: Vec<BufferedTurn>,
+    turns_seen: usize,
+}
+
+/// Conversation turns stored at several granularities, since a single turn recalled on
+/// its own often lacks the context that makes it useful. Every turn is stored with
+/// [`MemoryStore::remember_turn`]; exchanges and windows are extra memories holding the
+/// text of their turns, with `derived_from` linking them to the turns (see
+/// [`MemoryStore::sources_of`] and [`MemoryStore::derived_memories`]).
+///
+/// Recent turns are buffered per session in memory, so after a restart exchanges and
+/// windows only cover turns added since.
+pub struct ConversationMemory {
+    store: MemoryStore,
+    granularities: Vec<Granularity>,
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+impl ConversationMemory {
+    /// Embeds turns and exchanges.
+    pub fn new(store: MemoryStore) -> Self {
+        Self {
+            store,
+            granularities: vec![Granularity::Turn, Granularity::Exchange],
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The granularities written and recalled. Turns are stored either way, as the
+    /// other levels link to them, but only recalled with [`Granularity::Turn`].
+    pub fn with_granularities(mut self, granularities: &[Granularity]) -> Self {
+        self.granularities = granularities.to_vec();
+        self
+    }
+
+    pub fn store(&self) -> &MemoryStore {
+        &self.store
+    }
+
+    /// Stores a turn and any exchange or window it completes, returning the turn's id.
+    pub async fn add_turn(
+        &self,
+        session_id: &str,
+        role: &str,
+        text: &str,
+    ) -> Result<String, MemoryError> {
+        let id = self.store.remember_turn(session_id, role, text).await?;
+        let chunks = {
+            let mut sessions = self.sessions.lock().unwrap();
+            let session = sessions.entry(session_id.to_string()).or_default();
+            session.recent.push(BufferedTurn {
+                id: id.clone(),
+                role: role.to_string(),
+                text: text.to_string(),
+            });
+            session.turns_seen += 1;
+            let chunks = completed_chunks(session, &self.granularities);
+            let keep = self.largest_chunk();
+            let excess = session.recent.len().saturating_sub(keep);
+            session.recent.drain(..excess);
+            chunks
+        };
+
+        for (granularity, turns) in chunks {
+            let mut metadata = Map::new();
+            metadata.insert("kind".to_string(), json!(granularity.kind()));
+            metadata.insert("session_id".to_string(), json!(session_id));
+            let ids = turns.iter().map(|turn| turn.id.clone()).collect();
+            self.store
+                .remember_derived(&chunk_text(&turns), Some(metadata), ids)
+                .await?;
+        }
+        Ok(id)
+    }
+
+    /// Recalls memories at the configured granularities, from one session or all.
+    pub async fn recall(
+        &self,
+        query: &str,
+        limit: u64,
+        session_id: Option<&str>,
+    ) -> Result<Vec<Memory>, MemoryError> {
+        let kinds: Vec<String> = self
+            .granularities
+            .iter()
+            .map(|granularity| granularity.kind().to_string())
+            .collect();
+        let mut must = vec![Condition::matches("kind", kinds)];
+        if let Some(session_id) = session_id {
+            must.push(Condition::matches("session_id", session_id.to_string()));
+        }
+        self.store
+            .recall_filtered(query, limit, Filter::must(must))
+            .await
+    }
+
+    /// The turns a recalled memory covers, in order: the memory itself for a turn, the
+    /// linked turns for an exchange or window.
+    pub async fn turns_of(&self, memory: &Memory) -> Result<Vec<Turn>, MemoryError> {
+        if let Some(turn) = Turn::from_memory(memory.clone()) {
+            return Ok(vec![turn]);
+        }
+        let mut turns: Vec<Turn> = self
+            .store
+            .memories_by_id(derived_from(memory))
+            .await?
+            .into_iter()
+            .filter_map(Turn::from_memory)
+            .collect();
+        turns.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+        Ok(turns)
+    }
+
+    fn largest_chunk(&self) -> usize {
+        self.granularities
+            .iter()
+            .map(|granularity| match granularity {
+                Granularity::Turn => 1,
+                Granularity::Exchange => 2,
+                Granularity::Window { size, .. } => *size,
+            })
+            .max()
+            .unwrap_or(1)
+    }
+}
+
+// Exchanges and windows ending with the session's latest turn
+fn completed_chunks(
+    session: &Session,
+    granularities: &[Granularity],
+) -> Vec<(Granularity, Vec<BufferedTurn>)> {
+    let recent = &session.recent;
+    let mut chunks = Vec::new();
+    for granularity in granularities {
+        match *granularity {
+            Granularity::Turn => {}
+            Granularity::Exchange => {
+                if let [.., question, answer] = recent.as_slice() {
+                    if question.role == "user" && answer.role == "assistant" {
+                        chunks.push((*granularity, vec![question.clone(), answer.clone()]));
+                    }
+                }
+            }
+            Granularity::Window { size, stride } => {
+                let stride = stride.max(1);
+                if size > 0
+                    && session.turns_seen >= size
+                    && (session.turns_seen - size).is_multiple_of(stride)
+                    && recent.len() >= size
+                {
+                    chunks.push((*granularity, recent[recent.len() - size..].to_vec()));
+                }
+            }
+        }
+    }
+    chunks
+}
+
+fn chunk_text(turns: &[BufferedTurn]) -> String {
+    turns
+        .iter()
+        .map(|turn| format!("{}: {}", turn.role, turn.text))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_completed_chunks() {
+        let granularities = [
+            Granularity::Turn,
+            Granularity::Exchange,
+            Granularity::window(4),
+        ];
+        let mut session = Session::default();
+        let mut written = Vec::new();
+        for (idx, role) in [
+            "user",
+            "assistant",
+            "user",
+            "assistant",
+            "user",
+            "assistant",
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            session.recent.push(BufferedTurn {
+                id: idx.to_string(),
+                role: role.to_string(),
+                text: format!("turn {idx}"),
+            });
+            session.turns_seen += 1;
+            for (granularity, turns) in completed_chunks(&session, &granularities) {
+                let ids: Vec<String> = turns.iter().map(|turn| turn.id.clone()).collect();
+                written.push(format!("{}:{}", granularity.kind(), ids.join(",")));
+            }
+        }
+        assert_eq!(
+            written,
+            vec![
+                "exchange:0,1",
+                "exchange:2,3",
+                "window:0,1,2,3",
+                "exchange:4,5",
+                "window:2,3,4,5",
+            ]
+        );
+        assert_eq!(
+            chunk_text(&session.recent[..2]),
+            "user: turn 0\nassistant: turn 1"
+        );
+    }
+}
@@ -0,0 +1,216 @@
+use crate::memory::memory_store::{Memory, MemoryError, MemoryStore};
+use crate::memory::stats::NAMESPACE_FIELD;
+use crate::memory::summarize::{summarize_memories, SummarizeOptions, Summarizer};
+use crate::vectorstore::filter::FilterBuilder;
+use chrono::{DateTime, Duration, Utc};
+use qdrant_client::qdrant::Filter;
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
+
+// Namespace of memories without one
+const DEFAULT_NAMESPACE: &str = "default";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestPeriod {
+    Day,
+    Week,
+}
+
+impl DigestPeriod {
+    pub fn duration(&self) -> Duration {
+        match self {
+            Self::Day => Duration::days(1),
+            Self::Week => Duration::weeks(1),
+        }
+    }
+}
+
+/// What was added to one namespace of a store during one period.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Digest {
+    pub namespace: String,
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    /// Memories added during the period.
+    pub memories: usize,
+    pub summary: String,
+}
+
+type DigestCallback = Box<dyn Fn(&Digest)>;
+
+/// Job that summarizes the memories added to a store during the last day or week, one
+/// digest per `namespace`, so users can audit what their agent has been learning.
+/// Digests go to the [`on_digest`](Self::on_digest) callbacks and, as JSON, to the
+/// [`with_webhook`](Self::with_webhook) URL. Namespaces without new memories get none.
+pub struct DigestJob<S: Summarizer> {
+    summarizer: S,
+    period: DigestPeriod,
+    options: SummarizeOptions,
+    callbacks: Vec<DigestCallback>,
+    webhook: Option<String>,
+    http: Client,
+}
+
+impl<S: Summarizer> DigestJob<S> {
+    /// `summarizer` condenses both the memories and partial digests of large periods,
+    /// e.g. [`LlmSummarizer::digest`](crate::memory::summarize::LlmSummarizer::digest).
+    pub fn new(summarizer: S, period: DigestPeriod) -> Self {
+        Self {
+            summarizer,
+            period,
+            options: SummarizeOptions::default(),
+            callbacks: Vec::new(),
+            webhook: None,
+            http: Client::new(),
+        }
+    }
+
+    pub fn with_summarize_options(mut self, options: SummarizeOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn on_digest(mut self, callback: impl Fn(&Digest) + 'static) -> Self {
+        self.callbacks.push(Box::new(callback));
+        self
+    }
+
+    /// POSTs every digest as JSON to `url`. Delivery is best effort: failed requests are
+    /// logged and dropped.
+    pub fn with_webhook(mut self, url: impl Into<String>) -> Self {
+        self.webhook = Some(url.into());
+        self
+    }
+
+    /// Digests of the period ending at `until`, by namespace, also emitted.
+    pub async fn digest(
+        &self,
+        store: &MemoryStore,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<Digest>, MemoryError> {
+        let since = until - self.period.duration();
+        let timestamp = &store.vectorstore().payload_fields().timestamp;
+        let filter = FilterBuilder::new()
+            .datetime_range(timestamp.as_str(), Some(since), Some(until))
+            .build();
+        let memories = store.memories_matching(Filter::from(&filter)).await?;
+
+        let mut digests = Vec::new();
+        for (namespace, mut memories) in by_namespace(memories) {
+            // Oldest first, so the summary reads in the order things were learned
+            memories.sort_by(|a, b| timestamp_of(a, timestamp).cmp(timestamp_of(b, timestamp)));
+            let summary =
+                summarize_memories(&memories, &self.summarizer, &self.summarizer, self.options)
+                    .await?;
+            let digest = Digest {
+                namespace,
+                since,
+                until,
+                memories: memories.len(),
+                summary,
+            };
+            self.emit(&digest).await;
+            digests.push(digest);
+        }
+        Ok(digests)
+    }
+
+    /// Digests the last period every period, forever, starting now. Failed runs are
+    /// logged and retried on the next tick.
+    pub async fn run(&self, store: &MemoryStore) {
+        let period = self
+            .period
+            .duration()
+            .to_std()
+            .expect("digest periods are positive");
+        let mut ticker = tokio::time::interval(period);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = self.digest(store, Utc::now()).await {
+                log::error!("memory digest failed: {e}");
+            }
+        }
+    }
+
+    async fn emit(&self, digest: &Digest) {
+        for callback in &self.callbacks {
+            callback(digest);
+        }
+        let Some(url) = &self.webhook else {
+            return;
+        };
+        let result = self
+            .http
+            .post(url)
+            .json(digest)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        if let Err(e) = result {
+            log::warn!("Digest delivery to {url} failed: {e}");
+        }
+    }
+}
+
+fn timestamp_of<'a>(memory: &'a Memory, field: &str) -> &'a str {
+    memory
+        .metadata
+        .get(field)
+        .and_then(JsonValue::as_str)
+        .unwrap_or_default()
+}
+
+fn by_namespace(memories: Vec<Memory>) -> BTreeMap<String, Vec<Memory>> {
+    let mut namespaces: BTreeMap<String, Vec<Memory>> = BTreeMap::new();
+    for memory in memories {
+        let namespace = memory
+            .metadata
+            .get(NAMESPACE_FIELD)
+            .and_then(JsonValue::as_str)
+            .unwrap_or(DEFAULT_NAMESPACE)
+            .to_string();
+        namespaces.entry(namespace).or_default().push(memory);
+    }
+    namespaces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Map};
+
+    fn memory(id: &str, namespace: Option<&str>) -> Memory {
+        let mut metadata = Map::new();
+        if let Some(namespace) = namespace {
+            metadata.insert(NAMESPACE_FIELD.to_string(), json!(namespace));
+        }
+        Memory {
+            id: id.to_string(),
+            text: id.to_string(),
+            metadata,
+            score: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_by_namespace() {
+        let namespaces = by_namespace(vec![
+            memory("a", Some("alice")),
+            memory("b", None),
+            memory("c", Some("alice")),
+        ]);
+        let ids: Vec<(&str, Vec<&str>)> = namespaces
+            .iter()
+            .map(|(namespace, memories)| {
+                (
+                    namespace.as_str(),
+                    memories.iter().map(|m| m.id.as_str()).collect(),
+                )
+            })
+            .collect();
+        assert_eq!(ids, vec![("alice", vec!["a", "c"]), ("default", vec!["b"])]);
+        assert_eq!(DigestPeriod::Week.duration(), Duration::days(7));
+    }
+}
@@ -0,0 +1,175 @@
+use crate::memory::memory_store::{Memory, MemoryError, MemoryStore};
+use crate::memory::retriever::Retriever;
+use crate::vectorstore::filter::MetadataFilter;
+use crate::vectorstore::qdrant_client::SOURCE_FIELD;
+use std::collections::HashMap;
+
+/// Caps how many recalled memories may share a source, so one long document or one
+/// image's many descriptions do not fill the whole result. A client-side alternative to
+/// a group-by query for backends without one: more memories are recalled than needed
+/// and the surplus of each source is dropped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceDiversity {
+    /// Most memories kept per source.
+    pub max_per_source: usize,
+    /// Payload fields identifying the source, the first one present wins. Memories
+    /// without any of them are never capped.
+    pub fields: Vec<String>,
+    /// Memories recalled per requested one, to have some left after the cap.
+    pub overfetch: u64,
+}
+
+impl SourceDiversity {
+    /// At most `max_per_source` memories per `source` or, without one, per `image_path`.
+    pub fn new(max_per_source: usize) -> Self {
+        Self {
+            max_per_source: max_per_source.max(1),
+            fields: vec![SOURCE_FIELD.to_string(), "image_path".to_string()],
+            overfetch: 3,
+        }
+    }
+
+    pub fn with_fields(mut self, fields: &[&str]) -> Self {
+        self.fields = fields.iter().map(|field| field.to_string()).collect();
+        self
+    }
+
+    pub fn with_overfetch(mut self, overfetch: u64) -> Self {
+        self.overfetch = overfetch.max(1);
+        self
+    }
+
+    fn source<'a>(&self, memory: &'a Memory) -> Option<&'a str> {
+        self.fields
+            .iter()
+            .find_map(|field| memory.metadata.get(field)?.as_str())
+    }
+
+    /// The first `limit` memories, in order, with at most `max_per_source` per source.
+    pub fn apply(&self, memories: Vec<Memory>, limit: usize) -> Vec<Memory> {
+        let mut per_source: HashMap<String, usize> = HashMap::new();
+        memories
+            .into_iter()
+            .filter(|memory| match self.source(memory) {
+                Some(source) => {
+                    let count = per_source.entry(source.to_string()).or_default();
+                    *count += 1;
+                    *count <= self.max_per_source
+                }
+                None => true,
+            })
+            .take(limit)
+            .collect()
+    }
+}
+
+impl MemoryStore {
+    /// Like [`MemoryStore::recall`], with at most `diversity.max_per_source` memories per
+    /// source. Fewer than `limit` are returned when the overfetched ones run out.
+    pub async fn recall_diverse(
+        &self,
+        query: &str,
+        limit: u64,
+        diversity: &SourceDiversity,
+    ) -> Result<Vec<Memory>, MemoryError> {
+        let memories = self
+            .recall(query, limit.saturating_mul(diversity.overfetch))
+            .await?;
+        Ok(diversity.apply(memories, limit as usize))
+    }
+}
+
+/// [`Retriever`] applying a [`SourceDiversity`] cap to another retriever's results;
+/// [`retrieve_with`](Self::retrieve_with) takes another cap for one query.
+pub struct DiverseRetriever<R: Retriever> {
+    retriever: R,
+    diversity: SourceDiversity,
+}
+
+impl<R: Retriever> DiverseRetriever<R> {
+    pub fn new(retriever: R, diversity: SourceDiversity) -> Self {
+        Self {
+            retriever,
+            diversity,
+        }
+    }
+
+    pub fn retriever(&self) -> &R {
+        &self.retriever
+    }
+
+    pub async fn retrieve_with(
+        &self,
+        query: &str,
+        limit: u64,
+        filter: Option<&MetadataFilter>,
+        diversity: &SourceDiversity,
+    ) -> Result<Vec<Memory>, R::Error> {
+        let memories = self
+            .retriever
+            .retrieve(query, limit.saturating_mul(diversity.overfetch), filter)
+            .await?;
+        Ok(diversity.apply(memories, limit as usize))
+    }
+}
+
+impl<R: Retriever> Retriever for DiverseRetriever<R> {
+    type Error = R::Error;
+
+    async fn retrieve(
+        &self,
+        query: &str,
+        limit: u64,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<Memory>, R::Error> {
+        self.retrieve_with(query, limit, filter, &self.diversity)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Map};
+
+    fn memory(id: &str, field: &str, source: &str) -> Memory {
+        let mut metadata = Map::new();
+        if !field.is_empty() {
+            metadata.insert(field.to_string(), json!(source));
+        }
+        Memory {
+            id: id.to_string(),
+            text: id.to_string(),
+            metadata,
+            score: 0.5,
+        }
+    }
+
+    #[test]
+    fn test_apply() {
+        let memories = vec![
+            memory("a", "source", "manual.pdf"),
+            memory("b", "source", "manual.pdf"),
+            memory("c", "source", "manual.pdf"),
+            memory("d", "image_path", "boot.png"),
+            memory("e", "", ""),
+            memory("f", "image_path", "boot.png"),
+            memory("g", "source", "faq.md"),
+        ];
+        let ids = |memories: Vec<Memory>| memories.into_iter().map(|m| m.id).collect::<Vec<_>>();
+        assert_eq!(
+            ids(SourceDiversity::new(2).apply(memories.clone(), 10)),
+            vec!["a", "b", "d", "e", "f", "g"]
+        );
+        assert_eq!(
+            ids(SourceDiversity::new(1).apply(memories.clone(), 3)),
+            vec!["a", "d", "e"]
+        );
+        assert_eq!(
+            ids(SourceDiversity::new(1)
+                .with_fields(&["image_path"])
+                .apply(memories, 4)),
+            vec!["a", "b", "c", "d"]
+        );
+    }
+}
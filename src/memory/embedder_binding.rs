@@ -0,0 +1,72 @@
+use crate::embeddings::text_embedding_inference::TextEmbeddingInference;
+use crate::memory::memory_store::MemoryError;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+use std::collections::HashMap;
+use std::fmt;
+
+const PROVIDER_KEY: &str = "embedding_provider";
+const MODEL_KEY: &str = "embedding_model";
+
+/// The embedding provider and model a collection was written with. Vectors from
+/// different models live in unrelated spaces, so mixing them returns silently wrong results.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmbedderBinding {
+    pub provider: String,
+    pub model: String,
+}
+
+impl fmt::Display for EmbedderBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.provider, self.model)
+    }
+}
+
+impl EmbedderBinding {
+    pub fn new(provider: impl Into<String>, model: impl Into<String>) -> Self {
+        Self {
+            provider: provider.into(),
+            model: model.into(),
+        }
+    }
+
+    /// Binding of the model served by a TEI instance, as reported by its `/info` endpoint.
+    pub async fn from_tei(embedder: &TextEmbeddingInference) -> Result<Self, MemoryError> {
+        let info = embedder
+            .info()
+            .await
+            .map_err(|e| MemoryError::EmbeddingError(e.to_string()))?;
+        Ok(Self::new("tei", info.model_id))
+    }
+
+    /// Collection metadata recording this binding.
+    pub fn to_metadata(&self) -> HashMap<String, JsonValue> {
+        HashMap::from([
+            (PROVIDER_KEY.to_string(), json!(self.provider)),
+            (MODEL_KEY.to_string(), json!(self.model)),
+        ])
+    }
+
+    pub fn from_metadata(metadata: &HashMap<String, JsonValue>) -> Option<Self> {
+        Some(Self::new(
+            metadata.get(PROVIDER_KEY)?.as_str()?,
+            metadata.get(MODEL_KEY)?.as_str()?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata_round_trip() {
+        let binding = EmbedderBinding::new("tei", "BAAI/bge-small-en-v1.5");
+        assert_eq!(
+            EmbedderBinding::from_metadata(&binding.to_metadata()),
+            Some(binding.clone())
+        );
+        assert_eq!(binding.to_string(), "tei/BAAI/bge-small-en-v1.5");
+        assert_eq!(EmbedderBinding::from_metadata(&HashMap::new()), None);
+    }
+}
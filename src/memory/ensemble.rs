@@ -0,0 +1,207 @@
+use crate::embeddings::text_embedding_inference::TextEmbeddingInference;
+use crate::memory::memory_store::{Memory, MemoryError};
+use crate::memory::retriever::Retriever;
+use crate::vectorstore::filter::MetadataFilter;
+use crate::vectorstore::interop::{self, PayloadConvention};
+use crate::vectorstore::qdrant_client::QdrantClient;
+use futures::future::try_join_all;
+use qdrant_client::qdrant::Filter;
+use std::collections::HashMap;
+
+// Rank offset of reciprocal rank fusion, as in the original paper
+const DEFAULT_RRF_K: f32 = 60.0;
+
+/// One embedding model and where its vectors are stored.
+pub struct EnsembleMember {
+    embedder: TextEmbeddingInference,
+    collection_name: String,
+    /// Named vector to search, `None` for collections with a single unnamed vector.
+    vector_name: Option<String>,
+    weight: f32,
+}
+
+/// [`Retriever`] that embeds the query with several models, searches the collection or
+/// named vector of each, and fuses the rankings with weighted reciprocal rank fusion.
+/// Useful while migrating between embedding models, or to combine a general and a
+/// domain-tuned model. The returned scores are the fused scores.
+pub struct EnsembleRetriever {
+    client: QdrantClient,
+    members: Vec<EnsembleMember>,
+    rrf_k: f32,
+}
+
+impl EnsembleRetriever {
+    pub fn new(client: QdrantClient) -> Self {
+        Self {
+            client,
+            members: Vec::new(),
+            rrf_k: DEFAULT_RRF_K,
+        }
+    }
+
+    pub fn with_member(
+        self,
+        embedder: TextEmbeddingInference,
+        collection_name: impl Into<String>,
+        vector_name: Option<&str>,
+    ) -> Self {
+        self.with_weighted_member(embedder, collection_name, vector_name, 1.0)
+    }
+
+    pub fn with_weighted_member(
+        mut self,
+        embedder: TextEmbeddingInference,
+        collection_name: impl Into<String>,
+        vector_name: Option<&str>,
+        weight: f32,
+    ) -> Self {
+        self.members.push(EnsembleMember {
+            embedder,
+            collection_name: collection_name.into(),
+            vector_name: vector_name.map(str::to_string),
+            weight,
+        });
+        self
+    }
+
+    /// Rank offset `k` in `weight / (k + rank)`. Higher values flatten the rankings.
+    pub fn with_rrf_k(mut self, rrf_k: f32) -> Self {
+        self.rrf_k = rrf_k;
+        self
+    }
+
+    async fn search(
+        &self,
+        member: &EnsembleMember,
+        query: &str,
+        limit: u64,
+        filter: Option<Filter>,
+    ) -> Result<Vec<Memory>, MemoryError> {
+        let embedding = member
+            .embedder
+            .embed(vec![query.to_string()])
+            .await
+            .map_err(|e| MemoryError::EmbeddingError(e.to_string()))?
+            .pop()
+            .ok_or_else(|| MemoryError::EmbeddingError("empty embedding response".to_string()))?;
+
+        let documents = match &member.vector_name {
+            Some(vector_name) => self
+                .client
+                .query_points_named_filtered(
+                    &member.collection_name,
+                    embedding,
+                    limit,
+                    vector_name,
+                    filter.unwrap_or_default(),
+                )
+                .await?
+                .result
+                .into_iter()
+                .filter_map(|point| {
+                    let score = point.score;
+                    interop::from_scored_point(
+                        point,
+                        PayloadConvention::LiquidMemory,
+                        self.client.payload_fields(),
+                    )
+                    .map(|document| (document, score))
+                })
+                .collect(),
+            None => {
+                self.client
+                    .query_documents_filtered(
+                        &member.collection_name,
+                        embedding,
+                        limit,
+                        PayloadConvention::LiquidMemory,
+                        filter,
+                    )
+                    .await?
+            }
+        };
+        Ok(documents
+            .into_iter()
+            .map(|(document, score)| Memory::from_document(document, score))
+            .collect())
+    }
+}
+
+/// Weighted reciprocal rank fusion of several rankings, best first. Memories are matched
+/// by id; the first occurrence is kept.
+fn fuse(rankings: Vec<(f32, Vec<Memory>)>, rrf_k: f32, limit: usize) -> Vec<Memory> {
+    let mut fused: HashMap<String, Memory> = HashMap::new();
+    for (weight, memories) in rankings {
+        for (rank, memory) in memories.into_iter().enumerate() {
+            let score = weight / (rrf_k + rank as f32 + 1.0);
+            fused
+                .entry(memory.id.clone())
+                .and_modify(|existing| existing.score += score)
+                .or_insert(Memory { score, ..memory });
+        }
+    }
+
+    let mut fused: Vec<Memory> = fused.into_values().collect();
+    fused.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.id.cmp(&b.id)));
+    fused.truncate(limit);
+    fused
+}
+
+impl Retriever for EnsembleRetriever {
+    type Error = MemoryError;
+
+    async fn retrieve(
+        &self,
+        query: &str,
+        limit: u64,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<Memory>, MemoryError> {
+        let filter = filter.map(Filter::from);
+        let rankings = try_join_all(self.members.iter().map(|member| async {
+            let memories = self.search(member, query, limit, filter.clone()).await?;
+            Ok::<_, MemoryError>((member.weight, memories))
+        }))
+        .await?;
+        Ok(fuse(rankings, self.rrf_k, limit as usize))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Map;
+
+    fn memories(ids: &[&str]) -> Vec<Memory> {
+        ids.iter()
+            .map(|id| Memory {
+                id: id.to_string(),
+                text: id.to_string(),
+                metadata: Map::new(),
+                score: 0.5,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fuse() {
+        let fused = fuse(
+            vec![
+                (1.0, memories(&["a", "b", "c"])),
+                (1.0, memories(&["b", "d"])),
+            ],
+            DEFAULT_RRF_K,
+            3,
+        );
+        let ids: Vec<&str> = fused.iter().map(|memory| memory.id.as_str()).collect();
+        assert_eq!(ids, vec!["b", "a", "d"]);
+        assert!((fused[0].score - (1.0 / 62.0 + 1.0 / 61.0)).abs() < 1e-6);
+
+        // A heavier member wins ties
+        let fused = fuse(
+            vec![(1.0, memories(&["a"])), (2.0, memories(&["b"]))],
+            DEFAULT_RRF_K,
+            2,
+        );
+        assert_eq!(fused[0].id, "b");
+    }
+}
@@ -0,0 +1,330 @@
+use crate::llm::llm_client::LlmClientChat;
+use crate::memory::grounding::check_grounding;
+use crate::memory::memory_store::{Memory, MemoryError};
+use crate::vectorstore::caption_validation::parse_score;
+use futures::future::LocalBoxFuture;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+const RELEVANCE_PROMPT: &str = "On a scale of 1 to 10, how well does the answer below \
+address the question? Answer with the number only.";
+
+/// A generated answer with the question and the context it was generated from.
+#[derive(Debug, Clone, Serialize)]
+pub struct RagSample {
+    pub question: String,
+    pub answer: String,
+    pub context: Vec<Memory>,
+    /// Model that generated the answer.
+    pub model: String,
+}
+
+/// Scores a generated answer from 0 (worst) to 1 (best).
+#[allow(async_fn_in_trait)]
+pub trait AnswerEvaluator {
+    fn name(&self) -> &str;
+
+    async fn evaluate(&self, sample: &RagSample) -> Result<f32, MemoryError>;
+}
+
+// Object-safe view of an evaluator, so the worker is not generic over each of them
+trait DynAnswerEvaluator {
+    fn name_dyn(&self) -> &str;
+
+    fn evaluate_dyn<'a>(
+        &'a self,
+        sample: &'a RagSample,
+    ) -> LocalBoxFuture<'a, Result<f32, MemoryError>>;
+}
+
+impl<T: AnswerEvaluator> DynAnswerEvaluator for T {
+    fn name_dyn(&self) -> &str {
+        self.name()
+    }
+
+    fn evaluate_dyn<'a>(
+        &'a self,
+        sample: &'a RagSample,
+    ) -> LocalBoxFuture<'a, Result<f32, MemoryError>> {
+        Box::pin(self.evaluate(sample))
+    }
+}
+
+/// Share of the answer's sentences the context supports, judged by an LLM with
+/// [`check_grounding`].
+pub struct GroundednessEvaluator<C: LlmClientChat> {
+    llm_client: C,
+    model: String,
+}
+
+impl<C: LlmClientChat> GroundednessEvaluator<C> {
+    pub fn new(llm_client: C, model: impl Into<String>) -> Self {
+        Self {
+            llm_client,
+            model: model.into(),
+        }
+    }
+}
+
+impl<C: LlmClientChat> AnswerEvaluator for GroundednessEvaluator<C> {
+    fn name(&self) -> &str {
+        "groundedness"
+    }
+
+    async fn evaluate(&self, sample: &RagSample) -> Result<f32, MemoryError> {
+        let report = check_grounding(
+            &self.llm_client,
+            &self.model,
+            &sample.context,
+            &sample.answer,
+        )
+        .await?;
+        if report.sentences.is_empty() {
+            return Ok(1.0);
+        }
+        let supported = report.sentences.iter().filter(|v| v.supported).count();
+        Ok(supported as f32 / report.sentences.len() as f32)
+    }
+}
+
+/// How well the answer addresses the question, rated 1 to 10 by an LLM and divided by 10.
+pub struct RelevanceEvaluator<C: LlmClientChat> {
+    llm_client: C,
+    model: String,
+}
+
+impl<C: LlmClientChat> RelevanceEvaluator<C> {
+    pub fn new(llm_client: C, model: impl Into<String>) -> Self {
+        Self {
+            llm_client,
+            model: model.into(),
+        }
+    }
+}
+
+impl<C: LlmClientChat> AnswerEvaluator for RelevanceEvaluator<C> {
+    fn name(&self) -> &str {
+        "relevance"
+    }
+
+    async fn evaluate(&self, sample: &RagSample) -> Result<f32, MemoryError> {
+        let response = self
+            .llm_client
+            .send_message(
+                &self.model,
+                format!(
+                    "{RELEVANCE_PROMPT}\n\nQuestion: {}\nAnswer: {}",
+                    sample.question, sample.answer
+                ),
+                None::<&str>,
+                Some(0.0),
+            )
+            .await
+            .map_err(|e| MemoryError::LlmError(e.to_string()))?;
+        let rating = parse_score(&response).ok_or_else(|| {
+            MemoryError::LlmError(format!("unparsable relevance response: {response}"))
+        })?;
+        Ok(rating as f32 / 10.0)
+    }
+}
+
+/// One evaluator's score for one sampled answer.
+#[derive(Debug, Clone, Serialize)]
+pub struct EvaluationScore {
+    pub evaluator: String,
+    pub score: f32,
+    pub question: String,
+}
+
+/// Running scores of one evaluator.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct EvaluatorStats {
+    pub evaluated: u64,
+    pub failed: u64,
+    pub mean: f32,
+    pub min: f32,
+}
+
+impl EvaluatorStats {
+    fn record(&mut self, score: f32) {
+        self.min = if self.evaluated == 0 {
+            score
+        } else {
+            self.min.min(score)
+        };
+        self.evaluated += 1;
+        self.mean += (score - self.mean) / self.evaluated as f32;
+    }
+}
+
+type StatsByEvaluator = Arc<Mutex<BTreeMap<String, EvaluatorStats>>>;
+
+/// Creates the two ends of online evaluation: the [`EvaluationSampler`] given to
+/// answering types (see [`QaMemory::with_evaluation`](super::qa_memory::QaMemory::with_evaluation)
+/// and [`MemoryAugmentedChat::with_evaluation`](super::augmented_chat::MemoryAugmentedChat::with_evaluation))
+/// and the [`EvaluationWorker`] scoring what it samples, off the answer path.
+/// `sample_rate` is the share of answers evaluated, at most `capacity` of them waiting.
+pub fn evaluation(sample_rate: f64, capacity: usize) -> (EvaluationSampler, EvaluationWorker) {
+    let (sender, receiver) = mpsc::channel(capacity.max(1));
+    let stats = StatsByEvaluator::default();
+    let sampler = EvaluationSampler {
+        sender,
+        sample_rate: sample_rate.clamp(0.0, 1.0),
+        seen: Arc::new(AtomicU64::new(0)),
+        stats: stats.clone(),
+    };
+    let worker = EvaluationWorker {
+        receiver,
+        evaluators: Vec::new(),
+        on_score: None,
+        stats,
+    };
+    (sampler, worker)
+}
+
+/// Hands a share of the answers to an [`EvaluationWorker`]. Never waits: samples are
+/// dropped while the worker is `capacity` behind.
+#[derive(Clone)]
+pub struct EvaluationSampler {
+    sender: mpsc::Sender<RagSample>,
+    sample_rate: f64,
+    seen: Arc<AtomicU64>,
+    stats: StatsByEvaluator,
+}
+
+impl EvaluationSampler {
+    // Spreads samples evenly: answer n is sampled when it moves floor(n * rate) up
+    fn should_sample(&self) -> bool {
+        let seen = self.seen.fetch_add(1, Ordering::Relaxed);
+        ((seen + 1) as f64 * self.sample_rate).floor() > (seen as f64 * self.sample_rate).floor()
+    }
+
+    /// Queues the answer for evaluation if it is sampled.
+    pub fn offer(&self, sample: impl FnOnce() -> RagSample) {
+        if !self.should_sample() {
+            return;
+        }
+        if self.sender.try_send(sample()).is_err() {
+            log::warn!("Evaluation queue full or closed, answer not evaluated");
+        }
+    }
+
+    /// Scores so far, by evaluator.
+    pub fn stats(&self) -> BTreeMap<String, EvaluatorStats> {
+        self.stats.lock().unwrap().clone()
+    }
+}
+
+type ScoreCallback = Box<dyn Fn(&EvaluationScore)>;
+
+/// Runs the evaluators on the answers sampled by its [`EvaluationSampler`]. Drive it with
+/// [`run`](Self::run) next to the answering code, e.g. in `tokio::join!` or on a
+/// `tokio::task::LocalSet`.
+pub struct EvaluationWorker {
+    receiver: mpsc::Receiver<RagSample>,
+    evaluators: Vec<Box<dyn DynAnswerEvaluator>>,
+    on_score: Option<ScoreCallback>,
+    stats: StatsByEvaluator,
+}
+
+impl EvaluationWorker {
+    pub fn with_evaluator(mut self, evaluator: impl AnswerEvaluator + 'static) -> Self {
+        self.evaluators.push(Box::new(evaluator));
+        self
+    }
+
+    /// Called with every score, e.g. to export it as a metric.
+    pub fn on_score(mut self, callback: impl Fn(&EvaluationScore) + 'static) -> Self {
+        self.on_score = Some(Box::new(callback));
+        self
+    }
+
+    /// Evaluates samples until every sampler is dropped. Failed evaluations are logged
+    /// and counted.
+    pub async fn run(&mut self) {
+        while let Some(sample) = self.receiver.recv().await {
+            self.evaluate(&sample).await;
+        }
+    }
+
+    /// Scores so far, by evaluator.
+    pub fn stats(&self) -> BTreeMap<String, EvaluatorStats> {
+        self.stats.lock().unwrap().clone()
+    }
+
+    async fn evaluate(&self, sample: &RagSample) {
+        for evaluator in &self.evaluators {
+            let name = evaluator.name_dyn();
+            let result = evaluator.evaluate_dyn(sample).await;
+            let mut stats = self.stats.lock().unwrap();
+            let stats = stats.entry(name.to_string()).or_default();
+            match result {
+                Ok(score) => {
+                    stats.record(score);
+                    if let Some(callback) = &self.on_score {
+                        callback(&EvaluationScore {
+                            evaluator: name.to_string(),
+                            score,
+                            question: sample.question.clone(),
+                        });
+                    }
+                }
+                Err(e) => {
+                    stats.failed += 1;
+                    log::warn!("Evaluator {name} failed: {e}");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct AnswerLength;
+
+    impl AnswerEvaluator for AnswerLength {
+        fn name(&self) -> &str {
+            "length"
+        }
+
+        async fn evaluate(&self, sample: &RagSample) -> Result<f32, MemoryError> {
+            Ok(sample.answer.len() as f32 / 10.0)
+        }
+    }
+
+    fn sample(answer: &str) -> RagSample {
+        RagSample {
+            question: "Can I return boots?".to_string(),
+            answer: answer.to_string(),
+            context: Vec::new(),
+            model: "gpt-4o".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sampled_answers_are_scored() {
+        let (sampler, worker) = evaluation(0.5, 16);
+        let scores = Arc::new(Mutex::new(Vec::new()));
+        let recorded = scores.clone();
+        let mut worker = worker
+            .with_evaluator(AnswerLength)
+            .on_score(move |score| recorded.lock().unwrap().push(score.score));
+
+        for answer in ["a", "bb", "ccc", "dddd", "eeeee", "ffffff"] {
+            sampler.offer(|| sample(answer));
+        }
+        drop(sampler);
+        worker.run().await;
+
+        assert_eq!(*scores.lock().unwrap(), vec![0.2, 0.4, 0.6]);
+        let stats = &worker.stats()["length"];
+        assert_eq!(stats.evaluated, 3);
+        assert!((stats.mean - 0.4).abs() < 1e-6);
+        assert_eq!(stats.min, 0.2);
+    }
+}
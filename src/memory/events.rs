@@ -0,0 +1,135 @@
+use crate::memory::memory_store::MemoryStore;
+use futures::stream::{self, Stream};
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::{Map, Value as JsonValue};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+
+// Slow subscribers lag (and skip events) instead of blocking writes
+pub const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum MemoryEvent {
+    Remembered {
+        collection: String,
+        ids: Vec<String>,
+        metadata: Map<String, JsonValue>,
+    },
+    Forgotten {
+        collection: String,
+        ids: Vec<String>,
+    },
+}
+
+pub fn channel() -> broadcast::Sender<MemoryEvent> {
+    broadcast::channel(EVENT_CHANNEL_CAPACITY).0
+}
+
+/// POSTs every event received on `events` as JSON to `url` until the channel closes.
+/// Delivery is best effort: failed requests are logged and dropped.
+pub fn spawn_webhook(
+    mut events: broadcast::Receiver<MemoryEvent>,
+    url: impl Into<String>,
+) -> JoinHandle<()> {
+    let url = url.into();
+    let client = Client::new();
+    tokio::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(event) => {
+                    let result = client
+                        .post(&url)
+                        .json(&event)
+                        .send()
+                        .await
+                        .and_then(|response| response.error_for_status());
+                    if let Err(e) = result {
+                        log::warn!("Webhook delivery to {url} failed: {e}");
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    log::warn!("Webhook to {url} lagging, skipped {skipped} events");
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+impl MemoryEvent {
+    pub fn collection(&self) -> &str {
+        match self {
+            Self::Remembered { collection, .. } | Self::Forgotten { collection, .. } => collection,
+        }
+    }
+}
+
+/// Events of `events` for `collection`, ending when the channel closes. Events skipped
+/// by a lagging receiver are logged.
+pub fn watch(
+    events: broadcast::Receiver<MemoryEvent>,
+    collection: impl Into<String>,
+) -> impl Stream<Item = MemoryEvent> {
+    let collection = collection.into();
+    stream::unfold(events, move |mut events| {
+        let collection = collection.clone();
+        async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) if event.collection() == collection => return Some((event, events)),
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(skipped)) => {
+                        log::warn!("Watch of {collection} lagging, skipped {skipped} events");
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        }
+    })
+}
+
+impl MemoryStore {
+    /// Stream of remember/forget events for `collection` sent by this store, pushed as
+    /// writes succeed. Writes by other clients are only seen by
+    /// [`QdrantClient::watch`](crate::vectorstore::qdrant_client::QdrantClient::watch).
+    pub fn watch(&self, collection: impl Into<String>) -> impl Stream<Item = MemoryEvent> {
+        watch(self.subscribe(), collection)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_serialization() {
+        let event = MemoryEvent::Forgotten {
+            collection: "memories".to_string(),
+            ids: vec!["1".to_string()],
+        };
+        let value = serde_json::to_value(event).unwrap();
+        assert_eq!(value["event"], "forgotten");
+        assert_eq!(value["ids"][0], "1");
+    }
+
+    #[tokio::test]
+    async fn test_watch() {
+        use futures::StreamExt;
+
+        let events = channel();
+        let mut watched = Box::pin(watch(events.subscribe(), "memories"));
+        for collection in ["other", "memories"] {
+            events
+                .send(MemoryEvent::Forgotten {
+                    collection: collection.to_string(),
+                    ids: vec!["1".to_string()],
+                })
+                .unwrap();
+        }
+        drop(events);
+        assert_eq!(watched.next().await.unwrap().collection(), "memories");
+        assert!(watched.next().await.is_none());
+    }
+}
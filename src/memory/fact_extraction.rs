@@ -0,0 +1,259 @@
+use crate::llm::llm_client::LlmClientChat;
+use crate::memory::memory_store::{Memory, MemoryError, MemoryStore};
+use crate::memory::provenance::DERIVED_FROM_FIELD;
+use qdrant_client::qdrant::{Condition, Filter};
+use serde::Deserialize;
+use serde_json::{json, Map, Value as JsonValue};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+const TURN_KIND: &str = "turn";
+
+// Set on turns once they have been through extraction
+const EXTRACTED_FIELD: &str = "facts_extracted";
+
+const EXTRACTION_PROMPT: &str = "Below are numbered turns of a conversation. Extract durable \
+facts and preferences about the user or the world that are worth remembering in future \
+conversations; skip small talk, questions and anything only relevant right now. Use short \
+snake_case predicates (e.g. lives_in, prefers). Answer with a single JSON object and nothing \
+else, in the form {\"facts\": [{\"subject\": \"user\", \"predicate\": \"lives_in\", \
+\"text\": \"The user lives in Porto\", \"turns\": [1]}]}, with an empty list if there is \
+nothing to remember.\n\n";
+
+/// A conversation turn stored with [`MemoryStore::remember_turn`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Turn {
+    pub id: String,
+    pub session_id: String,
+    pub role: String,
+    pub text: String,
+    pub timestamp: String,
+}
+
+impl Turn {
+    pub fn from_memory(memory: Memory) -> Option<Self> {
+        let metadata = &memory.metadata;
+        if metadata.get("kind")?.as_str()? != TURN_KIND {
+            return None;
+        }
+        Some(Self {
+            session_id: metadata.get("session_id")?.as_str()?.to_string(),
+            role: metadata.get("role")?.as_str()?.to_string(),
+            timestamp: metadata.get("timestamp")?.as_str()?.to_string(),
+            id: memory.id,
+            text: memory.text,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ExtractedFact {
+    subject: String,
+    predicate: String,
+    text: String,
+    #[serde(default)]
+    turns: Vec<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExtractionResponse {
+    facts: Vec<ExtractedFact>,
+}
+
+fn extraction_prompt(turns: &[Turn]) -> String {
+    let mut prompt = EXTRACTION_PROMPT.to_string();
+    for (idx, turn) in turns.iter().enumerate() {
+        prompt.push_str(&format!("[{}] {}: {}\n", idx + 1, turn.role, turn.text));
+    }
+    prompt
+}
+
+fn parse_extraction(response: &str) -> Option<Vec<ExtractedFact>> {
+    let start = response.find('{')?;
+    let end = response.rfind('}')?;
+    let response: ExtractionResponse = serde_json::from_str(response.get(start..=end)?).ok()?;
+    Some(response.facts)
+}
+
+/// Ids of the turns a fact was extracted from, all turns when the model cited none.
+fn source_turns(fact: &ExtractedFact, turns: &[Turn]) -> Vec<String> {
+    let cited: Vec<String> = fact
+        .turns
+        .iter()
+        .filter_map(|number| turns.get(number.checked_sub(1)?))
+        .map(|turn| turn.id.clone())
+        .collect();
+    if cited.is_empty() {
+        return turns.iter().map(|turn| turn.id.clone()).collect();
+    }
+    cited
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExtractionReport {
+    pub turns_scanned: usize,
+    pub facts_written: usize,
+}
+
+impl MemoryStore {
+    /// Stores a conversation turn for later fact extraction.
+    pub async fn remember_turn(
+        &self,
+        session_id: &str,
+        role: &str,
+        text: &str,
+    ) -> Result<String, MemoryError> {
+        let mut metadata = Map::new();
+        metadata.insert("kind".to_string(), json!(TURN_KIND));
+        metadata.insert("session_id".to_string(), json!(session_id));
+        metadata.insert("role".to_string(), json!(role));
+        self.remember(text, Some(metadata)).await
+    }
+
+    /// Turns that have not been through fact extraction yet.
+    pub async fn unextracted_turns(&self) -> Result<Vec<Turn>, MemoryError> {
+        let filter = Filter {
+            must: vec![Condition::matches("kind", TURN_KIND.to_string())],
+            must_not: vec![Condition::matches(EXTRACTED_FIELD, true)],
+            ..Default::default()
+        };
+        Ok(self
+            .memories_matching(filter)
+            .await?
+            .into_iter()
+            .filter_map(Turn::from_memory)
+            .collect())
+    }
+}
+
+/// Job that pulls durable facts and preferences out of new conversation turns with an
+/// LLM and writes them as facts (see [`MemoryStore::remember_fact`]) with provenance:
+/// `derived_from` holds the ids of the turns a fact came from and `source_session` the
+/// session. Processed turns are marked, so each turn is extracted once even across
+/// restarts.
+pub struct FactExtractor<C: LlmClientChat> {
+    llm_client: C,
+    model: String,
+}
+
+impl<C: LlmClientChat> FactExtractor<C> {
+    pub fn new(llm_client: C, model: impl Into<String>) -> Self {
+        Self {
+            llm_client,
+            model: model.into(),
+        }
+    }
+
+    /// Extracts facts from the unprocessed turns in `turns`, one LLM call per session,
+    /// and writes them to `facts`.
+    pub async fn extract(
+        &self,
+        turns: &MemoryStore,
+        facts: &MemoryStore,
+    ) -> Result<ExtractionReport, MemoryError> {
+        let mut sessions: BTreeMap<String, Vec<Turn>> = BTreeMap::new();
+        for turn in turns.unextracted_turns().await? {
+            sessions
+                .entry(turn.session_id.clone())
+                .or_default()
+                .push(turn);
+        }
+
+        let mut report = ExtractionReport::default();
+        for (session_id, mut session_turns) in sessions {
+            session_turns.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+            let response = self
+                .llm_client
+                .send_message(
+                    &self.model,
+                    extraction_prompt(&session_turns),
+                    None::<&str>,
+                    Some(0.0),
+                )
+                .await
+                .map_err(|e| MemoryError::LlmError(e.to_string()))?;
+            let extracted = parse_extraction(&response).ok_or_else(|| {
+                MemoryError::LlmError(format!("unparsable fact extraction response: {response}"))
+            })?;
+
+            for fact in &extracted {
+                let mut metadata = Map::new();
+                metadata.insert(
+                    DERIVED_FROM_FIELD.to_string(),
+                    json!(source_turns(fact, &session_turns)),
+                );
+                metadata.insert("source_session".to_string(), json!(session_id));
+                facts
+                    .remember_fact(
+                        &fact.subject,
+                        &fact.predicate,
+                        &fact.text,
+                        None,
+                        Some(metadata),
+                    )
+                    .await?;
+            }
+
+            // Marked after the facts are written, so a failure retries the whole session
+            let mut fields = Map::new();
+            fields.insert(EXTRACTED_FIELD.to_string(), JsonValue::Bool(true));
+            turns
+                .set_metadata(
+                    session_turns.iter().map(|turn| turn.id.clone()).collect(),
+                    fields,
+                )
+                .await?;
+
+            report.turns_scanned += session_turns.len();
+            report.facts_written += extracted.len();
+        }
+        Ok(report)
+    }
+
+    /// Runs [`FactExtractor::extract`] every `interval`, forever. Failed runs are
+    /// logged and retried on the next tick.
+    pub async fn run(&self, turns: &MemoryStore, facts: &MemoryStore, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = self.extract(turns, facts).await {
+                log::error!("fact extraction failed: {e}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turn(id: &str, role: &str, text: &str) -> Turn {
+        Turn {
+            id: id.to_string(),
+            session_id: "s1".to_string(),
+            role: role.to_string(),
+            text: text.to_string(),
+            timestamp: "2025-01-01T00:00:00Z".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_extraction() {
+        let turns = vec![
+            turn("a", "user", "I just moved to Porto!"),
+            turn("b", "assistant", "Congratulations!"),
+        ];
+        assert!(extraction_prompt(&turns).ends_with("[2] assistant: Congratulations!\n"));
+
+        let response = r#"Sure: {"facts": [
+            {"subject": "user", "predicate": "lives_in", "text": "The user lives in Porto", "turns": [1]},
+            {"subject": "user", "predicate": "mood", "text": "The user is happy", "turns": [7]}
+        ]}"#;
+        let facts = parse_extraction(response).unwrap();
+        assert_eq!(facts.len(), 2);
+        assert_eq!(facts[0].predicate, "lives_in");
+        assert_eq!(source_turns(&facts[0], &turns), vec!["a"]);
+        assert_eq!(source_turns(&facts[1], &turns), vec!["a", "b"]);
+        assert!(parse_extraction("no facts").is_none());
+    }
+}
@@ -0,0 +1,264 @@
+use crate::memory::memory_store::{Memory, MemoryError, MemoryStore};
+use chrono::{DateTime, Utc};
+use qdrant_client::qdrant::{Condition, Filter};
+use serde::Serialize;
+use serde_json::{json, Map, Value as JsonValue};
+
+const FACT_KIND: &str = "fact";
+
+// Point-in-time recall filters validity client-side, so it fetches this many candidates
+// per requested fact
+const HISTORY_OVERFETCH: u64 = 4;
+
+/// A statement about `subject` that holds from `valid_from` until `valid_until`
+/// (open-ended when `None`). Facts are never overwritten: a newer fact for the same
+/// subject and predicate closes the validity interval of the current one.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Fact {
+    pub id: String,
+    pub subject: String,
+    pub predicate: String,
+    pub text: String,
+    pub valid_from: DateTime<Utc>,
+    pub valid_until: Option<DateTime<Utc>>,
+    pub score: f32,
+}
+
+fn parse_time(value: Option<&JsonValue>) -> Option<DateTime<Utc>> {
+    let value = value?.as_str()?;
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+impl Fact {
+    pub fn is_valid_at(&self, at: DateTime<Utc>) -> bool {
+        self.valid_from <= at && self.valid_until.is_none_or(|until| at < until)
+    }
+
+    /// Reads a fact back from a memory written by [`MemoryStore::remember_fact`].
+    pub fn from_memory(memory: Memory) -> Option<Self> {
+        let metadata = &memory.metadata;
+        if metadata.get("kind")?.as_str()? != FACT_KIND {
+            return None;
+        }
+        Some(Self {
+            subject: metadata.get("subject")?.as_str()?.to_string(),
+            predicate: metadata.get("predicate")?.as_str()?.to_string(),
+            valid_from: parse_time(metadata.get("valid_from"))?,
+            valid_until: parse_time(metadata.get("valid_until")),
+            id: memory.id,
+            text: memory.text,
+            score: memory.score,
+        })
+    }
+}
+
+fn fact_filter(subject: &str, predicate: &str) -> Filter {
+    Filter::must([
+        Condition::matches("kind", FACT_KIND.to_string()),
+        Condition::matches("subject", subject.to_string()),
+        Condition::matches("predicate", predicate.to_string()),
+    ])
+}
+
+// The facts a fact valid from `valid_from` goes between in `history` (oldest first): the
+// one it supersedes and the one that supersedes it, if any
+fn neighbours(history: &[Fact], valid_from: DateTime<Utc>) -> (Option<&Fact>, Option<&Fact>) {
+    let at = history.partition_point(|fact| fact.valid_from < valid_from);
+    let previous = at.checked_sub(1).map(|i| &history[i]);
+    let next = history[at..]
+        .iter()
+        .find(|fact| fact.valid_from > valid_from);
+    (previous, next)
+}
+
+impl MemoryStore {
+    /// Stores a fact valid from `valid_from` (now by default). Versions are ordered by
+    /// `valid_from`, not by when they were stored: the version valid just before the new
+    /// one gets `valid_until` set to the new `valid_from` and a `superseded_by` pointer,
+    /// and a backdated fact ends where the next version starts. Superseded facts are
+    /// kept as history.
+    pub async fn remember_fact(
+        &self,
+        subject: &str,
+        predicate: &str,
+        text: &str,
+        valid_from: Option<DateTime<Utc>>,
+        metadata: Option<Map<String, JsonValue>>,
+    ) -> Result<String, MemoryError> {
+        let valid_from = valid_from.unwrap_or_else(Utc::now);
+
+        // History is read before the write, so the new fact is not part of it
+        let history = self.fact_history(subject, predicate).await?;
+        let (previous, next) = neighbours(&history, valid_from);
+
+        let mut metadata = metadata.unwrap_or_default();
+        metadata.insert("kind".to_string(), json!(FACT_KIND));
+        metadata.insert("subject".to_string(), json!(subject));
+        metadata.insert("predicate".to_string(), json!(predicate));
+        metadata.insert("valid_from".to_string(), json!(valid_from.to_rfc3339()));
+        if let Some(next) = next {
+            metadata.insert(
+                "valid_until".to_string(),
+                json!(next.valid_from.to_rfc3339()),
+            );
+            metadata.insert("superseded_by".to_string(), json!(next.id));
+        }
+        // The new fact only shows up together with the end of the one it supersedes
+        let mut transaction = self.begin();
+        let id = transaction.remember(text, Some(metadata)).await?;
+        if let Some(previous) = previous {
+            let mut fields = Map::new();
+            fields.insert("valid_until".to_string(), json!(valid_from.to_rfc3339()));
+            fields.insert("superseded_by".to_string(), json!(id));
+            transaction.set_metadata(vec![previous.id.clone()], fields);
+        }
+        transaction.commit().await?;
+        Ok(id)
+    }
+
+    /// Returns the `limit` facts most similar to `query` that are valid at `at`, or
+    /// currently valid when `at` is `None`.
+    pub async fn recall_facts(
+        &self,
+        query: &str,
+        limit: u64,
+        at: Option<DateTime<Utc>>,
+    ) -> Result<Vec<Fact>, MemoryError> {
+        let mut conditions = vec![Condition::matches("kind", FACT_KIND.to_string())];
+        let fetch = match at {
+            None => {
+                conditions.push(Condition::is_empty("valid_until"));
+                limit
+            }
+            Some(_) => limit * HISTORY_OVERFETCH,
+        };
+        let at = at.unwrap_or_else(Utc::now);
+
+        let memories = self
+            .recall_filtered(query, fetch, Filter::must(conditions))
+            .await?;
+        Ok(memories
+            .into_iter()
+            .filter_map(Fact::from_memory)
+            .filter(|fact| fact.is_valid_at(at))
+            .take(limit as usize)
+            .collect())
+    }
+
+    /// Facts about `subject` that are currently valid, in no particular order.
+    pub async fn current_facts(&self, subject: &str) -> Result<Vec<Fact>, MemoryError> {
+        let filter = Filter::must([
+            Condition::matches("kind", FACT_KIND.to_string()),
+            Condition::matches("subject", subject.to_string()),
+            Condition::is_empty("valid_until"),
+        ]);
+        Ok(self
+            .memories_matching(filter)
+            .await?
+            .into_iter()
+            .filter_map(Fact::from_memory)
+            .collect())
+    }
+
+    /// Every version of a fact, oldest first.
+    pub async fn fact_history(
+        &self,
+        subject: &str,
+        predicate: &str,
+    ) -> Result<Vec<Fact>, MemoryError> {
+        let mut facts: Vec<Fact> = self
+            .memories_matching(fact_filter(subject, predicate))
+            .await?
+            .into_iter()
+            .filter_map(Fact::from_memory)
+            .collect();
+        facts.sort_by_key(|fact| fact.valid_from);
+        Ok(facts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory(valid_from: &str, valid_until: Option<&str>) -> Memory {
+        let mut metadata = Map::new();
+        metadata.insert("kind".to_string(), json!("fact"));
+        metadata.insert("subject".to_string(), json!("user"));
+        metadata.insert("predicate".to_string(), json!("lives_in"));
+        metadata.insert("valid_from".to_string(), json!(valid_from));
+        if let Some(valid_until) = valid_until {
+            metadata.insert("valid_until".to_string(), json!(valid_until));
+        }
+        Memory {
+            id: "1".to_string(),
+            text: "User lives in Porto".to_string(),
+            metadata,
+            score: 0.9,
+        }
+    }
+
+    fn time(value: &str) -> DateTime<Utc> {
+        parse_time(Some(&json!(value))).unwrap()
+    }
+
+    #[test]
+    fn test_fact_from_memory() {
+        let fact = Fact::from_memory(memory(
+            "2024-01-01T00:00:00Z",
+            Some("2025-06-01T00:00:00+00:00"),
+        ))
+        .unwrap();
+        assert_eq!(fact.predicate, "lives_in");
+        assert_eq!(fact.valid_until, Some(time("2025-06-01T00:00:00Z")));
+
+        let mut other = memory("2024-01-01T00:00:00Z", None);
+        other.metadata.remove("kind");
+        assert!(Fact::from_memory(other).is_none());
+    }
+
+    #[test]
+    fn test_is_valid_at() {
+        let closed =
+            Fact::from_memory(memory("2024-01-01T00:00:00Z", Some("2025-06-01T00:00:00Z")))
+                .unwrap();
+        assert!(!closed.is_valid_at(time("2023-12-31T00:00:00Z")));
+        assert!(closed.is_valid_at(time("2024-01-01T00:00:00Z")));
+        assert!(!closed.is_valid_at(time("2025-06-01T00:00:00Z")));
+
+        let open = Fact::from_memory(memory("2025-06-01T00:00:00Z", None)).unwrap();
+        assert!(open.is_valid_at(time("2030-01-01T00:00:00Z")));
+    }
+
+    #[test]
+    fn test_neighbours_in_time_order() {
+        let fact = |id: &str, valid_from: &str, valid_until: Option<&str>| Fact {
+            id: id.to_string(),
+            ..Fact::from_memory(memory(valid_from, valid_until)).unwrap()
+        };
+        let history = vec![
+            fact(
+                "porto",
+                "2020-01-01T00:00:00Z",
+                Some("2024-01-01T00:00:00Z"),
+            ),
+            fact("lisbon", "2024-01-01T00:00:00Z", None),
+        ];
+
+        let (previous, next) = neighbours(&history, time("2025-01-01T00:00:00Z"));
+        assert_eq!(previous.map(|f| f.id.as_str()), Some("lisbon"));
+        assert!(next.is_none());
+
+        // Backdated between the two: ends Porto and ends itself where Lisbon starts
+        let (previous, next) = neighbours(&history, time("2022-01-01T00:00:00Z"));
+        assert_eq!(previous.map(|f| f.id.as_str()), Some("porto"));
+        assert_eq!(next.map(|f| f.id.as_str()), Some("lisbon"));
+
+        // Backdated before all of them
+        let (previous, next) = neighbours(&history, time("2019-01-01T00:00:00Z"));
+        assert!(previous.is_none());
+        assert_eq!(next.map(|f| f.id.as_str()), Some("porto"));
+    }
+}
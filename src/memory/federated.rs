@@ -0,0 +1,169 @@
+use crate::memory::memory_store::Memory;
+use crate::memory::retriever::{DynRetriever, Retriever};
+use crate::vectorstore::filter::MetadataFilter;
+use futures::future::join_all;
+use serde_json::json;
+use thiserror::Error;
+
+/// Metadata field naming the source a federated result came from.
+pub const SOURCE_FIELD: &str = "federated_source";
+
+#[derive(Debug, Error)]
+#[error("Source {source_name} failed: {message}")]
+pub struct FederatedError {
+    pub source_name: String,
+    pub message: String,
+}
+
+struct Source {
+    name: String,
+    retriever: Box<dyn DynRetriever>,
+    quota: u64,
+    weight: f32,
+}
+
+/// Min-max normalizes the scores to [0, 1], so sources with different score scales can be
+/// merged. A single result, or results with equal scores, get 1.
+fn normalize(memories: &mut [Memory]) {
+    let (min, max) = memories
+        .iter()
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), memory| {
+            (min.min(memory.score), max.max(memory.score))
+        });
+    for memory in memories {
+        memory.score = if max > min {
+            (memory.score - min) / (max - min)
+        } else {
+            1.0
+        };
+    }
+}
+
+/// [`Retriever`] over several sources, e.g. collections on different backends, queried
+/// concurrently. Scores are normalized per source and weighted before merging, and each
+/// source contributes at most its quota of results. Results carry their source name in
+/// the `federated_source` metadata field.
+#[derive(Default)]
+pub struct FederatedRetriever {
+    sources: Vec<Source>,
+}
+
+impl FederatedRetriever {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a source contributing at most `quota` results, with weight 1.
+    pub fn with_source(
+        self,
+        name: impl Into<String>,
+        retriever: impl Retriever + 'static,
+        quota: u64,
+    ) -> Self {
+        self.with_weighted_source(name, retriever, quota, 1.0)
+    }
+
+    /// Adds a source whose normalized scores are multiplied by `weight`.
+    pub fn with_weighted_source(
+        mut self,
+        name: impl Into<String>,
+        retriever: impl Retriever + 'static,
+        quota: u64,
+        weight: f32,
+    ) -> Self {
+        self.sources.push(Source {
+            name: name.into(),
+            retriever: Box::new(retriever),
+            quota,
+            weight,
+        });
+        self
+    }
+}
+
+impl Retriever for FederatedRetriever {
+    type Error = FederatedError;
+
+    async fn retrieve(
+        &self,
+        query: &str,
+        limit: u64,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<Memory>, FederatedError> {
+        let responses = join_all(self.sources.iter().map(|source| {
+            source
+                .retriever
+                .retrieve_dyn(query, source.quota.min(limit), filter)
+        }))
+        .await;
+
+        let mut merged = Vec::new();
+        for (source, response) in self.sources.iter().zip(responses) {
+            let mut memories = response.map_err(|message| FederatedError {
+                source_name: source.name.clone(),
+                message,
+            })?;
+            normalize(&mut memories);
+            for mut memory in memories {
+                memory.score *= source.weight;
+                memory
+                    .metadata
+                    .insert(SOURCE_FIELD.to_string(), json!(source.name));
+                merged.push(memory);
+            }
+        }
+
+        merged.sort_by(|a, b| b.score.total_cmp(&a.score));
+        merged.truncate(limit as usize);
+        Ok(merged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Map;
+    use std::convert::Infallible;
+
+    struct Fixed(Vec<(&'static str, f32)>);
+
+    impl Retriever for Fixed {
+        type Error = Infallible;
+
+        async fn retrieve(
+            &self,
+            _query: &str,
+            limit: u64,
+            _filter: Option<&MetadataFilter>,
+        ) -> Result<Vec<Memory>, Infallible> {
+            Ok(self
+                .0
+                .iter()
+                .take(limit as usize)
+                .map(|(id, score)| Memory {
+                    id: id.to_string(),
+                    text: String::new(),
+                    metadata: Map::new(),
+                    score: *score,
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_federated_retriever() {
+        let retriever = FederatedRetriever::new()
+            .with_source(
+                "wiki",
+                Fixed(vec![("w1", 0.9), ("w2", 0.8), ("w3", 0.7)]),
+                2,
+            )
+            .with_weighted_source("tickets", Fixed(vec![("t1", 12.0), ("t2", 2.0)]), 5, 0.9);
+
+        let memories = retriever.retrieve("boots", 4, None).await.unwrap();
+        let ids: Vec<&str> = memories.iter().map(|memory| memory.id.as_str()).collect();
+        assert_eq!(ids, vec!["w1", "t1", "w2", "t2"]);
+        assert_eq!(memories[1].metadata[SOURCE_FIELD], "tickets");
+        assert!((memories[1].score - 0.9).abs() < 1e-6);
+    }
+}
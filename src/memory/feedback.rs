@@ -0,0 +1,136 @@
+use crate::memory::memory_store::{Memory, MemoryError, MemoryStore};
+use qdrant_client::qdrant::{Condition, Filter};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value as JsonValue};
+
+const FEEDBACK_UP_FIELD: &str = "feedback_up";
+const FEEDBACK_DOWN_FIELD: &str = "feedback_down";
+const FEEDBACK_LOG_FIELD: &str = "feedback_log";
+
+// Recent feedback kept per memory, oldest entries are dropped first
+const MAX_FEEDBACK_LOG: usize = 20;
+
+const RERANK_OVERFETCH: u64 = 4;
+
+/// Relevance feedback on a recalled memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeedbackSignal {
+    Up,
+    Down,
+}
+
+fn count(memory: &Memory, field: &str) -> u64 {
+    memory
+        .metadata
+        .get(field)
+        .and_then(JsonValue::as_u64)
+        .unwrap_or(0)
+}
+
+/// Net feedback of a memory in [-1, 1], 0 without feedback. Smoothed so that a single
+/// vote does not count as much as many.
+pub fn feedback_score(memory: &Memory) -> f32 {
+    let up = count(memory, FEEDBACK_UP_FIELD) as f32;
+    let down = count(memory, FEEDBACK_DOWN_FIELD) as f32;
+    (up - down) / (up + down + 1.0)
+}
+
+fn feedback_fields(memory: &Memory, query: &str, signal: FeedbackSignal) -> Map<String, JsonValue> {
+    let field = match signal {
+        FeedbackSignal::Up => FEEDBACK_UP_FIELD,
+        FeedbackSignal::Down => FEEDBACK_DOWN_FIELD,
+    };
+
+    let mut log = memory
+        .metadata
+        .get(FEEDBACK_LOG_FIELD)
+        .and_then(JsonValue::as_array)
+        .cloned()
+        .unwrap_or_default();
+    log.push(json!({
+        "query": query,
+        "signal": signal,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+    }));
+    let excess = log.len().saturating_sub(MAX_FEEDBACK_LOG);
+    log.drain(..excess);
+
+    let mut fields = Map::new();
+    fields.insert(field.to_string(), json!(count(memory, field) + 1));
+    fields.insert(FEEDBACK_LOG_FIELD.to_string(), JsonValue::Array(log));
+    fields
+}
+
+impl MemoryStore {
+    /// Records that the memory `point_id` was (`Up`) or was not (`Down`) relevant to `query`.
+    /// Counts are stored in the `feedback_up`/`feedback_down` payload fields, recent
+    /// feedback in `feedback_log`. Concurrent feedback on the same memory may lose votes.
+    pub async fn feedback(
+        &self,
+        query: &str,
+        point_id: &str,
+        signal: FeedbackSignal,
+    ) -> Result<(), MemoryError> {
+        let filter = Filter::must([Condition::has_id([point_id.to_string()])]);
+        let memory = self
+            .memories_matching(filter)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| MemoryError::NotFound(point_id.to_string()))?;
+
+        self.set_metadata(
+            vec![point_id.to_string()],
+            feedback_fields(&memory, query, signal),
+        )
+        .await
+    }
+
+    /// Like [`MemoryStore::recall`], with each similarity boosted or demoted by
+    /// `weight * feedback_score`. The returned scores are the adjusted scores.
+    pub async fn recall_with_feedback(
+        &self,
+        query: &str,
+        limit: u64,
+        weight: f32,
+    ) -> Result<Vec<Memory>, MemoryError> {
+        let mut memories = self.recall(query, limit * RERANK_OVERFETCH).await?;
+        for memory in &mut memories {
+            memory.score += weight * feedback_score(memory);
+        }
+        memories.sort_by(|a, b| b.score.total_cmp(&a.score));
+        memories.truncate(limit as usize);
+        Ok(memories)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feedback_fields() {
+        let mut memory = Memory {
+            id: "1".to_string(),
+            text: "The user prefers ankle boots".to_string(),
+            metadata: Map::new(),
+            score: 0.8,
+        };
+        assert_eq!(feedback_score(&memory), 0.0);
+
+        for _ in 0..MAX_FEEDBACK_LOG + 1 {
+            let fields = feedback_fields(&memory, "shoe preferences", FeedbackSignal::Up);
+            memory.metadata.extend(fields);
+        }
+        let fields = feedback_fields(&memory, "sandals", FeedbackSignal::Down);
+        memory.metadata.extend(fields);
+
+        assert_eq!(count(&memory, FEEDBACK_UP_FIELD), 21);
+        assert_eq!(count(&memory, FEEDBACK_DOWN_FIELD), 1);
+        let log = memory.metadata[FEEDBACK_LOG_FIELD].as_array().unwrap();
+        assert_eq!(log.len(), MAX_FEEDBACK_LOG);
+        assert_eq!(log[MAX_FEEDBACK_LOG - 1]["signal"], "down");
+        assert!((feedback_score(&memory) - 20.0 / 23.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,272 @@
+use crate::llm::llm_client::LlmClientChat;
+use crate::memory::memory_store::{Memory, MemoryError};
+use serde::{Deserialize, Serialize};
+
+const GROUNDING_PROMPT: &str = "Below are a numbered context and the numbered sentences of \
+an answer generated from it. For each sentence, decide whether the context supports it; \
+sentences that only restate the question or say the context has no answer are supported. \
+Answer with a single JSON object and nothing else, in the form \
+{\"sentences\": [{\"sentence\": 1, \"supported\": true, \"context\": [2]}]}, citing the \
+context entries that support each sentence.\n\n";
+
+/// What [`GroundingCheck`] does with an answer that has unsupported sentences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GroundingAction {
+    /// Marks unsupported sentences in the answer.
+    #[default]
+    Annotate,
+    /// Asks for a new answer, naming the unsupported sentences, up to this many times,
+    /// then annotates whatever is still unsupported.
+    Regenerate(usize),
+}
+
+/// Sentence-level check that a generated answer is supported by the retrieved context,
+/// with an LLM as the judge.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroundingCheck {
+    /// Judge model, the answering model if `None`.
+    pub model: Option<String>,
+    pub action: GroundingAction,
+    /// Appended to unsupported sentences by [`GroundingAction::Annotate`].
+    pub marker: String,
+}
+
+impl Default for GroundingCheck {
+    fn default() -> Self {
+        Self {
+            model: None,
+            action: GroundingAction::Annotate,
+            marker: "[unsupported]".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SentenceVerdict {
+    pub sentence: String,
+    pub supported: bool,
+    /// Ids of the context memories cited as support.
+    pub support: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GroundingReport {
+    pub sentences: Vec<SentenceVerdict>,
+    /// Answers regenerated before this one.
+    pub regenerations: usize,
+}
+
+impl GroundingReport {
+    pub fn is_grounded(&self) -> bool {
+        self.sentences.iter().all(|verdict| verdict.supported)
+    }
+
+    pub fn unsupported(&self) -> impl Iterator<Item = &str> {
+        self.sentences
+            .iter()
+            .filter(|verdict| !verdict.supported)
+            .map(|verdict| verdict.sentence.as_str())
+    }
+}
+
+/// Splits an answer into sentences at `.`, `!` or `?` followed by whitespace, and at
+/// line breaks so list items are checked one by one.
+pub fn split_sentences(text: &str) -> Vec<String> {
+    let mut sentences = Vec::new();
+    for line in text.lines() {
+        let mut current = String::new();
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            current.push(c);
+            let ends = matches!(c, '.' | '!' | '?' | '。' | '！' | '？')
+                && chars.peek().is_none_or(|next| next.is_whitespace());
+            if ends && !current.trim().is_empty() {
+                sentences.push(current.trim().to_string());
+                current.clear();
+            }
+        }
+        if !current.trim().is_empty() {
+            sentences.push(current.trim().to_string());
+        }
+    }
+    sentences
+}
+
+fn grounding_prompt(context: &[Memory], sentences: &[String]) -> String {
+    let mut prompt = format!("{GROUNDING_PROMPT}Context:\n");
+    for (idx, memory) in context.iter().enumerate() {
+        prompt.push_str(&format!("[{}] {}\n", idx + 1, memory.text));
+    }
+    prompt.push_str("\nAnswer sentences:\n");
+    for (idx, sentence) in sentences.iter().enumerate() {
+        prompt.push_str(&format!("({}) {sentence}\n", idx + 1));
+    }
+    prompt
+}
+
+#[derive(Deserialize)]
+struct JudgedSentence {
+    sentence: usize,
+    supported: bool,
+    #[serde(default)]
+    context: Vec<usize>,
+}
+
+#[derive(Deserialize)]
+struct GroundingResponse {
+    sentences: Vec<JudgedSentence>,
+}
+
+/// Maps the judged sentence and context numbers back to sentences and memory ids.
+/// Sentences the judge skipped count as unsupported.
+fn parse_grounding(
+    response: &str,
+    context: &[Memory],
+    sentences: &[String],
+) -> Option<Vec<SentenceVerdict>> {
+    let start = response.find('{')?;
+    let end = response.rfind('}')?;
+    let response: GroundingResponse = serde_json::from_str(response.get(start..=end)?).ok()?;
+
+    let mut verdicts: Vec<SentenceVerdict> = sentences
+        .iter()
+        .map(|sentence| SentenceVerdict {
+            sentence: sentence.clone(),
+            supported: false,
+            support: Vec::new(),
+        })
+        .collect();
+    for judged in response.sentences {
+        let Some(verdict) = judged
+            .sentence
+            .checked_sub(1)
+            .and_then(|idx| verdicts.get_mut(idx))
+        else {
+            continue;
+        };
+        verdict.supported = judged.supported;
+        verdict.support = judged
+            .context
+            .iter()
+            .filter_map(|number| context.get(number.checked_sub(1)?))
+            .map(|memory| memory.id.clone())
+            .collect();
+    }
+    Some(verdicts)
+}
+
+/// Asks `model` which sentences of `answer` the context supports.
+pub async fn check_grounding<C: LlmClientChat>(
+    llm_client: &C,
+    model: &str,
+    context: &[Memory],
+    answer: &str,
+) -> Result<GroundingReport, MemoryError> {
+    let sentences = split_sentences(answer);
+    if sentences.is_empty() {
+        return Ok(GroundingReport {
+            sentences: Vec::new(),
+            regenerations: 0,
+        });
+    }
+    let response = llm_client
+        .send_message(
+            model,
+            grounding_prompt(context, &sentences),
+            None::<&str>,
+            Some(0.0),
+        )
+        .await
+        .map_err(|e| MemoryError::LlmError(e.to_string()))?;
+    let sentences = parse_grounding(&response, context, &sentences).ok_or_else(|| {
+        MemoryError::LlmError(format!("unparsable grounding check response: {response}"))
+    })?;
+    Ok(GroundingReport {
+        sentences,
+        regenerations: 0,
+    })
+}
+
+/// `answer` with `marker` after each unsupported sentence, keeping its line breaks.
+pub fn annotate(answer: &str, report: &GroundingReport, marker: &str) -> String {
+    let mut annotated = String::with_capacity(answer.len());
+    let mut rest = answer;
+    for verdict in &report.sentences {
+        let Some(start) = rest.find(&verdict.sentence) else {
+            continue;
+        };
+        let end = start + verdict.sentence.len();
+        annotated.push_str(&rest[..end]);
+        if !verdict.supported {
+            annotated.push(' ');
+            annotated.push_str(marker);
+        }
+        rest = &rest[end..];
+    }
+    annotated.push_str(rest);
+    annotated
+}
+
+/// Prompt asking for a new answer without the unsupported sentences of the last one.
+pub fn regeneration_prompt(prompt: &str, report: &GroundingReport) -> String {
+    let mut prompt = format!(
+        "{prompt}\n\nA previous answer made claims the context does not support. Answer \
+        again using only the context, without these claims:\n"
+    );
+    for sentence in report.unsupported() {
+        prompt.push_str(&format!("- {sentence}\n"));
+    }
+    prompt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Map;
+
+    fn memory(id: &str, text: &str) -> Memory {
+        Memory {
+            id: id.to_string(),
+            text: text.to_string(),
+            metadata: Map::new(),
+            score: 0.8,
+        }
+    }
+
+    #[test]
+    fn test_split_sentences() {
+        assert_eq!(
+            split_sentences("Returns are free. They take 3.5 days!\n- Boots\n- Sandals"),
+            vec![
+                "Returns are free.",
+                "They take 3.5 days!",
+                "- Boots",
+                "- Sandals"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_grounding_and_annotate() {
+        let context = vec![memory("a", "Returns are free within 30 days.")];
+        let answer = "Returns are free. Shipping is free too.\nAsk us.";
+        let sentences = split_sentences(answer);
+        let response = "```json\n{\"sentences\": [\
+            {\"sentence\": 1, \"supported\": true, \"context\": [1, 7]},\
+            {\"sentence\": 2, \"supported\": false}]}\n```";
+        let report = GroundingReport {
+            sentences: parse_grounding(response, &context, &sentences).unwrap(),
+            regenerations: 0,
+        };
+        assert_eq!(report.sentences[0].support, vec!["a".to_string()]);
+        assert!(!report.is_grounded());
+        // Skipped by the judge
+        assert!(!report.sentences[2].supported);
+        assert_eq!(
+            annotate(answer, &report, "[unsupported]"),
+            "Returns are free. Shipping is free too. [unsupported]\nAsk us. [unsupported]"
+        );
+        assert!(regeneration_prompt("Question", &report).ends_with("- Ask us.\n"));
+        assert!(parse_grounding("Looks fine.", &context, &sentences).is_none());
+    }
+}
@@ -0,0 +1,220 @@
+use crate::llm::llm_client::LlmClientChat;
+use crate::memory::memory_store::{Memory, MemoryError, MemoryStore};
+use crate::vectorstore::caption_validation::parse_score;
+use chrono::{DateTime, Utc};
+use qdrant_client::qdrant::Filter;
+use serde_json::{json, Map, Value as JsonValue};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+const IMPORTANCE_PROMPT: &str = "On a scale of 1 to 10, where 1 is purely mundane \
+(e.g. brushing teeth, making the bed) and 10 is extremely poignant (e.g. a break up, \
+a college acceptance), rate the likely poignancy of the following memory. \
+Answer with the number only.\n\nMemory: ";
+
+// Importance used for memories stored without a score
+const DEFAULT_IMPORTANCE: f32 = 5.0;
+
+// Weighted recall re-ranks this many candidates per requested memory
+const RERANK_OVERFETCH: u64 = 4;
+
+/// Rates how important a memory is, from 1 to 10.
+#[allow(async_fn_in_trait)]
+pub trait ImportanceScorer {
+    async fn score(&self, text: &str) -> Result<u8, MemoryError>;
+}
+
+/// Asks an LLM for the importance of a memory, as in the generative agents paper.
+/// Scores are cached by text, so re-remembering the same text does not call the LLM again.
+pub struct LlmImportanceScorer<C: LlmClientChat> {
+    llm_client: C,
+    model: String,
+    cache: Mutex<HashMap<String, u8>>,
+}
+
+impl<C: LlmClientChat> LlmImportanceScorer<C> {
+    pub fn new(llm_client: C, model: impl Into<String>) -> Self {
+        Self {
+            llm_client,
+            model: model.into(),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<C: LlmClientChat> ImportanceScorer for LlmImportanceScorer<C> {
+    async fn score(&self, text: &str) -> Result<u8, MemoryError> {
+        if let Some(score) = self.cache.lock().unwrap().get(text) {
+            return Ok(*score);
+        }
+
+        let response = self
+            .llm_client
+            .send_message(
+                &self.model,
+                format!("{IMPORTANCE_PROMPT}{text}"),
+                None::<&str>,
+                Some(0.0),
+            )
+            .await
+            .map_err(|e| MemoryError::LlmError(e.to_string()))?;
+        let score = parse_score(&response).ok_or_else(|| {
+            MemoryError::LlmError(format!("unparsable importance response: {response}"))
+        })?;
+
+        self.cache.lock().unwrap().insert(text.to_string(), score);
+        Ok(score)
+    }
+}
+
+/// Weights of the generative agents retrieval score:
+/// `relevance * similarity + importance * importance / 10 + recency * 0.5^(age / half_life)`.
+#[derive(Debug, Clone, Copy)]
+pub struct RecallWeights {
+    pub relevance: f32,
+    pub importance: f32,
+    pub recency: f32,
+    /// Age in hours at which the recency term is halved.
+    pub half_life_hours: f32,
+}
+
+impl Default for RecallWeights {
+    fn default() -> Self {
+        Self {
+            relevance: 1.0,
+            importance: 1.0,
+            recency: 1.0,
+            // 0.995 per hour, the decay factor used in the paper
+            half_life_hours: 138.3,
+        }
+    }
+}
+
+fn importance(memory: &Memory) -> f32 {
+    memory
+        .metadata
+        .get("importance")
+        .and_then(JsonValue::as_f64)
+        .map(|importance| importance as f32)
+        .unwrap_or(DEFAULT_IMPORTANCE)
+}
+
+fn age_hours(memory: &Memory, now: DateTime<Utc>) -> f32 {
+    memory
+        .metadata
+        .get("timestamp")
+        .and_then(JsonValue::as_str)
+        .and_then(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok())
+        .map(|timestamp| (now - timestamp.with_timezone(&Utc)).num_seconds().max(0) as f32 / 3600.0)
+        .unwrap_or(0.0)
+}
+
+impl RecallWeights {
+    pub fn recency(&self, memory: &Memory, now: DateTime<Utc>) -> f32 {
+        0.5_f32.powf(age_hours(memory, now) / self.half_life_hours)
+    }
+
+    /// Combined score of a recalled memory; `memory.score` is its similarity to the query.
+    pub fn score(&self, memory: &Memory, now: DateTime<Utc>) -> f32 {
+        self.relevance * memory.score
+            + self.importance * importance(memory) / 10.0
+            + self.recency * self.recency(memory, now)
+    }
+
+    /// Query-independent score used to decide which memories to evict.
+    pub fn retention(&self, memory: &Memory, now: DateTime<Utc>) -> f32 {
+        self.importance * importance(memory) / 10.0 + self.recency * self.recency(memory, now)
+    }
+}
+
+impl MemoryStore {
+    /// Like [`MemoryStore::remember`], with the importance given by `scorer` stored in the
+    /// `importance` payload field.
+    pub async fn remember_with_importance(
+        &self,
+        text: &str,
+        metadata: Option<Map<String, JsonValue>>,
+        scorer: &impl ImportanceScorer,
+    ) -> Result<String, MemoryError> {
+        let importance = scorer.score(text).await?;
+        let mut metadata = metadata.unwrap_or_default();
+        metadata.insert("importance".to_string(), json!(importance));
+        self.remember(text, Some(metadata)).await
+    }
+
+    /// Recalls memories ranked by relevance, importance and recency instead of similarity
+    /// alone. The returned scores are the combined scores.
+    pub async fn recall_weighted(
+        &self,
+        query: &str,
+        limit: u64,
+        weights: RecallWeights,
+    ) -> Result<Vec<Memory>, MemoryError> {
+        let now = Utc::now();
+        let mut memories = self.recall(query, limit * RERANK_OVERFETCH).await?;
+        for memory in &mut memories {
+            memory.score = weights.score(memory, now);
+        }
+        memories.sort_by(|a, b| b.score.total_cmp(&a.score));
+        memories.truncate(limit as usize);
+        Ok(memories)
+    }
+
+    /// Forgets the memories with the lowest importance/recency retention score until at
+    /// most `keep` remain. Returns the ids of the evicted memories.
+    pub async fn evict(
+        &self,
+        keep: usize,
+        weights: RecallWeights,
+    ) -> Result<Vec<String>, MemoryError> {
+        let now = Utc::now();
+        let mut memories = self.memories_matching(Filter::default()).await?;
+        if memories.len() <= keep {
+            return Ok(Vec::new());
+        }
+
+        memories.sort_by(|a, b| {
+            weights
+                .retention(b, now)
+                .total_cmp(&weights.retention(a, now))
+        });
+        let evicted: Vec<String> = memories.split_off(keep).into_iter().map(|m| m.id).collect();
+        self.forget(evicted.clone()).await?;
+        Ok(evicted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory(score: f32, importance: Option<u8>, age_hours: i64, now: DateTime<Utc>) -> Memory {
+        let mut metadata = Map::new();
+        if let Some(importance) = importance {
+            metadata.insert("importance".to_string(), json!(importance));
+        }
+        let timestamp = now - chrono::Duration::hours(age_hours);
+        metadata.insert("timestamp".to_string(), json!(timestamp.to_rfc3339()));
+        Memory {
+            id: String::new(),
+            text: String::new(),
+            metadata,
+            score,
+        }
+    }
+
+    #[test]
+    fn test_recall_weights() {
+        let now = Utc::now();
+        let weights = RecallWeights {
+            half_life_hours: 24.0,
+            ..Default::default()
+        };
+        let fresh = memory(0.5, Some(8), 0, now);
+        assert!((weights.score(&fresh, now) - 2.3).abs() < 1e-3);
+
+        let day_old = memory(0.5, None, 24, now);
+        assert!((weights.recency(&day_old, now) - 0.5).abs() < 1e-3);
+        assert!((weights.retention(&day_old, now) - 1.0).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,782 @@
+use crate::embeddings::text_embedding_inference::TextEmbeddingInference;
+use crate::memory::embedder_binding::EmbedderBinding;
+use crate::memory::events::{self, MemoryEvent};
+use crate::memory::ownership::{self, AgentIdentity, Visibility, WRITTEN_BY_FIELD};
+use crate::memory::recall_cache::RecallCache;
+use crate::memory::sensitivity::{self, Sensitivity};
+use crate::memory::shutdown::Lifecycle;
+use crate::memory::transaction;
+use crate::memory::write_policy::{DynWritePolicy, WritePolicy};
+use crate::metrics::ClientStats;
+use crate::request_id;
+use crate::vectorstore::interop::{self, Document, PayloadConvention};
+use crate::vectorstore::presets::CollectionPreset;
+use crate::vectorstore::qdrant_client::{point_id_to_string, QdrantClient, SOURCE_FIELD};
+use crate::vectorstore::vector_store::VectorStoreError;
+use qdrant_client::qdrant::{Condition, Distance, Filter, VectorParamsBuilder};
+use qdrant_client::{Payload, QdrantError};
+use serde::Serialize;
+use serde_json::{Map, Value as JsonValue};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::broadcast;
+
+#[derive(Debug, Error)]
+pub enum MemoryError {
+    #[error("Vector store Error: {0}")]
+    VectorStoreError(#[from] QdrantError),
+    #[error("Embedding Error: {0}")]
+    EmbeddingError(String),
+    #[error("LLM Error: {0}")]
+    LlmError(String),
+    #[error("Memory not found: {0}")]
+    NotFound(String),
+    #[error("Collection {collection} is bound to embedder {expected}, not {actual}")]
+    EmbedderMismatch {
+        collection: String,
+        expected: EmbedderBinding,
+        actual: EmbedderBinding,
+    },
+    #[error("Write vetoed: {0}")]
+    WriteVetoed(String),
+    #[error("Store of agent {agent} cannot write memories of {written_by}")]
+    AgentMismatch { agent: String, written_by: String },
+    #[error("Store is shutting down")]
+    ShuttingDown,
+    #[error("Transaction {transaction} was only partly committed: {message}")]
+    PartialCommit {
+        transaction: String,
+        message: String,
+    },
+}
+
+impl From<VectorStoreError> for MemoryError {
+    fn from(e: VectorStoreError) -> Self {
+        match e {
+            VectorStoreError::QdrantError(e) => MemoryError::VectorStoreError(e),
+            VectorStoreError::PointNotFound(id) => MemoryError::NotFound(id),
+            e => MemoryError::VectorStoreError(QdrantError::Io(std::io::Error::other(e))),
+        }
+    }
+}
+
+/// A recalled memory and its similarity to the query.
+#[derive(Debug, Clone, Serialize)]
+pub struct Memory {
+    pub id: String,
+    pub text: String,
+    pub metadata: Map<String, JsonValue>,
+    pub score: f32,
+}
+
+impl Memory {
+    pub(crate) fn from_document(document: Document, score: f32) -> Self {
+        Self {
+            id: document.node_id.unwrap_or_default(),
+            text: document.text,
+            metadata: document.metadata,
+            score,
+        }
+    }
+}
+
+/// Long-term text memory on top of a Qdrant collection and a TEI embedding server.
+pub struct MemoryStore {
+    vectorstore: QdrantClient,
+    embedder: TextEmbeddingInference,
+    collection_name: String,
+    events: broadcast::Sender<MemoryEvent>,
+    access_level: Sensitivity,
+    agent: Option<AgentIdentity>,
+    embedder_binding: Option<EmbedderBinding>,
+    // Known collection bindings, `None` for collections without one
+    collection_bindings: Mutex<HashMap<String, Option<EmbedderBinding>>>,
+    recall_cache: Option<RecallCache>,
+    // Times each memory was returned by a recall through this store, by id
+    access_counts: Mutex<HashMap<String, u64>>,
+    write_policy: Option<Box<dyn DynWritePolicy>>,
+    lifecycle: Lifecycle,
+}
+
+impl MemoryStore {
+    pub fn new(
+        vectorstore: QdrantClient,
+        embedder: TextEmbeddingInference,
+        collection_name: impl Into<String>,
+    ) -> Self {
+        Self {
+            vectorstore,
+            embedder,
+            collection_name: collection_name.into(),
+            events: events::channel(),
+            access_level: Sensitivity::Secret,
+            agent: None,
+            embedder_binding: None,
+            collection_bindings: Mutex::new(HashMap::new()),
+            recall_cache: None,
+            access_counts: Mutex::new(HashMap::new()),
+            write_policy: None,
+            lifecycle: Lifecycle::default(),
+        }
+    }
+
+    /// Caches recall results for `ttl`, keyed by query embedding. Writes through the
+    /// store's [`QdrantClient`], including ingestion run with [`vectorstore`](Self::vectorstore),
+    /// invalidate the cached results of the collection; writes through other clients or
+    /// processes are only picked up once entries expire.
+    pub fn with_recall_cache(mut self, ttl: Duration, max_entries: usize) -> Self {
+        self.recall_cache = Some(RecallCache::new(ttl, max_entries));
+        self
+    }
+
+    fn count_accesses(&self, memories: &[Memory]) {
+        let mut access_counts = self.access_counts.lock().unwrap();
+        for memory in memories {
+            *access_counts.entry(memory.id.clone()).or_default() += 1;
+        }
+    }
+
+    /// Client the store reads and writes through.
+    pub fn vectorstore(&self) -> &QdrantClient {
+        &self.vectorstore
+    }
+
+    /// Times each memory was recalled through this store since it was created.
+    pub(crate) fn access_counts(&self) -> HashMap<String, u64> {
+        self.access_counts.lock().unwrap().clone()
+    }
+
+    pub(crate) fn recall_cache(&self) -> Option<&RecallCache> {
+        self.recall_cache.as_ref()
+    }
+
+    pub(crate) fn lifecycle(&self) -> &Lifecycle {
+        &self.lifecycle
+    }
+
+    /// Declares which provider and model the store's embedder is. Collections created
+    /// by the store record it, and writes or queries against a collection bound to a
+    /// different embedder fail with [`MemoryError::EmbedderMismatch`].
+    pub fn with_embedder_binding(mut self, binding: EmbedderBinding) -> Self {
+        self.embedder_binding = Some(binding);
+        self
+    }
+
+    /// Binds a collection to an embedder explicitly, for collections whose binding is not
+    /// recorded in Qdrant (e.g. created by another tool).
+    pub fn bind_collection(&self, collection_name: impl Into<String>, binding: EmbedderBinding) {
+        self.collection_bindings
+            .lock()
+            .unwrap()
+            .insert(collection_name.into(), Some(binding));
+    }
+
+    async fn check_embedder_binding(&self, collection_name: &str) -> Result<(), MemoryError> {
+        let Some(actual) = &self.embedder_binding else {
+            return Ok(());
+        };
+
+        let cached = self
+            .collection_bindings
+            .lock()
+            .unwrap()
+            .get(collection_name)
+            .cloned();
+        let expected = match cached {
+            Some(expected) => expected,
+            None => {
+                if !self.vectorstore.check_collection(collection_name).await? {
+                    // Will be created with this store's binding
+                    return Ok(());
+                }
+                let metadata = self
+                    .vectorstore
+                    .collection_metadata(collection_name)
+                    .await?;
+                let expected = EmbedderBinding::from_metadata(&metadata);
+                self.collection_bindings
+                    .lock()
+                    .unwrap()
+                    .insert(collection_name.to_string(), expected.clone());
+                expected
+            }
+        };
+
+        match expected {
+            Some(expected) if expected != *actual => Err(MemoryError::EmbedderMismatch {
+                collection: collection_name.to_string(),
+                expected,
+                actual: actual.clone(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Sets the access level applied to every recall that does not pass its own, so
+    /// memories more sensitive than `access_level` are never returned. Defaults to
+    /// [`Sensitivity::Secret`], i.e. no restriction.
+    pub fn with_access_level(mut self, access_level: Sensitivity) -> Self {
+        self.access_level = access_level;
+        self
+    }
+
+    /// Acts on behalf of `agent`: memories written without an owner are recorded as its
+    /// own with [`Visibility::Team`], writes naming another owner are rejected, and every
+    /// recall that does not name another agent only returns its own memories, its team's
+    /// and global ones. Metadata changes and deletes only reach the agent's own memories.
+    /// Without an agent the store is unrestricted.
+    pub fn with_agent(mut self, agent: AgentIdentity) -> Self {
+        self.agent = Some(agent);
+        self
+    }
+
+    pub fn agent(&self) -> Option<&AgentIdentity> {
+        self.agent.as_ref()
+    }
+
+    // Records the store's agent as the owner of a memory written without one and rejects
+    // memories owned by another agent
+    pub(crate) fn claim(
+        &self,
+        mut metadata: Map<String, JsonValue>,
+    ) -> Result<Map<String, JsonValue>, MemoryError> {
+        let Some(agent) = &self.agent else {
+            return Ok(metadata);
+        };
+        match metadata.get(WRITTEN_BY_FIELD) {
+            None => agent.apply(&mut metadata, Visibility::default()),
+            Some(written_by) if written_by.as_str() == Some(agent.agent_id.as_str()) => {}
+            Some(written_by) => {
+                return Err(MemoryError::AgentMismatch {
+                    agent: agent.agent_id.clone(),
+                    written_by: written_by
+                        .as_str()
+                        .map_or_else(|| written_by.to_string(), str::to_string),
+                })
+            }
+        }
+        Ok(metadata)
+    }
+
+    // Those of `ids` the store's agent may change or delete: the memories it wrote. Without
+    // an agent, all of them
+    pub(crate) async fn owned_ids(&self, ids: Vec<String>) -> Result<Vec<String>, MemoryError> {
+        let Some(agent) = &self.agent else {
+            return Ok(ids);
+        };
+        if ids.is_empty() {
+            return Ok(ids);
+        }
+        let filter = Filter::must([
+            Condition::has_id(ids.clone()),
+            Condition::matches(WRITTEN_BY_FIELD, agent.agent_id.clone()),
+        ]);
+        let owned: HashSet<String> = self
+            .vectorstore
+            .scroll_all(&self.collection_name, Some(filter), false)
+            .await?
+            .into_iter()
+            .filter_map(|point| point.id.as_ref().map(point_id_to_string))
+            .collect();
+        Ok(ids.into_iter().filter(|id| owned.contains(id)).collect())
+    }
+
+    // `ids` if the store's agent may change all of them, else the first it may not
+    pub(crate) async fn require_owned(&self, ids: Vec<String>) -> Result<Vec<String>, MemoryError> {
+        let owned = self.owned_ids(ids.clone()).await?;
+        match ids.into_iter().find(|id| !owned.contains(id)) {
+            Some(id) => Err(MemoryError::NotFound(id)),
+            None => Ok(owned),
+        }
+    }
+
+    // Filter of a read: the caller's, restricted to what the agent and access level may
+    // see, without uncommitted transaction writes
+    fn read_filter(
+        &self,
+        filter: Option<Filter>,
+        access_level: Sensitivity,
+        agent: Option<&AgentIdentity>,
+    ) -> Option<Filter> {
+        transaction::hide_pending(sensitivity::restrict(
+            ownership::restrict(filter, agent),
+            access_level,
+        ))
+    }
+
+    /// Reviews every memory written through this store, transactions included, before it
+    /// is stored; see [`WritePolicy`].
+    pub fn with_write_policy(mut self, policy: impl WritePolicy + 'static) -> Self {
+        self.write_policy = Some(Box::new(policy));
+        self
+    }
+
+    pub(crate) fn write_policy(&self) -> Option<&dyn DynWritePolicy> {
+        self.write_policy.as_deref()
+    }
+
+    /// Subscribes to remember/forget events. Events are only sent after the write succeeded.
+    pub fn subscribe(&self) -> broadcast::Receiver<MemoryEvent> {
+        self.events.subscribe()
+    }
+
+    pub(crate) fn emit(&self, event: MemoryEvent) {
+        // Sending only fails when nobody is subscribed
+        let _ = self.events.send(event);
+    }
+
+    /// Request counters and latencies of the Qdrant and embedding clients.
+    pub fn client_stats(&self) -> Vec<ClientStats> {
+        vec![
+            self.vectorstore.metrics().stats(),
+            self.embedder.metrics.stats(),
+        ]
+    }
+
+    pub fn collection_name(&self) -> &str {
+        &self.collection_name
+    }
+
+    /// Opens the embedding and Qdrant connections and loads the collection's index, to
+    /// take the latency hit at service start instead of on the first request.
+    pub async fn warm_up(&self) -> Result<(), MemoryError> {
+        self.embedder
+            .warm_up()
+            .await
+            .map_err(|e| MemoryError::EmbeddingError(e.to_string()))?;
+        if self
+            .vectorstore
+            .check_collection(&self.collection_name)
+            .await?
+        {
+            self.vectorstore.warm_up(&self.collection_name).await?;
+        }
+        Ok(())
+    }
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, MemoryError> {
+        let mut embeddings = self
+            .embedder
+            .embed(vec![text.to_string()])
+            .await
+            .map_err(|e| MemoryError::EmbeddingError(e.to_string()))?;
+        embeddings
+            .pop()
+            .ok_or_else(|| MemoryError::EmbeddingError("empty embedding response".to_string()))
+    }
+
+    async fn ensure_collection(&self, vector_size: u64) -> Result<(), MemoryError> {
+        if self
+            .vectorstore
+            .check_collection(&self.collection_name)
+            .await?
+        {
+            return Ok(());
+        }
+
+        let vector_params = VectorParamsBuilder::new(vector_size, Distance::Cosine);
+        match &self.embedder_binding {
+            Some(binding) => {
+                self.vectorstore
+                    .create_collection_with_metadata(
+                        &self.collection_name,
+                        vector_params,
+                        binding.to_metadata(),
+                    )
+                    .await?;
+                self.bind_collection(&self.collection_name, binding.clone());
+            }
+            None => {
+                self.vectorstore
+                    .create_collection(&self.collection_name, vector_params)
+                    .await?
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates the store's collection configured by `preset`, sized to the embedder's
+    /// dimension and bound to its [`EmbedderBinding`] if there is one. Does nothing if the
+    /// collection exists, whatever its configuration.
+    pub async fn create(&self, preset: CollectionPreset) -> Result<(), MemoryError> {
+        if self
+            .vectorstore
+            .check_collection(&self.collection_name)
+            .await?
+        {
+            return Ok(());
+        }
+        let vector_size = self.embed("dimension probe").await?.len() as u64;
+        let metadata = self
+            .embedder_binding
+            .as_ref()
+            .map(EmbedderBinding::to_metadata)
+            .unwrap_or_default();
+        self.vectorstore
+            .create_preset_collection(&self.collection_name, vector_size, preset, metadata)
+            .await?;
+        if let Some(binding) = &self.embedder_binding {
+            self.bind_collection(&self.collection_name, binding.clone());
+        }
+        Ok(())
+    }
+
+    /// Stores `text` with optional metadata and returns the id of the new memory.
+    /// The collection is created on first write, sized to the embedding dimension.
+    pub async fn remember(
+        &self,
+        text: &str,
+        metadata: Option<Map<String, JsonValue>>,
+    ) -> Result<String, MemoryError> {
+        request_id::traced(async {
+            let metadata = self.claim(metadata.unwrap_or_default())?;
+            let (text, metadata) = self.review_write(text, metadata).await?;
+            let id = self.write_memory(&text, metadata.clone()).await?;
+            self.emit(MemoryEvent::Remembered {
+                collection: self.collection_name.clone(),
+                ids: vec![id.clone()],
+                metadata,
+            });
+            Ok(id)
+        })
+        .await
+    }
+
+    // Embeds and writes a memory without emitting an event
+    pub(crate) async fn write_memory(
+        &self,
+        text: &str,
+        metadata: Map<String, JsonValue>,
+    ) -> Result<String, MemoryError> {
+        let _operation = self.begin_operation()?;
+        self.check_embedder_binding(&self.collection_name).await?;
+        let embedding = self.embed(text).await?;
+        self.ensure_collection(embedding.len() as u64).await?;
+
+        let mut payload = metadata;
+        self.vectorstore
+            .payload_fields()
+            .insert_text(&mut payload, text);
+
+        let source = payload.get(SOURCE_FIELD).and_then(JsonValue::as_str);
+        let id = self
+            .vectorstore
+            .new_point_id(&self.collection_name, source, text)?;
+        self.vectorstore
+            .upsert_points_with_ids(
+                &self.collection_name,
+                vec![id.clone()],
+                vec![embedding],
+                vec![Payload::from(payload)],
+            )
+            .await?;
+        Ok(id)
+    }
+
+    /// Like [`MemoryStore::remember`], tagging the memory with a sensitivity level.
+    pub async fn remember_with_sensitivity(
+        &self,
+        text: &str,
+        metadata: Option<Map<String, JsonValue>>,
+        sensitivity: Sensitivity,
+    ) -> Result<String, MemoryError> {
+        let mut metadata = metadata.unwrap_or_default();
+        sensitivity.apply(&mut metadata);
+        self.remember(text, Some(metadata)).await
+    }
+
+    /// Like [`MemoryStore::remember`], recording `agent` as the writer and who else may
+    /// recall the memory. A store acting for another agent fails with
+    /// [`MemoryError::AgentMismatch`].
+    pub async fn remember_as(
+        &self,
+        agent: &AgentIdentity,
+        text: &str,
+        metadata: Option<Map<String, JsonValue>>,
+        visibility: Visibility,
+    ) -> Result<String, MemoryError> {
+        let mut metadata = metadata.unwrap_or_default();
+        agent.apply(&mut metadata, visibility);
+        self.remember(text, Some(metadata)).await
+    }
+
+    /// Returns the `limit` memories most similar to `query`.
+    pub async fn recall(&self, query: &str, limit: u64) -> Result<Vec<Memory>, MemoryError> {
+        self.search_collection(&self.collection_name, query, limit)
+            .await
+    }
+
+    /// Like [`MemoryStore::recall`], on behalf of a caller with the given access level
+    /// instead of the store's.
+    pub async fn recall_with_access(
+        &self,
+        query: &str,
+        limit: u64,
+        access_level: Sensitivity,
+    ) -> Result<Vec<Memory>, MemoryError> {
+        self.search(
+            &self.collection_name,
+            query,
+            limit,
+            None,
+            access_level,
+            self.agent.as_ref(),
+        )
+        .await
+    }
+
+    /// Like [`MemoryStore::recall`], on behalf of `agent` instead of the store's agent:
+    /// only its own memories, its team's and global ones are returned.
+    pub async fn recall_as(
+        &self,
+        agent: &AgentIdentity,
+        query: &str,
+        limit: u64,
+    ) -> Result<Vec<Memory>, MemoryError> {
+        self.search(
+            &self.collection_name,
+            query,
+            limit,
+            None,
+            self.access_level,
+            Some(agent),
+        )
+        .await
+    }
+
+    /// Like [`MemoryStore::recall`], restricted to memories matching `filter`.
+    pub async fn recall_filtered(
+        &self,
+        query: &str,
+        limit: u64,
+        filter: Filter,
+    ) -> Result<Vec<Memory>, MemoryError> {
+        self.search(
+            &self.collection_name,
+            query,
+            limit,
+            Some(filter),
+            self.access_level,
+            self.agent.as_ref(),
+        )
+        .await
+    }
+
+    /// Like [`MemoryStore::recall`], but against any collection written by this crate.
+    pub async fn search_collection(
+        &self,
+        collection_name: &str,
+        query: &str,
+        limit: u64,
+    ) -> Result<Vec<Memory>, MemoryError> {
+        self.search(
+            collection_name,
+            query,
+            limit,
+            None,
+            self.access_level,
+            self.agent.as_ref(),
+        )
+        .await
+    }
+
+    async fn search(
+        &self,
+        collection_name: &str,
+        query: &str,
+        limit: u64,
+        filter: Option<Filter>,
+        access_level: Sensitivity,
+        agent: Option<&AgentIdentity>,
+    ) -> Result<Vec<Memory>, MemoryError> {
+        request_id::traced(async {
+            let _operation = self.begin_operation()?;
+            self.check_embedder_binding(collection_name).await?;
+            let embedding = self.embed(query).await?;
+            let filter = self.read_filter(filter, access_level, agent);
+
+            let cache_params = format!("{limit}:{filter:?}");
+            // Read before the query, so a result racing a write is cached under the
+            // generation before it and never served
+            let generation = self.vectorstore.write_generation(collection_name);
+            if let Some(cache) = &self.recall_cache {
+                if let Some(memories) = cache.get(
+                    collection_name,
+                    generation,
+                    &embedding,
+                    cache_params.clone(),
+                ) {
+                    self.count_accesses(&memories);
+                    return Ok(memories);
+                }
+            }
+
+            let documents = self
+                .vectorstore
+                .query_documents_filtered(
+                    collection_name,
+                    embedding.clone(),
+                    limit,
+                    PayloadConvention::LiquidMemory,
+                    filter,
+                )
+                .await?;
+
+            let memories: Vec<Memory> = documents
+                .into_iter()
+                .map(|(document, score)| Memory::from_document(document, score))
+                .collect();
+            if let Some(cache) = &self.recall_cache {
+                cache.insert(
+                    collection_name,
+                    generation,
+                    &embedding,
+                    cache_params,
+                    memories.clone(),
+                );
+            }
+            self.count_accesses(&memories);
+            Ok(memories)
+        })
+        .await
+    }
+
+    /// Every memory matching `filter` that the store's access level and agent may see, in
+    /// no particular order. Scores are 0.
+    pub async fn memories_matching(&self, filter: Filter) -> Result<Vec<Memory>, MemoryError> {
+        request_id::traced(async {
+            let _operation = self.begin_operation()?;
+            let filter = self.read_filter(Some(filter), self.access_level, self.agent.as_ref());
+            let points = self
+                .vectorstore
+                .scroll_all(&self.collection_name, filter, false)
+                .await?;
+
+            Ok(points
+                .into_iter()
+                .filter_map(|point| {
+                    let id = point.id.as_ref().map(point_id_to_string);
+                    let mut document = interop::from_payload(
+                        point.payload,
+                        PayloadConvention::LiquidMemory,
+                        self.vectorstore.payload_fields(),
+                    )?;
+                    document.node_id = document.node_id.or(id);
+                    Some(Memory::from_document(document, 0.0))
+                })
+                .collect())
+        })
+        .await
+    }
+
+    /// Merges `fields` into the metadata of the given memories. Fails with
+    /// [`MemoryError::NotFound`], changing nothing, if one of them does not exist or, for
+    /// a store acting for an agent, is not the agent's own.
+    pub async fn set_metadata(
+        &self,
+        ids: Vec<String>,
+        fields: Map<String, JsonValue>,
+    ) -> Result<(), MemoryError> {
+        request_id::traced(async {
+            let _operation = self.begin_operation()?;
+            let ids = self.require_owned(ids).await?;
+            self.vectorstore
+                .set_payload(&self.collection_name, ids, Payload::from(fields))
+                .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Deletes the given memories. A store acting for an agent leaves other agents'
+    /// memories alone, as if they did not exist.
+    pub async fn forget(&self, ids: Vec<String>) -> Result<(), MemoryError> {
+        request_id::traced(async {
+            let _operation = self.begin_operation()?;
+            let ids = self.owned_ids(ids).await?;
+            self.vectorstore
+                .delete_points(&self.collection_name, ids.clone())
+                .await?;
+
+            self.emit(MemoryEvent::Forgotten {
+                collection: self.collection_name.clone(),
+                ids,
+            });
+            Ok(())
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::ownership::VISIBILITY_FIELD;
+
+    fn store() -> MemoryStore {
+        MemoryStore::new(
+            QdrantClient::new("http://localhost:6334"),
+            TextEmbeddingInference::new(Some("http://localhost:8888")),
+            "memories",
+        )
+    }
+
+    #[test]
+    fn test_read_filter_scopes_to_agent() {
+        let unscoped = store();
+        let filter = unscoped.read_filter(None, Sensitivity::Secret, unscoped.agent());
+        assert!(!format!("{filter:?}").contains(WRITTEN_BY_FIELD));
+
+        let scoped = store().with_agent(AgentIdentity::new("planner"));
+        let filter = scoped.read_filter(None, Sensitivity::Secret, scoped.agent());
+        assert!(format!("{filter:?}").contains("planner"));
+        // Another agent's recall is scoped to that agent instead
+        let other = AgentIdentity::new("critic");
+        let filter = scoped.read_filter(None, Sensitivity::Secret, Some(&other));
+        assert!(format!("{filter:?}").contains("critic"));
+        assert!(!format!("{filter:?}").contains("planner"));
+
+        let metadata = scoped.claim(Map::new()).unwrap();
+        assert_eq!(metadata[WRITTEN_BY_FIELD], "planner");
+        let mut private = Map::new();
+        AgentIdentity::new("planner").apply(&mut private, Visibility::Private);
+        assert_eq!(scoped.claim(private).unwrap()[VISIBILITY_FIELD], "private");
+        // An agent-scoped store cannot write on behalf of another agent
+        let mut written_by_other = Map::new();
+        other.apply(&mut written_by_other, Visibility::Global);
+        assert!(matches!(
+            scoped.claim(written_by_other),
+            Err(MemoryError::AgentMismatch { written_by, .. }) if written_by == "critic"
+        ));
+        // Unscoped stores keep whoever the caller names
+        let mut written_by_other = Map::new();
+        other.apply(&mut written_by_other, Visibility::Global);
+        assert_eq!(
+            unscoped.claim(written_by_other).unwrap()[WRITTEN_BY_FIELD],
+            "critic"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_agent_changes_need_ownership() {
+        // Nothing listens there, so ownership cannot be checked
+        let offline = || {
+            MemoryStore::new(
+                QdrantClient::new("http://127.0.0.1:1"),
+                TextEmbeddingInference::new(Some("http://127.0.0.1:1")),
+                "memories",
+            )
+        };
+        let unscoped = offline();
+        let ids = vec!["a".to_string(), "b".to_string()];
+        assert_eq!(unscoped.owned_ids(ids.clone()).await.unwrap(), ids);
+
+        let scoped = offline().with_agent(AgentIdentity::new("planner"));
+        assert!(scoped.owned_ids(Vec::new()).await.unwrap().is_empty());
+        assert!(scoped.forget(ids.clone()).await.is_err());
+        assert!(scoped.set_metadata(ids, Map::new()).await.is_err());
+        assert_eq!(scoped.vectorstore().write_generation("memories"), 0);
+    }
+}
@@ -0,0 +1,35 @@
+pub mod augmented_chat;
+pub mod conflicts;
+pub mod conversation;
+pub mod digest;
+pub mod diversity;
+pub mod embedder_binding;
+pub mod ensemble;
+pub mod evaluation;
+pub mod events;
+pub mod fact_extraction;
+pub mod facts;
+pub mod federated;
+pub mod feedback;
+pub mod grounding;
+pub mod importance;
+pub mod memory_store;
+pub mod overflow;
+pub mod ownership;
+pub mod persona;
+pub mod provenance;
+pub mod qa_memory;
+pub mod recall_cache;
+pub mod recall_pipeline;
+pub mod replay;
+pub mod retriever;
+pub mod sensitivity;
+pub mod shutdown;
+pub mod speculative;
+pub mod stage_policy;
+pub mod stats;
+pub mod summarize;
+pub mod trace;
+pub mod transaction;
+pub mod translate;
+pub mod write_policy;
@@ -0,0 +1,222 @@
+use crate::llm::llm_client::LlmClientChat;
+use crate::memory::memory_store::{Memory, MemoryError};
+use crate::models::ModelRegistry;
+use crate::utils::{estimate_model_tokens, truncate_to_tokens};
+use serde_json::{json, Map};
+
+const SUMMARY_PROMPT: &str = "Condense the following passages into a short summary, keeping \
+names, numbers and facts that could answer a question. Answer with the summary only.\n\n";
+
+// Below this many tokens a truncated memory is dropped rather than kept as a fragment
+const MIN_TRUNCATED_TOKENS: usize = 32;
+
+/// Id of the memory [`OverflowStrategy::Summarize`] replaces overflowing memories with.
+pub const SUMMARY_ID: &str = "summary";
+
+/// What [`PromptOverflow`] does with retrieved context that does not fit the prompt.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverflowStrategy {
+    /// Keeps memories in recall order and cuts the first one that overflows.
+    TruncateContext,
+    /// Drops the lowest-scored memories until the rest fits.
+    #[default]
+    DropLowestScored,
+    /// Keeps the best memories that fit in half the space and replaces the others with
+    /// an LLM summary of them.
+    Summarize,
+}
+
+/// Fits retrieved context into the prompt budget of the target model, its context window
+/// minus its output limit in a [`ModelRegistry`], so an oversized prompt does not fail
+/// with a 400 mid-pipeline. Models the registry does not know are not limited.
+#[derive(Debug, Clone)]
+pub struct PromptOverflow {
+    pub strategy: OverflowStrategy,
+    models: ModelRegistry,
+}
+
+impl Default for PromptOverflow {
+    fn default() -> Self {
+        Self::new(OverflowStrategy::default())
+    }
+}
+
+fn memory_tokens(memory: &Memory, model: &str) -> usize {
+    // Numbering and line break around each memory in the prompt
+    estimate_model_tokens(&memory.text, model) + 2
+}
+
+impl PromptOverflow {
+    pub fn new(strategy: OverflowStrategy) -> Self {
+        Self {
+            strategy,
+            models: ModelRegistry::default(),
+        }
+    }
+
+    pub fn with_models(mut self, models: ModelRegistry) -> Self {
+        self.models = models;
+        self
+    }
+
+    /// Returns the memories of `context` that fit a prompt to `model` next to `fixed`,
+    /// the instructions and question around them. Unchanged when everything fits.
+    pub async fn fit<C: LlmClientChat>(
+        &self,
+        llm_client: &C,
+        model: &str,
+        fixed: &str,
+        context: Vec<Memory>,
+    ) -> Result<Vec<Memory>, MemoryError> {
+        let Some(budget) = self.models.prompt_budget(model) else {
+            return Ok(context);
+        };
+        let budget = budget.saturating_sub(estimate_model_tokens(fixed, model));
+        let total: usize = context.iter().map(|m| memory_tokens(m, model)).sum();
+        if total <= budget {
+            return Ok(context);
+        }
+        match self.strategy {
+            OverflowStrategy::TruncateContext => Ok(truncate_context(context, model, budget)),
+            OverflowStrategy::DropLowestScored => Ok(drop_lowest_scored(context, model, budget)),
+            OverflowStrategy::Summarize => {
+                summarize_overflow(llm_client, model, context, budget).await
+            }
+        }
+    }
+}
+
+fn truncate_context(context: Vec<Memory>, model: &str, budget: usize) -> Vec<Memory> {
+    let mut used = 0;
+    let mut kept = Vec::new();
+    for mut memory in context {
+        let tokens = memory_tokens(&memory, model);
+        if used + tokens > budget {
+            let left = budget.saturating_sub(used + 2);
+            if left >= MIN_TRUNCATED_TOKENS {
+                memory.text = truncate_to_tokens(&memory.text, model, left).to_string();
+                kept.push(memory);
+            }
+            break;
+        }
+        used += tokens;
+        kept.push(memory);
+    }
+    kept
+}
+
+fn drop_lowest_scored(context: Vec<Memory>, model: &str, budget: usize) -> Vec<Memory> {
+    let tokens: Vec<usize> = context.iter().map(|m| memory_tokens(m, model)).collect();
+    let mut by_score: Vec<usize> = (0..context.len()).collect();
+    by_score.sort_by(|a, b| context[*a].score.total_cmp(&context[*b].score));
+
+    let mut total: usize = tokens.iter().sum();
+    let mut dropped = vec![false; context.len()];
+    // The best memory is kept, truncated below, even if it alone overflows
+    for idx in by_score.into_iter().take(context.len().saturating_sub(1)) {
+        if total <= budget {
+            break;
+        }
+        dropped[idx] = true;
+        total -= tokens[idx];
+    }
+    let kept = context
+        .into_iter()
+        .zip(dropped)
+        .filter(|(_, dropped)| !dropped)
+        .map(|(memory, _)| memory)
+        .collect();
+    truncate_context(kept, model, budget)
+}
+
+async fn summarize_overflow<C: LlmClientChat>(
+    llm_client: &C,
+    model: &str,
+    context: Vec<Memory>,
+    budget: usize,
+) -> Result<Vec<Memory>, MemoryError> {
+    let mut kept = Vec::new();
+    let mut overflow = Vec::new();
+    let mut used = 0;
+    for memory in context {
+        let tokens = memory_tokens(&memory, model);
+        if overflow.is_empty() && used + tokens <= budget / 2 {
+            used += tokens;
+            kept.push(memory);
+        } else {
+            overflow.push(memory);
+        }
+    }
+
+    let passages = overflow
+        .iter()
+        .map(|memory| memory.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    // The summary request itself must fit the model
+    let passages = truncate_to_tokens(&passages, model, budget);
+    let summary = llm_client
+        .send_message(
+            model,
+            format!("{SUMMARY_PROMPT}{passages}"),
+            None::<&str>,
+            Some(0.0),
+        )
+        .await
+        .map_err(|e| MemoryError::LlmError(e.to_string()))?;
+
+    let mut metadata = Map::new();
+    metadata.insert(
+        "summarized".to_string(),
+        json!(overflow.iter().map(|m| m.id.as_str()).collect::<Vec<_>>()),
+    );
+    kept.push(Memory {
+        id: SUMMARY_ID.to_string(),
+        text: summary.trim().to_string(),
+        metadata,
+        score: overflow.iter().map(|m| m.score).fold(f32::MAX, f32::min),
+    });
+    Ok(truncate_context(kept, model, budget))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory(id: &str, words: usize, score: f32) -> Memory {
+        Memory {
+            id: id.to_string(),
+            text: vec!["boots"; words].join(" "),
+            metadata: Map::new(),
+            score,
+        }
+    }
+
+    fn ids(memories: &[Memory]) -> Vec<&str> {
+        memories.iter().map(|m| m.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_drop_lowest_scored() {
+        let context = vec![
+            memory("a", 100, 0.9),
+            memory("b", 100, 0.5),
+            memory("c", 100, 0.7),
+        ];
+        let budget = memory_tokens(&context[0], "gpt-4o") * 2;
+        assert_eq!(
+            ids(&drop_lowest_scored(context.clone(), "gpt-4o", budget)),
+            vec!["a", "c"]
+        );
+        let truncated = truncate_context(context, "gpt-4o", budget + 40);
+        assert_eq!(ids(&truncated), vec!["a", "b", "c"]);
+        assert!(truncated[2].text.len() < truncated[0].text.len());
+    }
+
+    #[test]
+    fn test_best_memory_is_truncated_not_dropped() {
+        let kept = drop_lowest_scored(vec![memory("a", 500, 0.9)], "gpt-4o", 100);
+        assert_eq!(ids(&kept), vec!["a"]);
+        assert!(estimate_model_tokens(&kept[0].text, "gpt-4o") <= 98);
+    }
+}
@@ -0,0 +1,136 @@
+use qdrant_client::qdrant::{Condition, Filter};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value as JsonValue};
+
+/// Payload field holding the id of the agent that wrote a memory.
+pub const WRITTEN_BY_FIELD: &str = "written_by";
+/// Payload field holding the team of the agent that wrote a memory.
+pub const TEAM_FIELD: &str = "team";
+/// Payload field holding the [`Visibility`] of a memory. Memories without it, e.g. written
+/// before agents shared the store, are global.
+pub const VISIBILITY_FIELD: &str = "visibility";
+
+/// Which agents may recall a memory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Visibility {
+    /// Only the agent that wrote it.
+    Private,
+    /// Agents of the writer's team.
+    #[default]
+    Team,
+    /// Every agent.
+    Global,
+}
+
+impl Visibility {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Private => "private",
+            Self::Team => "team",
+            Self::Global => "global",
+        }
+    }
+}
+
+/// An agent sharing a [`MemoryStore`](crate::memory::memory_store::MemoryStore) with
+/// others, see [`MemoryStore::with_agent`](crate::memory::memory_store::MemoryStore::with_agent),
+/// [`MemoryStore::remember_as`](crate::memory::memory_store::MemoryStore::remember_as)
+/// and [`MemoryStore::recall_as`](crate::memory::memory_store::MemoryStore::recall_as).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgentIdentity {
+    pub agent_id: String,
+    pub team: Option<String>,
+}
+
+impl AgentIdentity {
+    pub fn new(agent_id: impl Into<String>) -> Self {
+        Self {
+            agent_id: agent_id.into(),
+            team: None,
+        }
+    }
+
+    pub fn with_team(mut self, team: impl Into<String>) -> Self {
+        self.team = Some(team.into());
+        self
+    }
+
+    /// Sets the ownership fields of a memory's metadata. Team memories of an agent without
+    /// a team are private.
+    pub fn apply(&self, metadata: &mut Map<String, JsonValue>, visibility: Visibility) {
+        metadata.insert(WRITTEN_BY_FIELD.to_string(), json!(self.agent_id));
+        let visibility = match (&self.team, visibility) {
+            (None, Visibility::Team) => Visibility::Private,
+            (_, visibility) => visibility,
+        };
+        metadata.insert(VISIBILITY_FIELD.to_string(), json!(visibility.as_str()));
+        match &self.team {
+            Some(team) => metadata.insert(TEAM_FIELD.to_string(), json!(team)),
+            None => metadata.remove(TEAM_FIELD),
+        };
+    }
+
+    /// Filter keeping the memories this agent may recall: its own, its team's and global
+    /// ones.
+    pub fn access_filter(&self) -> Filter {
+        let mut visible = vec![
+            Condition::from(Filter::must_not([Condition::matches(
+                VISIBILITY_FIELD,
+                vec![
+                    Visibility::Private.as_str().to_string(),
+                    Visibility::Team.as_str().to_string(),
+                ],
+            )])),
+            Condition::matches(WRITTEN_BY_FIELD, self.agent_id.clone()),
+        ];
+        if let Some(team) = &self.team {
+            visible.push(Condition::from(Filter::must([
+                Condition::matches(VISIBILITY_FIELD, Visibility::Team.as_str().to_string()),
+                Condition::matches(TEAM_FIELD, team.clone()),
+            ])));
+        }
+        Filter::should(visible)
+    }
+}
+
+/// Combines an optional caller filter with the access filter of `agent`.
+pub fn restrict(filter: Option<Filter>, agent: Option<&AgentIdentity>) -> Option<Filter> {
+    match (filter, agent) {
+        (filter, None) => filter,
+        (None, Some(agent)) => Some(agent.access_filter()),
+        (Some(filter), Some(agent)) => Some(Filter::must([
+            Condition::from(filter),
+            Condition::from(agent.access_filter()),
+        ])),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let mut metadata = Map::new();
+        let agent = AgentIdentity::new("planner").with_team("support");
+        agent.apply(&mut metadata, Visibility::Team);
+        assert_eq!(metadata[WRITTEN_BY_FIELD], "planner");
+        assert_eq!(metadata[VISIBILITY_FIELD], "team");
+        assert_eq!(metadata[TEAM_FIELD], "support");
+
+        AgentIdentity::new("solo").apply(&mut metadata, Visibility::Team);
+        assert_eq!(metadata[VISIBILITY_FIELD], "private");
+        assert!(!metadata.contains_key(TEAM_FIELD));
+    }
+
+    #[test]
+    fn test_access_filter() {
+        assert_eq!(AgentIdentity::new("solo").access_filter().should.len(), 2);
+        let agent = AgentIdentity::new("planner").with_team("support");
+        assert_eq!(agent.access_filter().should.len(), 3);
+        assert!(restrict(None, None).is_none());
+        let caller = Filter::must([Condition::matches("kind", "fact".to_string())]);
+        assert_eq!(restrict(Some(caller), Some(&agent)).unwrap().must.len(), 2);
+    }
+}
@@ -0,0 +1,173 @@
+use crate::memory::facts::Fact;
+use crate::memory::memory_store::{Memory, MemoryError, MemoryStore};
+use qdrant_client::qdrant::{Condition, Filter};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map};
+use std::collections::BTreeMap;
+
+const PERSONA_KIND: &str = "persona";
+
+// Payload field holding the serialized persona
+const PERSONA_FIELD: &str = "persona";
+
+/// Who an agent is and what it knows about its user, prepended to prompts so the agent
+/// stays consistent across sessions. Stored in memory with [`MemoryStore::save_persona`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Persona {
+    pub name: String,
+    /// System prompt fragments, in order.
+    pub instructions: Vec<String>,
+    pub tone: Option<String>,
+    /// User preferences by predicate, e.g. `prefers` or `lives_in`.
+    pub preferences: BTreeMap<String, String>,
+}
+
+impl Persona {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_instruction(mut self, instruction: impl Into<String>) -> Self {
+        self.instructions.push(instruction.into());
+        self
+    }
+
+    pub fn with_tone(mut self, tone: impl Into<String>) -> Self {
+        self.tone = Some(tone.into());
+        self
+    }
+
+    pub fn with_preference(
+        mut self,
+        predicate: impl Into<String>,
+        text: impl Into<String>,
+    ) -> Self {
+        self.preferences.insert(predicate.into(), text.into());
+        self
+    }
+
+    /// Takes the preferences from facts, as written by
+    /// [`FactExtractor`](crate::memory::fact_extraction::FactExtractor); a newer fact for
+    /// a predicate replaces the older one.
+    pub fn update_from_facts(&mut self, facts: &[Fact]) {
+        let mut facts: Vec<&Fact> = facts.iter().collect();
+        facts.sort_by_key(|fact| fact.valid_from);
+        for fact in facts {
+            self.preferences
+                .insert(fact.predicate.clone(), fact.text.clone());
+        }
+    }
+
+    /// The prompt preamble for this persona, empty if it has nothing to say.
+    pub fn to_prompt(&self) -> String {
+        let mut prompt = String::new();
+        for instruction in &self.instructions {
+            prompt.push_str(instruction);
+            prompt.push('\n');
+        }
+        if let Some(tone) = &self.tone {
+            prompt.push_str(&format!("Tone: {tone}\n"));
+        }
+        if !self.preferences.is_empty() {
+            prompt.push_str("Known about the user:\n");
+            for text in self.preferences.values() {
+                prompt.push_str(&format!("- {text}\n"));
+            }
+        }
+        prompt
+    }
+
+    fn from_memory(memory: &Memory) -> Option<Self> {
+        serde_json::from_value(memory.metadata.get(PERSONA_FIELD)?.clone()).ok()
+    }
+}
+
+fn persona_filter(name: &str) -> Filter {
+    Filter::must([
+        Condition::matches("kind", PERSONA_KIND.to_string()),
+        Condition::matches("name", name.to_string()),
+    ])
+}
+
+impl MemoryStore {
+    /// Stores `persona`, replacing the stored one with the same name.
+    pub async fn save_persona(&self, persona: &Persona) -> Result<String, MemoryError> {
+        let previous: Vec<String> = self
+            .memories_matching(persona_filter(&persona.name))
+            .await?
+            .into_iter()
+            .map(|memory| memory.id)
+            .collect();
+
+        let mut metadata = Map::new();
+        metadata.insert("kind".to_string(), json!(PERSONA_KIND));
+        metadata.insert("name".to_string(), json!(persona.name));
+        metadata.insert(PERSONA_FIELD.to_string(), json!(persona));
+        let mut transaction = self.begin();
+        let text = format!("Persona {}\n{}", persona.name, persona.to_prompt());
+        let id = transaction.remember(&text, Some(metadata)).await?;
+        transaction.forget(previous);
+        transaction.commit().await?;
+        Ok(id)
+    }
+
+    /// The stored persona with this name, if any.
+    pub async fn persona(&self, name: &str) -> Result<Option<Persona>, MemoryError> {
+        Ok(self
+            .memories_matching(persona_filter(name))
+            .await?
+            .iter()
+            .find_map(Persona::from_memory))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn fact(predicate: &str, text: &str, year: i32) -> Fact {
+        Fact {
+            id: String::new(),
+            subject: "user".to_string(),
+            predicate: predicate.to_string(),
+            text: text.to_string(),
+            valid_from: Utc.with_ymd_and_hms(year, 1, 1, 0, 0, 0).unwrap(),
+            valid_until: None,
+            score: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_persona_prompt() {
+        let mut persona = Persona::new("shop-assistant")
+            .with_instruction("You are the assistant of an online shoe shop.")
+            .with_tone("friendly, concise")
+            .with_preference("shoe_size", "The user's shoe size is 41");
+        persona.update_from_facts(&[
+            fact("shoe_size", "The user's shoe size is 42", 2025),
+            fact("prefers", "The user prefers ankle boots", 2024),
+            fact("shoe_size", "The user's shoe size is 43", 2023),
+        ]);
+        assert_eq!(
+            persona.to_prompt(),
+            "You are the assistant of an online shoe shop.\nTone: friendly, concise\n\
+            Known about the user:\n- The user prefers ankle boots\n\
+            - The user's shoe size is 42\n"
+        );
+        assert_eq!(Persona::new("empty").to_prompt(), "");
+
+        let mut metadata = Map::new();
+        metadata.insert(PERSONA_FIELD.to_string(), json!(persona));
+        let memory = Memory {
+            id: "1".to_string(),
+            text: String::new(),
+            metadata,
+            score: 0.0,
+        };
+        assert_eq!(Persona::from_memory(&memory), Some(persona));
+    }
+}
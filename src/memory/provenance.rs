@@ -0,0 +1,139 @@
+use crate::memory::memory_store::{Memory, MemoryError, MemoryStore};
+use qdrant_client::qdrant::{Condition, Filter};
+use serde::Serialize;
+use serde_json::{json, Map, Value as JsonValue};
+use std::collections::HashSet;
+
+/// Payload field holding the ids of the memories a memory was derived from, e.g. the
+/// chunks of a summary or the turns of an extracted fact.
+pub const DERIVED_FROM_FIELD: &str = "derived_from";
+
+// Guards against cycles and runaway chains
+const MAX_PROVENANCE_DEPTH: usize = 16;
+
+/// Ids a memory was derived from, empty for original memories.
+pub fn derived_from(memory: &Memory) -> Vec<String> {
+    memory
+        .metadata
+        .get(DERIVED_FROM_FIELD)
+        .and_then(JsonValue::as_array)
+        .map(|ids| {
+            ids.iter()
+                .filter_map(|id| id.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// A memory reached while tracing provenance, `depth` steps away from the start.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProvenanceStep {
+    pub memory: Memory,
+    pub depth: usize,
+}
+
+impl MemoryStore {
+    /// Like [`MemoryStore::remember`], linking the new memory to the memories it was
+    /// derived from.
+    pub async fn remember_derived(
+        &self,
+        text: &str,
+        metadata: Option<Map<String, JsonValue>>,
+        derived_from: Vec<String>,
+    ) -> Result<String, MemoryError> {
+        let mut metadata = metadata.unwrap_or_default();
+        metadata.insert(DERIVED_FROM_FIELD.to_string(), json!(derived_from));
+        self.remember(text, Some(metadata)).await
+    }
+
+    /// The memories with the given ids, in no particular order. Unknown ids are skipped.
+    pub async fn memories_by_id(&self, ids: Vec<String>) -> Result<Vec<Memory>, MemoryError> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.memories_matching(Filter::must([Condition::has_id(ids)]))
+            .await
+    }
+
+    /// The memories `id` was directly derived from.
+    pub async fn sources_of(&self, id: &str) -> Result<Vec<Memory>, MemoryError> {
+        let memory = self
+            .memories_by_id(vec![id.to_string()])
+            .await?
+            .pop()
+            .ok_or_else(|| MemoryError::NotFound(id.to_string()))?;
+        self.memories_by_id(derived_from(&memory)).await
+    }
+
+    /// The memories directly derived from `id`.
+    pub async fn derived_memories(&self, id: &str) -> Result<Vec<Memory>, MemoryError> {
+        self.memories_matching(Filter::must([Condition::matches(
+            DERIVED_FROM_FIELD,
+            id.to_string(),
+        )]))
+        .await
+    }
+
+    /// Walks `derived_from` links breadth-first from `id` (e.g. summary → chunks → source
+    /// file), returning every memory on the way, starting with `id` itself at depth 0.
+    /// Links to memories outside this store's collection are not followed.
+    pub async fn trace_provenance(&self, id: &str) -> Result<Vec<ProvenanceStep>, MemoryError> {
+        let mut visited = HashSet::from([id.to_string()]);
+        let mut frontier = vec![id.to_string()];
+        let mut steps = Vec::new();
+
+        for depth in 0..=MAX_PROVENANCE_DEPTH {
+            if frontier.is_empty() {
+                break;
+            }
+            let memories = self.memories_by_id(frontier).await?;
+            if depth == 0 && memories.is_empty() {
+                return Err(MemoryError::NotFound(id.to_string()));
+            }
+            frontier = Vec::new();
+            for memory in memories {
+                for source in derived_from(&memory) {
+                    if visited.insert(source.clone()) {
+                        frontier.push(source);
+                    }
+                }
+                steps.push(ProvenanceStep { memory, depth });
+            }
+        }
+        Ok(steps)
+    }
+
+    /// The original memories `id` ultimately derives from, i.e. the traced memories that
+    /// were not derived from anything. A memory without links is its own origin.
+    pub async fn origins(&self, id: &str) -> Result<Vec<Memory>, MemoryError> {
+        Ok(self
+            .trace_provenance(id)
+            .await?
+            .into_iter()
+            .map(|step| step.memory)
+            .filter(|memory| derived_from(memory).is_empty())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derived_from() {
+        let mut memory = Memory {
+            id: "summary".to_string(),
+            text: "Summary of the return policy".to_string(),
+            metadata: Map::new(),
+            score: 0.0,
+        };
+        assert!(derived_from(&memory).is_empty());
+
+        memory.metadata.insert(
+            DERIVED_FROM_FIELD.to_string(),
+            json!(["chunk-1", "chunk-2"]),
+        );
+        assert_eq!(derived_from(&memory), vec!["chunk-1", "chunk-2"]);
+    }
+}
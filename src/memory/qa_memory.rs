@@ -0,0 +1,257 @@
+use crate::llm::llm_client::LlmClientChat;
+use crate::memory::evaluation::{EvaluationSampler, RagSample};
+use crate::memory::grounding::{
+    annotate, check_grounding, regeneration_prompt, GroundingAction, GroundingCheck,
+    GroundingReport,
+};
+use crate::memory::memory_store::{Memory, MemoryError, MemoryStore};
+use crate::memory::overflow::PromptOverflow;
+use serde::Serialize;
+use serde_json::{json, Map, Value as JsonValue};
+
+const ANSWER_FIELD: &str = "answer";
+
+const RAG_PROMPT: &str = "Answer the question using the numbered context below. \
+If the context does not contain the answer, say so.\n\n";
+
+/// Where an answer came from.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum AnswerSource {
+    /// A stored answer, returned without calling the LLM.
+    Stored {
+        id: String,
+        question: String,
+        score: f32,
+    },
+    /// Generated by the LLM from the recalled context.
+    Generated {
+        context: Vec<Memory>,
+        /// Set when the answer went through a [`GroundingCheck`].
+        #[serde(skip_serializing_if = "Option::is_none")]
+        grounding: Option<GroundingReport>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QaAnswer {
+    pub answer: String,
+    #[serde(flatten)]
+    pub source: AnswerSource,
+}
+
+fn rag_prompt(question: &str, context: &[Memory]) -> String {
+    let mut prompt = RAG_PROMPT.to_string();
+    for (idx, memory) in context.iter().enumerate() {
+        prompt.push_str(&format!("[{}] {}\n", idx + 1, memory.text));
+    }
+    prompt.push_str(&format!("\nQuestion: {question}"));
+    prompt
+}
+
+/// FAQ-style memory of question/answer pairs. Only the question is embedded; when a new
+/// question is close enough to a stored one its answer is returned as is, otherwise the
+/// answer is generated from memories recalled from the document store.
+pub struct QaMemory {
+    pairs: MemoryStore,
+    documents: MemoryStore,
+    min_similarity: f32,
+    context_limit: u64,
+    grounding: Option<GroundingCheck>,
+    overflow: Option<PromptOverflow>,
+    evaluation: Option<EvaluationSampler>,
+}
+
+impl QaMemory {
+    /// `pairs` holds the question/answer pairs and `documents` the context used when no
+    /// stored question matches. They must use different collections.
+    pub fn new(pairs: MemoryStore, documents: MemoryStore) -> Self {
+        Self {
+            pairs,
+            documents,
+            min_similarity: 0.9,
+            context_limit: 5,
+            grounding: None,
+            overflow: Some(PromptOverflow::default()),
+            evaluation: None,
+        }
+    }
+
+    /// Minimum similarity to a stored question to return its answer. Defaults to 0.9.
+    pub fn with_min_similarity(mut self, min_similarity: f32) -> Self {
+        self.min_similarity = min_similarity;
+        self
+    }
+
+    /// Number of documents recalled when falling back to the LLM. Defaults to 5.
+    pub fn with_context_limit(mut self, context_limit: u64) -> Self {
+        self.context_limit = context_limit;
+        self
+    }
+
+    /// Checks generated answers against their context before returning them. Stored
+    /// answers are returned as is.
+    pub fn with_grounding(mut self, grounding: Option<GroundingCheck>) -> Self {
+        self.grounding = grounding;
+        self
+    }
+
+    /// How recalled context that does not fit the model's prompt is shrunk. Defaults to
+    /// dropping the lowest-scored documents; `None` sends the prompt as is.
+    pub fn with_overflow(mut self, overflow: Option<PromptOverflow>) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Hands a sample of the generated answers to online evaluation (see
+    /// [`evaluation`](crate::memory::evaluation::evaluation)).
+    pub fn with_evaluation(mut self, sampler: EvaluationSampler) -> Self {
+        self.evaluation = Some(sampler);
+        self
+    }
+
+    pub fn pairs(&self) -> &MemoryStore {
+        &self.pairs
+    }
+
+    pub fn documents(&self) -> &MemoryStore {
+        &self.documents
+    }
+
+    /// Stores a question/answer pair and returns its id.
+    pub async fn remember_pair(
+        &self,
+        question: &str,
+        answer: &str,
+        metadata: Option<Map<String, JsonValue>>,
+    ) -> Result<String, MemoryError> {
+        let mut metadata = metadata.unwrap_or_default();
+        metadata.insert(ANSWER_FIELD.to_string(), json!(answer));
+        self.pairs.remember(question, Some(metadata)).await
+    }
+
+    /// The stored pair whose question is most similar to `question`, if it is at least
+    /// as similar as the threshold.
+    pub async fn recall_pair(&self, question: &str) -> Result<Option<QaAnswer>, MemoryError> {
+        let Some(memory) = self.pairs.recall(question, 1).await?.into_iter().next() else {
+            return Ok(None);
+        };
+        if memory.score < self.min_similarity {
+            return Ok(None);
+        }
+        let Some(answer) = memory
+            .metadata
+            .get(ANSWER_FIELD)
+            .and_then(JsonValue::as_str)
+        else {
+            return Ok(None);
+        };
+        Ok(Some(QaAnswer {
+            answer: answer.to_string(),
+            source: AnswerSource::Stored {
+                id: memory.id,
+                question: memory.text,
+                score: memory.score,
+            },
+        }))
+    }
+
+    /// Answers from a stored pair when one matches, from the LLM and recalled documents
+    /// otherwise.
+    pub async fn answer<C: LlmClientChat>(
+        &self,
+        question: &str,
+        llm_client: &C,
+        model: &str,
+    ) -> Result<QaAnswer, MemoryError> {
+        if let Some(answer) = self.recall_pair(question).await? {
+            return Ok(answer);
+        }
+
+        let mut context = self.documents.recall(question, self.context_limit).await?;
+        if let Some(overflow) = &self.overflow {
+            context = overflow
+                .fit(llm_client, model, &rag_prompt(question, &[]), context)
+                .await?;
+        }
+        let prompt = rag_prompt(question, &context);
+        let generate = |prompt: String| async move {
+            llm_client
+                .send_message(model, prompt, None::<&str>, Some(0.0))
+                .await
+                .map_err(|e| MemoryError::LlmError(e.to_string()))
+        };
+        let mut answer = generate(prompt.clone()).await?;
+
+        let Some(grounding) = &self.grounding else {
+            self.offer_evaluation(question, &answer, &context, model);
+            return Ok(QaAnswer {
+                answer,
+                source: AnswerSource::Generated {
+                    context,
+                    grounding: None,
+                },
+            });
+        };
+        let judge = grounding.model.as_deref().unwrap_or(model);
+        let mut report = check_grounding(llm_client, judge, &context, &answer).await?;
+        if let GroundingAction::Regenerate(max_regenerations) = grounding.action {
+            while !report.is_grounded() && report.regenerations < max_regenerations {
+                let regenerations = report.regenerations + 1;
+                answer = generate(regeneration_prompt(&prompt, &report)).await?;
+                report = check_grounding(llm_client, judge, &context, &answer).await?;
+                report.regenerations = regenerations;
+            }
+        }
+        if !report.is_grounded() {
+            answer = annotate(&answer, &report, &grounding.marker);
+        }
+        self.offer_evaluation(question, &answer, &context, model);
+        Ok(QaAnswer {
+            answer,
+            source: AnswerSource::Generated {
+                context,
+                grounding: Some(report),
+            },
+        })
+    }
+
+    fn offer_evaluation(&self, question: &str, answer: &str, context: &[Memory], model: &str) {
+        if let Some(sampler) = &self.evaluation {
+            sampler.offer(|| RagSample {
+                question: question.to_string(),
+                answer: answer.to_string(),
+                context: context.to_vec(),
+                model: model.to_string(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rag_prompt() {
+        let context = vec![Memory {
+            id: "1".to_string(),
+            text: "Returns are free within 30 days.".to_string(),
+            metadata: Map::new(),
+            score: 0.7,
+        }];
+        let prompt = rag_prompt("Can I return my boots?", &context);
+        assert!(prompt.contains("[1] Returns are free within 30 days.\n"));
+        assert!(prompt.ends_with("Question: Can I return my boots?"));
+
+        let answer = QaAnswer {
+            answer: "Yes".to_string(),
+            source: AnswerSource::Stored {
+                id: "2".to_string(),
+                question: "Can I return items?".to_string(),
+                score: 0.95,
+            },
+        };
+        assert_eq!(serde_json::to_value(&answer).unwrap()["source"], "stored");
+    }
+}
@@ -0,0 +1,180 @@
+use crate::memory::memory_store::Memory;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    collection: String,
+    // Write generation of the collection the result was read at
+    generation: u64,
+    embedding_hash: u64,
+    // Everything besides the embedding that changes the result: limit, filter, access level
+    params: String,
+}
+
+/// Short-lived cache of recall results keyed by query embedding, for hot repeated queries.
+/// Results are also keyed by the write generation of their collection (see
+/// [`QdrantClient::write_generation`](crate::vectorstore::qdrant_client::QdrantClient::write_generation)),
+/// so a write makes every earlier result of the collection a miss.
+pub struct RecallCache {
+    ttl: Duration,
+    max_entries: usize,
+    entries: Mutex<HashMap<CacheKey, (Instant, Vec<Memory>)>>,
+}
+
+fn hash_embedding(embedding: &[f32]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for value in embedding {
+        value.to_bits().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+impl RecallCache {
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            ttl,
+            max_entries,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn key(collection: &str, generation: u64, embedding: &[f32], params: String) -> CacheKey {
+        CacheKey {
+            collection: collection.to_string(),
+            generation,
+            embedding_hash: hash_embedding(embedding),
+            params,
+        }
+    }
+
+    pub fn get(
+        &self,
+        collection: &str,
+        generation: u64,
+        embedding: &[f32],
+        params: String,
+    ) -> Option<Vec<Memory>> {
+        let key = Self::key(collection, generation, embedding, params);
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(&key) {
+            Some((inserted, memories)) if inserted.elapsed() < self.ttl => Some(memories.clone()),
+            Some(_) => {
+                entries.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Caches `memories`, dropping the results of earlier generations of `collection`.
+    pub fn insert(
+        &self,
+        collection: &str,
+        generation: u64,
+        embedding: &[f32],
+        params: String,
+        memories: Vec<Memory>,
+    ) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|key, _| key.collection != collection || key.generation >= generation);
+        if entries.len() >= self.max_entries {
+            entries.retain(|_, (inserted, _)| inserted.elapsed() < self.ttl);
+            // Still full of live entries: start over rather than tracking usage
+            if entries.len() >= self.max_entries {
+                entries.clear();
+            }
+        }
+        entries.insert(
+            Self::key(collection, generation, embedding, params),
+            (Instant::now(), memories),
+        );
+    }
+
+    /// Drops every cached result of `collection`.
+    pub fn invalidate(&self, collection: &str) {
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|key, _| key.collection != collection);
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Map;
+
+    fn memories() -> Vec<Memory> {
+        vec![Memory {
+            id: "1".to_string(),
+            text: "User lives in Lisbon".to_string(),
+            metadata: Map::new(),
+            score: 0.9,
+        }]
+    }
+
+    #[test]
+    fn test_recall_cache() {
+        let cache = RecallCache::new(Duration::from_secs(60), 10);
+        let embedding = [0.1, 0.2, 0.3];
+        cache.insert("memories", 0, &embedding, "5".to_string(), memories());
+
+        assert_eq!(
+            cache
+                .get("memories", 0, &embedding, "5".to_string())
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(cache
+            .get("memories", 0, &embedding, "10".to_string())
+            .is_none());
+        assert!(cache
+            .get("memories", 0, &[0.1, 0.2, 0.4], "5".to_string())
+            .is_none());
+        // Written to since
+        assert!(cache
+            .get("memories", 1, &embedding, "5".to_string())
+            .is_none());
+
+        cache.invalidate("other");
+        assert_eq!(cache.len(), 1);
+        cache.invalidate("memories");
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_recall_cache_expiry() {
+        let cache = RecallCache::new(Duration::ZERO, 1);
+        cache.insert("memories", 0, &[1.0], String::new(), memories());
+        assert!(cache.get("memories", 0, &[1.0], String::new()).is_none());
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_recall_cache_drops_older_generations() {
+        let cache = RecallCache::new(Duration::from_secs(60), 10);
+        cache.insert("memories", 0, &[1.0], String::new(), memories());
+        cache.insert("products", 0, &[1.0], String::new(), memories());
+        cache.insert("memories", 1, &[1.0], String::new(), memories());
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get("memories", 0, &[1.0], String::new()).is_none());
+        assert!(cache.get("products", 0, &[1.0], String::new()).is_some());
+    }
+}
@@ -0,0 +1,679 @@
+use crate::llm::llm_client::LlmClientChat;
+use crate::memory::memory_store::{Memory, MemoryError};
+use crate::memory::retriever::Retriever;
+use crate::memory::stage_policy::{QueryClass, StagePolicy};
+use crate::memory::trace::{RetrievalTrace, TraceExport};
+use crate::vectorstore::caption_validation::parse_score;
+use crate::vectorstore::filter::MetadataFilter;
+use futures::future::{try_join_all, LocalBoxFuture};
+use serde::Serialize;
+use std::fmt;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+const REWRITE_PROMPT: &str = "Rewrite the following search query so it retrieves the \
+most relevant memories: expand abbreviations, resolve vague wording and drop filler. \
+Answer with the rewritten query only.\n\nQuery: ";
+
+const RERANK_PROMPT: &str = "On a scale of 1 to 10, how relevant is the memory below to \
+the query? Answer with the number only.";
+
+// Candidates fetched per requested memory when a reranker is configured
+const RERANK_OVERFETCH: u64 = 4;
+
+/// Rewrites a query before retrieval.
+#[allow(async_fn_in_trait)]
+pub trait QueryRewriter {
+    async fn rewrite(&self, query: &str) -> Result<String, MemoryError>;
+}
+
+/// Reorders retrieved memories, best first, setting their scores.
+#[allow(async_fn_in_trait)]
+pub trait Reranker {
+    async fn rerank(&self, query: &str, memories: Vec<Memory>) -> Result<Vec<Memory>, MemoryError>;
+}
+
+/// Removes memories that must not be returned, e.g. unsafe or off-policy content.
+#[allow(async_fn_in_trait)]
+pub trait Moderator {
+    async fn moderate(&self, memories: Vec<Memory>) -> Result<Vec<Memory>, MemoryError>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Stage {
+    QueryRewriter,
+    Reranker,
+    Moderation,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Stage::QueryRewriter => "query rewriter",
+            Stage::Reranker => "reranker",
+            Stage::Moderation => "moderation",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error("{stage} failed: {message}")]
+pub struct StageFailure {
+    pub stage: Stage,
+    pub message: String,
+}
+
+#[derive(Debug, Error)]
+pub enum RecallPipelineError {
+    #[error("Retrieval failed: {0}")]
+    RetrievalError(String),
+    #[error("Required stage {0}")]
+    StageError(StageFailure),
+}
+
+#[derive(Debug, Clone)]
+pub struct RecallOutcome {
+    pub memories: Vec<Memory>,
+    /// Optional stages that failed and were skipped.
+    pub degraded: Vec<StageFailure>,
+    /// Stages the [`StagePolicy`] decided not to run.
+    pub skipped: Vec<Stage>,
+    /// Set with [`RecallPipeline::with_trace_export`].
+    pub trace: Option<RetrievalTrace>,
+}
+
+// Object-safe views of the stages, so the pipeline is not generic over each of them
+trait DynQueryRewriter {
+    fn rewrite_dyn<'a>(&'a self, query: &'a str)
+        -> LocalBoxFuture<'a, Result<String, MemoryError>>;
+}
+
+impl<T: QueryRewriter> DynQueryRewriter for T {
+    fn rewrite_dyn<'a>(
+        &'a self,
+        query: &'a str,
+    ) -> LocalBoxFuture<'a, Result<String, MemoryError>> {
+        Box::pin(self.rewrite(query))
+    }
+}
+
+trait DynReranker {
+    fn rerank_dyn<'a>(
+        &'a self,
+        query: &'a str,
+        memories: Vec<Memory>,
+    ) -> LocalBoxFuture<'a, Result<Vec<Memory>, MemoryError>>;
+}
+
+impl<T: Reranker> DynReranker for T {
+    fn rerank_dyn<'a>(
+        &'a self,
+        query: &'a str,
+        memories: Vec<Memory>,
+    ) -> LocalBoxFuture<'a, Result<Vec<Memory>, MemoryError>> {
+        Box::pin(self.rerank(query, memories))
+    }
+}
+
+trait DynModerator {
+    fn moderate_dyn(
+        &self,
+        memories: Vec<Memory>,
+    ) -> LocalBoxFuture<'_, Result<Vec<Memory>, MemoryError>>;
+}
+
+impl<T: Moderator> DynModerator for T {
+    fn moderate_dyn(
+        &self,
+        memories: Vec<Memory>,
+    ) -> LocalBoxFuture<'_, Result<Vec<Memory>, MemoryError>> {
+        Box::pin(self.moderate(memories))
+    }
+}
+
+type DegradedCallback = Box<dyn Fn(&StageFailure)>;
+
+struct Optional<T: ?Sized> {
+    stage: Box<T>,
+    required: bool,
+}
+
+/// [`Retriever`] wrapping another with optional query rewriting, reranking and
+/// moderation. A failing stage configured with `required: false` is logged, reported to
+/// the [`on_degraded`](Self::on_degraded) callback and skipped, so recall falls through
+/// to the unenhanced result instead of failing; a failing required stage fails the
+/// recall. Note that skipping moderation returns unmoderated memories.
+pub struct RecallPipeline<R: Retriever> {
+    retriever: R,
+    rewriter: Option<Optional<dyn DynQueryRewriter>>,
+    reranker: Option<Optional<dyn DynReranker>>,
+    moderator: Option<Optional<dyn DynModerator>>,
+    on_degraded: Option<DegradedCallback>,
+    policy: Option<StagePolicy>,
+    trace_export: Option<TraceExport>,
+}
+
+impl<R: Retriever> RecallPipeline<R> {
+    pub fn new(retriever: R) -> Self {
+        Self {
+            retriever,
+            rewriter: None,
+            reranker: None,
+            moderator: None,
+            on_degraded: None,
+            policy: None,
+            trace_export: None,
+        }
+    }
+
+    pub fn with_query_rewriter(
+        mut self,
+        rewriter: impl QueryRewriter + 'static,
+        required: bool,
+    ) -> Self {
+        self.rewriter = Some(Optional {
+            stage: Box::new(rewriter),
+            required,
+        });
+        self
+    }
+
+    /// Reranks `limit * 4` candidates down to `limit`.
+    pub fn with_reranker(mut self, reranker: impl Reranker + 'static, required: bool) -> Self {
+        self.reranker = Some(Optional {
+            stage: Box::new(reranker),
+            required,
+        });
+        self
+    }
+
+    pub fn with_moderator(mut self, moderator: impl Moderator + 'static, required: bool) -> Self {
+        self.moderator = Some(Optional {
+            stage: Box::new(moderator),
+            required,
+        });
+        self
+    }
+
+    /// Called with every optional stage failure, e.g. to count degraded recalls.
+    pub fn on_degraded(mut self, callback: impl Fn(&StageFailure) + 'static) -> Self {
+        self.on_degraded = Some(Box::new(callback));
+        self
+    }
+
+    /// Lets `policy` skip expensive stages per query, e.g. to keep interactive recall
+    /// within a latency budget. Without a policy every configured stage runs.
+    pub fn with_policy(mut self, policy: StagePolicy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Traces every recall: the outcome carries a [`RetrievalTrace`] of the rewritten
+    /// query and the candidates after each stage, which is also written to `export`.
+    pub fn with_trace_export(mut self, export: TraceExport) -> Self {
+        self.trace_export = Some(export);
+        self
+    }
+
+    pub fn retriever(&self) -> &R {
+        &self.retriever
+    }
+
+    pub fn policy(&self) -> Option<&StagePolicy> {
+        self.policy.as_ref()
+    }
+
+    fn should_run(
+        &self,
+        stage: Stage,
+        required: bool,
+        class: QueryClass,
+        started: Instant,
+        remaining: Duration,
+        skipped: &mut Vec<Stage>,
+    ) -> bool {
+        let run = required
+            || self
+                .policy
+                .as_ref()
+                .is_none_or(|policy| policy.decide(stage, class, started.elapsed(), remaining));
+        if !run {
+            skipped.push(stage);
+        }
+        run
+    }
+
+    fn record(&self, stage: Stage, started: Instant) {
+        if let Some(policy) = &self.policy {
+            policy.record(stage, started.elapsed());
+        }
+    }
+
+    // Records the stage output, or its failure if `settle` just added one
+    fn trace_stage(
+        trace: &mut Option<RetrievalTrace>,
+        stage: Stage,
+        memories: &[Memory],
+        started: Instant,
+        failure: Option<&StageFailure>,
+    ) {
+        let Some(trace) = trace else {
+            return;
+        };
+        match failure {
+            Some(failure) => {
+                trace.record_failure(stage.to_string(), &failure.message, started.elapsed())
+            }
+            None => trace.record_stage(stage.to_string(), memories, started.elapsed()),
+        }
+    }
+
+    // Returns the stage output, or `None` if an optional stage failed
+    fn settle<T>(
+        &self,
+        stage: Stage,
+        required: bool,
+        result: Result<T, MemoryError>,
+        degraded: &mut Vec<StageFailure>,
+    ) -> Result<Option<T>, RecallPipelineError> {
+        let error = match result {
+            Ok(value) => return Ok(Some(value)),
+            Err(e) => e,
+        };
+        let failure = StageFailure {
+            stage,
+            message: error.to_string(),
+        };
+        if required {
+            return Err(RecallPipelineError::StageError(failure));
+        }
+        log::warn!("Skipping {failure}");
+        if let Some(callback) = &self.on_degraded {
+            callback(&failure);
+        }
+        degraded.push(failure);
+        Ok(None)
+    }
+
+    /// Like [`Retriever::retrieve`], also reporting the optional stages that were skipped.
+    pub async fn recall(
+        &self,
+        query: &str,
+        limit: u64,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<RecallOutcome, RecallPipelineError> {
+        let started = Instant::now();
+        let class = QueryClass::classify(query);
+        let retrieval_estimate = self
+            .policy
+            .as_ref()
+            .map_or(Duration::ZERO, StagePolicy::estimated_retrieval_latency);
+        let mut degraded = Vec::new();
+        let mut skipped = Vec::new();
+        let mut trace = self
+            .trace_export
+            .as_ref()
+            .map(|_| RetrievalTrace::new(query));
+
+        let mut search_query = query.to_string();
+        if let Some(rewriter) = &self.rewriter {
+            if self.should_run(
+                Stage::QueryRewriter,
+                rewriter.required,
+                class,
+                started,
+                retrieval_estimate,
+                &mut skipped,
+            ) {
+                let stage_started = Instant::now();
+                let result = rewriter.stage.rewrite_dyn(query).await;
+                self.record(Stage::QueryRewriter, stage_started);
+                match self.settle(
+                    Stage::QueryRewriter,
+                    rewriter.required,
+                    result,
+                    &mut degraded,
+                )? {
+                    Some(rewritten) => {
+                        if let Some(trace) = &mut trace {
+                            trace.record_rewrite(&rewritten);
+                        }
+                        search_query = rewritten;
+                    }
+                    None => Self::trace_stage(
+                        &mut trace,
+                        Stage::QueryRewriter,
+                        &[],
+                        stage_started,
+                        degraded.last(),
+                    ),
+                }
+            }
+        }
+
+        // Decided before retrieval, which overfetches for the reranker
+        let reranker = self.reranker.as_ref().filter(|reranker| {
+            self.should_run(
+                Stage::Reranker,
+                reranker.required,
+                class,
+                started,
+                retrieval_estimate,
+                &mut skipped,
+            )
+        });
+        let candidates = match reranker {
+            Some(_) => limit * RERANK_OVERFETCH,
+            None => limit,
+        };
+        let retrieval_started = Instant::now();
+        let mut memories = self
+            .retriever
+            .retrieve(&search_query, candidates, filter)
+            .await
+            .map_err(|e| RecallPipelineError::RetrievalError(e.to_string()))?;
+        if let Some(policy) = &self.policy {
+            policy.record_retrieval(retrieval_started.elapsed());
+        }
+        if let Some(trace) = &mut trace {
+            trace.record_stage("retrieval", &memories, retrieval_started.elapsed());
+        }
+
+        if let Some(reranker) = reranker {
+            let stage_started = Instant::now();
+            let result = reranker.stage.rerank_dyn(query, memories.clone()).await;
+            self.record(Stage::Reranker, stage_started);
+            let failure =
+                match self.settle(Stage::Reranker, reranker.required, result, &mut degraded)? {
+                    Some(reranked) => {
+                        memories = reranked;
+                        None
+                    }
+                    None => degraded.last(),
+                };
+            memories.truncate(limit as usize);
+            Self::trace_stage(
+                &mut trace,
+                Stage::Reranker,
+                &memories,
+                stage_started,
+                failure,
+            );
+        }
+        memories.truncate(limit as usize);
+
+        if let Some(moderator) = &self.moderator {
+            let stage_started = Instant::now();
+            let result = moderator.stage.moderate_dyn(memories.clone()).await;
+            self.record(Stage::Moderation, stage_started);
+            let failure =
+                match self.settle(Stage::Moderation, moderator.required, result, &mut degraded)? {
+                    Some(moderated) => {
+                        memories = moderated;
+                        None
+                    }
+                    None => degraded.last(),
+                };
+            Self::trace_stage(
+                &mut trace,
+                Stage::Moderation,
+                &memories,
+                stage_started,
+                failure,
+            );
+        }
+
+        if let (Some(export), Some(trace)) = (&self.trace_export, &trace) {
+            export.export(trace);
+        }
+        Ok(RecallOutcome {
+            memories,
+            degraded,
+            skipped,
+            trace,
+        })
+    }
+}
+
+impl<R: Retriever> Retriever for RecallPipeline<R> {
+    type Error = RecallPipelineError;
+
+    async fn retrieve(
+        &self,
+        query: &str,
+        limit: u64,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<Memory>, RecallPipelineError> {
+        Ok(self.recall(query, limit, filter).await?.memories)
+    }
+}
+
+/// Asks an LLM to rewrite the query.
+pub struct LlmQueryRewriter<C: LlmClientChat> {
+    llm_client: C,
+    model: String,
+}
+
+impl<C: LlmClientChat> LlmQueryRewriter<C> {
+    pub fn new(llm_client: C, model: impl Into<String>) -> Self {
+        Self {
+            llm_client,
+            model: model.into(),
+        }
+    }
+}
+
+impl<C: LlmClientChat> QueryRewriter for LlmQueryRewriter<C> {
+    async fn rewrite(&self, query: &str) -> Result<String, MemoryError> {
+        let response = self
+            .llm_client
+            .send_message(
+                &self.model,
+                format!("{REWRITE_PROMPT}{query}"),
+                None::<&str>,
+                Some(0.0),
+            )
+            .await
+            .map_err(|e| MemoryError::LlmError(e.to_string()))?;
+        let rewritten = response.trim();
+        if rewritten.is_empty() {
+            return Err(MemoryError::LlmError("empty query rewrite".to_string()));
+        }
+        Ok(rewritten.to_string())
+    }
+}
+
+/// Asks an LLM to rate the relevance of each candidate from 1 to 10, concurrently. The
+/// returned scores are the ratings divided by 10.
+pub struct LlmReranker<C: LlmClientChat> {
+    llm_client: C,
+    model: String,
+}
+
+impl<C: LlmClientChat> LlmReranker<C> {
+    pub fn new(llm_client: C, model: impl Into<String>) -> Self {
+        Self {
+            llm_client,
+            model: model.into(),
+        }
+    }
+
+    async fn rate(&self, query: &str, memory: &Memory) -> Result<u8, MemoryError> {
+        let response = self
+            .llm_client
+            .send_message(
+                &self.model,
+                format!("{RERANK_PROMPT}\n\nQuery: {query}\nMemory: {}", memory.text),
+                None::<&str>,
+                Some(0.0),
+            )
+            .await
+            .map_err(|e| MemoryError::LlmError(e.to_string()))?;
+        parse_score(&response).ok_or_else(|| {
+            MemoryError::LlmError(format!("unparsable relevance response: {response}"))
+        })
+    }
+}
+
+impl<C: LlmClientChat> Reranker for LlmReranker<C> {
+    async fn rerank(&self, query: &str, memories: Vec<Memory>) -> Result<Vec<Memory>, MemoryError> {
+        let ratings = try_join_all(memories.iter().map(|memory| self.rate(query, memory))).await?;
+        let mut memories: Vec<Memory> = memories
+            .into_iter()
+            .zip(ratings)
+            .map(|(memory, rating)| Memory {
+                score: rating as f32 / 10.0,
+                ..memory
+            })
+            .collect();
+        // Stable, so equally rated memories keep their retrieval order
+        memories.sort_by(|a, b| b.score.total_cmp(&a.score));
+        Ok(memories)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Map;
+    use std::cell::RefCell;
+    use std::convert::Infallible;
+    use std::rc::Rc;
+
+    struct Fixed;
+
+    impl Retriever for Fixed {
+        type Error = Infallible;
+
+        async fn retrieve(
+            &self,
+            query: &str,
+            limit: u64,
+            _filter: Option<&MetadataFilter>,
+        ) -> Result<Vec<Memory>, Infallible> {
+            Ok((0..limit)
+                .map(|idx| Memory {
+                    id: format!("{query}-{idx}"),
+                    text: String::new(),
+                    metadata: Map::new(),
+                    score: 1.0 - idx as f32 / 10.0,
+                })
+                .collect())
+        }
+    }
+
+    struct Failing;
+
+    impl QueryRewriter for Failing {
+        async fn rewrite(&self, _query: &str) -> Result<String, MemoryError> {
+            Err(MemoryError::LlmError("timeout".to_string()))
+        }
+    }
+
+    impl Reranker for Failing {
+        async fn rerank(
+            &self,
+            _query: &str,
+            _memories: Vec<Memory>,
+        ) -> Result<Vec<Memory>, MemoryError> {
+            Err(MemoryError::LlmError("timeout".to_string()))
+        }
+    }
+
+    struct Reverse;
+
+    impl Reranker for Reverse {
+        async fn rerank(
+            &self,
+            _query: &str,
+            mut memories: Vec<Memory>,
+        ) -> Result<Vec<Memory>, MemoryError> {
+            memories.reverse();
+            Ok(memories)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_optional_stages_degrade() {
+        let failures = Rc::new(RefCell::new(Vec::new()));
+        let seen = failures.clone();
+        let pipeline = RecallPipeline::new(Fixed)
+            .with_query_rewriter(Failing, false)
+            .with_reranker(Failing, false)
+            .on_degraded(move |failure| seen.borrow_mut().push(failure.stage));
+
+        let outcome = pipeline.recall("boots", 2, None).await.unwrap();
+        let ids: Vec<&str> = outcome.memories.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, vec!["boots-0", "boots-1"]);
+        assert_eq!(outcome.degraded.len(), 2);
+        assert_eq!(
+            *failures.borrow(),
+            vec![Stage::QueryRewriter, Stage::Reranker]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_required_stage_fails_recall() {
+        let pipeline = RecallPipeline::new(Fixed).with_reranker(Failing, true);
+        let error = pipeline.retrieve("boots", 2, None).await.unwrap_err();
+        assert!(
+            matches!(error, RecallPipelineError::StageError(ref failure) if failure.stage == Stage::Reranker)
+        );
+
+        // Reranks the overfetched candidates and keeps the best `limit`
+        let pipeline = RecallPipeline::new(Fixed).with_reranker(Reverse, true);
+        let memories = pipeline.retrieve("boots", 2, None).await.unwrap();
+        assert_eq!(memories[0].id, "boots-7");
+    }
+
+    #[tokio::test]
+    async fn test_trace_export() {
+        let dir = std::env::temp_dir().join(format!("traces-{}", uuid::Uuid::new_v4()));
+        let pipeline = RecallPipeline::new(Fixed)
+            .with_query_rewriter(Failing, false)
+            .with_reranker(Reverse, true)
+            .with_trace_export(TraceExport::new(&dir));
+
+        let outcome = crate::request_id::scope("req-7", pipeline.recall("boots", 2, None))
+            .await
+            .unwrap();
+        let trace = TraceExport::read(dir.join("req-7.json")).unwrap();
+        // Stage timings do not survive the JSON round trip bit for bit
+        assert_eq!(trace.stages.len(), outcome.trace.unwrap().stages.len());
+        let stages: Vec<&str> = trace.stages.iter().map(|s| s.stage.as_str()).collect();
+        assert_eq!(stages, vec!["query rewriter", "retrieval", "reranker"]);
+        assert_eq!(trace.stages[0].error.as_deref(), Some("LLM Error: timeout"));
+        assert_eq!(trace.stage("retrieval").unwrap().candidates.len(), 8);
+        let reranked: Vec<&str> = trace.stages[2]
+            .candidates
+            .iter()
+            .map(|c| c.id.as_str())
+            .collect();
+        assert_eq!(reranked, vec!["boots-7", "boots-6"]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_policy_skips_reranker_for_lookups() {
+        let pipeline = RecallPipeline::new(Fixed)
+            .with_reranker(Reverse, false)
+            .with_policy(StagePolicy::interactive());
+
+        let outcome = pipeline.recall("boots", 2, None).await.unwrap();
+        assert_eq!(outcome.skipped, vec![Stage::Reranker]);
+        assert_eq!(outcome.memories[0].id, "boots-0");
+
+        let question = "which boots suit a rainy hike";
+        let outcome = pipeline.recall(question, 2, None).await.unwrap();
+        assert!(outcome.skipped.is_empty());
+        assert_eq!(outcome.memories[0].id, format!("{question}-7"));
+
+        // A required reranker runs even for lookups
+        let pipeline = RecallPipeline::new(Fixed)
+            .with_reranker(Reverse, true)
+            .with_policy(StagePolicy::interactive());
+        let outcome = pipeline.recall("boots", 2, None).await.unwrap();
+        assert!(outcome.skipped.is_empty());
+        assert_eq!(outcome.memories[0].id, "boots-7");
+    }
+}
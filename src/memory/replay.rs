@@ -0,0 +1,184 @@
+use crate::memory::recall_pipeline::{RecallPipeline, RecallPipelineError};
+use crate::memory::retriever::Retriever;
+use crate::memory::trace::{RetrievalTrace, TraceExport};
+use serde::Serialize;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    #[error("Reading trace failed: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Replayed recall failed: {0}")]
+    RecallError(#[from] RecallPipelineError),
+}
+
+/// How the result of a recorded query changed under a new pipeline.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReplayDiff {
+    pub request_id: String,
+    pub query: String,
+    /// Ids of the recorded result, best first.
+    pub before: Vec<String>,
+    /// Ids returned by the new pipeline, best first.
+    pub after: Vec<String>,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl ReplayDiff {
+    fn new(trace: &RetrievalTrace, before: Vec<String>, after: Vec<String>) -> Self {
+        let added = after
+            .iter()
+            .filter(|id| !before.contains(id))
+            .cloned()
+            .collect();
+        let removed = before
+            .iter()
+            .filter(|id| !after.contains(id))
+            .cloned()
+            .collect();
+        Self {
+            request_id: trace.request_id.clone(),
+            query: trace.query.clone(),
+            before,
+            after,
+            added,
+            removed,
+        }
+    }
+
+    /// Same memories in the same order.
+    pub fn is_unchanged(&self) -> bool {
+        self.before == self.after
+    }
+
+    /// Share of the recorded memories still returned, 1 for an empty recorded result.
+    pub fn overlap(&self) -> f32 {
+        if self.before.is_empty() {
+            return 1.0;
+        }
+        1.0 - self.removed.len() as f32 / self.before.len() as f32
+    }
+}
+
+// Ids of the last stage that produced candidates: what the recorded request returned
+fn recorded_result(trace: &RetrievalTrace) -> Vec<String> {
+    trace
+        .stages
+        .iter()
+        .rev()
+        .find(|stage| stage.error.is_none())
+        .map(|stage| stage.candidates.iter().map(|c| c.id.clone()).collect())
+        .unwrap_or_default()
+}
+
+/// Re-runs the query of a recorded trace (see [`TraceExport`]) through `pipeline`,
+/// asking for as many memories as the recording returned, and diffs the two results.
+/// Filters are not recorded, so the query runs unfiltered.
+pub async fn replay<R: Retriever>(
+    trace: impl AsRef<Path>,
+    pipeline: &RecallPipeline<R>,
+) -> Result<ReplayDiff, ReplayError> {
+    replay_trace(&TraceExport::read(trace)?, pipeline).await
+}
+
+/// Like [`replay`], for a trace already read.
+pub async fn replay_trace<R: Retriever>(
+    trace: &RetrievalTrace,
+    pipeline: &RecallPipeline<R>,
+) -> Result<ReplayDiff, ReplayError> {
+    let before = recorded_result(trace);
+    let limit = before.len().max(1) as u64;
+    let outcome = pipeline.recall(&trace.query, limit, None).await?;
+    let after = outcome.memories.into_iter().map(|m| m.id).collect();
+    Ok(ReplayDiff::new(trace, before, after))
+}
+
+/// [`replay`]s every trace in the directory of `export`, in file name order.
+pub async fn replay_all<R: Retriever>(
+    export: &TraceExport,
+    pipeline: &RecallPipeline<R>,
+) -> Result<Vec<ReplayDiff>, ReplayError> {
+    let mut paths: Vec<_> = std::fs::read_dir(export.dir())?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    paths.retain(|path| path.extension().is_some_and(|ext| ext == "json"));
+    paths.sort();
+    let mut diffs = Vec::new();
+    for path in paths {
+        diffs.push(replay(path, pipeline).await?);
+    }
+    Ok(diffs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::memory_store::{Memory, MemoryError};
+    use crate::memory::recall_pipeline::Reranker;
+    use crate::vectorstore::filter::MetadataFilter;
+    use serde_json::Map;
+    use std::convert::Infallible;
+
+    struct Catalog;
+
+    impl Retriever for Catalog {
+        type Error = Infallible;
+
+        async fn retrieve(
+            &self,
+            _query: &str,
+            limit: u64,
+            _filter: Option<&MetadataFilter>,
+        ) -> Result<Vec<Memory>, Infallible> {
+            Ok(["boots", "sandals", "loafers", "wellies"]
+                .into_iter()
+                .take(limit as usize)
+                .map(|id| Memory {
+                    id: id.to_string(),
+                    text: id.to_string(),
+                    metadata: Map::new(),
+                    score: 1.0,
+                })
+                .collect())
+        }
+    }
+
+    struct Reverse;
+
+    impl Reranker for Reverse {
+        async fn rerank(
+            &self,
+            _query: &str,
+            mut memories: Vec<Memory>,
+        ) -> Result<Vec<Memory>, MemoryError> {
+            memories.reverse();
+            Ok(memories)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_against_new_reranker() {
+        let dir = std::env::temp_dir().join(format!("replay-{}", uuid::Uuid::new_v4()));
+        let export = TraceExport::new(&dir);
+        let recorded = RecallPipeline::new(Catalog).with_trace_export(export.clone());
+        crate::request_id::scope("req-1", recorded.recall("rain boots", 2, None))
+            .await
+            .unwrap();
+
+        let unchanged = replay_all(&export, &RecallPipeline::new(Catalog))
+            .await
+            .unwrap();
+        assert_eq!(unchanged.len(), 1);
+        assert!(unchanged[0].is_unchanged());
+
+        let reranked = RecallPipeline::new(Catalog).with_reranker(Reverse, true);
+        let diff = replay(export.path_of("req-1"), &reranked).await.unwrap();
+        assert_eq!(diff.before, vec!["boots", "sandals"]);
+        assert_eq!(diff.after, vec!["wellies", "loafers"]);
+        assert_eq!(diff.removed, vec!["boots", "sandals"]);
+        assert_eq!(diff.overlap(), 0.0);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
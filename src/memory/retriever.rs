@@ -0,0 +1,182 @@
+use crate::embeddings::embedder::Embedder;
+use crate::embeddings::text_embedding_inference::TextEmbeddingInference;
+use crate::memory::memory_store::{Memory, MemoryError, MemoryStore};
+use crate::vectorstore::filter::MetadataFilter;
+use crate::vectorstore::vector_store::{SearchHit, VectorStore, VectorStoreError};
+use futures::future::LocalBoxFuture;
+use qdrant_client::qdrant::Filter;
+use serde_json::Value as JsonValue;
+use std::error::Error;
+
+/// Text retrieval with structured filters, independent of the backend. Each backend
+/// translates the [`MetadataFilter`] to its native filter, so the same filter (e.g.
+/// "project X after date Y") selects the same memories everywhere.
+#[allow(async_fn_in_trait)]
+pub trait Retriever {
+    type Error: Error + Send + Sync + 'static;
+
+    /// Returns the `limit` memories most relevant to `query` among those matching `filter`.
+    async fn retrieve(
+        &self,
+        query: &str,
+        limit: u64,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<Memory>, Self::Error>;
+}
+
+// Object-safe view of a `Retriever`, so combinators can mix different backends
+pub(crate) trait DynRetriever {
+    fn retrieve_dyn<'a>(
+        &'a self,
+        query: &'a str,
+        limit: u64,
+        filter: Option<&'a MetadataFilter>,
+    ) -> LocalBoxFuture<'a, Result<Vec<Memory>, String>>;
+}
+
+impl<R: Retriever> DynRetriever for R {
+    fn retrieve_dyn<'a>(
+        &'a self,
+        query: &'a str,
+        limit: u64,
+        filter: Option<&'a MetadataFilter>,
+    ) -> LocalBoxFuture<'a, Result<Vec<Memory>, String>> {
+        Box::pin(async move {
+            self.retrieve(query, limit, filter)
+                .await
+                .map_err(|e| e.to_string())
+        })
+    }
+}
+
+impl Retriever for MemoryStore {
+    type Error = MemoryError;
+
+    async fn retrieve(
+        &self,
+        query: &str,
+        limit: u64,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<Memory>, MemoryError> {
+        match filter {
+            Some(filter) => {
+                self.recall_filtered(query, limit, Filter::from(filter))
+                    .await
+            }
+            None => self.recall(query, limit).await,
+        }
+    }
+}
+
+/// [`Retriever`] over any [`VectorStore`] collection whose payloads store the text in
+/// the `text` field, as written by this crate.
+pub struct VectorStoreRetriever<S: VectorStore, E: Embedder = TextEmbeddingInference> {
+    store: S,
+    embedder: E,
+    collection_name: String,
+}
+
+impl<S: VectorStore, E: Embedder> VectorStoreRetriever<S, E> {
+    pub fn new(store: S, embedder: E, collection_name: impl Into<String>) -> Self {
+        Self {
+            store,
+            embedder,
+            collection_name: collection_name.into(),
+        }
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+}
+
+fn hit_to_memory(hit: SearchHit) -> Memory {
+    let mut metadata = hit.payload;
+    let text = match metadata.remove("text") {
+        Some(JsonValue::String(text)) => text,
+        _ => String::new(),
+    };
+    Memory {
+        id: hit.id,
+        text,
+        metadata,
+        score: hit.score,
+    }
+}
+
+impl<S: VectorStore, E: Embedder> Retriever for VectorStoreRetriever<S, E> {
+    type Error = VectorStoreError;
+
+    async fn retrieve(
+        &self,
+        query: &str,
+        limit: u64,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<Memory>, VectorStoreError> {
+        let embedding = self
+            .embedder
+            .embed(vec![query.to_string()])
+            .await
+            .map_err(|e| VectorStoreError::EmbeddingError(e.to_string()))?
+            .pop()
+            .ok_or_else(|| {
+                VectorStoreError::EmbeddingError("empty embedding response".to_string())
+            })?;
+        let hits = self
+            .store
+            .query_filtered(&self.collection_name, embedding, limit, filter)
+            .await?;
+        Ok(hits.into_iter().map(hit_to_memory).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vectorstore::filter::FilterBuilder;
+    use crate::vectorstore::in_memory::InMemoryVectorStore;
+    use crate::vectorstore::vector_store::VectorPoint;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_vector_store_retriever_filters() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/embed")
+            .with_body("[[1.0, 0.0]]")
+            .create_async()
+            .await;
+
+        let store = InMemoryVectorStore::new();
+        let points = [
+            ("a", "x", "2024-06-01T00:00:00Z"),
+            ("b", "x", "2025-06-01T00:00:00Z"),
+            ("c", "y", "2025-06-01T00:00:00Z"),
+        ]
+        .into_iter()
+        .map(|(id, project, timestamp)| {
+            let payload = json!({"text": id, "project": project, "timestamp": timestamp});
+            VectorPoint::new(id, vec![1.0, 0.0], payload.as_object().unwrap().clone())
+        })
+        .collect();
+        store.upsert("memories", points).await.unwrap();
+
+        let retriever = VectorStoreRetriever::new(
+            store,
+            TextEmbeddingInference::new(Some(&server.url())),
+            "memories",
+        );
+        let filter = FilterBuilder::new()
+            .eq("project", "x")
+            .after("timestamp", "2025-01-01T00:00:00Z".parse().unwrap())
+            .build();
+        let memories = retriever
+            .retrieve("boots", 10, Some(&filter))
+            .await
+            .unwrap();
+        assert_eq!(memories.len(), 1);
+        assert_eq!(memories[0].text, "b");
+        assert_eq!(memories[0].metadata["project"], "x");
+        mock.assert_async().await;
+    }
+}
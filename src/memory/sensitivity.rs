@@ -0,0 +1,87 @@
+use qdrant_client::qdrant::{Condition, Filter};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value as JsonValue};
+
+/// Payload field holding the [`Sensitivity`] of a memory. Memories without it are public.
+pub const SENSITIVITY_FIELD: &str = "sensitivity";
+
+/// How private a memory is, and, as an access level, the most private memories a caller
+/// may recall. Levels are ordered: `Public < Internal < Secret`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sensitivity {
+    #[default]
+    Public,
+    Internal,
+    Secret,
+}
+
+impl Sensitivity {
+    const ALL: [Sensitivity; 3] = [Self::Public, Self::Internal, Self::Secret];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Public => "public",
+            Self::Internal => "internal",
+            Self::Secret => "secret",
+        }
+    }
+
+    /// Filter excluding every memory more sensitive than this access level, or `None` when
+    /// nothing has to be excluded.
+    pub fn access_filter(&self) -> Option<Filter> {
+        let denied: Vec<String> = Self::ALL
+            .iter()
+            .filter(|level| *level > self)
+            .map(|level| level.as_str().to_string())
+            .collect();
+        if denied.is_empty() {
+            return None;
+        }
+        Some(Filter::must_not([Condition::matches(
+            SENSITIVITY_FIELD,
+            denied,
+        )]))
+    }
+
+    /// Sets the sensitivity field of a memory's metadata.
+    pub fn apply(&self, metadata: &mut Map<String, JsonValue>) {
+        metadata.insert(SENSITIVITY_FIELD.to_string(), json!(self.as_str()));
+    }
+}
+
+/// Combines an optional caller filter with the access filter of `access`.
+pub fn restrict(filter: Option<Filter>, access: Sensitivity) -> Option<Filter> {
+    match (filter, access.access_filter()) {
+        (filter, None) => filter,
+        (None, access_filter) => access_filter,
+        (Some(filter), Some(access_filter)) => Some(Filter::must([
+            Condition::from(filter),
+            Condition::from(access_filter),
+        ])),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_access_filter() {
+        assert!(Sensitivity::Secret.access_filter().is_none());
+        let filter = Sensitivity::Public.access_filter().unwrap();
+        assert_eq!(filter.must_not.len(), 1);
+        assert_eq!(
+            serde_json::from_str::<Sensitivity>("\"internal\"").unwrap(),
+            Sensitivity::Internal
+        );
+    }
+
+    #[test]
+    fn test_restrict() {
+        assert!(restrict(None, Sensitivity::Secret).is_none());
+        let caller = Filter::must([Condition::matches("kind", "fact".to_string())]);
+        let combined = restrict(Some(caller), Sensitivity::Internal).unwrap();
+        assert_eq!(combined.must.len(), 2);
+    }
+}
@@ -0,0 +1,136 @@
+use crate::memory::memory_store::{MemoryError, MemoryStore};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// What [`MemoryStore::shutdown`] left behind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ShutdownReport {
+    /// Every in-flight operation finished before the deadline.
+    pub drained: bool,
+    /// Operations still running at the deadline; their writes may or may not land.
+    pub abandoned: usize,
+}
+
+// Whether the store accepts new operations, and how many are running
+#[derive(Default)]
+pub(crate) struct Lifecycle {
+    closed: AtomicBool,
+    in_flight: AtomicUsize,
+    idle: Notify,
+}
+
+impl Lifecycle {
+    async fn wait_idle(&self) {
+        loop {
+            let notified = self.idle.notified();
+            tokio::pin!(notified);
+            // Registers before the check, so a decrement in between is not missed
+            notified.as_mut().enable();
+            if self.in_flight.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// Counts an operation as in flight until dropped.
+pub(crate) struct OperationGuard<'a> {
+    lifecycle: &'a Lifecycle,
+}
+
+impl Drop for OperationGuard<'_> {
+    fn drop(&mut self) {
+        if self.lifecycle.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.lifecycle.idle.notify_waiters();
+        }
+    }
+}
+
+impl MemoryStore {
+    /// Starts an operation, failing with [`MemoryError::ShuttingDown`] once
+    /// [`shutdown`](Self::shutdown) was called.
+    pub(crate) fn begin_operation(&self) -> Result<OperationGuard<'_>, MemoryError> {
+        if self.lifecycle().closed.load(Ordering::SeqCst) {
+            return Err(MemoryError::ShuttingDown);
+        }
+        Ok(self.track_operation())
+    }
+
+    /// Counts an operation as in flight even after shutdown began, for work finishing what
+    /// was started before, e.g. a transaction commit.
+    pub(crate) fn track_operation(&self) -> OperationGuard<'_> {
+        let lifecycle = self.lifecycle();
+        lifecycle.in_flight.fetch_add(1, Ordering::SeqCst);
+        OperationGuard { lifecycle }
+    }
+
+    pub fn is_shut_down(&self) -> bool {
+        self.lifecycle().closed.load(Ordering::SeqCst)
+    }
+
+    /// Stops accepting new recalls and writes, which fail with
+    /// [`MemoryError::ShuttingDown`], waits up to `deadline` for those in flight (open
+    /// transactions can still commit or roll back) and drops the recall cache. Call it
+    /// before a service restart so no memory is left half-written; writes are sent to
+    /// Qdrant right away, so there is nothing else to flush.
+    pub async fn shutdown(&self, deadline: Duration) -> ShutdownReport {
+        let lifecycle = self.lifecycle();
+        lifecycle.closed.store(true, Ordering::SeqCst);
+        let drained = tokio::time::timeout(deadline, lifecycle.wait_idle())
+            .await
+            .is_ok();
+        if let Some(cache) = self.recall_cache() {
+            cache.clear();
+        }
+        ShutdownReport {
+            drained,
+            abandoned: lifecycle.in_flight.load(Ordering::SeqCst),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embeddings::text_embedding_inference::TextEmbeddingInference;
+    use crate::vectorstore::qdrant_client::QdrantClient;
+
+    #[tokio::test]
+    async fn test_shutdown() {
+        let store = MemoryStore::new(
+            QdrantClient::new("http://localhost:6334"),
+            TextEmbeddingInference::new(None),
+            "memories",
+        );
+        let guard = store.begin_operation().unwrap();
+        let report = store.shutdown(Duration::from_millis(10)).await;
+        assert_eq!(
+            report,
+            ShutdownReport {
+                drained: false,
+                abandoned: 1,
+            }
+        );
+        assert!(store.is_shut_down());
+        assert!(matches!(
+            store.remember("Size 42", None).await,
+            Err(MemoryError::ShuttingDown)
+        ));
+
+        // Finishes while a second shutdown waits for it
+        let (report, ()) = tokio::join!(store.shutdown(Duration::from_secs(5)), async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            drop(guard);
+        });
+        assert_eq!(
+            report,
+            ShutdownReport {
+                drained: true,
+                abandoned: 0,
+            }
+        );
+    }
+}
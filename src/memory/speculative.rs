@@ -0,0 +1,229 @@
+use crate::memory::memory_store::Memory;
+use crate::memory::retriever::{DynRetriever, Retriever};
+use crate::vectorstore::filter::MetadataFilter;
+use futures::stream::{FuturesUnordered, StreamExt};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+#[error("All strategies failed: {}", describe(failures))]
+pub struct SpeculativeError {
+    /// Strategy names and their errors.
+    pub failures: Vec<(String, String)>,
+}
+
+fn describe(failures: &[(String, String)]) -> String {
+    failures
+        .iter()
+        .map(|(name, e)| format!("{name}: {e}"))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// When a result set is good enough to stop waiting for the other strategies.
+#[derive(Debug, Clone, Copy)]
+pub struct QualityHeuristic {
+    pub min_results: usize,
+    /// Minimum score of the best result, on the strategy's own score scale.
+    pub min_top_score: f32,
+}
+
+impl Default for QualityHeuristic {
+    fn default() -> Self {
+        Self {
+            min_results: 1,
+            min_top_score: 0.5,
+        }
+    }
+}
+
+impl QualityHeuristic {
+    pub fn passes(&self, memories: &[Memory]) -> bool {
+        memories.len() >= self.min_results
+            && memories
+                .iter()
+                .map(|memory| memory.score)
+                .fold(f32::NEG_INFINITY, f32::max)
+                >= self.min_top_score
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SpeculativeOutcome {
+    pub memories: Vec<Memory>,
+    /// Name of the strategy whose results were returned.
+    pub strategy: String,
+    /// Whether the results passed the quality heuristic.
+    pub passed: bool,
+}
+
+struct Strategy {
+    name: String,
+    retriever: Box<dyn DynRetriever>,
+}
+
+/// [`Retriever`] that runs several strategies concurrently (e.g. dense-only and
+/// hybrid with reranking) and returns the first result set passing a quality heuristic,
+/// dropping the strategies still running. If none passes, the results of the earliest
+/// added strategy that succeeded are returned, so order strategies by quality.
+pub struct SpeculativeRetriever {
+    strategies: Vec<Strategy>,
+    quality: QualityHeuristic,
+}
+
+impl SpeculativeRetriever {
+    pub fn new(quality: QualityHeuristic) -> Self {
+        Self {
+            strategies: Vec::new(),
+            quality,
+        }
+    }
+
+    pub fn with_strategy(
+        mut self,
+        name: impl Into<String>,
+        retriever: impl Retriever + 'static,
+    ) -> Self {
+        self.strategies.push(Strategy {
+            name: name.into(),
+            retriever: Box::new(retriever),
+        });
+        self
+    }
+
+    /// Like [`Retriever::retrieve`], also reporting which strategy answered.
+    pub async fn speculate(
+        &self,
+        query: &str,
+        limit: u64,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<SpeculativeOutcome, SpeculativeError> {
+        let mut pending: FuturesUnordered<_> = self
+            .strategies
+            .iter()
+            .enumerate()
+            .map(|(idx, strategy)| async move {
+                let result = strategy.retriever.retrieve_dyn(query, limit, filter).await;
+                (idx, result)
+            })
+            .collect();
+
+        let mut fallback: Option<(usize, Vec<Memory>)> = None;
+        let mut failures = Vec::new();
+        while let Some((idx, result)) = pending.next().await {
+            match result {
+                Ok(memories) if self.quality.passes(&memories) => {
+                    return Ok(SpeculativeOutcome {
+                        memories,
+                        strategy: self.strategies[idx].name.clone(),
+                        passed: true,
+                    });
+                }
+                Ok(memories) => {
+                    if fallback.as_ref().is_none_or(|(best, _)| idx < *best) {
+                        fallback = Some((idx, memories));
+                    }
+                }
+                Err(e) => failures.push((self.strategies[idx].name.clone(), e)),
+            }
+        }
+
+        match fallback {
+            Some((idx, memories)) => Ok(SpeculativeOutcome {
+                memories,
+                strategy: self.strategies[idx].name.clone(),
+                passed: false,
+            }),
+            None => Err(SpeculativeError { failures }),
+        }
+    }
+}
+
+impl Retriever for SpeculativeRetriever {
+    type Error = SpeculativeError;
+
+    async fn retrieve(
+        &self,
+        query: &str,
+        limit: u64,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<Memory>, SpeculativeError> {
+        Ok(self.speculate(query, limit, filter).await?.memories)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Map;
+    use std::convert::Infallible;
+    use std::time::Duration;
+
+    struct Delayed {
+        delay_ms: u64,
+        score: f32,
+    }
+
+    impl Retriever for Delayed {
+        type Error = Infallible;
+
+        async fn retrieve(
+            &self,
+            _query: &str,
+            _limit: u64,
+            _filter: Option<&MetadataFilter>,
+        ) -> Result<Vec<Memory>, Infallible> {
+            tokio::time::sleep(Duration::from_millis(self.delay_ms)).await;
+            Ok(vec![Memory {
+                id: format!("{}", self.delay_ms),
+                text: String::new(),
+                metadata: Map::new(),
+                score: self.score,
+            }])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_first_good_answer() {
+        let retriever = SpeculativeRetriever::new(QualityHeuristic::default())
+            .with_strategy(
+                "hybrid",
+                Delayed {
+                    delay_ms: 50,
+                    score: 0.9,
+                },
+            )
+            .with_strategy(
+                "dense",
+                Delayed {
+                    delay_ms: 1,
+                    score: 0.8,
+                },
+            );
+        let outcome = retriever.speculate("boots", 5, None).await.unwrap();
+        assert_eq!(outcome.strategy, "dense");
+        assert!(outcome.passed);
+
+        // Nothing passes: the earliest added strategy wins
+        let retriever = SpeculativeRetriever::new(QualityHeuristic {
+            min_results: 1,
+            min_top_score: 0.95,
+        })
+        .with_strategy(
+            "hybrid",
+            Delayed {
+                delay_ms: 20,
+                score: 0.9,
+            },
+        )
+        .with_strategy(
+            "dense",
+            Delayed {
+                delay_ms: 1,
+                score: 0.8,
+            },
+        );
+        let outcome = retriever.speculate("boots", 5, None).await.unwrap();
+        assert_eq!(outcome.strategy, "hybrid");
+        assert!(!outcome.passed);
+    }
+}
@@ -0,0 +1,267 @@
+use crate::memory::recall_pipeline::Stage;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+// Queries with at most this many words and no question are treated as lookups
+const MAX_LOOKUP_WORDS: usize = 4;
+
+// A stage skipped this many times in a row for the latency budget runs once anyway, so
+// its latency estimate follows a provider that got faster
+const PROBE_EVERY: u64 = 20;
+
+// Weight of the latest run in a stage's latency estimate
+const LATENCY_WEIGHT: f64 = 0.2;
+
+const QUESTION_WORDS: [&str; 8] = [
+    "what", "why", "how", "when", "where", "which", "who", "should",
+];
+
+/// Coarse kind of query, used to skip expensive stages for simple lookups.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum QueryClass {
+    /// Short keyword lookup, e.g. "shoe size".
+    Lookup,
+    /// Question or longer query that benefits from rewriting and reranking.
+    Complex,
+}
+
+impl QueryClass {
+    pub fn classify(query: &str) -> Self {
+        let words: Vec<String> = query
+            .split_whitespace()
+            .map(|word| word.to_lowercase())
+            .collect();
+        let is_question = query.contains('?')
+            || words
+                .first()
+                .is_some_and(|word| QUESTION_WORDS.contains(&word.as_str()));
+        if words.len() <= MAX_LOOKUP_WORDS && !is_question {
+            QueryClass::Lookup
+        } else {
+            QueryClass::Complex
+        }
+    }
+}
+
+#[derive(Default)]
+struct LatencyCounters {
+    runs: AtomicU64,
+    skips: AtomicU64,
+    // Skips for the latency budget since the last run
+    over_budget: AtomicU64,
+    // Exponentially weighted moving average
+    latency_us: AtomicU64,
+}
+
+impl LatencyCounters {
+    fn record(&self, latency: Duration) {
+        let latency = latency.as_micros().min(u64::MAX as u128) as f64;
+        let first = self.runs.fetch_add(1, Ordering::Relaxed) == 0;
+        self.over_budget.store(0, Ordering::Relaxed);
+        let _ = self
+            .latency_us
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |mean| {
+                let mean = mean as f64;
+                if first {
+                    return Some(latency as u64);
+                }
+                Some((mean + LATENCY_WEIGHT * (latency - mean)) as u64)
+            });
+    }
+
+    fn mean(&self) -> Duration {
+        Duration::from_micros(self.latency_us.load(Ordering::Relaxed))
+    }
+}
+
+/// Decisions taken by a [`StagePolicy`] for one stage.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StageDecisionStats {
+    pub stage: Stage,
+    pub runs: u64,
+    pub skips: u64,
+    pub mean_latency_ms: f64,
+}
+
+/// Decides which optional stages of a [`RecallPipeline`](crate::memory::recall_pipeline::RecallPipeline)
+/// run for a query. A stage is skipped if the query is a lookup and the stage is not
+/// worth it for lookups, or if the recent latency of the stage and the work after it
+/// would exceed the latency budget; every 20th such skip in a row runs the stage anyway
+/// to measure it again. Required stages and moderation are never skipped.
+#[derive(Default)]
+pub struct StagePolicy {
+    latency_budget: Option<Duration>,
+    skip_for_lookups: Vec<Stage>,
+    stages: [LatencyCounters; 3],
+    retrieval: LatencyCounters,
+}
+
+fn counters_index(stage: Stage) -> usize {
+    match stage {
+        Stage::QueryRewriter => 0,
+        Stage::Reranker => 1,
+        Stage::Moderation => 2,
+    }
+}
+
+impl StagePolicy {
+    /// Runs every stage; decisions are still counted.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// For interactive recall: 300ms budget, no rewriting or reranking of lookups.
+    pub fn interactive() -> Self {
+        Self::new()
+            .with_latency_budget(Duration::from_millis(300))
+            .with_skip_for_lookups(&[Stage::QueryRewriter, Stage::Reranker])
+    }
+
+    pub fn with_latency_budget(mut self, budget: Duration) -> Self {
+        self.latency_budget = Some(budget);
+        self
+    }
+
+    pub fn with_skip_for_lookups(mut self, stages: &[Stage]) -> Self {
+        self.skip_for_lookups = stages.to_vec();
+        self
+    }
+
+    /// Moving average of the stage's latency, weighted towards recent runs; zero before
+    /// its first run.
+    pub fn estimated_latency(&self, stage: Stage) -> Duration {
+        self.stages[counters_index(stage)].mean()
+    }
+
+    pub fn estimated_retrieval_latency(&self) -> Duration {
+        self.retrieval.mean()
+    }
+
+    /// Whether to run `stage` when `elapsed` has been spent on the recall and the
+    /// mandatory work after the stage is expected to take `remaining`.
+    pub(crate) fn decide(
+        &self,
+        stage: Stage,
+        class: QueryClass,
+        elapsed: Duration,
+        remaining: Duration,
+    ) -> bool {
+        if stage == Stage::Moderation {
+            return true;
+        }
+        let not_worth_it = class == QueryClass::Lookup && self.skip_for_lookups.contains(&stage);
+        let counters = &self.stages[counters_index(stage)];
+        let over_budget = !not_worth_it
+            && self
+                .latency_budget
+                .is_some_and(|budget| elapsed + self.estimated_latency(stage) + remaining > budget);
+        let probe =
+            over_budget && counters.over_budget.fetch_add(1, Ordering::Relaxed) + 1 >= PROBE_EVERY;
+        let run = probe || (!not_worth_it && !over_budget);
+        if !run {
+            counters.skips.fetch_add(1, Ordering::Relaxed);
+        }
+        run
+    }
+
+    pub(crate) fn record(&self, stage: Stage, latency: Duration) {
+        self.stages[counters_index(stage)].record(latency);
+    }
+
+    pub(crate) fn record_retrieval(&self, latency: Duration) {
+        self.retrieval.record(latency);
+    }
+
+    pub fn stats(&self) -> Vec<StageDecisionStats> {
+        [Stage::QueryRewriter, Stage::Reranker, Stage::Moderation]
+            .into_iter()
+            .map(|stage| {
+                let counters = &self.stages[counters_index(stage)];
+                StageDecisionStats {
+                    stage,
+                    runs: counters.runs.load(Ordering::Relaxed),
+                    skips: counters.skips.load(Ordering::Relaxed),
+                    mean_latency_ms: counters.mean().as_secs_f64() * 1000.0,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(QueryClass::classify("shoe size"), QueryClass::Lookup);
+        assert_eq!(
+            QueryClass::classify("which boots suit a rainy hike"),
+            QueryClass::Complex
+        );
+        assert_eq!(QueryClass::classify("return policy?"), QueryClass::Complex);
+    }
+
+    #[test]
+    fn test_decide() {
+        let policy = StagePolicy::interactive();
+        assert!(!policy.decide(
+            Stage::Reranker,
+            QueryClass::Lookup,
+            Duration::ZERO,
+            Duration::ZERO
+        ));
+        assert!(policy.decide(
+            Stage::Reranker,
+            QueryClass::Complex,
+            Duration::ZERO,
+            Duration::ZERO
+        ));
+
+        // A reranker that takes 250ms does not fit after 100ms of retrieval
+        policy.record(Stage::Reranker, Duration::from_millis(250));
+        assert!(!policy.decide(
+            Stage::Reranker,
+            QueryClass::Complex,
+            Duration::ZERO,
+            Duration::from_millis(100)
+        ));
+        assert!(policy.decide(
+            Stage::Moderation,
+            QueryClass::Lookup,
+            Duration::from_secs(1),
+            Duration::ZERO
+        ));
+
+        let reranker = &policy.stats()[1];
+        assert_eq!((reranker.runs, reranker.skips), (1, 2));
+        assert!((reranker.mean_latency_ms - 250.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_probe_remeasures_skipped_stage() {
+        let policy = StagePolicy::new().with_latency_budget(Duration::from_millis(300));
+        policy.record(Stage::Reranker, Duration::from_millis(400));
+        let decide = || {
+            policy.decide(
+                Stage::Reranker,
+                QueryClass::Complex,
+                Duration::ZERO,
+                Duration::ZERO,
+            )
+        };
+        let runs: Vec<bool> = (0..PROBE_EVERY).map(|_| decide()).collect();
+        assert!(runs[..PROBE_EVERY as usize - 1].iter().all(|run| !run));
+        assert!(runs[PROBE_EVERY as usize - 1]);
+
+        // The reranker got faster: probes pull the estimate back under the budget
+        while !decide() {}
+        policy.record(Stage::Reranker, Duration::from_millis(50));
+        while policy.estimated_latency(Stage::Reranker) > Duration::from_millis(300) {
+            while !decide() {}
+            policy.record(Stage::Reranker, Duration::from_millis(50));
+        }
+        assert!(decide());
+    }
+}
@@ -0,0 +1,259 @@
+use crate::memory::memory_store::{Memory, MemoryError, MemoryStore};
+use crate::vectorstore::payload_schema::PayloadFields;
+use crate::vectorstore::qdrant_client::SOURCE_FIELD;
+use chrono::{DateTime, Utc};
+use qdrant_client::qdrant::Filter;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+/// Payload field grouping memories, e.g. by user or agent.
+pub const NAMESPACE_FIELD: &str = "namespace";
+
+const TOP_ACCESSED: usize = 10;
+
+// Key for memories without the grouped field
+const NONE_KEY: &str = "(none)";
+
+// Upper bounds of the age histogram buckets, in days
+const AGE_BUCKETS: [(&str, i64); 4] = [
+    ("< 1 day", 1),
+    ("1-7 days", 7),
+    ("7-30 days", 30),
+    ("30-365 days", 365),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AgeBucket {
+    pub label: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AccessedMemory {
+    pub id: String,
+    pub text: String,
+    pub accesses: u64,
+}
+
+/// What a [`MemoryStore`] holds, from [`MemoryStore::stats`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MemoryStats {
+    pub total: usize,
+    /// By `namespace` payload field.
+    pub by_namespace: BTreeMap<String, usize>,
+    /// By `source` payload field.
+    pub by_source: BTreeMap<String, usize>,
+    /// `image` for memories with an image path, `text` otherwise.
+    pub by_modality: BTreeMap<String, usize>,
+    /// By age of the `timestamp` payload field, youngest first; memories without a
+    /// timestamp are counted as `unknown`.
+    pub age_histogram: Vec<AgeBucket>,
+    /// Estimated bytes of payloads and dense vectors, without index overhead.
+    pub storage_bytes: u64,
+    /// Memories recalled most often through this store since it was created.
+    pub top_accessed: Vec<AccessedMemory>,
+}
+
+fn group_key(memory: &Memory, field: &str) -> String {
+    match memory.metadata.get(field) {
+        Some(JsonValue::String(value)) => value.clone(),
+        Some(JsonValue::Null) | None => NONE_KEY.to_string(),
+        Some(value) => value.to_string(),
+    }
+}
+
+fn age_bucket(memory: &Memory, fields: &PayloadFields, now: DateTime<Utc>) -> &'static str {
+    let Some(timestamp) = memory
+        .metadata
+        .get(&fields.timestamp)
+        .and_then(JsonValue::as_str)
+        .and_then(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok())
+    else {
+        return "unknown";
+    };
+    let age_days = (now - timestamp.with_timezone(&Utc)).num_days();
+    AGE_BUCKETS
+        .iter()
+        .find(|(_, max_days)| age_days < *max_days)
+        .map_or("> 1 year", |(label, _)| label)
+}
+
+impl MemoryStats {
+    pub(crate) fn from_memories(
+        memories: &[Memory],
+        access_counts: &HashMap<String, u64>,
+        vector_dimensions: u64,
+        fields: &PayloadFields,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let mut by_namespace = BTreeMap::new();
+        let mut by_source = BTreeMap::new();
+        let mut by_modality = BTreeMap::new();
+        let mut ages: HashMap<&str, usize> = HashMap::new();
+        let mut storage_bytes = 0;
+        for memory in memories {
+            *by_namespace
+                .entry(group_key(memory, NAMESPACE_FIELD))
+                .or_default() += 1;
+            *by_source
+                .entry(group_key(memory, SOURCE_FIELD))
+                .or_default() += 1;
+            let modality = if memory.metadata.contains_key(&fields.image_path) {
+                "image"
+            } else {
+                "text"
+            };
+            *by_modality.entry(modality.to_string()).or_default() += 1;
+            *ages.entry(age_bucket(memory, fields, now)).or_default() += 1;
+
+            let payload_bytes =
+                memory.text.len() + JsonValue::from(memory.metadata.clone()).to_string().len();
+            storage_bytes += payload_bytes as u64 + vector_dimensions * 4;
+        }
+
+        let age_histogram = AGE_BUCKETS
+            .iter()
+            .map(|(label, _)| *label)
+            .chain(["> 1 year", "unknown"])
+            .map(|label| AgeBucket {
+                label: label.to_string(),
+                count: ages.get(label).copied().unwrap_or(0),
+            })
+            .collect();
+
+        let mut top_accessed: Vec<AccessedMemory> = memories
+            .iter()
+            .filter_map(|memory| {
+                Some(AccessedMemory {
+                    id: memory.id.clone(),
+                    text: memory.text.clone(),
+                    accesses: *access_counts.get(&memory.id)?,
+                })
+            })
+            .collect();
+        top_accessed.sort_by(|a, b| b.accesses.cmp(&a.accesses).then_with(|| a.id.cmp(&b.id)));
+        top_accessed.truncate(TOP_ACCESSED);
+
+        Self {
+            total: memories.len(),
+            by_namespace,
+            by_source,
+            by_modality,
+            age_histogram,
+            storage_bytes,
+            top_accessed,
+        }
+    }
+
+    /// Plain-text summary for operators.
+    pub fn report(&self) -> String {
+        let mut report = format!(
+            "{} memories, ~{:.1} MiB\n",
+            self.total,
+            self.storage_bytes as f64 / (1024.0 * 1024.0)
+        );
+        for (title, counts) in [
+            ("By namespace", &self.by_namespace),
+            ("By source", &self.by_source),
+            ("By modality", &self.by_modality),
+        ] {
+            let _ = writeln!(report, "\n{title}:");
+            for (key, count) in counts {
+                let _ = writeln!(report, "  {key}: {count}");
+            }
+        }
+        report.push_str("\nBy age:\n");
+        for bucket in &self.age_histogram {
+            let _ = writeln!(report, "  {}: {}", bucket.label, bucket.count);
+        }
+        if !self.top_accessed.is_empty() {
+            report.push_str("\nMost recalled:\n");
+            for memory in &self.top_accessed {
+                let preview: String = memory.text.chars().take(60).collect();
+                let _ = writeln!(report, "  {}x {} {preview}", memory.accesses, memory.id);
+            }
+        }
+        report
+    }
+}
+
+impl MemoryStore {
+    /// Counts the memories visible at the store's access level by namespace, source,
+    /// modality and age, and estimates their storage. Scans the whole collection.
+    pub async fn stats(&self) -> Result<MemoryStats, MemoryError> {
+        let memories = self.memories_matching(Filter::default()).await?;
+        let vector_dimensions = self
+            .vectorstore()
+            .vector_sizes(self.collection_name())
+            .await?
+            .into_iter()
+            .map(|(_, size)| size)
+            .sum();
+        Ok(MemoryStats::from_memories(
+            &memories,
+            &self.access_counts(),
+            vector_dimensions,
+            self.vectorstore().payload_fields(),
+            Utc::now(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use serde_json::{json, Map};
+
+    fn memory(id: &str, metadata: JsonValue) -> Memory {
+        Memory {
+            id: id.to_string(),
+            text: format!("memory {id}"),
+            metadata: metadata.as_object().cloned().unwrap_or_else(Map::new),
+            score: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_from_memories() {
+        let now = Utc::now();
+        let memories = vec![
+            memory(
+                "a",
+                json!({"namespace": "alice", "timestamp": (now - Duration::hours(2)).to_rfc3339()}),
+            ),
+            memory(
+                "b",
+                json!({"namespace": "alice", "source": "catalog.pdf",
+                       "timestamp": (now - Duration::days(40)).to_rfc3339()}),
+            ),
+            memory("c", json!({"image_path": "images/boot.png"})),
+        ];
+        let access_counts = HashMap::from([("b".to_string(), 3), ("c".to_string(), 5)]);
+
+        let stats = MemoryStats::from_memories(
+            &memories,
+            &access_counts,
+            384,
+            &PayloadFields::default(),
+            now,
+        );
+        assert_eq!(stats.total, 3);
+        assert_eq!(stats.by_namespace["alice"], 2);
+        assert_eq!(stats.by_namespace["(none)"], 1);
+        assert_eq!(stats.by_source["catalog.pdf"], 1);
+        assert_eq!(stats.by_modality["image"], 1);
+        let counts: Vec<usize> = stats.age_histogram.iter().map(|b| b.count).collect();
+        assert_eq!(counts, vec![1, 0, 0, 1, 0, 1]);
+        assert!(stats.storage_bytes > 3 * 384 * 4);
+        let top: Vec<&str> = stats.top_accessed.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(top, vec!["c", "b"]);
+
+        let report = stats.report();
+        assert!(report.starts_with("3 memories"));
+        assert!(report.contains("  alice: 2\n"));
+        assert!(report.contains("  5x c memory c\n"));
+    }
+}
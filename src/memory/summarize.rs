@@ -0,0 +1,213 @@
+use crate::llm::llm_client::LlmClientChat;
+use crate::memory::memory_store::{Memory, MemoryError};
+use crate::utils::{chunk_text, estimate_tokens};
+use futures::stream::{self, StreamExt, TryStreamExt};
+
+const CHUNK_PROMPT: &str = "Summarize the following text in a few sentences, keeping names, \
+numbers and decisions. Answer with the summary only.\n\n";
+
+const REDUCE_PROMPT: &str = "The following are summaries of consecutive parts of one \
+document. Combine them into a single concise summary, keeping names, numbers and \
+decisions. Answer with the summary only.\n\n";
+
+const DIGEST_PROMPT: &str = "The following are memories an assistant stored recently, or \
+digests of them. Summarize what it learned as a short list: new facts and preferences, \
+documents and recurring topics. Answer with the summary only.\n\n";
+
+/// Condenses text, e.g. with an LLM.
+#[allow(async_fn_in_trait)]
+pub trait Summarizer {
+    async fn summarize(&self, text: &str) -> Result<String, MemoryError>;
+}
+
+/// Summarizes with an LLM prompt; [`LlmSummarizer::chunks`] and
+/// [`LlmSummarizer::reducer`] have prompts for the two steps of [`summarize_long`].
+pub struct LlmSummarizer<C: LlmClientChat> {
+    llm_client: C,
+    model: String,
+    prompt: String,
+}
+
+impl<C: LlmClientChat> LlmSummarizer<C> {
+    /// `prompt` is followed by the text to summarize.
+    pub fn new(llm_client: C, model: impl Into<String>, prompt: impl Into<String>) -> Self {
+        Self {
+            llm_client,
+            model: model.into(),
+            prompt: prompt.into(),
+        }
+    }
+
+    pub fn chunks(llm_client: C, model: impl Into<String>) -> Self {
+        Self::new(llm_client, model, CHUNK_PROMPT)
+    }
+
+    pub fn reducer(llm_client: C, model: impl Into<String>) -> Self {
+        Self::new(llm_client, model, REDUCE_PROMPT)
+    }
+
+    /// For [`DigestJob`](crate::memory::digest::DigestJob), both steps in one prompt.
+    pub fn digest(llm_client: C, model: impl Into<String>) -> Self {
+        Self::new(llm_client, model, DIGEST_PROMPT)
+    }
+}
+
+impl<C: LlmClientChat> Summarizer for LlmSummarizer<C> {
+    async fn summarize(&self, text: &str) -> Result<String, MemoryError> {
+        let response = self
+            .llm_client
+            .send_message(
+                &self.model,
+                format!("{}{text}", self.prompt),
+                None::<&str>,
+                Some(0.0),
+            )
+            .await
+            .map_err(|e| MemoryError::LlmError(e.to_string()))?;
+        Ok(response.trim().to_string())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SummarizeOptions {
+    /// Estimated tokens per chunk, and per group of summaries reduced together.
+    pub chunk_tokens: usize,
+    /// Summarizer calls in flight at once.
+    pub concurrency: usize,
+}
+
+impl Default for SummarizeOptions {
+    fn default() -> Self {
+        Self {
+            chunk_tokens: 2000,
+            concurrency: 4,
+        }
+    }
+}
+
+// Groups consecutive summaries into reducer inputs of at most `max_tokens`, at least two
+// per group so every round shrinks
+fn group_summaries(summaries: Vec<String>, max_tokens: usize) -> Vec<String> {
+    let mut groups: Vec<(String, usize)> = Vec::new();
+    for summary in summaries {
+        match groups.last_mut() {
+            Some((group, count))
+                if *count < 2
+                    || estimate_tokens(group) + estimate_tokens(&summary) <= max_tokens =>
+            {
+                group.push_str("\n\n");
+                group.push_str(&summary);
+                *count += 1;
+            }
+            _ => groups.push((summary, 1)),
+        }
+    }
+    groups.into_iter().map(|(group, _)| group).collect()
+}
+
+async fn summarize_all(
+    texts: Vec<String>,
+    summarizer: &impl Summarizer,
+    concurrency: usize,
+) -> Result<Vec<String>, MemoryError> {
+    stream::iter(texts)
+        .map(|text| async move { summarizer.summarize(&text).await })
+        .buffered(concurrency.max(1))
+        .try_collect()
+        .await
+}
+
+/// Map-reduce summary of a text of any length: the text is chunked, the chunks are
+/// summarized concurrently with `chunk_summarizer`, and the summaries are combined with
+/// `reducer` in groups that fit `chunk_tokens`, round after round, until one remains.
+/// A text that fits in one chunk takes a single `chunk_summarizer` call.
+pub async fn summarize_long(
+    text: &str,
+    chunk_summarizer: &impl Summarizer,
+    reducer: &impl Summarizer,
+    options: SummarizeOptions,
+) -> Result<String, MemoryError> {
+    let chunks = chunk_text(text, options.chunk_tokens);
+    let mut summaries = summarize_all(chunks, chunk_summarizer, options.concurrency).await?;
+    while summaries.len() > 1 {
+        let groups = group_summaries(summaries, options.chunk_tokens);
+        summaries = summarize_all(groups, reducer, options.concurrency).await?;
+    }
+    Ok(summaries.pop().unwrap_or_default())
+}
+
+/// Condenses recalled memories, best first, into one summary with [`summarize_long`].
+pub async fn summarize_memories(
+    memories: &[Memory],
+    chunk_summarizer: &impl Summarizer,
+    reducer: &impl Summarizer,
+    options: SummarizeOptions,
+) -> Result<String, MemoryError> {
+    let text = memories
+        .iter()
+        .map(|memory| memory.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    summarize_long(&text, chunk_summarizer, reducer, options).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Keeps the first word of every paragraph, counting calls
+    #[derive(Default)]
+    struct FirstWords(AtomicUsize);
+
+    impl Summarizer for FirstWords {
+        async fn summarize(&self, text: &str) -> Result<String, MemoryError> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Ok(text
+                .split("\n\n")
+                .filter_map(|paragraph| paragraph.split_whitespace().next())
+                .collect::<Vec<_>>()
+                .join(" "))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_summarize_long() {
+        let text = (1..=8)
+            .map(|idx| format!("Part{idx}-of-the-return-policy for boots and sandals."))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let chunks = FirstWords::default();
+        let reducer = FirstWords::default();
+        let options = SummarizeOptions {
+            chunk_tokens: 15,
+            concurrency: 2,
+        };
+
+        let summary = summarize_long(&text, &chunks, &reducer, options)
+            .await
+            .unwrap();
+        assert_eq!(chunks.0.load(Ordering::Relaxed), 8);
+        // 8 summaries reduced in pairs: 4 + 2 + 1 calls
+        assert_eq!(reducer.0.load(Ordering::Relaxed), 7);
+        assert_eq!(
+            summary,
+            "Part1-of-the-return-policy Part5-of-the-return-policy"
+        );
+
+        let short = FirstWords::default();
+        let summary = summarize_long("Boots.", &short, &reducer, options)
+            .await
+            .unwrap();
+        assert_eq!(summary, "Boots.");
+        assert_eq!(short.0.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_group_summaries() {
+        let summaries = vec!["a".repeat(40), "b".repeat(40), "c".repeat(40)];
+        let groups = group_summaries(summaries, 15);
+        assert_eq!(groups.len(), 2);
+        assert!(groups[0].starts_with('a') && groups[0].ends_with('b'));
+    }
+}
@@ -0,0 +1,146 @@
+use crate::memory::memory_store::Memory;
+use crate::request_id;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// A memory as it left one stage of a retrieval.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TracedCandidate {
+    pub id: String,
+    pub score: f32,
+    pub text: String,
+}
+
+impl From<&Memory> for TracedCandidate {
+    fn from(memory: &Memory) -> Self {
+        Self {
+            id: memory.id.clone(),
+            score: memory.score,
+            text: memory.text.clone(),
+        }
+    }
+}
+
+/// One stage of a retrieval: its output candidates in order, or why it failed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StageTrace {
+    /// `retrieval`, `reranker`, `moderation`, `packing`, ...
+    pub stage: String,
+    pub candidates: Vec<TracedCandidate>,
+    pub elapsed_ms: f64,
+    /// Error of a failed optional stage, whose input went on unchanged.
+    pub error: Option<String>,
+}
+
+/// Everything that went into one answer: the query, its rewrites, the candidates after
+/// each stage and the final prompt. Written per request by a [`TraceExport`] to inspect
+/// why an answer was produced.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RetrievalTrace {
+    pub request_id: String,
+    pub started_at: DateTime<Utc>,
+    pub query: String,
+    /// Queries actually searched, in order, when they differ from `query`.
+    pub rewrites: Vec<String>,
+    pub stages: Vec<StageTrace>,
+    /// Prompt sent to the LLM, for traces of answering types.
+    pub prompt: Option<String>,
+}
+
+impl RetrievalTrace {
+    /// Trace of the current request, see [`request_id::traced`].
+    pub fn new(query: impl Into<String>) -> Self {
+        let request_id =
+            request_id::current_request_id().unwrap_or_else(request_id::new_request_id);
+        Self {
+            request_id,
+            started_at: Utc::now(),
+            query: query.into(),
+            rewrites: Vec::new(),
+            stages: Vec::new(),
+            prompt: None,
+        }
+    }
+
+    pub fn record_rewrite(&mut self, query: impl Into<String>) {
+        self.rewrites.push(query.into());
+    }
+
+    pub fn record_stage(
+        &mut self,
+        stage: impl Into<String>,
+        memories: &[Memory],
+        elapsed: Duration,
+    ) {
+        self.stages.push(StageTrace {
+            stage: stage.into(),
+            candidates: memories.iter().map(TracedCandidate::from).collect(),
+            elapsed_ms: elapsed.as_secs_f64() * 1000.0,
+            error: None,
+        });
+    }
+
+    pub fn record_failure(
+        &mut self,
+        stage: impl Into<String>,
+        error: impl Into<String>,
+        elapsed: Duration,
+    ) {
+        self.stages.push(StageTrace {
+            stage: stage.into(),
+            candidates: Vec::new(),
+            elapsed_ms: elapsed.as_secs_f64() * 1000.0,
+            error: Some(error.into()),
+        });
+    }
+
+    pub fn stage(&self, stage: &str) -> Option<&StageTrace> {
+        self.stages.iter().find(|trace| trace.stage == stage)
+    }
+}
+
+/// Writes each [`RetrievalTrace`] as `<request id>.json` in a directory, created on
+/// first write. A trace written again for the same request replaces the earlier one.
+#[derive(Debug, Clone)]
+pub struct TraceExport {
+    dir: PathBuf,
+}
+
+impl TraceExport {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn path_of(&self, request_id: &str) -> PathBuf {
+        self.dir.join(format!("{request_id}.json"))
+    }
+
+    pub fn write(&self, trace: &RetrievalTrace) -> std::io::Result<PathBuf> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.path_of(&trace.request_id);
+        std::fs::write(&path, serde_json::to_string_pretty(trace)?)?;
+        Ok(path)
+    }
+
+    /// Like [`write`](Self::write), logging failures instead of returning them, so
+    /// tracing never fails the traced request.
+    pub(crate) fn export(&self, trace: &RetrievalTrace) {
+        if let Err(e) = self.write(trace) {
+            log::warn!(
+                "Writing retrieval trace {} to {} failed: {e}",
+                trace.request_id,
+                self.dir.display()
+            );
+        }
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> std::io::Result<RetrievalTrace> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+}
@@ -0,0 +1,392 @@
+use crate::circuit_breaker::TransientError;
+use crate::memory::events::MemoryEvent;
+use crate::memory::memory_store::{MemoryError, MemoryStore};
+use crate::request_id;
+use crate::vectorstore::qdrant_client::point_id_to_string;
+use crate::vectorstore::vector_store::{VectorStore, VectorStoreError};
+use qdrant_client::qdrant::{Condition, Filter};
+use serde_json::{json, Map, Value as JsonValue};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Payload field marking memories written by a transaction that is not committed yet.
+/// Recall and [`MemoryStore::memories_matching`] skip them.
+pub const PENDING_FIELD: &str = "pending_transaction";
+
+/// Adds the condition hiding uncommitted memories to a recall filter.
+pub(crate) fn hide_pending(filter: Option<Filter>) -> Option<Filter> {
+    let committed = Condition::is_empty(PENDING_FIELD);
+    Some(match filter {
+        Some(filter) => Filter::must([Condition::from(filter), committed]),
+        None => Filter::must([committed]),
+    })
+}
+
+// Applies the steps of a commit in order. An error before any step went through rolls
+// back `written` unless the store may have applied it anyway, e.g. after a timeout.
+async fn apply_commit(
+    store: &impl VectorStore,
+    collection: &str,
+    transaction: &str,
+    written: &[String],
+    metadata_updates: &[(Vec<String>, Map<String, JsonValue>)],
+    forgotten: &[String],
+) -> Result<(), MemoryError> {
+    let mut applied = false;
+    let fail = |e: VectorStoreError, applied: bool| async move {
+        if applied || e.is_transient() {
+            return MemoryError::PartialCommit {
+                transaction: transaction.to_string(),
+                message: e.to_string(),
+            };
+        }
+        // Memories a failed rollback leaves hidden are removed by
+        // `rollback_stale_transactions`
+        if !written.is_empty() {
+            let _ = store.delete(collection, written.to_vec()).await;
+        }
+        e.into()
+    };
+
+    if !written.is_empty() {
+        if let Err(e) = store
+            .delete_payload(
+                collection,
+                written.to_vec(),
+                vec![PENDING_FIELD.to_string()],
+            )
+            .await
+        {
+            return Err(fail(e, applied).await);
+        }
+        applied = true;
+    }
+    for (ids, fields) in metadata_updates {
+        if let Err(e) = store
+            .set_payload(collection, ids.clone(), fields.clone())
+            .await
+        {
+            return Err(fail(e, applied).await);
+        }
+        applied = true;
+    }
+    if !forgotten.is_empty() {
+        if let Err(e) = store.delete(collection, forgotten.to_vec()).await {
+            return Err(fail(e, applied).await);
+        }
+    }
+    Ok(())
+}
+
+/// Writes and deletes staged on a [`MemoryStore`] and applied together by
+/// [`commit`](Self::commit), e.g. to supersede fact A with facts B and C. New memories
+/// are written right away but hidden from recall until the commit; metadata changes and
+/// deletes are only sent with it, after the new memories are revealed.
+///
+/// A transaction dropped without a commit or rollback leaves its memories hidden until
+/// [`MemoryStore::rollback_stale_transactions`] removes them.
+pub struct MemoryTransaction<'a> {
+    store: &'a MemoryStore,
+    id: String,
+    written: Vec<(String, Map<String, JsonValue>)>,
+    metadata_updates: Vec<(Vec<String>, Map<String, JsonValue>)>,
+    forgotten: Vec<String>,
+}
+
+impl MemoryStore {
+    pub fn begin(&self) -> MemoryTransaction<'_> {
+        MemoryTransaction {
+            store: self,
+            id: Uuid::new_v4().to_string(),
+            written: Vec::new(),
+            metadata_updates: Vec::new(),
+            forgotten: Vec::new(),
+        }
+    }
+
+    /// Deletes memories of transactions begun more than `older_than` ago and neither
+    /// committed nor rolled back, e.g. because the process crashed. Returns how many were
+    /// deleted.
+    pub async fn rollback_stale_transactions(
+        &self,
+        older_than: Duration,
+    ) -> Result<usize, MemoryError> {
+        request_id::traced(async {
+            let cutoff = chrono::Utc::now()
+                - chrono::Duration::from_std(older_than).unwrap_or(chrono::Duration::MAX);
+            let pending = Filter::must_not([Condition::is_empty(PENDING_FIELD)]);
+            let stale: Vec<String> = self
+                .vectorstore()
+                .scroll_all(self.collection_name(), Some(pending), false)
+                .await?
+                .into_iter()
+                .filter(|point| {
+                    point
+                        .payload
+                        .get(&self.vectorstore().payload_fields().timestamp)
+                        .and_then(|value| value.as_str())
+                        .and_then(|timestamp| chrono::DateTime::parse_from_rfc3339(timestamp).ok())
+                        .is_some_and(|timestamp| timestamp < cutoff)
+                })
+                .filter_map(|point| point.id.as_ref().map(point_id_to_string))
+                .collect();
+            if !stale.is_empty() {
+                self.vectorstore()
+                    .delete_points(self.collection_name(), stale.clone())
+                    .await?;
+            }
+            Ok(stale.len())
+        })
+        .await
+    }
+}
+
+impl MemoryTransaction<'_> {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Writes a memory hidden until the commit and returns its id.
+    pub async fn remember(
+        &mut self,
+        text: &str,
+        metadata: Option<Map<String, JsonValue>>,
+    ) -> Result<String, MemoryError> {
+        let (text, metadata) = self
+            .store
+            .review_write(text, self.store.claim(metadata.unwrap_or_default())?)
+            .await?;
+        let mut staged = metadata.clone();
+        staged.insert(PENDING_FIELD.to_string(), json!(self.id));
+        let id = self.store.write_memory(&text, staged).await?;
+        self.written.push((id.clone(), metadata));
+        Ok(id)
+    }
+
+    /// Merges `fields` into the metadata of the given memories on commit. Memories written
+    /// by this transaction can be updated too. As with [`MemoryStore::set_metadata`], the
+    /// commit fails if one of them does not exist or is another agent's.
+    pub fn set_metadata(&mut self, ids: Vec<String>, fields: Map<String, JsonValue>) {
+        self.metadata_updates.push((ids, fields));
+    }
+
+    /// Deletes the given memories on commit, leaving other agents' memories alone as
+    /// [`MemoryStore::forget`] does.
+    pub fn forget(&mut self, ids: Vec<String>) {
+        self.forgotten.extend(ids);
+    }
+
+    /// Reveals the new memories, then applies the staged metadata changes and deletes the
+    /// forgotten memories, returning the ids of the new memories.
+    ///
+    /// If the store rejects the first step the transaction is rolled back. Once a step may
+    /// have been applied it is not: the commit fails with [`MemoryError::PartialCommit`]
+    /// and the caller can retry the remaining changes.
+    pub async fn commit(self) -> Result<Vec<String>, MemoryError> {
+        let _operation = self.store.track_operation();
+        let written: Vec<String> = self.written.iter().map(|(id, _)| id.clone()).collect();
+        let collection = self.store.collection_name().to_string();
+        let (metadata_updates, forgotten) = match request_id::traced(self.owned_changes()).await {
+            Ok(changes) => changes,
+            Err(e) => {
+                // Nothing was applied yet
+                let _ = self
+                    .store
+                    .vectorstore()
+                    .delete_points(&collection, written)
+                    .await;
+                return Err(e);
+            }
+        };
+        request_id::traced(apply_commit(
+            self.store.vectorstore(),
+            &collection,
+            &self.id,
+            &written,
+            &metadata_updates,
+            &forgotten,
+        ))
+        .await?;
+
+        for (id, metadata) in self.written {
+            self.store.emit(MemoryEvent::Remembered {
+                collection: collection.clone(),
+                ids: vec![id],
+                metadata,
+            });
+        }
+        if !forgotten.is_empty() {
+            self.store.emit(MemoryEvent::Forgotten {
+                collection,
+                ids: forgotten,
+            });
+        }
+        Ok(written)
+    }
+
+    // The staged metadata changes and deletes, restricted to what the store's agent may
+    // change
+    async fn owned_changes(
+        &self,
+    ) -> Result<(Vec<(Vec<String>, Map<String, JsonValue>)>, Vec<String>), MemoryError> {
+        let mut metadata_updates = Vec::with_capacity(self.metadata_updates.len());
+        for (ids, fields) in &self.metadata_updates {
+            let ids = self.store.require_owned(ids.clone()).await?;
+            metadata_updates.push((ids, fields.clone()));
+        }
+        let forgotten = self.store.owned_ids(self.forgotten.clone()).await?;
+        Ok((metadata_updates, forgotten))
+    }
+
+    /// Deletes the memories written by the transaction and drops the staged changes.
+    pub async fn rollback(self) -> Result<(), MemoryError> {
+        let _operation = self.store.track_operation();
+        let written: Vec<String> = self.written.into_iter().map(|(id, _)| id).collect();
+        if written.is_empty() {
+            return Ok(());
+        }
+        request_id::traced(
+            self.store
+                .vectorstore()
+                .delete_points(self.store.collection_name(), written),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::faults::{FaultInjector, FaultyVectorStore};
+    use crate::vectorstore::in_memory::InMemoryVectorStore;
+    use crate::vectorstore::vector_store::VectorPoint;
+
+    fn strings(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    // Store holding committed memory "a" and memories "b" and "c" pending in "tx"
+    async fn staged_store() -> InMemoryVectorStore {
+        let store = InMemoryVectorStore::new();
+        let mut pending = Map::new();
+        pending.insert(PENDING_FIELD.to_string(), json!("tx"));
+        store
+            .upsert(
+                "memories",
+                vec![
+                    VectorPoint::new("a", vec![1.0, 0.0], Map::new()),
+                    VectorPoint::new("b", vec![0.0, 1.0], pending.clone()),
+                    VectorPoint::new("c", vec![0.5, 0.5], pending),
+                ],
+            )
+            .await
+            .unwrap();
+        store
+    }
+
+    fn superseded() -> Vec<(Vec<String>, Map<String, JsonValue>)> {
+        let mut fields = Map::new();
+        fields.insert("superseded_by".to_string(), json!("b"));
+        vec![(strings(&["a"]), fields)]
+    }
+
+    fn pending(store: &InMemoryVectorStore, id: &str) -> bool {
+        store
+            .get("memories", id)
+            .unwrap()
+            .payload
+            .contains_key(PENDING_FIELD)
+    }
+
+    #[tokio::test]
+    async fn test_commit_reveals_then_applies() {
+        let store = staged_store().await;
+        apply_commit(
+            &store,
+            "memories",
+            "tx",
+            &strings(&["b", "c"]),
+            &superseded(),
+            &strings(&["c"]),
+        )
+        .await
+        .unwrap();
+        assert!(!pending(&store, "b"));
+        assert_eq!(
+            store.get("memories", "a").unwrap().payload["superseded_by"],
+            "b"
+        );
+        assert!(store.get("memories", "c").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_commit_rolls_back_when_rejected() {
+        // "c" was deleted out of band, so revealing the written memories is rejected
+        let store = staged_store().await;
+        store.delete("memories", strings(&["c"])).await.unwrap();
+        let result = apply_commit(
+            &store,
+            "memories",
+            "tx",
+            &strings(&["b", "c"]),
+            &superseded(),
+            &[],
+        )
+        .await;
+        assert!(matches!(result, Err(MemoryError::NotFound(id)) if id == "c"));
+        assert!(store.get("memories", "b").is_none());
+        assert!(!store
+            .get("memories", "a")
+            .unwrap()
+            .payload
+            .contains_key("superseded_by"));
+    }
+
+    #[tokio::test]
+    async fn test_commit_keeps_uncertain_writes() {
+        let store = FaultyVectorStore::wrap(
+            staged_store().await,
+            FaultInjector::new(7).with_error_rate(1.0),
+        );
+        let result = apply_commit(
+            &store,
+            "memories",
+            "tx",
+            &strings(&["b", "c"]),
+            &superseded(),
+            &[],
+        )
+        .await;
+        assert!(matches!(result, Err(MemoryError::PartialCommit { .. })));
+        // Not rolled back: the store may have revealed them
+        assert_eq!(store.store().len("memories"), 3);
+    }
+
+    #[tokio::test]
+    async fn test_commit_reports_partial_commit() {
+        let store = staged_store().await;
+        let mut fields = Map::new();
+        fields.insert("superseded_by".to_string(), json!("b"));
+        let result = apply_commit(
+            &store,
+            "memories",
+            "tx",
+            &strings(&["b", "c"]),
+            &[(strings(&["missing"]), fields)],
+            &strings(&["a"]),
+        )
+        .await;
+        assert!(matches!(result, Err(MemoryError::PartialCommit { .. })));
+        assert!(!pending(&store, "b"));
+        assert!(!pending(&store, "c"));
+        assert!(store.get("memories", "a").is_some());
+    }
+
+    #[test]
+    fn test_hide_pending() {
+        assert_eq!(hide_pending(None).unwrap().must.len(), 1);
+        let caller = Filter::must([Condition::matches("kind", "fact".to_string())]);
+        assert_eq!(hide_pending(Some(caller)).unwrap().must.len(), 2);
+    }
+}
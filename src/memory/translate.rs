@@ -0,0 +1,107 @@
+use crate::llm::llm_client::LlmClientChat;
+use crate::memory::memory_store::MemoryError;
+use futures::stream::{self, StreamExt, TryStreamExt};
+
+/// Payload field [`ingest_translated_documents`] stores the translation of each chunk in,
+/// next to the original text.
+///
+/// [`ingest_translated_documents`]: crate::vectorstore::ingestion::ingest_translated_documents
+pub const TRANSLATION_FIELD: &str = "translation";
+
+/// Translates text into one language, e.g. with an LLM.
+#[allow(async_fn_in_trait)]
+pub trait Translator {
+    async fn translate(&self, text: &str) -> Result<String, MemoryError>;
+}
+
+/// Translates with an LLM prompt, into English unless
+/// [`with_target_language`](Self::with_target_language) says otherwise.
+pub struct LlmTranslator<C: LlmClientChat> {
+    llm_client: C,
+    model: String,
+    target_language: String,
+}
+
+impl<C: LlmClientChat> LlmTranslator<C> {
+    pub fn new(llm_client: C, model: impl Into<String>) -> Self {
+        Self {
+            llm_client,
+            model: model.into(),
+            target_language: "English".to_string(),
+        }
+    }
+
+    pub fn with_target_language(mut self, language: impl Into<String>) -> Self {
+        self.target_language = language.into();
+        self
+    }
+
+    fn prompt(&self, text: &str) -> String {
+        format!(
+            "Translate the following text into {language}, keeping names, numbers and \
+            formatting. If it is already in {language}, repeat it unchanged. Answer with \
+            the translation only.\n\n{text}",
+            language = self.target_language
+        )
+    }
+}
+
+impl<C: LlmClientChat> Translator for LlmTranslator<C> {
+    async fn translate(&self, text: &str) -> Result<String, MemoryError> {
+        let response = self
+            .llm_client
+            .send_message(&self.model, self.prompt(text), None::<&str>, Some(0.0))
+            .await
+            .map_err(|e| MemoryError::LlmError(e.to_string()))?;
+        Ok(response.trim().to_string())
+    }
+}
+
+/// Translates `texts` with up to `concurrency` calls in flight, keeping their order.
+pub async fn translate_all(
+    texts: &[String],
+    translator: &impl Translator,
+    concurrency: usize,
+) -> Result<Vec<String>, MemoryError> {
+    stream::iter(texts)
+        .map(|text| translator.translate(text))
+        .buffered(concurrency.max(1))
+        .try_collect()
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    struct Glossary(HashMap<&'static str, &'static str>);
+
+    impl Translator for Glossary {
+        async fn translate(&self, text: &str) -> Result<String, MemoryError> {
+            self.0
+                .get(text)
+                .map(|translation| translation.to_string())
+                .ok_or_else(|| MemoryError::LlmError(format!("Unknown text: {text}")))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_translate_all() {
+        let glossary = Glossary(HashMap::from([
+            ("As botas são impermeáveis.", "The boots are waterproof."),
+            ("Devoluções grátis.", "Free returns."),
+        ]));
+        let texts = vec![
+            "As botas são impermeáveis.".to_string(),
+            "Devoluções grátis.".to_string(),
+        ];
+        assert_eq!(
+            translate_all(&texts, &glossary, 2).await.unwrap(),
+            vec!["The boots are waterproof.", "Free returns."]
+        );
+        assert!(translate_all(&["Olá".to_string()], &glossary, 2)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,245 @@
+use crate::circuit_breaker::{CircuitBreaker, CircuitOpenError, CircuitState, TransientError};
+use crate::request_id::current_request_id;
+use serde::Serialize;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+/// Request counters and latencies of one client (Qdrant, TEI, ...), with an optional cap on
+/// concurrent requests and a log of operations slower than a threshold.
+pub struct ClientMetrics {
+    name: String,
+    slow_threshold: Option<Duration>,
+    permits: Option<Semaphore>,
+    circuit_breaker: Option<CircuitBreaker>,
+    in_flight: AtomicU64,
+    requests: AtomicU64,
+    failures: AtomicU64,
+    slow_requests: AtomicU64,
+    total_latency_us: AtomicU64,
+    max_latency_us: AtomicU64,
+    total_queue_us: AtomicU64,
+}
+
+/// Point-in-time copy of [`ClientMetrics`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClientStats {
+    pub name: String,
+    pub in_flight: u64,
+    pub requests: u64,
+    pub failures: u64,
+    pub slow_requests: u64,
+    pub mean_latency_ms: f64,
+    pub max_latency_ms: f64,
+    /// Mean time spent waiting for a free slot when `max_in_flight` is set.
+    pub mean_queue_ms: f64,
+    /// State of the circuit breaker, if one is set.
+    pub circuit_state: Option<CircuitState>,
+}
+
+fn micros(duration: Duration) -> u64 {
+    duration.as_micros().min(u64::MAX as u128) as u64
+}
+
+impl ClientMetrics {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            slow_threshold: None,
+            permits: None,
+            circuit_breaker: None,
+            in_flight: AtomicU64::new(0),
+            requests: AtomicU64::new(0),
+            failures: AtomicU64::new(0),
+            slow_requests: AtomicU64::new(0),
+            total_latency_us: AtomicU64::new(0),
+            max_latency_us: AtomicU64::new(0),
+            total_queue_us: AtomicU64::new(0),
+        }
+    }
+
+    /// Logs every operation slower than `threshold`, with its parameters.
+    pub fn with_slow_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_threshold = Some(threshold);
+        self
+    }
+
+    /// Queues requests beyond `max_in_flight` concurrent ones.
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.permits = Some(Semaphore::new(max_in_flight.max(1)));
+        self
+    }
+
+    /// Fails requests fast while `circuit_breaker` is open, without sending or counting them.
+    pub fn with_circuit_breaker(mut self, circuit_breaker: CircuitBreaker) -> Self {
+        self.circuit_breaker = Some(circuit_breaker);
+        self
+    }
+
+    /// Runs `request`, recording its queue time, latency and outcome. `params` describes the
+    /// request in the slow-operation log.
+    pub async fn track<T, E: From<CircuitOpenError> + TransientError>(
+        &self,
+        operation: &str,
+        params: impl FnOnce() -> String,
+        request: impl Future<Output = Result<T, E>>,
+    ) -> Result<T, E> {
+        // Boxed so the layers below do not each hold a copy of a large request future
+        let request = Box::pin(request);
+        match &self.circuit_breaker {
+            Some(circuit_breaker) => {
+                circuit_breaker
+                    .call(self.track_request(operation, params, request))
+                    .await
+            }
+            None => self.track_request(operation, params, request).await,
+        }
+    }
+
+    async fn track_request<T, E>(
+        &self,
+        operation: &str,
+        params: impl FnOnce() -> String,
+        request: impl Future<Output = Result<T, E>>,
+    ) -> Result<T, E> {
+        let queued = Instant::now();
+        let _permit = match &self.permits {
+            // The semaphore is never closed
+            Some(permits) => permits.acquire().await.ok(),
+            None => None,
+        };
+        self.total_queue_us
+            .fetch_add(micros(queued.elapsed()), Ordering::Relaxed);
+
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        let started = Instant::now();
+        let result = request.await;
+        let latency = started.elapsed();
+        self.in_flight.fetch_sub(1, Ordering::Relaxed);
+
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        if result.is_err() {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+        }
+        self.total_latency_us
+            .fetch_add(micros(latency), Ordering::Relaxed);
+        self.max_latency_us
+            .fetch_max(micros(latency), Ordering::Relaxed);
+
+        if self
+            .slow_threshold
+            .is_some_and(|threshold| latency >= threshold)
+        {
+            self.slow_requests.fetch_add(1, Ordering::Relaxed);
+            let request_id = current_request_id()
+                .map(|request_id| format!(" [{request_id}]"))
+                .unwrap_or_default();
+            log::warn!(
+                "Slow {} {operation}{request_id} ({latency:?}, queued {:?}): {}",
+                self.name,
+                started - queued,
+                params()
+            );
+        }
+        result
+    }
+
+    pub fn stats(&self) -> ClientStats {
+        let requests = self.requests.load(Ordering::Relaxed);
+        let mean_ms = |total_us: &AtomicU64| match requests {
+            0 => 0.0,
+            n => total_us.load(Ordering::Relaxed) as f64 / n as f64 / 1000.0,
+        };
+        ClientStats {
+            name: self.name.clone(),
+            in_flight: self.in_flight.load(Ordering::Relaxed),
+            requests,
+            failures: self.failures.load(Ordering::Relaxed),
+            slow_requests: self.slow_requests.load(Ordering::Relaxed),
+            mean_latency_ms: mean_ms(&self.total_latency_us),
+            max_latency_ms: self.max_latency_us.load(Ordering::Relaxed) as f64 / 1000.0,
+            mean_queue_ms: mean_ms(&self.total_queue_us),
+            circuit_state: self.circuit_breaker.as_ref().map(CircuitBreaker::state),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct TestError(&'static str);
+
+    impl From<CircuitOpenError> for TestError {
+        fn from(_: CircuitOpenError) -> Self {
+            TestError("circuit open")
+        }
+    }
+
+    impl TransientError for TestError {
+        fn is_transient(&self) -> bool {
+            self.0 == "timeout"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_track() {
+        let metrics = ClientMetrics::new("test").with_slow_threshold(Duration::ZERO);
+        let ok: Result<u32, TestError> = metrics
+            .track("query", || "limit=1".to_string(), async { Ok(1) })
+            .await;
+        assert_eq!(ok, Ok(1));
+        let _ = metrics
+            .track("query", String::new, async {
+                Err::<u32, _>(TestError("timeout"))
+            })
+            .await;
+
+        let stats = metrics.stats();
+        assert_eq!(stats.requests, 2);
+        assert_eq!(stats.failures, 1);
+        assert_eq!(stats.slow_requests, 2);
+        assert_eq!(stats.in_flight, 0);
+    }
+
+    #[tokio::test]
+    async fn test_max_in_flight() {
+        let metrics = std::sync::Arc::new(ClientMetrics::new("test").with_max_in_flight(1));
+        let slow = |metrics: std::sync::Arc<ClientMetrics>| async move {
+            metrics
+                .track("embed", String::new, async {
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    Ok::<_, TestError>(())
+                })
+                .await
+        };
+        let (a, b) = tokio::join!(slow(metrics.clone()), slow(metrics.clone()));
+        assert!(a.is_ok() && b.is_ok());
+        // One of the two requests waited for the other
+        assert!(metrics.stats().mean_queue_ms >= 5.0);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker() {
+        let metrics = ClientMetrics::new("test").with_circuit_breaker(CircuitBreaker::new(
+            "test",
+            1,
+            Duration::from_secs(60),
+        ));
+        let _ = metrics
+            .track("query", String::new, async {
+                Err::<u32, _>(TestError("timeout"))
+            })
+            .await;
+        let rejected = metrics
+            .track("query", String::new, async { Ok::<u32, _>(1) })
+            .await;
+        assert_eq!(rejected, Err(TestError("circuit open")));
+
+        let stats = metrics.stats();
+        assert_eq!(stats.requests, 1);
+        assert_eq!(stats.circuit_state, Some(CircuitState::Open));
+    }
+}
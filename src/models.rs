@@ -0,0 +1,293 @@
+use crate::cost::{ModelPrice, PriceTable};
+use serde::Serialize;
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelKind {
+    Chat,
+    Embedding,
+}
+
+/// What a model accepts and costs.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ModelInfo {
+    pub name: String,
+    pub kind: ModelKind,
+    /// Input tokens the model accepts, prompt and output together for chat models.
+    pub context_window: usize,
+    pub max_output_tokens: Option<usize>,
+    pub supports_vision: bool,
+    pub supports_tools: bool,
+    pub embedding_dimension: Option<usize>,
+    pub price: Option<ModelPrice>,
+}
+
+impl ModelInfo {
+    pub fn chat(name: impl Into<String>, context_window: usize, max_output_tokens: usize) -> Self {
+        Self {
+            name: name.into(),
+            kind: ModelKind::Chat,
+            context_window,
+            max_output_tokens: Some(max_output_tokens),
+            supports_vision: false,
+            supports_tools: false,
+            embedding_dimension: None,
+            price: None,
+        }
+    }
+
+    pub fn embedding(name: impl Into<String>, context_window: usize, dimension: usize) -> Self {
+        Self {
+            name: name.into(),
+            kind: ModelKind::Embedding,
+            context_window,
+            max_output_tokens: None,
+            supports_vision: false,
+            supports_tools: false,
+            embedding_dimension: Some(dimension),
+            price: None,
+        }
+    }
+
+    pub fn with_vision(mut self) -> Self {
+        self.supports_vision = true;
+        self
+    }
+
+    pub fn with_tools(mut self) -> Self {
+        self.supports_tools = true;
+        self
+    }
+
+    pub fn with_price(mut self, price: ModelPrice) -> Self {
+        self.price = Some(price);
+        self
+    }
+
+    /// Checks a request against the model's capabilities before it is sent.
+    pub fn validate(&self, request: &ModelRequest) -> Result<(), ModelError> {
+        if request.has_image && !self.supports_vision {
+            return Err(ModelError::VisionUnsupported(self.name.clone()));
+        }
+        if request.uses_tools && !self.supports_tools {
+            return Err(ModelError::ToolsUnsupported(self.name.clone()));
+        }
+        if request.input_tokens > self.context_window {
+            return Err(ModelError::ContextExceeded {
+                model: self.name.clone(),
+                tokens: request.input_tokens,
+                context_window: self.context_window,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// The parts of a request [`ModelInfo::validate`] checks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ModelRequest {
+    pub input_tokens: usize,
+    pub has_image: bool,
+    pub uses_tools: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ModelError {
+    #[error("Model {0} does not accept images")]
+    VisionUnsupported(String),
+    #[error("Model {0} does not support tools")]
+    ToolsUnsupported(String),
+    #[error(
+        "Input of about {tokens} tokens exceeds the {context_window} token context of {model}"
+    )]
+    ContextExceeded {
+        model: String,
+        tokens: usize,
+        context_window: usize,
+    },
+}
+
+/// Known models by name. Lookups fall back to the longest registered prefix, so dated
+/// snapshots like `gpt-4o-2024-08-06` resolve to `gpt-4o`. The default registry lists
+/// common hosted models and the self-hosted ones the examples use, with list prices at
+/// the time of writing; models it does not know are not validated.
+#[derive(Debug, Clone)]
+pub struct ModelRegistry {
+    models: HashMap<String, ModelInfo>,
+}
+
+impl Default for ModelRegistry {
+    fn default() -> Self {
+        Self::empty()
+            .with_model(
+                ModelInfo::chat("gpt-4o", 128_000, 16_384)
+                    .with_vision()
+                    .with_tools()
+                    .with_price(ModelPrice::new(2.5, 10.0)),
+            )
+            .with_model(
+                ModelInfo::chat("gpt-4o-mini", 128_000, 16_384)
+                    .with_vision()
+                    .with_tools()
+                    .with_price(ModelPrice::new(0.15, 0.6)),
+            )
+            .with_model(
+                ModelInfo::chat("claude-3-5-sonnet", 200_000, 8192)
+                    .with_vision()
+                    .with_tools()
+                    .with_price(ModelPrice::new(3.0, 15.0)),
+            )
+            .with_model(
+                ModelInfo::chat("claude-3-5-haiku", 200_000, 8192)
+                    .with_tools()
+                    .with_price(ModelPrice::new(0.8, 4.0)),
+            )
+            .with_model(
+                ModelInfo::chat("llama3.2-vision", 128_000, 4096)
+                    .with_vision()
+                    .with_price(ModelPrice::new(0.0, 0.0)),
+            )
+            .with_model(
+                ModelInfo::chat("llama3.2", 128_000, 4096)
+                    .with_tools()
+                    .with_price(ModelPrice::new(0.0, 0.0)),
+            )
+            .with_model(
+                ModelInfo::embedding("text-embedding-3-small", 8191, 1536)
+                    .with_price(ModelPrice::new(0.02, 0.0)),
+            )
+            .with_model(
+                ModelInfo::embedding("text-embedding-3-large", 8191, 3072)
+                    .with_price(ModelPrice::new(0.13, 0.0)),
+            )
+            .with_model(
+                ModelInfo::embedding("BAAI/bge-large-en-v1.5", 512, 1024)
+                    .with_price(ModelPrice::new(0.0, 0.0)),
+            )
+            .with_model(
+                ModelInfo::embedding("BAAI/bge-small-en-v1.5", 512, 384)
+                    .with_price(ModelPrice::new(0.0, 0.0)),
+            )
+    }
+}
+
+impl ModelRegistry {
+    pub fn empty() -> Self {
+        Self {
+            models: HashMap::new(),
+        }
+    }
+
+    /// Adds or replaces a model.
+    pub fn with_model(mut self, model: ModelInfo) -> Self {
+        self.models.insert(model.name.clone(), model);
+        self
+    }
+
+    pub fn get(&self, model: &str) -> Option<&ModelInfo> {
+        if let Some(info) = self.models.get(model) {
+            return Some(info);
+        }
+        self.models
+            .iter()
+            .filter(|(name, _)| model.starts_with(name.as_str()))
+            .max_by_key(|(name, _)| name.len())
+            .map(|(_, info)| info)
+    }
+
+    /// Validates a request to `model`; unknown models pass.
+    pub fn validate(&self, model: &str, request: &ModelRequest) -> Result<(), ModelError> {
+        match self.get(model) {
+            Some(info) => info.validate(request),
+            None => Ok(()),
+        }
+    }
+
+    /// Output token cap for requests to `model`, `default` if it is unknown.
+    pub fn max_output_tokens(&self, model: &str, default: usize) -> usize {
+        self.get(model)
+            .and_then(|info| info.max_output_tokens)
+            .unwrap_or(default)
+    }
+
+    /// Prompt tokens left for `model` once its output limit is reserved, `None` if it is
+    /// unknown.
+    pub fn prompt_budget(&self, model: &str) -> Option<usize> {
+        let info = self.get(model)?;
+        Some(
+            info.context_window
+                .saturating_sub(info.max_output_tokens.unwrap_or(0)),
+        )
+    }
+
+    pub fn embedding_dimension(&self, model: &str) -> Option<usize> {
+        self.get(model).and_then(|info| info.embedding_dimension)
+    }
+
+    /// Prices of the models that have one.
+    pub fn price_table(&self) -> PriceTable {
+        self.models
+            .values()
+            .filter_map(|info| Some((info.name.clone(), info.price?)))
+            .fold(PriceTable::empty(), |table, (name, price)| {
+                table.with_price(name, price)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_by_prefix() {
+        let registry = ModelRegistry::default();
+        assert_eq!(
+            registry.get("gpt-4o-mini-2024-07-18").unwrap().name,
+            "gpt-4o-mini"
+        );
+        assert_eq!(registry.get("gpt-4o-2024-08-06").unwrap().name, "gpt-4o");
+        assert_eq!(
+            registry.get("claude-3-5-haiku-latest").unwrap().name,
+            "claude-3-5-haiku"
+        );
+        assert!(registry.get("mistral-large").is_none());
+        assert_eq!(
+            registry.embedding_dimension("BAAI/bge-large-en-v1.5"),
+            Some(1024)
+        );
+        assert_eq!(registry.max_output_tokens("mistral-large", 1024), 1024);
+        assert_eq!(
+            registry.prompt_budget("claude-3-5-haiku-latest"),
+            Some(191_808)
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let registry = ModelRegistry::default();
+        let image = ModelRequest {
+            has_image: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            registry.validate("claude-3-5-haiku-latest", &image),
+            Err(ModelError::VisionUnsupported(
+                "claude-3-5-haiku".to_string()
+            ))
+        );
+        assert!(registry.validate("gpt-4o", &image).is_ok());
+        assert!(registry.validate("unknown-model", &image).is_ok());
+
+        let long = ModelRequest {
+            input_tokens: 600,
+            ..Default::default()
+        };
+        assert!(matches!(
+            registry.validate("BAAI/bge-large-en-v1.5", &long),
+            Err(ModelError::ContextExceeded { tokens: 600, .. })
+        ));
+    }
+}
@@ -0,0 +1,2 @@
+pub mod run_registry;
+pub mod visual_memory;
@@ -0,0 +1,205 @@
+use crate::cost::CorpusStats;
+use crate::usage::{self, RunUsage};
+use crate::utils::fnv1a;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as JsonValue};
+use std::future::Future;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunKind {
+    Ingestion,
+    Evaluation,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum RunOutcome {
+    Succeeded,
+    Failed { error: String },
+}
+
+/// One ingestion or evaluation run, as recorded by [`RunRegistry::track`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunRecord {
+    /// UUIDv7, so ids sort by start time.
+    pub id: String,
+    /// Name shared by the runs of one pipeline, e.g. `"catalog-ingest"`.
+    pub name: String,
+    pub kind: RunKind,
+    /// Hex FNV-1a hash of `config`: runs with equal hashes ran with the same settings.
+    pub config_hash: String,
+    pub config: JsonValue,
+    pub corpus: CorpusStats,
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    pub usage: RunUsage,
+    pub outcome: RunOutcome,
+}
+
+impl RunRecord {
+    /// Top-level config fields whose value differs in `other`, e.g. to answer what changed
+    /// since the previous run of a pipeline. Configs that are not JSON objects have no
+    /// fields, compare their `config_hash` instead.
+    pub fn config_changes(&self, other: &RunRecord) -> Vec<String> {
+        let empty = Map::new();
+        let ours = self.config.as_object().unwrap_or(&empty);
+        let theirs = other.config.as_object().unwrap_or(&empty);
+        let mut keys: Vec<String> = ours
+            .keys()
+            .chain(theirs.keys())
+            .filter(|key| ours.get(*key) != theirs.get(*key))
+            .cloned()
+            .collect();
+        keys.sort();
+        keys.dedup();
+        keys
+    }
+}
+
+/// Hash of a run config, stable across processes and platforms.
+pub fn config_hash(config: &JsonValue) -> String {
+    format!("{:016x}", fnv1a(config.to_string().as_bytes()))
+}
+
+/// Named pipeline runs, persisted as a JSON Lines file with one [`RunRecord`] per line,
+/// for reproducibility: which settings, corpus and usage produced a collection, and what
+/// changed since an earlier run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunRegistry {
+    path: PathBuf,
+}
+
+impl RunRegistry {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Runs `operation` as run `name`, then records its config, corpus, duration, usage
+    /// (see [`usage::measured`]) and outcome. A failed run is recorded too and its error
+    /// returned.
+    pub async fn track<T, F: Future<Output = Result<T>>>(
+        &self,
+        name: &str,
+        kind: RunKind,
+        config: &impl Serialize,
+        corpus: CorpusStats,
+        operation: F,
+    ) -> Result<(T, RunRecord)> {
+        let config = serde_json::to_value(config)?;
+        let started_at = Utc::now();
+        let started = Instant::now();
+        let (output, usage) = usage::measured(operation).await;
+        let record = RunRecord {
+            id: Uuid::now_v7().to_string(),
+            name: name.to_string(),
+            kind,
+            config_hash: config_hash(&config),
+            config,
+            corpus,
+            started_at,
+            duration_ms: started.elapsed().as_millis() as u64,
+            usage,
+            outcome: match &output {
+                Ok(_) => RunOutcome::Succeeded,
+                Err(e) => RunOutcome::Failed {
+                    error: e.to_string(),
+                },
+            },
+        };
+        self.record(&record)?;
+        Ok((output?, record))
+    }
+
+    /// Appends a run to the file, creating it if needed.
+    pub fn record(&self, run: &RunRecord) -> Result<()> {
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(run)?)?;
+        Ok(())
+    }
+
+    /// Recorded runs, oldest first; none if the file does not exist.
+    pub fn list_runs(&self) -> Result<Vec<RunRecord>> {
+        let data = match std::fs::read_to_string(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        data.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Ok(serde_json::from_str(line)?))
+            .collect()
+    }
+
+    pub fn get_run(&self, id: &str) -> Result<Option<RunRecord>> {
+        Ok(self.list_runs()?.into_iter().find(|run| run.id == id))
+    }
+
+    /// The most recent run named `name`.
+    pub fn last_run(&self, name: &str) -> Result<Option<RunRecord>> {
+        Ok(self.list_runs()?.into_iter().rfind(|run| run.name == name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_run_registry() {
+        let path = std::env::temp_dir().join(format!("runs-{}.jsonl", Uuid::new_v4()));
+        let registry = RunRegistry::new(&path);
+        assert!(registry.list_runs().unwrap().is_empty());
+
+        let corpus = CorpusStats::from_texts(&["Boots are waterproof.".to_string()]);
+        let config = json!({"chunk_tokens": 256, "model": "bge-small"});
+        let (points, first) = registry
+            .track("catalog", RunKind::Ingestion, &config, corpus, async {
+                usage::record("bge-small", 5, 0);
+                Ok(1)
+            })
+            .await
+            .unwrap();
+        assert_eq!(points, 1);
+        assert_eq!(first.usage.calls(), 1);
+
+        let config = json!({"chunk_tokens": 512, "model": "bge-small"});
+        let error = registry
+            .track("catalog", RunKind::Ingestion, &config, corpus, async {
+                Err::<(), _>(anyhow!("Qdrant is down"))
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Qdrant is down");
+
+        let runs = registry.list_runs().unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0], first);
+        assert_eq!(registry.get_run(&first.id).unwrap(), Some(first.clone()));
+        let last = registry.last_run("catalog").unwrap().unwrap();
+        assert_eq!(
+            last.outcome,
+            RunOutcome::Failed {
+                error: "Qdrant is down".to_string()
+            }
+        );
+        assert_ne!(last.config_hash, first.config_hash);
+        assert_eq!(last.config_changes(&first), vec!["chunk_tokens"]);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,292 @@
+use crate::embeddings::text_embedding_inference::TextEmbeddingInference;
+use crate::llm::llm_client::LlmClientChat;
+use crate::request_id;
+use crate::utils::load_image_as_base64;
+use crate::vectorstore::asset_resolver::{
+    AssetResolver, AssetRouter, DynAssetResolver, ResolvedAsset,
+};
+use crate::vectorstore::payload_schema::PayloadFields;
+use crate::vectorstore::qdrant_client::{point_id_to_string, QdrantClient};
+use anyhow::{anyhow, Result};
+use qdrant_client::qdrant::ScoredPoint;
+use qdrant_client::Payload;
+use serde::Serialize;
+use serde_json::{json, Map};
+use std::collections::HashMap;
+
+const DESCRIBE_PROMPT: &str = "Describe this image in detail, including any visible text.";
+
+const ANSWER_PROMPT: &str = "Answer the question using only the numbered image descriptions \
+below. Cite the images you used with their numbers in brackets, e.g. [1]. If the descriptions \
+do not contain the answer, say so.";
+
+/// An image used to answer a question.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Citation {
+    pub image_path: String,
+    pub description: String,
+    /// Best similarity of the question to the image or to its description.
+    pub score: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Answer {
+    pub text: String,
+    /// Images cited in the answer, or every recalled image if the model cited none.
+    pub citations: Vec<Citation>,
+}
+
+/// Chat with your images: each image is described by a vision LLM, both the image and the
+/// description are embedded into a multivector collection, and questions are answered from
+/// the images recalled through either vector.
+///
+/// Questions are embedded with both embedders, so the image embedding model has to map
+/// text into the same space as images (e.g. CLIP).
+pub struct VisualMemory<C: LlmClientChat> {
+    client: QdrantClient,
+    llm_client: C,
+    model: String,
+    image_embedder: TextEmbeddingInference,
+    text_embedder: TextEmbeddingInference,
+    collection_name: String,
+    describe_prompt: String,
+    temperature: Option<f32>,
+    asset_resolver: Box<dyn DynAssetResolver>,
+}
+
+impl<C: LlmClientChat> VisualMemory<C> {
+    pub fn new(
+        client: QdrantClient,
+        llm_client: C,
+        model: impl Into<String>,
+        image_embedder: TextEmbeddingInference,
+        text_embedder: TextEmbeddingInference,
+        collection_name: impl Into<String>,
+    ) -> Self {
+        Self {
+            client,
+            llm_client,
+            model: model.into(),
+            image_embedder,
+            text_embedder,
+            collection_name: collection_name.into(),
+            describe_prompt: DESCRIBE_PROMPT.to_string(),
+            temperature: Some(0.0),
+            asset_resolver: Box::new(AssetRouter::default()),
+        }
+    }
+
+    pub fn with_describe_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.describe_prompt = prompt.into();
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: Option<f32>) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    /// Resolver used by [`resolve_asset`](Self::resolve_asset), [`AssetRouter::default`]
+    /// unless replaced.
+    pub fn with_asset_resolver(mut self, resolver: impl AssetResolver + 'static) -> Self {
+        self.asset_resolver = Box::new(resolver);
+        self
+    }
+
+    pub fn collection_name(&self) -> &str {
+        &self.collection_name
+    }
+
+    /// Describes, embeds and stores the image. Returns the generated description.
+    pub async fn add_image(&self, image_path: &str) -> Result<String> {
+        request_id::traced(async {
+            let description = self
+                .llm_client
+                .send_message(
+                    &self.model,
+                    &self.describe_prompt,
+                    Some(image_path),
+                    self.temperature,
+                )
+                .await?;
+
+            let image = load_image_as_base64(image_path).await?;
+            let image_embedding = first(self.image_embedder.embed(vec![image]).await)?;
+            let text_embedding = first(self.text_embedder.embed(vec![description.clone()]).await)?;
+
+            if !self.client.check_collection(&self.collection_name).await? {
+                self.client
+                    .create_multivector_collection(
+                        &self.collection_name,
+                        image_embedding.len() as u64,
+                        text_embedding.len() as u64,
+                    )
+                    .await?;
+            }
+
+            let fields = self.client.payload_fields();
+            let mut payload = Map::new();
+            payload.insert(fields.image_path.clone(), json!(image_path));
+            fields.insert_text(&mut payload, &description);
+            self.client
+                .upsert_points_multivector(
+                    &self.collection_name,
+                    image_embedding,
+                    text_embedding,
+                    Payload::from(payload),
+                )
+                .await?;
+
+            Ok(description)
+        })
+        .await
+    }
+
+    /// The `limit` images closest to the question through either vector, best first.
+    pub async fn recall(&self, question: &str, limit: u64) -> Result<Vec<Citation>> {
+        request_id::traced(async {
+            let image_query = first(self.image_embedder.embed(vec![question.to_string()]).await)?;
+            let text_query = first(self.text_embedder.embed(vec![question.to_string()]).await)?;
+            let (image_response, text_response) = self
+                .client
+                .query_points_multivector(&self.collection_name, image_query, text_query, limit)
+                .await?;
+
+            // Keep the best score of each point across both vectors
+            let mut citations: HashMap<String, Citation> = HashMap::new();
+            for point in image_response
+                .result
+                .into_iter()
+                .chain(text_response.result)
+            {
+                let Some((id, citation)) = to_citation(point, self.client.payload_fields()) else {
+                    continue;
+                };
+                citations
+                    .entry(id)
+                    .and_modify(|existing| existing.score = existing.score.max(citation.score))
+                    .or_insert(citation);
+            }
+
+            let mut citations: Vec<Citation> = citations.into_values().collect();
+            citations.sort_by(|a, b| b.score.total_cmp(&a.score));
+            citations.truncate(limit as usize);
+            Ok(citations)
+        })
+        .await
+    }
+
+    /// Bytes or URL of a recalled image, for rendering it.
+    pub async fn resolve_asset(&self, citation: &Citation) -> Result<ResolvedAsset> {
+        Ok(self
+            .asset_resolver
+            .resolve_dyn(&citation.image_path)
+            .await?)
+    }
+
+    /// Answers the question from the `limit` most relevant images.
+    pub async fn ask(&self, question: &str, limit: u64) -> Result<Answer> {
+        request_id::traced(async {
+            let recalled = self.recall(question, limit).await?;
+            if recalled.is_empty() {
+                return Ok(Answer {
+                    text: "No images have been added yet.".to_string(),
+                    citations: Vec::new(),
+                });
+            }
+
+            let response = self
+                .llm_client
+                .send_message(
+                    &self.model,
+                    build_prompt(question, &recalled),
+                    None::<&str>,
+                    self.temperature,
+                )
+                .await?;
+
+            let citations = cited(&response, &recalled);
+            Ok(Answer {
+                text: response,
+                citations,
+            })
+        })
+        .await
+    }
+}
+
+fn first(embeddings: Result<Vec<Vec<f32>>, Box<dyn std::error::Error>>) -> Result<Vec<f32>> {
+    embeddings
+        .map_err(|error| anyhow!("embedding request failed: {error}"))?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("embedding server returned no embeddings"))
+}
+
+fn to_citation(point: ScoredPoint, fields: &PayloadFields) -> Option<(String, Citation)> {
+    let id = point_id_to_string(point.id.as_ref()?);
+    let text = |field: &str| {
+        point
+            .payload
+            .get(field)
+            .and_then(|value| value.as_str())
+            .map(|value| value.to_string())
+    };
+    let citation = Citation {
+        image_path: text(&fields.image_path)?,
+        description: text(&fields.text).unwrap_or_default(),
+        score: point.score,
+    };
+    Some((id, citation))
+}
+
+fn build_prompt(question: &str, recalled: &[Citation]) -> String {
+    let mut prompt = format!("{ANSWER_PROMPT}\n\n");
+    for (i, citation) in recalled.iter().enumerate() {
+        prompt.push_str(&format!(
+            "[{}] {}\n{}\n\n",
+            i + 1,
+            citation.image_path,
+            citation.description
+        ));
+    }
+    prompt.push_str(&format!("Question: {question}"));
+    prompt
+}
+
+fn cited(response: &str, recalled: &[Citation]) -> Vec<Citation> {
+    let citations: Vec<Citation> = recalled
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| response.contains(&format!("[{}]", i + 1)))
+        .map(|(_, citation)| citation.clone())
+        .collect();
+    if citations.is_empty() {
+        return recalled.to_vec();
+    }
+    citations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn citation(image_path: &str) -> Citation {
+        Citation {
+            image_path: image_path.to_string(),
+            description: format!("A photo of {image_path}"),
+            score: 0.5,
+        }
+    }
+
+    #[test]
+    fn test_cited() {
+        let recalled = vec![citation("boot.png"), citation("sandal.png")];
+        assert!(build_prompt("Which shoes are brown?", &recalled)
+            .contains("[2] sandal.png\nA photo of sandal.png"));
+
+        let citations = cited("The boots are brown [1].", &recalled);
+        assert_eq!(citations, vec![citation("boot.png")]);
+        assert_eq!(cited("Brown boots.", &recalled), recalled);
+    }
+}
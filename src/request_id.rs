@@ -0,0 +1,62 @@
+use reqwest::RequestBuilder;
+use std::future::Future;
+use uuid::Uuid;
+
+/// Header carrying the request id to Qdrant, TEI and LLM providers.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+pub fn new_request_id() -> String {
+    Uuid::new_v4().to_string()
+}
+
+/// The id of the operation running on this task, if any.
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Runs `operation` under `request_id`: slow-operation logs mention it and every Qdrant,
+/// TEI and LLM call made by `operation` sends it as `x-request-id`. Work moved to other
+/// tasks with `tokio::spawn` does not inherit it.
+pub async fn scope<F: Future>(request_id: impl Into<String>, operation: F) -> F::Output {
+    REQUEST_ID.scope(request_id.into(), operation).await
+}
+
+/// Runs `operation` under the current request id, or a new one if there is none, so a
+/// nested operation shares the id of the operation that started it.
+pub async fn traced<F: Future>(operation: F) -> F::Output {
+    let request_id = current_request_id().unwrap_or_else(new_request_id);
+    scope(request_id, operation).await
+}
+
+/// Adds the current request id header to an HTTP request, if there is one.
+pub(crate) fn attach(request: RequestBuilder) -> RequestBuilder {
+    match current_request_id() {
+        Some(request_id) => request.header(REQUEST_ID_HEADER, request_id),
+        None => request,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_traced() {
+        assert_eq!(current_request_id(), None);
+
+        let (outer, inner) = scope("req-1", async {
+            let inner = traced(async { current_request_id() }).await;
+            (current_request_id(), inner)
+        })
+        .await;
+        assert_eq!(outer.as_deref(), Some("req-1"));
+        assert_eq!(inner.as_deref(), Some("req-1"));
+
+        let generated = traced(async { current_request_id() }).await.unwrap();
+        assert!(Uuid::parse_str(&generated).is_ok());
+    }
+}
@@ -0,0 +1,174 @@
+use crate::embeddings::adaptive_batch::AdaptiveBatcher;
+use crate::embeddings::text_embedding_inference::TextEmbeddingInference;
+use crate::metrics::ClientStats;
+use crate::request_id::{self, REQUEST_ID_HEADER};
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+
+// TEI rejects requests above its `--max-client-batch-size` (32 by default)
+const DEFAULT_MAX_BATCH_SIZE: usize = 32;
+const TARGET_BATCH_LATENCY: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum EmbeddingInput {
+    Single(String),
+    Batch(Vec<String>),
+}
+
+impl EmbeddingInput {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            EmbeddingInput::Single(text) => vec![text],
+            EmbeddingInput::Batch(texts) => texts,
+        }
+    }
+}
+
+/// Request body of the OpenAI `/v1/embeddings` endpoint. `model` is echoed back; the
+/// model actually used is whichever the embedding server was started with.
+#[derive(Debug, Deserialize)]
+pub struct EmbeddingRequest {
+    pub input: EmbeddingInput,
+    #[serde(default)]
+    pub model: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EmbeddingData {
+    object: &'static str,
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EmbeddingUsage {
+    prompt_tokens: u32,
+    total_tokens: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EmbeddingResponse {
+    object: &'static str,
+    data: Vec<EmbeddingData>,
+    model: String,
+    usage: EmbeddingUsage,
+}
+
+impl EmbeddingResponse {
+    fn new(model: String, embeddings: Vec<Vec<f32>>) -> Self {
+        Self {
+            object: "list",
+            data: embeddings
+                .into_iter()
+                .enumerate()
+                .map(|(index, embedding)| EmbeddingData {
+                    object: "embedding",
+                    index,
+                    embedding,
+                })
+                .collect(),
+            model,
+            // TEI does not report token counts
+            usage: EmbeddingUsage {
+                prompt_tokens: 0,
+                total_tokens: 0,
+            },
+        }
+    }
+}
+
+pub struct EmbeddingProxy {
+    embedder: TextEmbeddingInference,
+    batcher: AdaptiveBatcher,
+}
+
+impl EmbeddingProxy {
+    /// Inputs are embedded in batches that adapt to the embedding server's latency and
+    /// errors, never larger than `max_batch_size`.
+    pub fn new(embedder: TextEmbeddingInference, max_batch_size: Option<usize>) -> Self {
+        Self {
+            embedder,
+            batcher: AdaptiveBatcher::new(
+                1,
+                max_batch_size.unwrap_or(DEFAULT_MAX_BATCH_SIZE),
+                TARGET_BATCH_LATENCY,
+            ),
+        }
+    }
+
+    /// Embeds `texts` in batches, preserving input order.
+    pub async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, String> {
+        self.batcher
+            .embed(&self.embedder, texts)
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+pub fn router(proxy: EmbeddingProxy) -> Router {
+    Router::new()
+        .route("/v1/embeddings", post(create_embeddings))
+        .route("/metrics", get(metrics))
+        .with_state(Arc::new(proxy))
+}
+
+/// Request counters and latencies of the upstream embedding server.
+async fn metrics(State(proxy): State<Arc<EmbeddingProxy>>) -> Json<ClientStats> {
+    Json(proxy.embedder.metrics.stats())
+}
+
+/// Forwards the caller's `x-request-id` to the embedding server, or a new id if it sent none.
+async fn create_embeddings(
+    State(proxy): State<Arc<EmbeddingProxy>>,
+    headers: HeaderMap,
+    Json(request): Json<EmbeddingRequest>,
+) -> Result<Json<EmbeddingResponse>, (StatusCode, Json<serde_json::Value>)> {
+    let request_id = headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+        .unwrap_or_else(request_id::new_request_id);
+    let embeddings = request_id::scope(request_id, proxy.embed(request.input.into_vec()))
+        .await
+        .map_err(|e| {
+            // Same error shape as the OpenAI API
+            (
+                StatusCode::BAD_GATEWAY,
+                Json(json!({"error": {"message": e, "type": "upstream_error", "code": null}})),
+            )
+        })?;
+    Ok(Json(EmbeddingResponse::new(request.model, embeddings)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedding_request_input() {
+        let single: EmbeddingRequest =
+            serde_json::from_value(json!({"input": "Hello", "model": "bge"})).unwrap();
+        assert_eq!(single.input.into_vec(), vec!["Hello".to_string()]);
+
+        let batch: EmbeddingRequest =
+            serde_json::from_value(json!({"input": ["Hello", "World"]})).unwrap();
+        assert_eq!(batch.input.into_vec().len(), 2);
+    }
+
+    #[test]
+    fn test_embedding_response_format() {
+        let response = EmbeddingResponse::new("bge".to_string(), vec![vec![0.1], vec![0.2]]);
+        let value = serde_json::to_value(response).unwrap();
+        assert_eq!(value["object"], "list");
+        assert_eq!(value["data"][1]["index"], 1);
+        assert_eq!(value["data"][1]["object"], "embedding");
+        assert_eq!(value["model"], "bge");
+    }
+}
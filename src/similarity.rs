@@ -0,0 +1,108 @@
+// Client-side vector math, 8 lanes at a time with `wide` (SSE/AVX or NEON depending on
+// the target) and scalar code for the remainder
+
+use wide::f32x8;
+
+const LANES: usize = 8;
+
+fn lanes(chunk: &[f32]) -> f32x8 {
+    let mut array = [0.0; LANES];
+    array.copy_from_slice(chunk);
+    f32x8::from(array)
+}
+
+/// Dot product over the common prefix of `a` and `b`.
+pub fn dot(a: &[f32], b: &[f32]) -> f32 {
+    let len = a.len().min(b.len());
+    let (a, b) = (&a[..len], &b[..len]);
+    let mut sum = f32x8::ZERO;
+    let a_chunks = a.chunks_exact(LANES);
+    let b_chunks = b.chunks_exact(LANES);
+    let tail: f32 = a_chunks
+        .remainder()
+        .iter()
+        .zip(b_chunks.remainder())
+        .map(|(x, y)| x * y)
+        .sum();
+    for (x, y) in a_chunks.zip(b_chunks) {
+        sum = lanes(x).mul_add(lanes(y), sum);
+    }
+    sum.reduce_add() + tail
+}
+
+/// Squared Euclidean distance; cheaper than [`l2`] when only the ordering matters.
+pub fn l2_squared(a: &[f32], b: &[f32]) -> f32 {
+    let len = a.len().min(b.len());
+    let (a, b) = (&a[..len], &b[..len]);
+    let mut sum = f32x8::ZERO;
+    let a_chunks = a.chunks_exact(LANES);
+    let b_chunks = b.chunks_exact(LANES);
+    let tail: f32 = a_chunks
+        .remainder()
+        .iter()
+        .zip(b_chunks.remainder())
+        .map(|(x, y)| (x - y) * (x - y))
+        .sum();
+    for (x, y) in a_chunks.zip(b_chunks) {
+        let diff = lanes(x) - lanes(y);
+        sum = diff.mul_add(diff, sum);
+    }
+    sum.reduce_add() + tail
+}
+
+pub fn l2(a: &[f32], b: &[f32]) -> f32 {
+    l2_squared(a, b).sqrt()
+}
+
+pub fn norm(a: &[f32]) -> f32 {
+    dot(a, a).sqrt()
+}
+
+/// Cosine similarity in `[-1, 1]`; 0 if either vector is all zeros.
+pub fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let denominator = norm(a) * norm(b);
+    if denominator == 0.0 {
+        return 0.0;
+    }
+    dot(a, b) / denominator
+}
+
+/// Scales `a` to unit length in place, so that [`dot`] equals [`cosine`] afterwards.
+pub fn normalize(a: &mut [f32]) {
+    let norm = norm(a);
+    if norm > 0.0 {
+        a.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scalar_dot(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| x * y).sum()
+    }
+
+    #[test]
+    fn test_matches_scalar() {
+        // 19 = two full chunks and a remainder
+        let a: Vec<f32> = (0..19).map(|i| i as f32 * 0.5 - 3.0).collect();
+        let b: Vec<f32> = (0..19).map(|i| (i as f32).sin()).collect();
+
+        assert!((dot(&a, &b) - scalar_dot(&a, &b)).abs() < 1e-4);
+        let scalar_l2: f32 = a.iter().zip(&b).map(|(x, y)| (x - y) * (x - y)).sum();
+        assert!((l2_squared(&a, &b) - scalar_l2).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_cosine() {
+        assert!((cosine(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine(&[1.0, 0.0], &[0.0, 3.0]).abs() < 1e-6);
+        assert_eq!(cosine(&[0.0; 4], &[1.0; 4]), 0.0);
+
+        let mut a = vec![3.0, 4.0];
+        normalize(&mut a);
+        assert!((norm(&a) - 1.0).abs() < 1e-6);
+        assert_eq!(l2(&[0.0, 0.0], &[3.0, 4.0]), 5.0);
+    }
+}
@@ -0,0 +1,431 @@
+use crate::testing::hash_embedder::HashEmbedder;
+use crate::vectorstore::qdrant_client::point_id_from_key;
+use crate::vectorstore::vector_store::{VectorPoint, VectorStore, VectorStoreError};
+use image::{ImageFormat, Rgb, RgbImage};
+use serde::Serialize;
+use serde_json::{json, Map};
+use std::io::Cursor;
+
+const IMAGE_SIZE: u32 = 32;
+
+// Words shared by every topic, mixed into documents so topics are not trivially separable
+const FILLER: &[&str] = &[
+    "the", "a", "with", "for", "and", "new", "best", "every", "day", "good", "use", "more",
+    "about", "from", "this", "our", "guide", "notes", "overview", "details",
+];
+
+const TOPICS: &[(&str, &[&str])] = &[
+    (
+        "footwear",
+        &[
+            "boots",
+            "sandals",
+            "sneakers",
+            "leather",
+            "sole",
+            "laces",
+            "waterproof",
+            "hiking",
+            "heel",
+            "insole",
+            "size",
+            "fit",
+            "running",
+            "suede",
+            "trail",
+        ],
+    ),
+    (
+        "cooking",
+        &[
+            "recipe", "oven", "garlic", "simmer", "sauce", "flour", "butter", "roast", "onion",
+            "pan", "bake", "season", "broth", "knife", "dough",
+        ],
+    ),
+    (
+        "finance",
+        &[
+            "invoice",
+            "budget",
+            "interest",
+            "loan",
+            "account",
+            "tax",
+            "refund",
+            "payment",
+            "balance",
+            "credit",
+            "savings",
+            "fee",
+            "statement",
+            "transfer",
+            "deposit",
+        ],
+    ),
+    (
+        "travel",
+        &[
+            "flight",
+            "hotel",
+            "passport",
+            "luggage",
+            "itinerary",
+            "beach",
+            "booking",
+            "train",
+            "airport",
+            "visa",
+            "tour",
+            "museum",
+            "ferry",
+            "map",
+            "check-in",
+        ],
+    ),
+    (
+        "software",
+        &[
+            "deploy", "server", "latency", "database", "query", "cache", "release", "bug",
+            "compile", "index", "cluster", "api", "request", "timeout", "schema",
+        ],
+    ),
+];
+
+/// A generated document. Near-duplicates copy an earlier document with one word changed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CorpusDocument {
+    pub id: String,
+    pub topic: String,
+    pub text: String,
+    /// Id of the document this one nearly duplicates.
+    pub duplicate_of: Option<String>,
+    /// PNG image attached to multimodal documents.
+    #[serde(skip)]
+    pub image: Option<Vec<u8>>,
+}
+
+/// A generated query about one topic.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CorpusQuery {
+    pub topic: String,
+    pub text: String,
+}
+
+/// Seeded generator of synthetic corpora for benchmarks and examples: the same settings
+/// always give the same documents, so performance numbers are reproducible without
+/// shipping a dataset. Texts are drawn from small topic vocabularies mixed with filler
+/// words.
+///
+/// `CorpusGenerator::new(42).with_documents(10_000).generate()`
+#[derive(Debug, Clone)]
+pub struct CorpusGenerator {
+    seed: u64,
+    documents: usize,
+    min_words: usize,
+    max_words: usize,
+    topic_weights: Vec<(String, f64)>,
+    duplicate_ratio: f64,
+    image_ratio: f64,
+}
+
+impl CorpusGenerator {
+    /// 1000 documents of 20 to 120 words evenly spread over the built-in topics, 5% of
+    /// them near-duplicates, without images.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            documents: 1000,
+            min_words: 20,
+            max_words: 120,
+            topic_weights: TOPICS
+                .iter()
+                .map(|(topic, _)| (topic.to_string(), 1.0))
+                .collect(),
+            duplicate_ratio: 0.05,
+            image_ratio: 0.0,
+        }
+    }
+
+    /// Names of the built-in topics.
+    pub fn topics() -> Vec<&'static str> {
+        TOPICS.iter().map(|(topic, _)| *topic).collect()
+    }
+
+    pub fn with_documents(mut self, documents: usize) -> Self {
+        self.documents = documents;
+        self
+    }
+
+    /// Word count range of the documents, inclusive.
+    pub fn with_length(mut self, min_words: usize, max_words: usize) -> Self {
+        self.min_words = min_words.max(1);
+        self.max_words = max_words.max(self.min_words);
+        self
+    }
+
+    /// Relative weights of the built-in topics; unknown topics and non-positive weights
+    /// are ignored. Topics left out are not generated.
+    pub fn with_topic_mixture(mut self, weights: &[(&str, f64)]) -> Self {
+        let weights: Vec<(String, f64)> = weights
+            .iter()
+            .filter(|(topic, weight)| *weight > 0.0 && vocabulary(topic).is_some())
+            .map(|(topic, weight)| (topic.to_string(), *weight))
+            .collect();
+        if !weights.is_empty() {
+            self.topic_weights = weights;
+        }
+        self
+    }
+
+    /// Share of documents that nearly duplicate an earlier one.
+    pub fn with_duplicate_ratio(mut self, ratio: f64) -> Self {
+        self.duplicate_ratio = ratio.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Share of documents with an attached image.
+    pub fn with_image_ratio(mut self, ratio: f64) -> Self {
+        self.image_ratio = ratio.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn generate(&self) -> Vec<CorpusDocument> {
+        let mut rng = Rng::new(self.seed);
+        let mut documents: Vec<CorpusDocument> = Vec::with_capacity(self.documents);
+        for idx in 0..self.documents {
+            let id = point_id_from_key(&format!("corpus-{}-{idx}", self.seed));
+            let mut document = if !documents.is_empty() && rng.chance(self.duplicate_ratio) {
+                let original = &documents[rng.below(documents.len())];
+                CorpusDocument {
+                    id,
+                    topic: original.topic.clone(),
+                    text: near_duplicate(&original.text, &original.topic, &mut rng),
+                    duplicate_of: Some(original.id.clone()),
+                    image: None,
+                }
+            } else {
+                let topic = self.pick_topic(&mut rng);
+                let words = self.min_words + rng.below(self.max_words - self.min_words + 1);
+                CorpusDocument {
+                    id,
+                    text: sentence(&topic, words, &mut rng),
+                    topic,
+                    duplicate_of: None,
+                    image: None,
+                }
+            };
+            if rng.chance(self.image_ratio) {
+                document.image = Some(image(&document.topic, &mut rng));
+            }
+            documents.push(document);
+        }
+        documents
+    }
+
+    /// `count` short queries following the topic mixture, from a seed distinct from the
+    /// documents'.
+    pub fn queries(&self, count: usize) -> Vec<CorpusQuery> {
+        let mut rng = Rng::new(self.seed ^ 0x9e37_79b9_7f4a_7c15);
+        (0..count)
+            .map(|_| {
+                let topic = self.pick_topic(&mut rng);
+                let words = 2 + rng.below(3);
+                CorpusQuery {
+                    text: topic_words(&topic, words, &mut rng),
+                    topic,
+                }
+            })
+            .collect()
+    }
+
+    fn pick_topic(&self, rng: &mut Rng) -> String {
+        let total: f64 = self.topic_weights.iter().map(|(_, weight)| weight).sum();
+        let mut target = rng.unit() * total;
+        for (topic, weight) in &self.topic_weights {
+            if target < *weight {
+                return topic.clone();
+            }
+            target -= weight;
+        }
+        self.topic_weights[self.topic_weights.len() - 1].0.clone()
+    }
+}
+
+/// Embeds documents with `embedder` into points with `text`, `topic` and `duplicate_of`
+/// payload fields.
+pub fn to_points(documents: &[CorpusDocument], embedder: &HashEmbedder) -> Vec<VectorPoint> {
+    documents
+        .iter()
+        .map(|document| {
+            let mut payload = Map::new();
+            payload.insert("text".to_string(), json!(document.text));
+            payload.insert("topic".to_string(), json!(document.topic));
+            if let Some(original) = &document.duplicate_of {
+                payload.insert("duplicate_of".to_string(), json!(original));
+            }
+            VectorPoint::new(
+                document.id.clone(),
+                embedder.embed_one(&document.text),
+                payload,
+            )
+        })
+        .collect()
+}
+
+/// Embeds and upserts documents into `collection`, in batches of 256.
+pub async fn load_into(
+    store: &impl VectorStore,
+    collection: &str,
+    documents: &[CorpusDocument],
+    embedder: &HashEmbedder,
+) -> Result<(), VectorStoreError> {
+    for batch in documents.chunks(256) {
+        store.upsert(collection, to_points(batch, embedder)).await?;
+    }
+    Ok(())
+}
+
+fn vocabulary(topic: &str) -> Option<&'static [&'static str]> {
+    TOPICS
+        .iter()
+        .find(|(name, _)| *name == topic)
+        .map(|(_, words)| *words)
+}
+
+fn topic_words(topic: &str, count: usize, rng: &mut Rng) -> String {
+    let words = vocabulary(topic).unwrap_or(FILLER);
+    (0..count)
+        .map(|_| words[rng.below(words.len())])
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Topic words with about one filler word in four, split into sentences of up to 12 words
+fn sentence(topic: &str, count: usize, rng: &mut Rng) -> String {
+    let vocabulary = vocabulary(topic).unwrap_or(FILLER);
+    let mut text = String::new();
+    let mut in_sentence = 0;
+    for idx in 0..count {
+        let words = if rng.chance(0.25) { FILLER } else { vocabulary };
+        let word = words[rng.below(words.len())];
+        if in_sentence == 0 {
+            let mut chars = word.chars();
+            if let Some(first) = chars.next() {
+                text.extend(first.to_uppercase());
+                text.push_str(chars.as_str());
+            }
+        } else {
+            text.push_str(word);
+        }
+        in_sentence += 1;
+        if idx + 1 == count || (in_sentence >= 4 && rng.chance(0.15)) || in_sentence == 12 {
+            text.push('.');
+            in_sentence = 0;
+        }
+        if idx + 1 < count {
+            text.push(' ');
+        }
+    }
+    text
+}
+
+fn near_duplicate(text: &str, topic: &str, rng: &mut Rng) -> String {
+    let mut words: Vec<String> = text.split(' ').map(str::to_string).collect();
+    let idx = rng.below(words.len());
+    let replacement = topic_words(topic, 1, rng);
+    let punctuation = if words[idx].ends_with('.') { "." } else { "" };
+    words[idx] = format!("{replacement}{punctuation}");
+    words.join(" ")
+}
+
+// Noise around a color derived from the topic name, so images of a topic look alike
+fn image(topic: &str, rng: &mut Rng) -> Vec<u8> {
+    let hash = crate::utils::fnv1a(topic.as_bytes());
+    let base = [hash as u8, (hash >> 8) as u8, (hash >> 16) as u8];
+    let mut pixels = RgbImage::new(IMAGE_SIZE, IMAGE_SIZE);
+    for pixel in pixels.pixels_mut() {
+        let noise = rng.below(48) as u8;
+        *pixel = Rgb(base.map(|channel| channel.saturating_add(noise)));
+    }
+    let mut bytes = Cursor::new(Vec::new());
+    pixels
+        .write_to(&mut bytes, ImageFormat::Png)
+        .expect("encoding a PNG in memory does not fail");
+    bytes.into_inner()
+}
+
+// xorshift64*, deterministic across runs and platforms
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // A zero state would stay zero
+        Self(seed.wrapping_mul(0x2545_f491_4f6c_dd1d) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n.max(1) as u64) as usize
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        self.unit() < probability
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_is_reproducible() {
+        let generator = CorpusGenerator::new(7)
+            .with_documents(200)
+            .with_length(5, 30)
+            .with_duplicate_ratio(0.2)
+            .with_image_ratio(0.1);
+        let documents = generator.generate();
+        assert_eq!(documents, generator.generate());
+        assert_eq!(documents.len(), 200);
+        assert_ne!(
+            documents,
+            CorpusGenerator::new(8).with_documents(200).generate()
+        );
+
+        let duplicates = documents
+            .iter()
+            .filter(|d| d.duplicate_of.is_some())
+            .count();
+        assert!((20..=60).contains(&duplicates));
+        assert!(documents.iter().any(|d| d.image.is_some()));
+        for document in documents.iter().filter(|d| d.duplicate_of.is_none()) {
+            assert!((5..=30).contains(&document.text.split(' ').count()));
+            assert!(document.text.ends_with('.'));
+        }
+    }
+
+    #[test]
+    fn test_topic_mixture() {
+        let documents = CorpusGenerator::new(1)
+            .with_documents(100)
+            .with_duplicate_ratio(0.0)
+            .with_topic_mixture(&[("cooking", 1.0), ("astronomy", 5.0)])
+            .generate();
+        assert!(documents.iter().all(|d| d.topic == "cooking"));
+        let queries = CorpusGenerator::new(1).queries(10);
+        assert_eq!(queries.len(), 10);
+        assert!(queries
+            .iter()
+            .all(|q| CorpusGenerator::topics().contains(&q.topic.as_str())));
+    }
+}
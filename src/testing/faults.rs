@@ -0,0 +1,528 @@
+use crate::circuit_breaker::TransientError;
+use crate::embeddings::embedder::Embedder;
+use crate::llm::llm_client::{ChatMessage, LlmClientChat};
+use crate::vectorstore::filter::MetadataFilter;
+use crate::vectorstore::vector_store::{
+    MultiVectorPoint, SearchHit, VectorPoint, VectorStore, VectorStoreError,
+};
+use serde::Serialize;
+use serde_json::{Map, Value as JsonValue};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Error)]
+#[error("Injected fault in call {call}")]
+pub struct InjectedFault {
+    /// Number of the failed call, from 1.
+    pub call: u64,
+}
+
+impl From<InjectedFault> for VectorStoreError {
+    fn from(fault: InjectedFault) -> Self {
+        VectorStoreError::IoError(std::io::Error::other(fault))
+    }
+}
+
+/// Faults injected so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct FaultStats {
+    pub calls: u64,
+    pub errors: u64,
+    pub latency_spikes: u64,
+    pub malformed: u64,
+}
+
+struct InjectorState {
+    rng: u64,
+    stats: FaultStats,
+}
+
+/// Decides, call by call, which faults a wrapped client suffers: a latency spike, an
+/// error instead of the request, or a malformed response. Draws come from a seeded
+/// generator, so a test sees the same faults on every run.
+pub struct FaultInjector {
+    error_rate: f64,
+    latency_rate: f64,
+    latency: Duration,
+    malformed_rate: f64,
+    state: Mutex<InjectorState>,
+}
+
+impl FaultInjector {
+    /// An injector without faults until rates are set.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            error_rate: 0.0,
+            latency_rate: 0.0,
+            latency: Duration::ZERO,
+            malformed_rate: 0.0,
+            state: Mutex::new(InjectorState {
+                // xorshift gets stuck on 0
+                rng: seed | 1,
+                stats: FaultStats::default(),
+            }),
+        }
+    }
+
+    /// Share of calls failing with [`InjectedFault`] without reaching the client.
+    pub fn with_error_rate(mut self, rate: f64) -> Self {
+        self.error_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Share of calls delayed by `latency` before being sent.
+    pub fn with_latency_spikes(mut self, rate: f64, latency: Duration) -> Self {
+        self.latency_rate = rate.clamp(0.0, 1.0);
+        self.latency = latency;
+        self
+    }
+
+    /// Share of successful calls whose response is corrupted; what that means depends on
+    /// the wrapper.
+    pub fn with_malformed_rate(mut self, rate: f64) -> Self {
+        self.malformed_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn stats(&self) -> FaultStats {
+        self.state.lock().unwrap().stats
+    }
+
+    // xorshift64*, as in the HNSW index
+    fn draw(state: &mut InjectorState) -> f64 {
+        state.rng ^= state.rng >> 12;
+        state.rng ^= state.rng << 25;
+        state.rng ^= state.rng >> 27;
+        let value = state.rng.wrapping_mul(0x2545_f491_4f6c_dd1d);
+        (value >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // Sleeps through a latency spike if one is drawn, then fails the call or returns
+    // whether its response is to be malformed
+    async fn before_call(&self) -> Result<bool, InjectedFault> {
+        let (call, spike, error, malformed) = {
+            let mut state = self.state.lock().unwrap();
+            state.stats.calls += 1;
+            let spike = Self::draw(&mut state) < self.latency_rate;
+            let error = Self::draw(&mut state) < self.error_rate;
+            let malformed = !error && Self::draw(&mut state) < self.malformed_rate;
+            state.stats.latency_spikes += spike as u64;
+            state.stats.errors += error as u64;
+            state.stats.malformed += malformed as u64;
+            (state.stats.calls, spike, error, malformed)
+        };
+        if spike {
+            tokio::time::sleep(self.latency).await;
+        }
+        if error {
+            return Err(InjectedFault { call });
+        }
+        Ok(malformed)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum FaultyError<E: std::error::Error + 'static> {
+    #[error(transparent)]
+    Injected(#[from] InjectedFault),
+    #[error(transparent)]
+    Provider(E),
+}
+
+impl<E: std::error::Error + TransientError + 'static> TransientError for FaultyError<E> {
+    fn is_transient(&self) -> bool {
+        match self {
+            FaultyError::Injected(_) => true,
+            FaultyError::Provider(e) => e.is_transient(),
+        }
+    }
+}
+
+// Cut off halfway, as by a dropped connection, so JSON answers no longer parse
+fn truncate_response(response: String) -> String {
+    let half = response.chars().count() / 2;
+    response.chars().take(half).collect()
+}
+
+/// Chat client suffering the faults of a [`FaultInjector`]; malformed responses are cut
+/// off halfway. Clients made with [`LlmClientChat::new`] inject no faults.
+pub struct FaultyChat<C: LlmClientChat> {
+    client: C,
+    injector: FaultInjector,
+}
+
+impl<C: LlmClientChat> FaultyChat<C> {
+    pub fn wrap(client: C, injector: FaultInjector) -> Self {
+        Self { client, injector }
+    }
+
+    pub fn client(&self) -> &C {
+        &self.client
+    }
+
+    pub fn injector(&self) -> &FaultInjector {
+        &self.injector
+    }
+}
+
+impl<C: LlmClientChat> LlmClientChat for FaultyChat<C> {
+    type Error = FaultyError<C::Error>;
+
+    fn new(base_url: Option<&str>, api_key: Option<&str>) -> Self {
+        Self::wrap(C::new(base_url, api_key), FaultInjector::new(0))
+    }
+
+    async fn send_message(
+        &self,
+        model: impl Into<String>,
+        text: impl AsRef<str>,
+        image_path: Option<impl AsRef<Path>>,
+        temperature: Option<f32>,
+    ) -> Result<String, Self::Error> {
+        let malformed = self.injector.before_call().await?;
+        let response = self
+            .client
+            .send_message(model, text, image_path, temperature)
+            .await
+            .map_err(FaultyError::Provider)?;
+        Ok(if malformed {
+            truncate_response(response)
+        } else {
+            response
+        })
+    }
+
+    async fn send_message_json(
+        &self,
+        model: impl Into<String>,
+        text: impl AsRef<str>,
+        temperature: Option<f32>,
+    ) -> Result<String, Self::Error> {
+        let malformed = self.injector.before_call().await?;
+        let response = self
+            .client
+            .send_message_json(model, text, temperature)
+            .await
+            .map_err(FaultyError::Provider)?;
+        Ok(if malformed {
+            truncate_response(response)
+        } else {
+            response
+        })
+    }
+
+    async fn send_conversation(
+        &self,
+        model: impl Into<String>,
+        system: Option<&str>,
+        messages: &[ChatMessage],
+        temperature: Option<f32>,
+    ) -> Result<String, Self::Error> {
+        let malformed = self.injector.before_call().await?;
+        let response = self
+            .client
+            .send_conversation(model, system, messages, temperature)
+            .await
+            .map_err(FaultyError::Provider)?;
+        Ok(if malformed {
+            truncate_response(response)
+        } else {
+            response
+        })
+    }
+
+    async fn warm_up(&self) -> Result<(), Self::Error> {
+        self.injector.before_call().await?;
+        self.client.warm_up().await.map_err(FaultyError::Provider)
+    }
+}
+
+/// Embedder suffering the faults of a [`FaultInjector`]; malformed responses have every
+/// embedding cut to half its dimension.
+pub struct FaultyEmbedder<E: Embedder> {
+    embedder: E,
+    injector: FaultInjector,
+}
+
+impl<E: Embedder> FaultyEmbedder<E> {
+    pub fn wrap(embedder: E, injector: FaultInjector) -> Self {
+        Self { embedder, injector }
+    }
+
+    pub fn injector(&self) -> &FaultInjector {
+        &self.injector
+    }
+}
+
+impl<E: Embedder> Embedder for FaultyEmbedder<E> {
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, Box<dyn std::error::Error>> {
+        let malformed = self.injector.before_call().await?;
+        let mut embeddings = self.embedder.embed(texts).await?;
+        if malformed {
+            for embedding in &mut embeddings {
+                embedding.truncate(embedding.len() / 2);
+            }
+        }
+        Ok(embeddings)
+    }
+}
+
+/// Vector store suffering the faults of a [`FaultInjector`]; malformed responses come
+/// back without payloads. Every call counts, so a failed batch of an ingestion fails
+/// alone while the others go through.
+pub struct FaultyVectorStore<S: VectorStore> {
+    store: S,
+    injector: FaultInjector,
+}
+
+impl<S: VectorStore> FaultyVectorStore<S> {
+    pub fn wrap(store: S, injector: FaultInjector) -> Self {
+        Self { store, injector }
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    pub fn injector(&self) -> &FaultInjector {
+        &self.injector
+    }
+}
+
+fn strip_payloads(hits: &mut [SearchHit]) {
+    for hit in hits {
+        hit.payload.clear();
+    }
+}
+
+impl<S: VectorStore> VectorStore for FaultyVectorStore<S> {
+    async fn upsert(
+        &self,
+        collection: &str,
+        points: Vec<VectorPoint>,
+    ) -> Result<(), VectorStoreError> {
+        self.injector.before_call().await?;
+        self.store.upsert(collection, points).await
+    }
+
+    async fn query_filtered(
+        &self,
+        collection: &str,
+        vector: Vec<f32>,
+        limit: u64,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<SearchHit>, VectorStoreError> {
+        let malformed = self.injector.before_call().await?;
+        let mut hits = self
+            .store
+            .query_filtered(collection, vector, limit, filter)
+            .await?;
+        if malformed {
+            strip_payloads(&mut hits);
+        }
+        Ok(hits)
+    }
+
+    async fn delete(&self, collection: &str, ids: Vec<String>) -> Result<(), VectorStoreError> {
+        self.injector.before_call().await?;
+        self.store.delete(collection, ids).await
+    }
+
+    async fn delete_matching(
+        &self,
+        collection: &str,
+        filter: &MetadataFilter,
+    ) -> Result<u64, VectorStoreError> {
+        self.injector.before_call().await?;
+        self.store.delete_matching(collection, filter).await
+    }
+
+    async fn set_payload(
+        &self,
+        collection: &str,
+        ids: Vec<String>,
+        fields: Map<String, JsonValue>,
+    ) -> Result<(), VectorStoreError> {
+        self.injector.before_call().await?;
+        self.store.set_payload(collection, ids, fields).await
+    }
+
+    async fn delete_payload(
+        &self,
+        collection: &str,
+        ids: Vec<String>,
+        keys: Vec<String>,
+    ) -> Result<(), VectorStoreError> {
+        self.injector.before_call().await?;
+        self.store.delete_payload(collection, ids, keys).await
+    }
+
+    async fn scroll(
+        &self,
+        collection: &str,
+        offset: Option<String>,
+        limit: u64,
+    ) -> Result<(Vec<VectorPoint>, Option<String>), VectorStoreError> {
+        let malformed = self.injector.before_call().await?;
+        let (mut points, next) = self.store.scroll(collection, offset, limit).await?;
+        if malformed {
+            for point in &mut points {
+                point.payload.clear();
+            }
+        }
+        Ok((points, next))
+    }
+
+    async fn scroll_multi(
+        &self,
+        collection: &str,
+        vector_names: &[&str],
+        offset: Option<String>,
+        limit: u64,
+    ) -> Result<(Vec<MultiVectorPoint>, Option<String>), VectorStoreError> {
+        let malformed = self.injector.before_call().await?;
+        let (mut points, next) = self
+            .store
+            .scroll_multi(collection, vector_names, offset, limit)
+            .await?;
+        if malformed {
+            for point in &mut points {
+                point.payload.clear();
+            }
+        }
+        Ok((points, next))
+    }
+
+    async fn upsert_multi(
+        &self,
+        collection: &str,
+        points: Vec<MultiVectorPoint>,
+    ) -> Result<(), VectorStoreError> {
+        self.injector.before_call().await?;
+        self.store.upsert_multi(collection, points).await
+    }
+
+    async fn update_named_vector(
+        &self,
+        collection: &str,
+        point_id: &str,
+        vector_name: &str,
+        vector: Vec<f32>,
+    ) -> Result<(), VectorStoreError> {
+        self.injector.before_call().await?;
+        self.store
+            .update_named_vector(collection, point_id, vector_name, vector)
+            .await
+    }
+
+    async fn query_named(
+        &self,
+        collection: &str,
+        vector_name: &str,
+        vector: Vec<f32>,
+        limit: u64,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<SearchHit>, VectorStoreError> {
+        let malformed = self.injector.before_call().await?;
+        let mut hits = self
+            .store
+            .query_named(collection, vector_name, vector, limit, filter)
+            .await?;
+        if malformed {
+            strip_payloads(&mut hits);
+        }
+        Ok(hits)
+    }
+
+    async fn delete_multi(
+        &self,
+        collection: &str,
+        vector_names: &[&str],
+        ids: Vec<String>,
+    ) -> Result<(), VectorStoreError> {
+        self.injector.before_call().await?;
+        self.store.delete_multi(collection, vector_names, ids).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit_breaker::{CircuitBreaker, CircuitState, GuardedChat, GuardedError};
+    use crate::testing::hash_embedder::HashEmbedder;
+    use crate::vectorstore::in_memory::InMemoryVectorStore;
+
+    struct EchoChat;
+
+    impl LlmClientChat for EchoChat {
+        type Error = std::io::Error;
+
+        fn new(_base_url: Option<&str>, _api_key: Option<&str>) -> Self {
+            EchoChat
+        }
+
+        async fn send_message(
+            &self,
+            _model: impl Into<String>,
+            text: impl AsRef<str>,
+            _image_path: Option<impl AsRef<Path>>,
+            _temperature: Option<f32>,
+        ) -> Result<String, Self::Error> {
+            Ok(text.as_ref().to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_circuit_opens_on_injected_errors() {
+        let chat = GuardedChat::wrap(
+            FaultyChat::wrap(EchoChat, FaultInjector::new(7).with_error_rate(1.0)),
+            CircuitBreaker::new("faulty", 3, Duration::from_secs(60)),
+        );
+        for _ in 0..3 {
+            let result = chat.send_message("m", "hi", None::<&str>, None).await;
+            assert!(matches!(
+                result,
+                Err(GuardedError::Provider(FaultyError::Injected(_)))
+            ));
+        }
+        assert_eq!(chat.circuit_breaker().state(), CircuitState::Open);
+        let result = chat.send_message("m", "hi", None::<&str>, None).await;
+        assert!(matches!(result, Err(GuardedError::Open(_))));
+        assert_eq!(chat.client().injector().stats().calls, 3);
+    }
+
+    #[tokio::test]
+    async fn test_faults_are_seeded() {
+        let faults = |seed| async move {
+            let store = FaultyVectorStore::wrap(
+                InMemoryVectorStore::new(),
+                FaultInjector::new(seed).with_error_rate(0.3),
+            );
+            let mut failed = Vec::new();
+            for batch in 0..20 {
+                let point = VectorPoint::new(batch.to_string(), vec![1.0, 0.0], Default::default());
+                if store.upsert("products", vec![point]).await.is_err() {
+                    failed.push(batch);
+                }
+            }
+            (failed, store.injector().stats())
+        };
+        let (failed, stats) = faults(42).await;
+        assert_eq!(faults(42).await.0, failed);
+        assert!(!failed.is_empty() && failed.len() < 20);
+        assert_eq!(stats.errors, failed.len() as u64);
+
+        let embedder = FaultyEmbedder::wrap(
+            HashEmbedder::new(8),
+            FaultInjector::new(1).with_malformed_rate(1.0),
+        );
+        let embeddings = embedder.embed(vec!["boots".to_string()]).await.unwrap();
+        assert_eq!(embeddings[0].len(), 4);
+        assert_eq!(
+            truncate_response(r#"{"score": 7}"#.to_string()),
+            r#"{"scor"#
+        );
+    }
+}
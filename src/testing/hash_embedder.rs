@@ -0,0 +1,104 @@
+use crate::embeddings::embedder::Embedder;
+use crate::similarity::normalize;
+use crate::utils::fnv1a;
+
+// Character n-gram length; words are padded with spaces so short words still count
+const NGRAM: usize = 3;
+
+/// Deterministic offline [`Embedder`]: every lowercased word and character trigram is
+/// hashed into one of `dim` signed buckets and the counts are L2-normalized, so strings
+/// sharing words or spellings get a high cosine similarity. It captures no meaning
+/// ("boots" and "shoes" are unrelated), which is enough to test chunking, filtering and
+/// ranking logic without an embedding server.
+#[derive(Debug, Clone, Copy)]
+pub struct HashEmbedder {
+    dim: usize,
+}
+
+impl HashEmbedder {
+    pub fn new(dim: usize) -> Self {
+        Self { dim: dim.max(1) }
+    }
+
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// Embedding of one text; all zeros for a text without words.
+    pub fn embed_one(&self, text: &str) -> Vec<f32> {
+        let mut vector = vec![0.0; self.dim];
+        let mut add = |feature: &[u8]| {
+            let hash = fnv1a(feature);
+            let bucket = (hash % self.dim as u64) as usize;
+            vector[bucket] += if hash >> 63 == 0 { 1.0 } else { -1.0 };
+        };
+        for word in text.split_whitespace() {
+            let word = word.to_lowercase();
+            add(word.as_bytes());
+            let padded: Vec<char> = format!(" {word} ").chars().collect();
+            for ngram in padded.windows(NGRAM) {
+                add(ngram.iter().collect::<String>().as_bytes());
+            }
+        }
+        normalize(&mut vector);
+        vector
+    }
+}
+
+impl Embedder for HashEmbedder {
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, Box<dyn std::error::Error>> {
+        Ok(texts.iter().map(|text| self.embed_one(text)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::retriever::{Retriever, VectorStoreRetriever};
+    use crate::similarity::cosine;
+    use crate::vectorstore::in_memory::InMemoryVectorStore;
+    use crate::vectorstore::vector_store::{VectorPoint, VectorStore};
+    use serde_json::{json, Map};
+
+    #[test]
+    fn test_hash_embedder() {
+        let embedder = HashEmbedder::new(256);
+        let boots = embedder.embed_one("Waterproof leather boots");
+        assert_eq!(boots.len(), 256);
+        assert_eq!(boots, embedder.embed_one("waterproof  leather boots"));
+
+        let similar = cosine(&boots, &embedder.embed_one("leather boot"));
+        let unrelated = cosine(&boots, &embedder.embed_one("linen summer shirt"));
+        assert!(similar > 0.4, "{similar}");
+        assert!(similar > unrelated + 0.3, "{similar} vs {unrelated}");
+        assert!(embedder.embed_one("  ").iter().all(|value| *value == 0.0));
+    }
+
+    #[tokio::test]
+    async fn test_retrieval_offline() {
+        let embedder = HashEmbedder::new(128);
+        let store = InMemoryVectorStore::new();
+        let texts = [
+            "Returns are accepted within thirty days",
+            "Boots ship in two days",
+            "Sandals are final sale",
+        ];
+        let points = texts
+            .iter()
+            .enumerate()
+            .map(|(idx, text)| {
+                let mut payload = Map::new();
+                payload.insert("text".to_string(), json!(text));
+                VectorPoint::new(idx.to_string(), embedder.embed_one(text), payload)
+            })
+            .collect();
+        store.upsert("policies", points).await.unwrap();
+
+        let retriever = VectorStoreRetriever::new(store, embedder, "policies");
+        let memories = retriever
+            .retrieve("when do boots ship", 1, None)
+            .await
+            .unwrap();
+        assert_eq!(memories[0].text, "Boots ship in two days");
+    }
+}
@@ -0,0 +1,38 @@
+use crate::utils::{chunk_text, estimate_tokens};
+use crate::vectorstore::interop::{is_representable, round_trip, Document, PayloadConvention};
+
+// Checks shared by the property tests and the fuzz targets under `fuzz/`. They panic with
+// the offending input when an invariant breaks, which is how fuzzers report.
+
+fn non_whitespace(text: &str) -> impl Iterator<Item = char> + '_ {
+    text.chars().filter(|c| !c.is_whitespace())
+}
+
+/// [`chunk_text`] keeps every non-whitespace character in order, and returns no empty
+/// chunk and none over `max_tokens`.
+pub fn check_chunk_text(text: &str, max_tokens: usize) {
+    let chunks = chunk_text(text, max_tokens);
+    for chunk in &chunks {
+        assert!(!chunk.trim().is_empty(), "empty chunk from {text:?}");
+        assert!(
+            estimate_tokens(chunk) <= max_tokens.max(1),
+            "chunk {chunk:?} over {max_tokens} tokens from {text:?}"
+        );
+    }
+    assert!(
+        non_whitespace(text).eq(chunks.iter().flat_map(|chunk| non_whitespace(chunk))),
+        "chunks {chunks:?} lose or reorder text of {text:?}"
+    );
+}
+
+/// A document reads back from its payload under `convention`, unchanged if
+/// [`is_representable`].
+pub fn check_payload_round_trip(document: &Document, convention: PayloadConvention) {
+    let read = round_trip(document, convention)
+        .expect("document converts to a payload")
+        .unwrap_or_else(|| panic!("{document:?} does not read back as {convention:?}"));
+    assert_eq!(read.text, document.text);
+    if is_representable(document, convention) {
+        assert_eq!(&read, document);
+    }
+}
@@ -0,0 +1,5 @@
+pub mod corpus;
+pub mod faults;
+pub mod hash_embedder;
+pub mod invariants;
+pub mod snapshot;
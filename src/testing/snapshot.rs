@@ -0,0 +1,169 @@
+use crate::vectorstore::qdrant_client::point_id_from_key;
+use crate::vectorstore::vector_store::{VectorPoint, VectorStore, VectorStoreError};
+use serde::Deserialize;
+use serde_json::{json, Map, Value as JsonValue};
+use std::collections::BTreeMap;
+
+const PRODUCTS: &str = include_str!("products.json");
+
+/// Payload field holding the readable key of a snapshot point.
+pub const KEY_FIELD: &str = "key";
+
+#[derive(Deserialize)]
+struct SnapshotPoint {
+    key: String,
+    vector: Vec<f32>,
+    payload: Map<String, JsonValue>,
+}
+
+#[derive(Deserialize)]
+struct SnapshotFile {
+    collection: String,
+    points: Vec<SnapshotPoint>,
+    #[serde(default)]
+    queries: BTreeMap<String, Vec<f32>>,
+}
+
+/// A small collection of precomputed vectors and payloads, with query vectors, for
+/// deterministic retrieval tests without an embedding server.
+/// Load one with `Snapshot::products().load_into(&store)` and query it with
+/// [`query`](Self::query) vectors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    collection: String,
+    points: Vec<VectorPoint>,
+    queries: BTreeMap<String, Vec<f32>>,
+}
+
+impl Snapshot {
+    /// Parses a snapshot in the bundled format: `{"collection": ..., "points": [{"key":
+    /// ..., "vector": [...], "payload": {...}}], "queries": {"name": [...]}}`. Point ids
+    /// are derived from the keys with [`point_id_from_key`], so they are valid Qdrant ids,
+    /// and the key is kept in the `key` payload field.
+    pub fn from_json(json: &str) -> Result<Self, VectorStoreError> {
+        let file: SnapshotFile = serde_json::from_str(json)?;
+        let dimension = file.points.first().map_or(0, |point| point.vector.len());
+        if let Some(vector) = file
+            .points
+            .iter()
+            .map(|point| &point.vector)
+            .chain(file.queries.values())
+            .find(|vector| vector.len() != dimension)
+        {
+            return Err(VectorStoreError::DimensionMismatch {
+                expected: dimension,
+                actual: vector.len(),
+            });
+        }
+
+        let points = file
+            .points
+            .into_iter()
+            .map(|point| {
+                let mut payload = point.payload;
+                payload.insert(KEY_FIELD.to_string(), json!(point.key));
+                VectorPoint::new(point_id_from_key(&point.key), point.vector, payload)
+            })
+            .collect();
+        Ok(Self {
+            collection: file.collection,
+            points,
+            queries: file.queries,
+        })
+    }
+
+    /// Bundled catalog of 8 clothing products with 4-dimensional vectors, and the
+    /// queries "waterproof boots", "rain gear" and "summer clothes".
+    pub fn products() -> Self {
+        Self::from_json(PRODUCTS).expect("bundled snapshot is valid")
+    }
+
+    pub fn collection(&self) -> &str {
+        &self.collection
+    }
+
+    pub fn dimension(&self) -> usize {
+        self.points.first().map_or(0, |point| point.vector.len())
+    }
+
+    pub fn points(&self) -> &[VectorPoint] {
+        &self.points
+    }
+
+    /// Vector of a named query of the snapshot.
+    pub fn query(&self, name: &str) -> Option<Vec<f32>> {
+        self.queries.get(name).cloned()
+    }
+
+    /// Id of the point with the given key.
+    pub fn id(&self, key: &str) -> String {
+        point_id_from_key(key)
+    }
+
+    /// Upserts every point into `store` under the snapshot's collection name. Backends
+    /// that do not create collections on write (Qdrant) need the collection created
+    /// first, with [`dimension`](Self::dimension) and cosine distance.
+    pub async fn load_into(&self, store: &impl VectorStore) -> Result<(), VectorStoreError> {
+        self.load_into_collection(store, &self.collection).await
+    }
+
+    /// Like [`load_into`](Self::load_into), under another collection name.
+    pub async fn load_into_collection(
+        &self,
+        store: &impl VectorStore,
+        collection: &str,
+    ) -> Result<(), VectorStoreError> {
+        store.upsert(collection, self.points.clone()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vectorstore::filter::FilterBuilder;
+    use crate::vectorstore::in_memory::InMemoryVectorStore;
+
+    #[tokio::test]
+    async fn test_load_products() {
+        let store = InMemoryVectorStore::new();
+        let snapshot = Snapshot::products();
+        snapshot.load_into(&store).await.unwrap();
+        assert_eq!(store.len("products"), 8);
+        assert_eq!(snapshot.dimension(), 4);
+
+        let hits = store
+            .query("products", snapshot.query("rain gear").unwrap(), 3)
+            .await
+            .unwrap();
+        let keys: Vec<&JsonValue> = hits.iter().map(|hit| &hit.payload["key"]).collect();
+        assert_eq!(keys, vec!["rain-jacket", "rain-boots", "trail-boots"]);
+        assert_eq!(hits[0].id, snapshot.id("rain-jacket"));
+
+        let filter = FilterBuilder::new().eq("category", "outerwear").build();
+        let hits = store
+            .query_filtered(
+                "products",
+                snapshot.query("summer clothes").unwrap(),
+                1,
+                Some(&filter),
+            )
+            .await
+            .unwrap();
+        assert_eq!(hits[0].payload["key"], "wool-coat");
+    }
+
+    #[test]
+    fn test_from_json_checks_dimensions() {
+        let json = r#"{"collection": "c", "points": [
+            {"key": "a", "vector": [1.0, 0.0], "payload": {}},
+            {"key": "b", "vector": [1.0], "payload": {}}
+        ]}"#;
+        assert!(matches!(
+            Snapshot::from_json(json),
+            Err(VectorStoreError::DimensionMismatch {
+                expected: 2,
+                actual: 1
+            })
+        ));
+    }
+}
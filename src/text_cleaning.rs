@@ -0,0 +1,239 @@
+use std::collections::{HashMap, HashSet};
+use unicode_normalization::UnicodeNormalization;
+
+/// Unicode normalization form applied by [`TextCleaner`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnicodeForm {
+    /// Canonical composition: only merges combining sequences.
+    Nfc,
+    /// Compatibility composition: also folds ligatures (`ﬁ`), full-width and superscript
+    /// forms PDF extractors emit, at the cost of some typographic detail.
+    Nfkc,
+}
+
+/// Cleaning stages run before chunking and embedding, in this order: control characters
+/// are stripped, unicode is normalized, boilerplate lines are removed and whitespace is
+/// collapsed. Each stage can be turned off; paragraph breaks (`\n\n`) are always kept
+/// since [`chunk_text`](crate::utils::chunk_text) splits on them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextCleaner {
+    pub strip_control: bool,
+    pub unicode_form: Option<UnicodeForm>,
+    /// Removes page numbers (`12`, `Page 3`, `Page 3 of 10`, `3 / 10`).
+    pub remove_page_numbers: bool,
+    /// Removes lines found in at least this many documents, e.g. running headers and
+    /// footers of PDF pages; only applies to [`clean_all`](Self::clean_all).
+    pub repeated_line_threshold: Option<usize>,
+    /// Removes lines equal to one of these, ignoring case and surrounding whitespace.
+    pub boilerplate_lines: Vec<String>,
+    pub collapse_whitespace: bool,
+}
+
+impl Default for TextCleaner {
+    fn default() -> Self {
+        Self {
+            strip_control: true,
+            unicode_form: Some(UnicodeForm::Nfkc),
+            remove_page_numbers: true,
+            repeated_line_threshold: Some(3),
+            boilerplate_lines: Vec::new(),
+            collapse_whitespace: true,
+        }
+    }
+}
+
+// Invisible characters extractors leave in text: soft hyphen, zero-width space, word
+// joiner and byte order mark. Zero-width joiners are kept, emoji sequences need them.
+fn is_invisible(c: char) -> bool {
+    matches!(c, '\u{00AD}' | '\u{200B}' | '\u{2060}' | '\u{FEFF}')
+}
+
+fn strip_control(text: &str) -> String {
+    text.replace("\r\n", "\n")
+        .chars()
+        .filter_map(|c| match c {
+            // Form feeds separate PDF pages
+            '\u{000C}' => Some('\n'),
+            '\r' => Some('\n'),
+            '\n' | '\t' => Some(c),
+            c if c.is_control() || is_invisible(c) => None,
+            c => Some(c),
+        })
+        .collect()
+}
+
+fn is_page_number(line: &str) -> bool {
+    let line = line.trim().to_lowercase();
+    let line = line.strip_prefix("page").map_or(line.as_str(), str::trim);
+    let mut parts = line
+        .split(['/', ' '])
+        .filter(|part| !part.is_empty() && *part != "of");
+    let is_number = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(page), None, None) => is_number(page),
+        (Some(page), Some(total), None) => is_number(page) && is_number(total),
+        _ => false,
+    }
+}
+
+fn line_key(line: &str) -> String {
+    line.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut blank_lines = 0;
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if line.is_empty() {
+            blank_lines += 1;
+            continue;
+        }
+        if !collapsed.is_empty() {
+            collapsed.push_str(if blank_lines > 0 { "\n\n" } else { "\n" });
+        }
+        collapsed.push_str(&line);
+        blank_lines = 0;
+    }
+    collapsed
+}
+
+impl TextCleaner {
+    /// Cleaner running no stage, to enable them one by one.
+    pub fn none() -> Self {
+        Self {
+            strip_control: false,
+            unicode_form: None,
+            remove_page_numbers: false,
+            repeated_line_threshold: None,
+            boilerplate_lines: Vec::new(),
+            collapse_whitespace: false,
+        }
+    }
+
+    pub fn with_unicode_form(mut self, form: Option<UnicodeForm>) -> Self {
+        self.unicode_form = form;
+        self
+    }
+
+    pub fn with_repeated_line_threshold(mut self, threshold: Option<usize>) -> Self {
+        self.repeated_line_threshold = threshold;
+        self
+    }
+
+    pub fn with_boilerplate_line(mut self, line: impl Into<String>) -> Self {
+        self.boilerplate_lines.push(line.into());
+        self
+    }
+
+    // Every stage up to boilerplate removal, which may need the whole corpus
+    fn normalize(&self, text: &str) -> String {
+        let text = if self.strip_control {
+            strip_control(text)
+        } else {
+            text.to_string()
+        };
+        match self.unicode_form {
+            Some(UnicodeForm::Nfc) => text.nfc().collect(),
+            Some(UnicodeForm::Nfkc) => text.nfkc().collect(),
+            None => text,
+        }
+    }
+
+    fn finish(&self, text: &str, repeated: &HashSet<String>) -> String {
+        let boilerplate: HashSet<String> = self
+            .boilerplate_lines
+            .iter()
+            .map(|line| line_key(line))
+            .collect();
+        let kept: Vec<&str> = text
+            .split('\n')
+            .filter(|line| {
+                let key = line_key(line);
+                key.is_empty()
+                    || !(boilerplate.contains(&key)
+                        || repeated.contains(&key)
+                        || (self.remove_page_numbers && is_page_number(line)))
+            })
+            .collect();
+        let text = kept.join("\n");
+        if self.collapse_whitespace {
+            collapse_whitespace(&text)
+        } else {
+            text
+        }
+    }
+
+    pub fn clean(&self, text: &str) -> String {
+        self.finish(&self.normalize(text), &HashSet::new())
+    }
+
+    /// Cleans documents of one corpus, e.g. the pages of a PDF, also removing lines
+    /// repeated across at least `repeated_line_threshold` of them.
+    pub fn clean_all(&self, documents: &[String]) -> Vec<String> {
+        let normalized: Vec<String> = documents.iter().map(|text| self.normalize(text)).collect();
+        let mut repeated = HashSet::new();
+        if let Some(threshold) = self.repeated_line_threshold {
+            let mut counts: HashMap<String, usize> = HashMap::new();
+            for text in &normalized {
+                let lines: HashSet<String> = text
+                    .lines()
+                    .map(line_key)
+                    .filter(|key| !key.is_empty())
+                    .collect();
+                for line in lines {
+                    *counts.entry(line).or_default() += 1;
+                }
+            }
+            repeated = counts
+                .into_iter()
+                .filter(|(_, count)| *count >= threshold.max(2))
+                .map(|(line, _)| line)
+                .collect();
+        }
+        normalized
+            .iter()
+            .map(|text| self.finish(text, &repeated))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean() {
+        let text = "Boots\u{00AD} are\u{0007} waterproof.\r\n\r\n\r\n\
+            The \u{FB01}t is true   to size.\n  12  \nPage 3 of 10\n\u{000C}Returns\tare free.";
+        assert_eq!(
+            TextCleaner::default().clean(text),
+            "Boots are waterproof.\n\nThe fit is true to size.\n\nReturns are free."
+        );
+        assert_eq!(TextCleaner::none().clean(text), text);
+        // Emoji sequences keep their joiners
+        assert_eq!(TextCleaner::default().clean("👩\u{200D}💻"), "👩\u{200D}💻");
+    }
+
+    #[test]
+    fn test_clean_all_removes_repeated_lines() {
+        let pages: Vec<String> = ["Boots", "Sandals", "Returns"]
+            .iter()
+            .map(|topic| format!("ACME Catalog 2024\n{topic} are in stock.\nConfidential"))
+            .collect();
+        let cleaner = TextCleaner::default().with_boilerplate_line("confidential");
+        assert_eq!(
+            cleaner.clean_all(&pages),
+            vec![
+                "Boots are in stock.",
+                "Sandals are in stock.",
+                "Returns are in stock."
+            ]
+        );
+        let cleaner = cleaner.with_repeated_line_threshold(None);
+        assert!(cleaner.clean_all(&pages)[0].starts_with("ACME Catalog 2024"));
+    }
+}
@@ -0,0 +1,141 @@
+use crate::cost::PriceTable;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+tokio::task_local! {
+    static RUN_USAGE: Arc<Mutex<RunUsage>>;
+}
+
+/// Calls and tokens of one model.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelUsage {
+    pub calls: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+/// LLM and embedding usage of one run, by model. LLM tokens are those reported by the
+/// provider; embedding servers report none, so their input tokens are estimated with
+/// [`estimate_tokens`](crate::utils::estimate_tokens) and image inputs count as calls
+/// only.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunUsage {
+    pub models: BTreeMap<String, ModelUsage>,
+}
+
+impl RunUsage {
+    pub fn is_empty(&self) -> bool {
+        self.models.is_empty()
+    }
+
+    pub fn calls(&self) -> u64 {
+        self.models.values().map(|usage| usage.calls).sum()
+    }
+
+    pub fn input_tokens(&self) -> u64 {
+        self.models.values().map(|usage| usage.input_tokens).sum()
+    }
+
+    pub fn output_tokens(&self) -> u64 {
+        self.models.values().map(|usage| usage.output_tokens).sum()
+    }
+
+    /// Cost in USD of the models `prices` has a price for, see
+    /// [`unpriced`](Self::unpriced) for the others.
+    pub fn cost(&self, prices: &PriceTable) -> f64 {
+        self.models
+            .iter()
+            .filter_map(|(model, usage)| {
+                let price = prices.price(model)?;
+                Some(price.cost(usage.input_tokens as usize, usage.output_tokens as usize))
+            })
+            .sum()
+    }
+
+    /// Models used in the run that `prices` has no price for.
+    pub fn unpriced(&self, prices: &PriceTable) -> Vec<&str> {
+        self.models
+            .keys()
+            .filter(|model| prices.price(model).is_none())
+            .map(String::as_str)
+            .collect()
+    }
+
+    pub fn record(&mut self, model: &str, input_tokens: u64, output_tokens: u64) {
+        let usage = self.models.entry(model.to_string()).or_default();
+        usage.calls += 1;
+        usage.input_tokens += input_tokens;
+        usage.output_tokens += output_tokens;
+    }
+
+    pub fn merge(&mut self, other: &RunUsage) {
+        for (model, other) in &other.models {
+            let usage = self.models.entry(model.clone()).or_default();
+            usage.calls += other.calls;
+            usage.input_tokens += other.input_tokens;
+            usage.output_tokens += other.output_tokens;
+        }
+    }
+}
+
+/// Runs `operation` and returns its output with the usage of every LLM and embedding call
+/// it made. A measured operation nested in another one also counts towards the outer
+/// run. Work moved to other tasks with `tokio::spawn` is not counted.
+pub async fn measured<F: Future>(operation: F) -> (F::Output, RunUsage) {
+    let usage = Arc::new(Mutex::new(RunUsage::default()));
+    let output = RUN_USAGE.scope(usage.clone(), operation).await;
+    let usage = std::mem::take(&mut *usage.lock().unwrap());
+    if let Ok(outer) = RUN_USAGE.try_with(Arc::clone) {
+        outer.lock().unwrap().merge(&usage);
+    }
+    (output, usage)
+}
+
+/// Adds a call to the usage of the current run, if there is one.
+pub(crate) fn record(model: &str, input_tokens: u64, output_tokens: u64) {
+    let _ = RUN_USAGE.try_with(|usage| {
+        usage
+            .lock()
+            .unwrap()
+            .record(model, input_tokens, output_tokens)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cost::ModelPrice;
+
+    #[tokio::test]
+    async fn test_measured() {
+        record("gpt-4o", 1, 1);
+        let ((answer, inner), outer) = measured(async {
+            record("gpt-4o", 1000, 200);
+            let (_, inner) = measured(async {
+                record("bge-small", 300, 0);
+                record("gpt-4o", 500, 100);
+            })
+            .await;
+            (42, inner)
+        })
+        .await;
+        assert_eq!(answer, 42);
+        assert_eq!(inner.calls(), 2);
+        assert_eq!(outer.calls(), 3);
+        assert_eq!(
+            outer.models["gpt-4o"],
+            ModelUsage {
+                calls: 2,
+                input_tokens: 1500,
+                output_tokens: 300,
+            }
+        );
+        assert_eq!(outer.input_tokens(), 1800);
+
+        let prices = PriceTable::empty().with_price("gpt-4o", ModelPrice::new(2.5, 10.0));
+        assert!((outer.cost(&prices) - 0.00675).abs() < 1e-9);
+        assert_eq!(outer.unpriced(&prices), vec!["bge-small"]);
+    }
+}
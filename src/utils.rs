@@ -1,17 +1,427 @@
 use base64::{engine::general_purpose::STANDARD, Engine};
+use http_fetch::HttpFetcher;
+use image::imageops::FilterType;
+use image::ImageFormat;
+use std::io::Cursor;
 use std::io::Error;
 use std::path::Path;
 use tokio::fs;
 
+pub mod http_fetch;
+
+/// Reads an image file, or fetches it through the shared [`HttpFetcher`] if `path` is an
+/// `http(s)://` URL.
 pub async fn load_image(path: impl AsRef<Path>) -> Result<Vec<u8>, Error> {
-    fs::read(path).await
+    match path.as_ref().to_str() {
+        Some(url) if url.starts_with("http://") || url.starts_with("https://") => {
+            HttpFetcher::shared()
+                .fetch_image(url)
+                .await
+                .map_err(Error::other)
+        }
+        _ => fs::read(path).await,
+    }
 }
 
 pub fn base64_encode(data: &[u8]) -> String {
-    STANDARD.encode(&data)
+    STANDARD.encode(data)
 }
 
 pub async fn load_image_as_base64(path: impl AsRef<Path>) -> Result<String, Error> {
     let data = load_image(path).await?;
     Ok(base64_encode(&data))
 }
+
+/// 64-bit difference hash (dHash) of an encoded image. Resized or re-encoded copies of the
+/// same picture hash to values a small Hamming distance apart.
+pub fn dhash(data: &[u8]) -> Result<u64, image::ImageError> {
+    let image = image::load_from_memory(data)?
+        .resize_exact(9, 8, FilterType::Triangle)
+        .to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if image.get_pixel(x, y)[0] < image.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    Ok(hash)
+}
+
+/// JPEG thumbnail of an encoded image, fitting in `max_size` x `max_size` with the aspect
+/// ratio kept. Images already smaller are re-encoded at their size.
+pub fn thumbnail(data: &[u8], max_size: u32) -> Result<Vec<u8>, image::ImageError> {
+    let image = image::load_from_memory(data)?;
+    let image = if image.width() > max_size || image.height() > max_size {
+        image.thumbnail(max_size, max_size)
+    } else {
+        image
+    };
+    let mut buffer = Vec::new();
+    image
+        .to_rgb8()
+        .write_to(&mut Cursor::new(&mut buffer), ImageFormat::Jpeg)?;
+    Ok(buffer)
+}
+
+// 64-bit FNV-1a, stable across platforms and Rust versions unlike `DefaultHasher`
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Strips tags from an HTML document or fragment, dropping `<script>`/`<style>` contents,
+/// decoding the common entities and collapsing whitespace.
+pub fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = rest[start + 1..start + end].to_ascii_lowercase();
+        rest = &rest[start + end + 1..];
+        for skipped in ["script", "style"] {
+            if tag.starts_with(skipped) {
+                let close = format!("</{skipped}");
+                rest = match rest.to_ascii_lowercase().find(&close) {
+                    Some(pos) => &rest[pos..],
+                    None => "",
+                };
+            }
+        }
+        // Keep words from adjacent block elements apart
+        text.push(' ');
+    }
+    text.push_str(rest);
+
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Rough token count of `text` for budgeting prompts, at about 4 characters per token
+/// as with common BPE tokenizers on English text.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+// Characters per token by model family. WordPiece and SentencePiece vocabularies of
+// embedding and open-weight models split text into more tokens than the BPE
+// vocabularies of hosted chat models, so they get a lower, conservative ratio
+fn chars_per_token(model: &str) -> usize {
+    let model = model.to_lowercase();
+    let dense = [
+        "bert", "bge", "e5-", "minilm", "mpnet", "gte-", "nomic", "llama", "mistral", "gemma",
+    ];
+    if dense.iter().any(|family| model.contains(family)) {
+        3
+    } else {
+        4
+    }
+}
+
+/// Like [`estimate_tokens`], with the ratio of `model`'s tokenizer family.
+pub fn estimate_model_tokens(text: &str, model: &str) -> usize {
+    text.chars().count().div_ceil(chars_per_token(model))
+}
+
+/// Longest prefix of `text` within `max_tokens` estimated tokens of `model`, cut at the
+/// last whitespace in the final quarter of the budget if there is one, else between
+/// characters but never inside an emoji sequence. Shared by context packing, embedding
+/// inputs and prompt assembly so they cut text the same way.
+pub fn truncate_to_tokens<'a>(text: &'a str, model: &str, max_tokens: usize) -> &'a str {
+    let max_chars = max_tokens * chars_per_token(model);
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() <= max_chars {
+        return text;
+    }
+    if max_chars == 0 {
+        return "";
+    }
+    let end = match ((max_chars * 3 / 4).max(1)..=max_chars)
+        .rev()
+        .find(|&at| chars[at].is_whitespace())
+    {
+        Some(space) => space,
+        None => {
+            let mut end = max_chars;
+            while end > 1 && !is_boundary(&chars, end) {
+                end -= 1;
+            }
+            // An emoji sequence alone over the budget is cut anyway
+            if is_boundary(&chars, end) {
+                end
+            } else {
+                max_chars
+            }
+        }
+    };
+    let bytes: usize = chars[..end].iter().map(|c| c.len_utf8()).sum();
+    text[..bytes].trim_end()
+}
+
+// Whether a break between `prev` and `next` would split what renders as one character:
+// combining marks, variation selectors, skin tones, emoji tags and ZWJ sequences
+fn joins(prev: char, next: char) -> bool {
+    prev == '\u{200D}'
+        || matches!(
+            next,
+            '\u{0300}'..='\u{036F}'
+                | '\u{200D}'
+                | '\u{FE00}'..='\u{FE0F}'
+                | '\u{1F3FB}'..='\u{1F3FF}'
+                | '\u{E0020}'..='\u{E007F}'
+        )
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&c)
+}
+
+// Splits a word without whitespace into pieces of at most `max_tokens`, keeping emoji
+// sequences and flags whole unless one alone is over the budget
+fn split_long_word(word: &str, max_tokens: usize) -> Vec<String> {
+    let chars: Vec<char> = word.chars().collect();
+    let max_chars = max_tokens * 4;
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    while chars.len() - start > max_chars {
+        let mut end = start + max_chars;
+        while end > start + 1 && !is_boundary(&chars[start..], end - start) {
+            end -= 1;
+        }
+        if end == start + 1 && !is_boundary(&chars[start..], 1) {
+            end = start + max_chars;
+        }
+        pieces.push(chars[start..end].iter().collect());
+        start = end;
+    }
+    pieces.push(chars[start..].iter().collect());
+    pieces
+}
+
+fn is_boundary(chars: &[char], at: usize) -> bool {
+    let (prev, next) = (chars[at - 1], chars[at]);
+    if joins(prev, next) {
+        return false;
+    }
+    // Flags are pairs of regional indicators, counted from the start of the run
+    if is_regional_indicator(prev) && is_regional_indicator(next) {
+        let run = chars[..at]
+            .iter()
+            .rev()
+            .take_while(|c| is_regional_indicator(**c))
+            .count();
+        return run % 2 == 0;
+    }
+    true
+}
+
+// Splits `text` into pieces of at most `max_tokens`, at the last whitespace that fits
+fn split_words(text: &str, max_tokens: usize) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        let mut parts = if estimate_tokens(word) > max_tokens {
+            split_long_word(word, max_tokens)
+        } else {
+            vec![word.to_string()]
+        };
+        // Only the tail of a split word can share a piece with the words after it
+        let last = parts.pop().unwrap_or_default();
+        if !parts.is_empty() {
+            if !current.is_empty() {
+                pieces.push(std::mem::take(&mut current));
+            }
+            pieces.extend(parts);
+        }
+        if !current.is_empty()
+            && estimate_tokens(&current) + estimate_tokens(&last) + 1 > max_tokens
+        {
+            pieces.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(&last);
+    }
+    if !current.is_empty() {
+        pieces.push(current);
+    }
+    pieces
+}
+
+/// Splits `text` into chunks of at most `max_tokens` estimated tokens, packing whole
+/// paragraphs, then sentences, then words, so chunks break at the most natural boundary
+/// that fits. Words over the budget, e.g. runs of CJK text, are broken between
+/// characters, never inside an emoji sequence unless it alone is over the budget.
+pub fn chunk_text(text: &str, max_tokens: usize) -> Vec<String> {
+    let max_tokens = max_tokens.max(1);
+    let mut pieces = Vec::new();
+    for paragraph in text.split("\n\n").map(str::trim).filter(|p| !p.is_empty()) {
+        if estimate_tokens(paragraph) <= max_tokens {
+            pieces.push(paragraph.to_string());
+            continue;
+        }
+        for sentence in paragraph.split_inclusive(['.', '!', '?', '。', '！', '？']) {
+            let sentence = sentence.trim();
+            if estimate_tokens(sentence) <= max_tokens {
+                pieces.push(sentence.to_string());
+            } else {
+                pieces.extend(split_words(sentence, max_tokens));
+            }
+        }
+    }
+
+    let mut chunks: Vec<String> = Vec::new();
+    for piece in pieces {
+        match chunks.last_mut() {
+            Some(chunk) if estimate_tokens(chunk) + estimate_tokens(&piece) < max_tokens => {
+                chunk.push_str("\n\n");
+                chunk.push_str(&piece);
+            }
+            _ => chunks.push(piece),
+        }
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::invariants::check_chunk_text;
+    use proptest::prelude::*;
+
+    #[tokio::test]
+    async fn test_dhash_resized_copy() {
+        let data = load_image("images/boot.png").await.unwrap();
+        let original = image::load_from_memory(&data).unwrap();
+
+        let mut resized = Vec::new();
+        original
+            .resize(
+                original.width() / 2,
+                original.height() / 2,
+                FilterType::Triangle,
+            )
+            .write_to(
+                &mut std::io::Cursor::new(&mut resized),
+                image::ImageFormat::Png,
+            )
+            .unwrap();
+
+        let other = load_image("images/sandals.png").await.unwrap();
+        let hash = dhash(&data).unwrap();
+        assert!(hamming_distance(hash, dhash(&resized).unwrap()) <= 4);
+        assert!(hamming_distance(hash, dhash(&other).unwrap()) > 10);
+    }
+
+    #[tokio::test]
+    async fn test_thumbnail() {
+        let data = load_image("images/boot.png").await.unwrap();
+        let small = image::load_from_memory(&thumbnail(&data, 64).unwrap()).unwrap();
+        assert!(small.width().max(small.height()) <= 64);
+    }
+
+    #[test]
+    fn test_html_to_text() {
+        let html = "<html><head><style>p { color: red; }</style></head>\
+            <body><h1>Boots</h1><p>Suede&nbsp;ankle <b>boots</b> &amp; more</p>\
+            <script>alert('x')</script></body></html>";
+        assert_eq!(html_to_text(html), "Boots Suede ankle boots & more");
+    }
+
+    #[test]
+    fn test_chunk_text() {
+        let text = "Boots are waterproof.\n\nSandals are not. They dry fast though.\n\n\
+            Returns are accepted within thirty days of delivery";
+        let chunks = chunk_text(text, 12);
+        assert_eq!(
+            chunks,
+            vec![
+                "Boots are waterproof.",
+                "Sandals are not. They dry fast though.",
+                "Returns are accepted within thirty days of",
+                "delivery",
+            ]
+        );
+        assert!(chunks.iter().all(|chunk| estimate_tokens(chunk) <= 12));
+        assert_eq!(chunk_text(text, 1000).len(), 1);
+        // Long paragraphs break between sentences
+        assert_eq!(
+            chunk_text("Sandals are not. They dry fast though.", 6),
+            vec!["Sandals are not.", "They dry fast though."]
+        );
+    }
+
+    #[test]
+    fn test_truncate_to_tokens() {
+        let text = "Waterproof leather boots with a cushioned sole.";
+        assert_eq!(truncate_to_tokens(text, "gpt-4o", 100), text);
+        assert_eq!(truncate_to_tokens(text, "gpt-4o", 5), "Waterproof leather");
+        assert_eq!(
+            truncate_to_tokens(text, "BAAI/bge-small-en-v1.5", 4),
+            "Waterproof"
+        );
+        assert_eq!(truncate_to_tokens(text, "gpt-4o", 0), "");
+        assert!(estimate_model_tokens(text, "bge-small") > estimate_tokens(text));
+
+        // No whitespace: cut between characters, not inside the emoji sequence
+        assert_eq!(
+            truncate_to_tokens("靴子防水靴子防水", "gpt-4o", 1),
+            "靴子防水"
+        );
+        assert_eq!(truncate_to_tokens("abc👩\u{200D}💻", "gpt-4o", 1), "abc");
+    }
+
+    #[test]
+    fn test_chunk_text_long_words() {
+        // CJK text has no spaces and is split between characters
+        let cjk = "防水靴は雨の日に最適です".repeat(10);
+        let chunks = chunk_text(&cjk, 8);
+        assert!(chunks.len() > 1);
+        assert_eq!(chunks.concat(), cjk);
+
+        // Emoji sequences and flags are kept whole
+        let family = "👨\u{200D}👩\u{200D}👧";
+        let text = format!("{}{}", "🇯🇵".repeat(5), family.repeat(3));
+        for chunk in chunk_text(&text, 2) {
+            assert!(!chunk.starts_with('\u{200D}'));
+            assert_eq!(
+                chunk.chars().filter(|c| is_regional_indicator(*c)).count() % 2,
+                0
+            );
+        }
+        check_chunk_text(&text, 2);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_chunk_text(text in any::<String>(), max_tokens in 0usize..40) {
+            check_chunk_text(&text, max_tokens);
+        }
+
+        #[test]
+        fn prop_chunk_text_prose(
+            text in "([a-z\u{1F600}\u{200D}\u{1F1E6}-\u{1F1FF}\u{0301}。]{1,30}[ .!?\n]{1,3}){0,40}",
+            max_tokens in 1usize..20,
+        ) {
+            check_chunk_text(&text, max_tokens);
+        }
+    }
+}
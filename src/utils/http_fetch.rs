@@ -0,0 +1,419 @@
+use reqwest::header::{
+    HeaderMap, CACHE_CONTROL, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+};
+use reqwest::{Client, StatusCode, Url};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::time::Instant;
+
+/// Content types accepted by [`HttpFetcher::fetch_text`].
+pub const TEXT_CONTENT_TYPES: [&str; 5] = [
+    "text/",
+    "application/xml",
+    "application/rss+xml",
+    "application/atom+xml",
+    "application/xhtml+xml",
+];
+
+/// Content types accepted by [`HttpFetcher::fetch_image`].
+pub const IMAGE_CONTENT_TYPES: [&str; 1] = ["image/"];
+
+/// Default bound of the bodies kept for revalidation, see [`HttpFetcher::with_cache_size`].
+pub const DEFAULT_CACHE_BYTES: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Error)]
+pub enum FetchError {
+    #[error("Invalid URL {0}")]
+    InvalidUrl(String),
+    #[error("HTTP error: {0}")]
+    HttpError(#[from] reqwest::Error),
+    #[error("{url} returned status {status}")]
+    Status { url: String, status: u16 },
+    #[error("{url} returned unsupported content type {content_type:?}")]
+    UnsupportedContentType { url: String, content_type: String },
+}
+
+/// A fetched resource. `not_modified` is set when the server answered a conditional GET
+/// with 304 and `body` is the copy cached from the previous fetch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fetched {
+    pub url: String,
+    pub content_type: String,
+    pub body: Vec<u8>,
+    pub not_modified: bool,
+}
+
+#[derive(Clone)]
+struct CachedResponse {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    content_type: String,
+    body: Vec<u8>,
+    // From `Cache-Control: max-age`; expired responses are evicted first
+    expires_at: Option<Instant>,
+    last_used: u64,
+}
+
+// Responses kept for conditional GETs, bounded by the total size of their bodies and
+// evicted expired first, then least recently used
+struct ResponseCache {
+    entries: HashMap<String, CachedResponse>,
+    bytes: usize,
+    max_bytes: usize,
+    uses: u64,
+}
+
+impl ResponseCache {
+    fn new(max_bytes: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            bytes: 0,
+            max_bytes,
+            uses: 0,
+        }
+    }
+
+    fn get(&mut self, url: &str) -> Option<CachedResponse> {
+        self.uses += 1;
+        let entry = self.entries.get_mut(url)?;
+        entry.last_used = self.uses;
+        Some(entry.clone())
+    }
+
+    fn remove(&mut self, url: &str) {
+        if let Some(entry) = self.entries.remove(url) {
+            self.bytes -= entry.body.len();
+        }
+    }
+
+    fn insert(&mut self, url: &str, mut response: CachedResponse) {
+        self.remove(url);
+        if response.body.len() > self.max_bytes {
+            return;
+        }
+        let now = Instant::now();
+        while self.bytes + response.body.len() > self.max_bytes {
+            let Some(evicted) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| {
+                    let fresh = entry.expires_at.is_none_or(|expires_at| expires_at > now);
+                    (fresh, entry.last_used)
+                })
+                .map(|(url, _)| url.clone())
+            else {
+                break;
+            };
+            self.remove(&evicted);
+        }
+        self.uses += 1;
+        response.last_used = self.uses;
+        self.bytes += response.body.len();
+        self.entries.insert(url.to_string(), response);
+    }
+}
+
+/// HTTP client for crawling: requests to the same host are spaced at least
+/// `min_interval` apart, responses with an `ETag` or `Last-Modified` are cached and
+/// revalidated with a conditional GET, and bodies of unexpected content types are
+/// rejected before being read. The cache holds up to [`DEFAULT_CACHE_BYTES`] of bodies
+/// and skips images unless [`with_image_caching`](Self::with_image_caching) is set.
+pub struct HttpFetcher {
+    client: Client,
+    min_interval: Duration,
+    // Earliest time of the next request, by host
+    next_request: Mutex<HashMap<String, Instant>>,
+    cache: Mutex<ResponseCache>,
+    cache_images: bool,
+}
+
+impl Default for HttpFetcher {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(1))
+    }
+}
+
+fn header(headers: &HeaderMap, name: reqwest::header::HeaderName) -> Option<String> {
+    headers.get(name)?.to_str().ok().map(str::to_string)
+}
+
+// `None` if the response must not be stored, else its freshness lifetime if given
+fn cache_lifetime(headers: &HeaderMap) -> Option<Option<Duration>> {
+    let Some(cache_control) = header(headers, CACHE_CONTROL) else {
+        return Some(None);
+    };
+    let mut max_age = None;
+    for directive in cache_control.split(',').map(str::trim) {
+        if directive.eq_ignore_ascii_case("no-store") {
+            return None;
+        }
+        if let Some(seconds) = directive.strip_prefix("max-age=") {
+            max_age = seconds.parse().ok().map(Duration::from_secs);
+        }
+    }
+    Some(max_age)
+}
+
+impl HttpFetcher {
+    pub fn new(min_interval: Duration) -> Self {
+        let client = Client::builder()
+            .user_agent(concat!("liquid-memory/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .unwrap_or_default();
+        Self {
+            client,
+            min_interval,
+            next_request: Mutex::new(HashMap::new()),
+            cache: Mutex::new(ResponseCache::new(DEFAULT_CACHE_BYTES)),
+            cache_images: false,
+        }
+    }
+
+    /// Bounds the total size of the cached bodies; 0 disables the cache.
+    pub fn with_cache_size(mut self, max_bytes: usize) -> Self {
+        self.cache = Mutex::new(ResponseCache::new(max_bytes));
+        self
+    }
+
+    /// Also caches `image/*` responses for revalidation. Off by default, as images are
+    /// rarely fetched twice and would take up most of the cache.
+    pub fn with_image_caching(mut self, cache_images: bool) -> Self {
+        self.cache_images = cache_images;
+        self
+    }
+
+    /// Fetcher shared by helpers that have none passed in, e.g. [`load_image`](super::load_image)
+    /// for URLs, so they are rate limited together.
+    pub fn shared() -> &'static HttpFetcher {
+        static SHARED: OnceLock<HttpFetcher> = OnceLock::new();
+        SHARED.get_or_init(HttpFetcher::default)
+    }
+
+    // Waits for the host's turn, reserving the next slot before sleeping so concurrent
+    // requests queue up instead of all firing at once
+    async fn wait_turn(&self, host: &str) {
+        let start = {
+            let mut next_request = self.next_request.lock().unwrap();
+            let now = Instant::now();
+            let start = next_request.get(host).map_or(now, |next| (*next).max(now));
+            next_request.insert(host.to_string(), start + self.min_interval);
+            start
+        };
+        tokio::time::sleep_until(start).await;
+    }
+
+    /// Fetches `url` if its content type starts with one of `accepted` (all are accepted
+    /// if it is empty).
+    pub async fn fetch(&self, url: &str, accepted: &[&str]) -> Result<Fetched, FetchError> {
+        let parsed = Url::parse(url).map_err(|_| FetchError::InvalidUrl(url.to_string()))?;
+        let host = parsed
+            .host_str()
+            .ok_or_else(|| FetchError::InvalidUrl(url.to_string()))?;
+        self.wait_turn(host).await;
+
+        let cached = self.cache.lock().unwrap().get(url);
+        let mut request = self.client.get(url);
+        if let Some(cached) = &cached {
+            if let Some(etag) = &cached.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &cached.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        let response = request.send().await?;
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some(cached) = cached {
+                return Ok(Fetched {
+                    url: url.to_string(),
+                    content_type: cached.content_type,
+                    body: cached.body,
+                    not_modified: true,
+                });
+            }
+        }
+        if !response.status().is_success() {
+            return Err(FetchError::Status {
+                url: url.to_string(),
+                status: response.status().as_u16(),
+            });
+        }
+
+        let headers = response.headers();
+        let content_type = header(headers, CONTENT_TYPE).unwrap_or_default();
+        if !accepted.is_empty()
+            && !accepted
+                .iter()
+                .any(|prefix| content_type.to_ascii_lowercase().starts_with(prefix))
+        {
+            return Err(FetchError::UnsupportedContentType {
+                url: url.to_string(),
+                content_type,
+            });
+        }
+        let etag = header(headers, ETAG);
+        let last_modified = header(headers, LAST_MODIFIED);
+        let lifetime = cache_lifetime(headers);
+        let is_image = content_type.to_ascii_lowercase().starts_with("image/");
+        let body = response.bytes().await?.to_vec();
+
+        let mut cache = self.cache.lock().unwrap();
+        match lifetime {
+            Some(lifetime)
+                if (etag.is_some() || last_modified.is_some())
+                    && (self.cache_images || !is_image) =>
+            {
+                cache.insert(
+                    url,
+                    CachedResponse {
+                        etag,
+                        last_modified,
+                        content_type: content_type.clone(),
+                        body: body.clone(),
+                        expires_at: lifetime.map(|lifetime| Instant::now() + lifetime),
+                        last_used: 0,
+                    },
+                );
+            }
+            // A stale copy must not answer a later 304
+            _ => cache.remove(url),
+        }
+        Ok(Fetched {
+            url: url.to_string(),
+            content_type,
+            body,
+            not_modified: false,
+        })
+    }
+
+    /// Fetches an HTML, XML or plain-text document.
+    pub async fn fetch_text(&self, url: &str) -> Result<(String, bool), FetchError> {
+        let fetched = self.fetch(url, &TEXT_CONTENT_TYPES).await?;
+        Ok((
+            String::from_utf8_lossy(&fetched.body).into_owned(),
+            fetched.not_modified,
+        ))
+    }
+
+    pub async fn fetch_image(&self, url: &str) -> Result<Vec<u8>, FetchError> {
+        Ok(self.fetch(url, &IMAGE_CONTENT_TYPES).await?.body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_conditional_get() {
+        let mut server = mockito::Server::new_async().await;
+        let first = server
+            .mock("GET", "/page")
+            .match_header("if-none-match", mockito::Matcher::Missing)
+            .with_header("content-type", "text/html; charset=utf-8")
+            .with_header("etag", "\"v1\"")
+            .with_body("<p>Boots</p>")
+            .create_async()
+            .await;
+        let revalidated = server
+            .mock("GET", "/page")
+            .match_header("if-none-match", "\"v1\"")
+            .with_status(304)
+            .create_async()
+            .await;
+
+        let fetcher = HttpFetcher::new(Duration::ZERO);
+        let url = format!("{}/page", server.url());
+        assert_eq!(
+            fetcher.fetch_text(&url).await.unwrap(),
+            ("<p>Boots</p>".to_string(), false)
+        );
+        assert_eq!(
+            fetcher.fetch_text(&url).await.unwrap(),
+            ("<p>Boots</p>".to_string(), true)
+        );
+        first.assert_async().await;
+        revalidated.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_content_type_and_rate_limit() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/boot.png")
+            .with_header("content-type", "text/html")
+            .with_body("<p>Not found</p>")
+            .expect(2)
+            .create_async()
+            .await;
+
+        let fetcher = HttpFetcher::new(Duration::from_millis(100));
+        let url = format!("{}/boot.png", server.url());
+        let started = Instant::now();
+        assert!(matches!(
+            fetcher.fetch_image(&url).await,
+            Err(FetchError::UnsupportedContentType { .. })
+        ));
+        assert!(fetcher.fetch_image(&url).await.is_err());
+        assert!(started.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn test_cache_bounds() {
+        let mut server = mockito::Server::new_async().await;
+        for (path, body) in [("/a", "aaaa"), ("/b", "bbbb"), ("/c", "cccc")] {
+            server
+                .mock("GET", path)
+                .with_header("content-type", "text/plain")
+                .with_header("etag", "\"v1\"")
+                .with_body(body)
+                .create_async()
+                .await;
+        }
+        server
+            .mock("GET", "/boot.png")
+            .with_header("content-type", "image/png")
+            .with_header("etag", "\"v1\"")
+            .with_body("png")
+            .create_async()
+            .await;
+
+        let fetcher = HttpFetcher::new(Duration::ZERO).with_cache_size(8);
+        for path in ["/a", "/b", "/a", "/c", "/boot.png"] {
+            fetcher
+                .fetch(&format!("{}{path}", server.url()), &[])
+                .await
+                .unwrap();
+        }
+        let cache = fetcher.cache.lock().unwrap();
+        let mut cached: Vec<&str> = cache.entries.keys().map(String::as_str).collect();
+        cached.sort();
+        // /b was the least recently used when /c did not fit, and images are not cached
+        let url = server.url();
+        assert_eq!(cached, vec![format!("{url}/a"), format!("{url}/c")]);
+        assert_eq!(cache.bytes, 8);
+    }
+
+    #[test]
+    fn test_cache_evicts_expired_first() {
+        let response = |expires_at| CachedResponse {
+            etag: Some("\"v1\"".to_string()),
+            last_modified: None,
+            content_type: "text/plain".to_string(),
+            body: vec![0; 4],
+            expires_at,
+            last_used: 0,
+        };
+        let mut cache = ResponseCache::new(8);
+        cache.insert("fresh", response(None));
+        cache.insert("expired", response(Some(Instant::now())));
+        cache.get("fresh");
+        cache.get("expired");
+        cache.insert("new", response(None));
+        assert!(cache.entries.contains_key("fresh"));
+        assert!(!cache.entries.contains_key("expired"));
+    }
+}
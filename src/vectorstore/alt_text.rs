@@ -0,0 +1,88 @@
+use crate::llm::llm_client::LlmClientChat;
+use std::path::Path;
+
+/// Screen readers commonly cut alt-text off around this length.
+pub const ALT_TEXT_MAX_CHARS: usize = 125;
+
+const ALT_TEXT_PROMPT: &str = "Write alt-text for this image for a screen reader user. \
+Use one short sentence under 125 characters, describe only what is visible and what matters \
+for understanding it, and do not start with \"Image of\" or \"Picture of\".";
+
+// Prefixes screen readers already announce, so they only add noise
+const REDUNDANT_PREFIXES: [&str; 6] = [
+    "image of ",
+    "picture of ",
+    "photo of ",
+    "a photo of ",
+    "an image of ",
+    "a picture of ",
+];
+
+/// Trims quotes, redundant prefixes and whitespace from a model response and shortens it to
+/// at most `max_chars` characters, cutting on a word boundary.
+pub fn clean_alt_text(response: &str, max_chars: usize) -> String {
+    let mut text = response
+        .trim()
+        .trim_matches(|c| c == '"' || c == '\'')
+        .trim();
+    if let Some(rest) = text.strip_prefix("Alt-text:") {
+        text = rest.trim();
+    }
+    let lowercase = text.to_lowercase();
+    if let Some(prefix) = REDUNDANT_PREFIXES
+        .iter()
+        .find(|prefix| lowercase.starts_with(*prefix))
+    {
+        text = &text[prefix.len()..];
+    }
+
+    let mut alt_text: String = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if alt_text.chars().count() > max_chars {
+        let cut: String = alt_text.chars().take(max_chars).collect();
+        alt_text = match cut.rfind(' ') {
+            Some(idx) => cut[..idx].to_string(),
+            None => cut,
+        };
+        alt_text = alt_text.trim_end_matches([',', ';', ':']).to_string();
+    }
+
+    let mut chars = alt_text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => alt_text,
+    }
+}
+
+/// Generates concise alt-text for an image. Unlike retrieval captions, which are long and
+/// keyword-rich, alt-text is meant to be rendered as-is in accessible UIs.
+pub async fn generate_alt_text<C: LlmClientChat>(
+    llm_client: &C,
+    model: &str,
+    image_path: impl AsRef<Path>,
+) -> Result<String, C::Error> {
+    let response = llm_client
+        .send_message(model, ALT_TEXT_PROMPT, Some(image_path), Some(0.0))
+        .await?;
+    Ok(clean_alt_text(&response, ALT_TEXT_MAX_CHARS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_alt_text() {
+        assert_eq!(
+            clean_alt_text("\"Image of brown suede ankle boots.\"\n", 125),
+            "Brown suede ankle boots."
+        );
+        assert_eq!(
+            clean_alt_text("Alt-text: a pair of  sandals", 125),
+            "A pair of sandals"
+        );
+        assert_eq!(
+            clean_alt_text("Brown boots, white laces, rubber soles", 20),
+            "Brown boots, white"
+        );
+    }
+}
@@ -0,0 +1,267 @@
+use futures::future::LocalBoxFuture;
+use std::path::{Component, PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AssetError {
+    #[error("No resolver for asset reference {0}")]
+    UnsupportedReference(String),
+    #[error("Invalid asset reference {0}")]
+    InvalidReference(String),
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Resolver error: {0}")]
+    ResolverError(String),
+}
+
+/// A stored asset reference made fetchable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolvedAsset {
+    Bytes(Vec<u8>),
+    /// URL the asset can be fetched from without credentials, e.g. a presigned URL.
+    Url(String),
+}
+
+/// Turns a stored `image_path` or `source` reference (local path, `s3://`, `gs://`, ...)
+/// into bytes or a URL at recall time, so where assets live can change without
+/// rewriting the memories pointing at them.
+#[allow(async_fn_in_trait)]
+pub trait AssetResolver {
+    async fn resolve(&self, reference: &str) -> Result<ResolvedAsset, AssetError>;
+}
+
+// Object-safe view of an `AssetResolver`, so a router can mix resolvers
+pub(crate) trait DynAssetResolver {
+    fn resolve_dyn<'a>(
+        &'a self,
+        reference: &'a str,
+    ) -> LocalBoxFuture<'a, Result<ResolvedAsset, AssetError>>;
+}
+
+impl<R: AssetResolver> DynAssetResolver for R {
+    fn resolve_dyn<'a>(
+        &'a self,
+        reference: &'a str,
+    ) -> LocalBoxFuture<'a, Result<ResolvedAsset, AssetError>> {
+        Box::pin(self.resolve(reference))
+    }
+}
+
+fn scheme(reference: &str) -> Option<&str> {
+    reference.split_once("://").map(|(scheme, _)| scheme)
+}
+
+/// Reads local paths and `file://` references, relative paths from an optional root.
+/// With a root, references are confined to it: absolute paths and `..` are rejected.
+#[derive(Debug, Clone, Default)]
+pub struct LocalFileResolver {
+    root: Option<PathBuf>,
+}
+
+impl LocalFileResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = Some(root.into());
+        self
+    }
+}
+
+impl AssetResolver for LocalFileResolver {
+    async fn resolve(&self, reference: &str) -> Result<ResolvedAsset, AssetError> {
+        let path = match scheme(reference) {
+            None => PathBuf::from(reference),
+            Some("file") => PathBuf::from(&reference["file://".len()..]),
+            Some(_) => return Err(AssetError::UnsupportedReference(reference.to_string())),
+        };
+        let path = match &self.root {
+            Some(root) => {
+                let escapes = path.components().any(|component| {
+                    !matches!(component, Component::Normal(_) | Component::CurDir)
+                });
+                if escapes {
+                    return Err(AssetError::InvalidReference(reference.to_string()));
+                }
+                root.join(path)
+            }
+            None => path,
+        };
+        Ok(ResolvedAsset::Bytes(tokio::fs::read(path).await?))
+    }
+}
+
+/// Maps references to the public HTTPS URLs of their objects: `http(s)://` as is,
+/// `s3://bucket/key` to the bucket's virtual-hosted URL and `gs://bucket/key` to
+/// `storage.googleapis.com`. Only works for public objects; private buckets need a
+/// resolver that presigns.
+#[derive(Debug, Clone)]
+pub struct PublicUrlResolver {
+    s3_region: String,
+}
+
+impl Default for PublicUrlResolver {
+    fn default() -> Self {
+        Self {
+            s3_region: "us-east-1".to_string(),
+        }
+    }
+}
+
+impl PublicUrlResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_s3_region(mut self, region: impl Into<String>) -> Self {
+        self.s3_region = region.into();
+        self
+    }
+
+    pub fn url(&self, reference: &str) -> Result<String, AssetError> {
+        let invalid = || AssetError::InvalidReference(reference.to_string());
+        match reference.split_once("://") {
+            Some(("http" | "https", _)) => Ok(reference.to_string()),
+            Some((scheme @ ("s3" | "gs"), location)) => {
+                let (bucket, key) = location.split_once('/').ok_or_else(invalid)?;
+                if bucket.is_empty() || key.is_empty() {
+                    return Err(invalid());
+                }
+                Ok(match scheme {
+                    "s3" => format!("https://{bucket}.s3.{}.amazonaws.com/{key}", self.s3_region),
+                    _ => format!("https://storage.googleapis.com/{bucket}/{key}"),
+                })
+            }
+            _ => Err(AssetError::UnsupportedReference(reference.to_string())),
+        }
+    }
+}
+
+impl AssetResolver for PublicUrlResolver {
+    async fn resolve(&self, reference: &str) -> Result<ResolvedAsset, AssetError> {
+        Ok(ResolvedAsset::Url(self.url(reference)?))
+    }
+}
+
+/// Picks a resolver by the scheme of the reference. By default local paths and `file://`
+/// go to [`LocalFileResolver`] and `http(s)://`, `s3://` and `gs://` to
+/// [`PublicUrlResolver`]; register a presigning resolver with
+/// [`with_scheme`](Self::with_scheme) for private buckets.
+pub struct AssetRouter {
+    local: Box<dyn DynAssetResolver>,
+    schemes: Vec<(String, Box<dyn DynAssetResolver>)>,
+}
+
+impl Default for AssetRouter {
+    fn default() -> Self {
+        Self {
+            local: Box::new(LocalFileResolver::new()),
+            schemes: Vec::new(),
+        }
+        .with_scheme("http", PublicUrlResolver::new())
+        .with_scheme("https", PublicUrlResolver::new())
+        .with_scheme("s3", PublicUrlResolver::new())
+        .with_scheme("gs", PublicUrlResolver::new())
+    }
+}
+
+impl AssetRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resolver for local paths and `file://` references.
+    pub fn with_local(mut self, resolver: impl AssetResolver + 'static) -> Self {
+        self.local = Box::new(resolver);
+        self
+    }
+
+    /// Replaces the resolver of a scheme, e.g. `"s3"`.
+    pub fn with_scheme(
+        mut self,
+        scheme: impl Into<String>,
+        resolver: impl AssetResolver + 'static,
+    ) -> Self {
+        let scheme = scheme.into();
+        self.schemes.retain(|(existing, _)| *existing != scheme);
+        self.schemes.push((scheme, Box::new(resolver)));
+        self
+    }
+}
+
+impl AssetResolver for AssetRouter {
+    async fn resolve(&self, reference: &str) -> Result<ResolvedAsset, AssetError> {
+        match scheme(reference) {
+            None | Some("file") => self.local.resolve_dyn(reference).await,
+            Some(scheme) => match self.schemes.iter().find(|(known, _)| known == scheme) {
+                Some((_, resolver)) => resolver.resolve_dyn(reference).await,
+                None => Err(AssetError::UnsupportedReference(reference.to_string())),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Presigner;
+
+    impl AssetResolver for Presigner {
+        async fn resolve(&self, reference: &str) -> Result<ResolvedAsset, AssetError> {
+            Ok(ResolvedAsset::Url(format!("{reference}?signature=abc")))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_asset_router() {
+        let router = AssetRouter::new();
+        assert_eq!(
+            router.resolve("s3://catalog/boots/1.png").await.unwrap(),
+            ResolvedAsset::Url("https://catalog.s3.us-east-1.amazonaws.com/boots/1.png".into())
+        );
+        assert_eq!(
+            router.resolve("gs://catalog/boots/1.png").await.unwrap(),
+            ResolvedAsset::Url("https://storage.googleapis.com/catalog/boots/1.png".into())
+        );
+        assert!(matches!(
+            router.resolve("images/boot.png").await.unwrap(),
+            ResolvedAsset::Bytes(bytes) if !bytes.is_empty()
+        ));
+        assert!(matches!(
+            router.resolve("ftp://host/boot.png").await,
+            Err(AssetError::UnsupportedReference(_))
+        ));
+        assert!(matches!(
+            router.resolve("s3://catalog").await,
+            Err(AssetError::InvalidReference(_))
+        ));
+
+        let router = AssetRouter::new().with_scheme("s3", Presigner);
+        assert_eq!(
+            router.resolve("s3://private/boot.png").await.unwrap(),
+            ResolvedAsset::Url("s3://private/boot.png?signature=abc".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_local_resolver_stays_in_root() {
+        let resolver = LocalFileResolver::new().with_root("images");
+        assert!(matches!(
+            resolver.resolve("boot.png").await.unwrap(),
+            ResolvedAsset::Bytes(bytes) if !bytes.is_empty()
+        ));
+        for reference in [
+            "/etc/passwd",
+            "file:///etc/passwd",
+            "../Cargo.toml",
+            "a/../../Cargo.toml",
+        ] {
+            assert!(matches!(
+                resolver.resolve(reference).await,
+                Err(AssetError::InvalidReference(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use crate::llm::llm_client::LlmClientChat;
+use serde::Serialize;
+use std::path::Path;
+
+const SELF_CHECK_PROMPT: &str = "Rate how accurately and completely the following description \
+describes this image, from 1 (wrong or unrelated) to 10 (accurate and complete). \
+Answer with the number only.\n\nDescription:\n";
+
+// Phrases models produce when they could not, or would not, describe the image
+const REFUSAL_MARKERS: [&str; 5] = ["i'm sorry", "i cannot", "i can't", "unable to", "as an ai"];
+
+/// Checks applied to generated image captions before they are embedded.
+#[derive(Debug, Clone)]
+pub struct CaptionValidation {
+    pub min_chars: usize,
+    pub max_chars: usize,
+    /// Minimum share of ASCII letters among all letters, a cheap guard against captions
+    /// in an unexpected language or script. `None` disables the check.
+    pub min_ascii_letter_ratio: Option<f32>,
+    /// Minimum 1-10 score the LLM has to give the caption when shown the image again.
+    /// `None` disables the self-check.
+    pub min_self_check_score: Option<u8>,
+    /// Number of times a caption is regenerated after failing validation.
+    pub max_retries: u32,
+}
+
+impl Default for CaptionValidation {
+    fn default() -> Self {
+        Self {
+            min_chars: 50,
+            max_chars: 4000,
+            min_ascii_letter_ratio: Some(0.9),
+            min_self_check_score: None,
+            max_retries: 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CaptionQuality {
+    pub passed: bool,
+    pub issues: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub self_check_score: Option<u8>,
+    pub attempts: u32,
+}
+
+fn ascii_letter_ratio(text: &str) -> f32 {
+    let (ascii, total) = text
+        .chars()
+        .filter(|c| c.is_alphabetic())
+        .fold((0, 0), |(ascii, total), c| {
+            (ascii + c.is_ascii() as usize, total + 1)
+        });
+    if total == 0 {
+        return 0.0;
+    }
+    ascii as f32 / total as f32
+}
+
+pub(crate) fn parse_score(response: &str) -> Option<u8> {
+    response
+        .split(|c: char| !c.is_ascii_digit())
+        .find(|token| !token.is_empty())?
+        .parse()
+        .ok()
+        .filter(|score| (1..=10).contains(score))
+}
+
+impl CaptionValidation {
+    /// Checks that do not need the LLM: length, language and refusals.
+    pub fn check_text(&self, caption: &str) -> Vec<String> {
+        let mut issues = Vec::new();
+        let length = caption.trim().chars().count();
+        if length < self.min_chars {
+            issues.push(format!("too short ({length} < {} chars)", self.min_chars));
+        }
+        if length > self.max_chars {
+            issues.push(format!("too long ({length} > {} chars)", self.max_chars));
+        }
+        if let Some(min_ratio) = self.min_ascii_letter_ratio {
+            let ratio = ascii_letter_ratio(caption);
+            if ratio < min_ratio {
+                issues.push(format!(
+                    "unexpected language ({ratio:.2} ascii letter ratio)"
+                ));
+            }
+        }
+        let lowercase = caption.to_lowercase();
+        if REFUSAL_MARKERS
+            .iter()
+            .any(|marker| lowercase.starts_with(marker))
+        {
+            issues.push("refusal".to_string());
+        }
+        issues
+    }
+
+    /// Runs all checks, including the LLM self-check when enabled.
+    pub async fn validate<C: LlmClientChat>(
+        &self,
+        caption: &str,
+        llm_client: &C,
+        model: &str,
+        image_path: impl AsRef<Path>,
+    ) -> Result<CaptionQuality, C::Error> {
+        let mut quality = CaptionQuality {
+            issues: self.check_text(caption),
+            ..Default::default()
+        };
+
+        // Only pay for the self-check when the cheap checks passed
+        if let (Some(min_score), true) = (self.min_self_check_score, quality.issues.is_empty()) {
+            let response = llm_client
+                .send_message(
+                    model,
+                    format!("{SELF_CHECK_PROMPT}{caption}"),
+                    Some(image_path),
+                    Some(0.0),
+                )
+                .await?;
+            match parse_score(&response) {
+                Some(score) if score >= min_score => quality.self_check_score = Some(score),
+                Some(score) => {
+                    quality.self_check_score = Some(score);
+                    quality
+                        .issues
+                        .push(format!("self-check score {score} < {min_score}"));
+                }
+                None => quality
+                    .issues
+                    .push(format!("unparsable self-check response: {response}")),
+            }
+        }
+
+        quality.passed = quality.issues.is_empty();
+        Ok(quality)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_text() {
+        let validation = CaptionValidation::default();
+        let caption =
+            "A pair of light brown suede ankle boots with a low heel on a white background.";
+        assert!(validation.check_text(caption).is_empty());
+        assert_eq!(validation.check_text("Boots").len(), 1);
+        assert!(validation
+            .check_text("I'm sorry, but I can't help with identifying this image at all here.")
+            .contains(&"refusal".to_string()));
+        assert!(validation.check_text(&"靴".repeat(60))[0].starts_with("unexpected language"));
+    }
+
+    #[test]
+    fn test_parse_score() {
+        assert_eq!(parse_score("8"), Some(8));
+        assert_eq!(parse_score("Score: 10/10"), Some(10));
+        assert_eq!(parse_score("0"), None);
+        assert_eq!(parse_score("great"), None);
+    }
+}
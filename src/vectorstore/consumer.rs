@@ -0,0 +1,219 @@
+use crate::embeddings::text_embedding_inference::TextEmbeddingInference;
+use crate::vectorstore::interop::Document;
+use crate::vectorstore::qdrant_client::{point_id_from_key, QdrantClient};
+use anyhow::{anyhow, Result};
+use qdrant_client::Payload;
+use serde_json::Value as JsonValue;
+
+/// A message pulled from a broker.
+pub trait SourceMessage {
+    /// Stable identifier of the message (e.g. stream + sequence, topic + partition + offset).
+    fn key(&self) -> String;
+    fn payload(&self) -> &[u8];
+}
+
+/// A topic or stream of documents with explicit acknowledgement.
+#[allow(async_fn_in_trait)]
+pub trait MessageSource {
+    type Message: SourceMessage;
+
+    /// Waits for up to `max_messages` messages. May return an empty batch on timeout.
+    async fn next_batch(&mut self, max_messages: usize) -> Result<Vec<Self::Message>>;
+
+    /// Acknowledges (commits) messages once they are safely stored.
+    async fn ack(&mut self, messages: Vec<Self::Message>) -> Result<()>;
+}
+
+/// A message body is either a JSON object in the crate payload layout (`text` plus
+/// metadata) or plain UTF-8 text.
+pub fn message_to_document(payload: &[u8]) -> Option<Document> {
+    match serde_json::from_slice::<JsonValue>(payload) {
+        Ok(JsonValue::Object(mut map)) => {
+            let text = match map.remove("text")? {
+                JsonValue::String(text) => text,
+                _ => return None,
+            };
+            Some(Document::new(text).with_metadata(map))
+        }
+        _ => {
+            let text = std::str::from_utf8(payload).ok()?.trim();
+            (!text.is_empty()).then(|| Document::new(text))
+        }
+    }
+}
+
+/// Pulls one batch, embeds and upserts it, then acknowledges it. Returns the number of
+/// points written.
+///
+/// Messages are only acknowledged after the upsert completed, so a failure anywhere leaves
+/// the whole batch to be redelivered (at-least-once). Messages that cannot be parsed are
+/// acknowledged and skipped, otherwise they would be redelivered forever.
+pub async fn consume_batch<S: MessageSource>(
+    source: &mut S,
+    collection_name: &str,
+    batch_size: usize,
+    text_embedding_client: &TextEmbeddingInference,
+    client: &QdrantClient,
+) -> Result<usize> {
+    let messages = source.next_batch(batch_size).await?;
+    if messages.is_empty() {
+        return Ok(0);
+    }
+
+    let mut ids = Vec::new();
+    let mut texts = Vec::new();
+    let mut payloads = Vec::new();
+    for message in &messages {
+        let Some(document) = message_to_document(message.payload()) else {
+            log::warn!("Skipping unparsable message {}", message.key());
+            continue;
+        };
+        let mut payload = document.metadata;
+        client
+            .payload_fields()
+            .insert_text(&mut payload, &document.text);
+        // Redelivered messages overwrite the point they produced the first time
+        ids.push(point_id_from_key(&message.key()));
+        payloads.push(Payload::from(payload));
+        texts.push(document.text);
+    }
+
+    let count = texts.len();
+    if count > 0 {
+        let embeddings = text_embedding_client
+            .embed(texts)
+            .await
+            .map_err(|e| anyhow!(e.to_string()))?;
+        client
+            .upsert_points_with_ids(collection_name, ids, embeddings, payloads)
+            .await?;
+    }
+
+    source.ack(messages).await?;
+    Ok(count)
+}
+
+/// Consumes `source` until an error occurs. Unacknowledged messages of the failing batch
+/// are redelivered when the consumer is restarted.
+pub async fn consume<S: MessageSource>(
+    source: &mut S,
+    collection_name: &str,
+    batch_size: usize,
+    text_embedding_client: &TextEmbeddingInference,
+    client: &QdrantClient,
+) -> Result<()> {
+    loop {
+        consume_batch(
+            source,
+            collection_name,
+            batch_size,
+            text_embedding_client,
+            client,
+        )
+        .await?;
+    }
+}
+
+#[cfg(feature = "nats")]
+pub mod nats {
+    use super::{MessageSource, SourceMessage};
+    use anyhow::{anyhow, Result};
+    use async_nats::jetstream::{self, consumer::pull, consumer::AckPolicy};
+    use futures::StreamExt;
+    use std::time::Duration;
+
+    pub struct NatsMessage {
+        key: String,
+        message: jetstream::Message,
+    }
+
+    impl SourceMessage for NatsMessage {
+        fn key(&self) -> String {
+            self.key.clone()
+        }
+
+        fn payload(&self) -> &[u8] {
+            &self.message.payload
+        }
+    }
+
+    /// JetStream pull consumer with explicit acks.
+    pub struct NatsSource {
+        consumer: jetstream::consumer::Consumer<pull::Config>,
+        fetch_timeout: Duration,
+    }
+
+    impl NatsSource {
+        /// Binds to (or creates) the durable consumer `durable_name` on `stream`.
+        pub async fn connect(url: &str, stream: &str, durable_name: &str) -> Result<Self> {
+            let client = async_nats::connect(url).await?;
+            let consumer = jetstream::new(client)
+                .get_stream(stream)
+                .await?
+                .get_or_create_consumer(
+                    durable_name,
+                    pull::Config {
+                        durable_name: Some(durable_name.to_string()),
+                        ack_policy: AckPolicy::Explicit,
+                        ..Default::default()
+                    },
+                )
+                .await?;
+            Ok(Self {
+                consumer,
+                fetch_timeout: Duration::from_secs(5),
+            })
+        }
+    }
+
+    impl MessageSource for NatsSource {
+        type Message = NatsMessage;
+
+        async fn next_batch(&mut self, max_messages: usize) -> Result<Vec<NatsMessage>> {
+            let mut batch = self
+                .consumer
+                .fetch()
+                .max_messages(max_messages)
+                .expires(self.fetch_timeout)
+                .messages()
+                .await?;
+
+            let mut messages = Vec::new();
+            while let Some(message) = batch.next().await {
+                let message = message.map_err(|e| anyhow!(e))?;
+                let info = message.info().map_err(|e| anyhow!(e))?;
+                let key = format!("{}:{}", info.stream, info.stream_sequence);
+                messages.push(NatsMessage { key, message });
+            }
+            Ok(messages)
+        }
+
+        async fn ack(&mut self, messages: Vec<NatsMessage>) -> Result<()> {
+            for message in messages {
+                message.message.ack().await.map_err(|e| anyhow!(e))?;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_to_document_json() {
+        let document =
+            message_to_document(br#"{"text": "Hello World", "source": "chat"}"#).unwrap();
+        assert_eq!(document.text, "Hello World");
+        assert_eq!(document.metadata["source"], "chat");
+    }
+
+    #[test]
+    fn test_message_to_document_plain_text() {
+        let document = message_to_document(b"  Hello World\n").unwrap();
+        assert_eq!(document.text, "Hello World");
+        assert!(message_to_document(b"   ").is_none());
+        assert!(message_to_document(br#"{"source": "chat"}"#).is_none());
+    }
+}
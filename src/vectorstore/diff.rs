@@ -0,0 +1,228 @@
+use crate::vectorstore::qdrant_client::point_id_from_key;
+use crate::vectorstore::vector_store::{VectorPoint, VectorStore, VectorStoreError};
+use serde::Serialize;
+use serde_json::{Map, Value as JsonValue};
+use std::collections::BTreeMap;
+
+const DIFF_PAGE_SIZE: u64 = 256;
+
+/// How [`diff_collections`] pairs up the points of two collections.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DiffKey {
+    /// By point id, e.g. after [`copy_collection`](super::vector_store::copy_collection)
+    /// or a backup restore.
+    #[default]
+    Id,
+    /// By a hash of the `text` field, or of the whole payload without one, e.g. after a
+    /// re-ingestion that assigned new ids.
+    ContentHash,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffOptions {
+    pub key: DiffKey,
+    /// Payload fields not compared, e.g. write timestamps.
+    pub ignored_fields: Vec<String>,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            key: DiffKey::default(),
+            ignored_fields: vec!["timestamp".to_string()],
+        }
+    }
+}
+
+impl DiffOptions {
+    pub fn new(key: DiffKey) -> Self {
+        Self {
+            key,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_ignored_fields(mut self, fields: &[&str]) -> Self {
+        self.ignored_fields = fields.iter().map(|field| field.to_string()).collect();
+        self
+    }
+}
+
+/// A point present in both collections with different payloads.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PayloadMismatch {
+    /// Point id, or content hash with [`DiffKey::ContentHash`].
+    pub key: String,
+    /// Fields that differ or are missing on one side.
+    pub fields: Vec<String>,
+}
+
+/// Result of [`diff_collections`]. Keys are point ids or content hashes, as configured.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CollectionDiff {
+    pub only_in_a: Vec<String>,
+    pub only_in_b: Vec<String>,
+    pub payload_mismatches: Vec<PayloadMismatch>,
+    /// Points present in both with the same payload.
+    pub matching: usize,
+}
+
+impl CollectionDiff {
+    /// Whether both collections hold the same points.
+    pub fn is_empty(&self) -> bool {
+        self.only_in_a.is_empty() && self.only_in_b.is_empty() && self.payload_mismatches.is_empty()
+    }
+}
+
+/// Compares two collections, possibly on different backends, to validate a migration,
+/// re-ingestion or restore. Both are read whole into memory; vectors are not compared.
+/// With [`DiffKey::ContentHash`], points with the same content count once.
+pub async fn diff_collections(
+    a: &impl VectorStore,
+    a_collection: &str,
+    b: &impl VectorStore,
+    b_collection: &str,
+    options: &DiffOptions,
+) -> Result<CollectionDiff, VectorStoreError> {
+    let a_points = keyed_payloads(a, a_collection, options).await?;
+    let mut b_points = keyed_payloads(b, b_collection, options).await?;
+
+    let mut diff = CollectionDiff::default();
+    for (key, a_payload) in a_points {
+        let Some(b_payload) = b_points.remove(&key) else {
+            diff.only_in_a.push(key);
+            continue;
+        };
+        let fields = mismatched_fields(&a_payload, &b_payload);
+        if fields.is_empty() {
+            diff.matching += 1;
+        } else {
+            diff.payload_mismatches
+                .push(PayloadMismatch { key, fields });
+        }
+    }
+    diff.only_in_b = b_points.into_keys().collect();
+    Ok(diff)
+}
+
+async fn keyed_payloads(
+    store: &impl VectorStore,
+    collection: &str,
+    options: &DiffOptions,
+) -> Result<BTreeMap<String, Map<String, JsonValue>>, VectorStoreError> {
+    let mut payloads = BTreeMap::new();
+    let mut offset = None;
+    loop {
+        let (points, next) = store.scroll(collection, offset, DIFF_PAGE_SIZE).await?;
+        for point in points {
+            let key = point_key(&point, options.key);
+            let mut payload = point.payload;
+            payload.retain(|field, _| !options.ignored_fields.contains(field));
+            payloads.insert(key, payload);
+        }
+        match next {
+            Some(next) => offset = Some(next),
+            None => return Ok(payloads),
+        }
+    }
+}
+
+fn point_key(point: &VectorPoint, key: DiffKey) -> String {
+    match key {
+        DiffKey::Id => point.id.clone(),
+        DiffKey::ContentHash => match point.payload.get("text").and_then(JsonValue::as_str) {
+            Some(text) => point_id_from_key(text),
+            None => point_id_from_key(&JsonValue::Object(point.payload.clone()).to_string()),
+        },
+    }
+}
+
+fn mismatched_fields(a: &Map<String, JsonValue>, b: &Map<String, JsonValue>) -> Vec<String> {
+    let mut fields: Vec<String> = a
+        .iter()
+        .filter(|(field, value)| b.get(*field) != Some(*value))
+        .map(|(field, _)| field.clone())
+        .chain(b.keys().filter(|field| !a.contains_key(*field)).cloned())
+        .collect();
+    fields.sort();
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vectorstore::in_memory::InMemoryVectorStore;
+    use serde_json::json;
+
+    fn point(id: &str, text: &str, color: &str) -> VectorPoint {
+        let mut payload = Map::new();
+        payload.insert("text".to_string(), json!(text));
+        payload.insert("color".to_string(), json!(color));
+        payload.insert("timestamp".to_string(), json!(id));
+        VectorPoint::new(id, vec![1.0, 0.0], payload)
+    }
+
+    #[tokio::test]
+    async fn test_diff_collections() {
+        let store = InMemoryVectorStore::new();
+        store
+            .upsert(
+                "a",
+                vec![
+                    point("1", "boots", "red"),
+                    point("2", "sandals", "blue"),
+                    point("3", "loafers", "brown"),
+                ],
+            )
+            .await
+            .unwrap();
+        store
+            .upsert(
+                "b",
+                vec![
+                    point("1", "boots", "red"),
+                    point("2", "sandals", "green"),
+                    point("4", "wellies", "yellow"),
+                ],
+            )
+            .await
+            .unwrap();
+
+        let diff = diff_collections(&store, "a", &store, "b", &DiffOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(diff.only_in_a, vec!["3"]);
+        assert_eq!(diff.only_in_b, vec!["4"]);
+        assert_eq!(
+            diff.payload_mismatches,
+            vec![PayloadMismatch {
+                key: "2".to_string(),
+                fields: vec!["color".to_string()],
+            }]
+        );
+        assert_eq!(diff.matching, 1);
+
+        // Same content under new ids
+        store
+            .upsert(
+                "c",
+                vec![
+                    point("7", "boots", "red"),
+                    point("8", "sandals", "blue"),
+                    point("9", "loafers", "brown"),
+                ],
+            )
+            .await
+            .unwrap();
+        let by_content = DiffOptions::new(DiffKey::ContentHash);
+        let diff = diff_collections(&store, "a", &store, "c", &by_content)
+            .await
+            .unwrap();
+        assert!(diff.is_empty());
+        assert_eq!(diff.matching, 3);
+        let diff = diff_collections(&store, "a", &store, "c", &DiffOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(diff.only_in_a.len(), 3);
+    }
+}
@@ -0,0 +1,381 @@
+use chrono::{DateTime, Utc};
+use qdrant_client::qdrant::{Condition, DatetimeRange, Filter, PointId, Range, Timestamp};
+use serde::Serialize;
+use serde_json::{Map, Value as JsonValue};
+
+/// A condition on one payload field. Nested fields use dotted paths (`project.name`), and
+/// array fields match when any of their elements does, as in Qdrant.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum FieldCondition {
+    /// Equal to a string, number or boolean. Numbers compare as `f64`, so `3` equals
+    /// `3.0` on every backend.
+    Eq { field: String, value: JsonValue },
+    /// Equal to any of the values; matches nothing if `values` is empty.
+    Any {
+        field: String,
+        values: Vec<JsonValue>,
+    },
+    /// Number within the inclusive bounds.
+    Range {
+        field: String,
+        gte: Option<f64>,
+        lte: Option<f64>,
+    },
+    /// RFC 3339 datetime at or after `after` and before `before`.
+    DatetimeRange {
+        field: String,
+        after: Option<DateTime<Utc>>,
+        before: Option<DateTime<Utc>>,
+    },
+}
+
+/// Backend-agnostic payload filter. Backends push it down to their native filters
+/// (`Filter` for Qdrant) or evaluate it with [`MetadataFilter::matches`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MetadataFilter {
+    pub must: Vec<FieldCondition>,
+    pub must_not: Vec<FieldCondition>,
+}
+
+/// Builds a [`MetadataFilter`]; all conditions have to hold.
+///
+/// ```
+/// use chrono::{TimeZone, Utc};
+/// use liquid_memory::vectorstore::filter::FilterBuilder;
+///
+/// let filter = FilterBuilder::new()
+///     .eq("project", "x")
+///     .after("timestamp", Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap())
+///     .build();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FilterBuilder {
+    filter: MetadataFilter,
+}
+
+impl FilterBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn eq(mut self, field: impl Into<String>, value: impl Into<JsonValue>) -> Self {
+        self.filter.must.push(FieldCondition::Eq {
+            field: field.into(),
+            value: value.into(),
+        });
+        self
+    }
+
+    pub fn not_eq(mut self, field: impl Into<String>, value: impl Into<JsonValue>) -> Self {
+        self.filter.must_not.push(FieldCondition::Eq {
+            field: field.into(),
+            value: value.into(),
+        });
+        self
+    }
+
+    pub fn any(
+        mut self,
+        field: impl Into<String>,
+        values: impl IntoIterator<Item = impl Into<JsonValue>>,
+    ) -> Self {
+        self.filter.must.push(FieldCondition::Any {
+            field: field.into(),
+            values: values.into_iter().map(Into::into).collect(),
+        });
+        self
+    }
+
+    pub fn range(mut self, field: impl Into<String>, gte: Option<f64>, lte: Option<f64>) -> Self {
+        self.filter.must.push(FieldCondition::Range {
+            field: field.into(),
+            gte,
+            lte,
+        });
+        self
+    }
+
+    /// Datetime at or after `after`.
+    pub fn after(self, field: impl Into<String>, after: DateTime<Utc>) -> Self {
+        self.datetime_range(field, Some(after), None)
+    }
+
+    /// Datetime strictly before `before`.
+    pub fn before(self, field: impl Into<String>, before: DateTime<Utc>) -> Self {
+        self.datetime_range(field, None, Some(before))
+    }
+
+    pub fn datetime_range(
+        mut self,
+        field: impl Into<String>,
+        after: Option<DateTime<Utc>>,
+        before: Option<DateTime<Utc>>,
+    ) -> Self {
+        self.filter.must.push(FieldCondition::DatetimeRange {
+            field: field.into(),
+            after,
+            before,
+        });
+        self
+    }
+
+    pub fn build(self) -> MetadataFilter {
+        self.filter
+    }
+}
+
+fn lookup<'a>(payload: &'a Map<String, JsonValue>, field: &str) -> Option<&'a JsonValue> {
+    let mut parts = field.split('.');
+    let mut value = payload.get(parts.next()?)?;
+    for part in parts {
+        value = value.get(part)?;
+    }
+    Some(value)
+}
+
+// Equality as Qdrant evaluates `FieldCondition::Eq`: numbers by value, whatever their type
+fn json_eq(value: &JsonValue, expected: &JsonValue) -> bool {
+    match (value, expected) {
+        (JsonValue::Number(value), JsonValue::Number(expected)) => {
+            value.as_f64() == expected.as_f64()
+        }
+        (value, expected) => value == expected,
+    }
+}
+
+fn value_matches(value: &JsonValue, condition: &FieldCondition) -> bool {
+    if let JsonValue::Array(values) = value {
+        return values.iter().any(|value| value_matches(value, condition));
+    }
+    match condition {
+        FieldCondition::Eq {
+            value: expected, ..
+        } => json_eq(value, expected),
+        FieldCondition::Any { values, .. } => {
+            values.iter().any(|expected| json_eq(value, expected))
+        }
+        FieldCondition::Range { gte, lte, .. } => value.as_f64().is_some_and(|number| {
+            gte.is_none_or(|gte| number >= gte) && lte.is_none_or(|lte| number <= lte)
+        }),
+        FieldCondition::DatetimeRange { after, before, .. } => value
+            .as_str()
+            .and_then(|datetime| DateTime::parse_from_rfc3339(datetime).ok())
+            .is_some_and(|datetime| {
+                after.is_none_or(|after| datetime >= after)
+                    && before.is_none_or(|before| datetime < before)
+            }),
+    }
+}
+
+impl FieldCondition {
+    pub fn field(&self) -> &str {
+        match self {
+            Self::Eq { field, .. }
+            | Self::Any { field, .. }
+            | Self::Range { field, .. }
+            | Self::DatetimeRange { field, .. } => field,
+        }
+    }
+
+    pub fn matches(&self, payload: &Map<String, JsonValue>) -> bool {
+        lookup(payload, self.field()).is_some_and(|value| value_matches(value, self))
+    }
+
+    fn to_qdrant(&self) -> Condition {
+        match self {
+            Self::Eq { field, value } => match value {
+                JsonValue::Bool(value) => Condition::matches(field.as_str(), *value),
+                // An integer match would miss the same number stored as a float
+                JsonValue::Number(number) => Condition::range(
+                    field.as_str(),
+                    Range {
+                        gte: number.as_f64(),
+                        lte: number.as_f64(),
+                        ..Default::default()
+                    },
+                ),
+                JsonValue::String(value) => Condition::matches(field.as_str(), value.clone()),
+                value => Condition::matches(field.as_str(), value.to_string()),
+            },
+            // Qdrant matches everything with an empty `should`, no id is in an empty list
+            Self::Any { values, .. } if values.is_empty() => {
+                Condition::has_id(Vec::<PointId>::new())
+            }
+            Self::Any { field, values } => Filter::should(values.iter().map(|value| {
+                Self::Eq {
+                    field: field.clone(),
+                    value: value.clone(),
+                }
+                .to_qdrant()
+            }))
+            .into(),
+            Self::Range { field, gte, lte } => Condition::range(
+                field.as_str(),
+                Range {
+                    gte: *gte,
+                    lte: *lte,
+                    ..Default::default()
+                },
+            ),
+            Self::DatetimeRange {
+                field,
+                after,
+                before,
+            } => Condition::datetime_range(
+                field.as_str(),
+                DatetimeRange {
+                    gte: after.map(timestamp),
+                    lt: before.map(timestamp),
+                    ..Default::default()
+                },
+            ),
+        }
+    }
+}
+
+fn timestamp(datetime: DateTime<Utc>) -> Timestamp {
+    Timestamp {
+        seconds: datetime.timestamp(),
+        nanos: datetime.timestamp_subsec_nanos() as i32,
+    }
+}
+
+impl MetadataFilter {
+    pub fn is_empty(&self) -> bool {
+        self.must.is_empty() && self.must_not.is_empty()
+    }
+
+    pub fn matches(&self, payload: &Map<String, JsonValue>) -> bool {
+        self.must.iter().all(|condition| condition.matches(payload))
+            && !self
+                .must_not
+                .iter()
+                .any(|condition| condition.matches(payload))
+    }
+
+    /// Combines both filters; all conditions of each have to hold.
+    pub fn and(mut self, other: MetadataFilter) -> Self {
+        self.must.extend(other.must);
+        self.must_not.extend(other.must_not);
+        self
+    }
+}
+
+impl From<&MetadataFilter> for Filter {
+    fn from(filter: &MetadataFilter) -> Self {
+        Filter {
+            must: filter.must.iter().map(FieldCondition::to_qdrant).collect(),
+            must_not: filter
+                .must_not
+                .iter()
+                .map(FieldCondition::to_qdrant)
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    #[test]
+    fn test_metadata_filter_matches() {
+        let filter = FilterBuilder::new()
+            .eq("project.name", "x")
+            .any("tags", ["boots", "sandals"])
+            .range("price", Some(100.0), None)
+            .after(
+                "timestamp",
+                Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap(),
+            )
+            .not_eq("archived", true)
+            .build();
+
+        let payload = json!({
+            "project": {"name": "x"},
+            "tags": ["shoes", "boots"],
+            "price": 799,
+            "timestamp": "2025-03-01T10:00:00+00:00",
+        });
+        let payload = payload.as_object().unwrap();
+        assert!(filter.matches(payload));
+
+        let mut archived = payload.clone();
+        archived.insert("archived".to_string(), json!(true));
+        assert!(!filter.matches(&archived));
+
+        let mut old = payload.clone();
+        old.insert("timestamp".to_string(), json!("2024-12-31T23:59:59Z"));
+        assert!(!filter.matches(&old));
+
+        let mut missing = payload.clone();
+        missing.remove("price");
+        assert!(!filter.matches(&missing));
+    }
+
+    #[test]
+    fn test_metadata_filter_to_qdrant() {
+        let filter = FilterBuilder::new()
+            .eq("project", "x")
+            .before(
+                "timestamp",
+                Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap(),
+            )
+            .not_eq("archived", true)
+            .build();
+        let filter = Filter::from(&filter);
+        assert_eq!(filter.must.len(), 2);
+        assert_eq!(
+            filter.must[0],
+            Condition::matches("project", "x".to_string())
+        );
+        assert_eq!(filter.must_not[0], Condition::matches("archived", true));
+    }
+
+    #[test]
+    fn test_empty_any_matches_nothing() {
+        let filter = FilterBuilder::new()
+            .any("tags", Vec::<String>::new())
+            .build();
+        assert!(!filter.matches(json!({"tags": ["boots"]}).as_object().unwrap()));
+        assert!(!filter.matches(&Map::new()));
+        assert_eq!(
+            Filter::from(&filter).must[0],
+            Condition::has_id(Vec::<PointId>::new())
+        );
+    }
+
+    #[test]
+    fn test_numbers_equal_across_types() {
+        let filter = FilterBuilder::new()
+            .eq("rating", 3)
+            .any("size", [42.0, 43.0])
+            .build();
+        for payload in [
+            json!({"rating": 3, "size": 42}),
+            json!({"rating": 3.0, "size": 42.0}),
+        ] {
+            assert!(filter.matches(payload.as_object().unwrap()));
+        }
+        assert!(!filter.matches(json!({"rating": 3.5, "size": 42}).as_object().unwrap()));
+
+        // Qdrant gets the same value range for integers and floats
+        let rating = |value: JsonValue| {
+            Filter::from(&FilterBuilder::new().eq("rating", value).build()).must[0].clone()
+        };
+        let three = Condition::range(
+            "rating",
+            Range {
+                gte: Some(3.0),
+                lte: Some(3.0),
+                ..Default::default()
+            },
+        );
+        assert_eq!(rating(json!(3)), three);
+        assert_eq!(rating(json!(3.0)), three);
+    }
+}
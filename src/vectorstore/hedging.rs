@@ -0,0 +1,173 @@
+use futures::future::{select, Either};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const LATENCY_WINDOW: usize = 256;
+
+/// Sends a second copy of a query when the first one takes longer than a percentile of
+/// recent query latencies, and returns whichever answers first. With read replicas the
+/// copy goes to the next replica, so one slow node does not stall interactive recall.
+///
+/// Only reads are hedged; the losing request is cancelled but the server may still
+/// process it.
+pub struct RequestHedging {
+    percentile: f64,
+    min_delay: Duration,
+    min_samples: usize,
+    latencies: Mutex<VecDeque<Duration>>,
+    hedged: AtomicU64,
+}
+
+impl Default for RequestHedging {
+    fn default() -> Self {
+        Self {
+            percentile: 0.95,
+            min_delay: Duration::from_millis(5),
+            min_samples: 20,
+            latencies: Mutex::new(VecDeque::with_capacity(LATENCY_WINDOW)),
+            hedged: AtomicU64::new(0),
+        }
+    }
+}
+
+impl RequestHedging {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Latency percentile after which the second request is sent, 0.95 by default.
+    pub fn with_percentile(mut self, percentile: f64) -> Self {
+        self.percentile = percentile.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Lower bound of the hedging delay, so fast queries are not all sent twice.
+    pub fn with_min_delay(mut self, min_delay: Duration) -> Self {
+        self.min_delay = min_delay;
+        self
+    }
+
+    /// Queries to observe before hedging starts, 20 by default.
+    pub fn with_min_samples(mut self, min_samples: usize) -> Self {
+        self.min_samples = min_samples;
+        self
+    }
+
+    /// Delay after which a query is hedged, `None` until enough latencies are known.
+    pub fn delay(&self) -> Option<Duration> {
+        let latencies = self.latencies.lock().unwrap();
+        if latencies.is_empty() || latencies.len() < self.min_samples {
+            return None;
+        }
+        let mut sorted: Vec<Duration> = latencies.iter().copied().collect();
+        sorted.sort();
+        let idx = ((sorted.len() - 1) as f64 * self.percentile).round() as usize;
+        Some(sorted[idx].max(self.min_delay))
+    }
+
+    /// Number of queries a second request was sent for.
+    pub fn hedged_requests(&self) -> u64 {
+        self.hedged.load(Ordering::Relaxed)
+    }
+
+    fn record(&self, latency: Duration) {
+        let mut latencies = self.latencies.lock().unwrap();
+        if latencies.len() == LATENCY_WINDOW {
+            latencies.pop_front();
+        }
+        latencies.push_back(latency);
+    }
+
+    /// Runs `send`, and runs it again if the first request is still pending after
+    /// [`delay`](Self::delay). If the first response is an error, the other request is
+    /// awaited.
+    pub(crate) async fn run<T, E, F>(&self, send: impl Fn() -> F) -> Result<T, E>
+    where
+        F: Future<Output = Result<T, E>>,
+    {
+        let started = Instant::now();
+        let Some(delay) = self.delay() else {
+            let result = send().await;
+            if result.is_ok() {
+                self.record(started.elapsed());
+            }
+            return result;
+        };
+
+        let mut first = pin!(send());
+        match select(first.as_mut(), pin!(tokio::time::sleep(delay))).await {
+            Either::Left((result, _)) => {
+                if result.is_ok() {
+                    self.record(started.elapsed());
+                }
+                return result;
+            }
+            Either::Right(((), _)) => {}
+        }
+
+        self.hedged.fetch_add(1, Ordering::Relaxed);
+        let hedged_at = Instant::now();
+        let second = pin!(send());
+        let (result, started) = match select(first, second).await {
+            Either::Left((Ok(response), _)) => (Ok(response), started),
+            Either::Right((Ok(response), _)) => (Ok(response), hedged_at),
+            Either::Left((Err(_), second)) => (second.await, hedged_at),
+            Either::Right((Err(_), first)) => (first.await, started),
+        };
+        if result.is_ok() {
+            self.record(started.elapsed());
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[tokio::test]
+    async fn test_hedged_request_wins() {
+        let hedging = RequestHedging::new().with_min_samples(3);
+        for _ in 0..3 {
+            hedging.run(|| async { Ok::<_, ()>(0) }).await.unwrap();
+        }
+        assert_eq!(hedging.delay(), Some(Duration::from_millis(5)));
+
+        let attempts = AtomicUsize::new(0);
+        let started = Instant::now();
+        let response = hedging
+            .run(|| {
+                let attempt = attempts.fetch_add(1, Ordering::Relaxed);
+                async move {
+                    if attempt == 0 {
+                        tokio::time::sleep(Duration::from_secs(5)).await;
+                    }
+                    Ok::<_, ()>(attempt)
+                }
+            })
+            .await;
+        assert_eq!(response, Ok(1));
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(hedging.hedged_requests(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_no_hedging_without_samples() {
+        let hedging = RequestHedging::new();
+        assert_eq!(hedging.delay(), None);
+        let attempts = AtomicUsize::new(0);
+        let _ = hedging
+            .run(|| {
+                attempts.fetch_add(1, Ordering::Relaxed);
+                async { Err::<(), _>("unavailable") }
+            })
+            .await;
+        assert_eq!(attempts.load(Ordering::Relaxed), 1);
+        assert_eq!(hedging.delay(), None);
+    }
+}
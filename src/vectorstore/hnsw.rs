@@ -0,0 +1,343 @@
+use crate::similarity;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashSet};
+
+#[derive(Debug, Clone, Copy)]
+struct Candidate {
+    similarity: f32,
+    node: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.similarity
+            .total_cmp(&other.similarity)
+            .then(self.node.cmp(&other.node))
+    }
+}
+
+struct Node {
+    // Normalized, so the dot product is the cosine similarity
+    vector: Vec<f32>,
+    // Neighbors for each layer the node is on, layer 0 first
+    neighbors: Vec<Vec<usize>>,
+    deleted: bool,
+}
+
+/// Hierarchical navigable small world graph (Malkov & Yashunin) for approximate cosine
+/// similarity search. Nodes are identified by insertion order. Deleted nodes are only
+/// marked, so the graph stays connected; they are skipped in results and still take up
+/// memory, so owners should rebuild the index once [`deleted`](Self::deleted) grows.
+pub struct Hnsw {
+    m: usize,
+    m0: usize,
+    ef_construction: usize,
+    level_factor: f64,
+    nodes: Vec<Node>,
+    deleted: usize,
+    entry_point: Option<usize>,
+    max_level: usize,
+    rng: u64,
+}
+
+impl Default for Hnsw {
+    fn default() -> Self {
+        Self::new(16, 100)
+    }
+}
+
+impl Hnsw {
+    /// `m` is the number of neighbors per node and layer (twice that on layer 0), and
+    /// `ef_construction` the candidate list size when inserting.
+    pub fn new(m: usize, ef_construction: usize) -> Self {
+        let m = m.max(2);
+        Self {
+            m,
+            m0: 2 * m,
+            ef_construction: ef_construction.max(m),
+            level_factor: 1.0 / (m as f64).ln(),
+            nodes: Vec::new(),
+            deleted: 0,
+            entry_point: None,
+            max_level: 0,
+            rng: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Number of nodes marked deleted, included in [`len`](Self::len).
+    pub fn deleted(&self) -> usize {
+        self.deleted
+    }
+
+    // xorshift64*, good enough for level assignment and deterministic across runs
+    fn random_unit(&mut self) -> f64 {
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        let value = self.rng.wrapping_mul(0x2545_f491_4f6c_dd1d);
+        ((value >> 11) as f64 + 1.0) / (1u64 << 53) as f64
+    }
+
+    fn random_level(&mut self) -> usize {
+        (-self.random_unit().ln() * self.level_factor) as usize
+    }
+
+    fn similarity(&self, query: &[f32], node: usize) -> f32 {
+        similarity::dot(query, &self.nodes[node].vector)
+    }
+
+    fn search_layer(
+        &self,
+        query: &[f32],
+        entry_points: &[usize],
+        ef: usize,
+        layer: usize,
+    ) -> Vec<Candidate> {
+        let mut visited: HashSet<usize> = entry_points.iter().copied().collect();
+        let mut candidates = BinaryHeap::new();
+        let mut results = BinaryHeap::new();
+        for &node in entry_points {
+            let candidate = Candidate {
+                similarity: self.similarity(query, node),
+                node,
+            };
+            candidates.push(candidate);
+            results.push(Reverse(candidate));
+        }
+
+        while let Some(candidate) = candidates.pop() {
+            let worst = results.peek().map(|Reverse(c)| c.similarity);
+            if results.len() >= ef && worst.is_some_and(|worst| candidate.similarity < worst) {
+                break;
+            }
+            for &neighbor in &self.nodes[candidate.node].neighbors[layer] {
+                if !visited.insert(neighbor) {
+                    continue;
+                }
+                let similarity = self.similarity(query, neighbor);
+                let worst = results.peek().map(|Reverse(c)| c.similarity);
+                if results.len() < ef || worst.is_some_and(|worst| similarity > worst) {
+                    let candidate = Candidate {
+                        similarity,
+                        node: neighbor,
+                    };
+                    candidates.push(candidate);
+                    results.push(Reverse(candidate));
+                    if results.len() > ef {
+                        results.pop();
+                    }
+                }
+            }
+        }
+
+        let mut results: Vec<Candidate> = results.into_iter().map(|Reverse(c)| c).collect();
+        results.sort_by(|a, b| b.cmp(a));
+        results
+    }
+
+    fn prune(&mut self, node: usize, layer: usize, max_connections: usize) {
+        let vector = &self.nodes[node].vector;
+        let mut scored: Vec<Candidate> = self.nodes[node].neighbors[layer]
+            .iter()
+            .map(|&neighbor| Candidate {
+                similarity: similarity::dot(vector, &self.nodes[neighbor].vector),
+                node: neighbor,
+            })
+            .collect();
+        scored.sort_by(|a, b| b.cmp(a));
+        scored.truncate(max_connections);
+        self.nodes[node].neighbors[layer] = scored.into_iter().map(|c| c.node).collect();
+    }
+
+    /// Adds a vector and returns its node id.
+    pub fn insert(&mut self, mut vector: Vec<f32>) -> usize {
+        similarity::normalize(&mut vector);
+        let level = self.random_level();
+        let node = self.nodes.len();
+        self.nodes.push(Node {
+            vector,
+            neighbors: vec![Vec::new(); level + 1],
+            deleted: false,
+        });
+
+        let Some(entry_point) = self.entry_point else {
+            self.entry_point = Some(node);
+            self.max_level = level;
+            return node;
+        };
+
+        let query = self.nodes[node].vector.clone();
+        let mut entry_points = vec![entry_point];
+        for layer in (level + 1..=self.max_level).rev() {
+            let nearest = self.search_layer(&query, &entry_points, 1, layer);
+            entry_points = vec![nearest[0].node];
+        }
+
+        for layer in (0..=level.min(self.max_level)).rev() {
+            let candidates = self.search_layer(&query, &entry_points, self.ef_construction, layer);
+            let max_connections = if layer == 0 { self.m0 } else { self.m };
+            let neighbors: Vec<usize> = candidates.iter().take(self.m).map(|c| c.node).collect();
+            for &neighbor in &neighbors {
+                self.nodes[neighbor].neighbors[layer].push(node);
+                if self.nodes[neighbor].neighbors[layer].len() > max_connections {
+                    self.prune(neighbor, layer, max_connections);
+                }
+            }
+            self.nodes[node].neighbors[layer] = neighbors;
+            entry_points = candidates.into_iter().map(|c| c.node).collect();
+        }
+
+        if level > self.max_level {
+            self.max_level = level;
+            self.entry_point = Some(node);
+        }
+        node
+    }
+
+    pub fn delete(&mut self, node: usize) {
+        if let Some(node) = self.nodes.get_mut(node) {
+            if !node.deleted {
+                node.deleted = true;
+                self.deleted += 1;
+            }
+        }
+    }
+
+    /// Returns up to `k` non-deleted `(node, cosine similarity)` pairs, best first. Larger
+    /// `ef` trades speed for recall; it is widened while deleted nodes crowd out live ones.
+    pub fn search(&self, query: &[f32], k: usize, ef: usize) -> Vec<(usize, f32)> {
+        let Some(entry_point) = self.entry_point else {
+            return Vec::new();
+        };
+        let mut query = query.to_vec();
+        similarity::normalize(&mut query);
+
+        let mut entry_points = vec![entry_point];
+        for layer in (1..=self.max_level).rev() {
+            let nearest = self.search_layer(&query, &entry_points, 1, layer);
+            entry_points = vec![nearest[0].node];
+        }
+        let live = self.nodes.len() - self.deleted;
+        let mut ef = ef.max(k);
+        loop {
+            let hits: Vec<(usize, f32)> = self
+                .search_layer(&query, &entry_points, ef, 0)
+                .into_iter()
+                .filter(|c| !self.nodes[c.node].deleted)
+                .take(k)
+                .map(|c| (c.node, c.similarity))
+                .collect();
+            if hits.len() >= k.min(live) || ef >= self.nodes.len() {
+                return hits;
+            }
+            ef *= 2;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vectors(count: usize, dimension: usize) -> Vec<Vec<f32>> {
+        let mut state = 42u32;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as f32 / u32::MAX as f32 - 0.5
+        };
+        (0..count)
+            .map(|_| (0..dimension).map(|_| next()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_recall_against_brute_force() {
+        let data = vectors(2000, 16);
+        let mut index = Hnsw::default();
+        for vector in &data {
+            index.insert(vector.clone());
+        }
+
+        let mut hits = 0;
+        let queries = vectors(20, 16);
+        for query in &queries {
+            let mut exact: Vec<(usize, f32)> = data
+                .iter()
+                .enumerate()
+                .map(|(node, vector)| (node, similarity::cosine(query, vector)))
+                .collect();
+            exact.sort_by(|a, b| b.1.total_cmp(&a.1));
+            let exact: HashSet<usize> = exact.iter().take(10).map(|(node, _)| *node).collect();
+
+            let approximate = index.search(query, 10, 64);
+            hits += approximate
+                .iter()
+                .filter(|(node, _)| exact.contains(node))
+                .count();
+        }
+        // recall@10
+        assert!(hits as f32 / (queries.len() * 10) as f32 >= 0.9);
+    }
+
+    #[test]
+    fn test_delete() {
+        let mut index = Hnsw::default();
+        let a = index.insert(vec![1.0, 0.0]);
+        let b = index.insert(vec![0.9, 0.1]);
+        index.insert(vec![0.0, 1.0]);
+        assert_eq!(index.search(&[1.0, 0.0], 1, 10)[0].0, a);
+        index.delete(a);
+        assert_eq!(index.search(&[1.0, 0.0], 1, 10)[0].0, b);
+        assert_eq!(index.search(&[1.0, 0.0], 5, 10).len(), 2);
+        index.delete(a);
+        assert_eq!(index.deleted(), 1);
+    }
+
+    #[test]
+    fn test_search_skips_deleted() {
+        let data = vectors(500, 8);
+        let mut index = Hnsw::default();
+        for vector in &data {
+            index.insert(vector.clone());
+        }
+        let query = &data[0];
+        // Delete the nearest 100, which would otherwise fill the whole candidate list
+        let mut nearest: Vec<(usize, f32)> = data
+            .iter()
+            .enumerate()
+            .map(|(node, vector)| (node, similarity::cosine(query, vector)))
+            .collect();
+        nearest.sort_by(|a, b| b.1.total_cmp(&a.1));
+        for &(node, _) in &nearest[..100] {
+            index.delete(node);
+        }
+
+        let hits = index.search(query, 10, 16);
+        assert_eq!(hits.len(), 10);
+        assert!(hits.iter().all(|(node, _)| *node != nearest[0].0));
+    }
+}
@@ -0,0 +1,81 @@
+use crate::vectorstore::qdrant_client::point_id_from_key;
+use uuid::Uuid;
+
+/// How point ids are generated for writes that do not pass their own.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IdStrategy {
+    /// Random UUIDs.
+    #[default]
+    UuidV4,
+    /// Time-ordered UUIDs, so points written together get neighbouring ids, which keeps
+    /// append-heavy collections' inserts local in Qdrant's id index.
+    UuidV7,
+    /// UUIDs derived from the point's content and the source it was ingested from, so
+    /// writing the same content from the same source again overwrites the point instead of
+    /// duplicating it. The same chunk from two sources gets two ids; points without a
+    /// source are keyed on their content alone and share an id across writers.
+    ContentHash,
+    /// No generated ids: writes have to pass their own.
+    CallerProvided,
+}
+
+impl IdStrategy {
+    /// Id for a point with the given source and content, `None` for
+    /// [`IdStrategy::CallerProvided`]. A source equal to the content, e.g. an image path,
+    /// adds nothing to the key.
+    pub fn generate(&self, source: Option<&str>, content: &str) -> Option<String> {
+        match self {
+            Self::UuidV4 => Some(Uuid::new_v4().to_string()),
+            Self::UuidV7 => Some(Uuid::now_v7().to_string()),
+            Self::ContentHash => Some(match source {
+                Some(source) if source != content => {
+                    point_id_from_key(&format!("{source}\0{content}"))
+                }
+                _ => point_id_from_key(content),
+            }),
+            Self::CallerProvided => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate() {
+        assert_eq!(
+            IdStrategy::ContentHash.generate(None, "boots"),
+            IdStrategy::ContentHash.generate(None, "boots")
+        );
+        assert_ne!(
+            IdStrategy::UuidV4.generate(None, "boots"),
+            IdStrategy::UuidV4.generate(None, "boots")
+        );
+        let first = IdStrategy::UuidV7.generate(None, "a").unwrap();
+        let second = IdStrategy::UuidV7.generate(None, "b").unwrap();
+        assert!(first < second);
+        assert_eq!(IdStrategy::CallerProvided.generate(None, "boots"), None);
+    }
+
+    #[test]
+    fn test_content_hash_keys_on_source() {
+        let hash = IdStrategy::ContentHash;
+        assert_eq!(
+            hash.generate(Some("a.md"), "boots"),
+            hash.generate(Some("a.md"), "boots")
+        );
+        assert_ne!(
+            hash.generate(Some("a.md"), "boots"),
+            hash.generate(Some("b.md"), "boots")
+        );
+        assert_ne!(
+            hash.generate(Some("a.md"), "boots"),
+            hash.generate(None, "boots")
+        );
+        assert_eq!(
+            hash.generate(Some("cat.png"), "cat.png"),
+            hash.generate(None, "cat.png")
+        );
+    }
+}
@@ -0,0 +1,508 @@
+use crate::similarity;
+use crate::vectorstore::filter::MetadataFilter;
+use crate::vectorstore::hnsw::Hnsw;
+use crate::vectorstore::vector_store::{
+    named_vector_collection, SearchHit, VectorPoint, VectorStore, VectorStoreError,
+};
+use serde_json::{Map, Value as JsonValue};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+// Below this many points an exact scan is as fast as the graph and always exact
+const EXACT_SEARCH_LIMIT: usize = 1024;
+
+const MIN_EF_SEARCH: usize = 64;
+
+// The index is rebuilt from the live points once this fraction of its nodes are deleted
+const MAX_DELETED_FRACTION: f64 = 0.25;
+
+struct StoredPoint {
+    node: usize,
+    vector: Vec<f32>,
+    payload: Map<String, JsonValue>,
+}
+
+struct InMemoryCollection {
+    dimension: usize,
+    index: Hnsw,
+    points: HashMap<String, StoredPoint>,
+    // Node id of the index to point id
+    node_ids: Vec<String>,
+}
+
+impl InMemoryCollection {
+    fn new(dimension: usize) -> Self {
+        Self {
+            dimension,
+            index: Hnsw::default(),
+            points: HashMap::new(),
+            node_ids: Vec::new(),
+        }
+    }
+
+    fn upsert(&mut self, point: VectorPoint) {
+        // Updated points get a new node, the old one is only marked deleted
+        if let Some(previous) = self.points.get(&point.id) {
+            self.index.delete(previous.node);
+        }
+        let node = self.index.insert(point.vector.clone());
+        self.node_ids.push(point.id.clone());
+        self.points.insert(
+            point.id,
+            StoredPoint {
+                node,
+                vector: point.vector,
+                payload: point.payload,
+            },
+        );
+    }
+
+    fn delete(&mut self, id: &str) {
+        if let Some(point) = self.points.remove(id) {
+            self.index.delete(point.node);
+        }
+    }
+
+    // Replaced and deleted points stay in the index as deleted nodes until it is rebuilt
+    fn compact(&mut self) {
+        if (self.index.deleted() as f64) <= MAX_DELETED_FRACTION * self.index.len() as f64 {
+            return;
+        }
+        let mut index = Hnsw::default();
+        let mut node_ids = Vec::with_capacity(self.points.len());
+        for (id, point) in &mut self.points {
+            point.node = index.insert(point.vector.clone());
+            node_ids.push(id.clone());
+        }
+        self.index = index;
+        self.node_ids = node_ids;
+    }
+
+    // Applies `update` to the payload of every point, or to none if one is missing
+    fn update_payloads(
+        &mut self,
+        ids: &[String],
+        update: impl Fn(&mut Map<String, JsonValue>),
+    ) -> Result<(), VectorStoreError> {
+        if let Some(missing) = ids.iter().find(|id| !self.points.contains_key(*id)) {
+            return Err(VectorStoreError::PointNotFound(missing.clone()));
+        }
+        for id in ids {
+            update(&mut self.points.get_mut(id).unwrap().payload);
+        }
+        Ok(())
+    }
+
+    fn hit(&self, id: &str, score: f32) -> Option<SearchHit> {
+        let point = self.points.get(id)?;
+        Some(SearchHit {
+            id: id.to_string(),
+            score,
+            payload: point.payload.clone(),
+        })
+    }
+
+    fn query(
+        &self,
+        vector: &[f32],
+        limit: usize,
+        filter: Option<&MetadataFilter>,
+    ) -> Vec<SearchHit> {
+        // Filtered queries scan the matching points, so they never miss matches the graph
+        // search would have pruned
+        if self.points.len() <= EXACT_SEARCH_LIMIT || filter.is_some() {
+            let mut hits: Vec<SearchHit> = self
+                .points
+                .iter()
+                .filter(|(_, point)| filter.is_none_or(|filter| filter.matches(&point.payload)))
+                .filter_map(|(id, point)| self.hit(id, similarity::cosine(vector, &point.vector)))
+                .collect();
+            hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+            hits.truncate(limit);
+            return hits;
+        }
+
+        self.index
+            .search(vector, limit, (2 * limit).max(MIN_EF_SEARCH))
+            .into_iter()
+            .filter_map(|(node, score)| self.hit(&self.node_ids[node], score))
+            .collect()
+    }
+}
+
+/// [`VectorStore`] kept in process memory, for tests and local/offline use. Collections are
+/// created on first upsert and use cosine similarity. Large collections are searched with
+/// an HNSW index, so results are approximate there.
+#[derive(Default)]
+pub struct InMemoryVectorStore {
+    collections: RwLock<HashMap<String, InMemoryCollection>>,
+}
+
+impl InMemoryVectorStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of points in the collection, 0 if it does not exist.
+    pub fn len(&self, collection: &str) -> usize {
+        self.collections
+            .read()
+            .unwrap()
+            .get(collection)
+            .map_or(0, |collection| collection.points.len())
+    }
+
+    pub fn get(&self, collection: &str, id: &str) -> Option<VectorPoint> {
+        let collections = self.collections.read().unwrap();
+        let point = collections.get(collection)?.points.get(id)?;
+        Some(VectorPoint::new(
+            id,
+            point.vector.clone(),
+            point.payload.clone(),
+        ))
+    }
+}
+
+impl VectorStore for InMemoryVectorStore {
+    async fn upsert(
+        &self,
+        collection: &str,
+        points: Vec<VectorPoint>,
+    ) -> Result<(), VectorStoreError> {
+        let Some(dimension) = points.first().map(|point| point.vector.len()) else {
+            return Ok(());
+        };
+        let mut collections = self.collections.write().unwrap();
+        let collection = collections
+            .entry(collection.to_string())
+            .or_insert_with(|| InMemoryCollection::new(dimension));
+
+        if let Some(point) = points
+            .iter()
+            .find(|point| point.vector.len() != collection.dimension)
+        {
+            return Err(VectorStoreError::DimensionMismatch {
+                expected: collection.dimension,
+                actual: point.vector.len(),
+            });
+        }
+        for point in points {
+            collection.upsert(point);
+        }
+        collection.compact();
+        Ok(())
+    }
+
+    async fn query_filtered(
+        &self,
+        collection: &str,
+        vector: Vec<f32>,
+        limit: u64,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<SearchHit>, VectorStoreError> {
+        let collections = self.collections.read().unwrap();
+        let collection = collections
+            .get(collection)
+            .ok_or_else(|| VectorStoreError::CollectionNotFound(collection.to_string()))?;
+        if vector.len() != collection.dimension {
+            return Err(VectorStoreError::DimensionMismatch {
+                expected: collection.dimension,
+                actual: vector.len(),
+            });
+        }
+        Ok(collection.query(&vector, limit as usize, filter))
+    }
+
+    async fn delete(&self, collection: &str, ids: Vec<String>) -> Result<(), VectorStoreError> {
+        let mut collections = self.collections.write().unwrap();
+        let collection = collections
+            .get_mut(collection)
+            .ok_or_else(|| VectorStoreError::CollectionNotFound(collection.to_string()))?;
+        for id in ids {
+            collection.delete(&id);
+        }
+        collection.compact();
+        Ok(())
+    }
+
+    async fn delete_matching(
+        &self,
+        collection: &str,
+        filter: &MetadataFilter,
+    ) -> Result<u64, VectorStoreError> {
+        let mut collections = self.collections.write().unwrap();
+        let collection = collections
+            .get_mut(collection)
+            .ok_or_else(|| VectorStoreError::CollectionNotFound(collection.to_string()))?;
+        let matching: Vec<String> = collection
+            .points
+            .iter()
+            .filter(|(_, point)| filter.matches(&point.payload))
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &matching {
+            collection.delete(id);
+        }
+        collection.compact();
+        Ok(matching.len() as u64)
+    }
+
+    async fn set_payload(
+        &self,
+        collection: &str,
+        ids: Vec<String>,
+        fields: Map<String, JsonValue>,
+    ) -> Result<(), VectorStoreError> {
+        let mut collections = self.collections.write().unwrap();
+        collections
+            .get_mut(collection)
+            .ok_or_else(|| VectorStoreError::CollectionNotFound(collection.to_string()))?
+            .update_payloads(&ids, |payload| payload.extend(fields.clone()))
+    }
+
+    async fn delete_payload(
+        &self,
+        collection: &str,
+        ids: Vec<String>,
+        keys: Vec<String>,
+    ) -> Result<(), VectorStoreError> {
+        let mut collections = self.collections.write().unwrap();
+        collections
+            .get_mut(collection)
+            .ok_or_else(|| VectorStoreError::CollectionNotFound(collection.to_string()))?
+            .update_payloads(&ids, |payload| {
+                for key in &keys {
+                    payload.remove(key);
+                }
+            })
+    }
+
+    async fn scroll(
+        &self,
+        collection: &str,
+        offset: Option<String>,
+        limit: u64,
+    ) -> Result<(Vec<VectorPoint>, Option<String>), VectorStoreError> {
+        let collections = self.collections.read().unwrap();
+        let collection = collections
+            .get(collection)
+            .ok_or_else(|| VectorStoreError::CollectionNotFound(collection.to_string()))?;
+        let mut ids: Vec<&String> = collection
+            .points
+            .keys()
+            .filter(|id| offset.as_ref().is_none_or(|offset| *id >= offset))
+            .collect();
+        ids.sort();
+        let next = ids.get(limit as usize).map(|id| id.to_string());
+        let points = ids
+            .into_iter()
+            .take(limit as usize)
+            .map(|id| {
+                let point = &collection.points[id];
+                VectorPoint::new(id, point.vector.clone(), point.payload.clone())
+            })
+            .collect();
+        Ok((points, next))
+    }
+
+    async fn update_named_vector(
+        &self,
+        collection: &str,
+        point_id: &str,
+        vector_name: &str,
+        vector: Vec<f32>,
+    ) -> Result<(), VectorStoreError> {
+        let collection = named_vector_collection(collection, vector_name);
+        let point = self
+            .get(&collection, point_id)
+            .ok_or_else(|| VectorStoreError::PointNotFound(point_id.to_string()))?;
+        self.upsert(
+            &collection,
+            vec![VectorPoint::new(point_id, vector, point.payload)],
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vectorstore::filter::FilterBuilder;
+    use crate::vectorstore::vector_store::MultiVectorPoint;
+    use serde_json::json;
+
+    fn point(id: &str, vector: Vec<f32>, text: &str) -> VectorPoint {
+        let mut payload = Map::new();
+        payload.insert("text".to_string(), json!(text));
+        VectorPoint::new(id, vector, payload)
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_store() {
+        let store = InMemoryVectorStore::new();
+        store
+            .upsert(
+                "memories",
+                vec![
+                    point("a", vec![1.0, 0.0], "boots"),
+                    point("b", vec![0.0, 1.0], "sandals"),
+                ],
+            )
+            .await
+            .unwrap();
+
+        let hits = store.query("memories", vec![0.9, 0.1], 1).await.unwrap();
+        assert_eq!(hits[0].id, "a");
+        assert_eq!(hits[0].payload["text"], "boots");
+
+        // Replacing a point moves it
+        store
+            .upsert("memories", vec![point("a", vec![0.0, -1.0], "boots")])
+            .await
+            .unwrap();
+        assert_eq!(store.len("memories"), 2);
+        let hits = store.query("memories", vec![0.9, 0.1], 2).await.unwrap();
+        assert_eq!(hits[0].id, "b");
+
+        let mut fields = Map::new();
+        fields.insert("size".to_string(), json!(42));
+        store
+            .set_payload("memories", vec!["b".to_string()], fields.clone())
+            .await
+            .unwrap();
+        assert_eq!(store.get("memories", "b").unwrap().payload["size"], 42);
+        assert!(matches!(
+            store
+                .set_payload("memories", vec!["a".to_string(), "c".to_string()], fields)
+                .await,
+            Err(VectorStoreError::PointNotFound(id)) if id == "c"
+        ));
+        assert!(!store
+            .get("memories", "a")
+            .unwrap()
+            .payload
+            .contains_key("size"));
+        store
+            .delete_payload("memories", vec!["b".to_string()], vec!["size".to_string()])
+            .await
+            .unwrap();
+        assert!(!store
+            .get("memories", "b")
+            .unwrap()
+            .payload
+            .contains_key("size"));
+
+        store
+            .upsert("memories", vec![point("c", vec![1.0, 1.0], "clogs")])
+            .await
+            .unwrap();
+        let clogs = FilterBuilder::new().eq("text", "clogs").build();
+        assert_eq!(store.delete_matching("memories", &clogs).await.unwrap(), 1);
+        assert_eq!(store.len("memories"), 2);
+
+        store
+            .delete("memories", vec!["b".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(store.len("memories"), 1);
+        assert!(matches!(
+            store.query("memories", vec![1.0], 1).await,
+            Err(VectorStoreError::DimensionMismatch { .. })
+        ));
+        assert!(matches!(
+            store.query("missing", vec![1.0, 0.0], 1).await,
+            Err(VectorStoreError::CollectionNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_store_uses_index() {
+        let store = InMemoryVectorStore::new();
+        let points: Vec<VectorPoint> = (0..2 * EXACT_SEARCH_LIMIT)
+            .map(|i| {
+                let angle = i as f32 / 1000.0;
+                point(&i.to_string(), vec![angle.cos(), angle.sin()], "")
+            })
+            .collect();
+        store.upsert("large", points).await.unwrap();
+
+        let angle: f32 = 0.5;
+        let hits = store
+            .query("large", vec![angle.cos(), angle.sin()], 3)
+            .await
+            .unwrap();
+        assert_eq!(hits[0].id, "500");
+
+        // Deleting most points rebuilds the index from the rest
+        let deleted: Vec<String> = (0..1500).map(|i| i.to_string()).collect();
+        store.delete("large", deleted).await.unwrap();
+        {
+            let collections = store.collections.read().unwrap();
+            let collection = &collections["large"];
+            assert_eq!(collection.index.deleted(), 0);
+            assert_eq!(collection.index.len(), 2 * EXACT_SEARCH_LIMIT - 1500);
+        }
+        let angle: f32 = 1.7;
+        let hits = store
+            .query("large", vec![angle.cos(), angle.sin()], 3)
+            .await
+            .unwrap();
+        assert_eq!(hits[0].id, "1700");
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_named_vectors() {
+        let store = InMemoryVectorStore::new();
+        let payload = |text: &str| json!({"text": text}).as_object().unwrap().clone();
+        store
+            .upsert_multi(
+                "products",
+                vec![
+                    MultiVectorPoint::new("boot", payload("boots"))
+                        .with_vector("image", vec![1.0, 0.0])
+                        .with_vector("text", vec![0.0, 1.0, 0.0]),
+                    MultiVectorPoint::new("sandal", payload("sandals"))
+                        .with_vector("image", vec![0.0, 1.0])
+                        .with_vector("text", vec![1.0, 0.0, 0.0]),
+                ],
+            )
+            .await
+            .unwrap();
+
+        let by_image = store
+            .query_named("products", "image", vec![1.0, 0.1], 1, None)
+            .await
+            .unwrap();
+        assert_eq!(by_image[0].id, "boot");
+        let by_text = store
+            .query_named("products", "text", vec![1.0, 0.1, 0.0], 1, None)
+            .await
+            .unwrap();
+        assert_eq!(by_text[0].payload["text"], "sandals");
+
+        store
+            .update_named_vector("products", "boot", "text", vec![1.0, 0.0, 0.0])
+            .await
+            .unwrap();
+        let boot = store.get("products.text", "boot").unwrap();
+        assert_eq!(boot.vector, vec![1.0, 0.0, 0.0]);
+        assert_eq!(boot.payload["text"], "boots");
+        assert_eq!(
+            store.get("products.image", "boot").unwrap().vector,
+            vec![1.0, 0.0]
+        );
+        assert!(matches!(
+            store
+                .update_named_vector("products", "clog", "text", vec![1.0, 0.0, 0.0])
+                .await,
+            Err(VectorStoreError::PointNotFound(_))
+        ));
+
+        store
+            .delete_multi("products", &["image", "text"], vec!["boot".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(store.len("products.image"), 1);
+        assert_eq!(store.len("products.text"), 1);
+    }
+}
@@ -1,11 +1,201 @@
-use crate::embeddings::text_embedding_inference::TextEmbeddingInference;
+use crate::detection::region_detector::{crop_region, RegionDetector};
+use crate::embeddings::sparse::{Bm25Encoder, SparseEmbedding, SparseEncoding};
+use crate::embeddings::text_embedding_inference::{InputKind, TextEmbeddingInference};
+use crate::injection::{InjectionAction, InjectionScanner, INJECTION_RISK_FIELD};
+use crate::keywords::{KeywordExtractor, KEYWORDS_FIELD};
 use crate::llm::llm_client::LlmClientChat;
-use crate::utils::load_image_as_base64;
-use crate::vectorstore::qdrant_client::QdrantClient;
-use anyhow::Result;
-use chrono;
+use crate::memory::summarize::{summarize_long, SummarizeOptions, Summarizer};
+use crate::memory::translate::{translate_all, Translator, TRANSLATION_FIELD};
+use crate::text_cleaning::TextCleaner;
+use crate::usage::{self, RunUsage};
+use crate::utils::{
+    base64_encode, chunk_text, dhash, estimate_tokens, hamming_distance, load_image,
+    load_image_as_base64, thumbnail,
+};
+use crate::vectorstore::alt_text::generate_alt_text;
+use crate::vectorstore::caption_validation::CaptionValidation;
+use crate::vectorstore::filter::FilterBuilder;
+use crate::vectorstore::product_extraction::extract_product;
+use crate::vectorstore::qdrant_client::{point_id_to_string, QdrantClient, SOURCE_FIELD};
+use crate::vectorstore::retry_queue::{FailedItem, RetryContent, RetryQueue};
+use crate::vectorstore::vector_store::{VectorPoint, VectorStore};
+use anyhow::{anyhow, Result};
+use futures::future::try_join_all;
+use futures::try_join;
+use image::{DynamicImage, ImageFormat};
+use qdrant_client::qdrant::{Condition, Filter};
 use qdrant_client::Payload;
-use serde_json::json;
+use serde::Serialize;
+use serde_json::{json, Map, Value as JsonValue};
+use std::io::Cursor;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+// Translator calls in flight at once during ingestion
+const TRANSLATION_CONCURRENCY: usize = 4;
+
+/// Payload field identifying the ingestion run that wrote a source's current chunks.
+pub const SOURCE_REVISION_FIELD: &str = "source_revision";
+
+/// Payload field holding an inline `data:image/jpeg;base64,...` thumbnail of the image.
+pub const THUMBNAIL_FIELD: &str = "thumbnail";
+
+/// What to do with an image whose perceptual hash is close to one already stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateAction {
+    /// Do not ingest the image.
+    Skip,
+    /// Ingest the image with a `duplicate_of` payload field pointing at the original.
+    Link,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ImageDedup {
+    /// Maximum Hamming distance between two dHashes to consider the images duplicates.
+    pub max_distance: u32,
+    pub action: DuplicateAction,
+}
+
+impl Default for ImageDedup {
+    fn default() -> Self {
+        Self {
+            max_distance: 6,
+            action: DuplicateAction::Skip,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ThumbnailOptions {
+    /// Maximum width and height in pixels; keep it small, the thumbnail is returned with
+    /// every recalled point.
+    pub max_size: u32,
+}
+
+impl Default for ThumbnailOptions {
+    fn default() -> Self {
+        Self { max_size: 128 }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ImageIngestOptions {
+    pub dedup: Option<ImageDedup>,
+    /// Stores a thumbnail in the `thumbnail` payload field, so UIs can render recall
+    /// results without access to `image_path`.
+    pub thumbnail: Option<ThumbnailOptions>,
+    /// Loads, hashes and deduplicates the images without embedding or writing them.
+    pub dry_run: bool,
+    /// Queues images failing to embed or write in this file instead of failing the run,
+    /// see [`retry_failed`](crate::vectorstore::retry_queue::retry_failed).
+    pub retry_queue: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TextIngestOptions {
+    /// Stored in the indexed `source` payload field, as by [`ingest_texts_from_source`].
+    pub source_uri: Option<String>,
+    /// Cleans the documents, as one corpus, before chunking.
+    pub cleaning: Option<TextCleaner>,
+    /// Splits every document into chunks of at most this many estimated tokens with
+    /// [`chunk_text`]; documents are stored whole if `None`.
+    pub chunk_tokens: Option<usize>,
+    /// Stores each chunk's top keywords in the indexed [`KEYWORDS_FIELD`] payload field.
+    pub keywords: Option<KeywordExtractor>,
+    /// Strips likely prompt injections from the cleaned documents or tags the chunks
+    /// containing them in the indexed [`INJECTION_RISK_FIELD`] payload field.
+    pub injection: Option<InjectionScanner>,
+    /// Also stores a sparse vector per chunk, for a collection made with
+    /// [`QdrantClient::create_hybrid_collection`]; the dense embedding then goes to its
+    /// `dense` vector.
+    pub sparse: Option<SparseEncoding>,
+    /// Loads and chunks the documents without embedding or writing them.
+    pub dry_run: bool,
+    /// Queues chunks whose batch fails to embed or write in this file instead of failing
+    /// the run, see [`retry_failed`](crate::vectorstore::retry_queue::retry_failed).
+    pub retry_queue: Option<PathBuf>,
+}
+
+/// What an ingestion run did or, for a dry run, would do.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct IngestReport {
+    pub dry_run: bool,
+    /// Input documents or images.
+    pub documents: usize,
+    pub points: usize,
+    /// Estimated tokens sent to the text embedder, 0 for images.
+    pub embedding_tokens: usize,
+    /// Requests to the embedding server; for texts, estimated at the current batch size of
+    /// [`QdrantClient::embedding_batcher`].
+    pub embedding_calls: usize,
+    /// Dimensions of the collection's vectors, unknown for a dry run into a collection
+    /// that does not exist yet or while Qdrant is unreachable: a dry run never needs it.
+    pub vector_dimensions: Option<u64>,
+    /// Estimated bytes of the new payloads and, if the dimensions are known, vectors.
+    pub projected_bytes: u64,
+    /// Points that failed and were queued for a retry; `points` still counts them.
+    pub failed: usize,
+    /// Retry queue of the run, if one was configured.
+    pub retry_queue: Option<PathBuf>,
+    /// LLM and embedding calls of the run, empty for a dry run.
+    pub usage: RunUsage,
+}
+
+fn payload_bytes(payloads: &[Map<String, JsonValue>]) -> u64 {
+    payloads
+        .iter()
+        .map(|payload| JsonValue::Object(payload.clone()).to_string().len() as u64)
+        .sum()
+}
+
+// Summed dimensions of the collection's vectors, `None` if it does not exist
+async fn collection_dimensions(
+    collection_name: &str,
+    client: &QdrantClient,
+) -> Result<Option<u64>> {
+    if !client.check_collection(collection_name).await? {
+        return Ok(None);
+    }
+    let sizes = client.vector_sizes(collection_name).await?;
+    Ok(Some(sizes.into_iter().map(|(_, size)| size).sum()))
+}
+
+/// Payload fields with the thumbnail of an encoded image, e.g. to pass as metadata to
+/// [`ingest_multivector_with_metadata`].
+pub fn thumbnail_fields(data: &[u8], options: ThumbnailOptions) -> Result<Map<String, JsonValue>> {
+    let jpeg = thumbnail(data, options.max_size)?;
+    let mut fields = Map::new();
+    fields.insert(
+        THUMBNAIL_FIELD.to_string(),
+        json!(format!("data:image/jpeg;base64,{}", base64_encode(&jpeg))),
+    );
+    Ok(fields)
+}
+
+fn format_image_hash(hash: u64) -> String {
+    format!("{hash:016x}")
+}
+
+/// (point id, dHash) of every original (non-duplicate) image in the collection.
+async fn stored_image_hashes(
+    collection_name: &str,
+    client: &QdrantClient,
+) -> Result<Vec<(String, u64)>> {
+    let filter = Filter::must_not([Condition::is_empty("image_hash")]);
+    let points = client
+        .scroll_all(collection_name, Some(filter), false)
+        .await?;
+
+    Ok(points
+        .into_iter()
+        .filter(|point| !point.payload.contains_key("duplicate_of"))
+        .filter_map(|point| {
+            let hash = point.payload.get("image_hash")?.as_str()?;
+            let hash = u64::from_str_radix(hash, 16).ok()?;
+            Some((point_id_to_string(point.id.as_ref()?), hash))
+        })
+        .collect())
+}
 
 pub async fn ingest_images(
     collection_name: &str,
@@ -13,30 +203,242 @@ pub async fn ingest_images(
     embedding_url: &str,
     client: &QdrantClient,
 ) -> Result<()> {
+    ingest_images_with_dedup(collection_name, image_paths, embedding_url, client, None).await
+}
+
+/// Like [`ingest_images`], but images that are near-duplicates (by dHash) of an image
+/// already in the collection or earlier in `image_paths` are skipped or linked.
+/// The hash is always stored in the `image_hash` payload field.
+pub async fn ingest_images_with_dedup(
+    collection_name: &str,
+    image_paths: Vec<String>,
+    embedding_url: &str,
+    client: &QdrantClient,
+    dedup: Option<ImageDedup>,
+) -> Result<()> {
+    let options = ImageIngestOptions {
+        dedup,
+        ..Default::default()
+    };
+    ingest_images_with_options(collection_name, image_paths, embedding_url, client, options)
+        .await?;
+    Ok(())
+}
+
+/// Like [`ingest_images`], with deduplication (see [`ingest_images_with_dedup`]), inline
+/// thumbnails and dry runs configured by `options`.
+pub async fn ingest_images_with_options(
+    collection_name: &str,
+    image_paths: Vec<String>,
+    embedding_url: &str,
+    client: &QdrantClient,
+    options: ImageIngestOptions,
+) -> Result<IngestReport> {
+    let dedup = options.dedup;
+    let mut ids = Vec::new();
     let mut embeddings = Vec::new();
     let mut payloads = Vec::new();
+    let mut written_paths = Vec::new();
+    let mut failed = Vec::new();
+    let mut report = IngestReport {
+        dry_run: options.dry_run,
+        documents: image_paths.len(),
+        ..Default::default()
+    };
+
+    let mut known_hashes = match dedup {
+        Some(_) => stored_image_hashes(collection_name, client).await?,
+        None => Vec::new(),
+    };
+    let image_embedding_client =
+        TextEmbeddingInference::new(Some(embedding_url)).with_input_kind(InputKind::ImageBase64);
 
     for image_path in image_paths {
-        let base64_image = vec![load_image_as_base64(&image_path).await?];
+        let image = load_image(&image_path).await?;
+        let hash = dhash(&image)?;
 
-        let image_embedding_client = TextEmbeddingInference::new(Some(embedding_url));
-        let response = image_embedding_client
-            .embed(base64_image.clone())
-            .await
-            .unwrap();
+        let duplicate_of = dedup.and_then(|dedup| {
+            known_hashes
+                .iter()
+                .find(|(_, known)| hamming_distance(*known, hash) <= dedup.max_distance)
+                .map(|(id, _)| id.clone())
+        });
+        if duplicate_of.is_some() && dedup.map(|d| d.action) == Some(DuplicateAction::Skip) {
+            continue;
+        }
 
-        embeddings.push(response[0].clone());
+        let id = client.new_point_id(collection_name, None, &image_path)?;
+        let fields = client.payload_fields();
+        let mut payload = Map::new();
+        payload.insert(fields.image_path.clone(), json!(image_path));
+        payload.insert(SOURCE_FIELD.to_string(), json!(image_path));
+        payload.insert("image_hash".to_string(), json!(format_image_hash(hash)));
+        fields.insert_timestamp(&mut payload);
+        if let Some(thumbnail) = options.thumbnail {
+            payload.extend(thumbnail_fields(&image, thumbnail)?);
+        }
+        match duplicate_of {
+            Some(original) => {
+                payload.insert("duplicate_of".to_string(), json!(original));
+            }
+            None => known_hashes.push((id.clone(), hash)),
+        }
 
-        let payload = Payload::try_from(json!({
-            "image_path": image_path,
-            "timestamp": chrono::Utc::now().to_rfc3339()
-        }))?;
+        report.embedding_calls += 1;
+        if !options.dry_run {
+            let response = image_embedding_client
+                .embed(vec![base64_encode(&image)])
+                .await
+                .map_err(|e| anyhow!("Image embedding failed: {e}"));
+            match (response, &options.retry_queue) {
+                (Ok(response), _) => embeddings.extend(response.into_iter().next()),
+                (Err(e), Some(_)) => {
+                    failed.push(failed_image(
+                        collection_name,
+                        id,
+                        image_path,
+                        embedding_url,
+                        payload,
+                        &e,
+                    ));
+                    continue;
+                }
+                (Err(e), None) => return Err(e),
+            }
+        }
+
+        ids.push(id);
+        written_paths.push(image_path);
         payloads.push(payload);
     }
 
+    report.vector_dimensions = match embeddings.first() {
+        Some(embedding) => Some(embedding.len() as u64),
+        // A dry run only reads Qdrant to project the vectors' size
+        None if options.dry_run => collection_dimensions(collection_name, client)
+            .await
+            .unwrap_or(None),
+        None => collection_dimensions(collection_name, client).await?,
+    };
+    report.points = payloads.len() + failed.len();
+    report.projected_bytes =
+        payload_bytes(&payloads) + report.points as u64 * report.vector_dimensions.unwrap_or(0) * 4;
+    if options.dry_run {
+        return Ok(report);
+    }
+
     // Upsert points to vector store
+    let written: Vec<Payload> = payloads.iter().cloned().map(Payload::from).collect();
+    let upserted = client
+        .upsert_points_with_ids(collection_name, ids.clone(), embeddings, written)
+        .await;
+    match (upserted, &options.retry_queue) {
+        (Ok(_), _) => {}
+        (Err(e), Some(_)) => {
+            let e = anyhow::Error::from(e);
+            failed.extend(ids.into_iter().zip(written_paths).zip(payloads).map(
+                |((id, image_path), payload)| {
+                    failed_image(collection_name, id, image_path, embedding_url, payload, &e)
+                },
+            ));
+        }
+        (Err(e), None) => return Err(e.into()),
+    }
+    if let Some(path) = &options.retry_queue {
+        RetryQueue::new(path).push(&failed)?;
+        report.failed = failed.len();
+        report.retry_queue = Some(path.clone());
+    }
+    client
+        .create_keyword_index(collection_name, SOURCE_FIELD)
+        .await?;
+    Ok(report)
+}
+
+fn failed_image(
+    collection_name: &str,
+    id: String,
+    image_path: String,
+    embedding_url: &str,
+    payload: Map<String, JsonValue>,
+    error: &anyhow::Error,
+) -> FailedItem {
+    log::warn!("Queueing image {image_path} for retry: {error}");
+    FailedItem {
+        collection: collection_name.to_string(),
+        id,
+        content: RetryContent::Image { image_path },
+        embedding_url: embedding_url.to_string(),
+        sparse: None,
+        payload,
+        error: error.to_string(),
+        attempts: 1,
+    }
+}
+
+fn encode_png(image: &DynamicImage) -> Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    image.write_to(&mut Cursor::new(&mut buffer), ImageFormat::Png)?;
+    Ok(buffer)
+}
+
+/// Ingests each image and every region found by `detector` as separate points. Region
+/// points carry their `region` bounding box, `label`, `score` and the `parent_id` of the
+/// whole-image point, so a query matching an object resolves to the image containing it.
+pub async fn ingest_image_regions(
+    collection_name: &str,
+    image_paths: Vec<String>,
+    embedding_url: &str,
+    detector: &impl RegionDetector,
+    client: &QdrantClient,
+) -> Result<()> {
+    let image_embedding_client =
+        TextEmbeddingInference::new(Some(embedding_url)).with_input_kind(InputKind::ImageBase64);
+
+    for image_path in image_paths {
+        let data = load_image(&image_path).await?;
+        let image = image::load_from_memory(&data)?;
+        let regions = detector.detect(&image)?;
+
+        let parent_id = client.new_point_id(collection_name, None, &image_path)?;
+        let fields = client.payload_fields();
+        let mut image_payload = Map::new();
+        image_payload.insert(fields.image_path.clone(), json!(image_path));
+        image_payload.insert(SOURCE_FIELD.to_string(), json!(image_path));
+        fields.insert_timestamp(&mut image_payload);
+
+        let mut ids = vec![parent_id.clone()];
+        let mut images = vec![base64_encode(&data)];
+        let mut payloads = vec![Payload::from(image_payload.clone())];
+
+        for region in regions {
+            let crop = encode_png(&crop_region(&image, &region.bbox))?;
+            ids.push(client.new_point_id(
+                collection_name,
+                None,
+                &format!("{image_path}#{:?}", region.bbox),
+            )?);
+            images.push(base64_encode(&crop));
+            let mut payload = image_payload.clone();
+            payload.insert("parent_id".to_string(), json!(parent_id));
+            payload.insert("region".to_string(), json!(region.bbox));
+            payload.insert("label".to_string(), json!(region.label));
+            payload.insert("score".to_string(), json!(region.score));
+            payloads.push(Payload::from(payload));
+        }
+
+        // An image can have more regions than the embedding server takes in one request
+        let embeddings = client
+            .embedding_batcher()
+            .embed(&image_embedding_client, images)
+            .await
+            .map_err(|e| anyhow!("Image embedding failed: {e}"))?;
+        client
+            .upsert_points_with_ids(collection_name, ids, embeddings, payloads)
+            .await?;
+    }
     client
-        .upsert_points(collection_name, embeddings, payloads)
+        .create_keyword_index(collection_name, SOURCE_FIELD)
         .await?;
     Ok(())
 }
@@ -47,23 +449,517 @@ pub async fn ingest_texts(
     text_embedding_client: &TextEmbeddingInference,
     client: &QdrantClient,
 ) -> Result<()> {
-    let embeddings = text_embedding_client.embed(texts.clone()).await.unwrap();
+    upsert_texts(
+        collection_name,
+        Map::new(),
+        texts,
+        text_embedding_client,
+        client,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Like [`ingest_texts`] for the chunks of one file or URL, stored in the indexed `source`
+/// payload field so they can be removed with [`QdrantClient::delete_by_source`].
+pub async fn ingest_texts_from_source(
+    collection_name: &str,
+    source_uri: &str,
+    texts: Vec<String>,
+    text_embedding_client: &TextEmbeddingInference,
+    client: &QdrantClient,
+) -> Result<()> {
+    let mut fields = Map::new();
+    fields.insert(SOURCE_FIELD.to_string(), json!(source_uri));
+    upsert_texts(
+        collection_name,
+        fields,
+        texts,
+        text_embedding_client,
+        client,
+    )
+    .await?;
+    client
+        .create_keyword_index(collection_name, SOURCE_FIELD)
+        .await?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReingestReport {
+    pub chunks_ingested: usize,
+    pub stale_removed: u64,
+}
 
-    let payloads: Vec<Payload> = texts
+/// Replaces every point ingested from `source_uri` with the new chunks of the document.
+/// The new chunks are written under a fresh `source_revision` before the older revisions
+/// are deleted, so queries never see the document missing and stale chunks stop
+/// answering as soon as this returns. A failed run leaves the old chunks in place.
+pub async fn reingest_document(
+    collection_name: &str,
+    source_uri: &str,
+    chunks: Vec<String>,
+    text_embedding_client: &TextEmbeddingInference,
+    client: &QdrantClient,
+) -> Result<ReingestReport> {
+    let payloads = text_payloads(&Map::new(), &chunks, client);
+    let ids = chunks
+        .iter()
+        .map(|chunk| client.new_point_id(collection_name, Some(source_uri), chunk))
+        .collect::<Result<Vec<String>, _>>()?;
+    let embeddings = client
+        .embedding_batcher()
+        .embed(text_embedding_client, chunks)
+        .await
+        .map_err(|e| anyhow!("Text embedding failed: {e}"))?;
+    let points = ids
         .into_iter()
-        .map(|text| {
-            Payload::try_from(json!({
-                "text": text,
-                "timestamp": chrono::Utc::now().to_rfc3339()
-            }))
-            .unwrap()
-        })
+        .zip(embeddings)
+        .zip(payloads)
+        .map(|((id, embedding), payload)| VectorPoint::new(id, embedding, payload))
         .collect();
 
+    let report = replace_source(client, collection_name, source_uri, points).await?;
     client
-        .upsert_points(collection_name, embeddings, payloads)
+        .create_keyword_index(collection_name, SOURCE_FIELD)
         .await?;
-    Ok(())
+    Ok(report)
+}
+
+// Writes `points` as a new revision of `source_uri`, then deletes the older revisions
+async fn replace_source(
+    store: &impl VectorStore,
+    collection_name: &str,
+    source_uri: &str,
+    mut points: Vec<VectorPoint>,
+) -> Result<ReingestReport> {
+    let revision = Uuid::new_v4().to_string();
+    for point in &mut points {
+        point
+            .payload
+            .insert(SOURCE_FIELD.to_string(), json!(source_uri));
+        point
+            .payload
+            .insert(SOURCE_REVISION_FIELD.to_string(), json!(revision));
+    }
+    let chunks_ingested = points.len();
+    store.upsert(collection_name, points).await?;
+
+    let stale = FilterBuilder::new()
+        .eq(SOURCE_FIELD, source_uri)
+        .not_eq(SOURCE_REVISION_FIELD, revision)
+        .build();
+    let stale_removed = store.delete_matching(collection_name, &stale).await?;
+    Ok(ReingestReport {
+        chunks_ingested,
+        stale_removed,
+    })
+}
+
+/// Summarizes a long document with [`summarize_long`] and stores the summary as one
+/// point with `kind: "summary"` and the document's `source`, so it is recalled for broad
+/// questions and removed with the document's chunks. Returns the summary.
+#[allow(clippy::too_many_arguments)]
+pub async fn ingest_summary(
+    collection_name: &str,
+    source_uri: &str,
+    text: &str,
+    chunk_summarizer: &impl Summarizer,
+    reducer: &impl Summarizer,
+    options: SummarizeOptions,
+    text_embedding_client: &TextEmbeddingInference,
+    client: &QdrantClient,
+) -> Result<String> {
+    let summary = summarize_long(text, chunk_summarizer, reducer, options).await?;
+    let mut fields = Map::new();
+    fields.insert(SOURCE_FIELD.to_string(), json!(source_uri));
+    fields.insert("kind".to_string(), json!("summary"));
+    upsert_texts(
+        collection_name,
+        fields,
+        vec![summary.clone()],
+        text_embedding_client,
+        client,
+    )
+    .await?;
+    client
+        .create_keyword_index(collection_name, SOURCE_FIELD)
+        .await?;
+    Ok(summary)
+}
+
+/// Ingests documents as configured by `options`, chunking them first if
+/// `options.chunk_tokens` is set. With `options.dry_run` nothing is embedded or written
+/// and the report is a projection.
+pub async fn ingest_documents(
+    collection_name: &str,
+    documents: Vec<String>,
+    options: &TextIngestOptions,
+    text_embedding_client: &TextEmbeddingInference,
+    client: &QdrantClient,
+) -> Result<IngestReport> {
+    let report = IngestReport {
+        dry_run: options.dry_run,
+        documents: documents.len(),
+        ..Default::default()
+    };
+    let chunks = document_chunks(documents, options);
+    let (report, usage) = usage::measured(ingest_chunks(
+        collection_name,
+        report,
+        chunks,
+        None,
+        options,
+        text_embedding_client,
+        client,
+    ))
+    .await;
+    Ok(IngestReport { usage, ..report? })
+}
+
+/// Like [`ingest_documents`], translating every chunk with `translator` before
+/// embedding: the translation is embedded and stored in the [`TRANSLATION_FIELD`]
+/// payload field while the original stays the point's text, for display. Lets a
+/// monolingual embedding model retrieve documents in any language. A dry run translates
+/// nothing and projects the embedding of the originals.
+pub async fn ingest_translated_documents(
+    collection_name: &str,
+    documents: Vec<String>,
+    options: &TextIngestOptions,
+    translator: &impl Translator,
+    text_embedding_client: &TextEmbeddingInference,
+    client: &QdrantClient,
+) -> Result<IngestReport> {
+    let report = IngestReport {
+        dry_run: options.dry_run,
+        documents: documents.len(),
+        ..Default::default()
+    };
+    let chunks = document_chunks(documents, options);
+    let (report, usage) = usage::measured(async move {
+        let translations = if options.dry_run {
+            None
+        } else {
+            Some(translate_all(&chunks, translator, TRANSLATION_CONCURRENCY).await?)
+        };
+        ingest_chunks(
+            collection_name,
+            report,
+            chunks,
+            translations,
+            options,
+            text_embedding_client,
+            client,
+        )
+        .await
+    })
+    .await;
+    Ok(IngestReport { usage, ..report? })
+}
+
+// Cleans, strips and chunks documents as configured by `options`
+fn document_chunks(documents: Vec<String>, options: &TextIngestOptions) -> Vec<String> {
+    let documents = match &options.cleaning {
+        Some(cleaner) => cleaner.clean_all(&documents),
+        None => documents,
+    };
+    let documents = match &options.injection {
+        Some(scanner) if scanner.action == InjectionAction::Strip => documents
+            .iter()
+            .map(|document| scanner.strip(document))
+            .collect(),
+        _ => documents,
+    };
+    match options.chunk_tokens {
+        Some(max_tokens) => documents
+            .iter()
+            .flat_map(|document| chunk_text(document, max_tokens))
+            .collect(),
+        None => documents,
+    }
+}
+
+// Fills in the counts of `report` and returns the embedder inputs and payloads of the
+// chunks; a dry run is projected from the same plan as a real run
+fn plan_chunks(
+    report: &mut IngestReport,
+    chunks: &[String],
+    translations: Option<Vec<String>>,
+    options: &TextIngestOptions,
+    client: &QdrantClient,
+) -> (Vec<String>, Vec<Map<String, JsonValue>>) {
+    let mut fields = Map::new();
+    if let Some(source_uri) = &options.source_uri {
+        fields.insert(SOURCE_FIELD.to_string(), json!(source_uri));
+    }
+
+    let embedded = translations.clone().unwrap_or_else(|| chunks.to_vec());
+    report.points = chunks.len();
+    report.embedding_tokens = embedded.iter().map(|text| estimate_tokens(text)).sum();
+    report.embedding_calls = chunks
+        .len()
+        .div_ceil(client.embedding_batcher().batch_size());
+    let mut payloads = text_payloads(&fields, chunks, client);
+    if let Some(translations) = &translations {
+        for (payload, translation) in payloads.iter_mut().zip(translations) {
+            payload.insert(TRANSLATION_FIELD.to_string(), json!(translation));
+        }
+    }
+    if let Some(extractor) = &options.keywords {
+        for (payload, chunk) in payloads.iter_mut().zip(chunks) {
+            payload.insert(KEYWORDS_FIELD.to_string(), json!(extractor.extract(chunk)));
+        }
+    }
+    if let Some(scanner) = tagging(options) {
+        for ((payload, chunk), text) in payloads.iter_mut().zip(chunks).zip(&embedded) {
+            // An injection may only be recognizable in translation
+            scanner.tag(payload, chunk);
+            scanner.tag(payload, text);
+        }
+    }
+    report.projected_bytes = payload_bytes(&payloads);
+    (embedded, payloads)
+}
+
+fn tagging(options: &TextIngestOptions) -> Option<&InjectionScanner> {
+    options
+        .injection
+        .as_ref()
+        .filter(|scanner| scanner.action == InjectionAction::Tag)
+}
+
+// Writes chunks, embedding their translations instead if there are any
+async fn ingest_chunks(
+    collection_name: &str,
+    mut report: IngestReport,
+    chunks: Vec<String>,
+    translations: Option<Vec<String>>,
+    options: &TextIngestOptions,
+    text_embedding_client: &TextEmbeddingInference,
+    client: &QdrantClient,
+) -> Result<IngestReport> {
+    let (embedded, payloads) = plan_chunks(&mut report, &chunks, translations, options, client);
+
+    report.vector_dimensions = if options.dry_run {
+        // A dry run only reads Qdrant to project the vectors' size
+        collection_dimensions(collection_name, client)
+            .await
+            .unwrap_or(None)
+    } else {
+        let ids = chunks
+            .iter()
+            .map(|chunk| client.new_point_id(collection_name, options.source_uri.as_deref(), chunk))
+            .collect::<Result<Vec<String>, _>>()?;
+        match &options.retry_queue {
+            None => Some(
+                write_texts(
+                    collection_name,
+                    ids,
+                    embedded,
+                    payloads,
+                    text_embedding_client,
+                    options.sparse.as_ref(),
+                    client,
+                )
+                .await?,
+            ),
+            Some(path) => {
+                let (dimensions, failed) = write_text_batches(
+                    collection_name,
+                    ids,
+                    embedded,
+                    payloads,
+                    text_embedding_client,
+                    options.sparse.as_ref(),
+                    client,
+                )
+                .await;
+                RetryQueue::new(path).push(&failed)?;
+                report.failed = failed.len();
+                report.retry_queue = Some(path.clone());
+                match dimensions {
+                    Some(dimensions) => Some(dimensions),
+                    // Qdrant may be what failed, the queued run is reported regardless
+                    None => collection_dimensions(collection_name, client)
+                        .await
+                        .unwrap_or(None),
+                }
+            }
+        }
+    };
+    report.projected_bytes += report.points as u64 * report.vector_dimensions.unwrap_or(0) * 4;
+
+    if !options.dry_run && options.source_uri.is_some() {
+        client
+            .create_keyword_index(collection_name, SOURCE_FIELD)
+            .await?;
+    }
+    if !options.dry_run && options.keywords.is_some() {
+        client
+            .create_keyword_index(collection_name, KEYWORDS_FIELD)
+            .await?;
+    }
+    if !options.dry_run && tagging(options).is_some() {
+        client
+            .create_keyword_index(collection_name, INJECTION_RISK_FIELD)
+            .await?;
+    }
+    Ok(report)
+}
+
+fn text_payloads(
+    fields: &Map<String, JsonValue>,
+    texts: &[String],
+    client: &QdrantClient,
+) -> Vec<Map<String, JsonValue>> {
+    texts
+        .iter()
+        .map(|text| {
+            let mut payload = fields.clone();
+            client.payload_fields().insert_text(&mut payload, text);
+            payload
+        })
+        .collect()
+}
+
+// Writes texts batch by batch, returning the embedding dimensions, if a batch succeeded,
+// and the items of the failed batches
+async fn write_text_batches(
+    collection_name: &str,
+    ids: Vec<String>,
+    texts: Vec<String>,
+    payloads: Vec<Map<String, JsonValue>>,
+    text_embedding_client: &TextEmbeddingInference,
+    sparse: Option<&SparseEncoding>,
+    client: &QdrantClient,
+) -> (Option<u64>, Vec<FailedItem>) {
+    let batch_size = client.embedding_batcher().batch_size();
+    let mut dimensions = None;
+    let mut failed = Vec::new();
+    for ((ids, texts), payloads) in ids
+        .chunks(batch_size)
+        .zip(texts.chunks(batch_size))
+        .zip(payloads.chunks(batch_size))
+    {
+        let written = write_texts(
+            collection_name,
+            ids.to_vec(),
+            texts.to_vec(),
+            payloads.to_vec(),
+            text_embedding_client,
+            sparse,
+            client,
+        )
+        .await;
+        match written {
+            Ok(batch_dimensions) => dimensions = Some(batch_dimensions),
+            Err(e) => {
+                log::warn!("Queueing {} chunks for retry: {e}", texts.len());
+                failed.extend(
+                    ids.iter()
+                        .zip(texts)
+                        .zip(payloads)
+                        .map(|((id, text), payload)| FailedItem {
+                            collection: collection_name.to_string(),
+                            id: id.clone(),
+                            content: RetryContent::Text { text: text.clone() },
+                            embedding_url: text_embedding_client.base_url.clone(),
+                            sparse: sparse.cloned(),
+                            payload: payload.clone(),
+                            error: e.to_string(),
+                            attempts: 1,
+                        }),
+                );
+            }
+        }
+    }
+    (dimensions, failed)
+}
+
+async fn upsert_texts(
+    collection_name: &str,
+    fields: Map<String, JsonValue>,
+    texts: Vec<String>,
+    text_embedding_client: &TextEmbeddingInference,
+    client: &QdrantClient,
+) -> Result<u64> {
+    let payloads = text_payloads(&fields, &texts, client);
+    let source = fields.get(SOURCE_FIELD).and_then(JsonValue::as_str);
+    let ids = texts
+        .iter()
+        .map(|text| client.new_point_id(collection_name, source, text))
+        .collect::<Result<Vec<String>, _>>()?;
+    write_texts(
+        collection_name,
+        ids,
+        texts,
+        payloads,
+        text_embedding_client,
+        None,
+        client,
+    )
+    .await
+}
+
+async fn sparse_embeddings(
+    texts: &[String],
+    encoding: &SparseEncoding,
+) -> Result<Vec<SparseEmbedding>> {
+    match encoding {
+        SparseEncoding::Bm25 => {
+            let encoder = Bm25Encoder::new();
+            Ok(texts.iter().map(|text| encoder.encode(text)).collect())
+        }
+        SparseEncoding::Splade { url } => TextEmbeddingInference::new(Some(url))
+            .embed_sparse(texts.to_vec())
+            .await
+            .map_err(|e| anyhow!("Sparse embedding failed: {e}")),
+    }
+}
+
+// Embeds and writes texts under the given ids, returning the embedding dimensions
+async fn write_texts(
+    collection_name: &str,
+    ids: Vec<String>,
+    texts: Vec<String>,
+    payloads: Vec<Map<String, JsonValue>>,
+    text_embedding_client: &TextEmbeddingInference,
+    sparse: Option<&SparseEncoding>,
+    client: &QdrantClient,
+) -> Result<u64> {
+    let payloads: Vec<Payload> = payloads.into_iter().map(Payload::from).collect();
+    let sparse_embeddings = match sparse {
+        Some(encoding) => Some(sparse_embeddings(&texts, encoding).await?),
+        None => None,
+    };
+    let embeddings = client
+        .embedding_batcher()
+        .embed(text_embedding_client, texts)
+        .await
+        .map_err(|e| anyhow!("Text embedding failed: {e}"))?;
+
+    // Waits for the write, so callers can rely on the chunks being searchable
+    let dimensions = embeddings
+        .first()
+        .map_or(0, |embedding| embedding.len() as u64);
+    match sparse_embeddings {
+        Some(sparse_embeddings) => {
+            client
+                .upsert_points_hybrid(
+                    collection_name,
+                    ids,
+                    embeddings.into_iter().zip(sparse_embeddings),
+                    payloads,
+                )
+                .await?
+        }
+        None => {
+            client
+                .upsert_points_with_ids(collection_name, ids, embeddings, payloads)
+                .await?
+        }
+    };
+    Ok(dimensions)
 }
 
 pub async fn ingest_multivector(
@@ -74,40 +970,96 @@ pub async fn ingest_multivector(
     text_embedding_url: &str,
     client: &QdrantClient,
 ) -> Result<()> {
-    let text_embedding_client = TextEmbeddingInference::new(Some(text_embedding_url));
-    let image_embedding_client = TextEmbeddingInference::new(Some(image_embedding_url));
-
-    let mut images = Vec::new();
-
-    let text_embeddings = text_embedding_client.embed(texts.clone()).await.unwrap();
+    let metadata = vec![Map::new(); image_paths.len()];
+    ingest_multivector_with_metadata(
+        collection_name,
+        image_paths,
+        texts,
+        metadata,
+        image_embedding_url,
+        text_embedding_url,
+        client,
+    )
+    .await
+}
 
-    for image_path in image_paths {
-        let base64_image = load_image_as_base64(&image_path).await?;
-        images.push(base64_image.clone());
-    }
+/// Like [`ingest_multivector`], with extra payload fields for each image/text pair.
+pub async fn ingest_multivector_with_metadata(
+    collection_name: &str,
+    image_paths: Vec<String>,
+    texts: Vec<String>,
+    metadata: Vec<Map<String, JsonValue>>,
+    image_embedding_url: &str,
+    text_embedding_url: &str,
+    client: &QdrantClient,
+) -> Result<()> {
+    let text_embedding_client = TextEmbeddingInference::new(Some(text_embedding_url));
+    let image_embedding_client = TextEmbeddingInference::new(Some(image_embedding_url))
+        .with_input_kind(InputKind::ImageBase64);
 
-    let image_embedding = image_embedding_client.embed(images.clone()).await.unwrap();
+    let fields = client.payload_fields();
+    let mut pairs = image_paths.iter().zip(&texts).zip(metadata);
+    // Pairs are embedded and written in batches, within TEI's cap on the inputs of a
+    // request and gRPC's on the size of a message
+    loop {
+        let batch: Vec<_> = pairs
+            .by_ref()
+            .take(client.embedding_batcher().batch_size())
+            .collect();
+        if batch.is_empty() {
+            break;
+        }
 
-    for (idx, (image_path, text)) in images.iter().zip(texts.iter()).enumerate() {
-        let payload = Payload::try_from(json!({
-            "image_path": image_path,
-            "text": text,
-            "timestamp": chrono::Utc::now().to_rfc3339()
-        }))?;
+        // Text and image embeddings don't depend on each other, so both servers work at once
+        let text_embeddings = async {
+            let texts = batch.iter().map(|((_, text), _)| (*text).clone()).collect();
+            client
+                .embedding_batcher()
+                .embed(&text_embedding_client, texts)
+                .await
+                .map_err(|e| anyhow!("Text embedding failed: {e}"))
+        };
+        let image_embeddings = async {
+            let images = try_join_all(
+                batch
+                    .iter()
+                    .map(|((image_path, _), _)| load_image_as_base64(image_path)),
+            )
+            .await?;
+            client
+                .embedding_batcher()
+                .embed(&image_embedding_client, images)
+                .await
+                .map_err(|e| anyhow!("Image embedding failed: {e}"))
+        };
+        let (text_embeddings, image_embeddings) = try_join!(text_embeddings, image_embeddings)?;
 
-        client
-            .upsert_points_multivector(
-                collection_name,
-                image_embedding[idx].clone(),
-                text_embeddings[idx].clone(),
-                payload,
+        let points: Vec<(Vec<f32>, Vec<f32>, Payload)> = batch
+            .into_iter()
+            .zip(image_embeddings)
+            .zip(text_embeddings)
+            .map(
+                |((((image_path, text), metadata), image_embedding), text_embedding)| {
+                    let mut payload = metadata;
+                    payload.insert(fields.image_path.clone(), json!(image_path));
+                    payload.insert(SOURCE_FIELD.to_string(), json!(image_path));
+                    fields.insert_text(&mut payload, text);
+                    (image_embedding, text_embedding, Payload::from(payload))
+                },
             )
+            .collect();
+        client
+            .upsert_points_multivector_batch(collection_name, points)
             .await?;
     }
+    client
+        .create_keyword_index(collection_name, SOURCE_FIELD)
+        .await?;
 
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn ingest_image_to_text(
     collection_name: &str,
     model: &str,
@@ -142,4 +1094,355 @@ pub async fn ingest_image_to_text(
     Ok(())
 }
 
-//TODO: Ingest with Image2Text
+/// Like [`ingest_image_to_text`], and also stores concise alt-text for every image in the
+/// `alt_text` payload field, next to its `image_width` and `image_height`, so UIs can render
+/// accessible images straight from recall results.
+#[allow(clippy::too_many_arguments)]
+pub async fn ingest_image_to_text_with_alt_text(
+    collection_name: &str,
+    model: &str,
+    temperature: Option<f32>,
+    image_paths: Vec<String>,
+    prompt: String,
+    llm_client: impl LlmClientChat,
+    image_embedding_url: &str,
+    text_embedding_url: &str,
+    client: &QdrantClient,
+) -> Result<()> {
+    let mut texts = Vec::new();
+    let mut metadata = Vec::new();
+
+    for image_path in &image_paths {
+        let caption = llm_client
+            .send_message(model, &prompt, Some(image_path), temperature)
+            .await?;
+        let alt_text = generate_alt_text(&llm_client, model, image_path).await?;
+        let (width, height) = image::image_dimensions(image_path)?;
+
+        let mut fields = Map::new();
+        fields.insert("alt_text".to_string(), json!(alt_text));
+        fields.insert("image_width".to_string(), json!(width));
+        fields.insert("image_height".to_string(), json!(height));
+        texts.push(caption);
+        metadata.push(fields);
+    }
+
+    ingest_multivector_with_metadata(
+        collection_name,
+        image_paths,
+        texts,
+        metadata,
+        image_embedding_url,
+        text_embedding_url,
+        client,
+    )
+    .await
+}
+
+/// Like [`ingest_image_to_text`], and also extracts the product shown in every image
+/// (brand, product name, price, currency) into typed payload fields, so a catalog can be
+/// filtered with [`price_range_filter`](crate::vectorstore::product_extraction::price_range_filter).
+/// A float index is created on `price`.
+#[allow(clippy::too_many_arguments)]
+pub async fn ingest_image_to_text_with_products(
+    collection_name: &str,
+    model: &str,
+    temperature: Option<f32>,
+    image_paths: Vec<String>,
+    prompt: String,
+    llm_client: impl LlmClientChat,
+    image_embedding_url: &str,
+    text_embedding_url: &str,
+    client: &QdrantClient,
+) -> Result<()> {
+    let mut texts = Vec::new();
+    let mut metadata = Vec::new();
+
+    for image_path in &image_paths {
+        let caption = llm_client
+            .send_message(model, &prompt, Some(image_path), temperature)
+            .await?;
+        let fields = match extract_product(&llm_client, model, image_path).await? {
+            Some(product) => product.to_payload_fields(),
+            None => {
+                log::warn!("Could not extract product from {image_path}");
+                Map::new()
+            }
+        };
+        texts.push(caption);
+        metadata.push(fields);
+    }
+
+    ingest_multivector_with_metadata(
+        collection_name,
+        image_paths,
+        texts,
+        metadata,
+        image_embedding_url,
+        text_embedding_url,
+        client,
+    )
+    .await?;
+
+    client.create_float_index(collection_name, "price").await?;
+
+    Ok(())
+}
+
+/// Like [`ingest_image_to_text`], but every caption is checked with `validation` and
+/// regenerated up to `validation.max_retries` times. Captions that still fail are ingested
+/// with their issues in the `caption_quality` payload field, so they can be filtered out or
+/// reviewed instead of silently polluting recall.
+#[allow(clippy::too_many_arguments)]
+pub async fn ingest_image_to_text_with_validation(
+    collection_name: &str,
+    model: &str,
+    temperature: Option<f32>,
+    image_paths: Vec<String>,
+    prompt: String,
+    llm_client: impl LlmClientChat,
+    validation: &CaptionValidation,
+    image_embedding_url: &str,
+    text_embedding_url: &str,
+    client: &QdrantClient,
+) -> Result<()> {
+    let mut texts = Vec::new();
+    let mut metadata = Vec::new();
+
+    for image_path in &image_paths {
+        let mut attempts = 0;
+        let (caption, mut quality) = loop {
+            attempts += 1;
+            let caption = llm_client
+                .send_message(model, &prompt, Some(image_path), temperature)
+                .await?;
+            let quality = validation
+                .validate(&caption, &llm_client, model, image_path)
+                .await?;
+            if quality.passed || attempts > validation.max_retries {
+                break (caption, quality);
+            }
+        };
+        quality.attempts = attempts;
+
+        let mut fields = Map::new();
+        fields.insert("caption_quality".to_string(), json!(quality));
+        texts.push(caption);
+        metadata.push(fields);
+    }
+
+    ingest_multivector_with_metadata(
+        collection_name,
+        image_paths,
+        texts,
+        metadata,
+        image_embedding_url,
+        text_embedding_url,
+        client,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embeddings::adaptive_batch::AdaptiveBatcher;
+    use crate::testing::faults::{FaultInjector, FaultyVectorStore};
+    use crate::vectorstore::in_memory::InMemoryVectorStore;
+
+    #[tokio::test]
+    async fn test_dry_run_projects_real_run() {
+        let mut server = mockito::Server::new_async().await;
+        let embed = server
+            .mock("POST", "/embed")
+            .with_body("[[1.0, 0.0], [0.0, 1.0]]")
+            .expect(1)
+            .create_async()
+            .await;
+        let path = std::env::temp_dir().join(format!("retry-{}.jsonl", Uuid::new_v4()));
+        let options = TextIngestOptions {
+            retry_queue: Some(path.clone()),
+            ..Default::default()
+        };
+        let documents = vec!["Suede boots".to_string(), "Leather sandals".to_string()];
+        // Nothing listens there, so the real run queues its points
+        let client = QdrantClient::new("http://127.0.0.1:1");
+        let tei = TextEmbeddingInference::new(Some(&server.url()));
+
+        let dry_options = TextIngestOptions {
+            dry_run: true,
+            ..options.clone()
+        };
+        // Also checks that a dry run does not need Qdrant
+        let dry = ingest_documents("docs", documents.clone(), &dry_options, &tei, &client)
+            .await
+            .unwrap();
+        assert!(dry.dry_run);
+        assert_eq!(dry.vector_dimensions, None);
+        assert!(!embed.matched_async().await);
+        assert_eq!(client.write_generation("docs"), 0);
+
+        let real = ingest_documents("docs", documents, &options, &tei, &client)
+            .await
+            .unwrap();
+        embed.assert_async().await;
+        assert!(client.write_generation("docs") > 0);
+        assert_eq!(real.failed, 2);
+        assert_eq!(
+            (
+                dry.documents,
+                dry.points,
+                dry.embedding_tokens,
+                dry.embedding_calls
+            ),
+            (
+                real.documents,
+                real.points,
+                real.embedding_tokens,
+                real.embedding_calls
+            )
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_multivector_ingestion_is_batched() {
+        let mut text_server = mockito::Server::new_async().await;
+        let text_embed = text_server
+            .mock("POST", "/embed")
+            .match_body(mockito::Matcher::PartialJson(
+                json!({"inputs": ["Red boots", "Blue sandals"]}),
+            ))
+            .with_body("[[1.0, 0.0], [0.0, 1.0]]")
+            .expect(1)
+            .create_async()
+            .await;
+        let mut image_server = mockito::Server::new_async().await;
+        let image_embed = image_server
+            .mock("POST", "/embed")
+            .with_body("[[1.0, 0.0], [0.0, 1.0]]")
+            .expect(1)
+            .create_async()
+            .await;
+        let dir = std::env::temp_dir().join(format!("multivector-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let image_paths: Vec<String> = (0..3)
+            .map(|idx| {
+                let path = dir.join(format!("{idx}.jpg"));
+                std::fs::write(&path, [idx as u8]).unwrap();
+                path.to_string_lossy().into_owned()
+            })
+            .collect();
+        let texts = ["Red boots", "Blue sandals", "Green loafers"]
+            .map(String::from)
+            .to_vec();
+        // Nothing listens there, so the first write fails and ends the run
+        let client = QdrantClient::new("http://127.0.0.1:1").with_embedding_batcher(
+            AdaptiveBatcher::new(2, 2, std::time::Duration::from_secs(2)),
+        );
+
+        let result = ingest_multivector(
+            "products",
+            image_paths,
+            texts,
+            &image_server.url(),
+            &text_server.url(),
+            &client,
+        )
+        .await;
+        assert!(result.is_err());
+        text_embed.assert_async().await;
+        image_embed.assert_async().await;
+        assert_eq!(client.write_generation("products"), 1);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    fn chunk(id: &str, text: &str) -> VectorPoint {
+        let mut payload = Map::new();
+        payload.insert("text".to_string(), json!(text));
+        VectorPoint::new(id, vec![1.0, 0.0], payload)
+    }
+
+    async fn ingested_store() -> InMemoryVectorStore {
+        let store = InMemoryVectorStore::new();
+        replace_source(
+            &store,
+            "docs",
+            "manual.md",
+            vec![chunk("a", "Boots"), chunk("b", "Sandals")],
+        )
+        .await
+        .unwrap();
+        replace_source(&store, "docs", "faq.md", vec![chunk("f", "Returns")])
+            .await
+            .unwrap();
+        store
+    }
+
+    #[tokio::test]
+    async fn test_reingest_replaces_changed_document() {
+        let store = ingested_store().await;
+        let report = replace_source(
+            &store,
+            "docs",
+            "manual.md",
+            vec![chunk("b", "Sandals"), chunk("c", "Loafers")],
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            report,
+            ReingestReport {
+                chunks_ingested: 2,
+                stale_removed: 1,
+            }
+        );
+        assert!(store.get("docs", "a").is_none());
+        assert_eq!(
+            store.get("docs", "b").unwrap().payload[SOURCE_REVISION_FIELD],
+            store.get("docs", "c").unwrap().payload[SOURCE_REVISION_FIELD]
+        );
+        assert!(store.get("docs", "f").is_some());
+        assert_eq!(store.len("docs"), 3);
+    }
+
+    #[tokio::test]
+    async fn test_failed_reingest_keeps_old_chunks() {
+        let store = FaultyVectorStore::wrap(
+            ingested_store().await,
+            FaultInjector::new(7).with_error_rate(1.0),
+        );
+        assert!(
+            replace_source(&store, "docs", "manual.md", vec![chunk("c", "Loafers")])
+                .await
+                .is_err()
+        );
+        let store = store.store();
+        assert!(store.get("docs", "a").is_some());
+        assert!(store.get("docs", "b").is_some());
+        assert!(store.get("docs", "c").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_reingest_is_cleaned_up_by_next_run() {
+        // With this seed the write goes through and the delete of the old revision fails
+        let store = FaultyVectorStore::wrap(
+            ingested_store().await,
+            FaultInjector::new(1).with_error_rate(0.5),
+        );
+        assert!(
+            replace_source(&store, "docs", "manual.md", vec![chunk("c", "Loafers")])
+                .await
+                .is_err()
+        );
+        let store = store.store();
+        assert_eq!(store.len("docs"), 4);
+
+        let report = replace_source(store, "docs", "manual.md", vec![chunk("c", "Loafers")])
+            .await
+            .unwrap();
+        assert_eq!(report.stale_removed, 2);
+        assert!(store.get("docs", "a").is_none());
+        assert_eq!(store.len("docs"), 2);
+    }
+}
@@ -0,0 +1,388 @@
+use crate::vectorstore::payload_schema::PayloadFields;
+use crate::vectorstore::qdrant_client::point_id_to_string;
+use qdrant_client::qdrant::{PointStruct, ScoredPoint, Value};
+use qdrant_client::{Payload, QdrantError};
+use serde_json::{json, Map, Value as JsonValue};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+// LangChain (`langchain_qdrant.QdrantVectorStore`) default keys
+const LANGCHAIN_CONTENT_KEY: &str = "page_content";
+const LANGCHAIN_METADATA_KEY: &str = "metadata";
+
+// LlamaIndex (`llama_index.vector_stores.qdrant.QdrantVectorStore`) keys
+const LLAMAINDEX_NODE_CONTENT_KEY: &str = "_node_content";
+const LLAMAINDEX_NODE_TYPE_KEY: &str = "_node_type";
+const LLAMAINDEX_DOC_ID_KEYS: [&str; 3] = ["doc_id", "document_id", "ref_doc_id"];
+
+/// Payload layout of a collection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadConvention {
+    /// Flat payload with the text under the [`PayloadFields`] text field, `text` by
+    /// default, as written by this crate.
+    LiquidMemory,
+    /// `{"page_content": "...", "metadata": {...}}`
+    LangChain,
+    /// Serialized node under `_node_content` with metadata flattened next to it.
+    LlamaIndex,
+}
+
+/// Framework-agnostic view of a stored point.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Document {
+    pub node_id: Option<String>,
+    pub text: String,
+    pub metadata: Map<String, JsonValue>,
+}
+
+impl Document {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            node_id: None,
+            text: text.into(),
+            metadata: Map::new(),
+        }
+    }
+
+    pub fn with_metadata(mut self, metadata: Map<String, JsonValue>) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    pub fn with_node_id(mut self, node_id: impl Into<String>) -> Self {
+        self.node_id = Some(node_id.into());
+        self
+    }
+}
+
+/// Converts a document to a payload; `fields` names the text field of a LiquidMemory
+/// payload.
+pub fn to_payload(
+    document: &Document,
+    convention: PayloadConvention,
+    fields: &PayloadFields,
+) -> Result<Payload, QdrantError> {
+    let payload = match convention {
+        PayloadConvention::LiquidMemory => {
+            let mut map = document.metadata.clone();
+            map.insert(fields.text.clone(), json!(document.text));
+            JsonValue::Object(map)
+        }
+        PayloadConvention::LangChain => json!({
+            LANGCHAIN_CONTENT_KEY: document.text,
+            LANGCHAIN_METADATA_KEY: document.metadata,
+        }),
+        PayloadConvention::LlamaIndex => {
+            let node = json!({
+                "id_": document.node_id,
+                "text": document.text,
+                "metadata": document.metadata,
+                "class_name": "TextNode",
+            });
+            let mut map = document.metadata.clone();
+            map.insert(
+                LLAMAINDEX_NODE_CONTENT_KEY.to_string(),
+                json!(node.to_string()),
+            );
+            map.insert(LLAMAINDEX_NODE_TYPE_KEY.to_string(), json!("TextNode"));
+            // LlamaIndex writes the literal "None" for nodes without a source document
+            for key in LLAMAINDEX_DOC_ID_KEYS {
+                map.entry(key.to_string()).or_insert(json!("None"));
+            }
+            JsonValue::Object(map)
+        }
+    };
+    Payload::try_from(payload)
+}
+
+/// Reads a document from a raw point payload. Returns `None` if the payload does not
+/// follow the given convention.
+pub fn from_payload(
+    payload: HashMap<String, Value>,
+    convention: PayloadConvention,
+    fields: &PayloadFields,
+) -> Option<Document> {
+    let mut map: Map<String, JsonValue> = payload
+        .into_iter()
+        .map(|(key, value)| (key, value.into_json()))
+        .collect();
+
+    match convention {
+        PayloadConvention::LiquidMemory => {
+            let text = take_string(&mut map, &fields.text)?;
+            Some(Document::new(text).with_metadata(map))
+        }
+        PayloadConvention::LangChain => {
+            let text = take_string(&mut map, LANGCHAIN_CONTENT_KEY)?;
+            let metadata = match map.remove(LANGCHAIN_METADATA_KEY) {
+                Some(JsonValue::Object(metadata)) => metadata,
+                _ => Map::new(),
+            };
+            Some(Document::new(text).with_metadata(metadata))
+        }
+        PayloadConvention::LlamaIndex => {
+            let node_content = take_string(&mut map, LLAMAINDEX_NODE_CONTENT_KEY)?;
+            let node: JsonValue = serde_json::from_str(&node_content).ok()?;
+            map.remove(LLAMAINDEX_NODE_TYPE_KEY);
+            for key in LLAMAINDEX_DOC_ID_KEYS {
+                if map.get(key) == Some(&json!("None")) {
+                    map.remove(key);
+                }
+            }
+            Some(Document {
+                node_id: node["id_"].as_str().map(str::to_string),
+                text: node["text"].as_str().unwrap_or_default().to_string(),
+                metadata: map,
+            })
+        }
+    }
+}
+
+/// Reads a document from a query result, falling back to the point id when the payload
+/// carries no node id.
+pub fn from_scored_point(
+    point: ScoredPoint,
+    convention: PayloadConvention,
+    fields: &PayloadFields,
+) -> Option<Document> {
+    let point_id = point.id.as_ref().map(point_id_to_string);
+    let mut document = from_payload(point.payload, convention, fields)?;
+    if document.node_id.is_none() {
+        document.node_id = point_id;
+    }
+    Some(document)
+}
+
+/// Builds points keyed by the document node id (or a fresh UUID), so that Python stacks
+/// resolve the same ids.
+pub fn to_point_structs(
+    embeddings: Vec<Vec<f32>>,
+    documents: &[Document],
+    convention: PayloadConvention,
+    fields: &PayloadFields,
+) -> Result<Vec<PointStruct>, QdrantError> {
+    embeddings
+        .into_iter()
+        .zip(documents)
+        .map(|(embedding, document)| {
+            let id = document
+                .node_id
+                .clone()
+                .unwrap_or_else(|| Uuid::new_v4().to_string());
+            Ok(PointStruct::new(
+                id,
+                embedding,
+                to_payload(document, convention, fields)?,
+            ))
+        })
+        .collect()
+}
+
+fn take_string(map: &mut Map<String, JsonValue>, key: &str) -> Option<String> {
+    match map.remove(key)? {
+        JsonValue::String(s) => Some(s),
+        _ => None,
+    }
+}
+
+/// Converts a document to its stored form and back, through the same Qdrant values a
+/// point payload is written as, with the default [`PayloadFields`]. Returns `None` if the
+/// payload can't be read back under `convention`; see [`is_representable`] for the documents that come back unchanged.
+pub fn round_trip(
+    document: &Document,
+    convention: PayloadConvention,
+) -> Result<Option<Document>, QdrantError> {
+    let fields = PayloadFields::default();
+    let payload: HashMap<String, Value> = to_payload(document, convention, &fields)?.into();
+    Ok(from_payload(payload, convention, &fields))
+}
+
+/// Whether `document` survives [`round_trip`] under `convention` unchanged. Qdrant stores
+/// integers as `i64`, so larger ones come back as floats, and each convention reserves
+/// some fields: LiquidMemory keeps the text under `text` and, like LangChain, has no
+/// node id; LlamaIndex owns `_node_content`, `_node_type` and `"None"` document ids.
+pub fn is_representable(document: &Document, convention: PayloadConvention) -> bool {
+    let fits = document.metadata.values().all(fits_payload_value);
+    let reserved_free = match convention {
+        PayloadConvention::LiquidMemory => {
+            document.node_id.is_none() && !document.metadata.contains_key("text")
+        }
+        PayloadConvention::LangChain => document.node_id.is_none(),
+        PayloadConvention::LlamaIndex => {
+            !document.metadata.contains_key(LLAMAINDEX_NODE_CONTENT_KEY)
+                && !document.metadata.contains_key(LLAMAINDEX_NODE_TYPE_KEY)
+                && LLAMAINDEX_DOC_ID_KEYS
+                    .iter()
+                    .all(|key| document.metadata.get(*key) != Some(&json!("None")))
+        }
+    };
+    fits && reserved_free
+}
+
+fn fits_payload_value(value: &JsonValue) -> bool {
+    match value {
+        JsonValue::Number(number) => number.is_i64() || number.is_f64(),
+        JsonValue::Array(values) => values.iter().all(fits_payload_value),
+        JsonValue::Object(map) => map.values().all(fits_payload_value),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::invariants::check_payload_round_trip;
+    use proptest::prelude::*;
+
+    fn document() -> Document {
+        let metadata = json!({"source": "faq.md", "page": 2});
+        Document::new("What is Deep Learning?")
+            .with_node_id("5c1ab5a4-0d1c-4a14-9d8e-0c6d1b0f7c11")
+            .with_metadata(metadata.as_object().unwrap().clone())
+    }
+
+    #[test]
+    fn test_langchain_payload_layout() {
+        let payload: JsonValue = to_payload(
+            &document(),
+            PayloadConvention::LangChain,
+            &PayloadFields::default(),
+        )
+        .unwrap()
+        .into();
+        assert_eq!(payload["page_content"], "What is Deep Learning?");
+        assert_eq!(payload["metadata"]["source"], "faq.md");
+    }
+
+    #[test]
+    fn test_llamaindex_payload_layout() {
+        let payload: JsonValue = to_payload(
+            &document(),
+            PayloadConvention::LlamaIndex,
+            &PayloadFields::default(),
+        )
+        .unwrap()
+        .into();
+        assert_eq!(payload["_node_type"], "TextNode");
+        assert_eq!(payload["source"], "faq.md");
+        assert_eq!(payload["ref_doc_id"], "None");
+        let node: JsonValue =
+            serde_json::from_str(payload["_node_content"].as_str().unwrap()).unwrap();
+        assert_eq!(node["text"], "What is Deep Learning?");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let document = document();
+        let langchain = round_trip(&document, PayloadConvention::LangChain)
+            .unwrap()
+            .unwrap();
+        assert_eq!(langchain.text, document.text);
+        assert_eq!(langchain.metadata, document.metadata);
+
+        let llamaindex = round_trip(&document, PayloadConvention::LlamaIndex)
+            .unwrap()
+            .unwrap();
+        assert_eq!(llamaindex, document);
+
+        let native = round_trip(&document, PayloadConvention::LiquidMemory)
+            .unwrap()
+            .unwrap();
+        assert_eq!(native.text, document.text);
+        assert_eq!(native.metadata, document.metadata);
+    }
+
+    #[test]
+    fn test_from_payload_wrong_convention() {
+        let payload: HashMap<String, Value> = to_payload(
+            &document(),
+            PayloadConvention::LangChain,
+            &PayloadFields::default(),
+        )
+        .unwrap()
+        .into();
+        assert!(from_payload(
+            payload,
+            PayloadConvention::LlamaIndex,
+            &PayloadFields::default()
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_liquid_memory_follows_payload_fields() {
+        let fields = PayloadFields::default().with_text("content");
+        let payload = to_payload(&document(), PayloadConvention::LiquidMemory, &fields).unwrap();
+        let payload: HashMap<String, Value> = payload.into();
+        assert!(payload.contains_key("content"));
+        assert!(!payload.contains_key("text"));
+
+        let read = from_payload(payload.clone(), PayloadConvention::LiquidMemory, &fields).unwrap();
+        assert_eq!(read.text, "What is Deep Learning?");
+        assert!(from_payload(
+            payload,
+            PayloadConvention::LiquidMemory,
+            &PayloadFields::default()
+        )
+        .is_none());
+    }
+
+    fn json_value() -> impl Strategy<Value = JsonValue> {
+        let leaf = prop_oneof![
+            Just(JsonValue::Null),
+            any::<bool>().prop_map(JsonValue::from),
+            any::<i64>().prop_map(JsonValue::from),
+            any::<u64>().prop_map(JsonValue::from),
+            any::<f64>().prop_map(JsonValue::from),
+            any::<String>().prop_map(JsonValue::from),
+        ];
+        leaf.prop_recursive(4, 32, 6, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..6).prop_map(JsonValue::from),
+                prop::collection::hash_map(any::<String>(), inner, 0..6)
+                    .prop_map(|map| JsonValue::Object(map.into_iter().collect())),
+            ]
+        })
+    }
+
+    fn any_document() -> impl Strategy<Value = Document> {
+        (
+            prop::option::of(any::<String>()),
+            any::<String>(),
+            prop::collection::hash_map("\\PC{0,12}", json_value(), 0..6),
+        )
+            .prop_map(|(node_id, text, metadata)| Document {
+                node_id,
+                text,
+                metadata: metadata.into_iter().collect(),
+            })
+    }
+
+    fn any_convention() -> impl Strategy<Value = PayloadConvention> {
+        prop_oneof![
+            Just(PayloadConvention::LiquidMemory),
+            Just(PayloadConvention::LangChain),
+            Just(PayloadConvention::LlamaIndex),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn prop_round_trip(document in any_document(), convention in any_convention()) {
+            check_payload_round_trip(&document, convention);
+        }
+    }
+
+    #[test]
+    fn test_is_representable() {
+        let native = Document::new("Boots")
+            .with_metadata(json!({"text": "shadowed"}).as_object().unwrap().clone());
+        assert!(!is_representable(&native, PayloadConvention::LiquidMemory));
+        assert!(is_representable(&native, PayloadConvention::LangChain));
+
+        let large = Document::new("Boots")
+            .with_metadata(json!({"sku": u64::MAX}).as_object().unwrap().clone());
+        assert!(!is_representable(&large, PayloadConvention::LangChain));
+        check_payload_round_trip(&large, PayloadConvention::LangChain);
+    }
+}
@@ -0,0 +1,618 @@
+use crate::similarity;
+use crate::vectorstore::filter::MetadataFilter;
+use crate::vectorstore::vector_store::{
+    named_vector_collection, SearchHit, VectorPoint, VectorStore, VectorStoreError,
+};
+use memmap2::Mmap;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as JsonValue};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+const META_FILE: &str = "meta.json";
+const VECTORS_FILE: &str = "vectors.bin";
+const PAYLOADS_FILE: &str = "payloads.jsonl";
+const INDEX_FILE: &str = "index.jsonl";
+
+#[derive(Serialize, Deserialize)]
+struct CollectionMeta {
+    dimension: usize,
+}
+
+// Append-only log of the payload index, replayed on open
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum IndexRecord {
+    Put {
+        id: String,
+        row: usize,
+        offset: usize,
+        len: usize,
+    },
+    Delete {
+        id: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    row: usize,
+    offset: usize,
+    len: usize,
+}
+
+fn map(file: &File) -> io::Result<Mmap> {
+    // SAFETY: the files are only ever appended to, by this store, and already written
+    // bytes are never changed. See the warning on `MmapVectorStore`.
+    unsafe { Mmap::map(file) }
+}
+
+fn append(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)
+}
+
+struct MmapCollection {
+    dimension: usize,
+    vectors: File,
+    payloads: File,
+    index: File,
+    vector_map: Mmap,
+    payload_map: Mmap,
+    entries: HashMap<String, Entry>,
+    // Point id of each vector row, `None` once the row was replaced or deleted
+    row_ids: Vec<Option<String>>,
+}
+
+impl MmapCollection {
+    fn create(dir: &Path, dimension: usize) -> Result<Self, VectorStoreError> {
+        fs::create_dir_all(dir)?;
+        fs::write(
+            dir.join(META_FILE),
+            serde_json::to_vec(&CollectionMeta { dimension })?,
+        )?;
+        Self::open(dir)
+    }
+
+    fn open(dir: &Path) -> Result<Self, VectorStoreError> {
+        let meta: CollectionMeta = serde_json::from_slice(&fs::read(dir.join(META_FILE))?)?;
+        if meta.dimension == 0 {
+            return Err(VectorStoreError::IoError(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("collection {} has dimension 0", dir.display()),
+            )));
+        }
+        let vectors = append(&dir.join(VECTORS_FILE))?;
+        let payloads = append(&dir.join(PAYLOADS_FILE))?;
+        let index = append(&dir.join(INDEX_FILE))?;
+
+        // Drop a partially written row so that new rows stay aligned. Records pointing
+        // past the end of the data files come from an interrupted write and are ignored.
+        let size = (meta.dimension * 4) as u64;
+        let rows = vectors.metadata()?.len() / size;
+        vectors.set_len(rows * size)?;
+        let rows = rows as usize;
+        let vector_map = map(&vectors)?;
+        let payload_map = map(&payloads)?;
+
+        let mut entries = HashMap::new();
+        let mut row_ids = vec![None; rows];
+        for line in BufReader::new(&index).lines() {
+            let Ok(record) = serde_json::from_str::<IndexRecord>(&line?) else {
+                break;
+            };
+            match record {
+                IndexRecord::Put {
+                    id,
+                    row,
+                    offset,
+                    len,
+                } if row < rows && offset + len <= payload_map.len() => {
+                    if let Some(previous) = entries.insert(id.clone(), Entry { row, offset, len }) {
+                        row_ids[previous.row] = None;
+                    }
+                    row_ids[row] = Some(id);
+                }
+                IndexRecord::Put { .. } => {}
+                IndexRecord::Delete { id } => {
+                    if let Some(previous) = entries.remove(&id) {
+                        row_ids[previous.row] = None;
+                    }
+                }
+            }
+        }
+
+        Ok(Self {
+            dimension: meta.dimension,
+            vectors,
+            payloads,
+            index,
+            vector_map,
+            payload_map,
+            entries,
+            row_ids,
+        })
+    }
+
+    fn remap(&mut self) -> io::Result<()> {
+        self.vector_map = map(&self.vectors)?;
+        self.payload_map = map(&self.payloads)?;
+        Ok(())
+    }
+
+    fn vector(&self, row: usize) -> Vec<f32> {
+        let size = self.dimension * 4;
+        self.vector_map[row * size..(row + 1) * size]
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect()
+    }
+
+    fn payload(&self, entry: Entry) -> Result<Map<String, JsonValue>, VectorStoreError> {
+        Ok(serde_json::from_slice(
+            &self.payload_map[entry.offset..entry.offset + entry.len],
+        )?)
+    }
+
+    fn write_records(&mut self, records: &[IndexRecord]) -> Result<(), VectorStoreError> {
+        let mut index = BufWriter::new(&self.index);
+        for record in records {
+            serde_json::to_writer(&mut index, record)?;
+            index.write_all(b"\n")?;
+        }
+        index.flush()?;
+        Ok(())
+    }
+
+    fn upsert(&mut self, points: Vec<VectorPoint>) -> Result<(), VectorStoreError> {
+        let mut row = self.row_ids.len();
+        let mut offset = self.payload_map.len();
+        let mut records = Vec::with_capacity(points.len());
+        {
+            let mut vectors = BufWriter::new(&self.vectors);
+            let mut payloads = BufWriter::new(&self.payloads);
+            for point in points {
+                for value in &point.vector {
+                    vectors.write_all(&value.to_le_bytes())?;
+                }
+                let payload = serde_json::to_vec(&point.payload)?;
+                payloads.write_all(&payload)?;
+                payloads.write_all(b"\n")?;
+                records.push(IndexRecord::Put {
+                    id: point.id,
+                    row,
+                    offset,
+                    len: payload.len(),
+                });
+                row += 1;
+                offset += payload.len() + 1;
+            }
+            vectors.flush()?;
+            payloads.flush()?;
+        }
+        // The data is written before the index, so a crash never leaves dangling records
+        self.write_records(&records)?;
+        self.remap()?;
+
+        for record in records {
+            if let IndexRecord::Put {
+                id,
+                row,
+                offset,
+                len,
+            } = record
+            {
+                self.row_ids.push(Some(id.clone()));
+                if let Some(previous) = self.entries.insert(id, Entry { row, offset, len }) {
+                    self.row_ids[previous.row] = None;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn delete(&mut self, ids: Vec<String>) -> Result<(), VectorStoreError> {
+        let records: Vec<IndexRecord> = ids
+            .into_iter()
+            .filter(|id| self.entries.contains_key(id))
+            .map(|id| IndexRecord::Delete { id })
+            .collect();
+        self.write_records(&records)?;
+        for record in records {
+            if let IndexRecord::Delete { id } = record {
+                if let Some(previous) = self.entries.remove(&id) {
+                    self.row_ids[previous.row] = None;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn matching(&self, filter: &MetadataFilter) -> Result<Vec<String>, VectorStoreError> {
+        let mut ids = Vec::new();
+        for (id, entry) in &self.entries {
+            if filter.matches(&self.payload(*entry)?) {
+                ids.push(id.clone());
+            }
+        }
+        Ok(ids)
+    }
+
+    fn query(
+        &self,
+        vector: &[f32],
+        limit: usize,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<SearchHit>, VectorStoreError> {
+        // Exact scan over the mapped rows; only the pages being read are held in memory
+        let size = self.dimension * 4;
+        let mut row_vector = vec![0.0; self.dimension];
+        let mut scores: Vec<(usize, f32)> = Vec::new();
+        for (row, bytes) in self.vector_map.chunks_exact(size).enumerate() {
+            if self.row_ids.get(row).is_none_or(|id| id.is_none()) {
+                continue;
+            }
+            for (value, bytes) in row_vector.iter_mut().zip(bytes.chunks_exact(4)) {
+                *value = f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            }
+            scores.push((row, similarity::cosine(vector, &row_vector)));
+        }
+        // Without a filter only the top rows are needed; with one, payloads are read best
+        // first until enough of them match
+        if filter.is_none() && scores.len() > limit && limit > 0 {
+            scores.select_nth_unstable_by(limit - 1, |a, b| b.1.total_cmp(&a.1));
+            scores.truncate(limit);
+        }
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let mut hits = Vec::with_capacity(limit);
+        for (row, score) in scores {
+            if hits.len() == limit {
+                break;
+            }
+            let Some(id) = self.row_ids[row].as_ref() else {
+                continue;
+            };
+            let payload = self.payload(self.entries[id])?;
+            if filter.is_none_or(|filter| filter.matches(&payload)) {
+                hits.push(SearchHit {
+                    id: id.clone(),
+                    score,
+                    payload,
+                });
+            }
+        }
+        Ok(hits)
+    }
+}
+
+/// [`VectorStore`] persisted in a local directory, for embedded use in desktop apps. Each
+/// collection is a subdirectory holding an append-only file of raw vectors and one of
+/// payloads, both memory-mapped, plus an index log of point offsets. Only the index is
+/// kept in RAM, so stores can be much larger than memory. Queries are exact scans over
+/// the mapped vectors and use cosine similarity.
+///
+/// Replaced and deleted points keep their space on disk. The directory must not be
+/// modified by anything else, including a second store, while it is open.
+pub struct MmapVectorStore {
+    dir: PathBuf,
+    collections: RwLock<HashMap<String, MmapCollection>>,
+}
+
+impl MmapVectorStore {
+    /// Opens the store in `dir`, creating the directory if needed and loading the index
+    /// of every existing collection.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, VectorStoreError> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        let mut collections = HashMap::new();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if !path.join(META_FILE).exists() {
+                continue;
+            }
+            if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+                collections.insert(name.to_string(), MmapCollection::open(&path)?);
+            }
+        }
+        Ok(Self {
+            dir,
+            collections: RwLock::new(collections),
+        })
+    }
+
+    /// Number of points in the collection, 0 if it does not exist.
+    pub fn len(&self, collection: &str) -> usize {
+        self.collections
+            .read()
+            .unwrap()
+            .get(collection)
+            .map_or(0, |collection| collection.entries.len())
+    }
+
+    pub fn get(&self, collection: &str, id: &str) -> Result<Option<VectorPoint>, VectorStoreError> {
+        let collections = self.collections.read().unwrap();
+        let Some(collection) = collections.get(collection) else {
+            return Ok(None);
+        };
+        let Some(&entry) = collection.entries.get(id) else {
+            return Ok(None);
+        };
+        Ok(Some(VectorPoint::new(
+            id,
+            collection.vector(entry.row),
+            collection.payload(entry)?,
+        )))
+    }
+
+    // Rewrites the given points with `update` applied to their payloads, or none of them
+    // if one is missing
+    async fn update_payloads(
+        &self,
+        collection: &str,
+        ids: &[String],
+        update: impl Fn(&mut Map<String, JsonValue>),
+    ) -> Result<(), VectorStoreError> {
+        if !self.collections.read().unwrap().contains_key(collection) {
+            return Err(VectorStoreError::CollectionNotFound(collection.to_string()));
+        }
+        let mut points = Vec::with_capacity(ids.len());
+        for id in ids {
+            let mut point = self
+                .get(collection, id)?
+                .ok_or_else(|| VectorStoreError::PointNotFound(id.clone()))?;
+            update(&mut point.payload);
+            points.push(point);
+        }
+        self.upsert(collection, points).await
+    }
+
+    fn collection_dir(&self, collection: &str) -> Result<PathBuf, VectorStoreError> {
+        // Collection names become directory names
+        if collection.is_empty() || collection.starts_with('.') || collection.contains(['/', '\\'])
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid collection name: {collection}"),
+            )
+            .into());
+        }
+        Ok(self.dir.join(collection))
+    }
+}
+
+impl VectorStore for MmapVectorStore {
+    async fn upsert(
+        &self,
+        collection: &str,
+        points: Vec<VectorPoint>,
+    ) -> Result<(), VectorStoreError> {
+        let Some(dimension) = points.first().map(|point| point.vector.len()) else {
+            return Ok(());
+        };
+        // A row of 0 bytes cannot be stored or found again
+        if let Some(point) = points.iter().find(|point| point.vector.is_empty()) {
+            return Err(VectorStoreError::EmptyVector(point.id.clone()));
+        }
+        let mut collections = self.collections.write().unwrap();
+        if !collections.contains_key(collection) {
+            let dir = self.collection_dir(collection)?;
+            collections.insert(
+                collection.to_string(),
+                MmapCollection::create(&dir, dimension)?,
+            );
+        }
+        let collection = collections.get_mut(collection).unwrap();
+
+        if let Some(point) = points
+            .iter()
+            .find(|point| point.vector.len() != collection.dimension)
+        {
+            return Err(VectorStoreError::DimensionMismatch {
+                expected: collection.dimension,
+                actual: point.vector.len(),
+            });
+        }
+        collection.upsert(points)
+    }
+
+    async fn query_filtered(
+        &self,
+        collection: &str,
+        vector: Vec<f32>,
+        limit: u64,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<SearchHit>, VectorStoreError> {
+        let collections = self.collections.read().unwrap();
+        let collection = collections
+            .get(collection)
+            .ok_or_else(|| VectorStoreError::CollectionNotFound(collection.to_string()))?;
+        if vector.len() != collection.dimension {
+            return Err(VectorStoreError::DimensionMismatch {
+                expected: collection.dimension,
+                actual: vector.len(),
+            });
+        }
+        collection.query(&vector, limit as usize, filter)
+    }
+
+    async fn delete(&self, collection: &str, ids: Vec<String>) -> Result<(), VectorStoreError> {
+        let mut collections = self.collections.write().unwrap();
+        collections
+            .get_mut(collection)
+            .ok_or_else(|| VectorStoreError::CollectionNotFound(collection.to_string()))?
+            .delete(ids)
+    }
+
+    async fn delete_matching(
+        &self,
+        collection: &str,
+        filter: &MetadataFilter,
+    ) -> Result<u64, VectorStoreError> {
+        let mut collections = self.collections.write().unwrap();
+        let collection = collections
+            .get_mut(collection)
+            .ok_or_else(|| VectorStoreError::CollectionNotFound(collection.to_string()))?;
+        let matching = collection.matching(filter)?;
+        let count = matching.len() as u64;
+        collection.delete(matching)?;
+        Ok(count)
+    }
+
+    async fn set_payload(
+        &self,
+        collection: &str,
+        ids: Vec<String>,
+        fields: Map<String, JsonValue>,
+    ) -> Result<(), VectorStoreError> {
+        self.update_payloads(collection, &ids, |payload| payload.extend(fields.clone()))
+            .await
+    }
+
+    async fn delete_payload(
+        &self,
+        collection: &str,
+        ids: Vec<String>,
+        keys: Vec<String>,
+    ) -> Result<(), VectorStoreError> {
+        self.update_payloads(collection, &ids, |payload| {
+            for key in &keys {
+                payload.remove(key);
+            }
+        })
+        .await
+    }
+
+    async fn scroll(
+        &self,
+        collection: &str,
+        offset: Option<String>,
+        limit: u64,
+    ) -> Result<(Vec<VectorPoint>, Option<String>), VectorStoreError> {
+        let collections = self.collections.read().unwrap();
+        let collection = collections
+            .get(collection)
+            .ok_or_else(|| VectorStoreError::CollectionNotFound(collection.to_string()))?;
+        let mut ids: Vec<&String> = collection
+            .entries
+            .keys()
+            .filter(|id| offset.as_ref().is_none_or(|offset| *id >= offset))
+            .collect();
+        ids.sort();
+        let next = ids.get(limit as usize).map(|id| id.to_string());
+        let points = ids
+            .into_iter()
+            .take(limit as usize)
+            .map(|id| {
+                let entry = collection.entries[id];
+                Ok(VectorPoint::new(
+                    id,
+                    collection.vector(entry.row),
+                    collection.payload(entry)?,
+                ))
+            })
+            .collect::<Result<_, VectorStoreError>>()?;
+        Ok((points, next))
+    }
+
+    async fn update_named_vector(
+        &self,
+        collection: &str,
+        point_id: &str,
+        vector_name: &str,
+        vector: Vec<f32>,
+    ) -> Result<(), VectorStoreError> {
+        let collection = named_vector_collection(collection, vector_name);
+        let point = self
+            .get(&collection, point_id)?
+            .ok_or_else(|| VectorStoreError::PointNotFound(point_id.to_string()))?;
+        self.upsert(
+            &collection,
+            vec![VectorPoint::new(point_id, vector, point.payload)],
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vectorstore::filter::FilterBuilder;
+    use serde_json::json;
+
+    fn point(id: &str, vector: Vec<f32>, text: &str) -> VectorPoint {
+        let mut payload = Map::new();
+        payload.insert("text".to_string(), json!(text));
+        VectorPoint::new(id, vector, payload)
+    }
+
+    #[tokio::test]
+    async fn test_mmap_store_persists() {
+        let dir = std::env::temp_dir().join(format!("liquid-memory-{}", uuid::Uuid::new_v4()));
+        {
+            let store = MmapVectorStore::open(&dir).unwrap();
+            store
+                .upsert(
+                    "memories",
+                    vec![
+                        point("a", vec![1.0, 0.0], "boots"),
+                        point("b", vec![0.0, 1.0], "sandals"),
+                        point("c", vec![-1.0, 0.0], "loafers"),
+                    ],
+                )
+                .await
+                .unwrap();
+            store
+                .upsert("memories", vec![point("a", vec![0.0, -1.0], "wellies")])
+                .await
+                .unwrap();
+            store
+                .delete("memories", vec!["c".to_string()])
+                .await
+                .unwrap();
+        }
+
+        let store = MmapVectorStore::open(&dir).unwrap();
+        assert_eq!(store.len("memories"), 2);
+        let hits = store.query("memories", vec![0.1, -0.9], 2).await.unwrap();
+        assert_eq!(hits[0].id, "a");
+        assert_eq!(hits[0].payload["text"], "wellies");
+        assert_eq!(hits[1].id, "b");
+        assert_eq!(
+            store.get("memories", "b").unwrap().unwrap().vector,
+            vec![0.0, 1.0]
+        );
+        assert!(store.get("memories", "c").unwrap().is_none());
+        assert!(matches!(
+            store
+                .upsert("../escape", vec![point("d", vec![1.0], "")])
+                .await,
+            Err(VectorStoreError::IoError(_))
+        ));
+
+        assert!(matches!(
+            store.upsert("empty", vec![point("e", vec![], "")]).await,
+            Err(VectorStoreError::EmptyVector(id)) if id == "e"
+        ));
+        assert!(!dir.join("empty").exists());
+        fs::create_dir_all(dir.join("zero")).unwrap();
+        fs::write(dir.join("zero").join(META_FILE), r#"{"dimension":0}"#).unwrap();
+        assert!(MmapCollection::open(&dir.join("zero")).is_err());
+
+        let sandals = FilterBuilder::new().eq("text", "sandals").build();
+        assert_eq!(
+            store.delete_matching("memories", &sandals).await.unwrap(),
+            1
+        );
+        assert!(store.get("memories", "b").unwrap().is_none());
+        assert_eq!(store.len("memories"), 1);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
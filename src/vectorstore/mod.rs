@@ -1,2 +1,21 @@
+pub mod alt_text;
+pub mod asset_resolver;
+pub mod caption_validation;
+pub mod consumer;
+pub mod diff;
+pub mod filter;
+pub mod hedging;
+pub mod hnsw;
+pub mod ids;
+pub mod in_memory;
 pub mod ingestion;
+pub mod interop;
+pub mod mmap_store;
+pub mod payload_schema;
+pub mod presets;
+pub mod product_extraction;
 pub mod qdrant_client;
+pub mod retry_queue;
+pub mod sync;
+pub mod vector_store;
+pub mod watch;
@@ -0,0 +1,262 @@
+use serde_json::{json, Map, Value as JsonValue};
+use std::collections::HashMap;
+use thiserror::Error;
+
+const SCHEMA_KEY: &str = "payload_schema";
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("payload field `{path}` {reason}")]
+pub struct SchemaViolation {
+    /// Dotted path of the offending field, `$` for the payload itself.
+    pub path: String,
+    pub reason: String,
+}
+
+impl SchemaViolation {
+    fn new(path: &str, reason: impl Into<String>) -> Self {
+        Self {
+            path: path.to_string(),
+            reason: reason.into(),
+        }
+    }
+}
+
+/// JSON schema the payloads written to a collection must satisfy, stored in the
+/// collection metadata. The `type`, `required`, `properties`, `items` and `enum` keywords
+/// are checked; other keywords are kept but ignored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PayloadSchema {
+    schema: JsonValue,
+}
+
+impl PayloadSchema {
+    pub fn new(schema: JsonValue) -> Self {
+        Self { schema }
+    }
+
+    /// Schema requiring the given top-level fields, e.g. `["text", "source"]`.
+    pub fn requiring(fields: &[&str]) -> Self {
+        Self::new(json!({"type": "object", "required": fields}))
+    }
+
+    pub fn as_json(&self) -> &JsonValue {
+        &self.schema
+    }
+
+    pub fn validate(&self, payload: &Map<String, JsonValue>) -> Result<(), SchemaViolation> {
+        validate_value(&self.schema, &JsonValue::Object(payload.clone()), "$")
+    }
+
+    /// Like [`validate`](Self::validate), for any JSON value.
+    pub fn validate_json(&self, value: &JsonValue) -> Result<(), SchemaViolation> {
+        validate_value(&self.schema, value, "$")
+    }
+
+    /// Collection metadata recording this schema.
+    pub fn to_metadata(&self) -> HashMap<String, JsonValue> {
+        HashMap::from([(SCHEMA_KEY.to_string(), self.schema.clone())])
+    }
+
+    pub fn from_metadata(metadata: &HashMap<String, JsonValue>) -> Option<Self> {
+        metadata.get(SCHEMA_KEY).cloned().map(Self::new)
+    }
+}
+
+/// Names of the payload fields the crate writes and reads, for collections that follow
+/// other conventions, e.g. `content` instead of `text`. Set on
+/// [`QdrantClient::with_payload_fields`](crate::vectorstore::qdrant_client::QdrantClient::with_payload_fields).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PayloadFields {
+    pub text: String,
+    pub image_path: String,
+    pub timestamp: String,
+}
+
+impl Default for PayloadFields {
+    fn default() -> Self {
+        Self {
+            text: "text".to_string(),
+            image_path: "image_path".to_string(),
+            timestamp: "timestamp".to_string(),
+        }
+    }
+}
+
+impl PayloadFields {
+    pub fn with_text(mut self, field: impl Into<String>) -> Self {
+        self.text = field.into();
+        self
+    }
+
+    pub fn with_image_path(mut self, field: impl Into<String>) -> Self {
+        self.image_path = field.into();
+        self
+    }
+
+    pub fn with_timestamp(mut self, field: impl Into<String>) -> Self {
+        self.timestamp = field.into();
+        self
+    }
+
+    /// Inserts `text` and the current time under the configured names.
+    pub fn insert_text(&self, payload: &mut Map<String, JsonValue>, text: &str) {
+        payload.insert(self.text.clone(), json!(text));
+        self.insert_timestamp(payload);
+    }
+
+    pub fn insert_timestamp(&self, payload: &mut Map<String, JsonValue>) {
+        payload.insert(
+            self.timestamp.clone(),
+            json!(chrono::Utc::now().to_rfc3339()),
+        );
+    }
+}
+
+fn type_matches(expected: &str, value: &JsonValue) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn child_path(path: &str, key: &str) -> String {
+    if path == "$" {
+        key.to_string()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+fn validate_value(
+    schema: &JsonValue,
+    value: &JsonValue,
+    path: &str,
+) -> Result<(), SchemaViolation> {
+    let types: Vec<&str> = match schema.get("type") {
+        Some(JsonValue::String(expected)) => vec![expected.as_str()],
+        Some(JsonValue::Array(expected)) => expected.iter().filter_map(JsonValue::as_str).collect(),
+        _ => Vec::new(),
+    };
+    if !types.is_empty() && !types.iter().any(|expected| type_matches(expected, value)) {
+        return Err(SchemaViolation::new(
+            path,
+            format!("should be of type {}", types.join(" or ")),
+        ));
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(JsonValue::as_array) {
+        if !allowed.contains(value) {
+            return Err(SchemaViolation::new(
+                path,
+                format!("should be one of {}", JsonValue::Array(allowed.clone())),
+            ));
+        }
+    }
+
+    if let JsonValue::Object(fields) = value {
+        for required in schema
+            .get("required")
+            .and_then(JsonValue::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(JsonValue::as_str)
+        {
+            if !fields.contains_key(required) {
+                return Err(SchemaViolation::new(
+                    &child_path(path, required),
+                    "is required",
+                ));
+            }
+        }
+        if let Some(properties) = schema.get("properties").and_then(JsonValue::as_object) {
+            for (key, property) in properties {
+                if let Some(field) = fields.get(key) {
+                    validate_value(property, field, &child_path(path, key))?;
+                }
+            }
+        }
+    }
+
+    if let (JsonValue::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+        for (idx, item) in items.iter().enumerate() {
+            validate_value(item_schema, item, &child_path(path, &idx.to_string()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload(value: JsonValue) -> Map<String, JsonValue> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn test_payload_fields() {
+        let fields = PayloadFields::default().with_text("content");
+        let mut payload = Map::new();
+        fields.insert_text(&mut payload, "Boots");
+        assert_eq!(payload["content"], "Boots");
+        assert!(payload.contains_key("timestamp"));
+        assert!(!payload.contains_key("text"));
+    }
+
+    #[test]
+    fn test_validate() {
+        let schema = PayloadSchema::new(json!({
+            "type": "object",
+            "required": ["text", "source"],
+            "properties": {
+                "text": {"type": "string"},
+                "kind": {"enum": ["chunk", "summary"]},
+                "tags": {"type": "array", "items": {"type": "string"}}
+            }
+        }));
+
+        let valid = payload(json!({"text": "Boots", "source": "catalog.pdf", "tags": ["winter"]}));
+        assert_eq!(schema.validate(&valid), Ok(()));
+
+        let missing = schema
+            .validate(&payload(json!({"text": "Boots"})))
+            .unwrap_err();
+        assert_eq!(missing.path, "source");
+        assert_eq!(missing.to_string(), "payload field `source` is required");
+
+        let wrong_type = schema
+            .validate(&payload(json!({"text": 3, "source": "a"})))
+            .unwrap_err();
+        assert_eq!(wrong_type.reason, "should be of type string");
+
+        let bad_item = schema
+            .validate(&payload(
+                json!({"text": "a", "source": "a", "tags": ["ok", 1]}),
+            ))
+            .unwrap_err();
+        assert_eq!(bad_item.path, "tags.1");
+
+        let bad_enum = schema
+            .validate(&payload(
+                json!({"text": "a", "source": "a", "kind": "note"}),
+            ))
+            .unwrap_err();
+        assert_eq!(bad_enum.path, "kind");
+    }
+
+    #[test]
+    fn test_metadata_round_trip() {
+        let schema = PayloadSchema::requiring(&["text"]);
+        assert_eq!(
+            PayloadSchema::from_metadata(&schema.to_metadata()),
+            Some(schema)
+        );
+        assert_eq!(PayloadSchema::from_metadata(&HashMap::new()), None);
+    }
+}
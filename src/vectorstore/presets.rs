@@ -0,0 +1,141 @@
+use crate::memory::stats::NAMESPACE_FIELD;
+use crate::vectorstore::payload_schema::{PayloadFields, PayloadSchema};
+use crate::vectorstore::qdrant_client::SOURCE_FIELD;
+use qdrant_client::qdrant::quantization_config::Quantization;
+use qdrant_client::qdrant::{
+    BinaryQuantizationBuilder, Distance, ScalarQuantizationBuilder, VectorParamsBuilder,
+};
+use serde_json::json;
+
+/// Collection settings for common workloads, so a new collection is well configured in
+/// one call to [`QdrantClient::create_preset_collection`](super::qdrant_client::QdrantClient::create_preset_collection)
+/// or [`MemoryStore::create`](crate::memory::memory_store::MemoryStore::create). Field
+/// names follow the client's [`PayloadFields`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollectionPreset {
+    /// Conversation turns and facts: small and hot, vectors in RAM, filtered by session,
+    /// kind and time.
+    ChatMemory,
+    /// Chunks of ingested documents: large, so full vectors stay on disk and searches go
+    /// through quantized copies; filtered by source.
+    DocumentRag,
+    /// Image embeddings, e.g. CLIP: high-dimensional and near-duplicate heavy, so binary
+    /// quantization keeps them cheap.
+    ImageCatalog,
+    /// Code chunks: identifiers make near neighbours matter, so vectors are not quantized.
+    CodeSearch,
+}
+
+impl CollectionPreset {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ChatMemory => "chat_memory",
+            Self::DocumentRag => "document_rag",
+            Self::ImageCatalog => "image_catalog",
+            Self::CodeSearch => "code_search",
+        }
+    }
+
+    pub fn vector_params(&self, vector_size: u64) -> VectorParamsBuilder {
+        let on_disk = matches!(self, Self::DocumentRag | Self::ImageCatalog);
+        VectorParamsBuilder::new(vector_size, Distance::Cosine).on_disk(on_disk)
+    }
+
+    pub fn quantization(&self) -> Option<Quantization> {
+        match self {
+            Self::ChatMemory | Self::DocumentRag => {
+                Some(ScalarQuantizationBuilder::default().into())
+            }
+            Self::ImageCatalog => Some(BinaryQuantizationBuilder::new(true).into()),
+            Self::CodeSearch => None,
+        }
+    }
+
+    /// Fields getting a keyword index.
+    pub fn keyword_fields(&self, fields: &PayloadFields) -> Vec<String> {
+        let image_path = fields.image_path.as_str();
+        let keyword_fields = match self {
+            Self::ChatMemory => vec!["kind", "session_id", NAMESPACE_FIELD],
+            Self::DocumentRag => vec![SOURCE_FIELD, NAMESPACE_FIELD],
+            Self::ImageCatalog => vec![SOURCE_FIELD, image_path],
+            Self::CodeSearch => vec![SOURCE_FIELD, "language", "symbol"],
+        };
+        keyword_fields.into_iter().map(str::to_string).collect()
+    }
+
+    /// Fields getting a datetime index.
+    pub fn datetime_fields(&self, fields: &PayloadFields) -> Vec<String> {
+        match self {
+            Self::ChatMemory | Self::DocumentRag => vec![fields.timestamp.clone()],
+            Self::ImageCatalog | Self::CodeSearch => Vec::new(),
+        }
+    }
+
+    /// Schema every payload written to the collection has to satisfy.
+    pub fn payload_schema(&self, fields: &PayloadFields) -> PayloadSchema {
+        let (required, properties) = match self {
+            Self::ChatMemory => (
+                vec![fields.text.as_str(), fields.timestamp.as_str()],
+                json!({
+                    &fields.text: {"type": "string"},
+                    &fields.timestamp: {"type": "string"},
+                    "session_id": {"type": "string"},
+                }),
+            ),
+            Self::DocumentRag => (
+                vec![fields.text.as_str(), SOURCE_FIELD],
+                json!({
+                    &fields.text: {"type": "string"},
+                    SOURCE_FIELD: {"type": "string"},
+                }),
+            ),
+            Self::ImageCatalog => (
+                vec![fields.image_path.as_str()],
+                json!({&fields.image_path: {"type": "string"}}),
+            ),
+            Self::CodeSearch => (
+                vec![fields.text.as_str(), SOURCE_FIELD],
+                json!({
+                    &fields.text: {"type": "string"},
+                    SOURCE_FIELD: {"type": "string"},
+                    "language": {"type": "string"},
+                }),
+            ),
+        };
+        PayloadSchema::new(json!({
+            "type": "object",
+            "required": required,
+            "properties": properties,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Map;
+
+    #[test]
+    fn test_presets_follow_payload_fields() {
+        let fields = PayloadFields::default()
+            .with_text("content")
+            .with_timestamp("created_at");
+        let preset = CollectionPreset::ChatMemory;
+        assert_eq!(preset.datetime_fields(&fields), vec!["created_at"]);
+
+        let schema = preset.payload_schema(&fields);
+        let mut payload = Map::new();
+        payload.insert("content".to_string(), json!("I wear size 42"));
+        assert!(schema.validate(&payload).is_err());
+        payload.insert("created_at".to_string(), json!("2026-10-14T09:00:00Z"));
+        assert!(schema.validate(&payload).is_ok());
+        payload.insert("session_id".to_string(), json!(7));
+        assert!(schema.validate(&payload).is_err());
+
+        assert!(CollectionPreset::CodeSearch.quantization().is_none());
+        assert_eq!(
+            CollectionPreset::ImageCatalog.keyword_fields(&fields),
+            vec!["source", "image_path"]
+        );
+    }
+}
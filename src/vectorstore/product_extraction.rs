@@ -0,0 +1,176 @@
+use crate::llm::llm_client::LlmClientChat;
+use qdrant_client::qdrant::{Condition, Filter, Range};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value as JsonValue};
+use std::path::Path;
+
+/// Product attributes read off a catalog image. Every field is optional since not every
+/// image shows a brand or price tag.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProductInfo {
+    pub brand: Option<String>,
+    pub product_name: Option<String>,
+    pub price: Option<f64>,
+    /// ISO 4217 code, e.g. "EUR".
+    pub currency: Option<String>,
+}
+
+/// JSON schema the model is asked to follow.
+pub fn product_schema() -> JsonValue {
+    json!({
+        "type": "object",
+        "properties": {
+            "brand": {"type": ["string", "null"]},
+            "product_name": {"type": ["string", "null"]},
+            "price": {"type": ["number", "null"]},
+            "currency": {"type": ["string", "null"], "description": "ISO 4217 code"}
+        },
+        "required": ["brand", "product_name", "price", "currency"],
+        "additionalProperties": false
+    })
+}
+
+fn extraction_prompt() -> String {
+    format!(
+        "Extract the product shown in this image. Answer with a single JSON object matching \
+        this JSON schema and nothing else, using null for anything not visible:\n{}",
+        product_schema()
+    )
+}
+
+fn non_empty_string(value: Option<&JsonValue>) -> Option<String> {
+    let value = value?.as_str()?.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+// Models regularly return prices as strings ("$129.99", "1.299,00")
+fn parse_price(value: Option<&JsonValue>) -> Option<f64> {
+    match value? {
+        JsonValue::Number(number) => number.as_f64(),
+        JsonValue::String(text) => {
+            let digits: String = text
+                .chars()
+                .filter(|c| c.is_ascii_digit() || *c == '.' || *c == ',')
+                .collect();
+            let normalized = match (digits.rfind('.'), digits.rfind(',')) {
+                // The right-most separator is the decimal one
+                (Some(dot), Some(comma)) if comma > dot => {
+                    digits.replace('.', "").replace(',', ".")
+                }
+                // A comma followed by exactly three digits groups thousands ("1,299"),
+                // otherwise it is a decimal comma ("12,99")
+                (None, Some(_)) if digits.split(',').skip(1).all(|group| group.len() == 3) => {
+                    digits.replace(',', "")
+                }
+                (None, Some(_)) => digits.replace(',', "."),
+                _ => digits.replace(',', ""),
+            };
+            normalized.parse().ok()
+        }
+        _ => None,
+    }
+}
+
+/// Parses a model response into a [`ProductInfo`], tolerating code fences and text around
+/// the JSON object.
+pub fn parse_product_info(response: &str) -> Option<ProductInfo> {
+    let start = response.find('{')?;
+    let end = response.rfind('}')?;
+    let object: Map<String, JsonValue> = serde_json::from_str(response.get(start..=end)?).ok()?;
+
+    Some(ProductInfo {
+        brand: non_empty_string(object.get("brand")),
+        product_name: non_empty_string(object.get("product_name")),
+        price: parse_price(object.get("price")).filter(|price| *price >= 0.0),
+        currency: non_empty_string(object.get("currency")).map(|c| c.to_uppercase()),
+    })
+}
+
+impl ProductInfo {
+    /// Typed payload fields; missing attributes are left out so filters on them do not match.
+    pub fn to_payload_fields(&self) -> Map<String, JsonValue> {
+        match serde_json::to_value(self) {
+            Ok(JsonValue::Object(fields)) => fields
+                .into_iter()
+                .filter(|(_, value)| !value.is_null())
+                .collect(),
+            _ => Map::new(),
+        }
+    }
+}
+
+/// Asks the model for the product attributes of an image. Returns `None` if the response
+/// is not valid JSON.
+pub async fn extract_product<C: LlmClientChat>(
+    llm_client: &C,
+    model: &str,
+    image_path: impl AsRef<Path>,
+) -> Result<Option<ProductInfo>, C::Error> {
+    let response = llm_client
+        .send_message(model, extraction_prompt(), Some(image_path), Some(0.0))
+        .await?;
+    Ok(parse_product_info(&response))
+}
+
+/// Filter on the `price` payload field, with inclusive bounds.
+pub fn price_range_filter(min: Option<f64>, max: Option<f64>) -> Filter {
+    Filter::must([Condition::range(
+        "price",
+        Range {
+            gte: min,
+            lte: max,
+            ..Default::default()
+        },
+    )])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_product_info() {
+        let response = "```json\n{\"brand\": \"Acme\", \"product_name\": \"Suede Ankle Boot\", \
+            \"price\": \"$1,299.50\", \"currency\": \"usd\"}\n```";
+        assert_eq!(
+            parse_product_info(response),
+            Some(ProductInfo {
+                brand: Some("Acme".to_string()),
+                product_name: Some("Suede Ankle Boot".to_string()),
+                price: Some(1299.5),
+                currency: Some("USD".to_string()),
+            })
+        );
+
+        let info = parse_product_info(
+            r#"{"brand": null, "product_name": "Sandal", "price": "89,90", "currency": ""}"#,
+        )
+        .unwrap();
+        assert_eq!(info.price, Some(89.9));
+        assert_eq!(info.currency, None);
+        assert!(parse_product_info("no product visible").is_none());
+    }
+
+    #[test]
+    fn test_parse_price() {
+        let price = |text: &str| parse_price(Some(&JsonValue::from(text)));
+        assert_eq!(price("$1,299"), Some(1299.0));
+        assert_eq!(price("1,299,000 KRW"), Some(1_299_000.0));
+        assert_eq!(price("12,99 €"), Some(12.99));
+        assert_eq!(price("1.299,00"), Some(1299.0));
+        assert_eq!(price("$129.99"), Some(129.99));
+        assert_eq!(parse_price(Some(&JsonValue::from(42))), Some(42.0));
+    }
+
+    #[test]
+    fn test_to_payload_fields() {
+        let info = ProductInfo {
+            product_name: Some("Coat".to_string()),
+            price: Some(120.0),
+            ..Default::default()
+        };
+        let fields = info.to_payload_fields();
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields["price"], json!(120.0));
+    }
+}
@@ -1,13 +1,52 @@
+use crate::embeddings::adaptive_batch::AdaptiveBatcher;
+use crate::embeddings::sparse::SparseEmbedding;
+use crate::metrics::ClientMetrics;
+use crate::request_id::{current_request_id, REQUEST_ID_HEADER};
+use crate::vectorstore::filter::MetadataFilter;
+use crate::vectorstore::hedging::RequestHedging;
+use crate::vectorstore::ids::IdStrategy;
+use crate::vectorstore::interop::{self, Document, PayloadConvention};
+use crate::vectorstore::payload_schema::{PayloadFields, PayloadSchema, SchemaViolation};
+use crate::vectorstore::presets::CollectionPreset;
+use crate::vectorstore::vector_store::{
+    MultiVectorPoint, SearchHit, VectorPoint, VectorStore, VectorStoreError,
+};
+use qdrant_client::qdrant::point_id::PointIdOptions;
+use qdrant_client::qdrant::value::Kind;
+use qdrant_client::qdrant::vector_output;
+use qdrant_client::qdrant::vectors_config;
+use qdrant_client::qdrant::vectors_output;
 use qdrant_client::qdrant::{
-    CreateCollectionBuilder, Distance, Filter, ListCollectionsResponse, PointStruct,
-    PointsOperationResponse, QueryPointsBuilder, QueryResponse, ScalarQuantizationBuilder,
+    Condition, CountPointsBuilder, CreateCollectionBuilder, CreateFieldIndexCollectionBuilder,
+    DeletePayloadPointsBuilder, DeletePointsBuilder, Distance, FieldType, Filter,
+    ListCollectionsResponse, Modifier, NamedVectors, PointId, PointStruct, PointVectors,
+    PointsIdsList, PointsOperationResponse, PointsUpdateOperation, QueryPoints, QueryPointsBuilder,
+    QueryResponse, RetrievedPoint, ScalarQuantizationBuilder, ScoredPoint, ScrollPointsBuilder,
     SearchBatchPointsBuilder, SearchBatchResponse, SearchParamsBuilder, SearchPointsBuilder,
-    SearchResponse, UpsertPointsBuilder, VectorParamsBuilder, Vectors, VectorsConfigBuilder,
+    SearchResponse, SetPayloadPointsBuilder, SparseVectorParamsBuilder, SparseVectorsConfigBuilder,
+    UpdateBatchPointsBuilder, UpdateCollectionBuilder, UpdatePointVectorsBuilder,
+    UpsertPointsBuilder, Value, Vector, VectorParamsBuilder, VectorsConfigBuilder,
 };
 use qdrant_client::{Payload, Qdrant, QdrantError};
+use serde_json::{Map, Value as JsonValue};
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
 use uuid::Uuid;
 
+const SCROLL_PAGE_SIZE: u32 = 256;
+
+/// Payload field holding the file path or URL a point was ingested from.
+pub const SOURCE_FIELD: &str = "source";
+
+/// Dense vector of a hybrid collection, see [`QdrantClient::create_hybrid_collection`].
+pub const DENSE_VECTOR: &str = "dense";
+
+/// Sparse vector of a hybrid collection.
+pub const SPARSE_VECTOR: &str = "sparse";
+
 pub fn texts_to_payload(texts: Vec<String>, field_name: &str) -> Result<Vec<Payload>, QdrantError> {
     texts
         .iter()
@@ -15,22 +54,268 @@ pub fn texts_to_payload(texts: Vec<String>, field_name: &str) -> Result<Vec<Payl
         .collect::<Result<Vec<Payload>, _>>()
 }
 
-fn to_point_struct(point: Vec<f32>, payload: Payload) -> PointStruct {
-    PointStruct::new(Uuid::new_v4().to_string(), point, payload)
+// Numeric ids are sent as numbers, anything else as a UUID
+fn parse_point_id(id: &str) -> PointId {
+    match id.parse::<u64>() {
+        Ok(num) => PointId::from(num),
+        Err(_) => PointId::from(id.to_string()),
+    }
+}
+
+pub fn point_id_to_string(id: &PointId) -> String {
+    match &id.point_id_options {
+        Some(PointIdOptions::Num(num)) => num.to_string(),
+        Some(PointIdOptions::Uuid(uuid)) => uuid.clone(),
+        None => String::new(),
+    }
+}
+
+/// Deterministic point id for an external key (message id, URL, ...), so re-ingesting the
+/// same item overwrites its point.
+pub fn point_id_from_key(key: &str) -> String {
+    Uuid::new_v5(&Uuid::NAMESPACE_OID, key.as_bytes()).to_string()
+}
+
+fn to_point_struct(id: String, point: Vec<f32>, payload: Payload) -> PointStruct {
+    PointStruct::new(id, point, payload)
+}
+
+fn caller_provided_ids_error(collection_name: &str) -> QdrantError {
+    QdrantError::ConversionError(format!(
+        "collection {collection_name} requires caller-provided point ids"
+    ))
+}
+
+fn schema_violation_error(collection_name: &str, violation: SchemaViolation) -> QdrantError {
+    QdrantError::ConversionError(format!(
+        "payload rejected by the schema of collection {collection_name}: {violation}"
+    ))
 }
 
 pub struct QdrantClient {
     client: Qdrant,
+    // Serve queries and scrolls round-robin when set, writes always go to `client`
+    read_replicas: Vec<Qdrant>,
+    next_replica: AtomicUsize,
+    hedging: Option<RequestHedging>,
+    metrics: ClientMetrics,
+    // Schemas payloads are validated against before writes, by collection
+    payload_schemas: Mutex<HashMap<String, PayloadSchema>>,
+    payload_fields: PayloadFields,
+    id_strategy: IdStrategy,
+    // Overrides of `id_strategy`, by collection
+    id_strategies: Mutex<HashMap<String, IdStrategy>>,
+    // Shared by the ingestion helpers, so the batch size learnt by one write carries over
+    embedding_batcher: AdaptiveBatcher,
+    // Writes sent through this client, by collection
+    write_generations: Mutex<HashMap<String, u64>>,
 }
 
 impl QdrantClient {
     pub fn new(url: &str) -> Self {
         let client = Qdrant::from_url(url).build().unwrap();
-        Self { client }
+        Self {
+            client,
+            read_replicas: Vec::new(),
+            next_replica: AtomicUsize::new(0),
+            hedging: None,
+            metrics: ClientMetrics::new("qdrant"),
+            payload_schemas: Mutex::new(HashMap::new()),
+            payload_fields: PayloadFields::default(),
+            id_strategy: IdStrategy::default(),
+            id_strategies: Mutex::new(HashMap::new()),
+            embedding_batcher: AdaptiveBatcher::default(),
+            write_generations: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // The client to send a request with, tagged with the current request id
+    fn qdrant(&self) -> Cow<'_, Qdrant> {
+        match current_request_id() {
+            Some(request_id) => Cow::Owned(self.client.with_header(REQUEST_ID_HEADER, request_id)),
+            None => Cow::Borrowed(&self.client),
+        }
+    }
+
+    // The client to send a query with: the next read replica, or the primary if there is none
+    fn qdrant_read(&self) -> Cow<'_, Qdrant> {
+        if self.read_replicas.is_empty() {
+            return self.qdrant();
+        }
+        let idx = self.next_replica.fetch_add(1, Ordering::Relaxed) % self.read_replicas.len();
+        let replica = &self.read_replicas[idx];
+        match current_request_id() {
+            Some(request_id) => Cow::Owned(replica.with_header(REQUEST_ID_HEADER, request_id)),
+            None => Cow::Borrowed(replica),
+        }
+    }
+
+    /// Sends queries, searches and scrolls to these replicas in turn instead of the primary
+    /// `url` given to [`QdrantClient::new`], which keeps serving writes and collection
+    /// management. Replicas may lag behind the primary, so a point is not necessarily
+    /// found right after its upsert. Fails if a URL is invalid.
+    pub fn with_read_replicas(mut self, urls: &[&str]) -> Result<Self, QdrantError> {
+        self.read_replicas = urls
+            .iter()
+            .map(|url| Qdrant::from_url(url).build())
+            .collect::<Result<_, _>>()?;
+        Ok(self)
+    }
+
+    /// Hedges vector queries: a query still pending after a percentile of recent query
+    /// latencies is sent again, to the next read replica if there are any.
+    pub fn with_hedging(mut self, hedging: RequestHedging) -> Self {
+        self.hedging = Some(hedging);
+        self
+    }
+
+    pub fn hedging(&self) -> Option<&RequestHedging> {
+        self.hedging.as_ref()
+    }
+
+    // Sends a vector query to a read replica, hedged if hedging is set
+    async fn query_read(
+        &self,
+        query: impl Into<QueryPoints>,
+    ) -> Result<QueryResponse, QdrantError> {
+        let query = query.into();
+        match &self.hedging {
+            // Boxed, the two in-flight requests would make every query future twice as large
+            Some(hedging) => {
+                Box::pin(hedging.run(|| async { self.qdrant_read().query(query.clone()).await }))
+                    .await
+            }
+            None => self.qdrant_read().query(query).await,
+        }
+    }
+
+    /// Replaces the default metrics, e.g. to log slow operations or cap concurrent requests.
+    pub fn with_metrics(mut self, metrics: ClientMetrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Field names used by ingestion and multivector helpers writing through this client.
+    pub fn with_payload_fields(mut self, fields: PayloadFields) -> Self {
+        self.payload_fields = fields;
+        self
+    }
+
+    pub fn payload_fields(&self) -> &PayloadFields {
+        &self.payload_fields
+    }
+
+    /// How ids are generated for points written without one, in collections without
+    /// their own strategy.
+    pub fn with_id_strategy(mut self, strategy: IdStrategy) -> Self {
+        self.id_strategy = strategy;
+        self
+    }
+
+    /// Uses `strategy` for points written to `collection_name` without an id.
+    pub fn set_id_strategy(&self, collection_name: &str, strategy: IdStrategy) {
+        self.id_strategies
+            .lock()
+            .unwrap()
+            .insert(collection_name.to_string(), strategy);
+    }
+
+    pub fn id_strategy(&self, collection_name: &str) -> IdStrategy {
+        self.id_strategies
+            .lock()
+            .unwrap()
+            .get(collection_name)
+            .copied()
+            .unwrap_or(self.id_strategy)
+    }
+
+    /// Id for a new point of `collection_name` with the given content, e.g. its text or
+    /// image path, ingested from `source`. Fails for collections with
+    /// [`IdStrategy::CallerProvided`].
+    pub fn new_point_id(
+        &self,
+        collection_name: &str,
+        source: Option<&str>,
+        content: &str,
+    ) -> Result<String, QdrantError> {
+        self.id_strategy(collection_name)
+            .generate(source, content)
+            .ok_or_else(|| caller_provided_ids_error(collection_name))
+    }
+
+    // Id for a point written without one. Only content-hashed ids look at the payload:
+    // its source with its text or image path, or the whole payload if it has neither
+    fn payload_point_id(
+        &self,
+        collection_name: &str,
+        payload: Payload,
+    ) -> Result<(String, Payload), QdrantError> {
+        let strategy = self.id_strategy(collection_name);
+        let (id, payload) = if strategy == IdStrategy::ContentHash {
+            let payload = HashMap::<String, Value>::from(payload);
+            let string_field = |field: &str| match payload.get(field)?.kind.as_ref()? {
+                Kind::StringValue(value) => Some(value.as_str()),
+                _ => None,
+            };
+            let id = match [&self.payload_fields.text, &self.payload_fields.image_path]
+                .into_iter()
+                .find_map(|field| string_field(field))
+            {
+                Some(content) => strategy.generate(string_field(SOURCE_FIELD), content),
+                None => {
+                    let whole = Map::from(Payload::from(payload.clone()));
+                    strategy.generate(None, &JsonValue::Object(whole).to_string())
+                }
+            };
+            (id, payload.into())
+        } else {
+            (strategy.generate(None, ""), payload)
+        };
+        let id = id.ok_or_else(|| caller_provided_ids_error(collection_name))?;
+        Ok((id, payload))
+    }
+
+    /// Batcher the ingestion helpers embed texts with before writing through this client.
+    pub fn with_embedding_batcher(mut self, batcher: AdaptiveBatcher) -> Self {
+        self.embedding_batcher = batcher;
+        self
+    }
+
+    pub fn embedding_batcher(&self) -> &AdaptiveBatcher {
+        &self.embedding_batcher
+    }
+
+    /// Number of writes sent to `collection_name` through this client, e.g. to key caches
+    /// of query results that must not outlive a write.
+    pub fn write_generation(&self, collection_name: &str) -> u64 {
+        self.write_generations
+            .lock()
+            .unwrap()
+            .get(collection_name)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    // Counts the write once it finished, also if it failed since it may still have been
+    // applied, so results read before it end up under the previous generation
+    async fn write<T>(&self, collection_name: &str, write: impl Future<Output = T>) -> T {
+        let result = write.await;
+        *self
+            .write_generations
+            .lock()
+            .unwrap()
+            .entry(collection_name.to_string())
+            .or_default() += 1;
+        result
+    }
+
+    /// Request counters and latencies of point operations (upserts, queries, scrolls).
+    pub fn metrics(&self) -> &ClientMetrics {
+        &self.metrics
     }
 
     pub async fn get_collections(&self) -> Result<ListCollectionsResponse, QdrantError> {
-        let collections = self.client.list_collections().await?;
+        let collections = self.qdrant().list_collections().await?;
         Ok(collections)
     }
 
@@ -39,7 +324,7 @@ impl QdrantClient {
         collection_name: impl Into<String>,
         vector_params: VectorParamsBuilder,
     ) -> Result<(), QdrantError> {
-        self.client
+        self.qdrant()
             .create_collection(
                 CreateCollectionBuilder::new(collection_name)
                     .vectors_config(vector_params)
@@ -49,6 +334,199 @@ impl QdrantClient {
         Ok(())
     }
 
+    /// Like [`create_collection`](Self::create_collection), storing arbitrary JSON metadata
+    /// with the collection.
+    pub async fn create_collection_with_metadata(
+        &self,
+        collection_name: impl Into<String>,
+        vector_params: VectorParamsBuilder,
+        metadata: HashMap<String, JsonValue>,
+    ) -> Result<(), QdrantError> {
+        self.qdrant()
+            .create_collection(
+                CreateCollectionBuilder::new(collection_name)
+                    .vectors_config(vector_params)
+                    .quantization_config(ScalarQuantizationBuilder::default())
+                    .metadata(metadata),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Creates a collection configured by `preset`: vector storage, quantization, payload
+    /// indexes and a registered payload schema (see
+    /// [`register_payload_schema`](Self::register_payload_schema)), with `metadata` stored
+    /// next to the schema.
+    pub async fn create_preset_collection(
+        &self,
+        collection_name: &str,
+        vector_size: u64,
+        preset: CollectionPreset,
+        metadata: HashMap<String, JsonValue>,
+    ) -> Result<(), QdrantError> {
+        let fields = self.payload_fields();
+        let schema = preset.payload_schema(fields);
+        let mut metadata = metadata;
+        metadata.extend(schema.to_metadata());
+        metadata.insert("preset".to_string(), JsonValue::from(preset.name()));
+        let mut collection = CreateCollectionBuilder::new(collection_name)
+            .vectors_config(preset.vector_params(vector_size))
+            .metadata(metadata);
+        if let Some(quantization) = preset.quantization() {
+            collection = collection.quantization_config(quantization);
+        }
+        self.qdrant().create_collection(collection).await?;
+
+        for field in preset.keyword_fields(fields) {
+            self.create_keyword_index(collection_name, field).await?;
+        }
+        for field in preset.datetime_fields(fields) {
+            self.create_datetime_index(collection_name, field).await?;
+        }
+        self.payload_schemas
+            .lock()
+            .unwrap()
+            .insert(collection_name.to_string(), schema);
+        Ok(())
+    }
+
+    /// Metadata stored with the collection, empty if there is none.
+    pub async fn collection_metadata(
+        &self,
+        collection_name: impl Into<String>,
+    ) -> Result<HashMap<String, JsonValue>, QdrantError> {
+        let response = self
+            .qdrant()
+            .collection_info(collection_name.into())
+            .await?;
+        Ok(response
+            .result
+            .and_then(|info| info.config)
+            .map(|config| {
+                config
+                    .metadata
+                    .into_iter()
+                    .map(|(key, value)| (key, value.into_json()))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// Stores `schema` in the collection metadata and validates every later point write
+    /// through this client against it; writes with a non-conforming payload fail with
+    /// [`QdrantError::ConversionError`] before reaching Qdrant. Partial updates with
+    /// [`set_payload`](Self::set_payload) are not validated.
+    pub async fn register_payload_schema(
+        &self,
+        collection_name: &str,
+        schema: PayloadSchema,
+    ) -> Result<(), QdrantError> {
+        self.qdrant()
+            .update_collection(
+                UpdateCollectionBuilder::new(collection_name).metadata(schema.to_metadata()),
+            )
+            .await?;
+        self.payload_schemas
+            .lock()
+            .unwrap()
+            .insert(collection_name.to_string(), schema);
+        Ok(())
+    }
+
+    /// Loads the schema recorded in the collection metadata, e.g. by another process,
+    /// and validates later writes through this client against it.
+    pub async fn load_payload_schema(
+        &self,
+        collection_name: &str,
+    ) -> Result<Option<PayloadSchema>, QdrantError> {
+        let schema =
+            PayloadSchema::from_metadata(&self.collection_metadata(collection_name).await?);
+        let mut schemas = self.payload_schemas.lock().unwrap();
+        match &schema {
+            Some(schema) => schemas.insert(collection_name.to_string(), schema.clone()),
+            None => schemas.remove(collection_name),
+        };
+        Ok(schema)
+    }
+
+    /// The schema writes to the collection are validated against, if any.
+    pub fn payload_schema(&self, collection_name: &str) -> Option<PayloadSchema> {
+        self.payload_schemas
+            .lock()
+            .unwrap()
+            .get(collection_name)
+            .cloned()
+    }
+
+    fn validate_points(
+        &self,
+        collection_name: &str,
+        points: &[PointStruct],
+    ) -> Result<(), QdrantError> {
+        let Some(schema) = self.payload_schema(collection_name) else {
+            return Ok(());
+        };
+        for point in points {
+            let payload: Map<String, JsonValue> = point
+                .payload
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone().into_json()))
+                .collect();
+            schema
+                .validate(&payload)
+                .map_err(|violation| schema_violation_error(collection_name, violation))?;
+        }
+        Ok(())
+    }
+
+    /// Name (`None` for the unnamed vector) and dimension of every dense vector of the
+    /// collection.
+    pub async fn vector_sizes(
+        &self,
+        collection_name: impl Into<String>,
+    ) -> Result<Vec<(Option<String>, u64)>, QdrantError> {
+        let info = self
+            .qdrant()
+            .collection_info(collection_name.into())
+            .await?;
+        let vectors_config = info
+            .result
+            .and_then(|info| info.config)
+            .and_then(|config| config.params)
+            .and_then(|params| params.vectors_config)
+            .and_then(|vectors_config| vectors_config.config);
+
+        Ok(match vectors_config {
+            Some(vectors_config::Config::Params(params)) => vec![(None, params.size)],
+            Some(vectors_config::Config::ParamsMap(params)) => params
+                .map
+                .into_iter()
+                .map(|(name, params)| (Some(name), params.size))
+                .collect(),
+            None => Vec::new(),
+        })
+    }
+
+    /// Runs a trivial query against every vector of the collection, so its HNSW segments
+    /// are loaded and the gRPC connection is open before the first real query. Read
+    /// replicas are warmed up too.
+    pub async fn warm_up(&self, collection_name: impl Into<String>) -> Result<(), QdrantError> {
+        let collection_name = collection_name.into();
+        for (name, size) in self.vector_sizes(&collection_name).await? {
+            let mut query = QueryPointsBuilder::new(&collection_name)
+                .query(vec![1.0; size as usize])
+                .limit(1);
+            if let Some(name) = name {
+                query = query.using(name);
+            }
+            self.qdrant().query(query.clone()).await?;
+            for replica in &self.read_replicas {
+                replica.query(query.clone()).await?;
+            }
+        }
+        Ok(())
+    }
+
     pub async fn create_multivector_collection(
         &self,
         collection_name: impl Into<String>,
@@ -65,7 +543,7 @@ impl QdrantClient {
             VectorParamsBuilder::new(vector_size_txt, Distance::Cosine).build(),
         );
 
-        self.client
+        self.qdrant()
             .create_collection(
                 CreateCollectionBuilder::new(collection_name).vectors_config(vectors_config),
             )
@@ -73,11 +551,45 @@ impl QdrantClient {
         Ok(())
     }
 
+    /// Collection with a dense [`DENSE_VECTOR`] and a sparse [`SPARSE_VECTOR`] per point.
+    /// The sparse vector uses the `idf` modifier, so BM25 term weights get their inverse
+    /// document frequency from the collection at query time.
+    pub async fn create_hybrid_collection(
+        &self,
+        collection_name: impl Into<String>,
+        dense_size: u64,
+    ) -> Result<(), QdrantError> {
+        let mut vectors_config = VectorsConfigBuilder::default();
+        vectors_config.add_named_vector_params(
+            DENSE_VECTOR,
+            VectorParamsBuilder::new(dense_size, Distance::Cosine).build(),
+        );
+        let mut sparse_vectors_config = SparseVectorsConfigBuilder::default();
+        sparse_vectors_config.add_named_vector_params(
+            SPARSE_VECTOR,
+            SparseVectorParamsBuilder::default().modifier(Modifier::Idf),
+        );
+
+        self.qdrant()
+            .create_collection(
+                CreateCollectionBuilder::new(collection_name)
+                    .vectors_config(vectors_config)
+                    .sparse_vectors_config(sparse_vectors_config),
+            )
+            .await?;
+        Ok(())
+    }
+
     pub async fn delete_collection(
         &self,
         collection_name: impl Into<String>,
     ) -> Result<(), QdrantError> {
-        self.client.delete_collection(collection_name).await?;
+        let collection_name = collection_name.into();
+        self.write(
+            &collection_name,
+            self.qdrant().delete_collection(&collection_name),
+        )
+        .await?;
         Ok(())
     }
 
@@ -95,24 +607,267 @@ impl QdrantClient {
         &self,
         collection_name: impl Into<String>,
     ) -> Result<bool, QdrantError> {
-        let collection_exists = self.client.collection_exists(collection_name).await?;
+        let collection_exists = self.qdrant().collection_exists(collection_name).await?;
         Ok(collection_exists)
     }
 
+    /// Upserts one point per embedding/payload pair under ids generated by the
+    /// collection's [`IdStrategy`]. Both are moved into the points, so pass iterators
+    /// (e.g. `vec.into_iter()`) rather than cloning.
     pub async fn upsert_points(
         &self,
         collection_name: &str,
-        embeddings: Vec<Vec<f32>>,
-        payload: Vec<Payload>,
+        embeddings: impl IntoIterator<Item = Vec<f32>>,
+        payload: impl IntoIterator<Item = Payload>,
     ) -> Result<PointsOperationResponse, QdrantError> {
         let points: Vec<PointStruct> = embeddings
-            .iter()
-            .zip(payload.iter())
-            .map(|(embedding, payload)| to_point_struct(embedding.clone(), payload.clone()))
+            .into_iter()
+            .zip(payload)
+            .map(|(embedding, payload)| {
+                let (id, payload) = self.payload_point_id(collection_name, payload)?;
+                Ok(to_point_struct(id, embedding, payload))
+            })
+            .collect::<Result<_, QdrantError>>()?;
+        self.validate_points(collection_name, &points)?;
+        let num_points = points.len();
+        self.write(
+            collection_name,
+            self.metrics.track(
+                "upsert_points",
+                || format!("collection={collection_name} points={num_points}"),
+                self.qdrant()
+                    .upsert_points(UpsertPointsBuilder::new(collection_name, points).build()),
+            ),
+        )
+        .await
+    }
+
+    pub async fn upsert_points_with_ids(
+        &self,
+        collection_name: &str,
+        ids: impl IntoIterator<Item = String>,
+        embeddings: impl IntoIterator<Item = Vec<f32>>,
+        payload: impl IntoIterator<Item = Payload>,
+    ) -> Result<PointsOperationResponse, QdrantError> {
+        let points: Vec<PointStruct> = ids
+            .into_iter()
+            .zip(embeddings)
+            .zip(payload)
+            .map(|((id, embedding), payload)| PointStruct::new(id, embedding, payload))
             .collect();
-        self.client
-            .upsert_points(UpsertPointsBuilder::new(collection_name, points).build())
-            .await
+        self.validate_points(collection_name, &points)?;
+        let num_points = points.len();
+        self.write(
+            collection_name,
+            self.metrics.track(
+                "upsert_points",
+                || format!("collection={collection_name} points={num_points}"),
+                self.qdrant()
+                    .upsert_points(UpsertPointsBuilder::new(collection_name, points).wait(true)),
+            ),
+        )
+        .await
+    }
+
+    /// Writes points of a hybrid collection, each with its dense and sparse vector.
+    pub async fn upsert_points_hybrid(
+        &self,
+        collection_name: &str,
+        ids: impl IntoIterator<Item = String>,
+        embeddings: impl IntoIterator<Item = (Vec<f32>, SparseEmbedding)>,
+        payload: impl IntoIterator<Item = Payload>,
+    ) -> Result<PointsOperationResponse, QdrantError> {
+        let points: Vec<PointStruct> = ids
+            .into_iter()
+            .zip(embeddings)
+            .zip(payload)
+            .map(|((id, (dense, sparse)), payload)| {
+                let vectors = NamedVectors::default()
+                    .add_vector(DENSE_VECTOR, Vector::new_dense(dense))
+                    .add_vector(
+                        SPARSE_VECTOR,
+                        Vector::new_sparse(sparse.indices, sparse.values),
+                    );
+                PointStruct::new(id, vectors, payload)
+            })
+            .collect();
+        self.validate_points(collection_name, &points)?;
+        let num_points = points.len();
+        self.write(
+            collection_name,
+            self.metrics.track(
+                "upsert_points_hybrid",
+                || format!("collection={collection_name} points={num_points}"),
+                self.qdrant()
+                    .upsert_points(UpsertPointsBuilder::new(collection_name, points).wait(true)),
+            ),
+        )
+        .await
+    }
+
+    pub async fn delete_points(
+        &self,
+        collection_name: &str,
+        ids: Vec<String>,
+    ) -> Result<PointsOperationResponse, QdrantError> {
+        let num_points = ids.len();
+        self.write(
+            collection_name,
+            self.metrics.track(
+                "delete_points",
+                || format!("collection={collection_name} points={num_points}"),
+                self.qdrant().delete_points(
+                    DeletePointsBuilder::new(collection_name)
+                        .points(ids)
+                        .wait(true),
+                ),
+            ),
+        )
+        .await
+    }
+
+    /// Deletes every point matching `filter` and returns how many there were.
+    pub async fn delete_points_matching(
+        &self,
+        collection_name: &str,
+        filter: Filter,
+    ) -> Result<u64, QdrantError> {
+        self.write(
+            collection_name,
+            self.metrics.track(
+                "delete_points_matching",
+                || format!("collection={collection_name} filter={filter:?}"),
+                async {
+                    let count = self
+                        .qdrant()
+                        .count(
+                            CountPointsBuilder::new(collection_name)
+                                .filter(filter.clone())
+                                .exact(true),
+                        )
+                        .await?
+                        .result
+                        .map_or(0, |result| result.count);
+                    self.qdrant()
+                        .delete_points(
+                            DeletePointsBuilder::new(collection_name)
+                                .points(filter.clone())
+                                .wait(true),
+                        )
+                        .await?;
+                    Ok(count)
+                },
+            ),
+        )
+        .await
+    }
+
+    /// Deletes every point ingested from `source_uri`, e.g. when the document was retracted
+    /// or is about to be re-ingested. Returns the number of deleted points.
+    pub async fn delete_by_source(
+        &self,
+        collection_name: &str,
+        source_uri: &str,
+    ) -> Result<u64, QdrantError> {
+        self.delete_points_matching(
+            collection_name,
+            Filter::must([Condition::matches(SOURCE_FIELD, source_uri.to_string())]),
+        )
+        .await
+    }
+
+    /// Merges `payload` into the payload of the given points, leaving other fields untouched.
+    pub async fn set_payload(
+        &self,
+        collection_name: &str,
+        ids: Vec<String>,
+        payload: Payload,
+    ) -> Result<PointsOperationResponse, QdrantError> {
+        let num_points = ids.len();
+        self.write(
+            collection_name,
+            self.metrics.track(
+                "set_payload",
+                || format!("collection={collection_name} points={num_points}"),
+                self.qdrant().set_payload(
+                    SetPayloadPointsBuilder::new(collection_name, payload)
+                        .points_selector(PointsIdsList::from(ids))
+                        .wait(true),
+                ),
+            ),
+        )
+        .await
+    }
+
+    /// Removes `keys` from the payload of the given points.
+    pub async fn delete_payload(
+        &self,
+        collection_name: &str,
+        ids: Vec<String>,
+        keys: Vec<String>,
+    ) -> Result<PointsOperationResponse, QdrantError> {
+        let num_points = ids.len();
+        self.write(
+            collection_name,
+            self.metrics.track(
+                "delete_payload",
+                || format!("collection={collection_name} points={num_points} keys={keys:?}"),
+                self.qdrant().delete_payload(
+                    DeletePayloadPointsBuilder::new(collection_name, keys.clone())
+                        .points_selector(PointsIdsList::from(ids))
+                        .wait(true),
+                ),
+            ),
+        )
+        .await
+    }
+
+    /// Replaces the `name` vector of one point, leaving its other vectors and payload
+    /// untouched, e.g. to re-embed a point's text after its caption improved.
+    pub async fn update_named_vector(
+        &self,
+        collection_name: &str,
+        point_id: &str,
+        name: &str,
+        vector: Vec<f32>,
+    ) -> Result<PointsOperationResponse, QdrantError> {
+        let point = PointVectors {
+            id: Some(parse_point_id(point_id)),
+            vectors: Some(HashMap::from([(name.to_string(), vector)]).into()),
+        };
+        self.write(
+            collection_name,
+            self.metrics.track(
+                "update_named_vector",
+                || format!("collection={collection_name} id={point_id} vector={name}"),
+                self.qdrant().update_vectors(
+                    UpdatePointVectorsBuilder::new(collection_name, vec![point]).wait(true),
+                ),
+            ),
+        )
+        .await
+    }
+
+    /// Runs `operations` in one request. Qdrant applies them in order and stops at the
+    /// first failure, keeping the operations before it.
+    pub async fn update_batch(
+        &self,
+        collection_name: &str,
+        operations: Vec<PointsUpdateOperation>,
+    ) -> Result<(), QdrantError> {
+        let num_operations = operations.len();
+        self.write(
+            collection_name,
+            self.metrics.track(
+                "update_batch",
+                || format!("collection={collection_name} operations={num_operations}"),
+                self.qdrant().update_points_batch(
+                    UpdateBatchPointsBuilder::new(collection_name, operations).wait(true),
+                ),
+            ),
+        )
+        .await?;
+        Ok(())
     }
 
     pub async fn upsert_points_multivector(
@@ -122,25 +877,65 @@ impl QdrantClient {
         vec_txt: Vec<f32>,
         payload: Payload,
     ) -> Result<PointsOperationResponse, QdrantError> {
-        let response = self
-            .client
-            .upsert_points(
-                UpsertPointsBuilder::new(
-                    collection_name,
-                    vec![PointStruct::new(
-                        Uuid::new_v4().to_string(),
-                        HashMap::from([
-                            ("image".to_string(), vec_img),
-                            ("text".to_string(), vec_txt),
-                        ]),
-                        payload,
-                    )],
-                )
-                .wait(true),
-            )
-            .await?;
+        self.upsert_points_multivector_batch(collection_name, vec![(vec_img, vec_txt, payload)])
+            .await
+    }
 
-        Ok(response)
+    /// Writes `(image vector, text vector, payload)` points in a single request.
+    pub async fn upsert_points_multivector_batch(
+        &self,
+        collection_name: &str,
+        points: Vec<(Vec<f32>, Vec<f32>, Payload)>,
+    ) -> Result<PointsOperationResponse, QdrantError> {
+        let points: Vec<PointStruct> = points
+            .into_iter()
+            .map(|(vec_img, vec_txt, payload)| {
+                let (id, payload) = self.payload_point_id(collection_name, payload)?;
+                Ok(PointStruct::new(
+                    id,
+                    HashMap::from([
+                        ("image".to_string(), vec_img),
+                        ("text".to_string(), vec_txt),
+                    ]),
+                    payload,
+                ))
+            })
+            .collect::<Result<_, QdrantError>>()?;
+        self.validate_points(collection_name, &points)?;
+        let num_points = points.len();
+        self.write(
+            collection_name,
+            self.metrics.track(
+                "upsert_points_multivector",
+                || format!("collection={collection_name} points={num_points}"),
+                self.qdrant()
+                    .upsert_points(UpsertPointsBuilder::new(collection_name, points).wait(true)),
+            ),
+        )
+        .await
+    }
+
+    pub async fn upsert_documents(
+        &self,
+        collection_name: &str,
+        embeddings: Vec<Vec<f32>>,
+        documents: &[Document],
+        convention: PayloadConvention,
+    ) -> Result<PointsOperationResponse, QdrantError> {
+        let points =
+            interop::to_point_structs(embeddings, documents, convention, self.payload_fields())?;
+        self.validate_points(collection_name, &points)?;
+        let num_points = points.len();
+        self.write(
+            collection_name,
+            self.metrics.track(
+                "upsert_documents",
+                || format!("collection={collection_name} points={num_points}"),
+                self.qdrant()
+                    .upsert_points(UpsertPointsBuilder::new(collection_name, points).wait(true)),
+            ),
+        )
+        .await
     }
 
     pub async fn query_points(
@@ -149,14 +944,19 @@ impl QdrantClient {
         vector: Vec<f32>,
         limit: u64,
     ) -> Result<QueryResponse, QdrantError> {
+        let collection_name = collection_name.into();
         let response = self
-            .client
-            .query(
-                QueryPointsBuilder::new(collection_name)
-                    .query(vector)
-                    .limit(limit)
-                    .with_payload(true)
-                    .with_vectors(true),
+            .metrics
+            .track(
+                "query_points",
+                || format!("collection={collection_name} limit={limit}"),
+                self.query_read(
+                    QueryPointsBuilder::new(&collection_name)
+                        .query(vector)
+                        .limit(limit)
+                        .with_payload(true)
+                        .with_vectors(true),
+                ),
             )
             .await?;
 
@@ -170,18 +970,165 @@ impl QdrantClient {
         limit: u64,
         vector_name: impl Into<String>,
     ) -> Result<QueryResponse, QdrantError> {
+        let collection_name = collection_name.into();
+        let vector_name = vector_name.into();
         let response = self
-            .client
-            .query(
-                QueryPointsBuilder::new(collection_name)
-                    .query(vector)
-                    .limit(limit)
-                    .using(vector_name),
+            .metrics
+            .track(
+                "query_points_named",
+                || format!("collection={collection_name} vector={vector_name} limit={limit}"),
+                self.query_read(
+                    QueryPointsBuilder::new(&collection_name)
+                        .query(vector)
+                        .limit(limit)
+                        .using(&vector_name),
+                ),
             )
             .await?;
         Ok(response)
     }
 
+    /// Like [`query_points_named`](Self::query_points_named), restricted to points matching
+    /// `filter`.
+    pub async fn query_points_named_filtered(
+        &self,
+        collection_name: impl Into<String>,
+        vector: Vec<f32>,
+        limit: u64,
+        vector_name: impl Into<String>,
+        filter: Filter,
+    ) -> Result<QueryResponse, QdrantError> {
+        let collection_name = collection_name.into();
+        let vector_name = vector_name.into();
+        self.metrics
+            .track(
+                "query_points_named",
+                || {
+                    format!(
+                        "collection={collection_name} vector={vector_name} limit={limit} \
+                         filter={filter:?}"
+                    )
+                },
+                self.query_read(
+                    QueryPointsBuilder::new(&collection_name)
+                        .query(vector)
+                        .limit(limit)
+                        .filter(filter.clone())
+                        .with_payload(true)
+                        .using(&vector_name),
+                ),
+            )
+            .await
+    }
+
+    /// Creates a payload index for range filters on a numeric field.
+    pub async fn create_float_index(
+        &self,
+        collection_name: impl Into<String>,
+        field_name: impl Into<String>,
+    ) -> Result<(), QdrantError> {
+        self.qdrant()
+            .create_field_index(
+                CreateFieldIndexCollectionBuilder::new(
+                    collection_name,
+                    field_name,
+                    FieldType::Float,
+                )
+                .wait(true),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Creates a payload index for range filters on an RFC 3339 timestamp field.
+    pub async fn create_datetime_index(
+        &self,
+        collection_name: impl Into<String>,
+        field_name: impl Into<String>,
+    ) -> Result<(), QdrantError> {
+        self.qdrant()
+            .create_field_index(
+                CreateFieldIndexCollectionBuilder::new(
+                    collection_name,
+                    field_name,
+                    FieldType::Datetime,
+                )
+                .wait(true),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Creates a payload index for exact-match filters on a string field. Creating an
+    /// existing index is a no-op.
+    pub async fn create_keyword_index(
+        &self,
+        collection_name: impl Into<String>,
+        field_name: impl Into<String>,
+    ) -> Result<(), QdrantError> {
+        self.qdrant()
+            .create_field_index(
+                CreateFieldIndexCollectionBuilder::new(
+                    collection_name,
+                    field_name,
+                    FieldType::Keyword,
+                )
+                .wait(true),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Queries a collection written with the given payload convention (e.g. by LangChain or
+    /// LlamaIndex). Points whose payload does not follow the convention are skipped.
+    pub async fn query_documents(
+        &self,
+        collection_name: impl Into<String>,
+        vector: Vec<f32>,
+        limit: u64,
+        convention: PayloadConvention,
+    ) -> Result<Vec<(Document, f32)>, QdrantError> {
+        self.query_documents_filtered(collection_name, vector, limit, convention, None)
+            .await
+    }
+
+    /// Like [`query_documents`](Self::query_documents), restricted to points matching `filter`.
+    pub async fn query_documents_filtered(
+        &self,
+        collection_name: impl Into<String>,
+        vector: Vec<f32>,
+        limit: u64,
+        convention: PayloadConvention,
+        filter: Option<Filter>,
+    ) -> Result<Vec<(Document, f32)>, QdrantError> {
+        let collection_name = collection_name.into();
+        let mut query = QueryPointsBuilder::new(&collection_name)
+            .query(vector)
+            .limit(limit)
+            .with_payload(true);
+        if let Some(filter) = &filter {
+            query = query.filter(filter.clone());
+        }
+        let response = self
+            .metrics
+            .track(
+                "query_documents",
+                || format!("collection={collection_name} limit={limit} filter={filter:?}"),
+                self.query_read(query),
+            )
+            .await?;
+
+        Ok(response
+            .result
+            .into_iter()
+            .filter_map(|point| {
+                let score = point.score;
+                interop::from_scored_point(point, convention, self.payload_fields())
+                    .map(|document| (document, score))
+            })
+            .collect())
+    }
+
     pub async fn query_points_multivector(
         &self,
         collection_name: impl Into<String>,
@@ -194,10 +1141,8 @@ impl QdrantClient {
 
         let collection_name = collection_name.into();
 
-        let uri = &self.client.config.uri;
-
-        let client_img = Qdrant::from_url(uri).build().unwrap();
-        let client_txt = Qdrant::from_url(uri).build().unwrap();
+        let client_img = self.qdrant_read().into_owned();
+        let client_txt = self.qdrant_read().into_owned();
 
         let collection_name_cln = collection_name.clone();
         tokio::spawn(async move {
@@ -234,6 +1179,43 @@ impl QdrantClient {
         Ok((image_response, text_response))
     }
 
+    /// Pages through every point matching `filter` and returns them all at once.
+    pub async fn scroll_all(
+        &self,
+        collection_name: impl Into<String>,
+        filter: Option<Filter>,
+        with_vectors: bool,
+    ) -> Result<Vec<RetrievedPoint>, QdrantError> {
+        let collection_name = collection_name.into();
+        let mut points = Vec::new();
+        let mut offset: Option<PointId> = None;
+        loop {
+            let mut request = ScrollPointsBuilder::new(collection_name.clone())
+                .filter(filter.clone().unwrap_or_default())
+                .limit(SCROLL_PAGE_SIZE)
+                .with_payload(true)
+                .with_vectors(with_vectors);
+            if let Some(offset) = offset.take() {
+                request = request.offset(offset);
+            }
+
+            let response = self
+                .metrics
+                .track(
+                    "scroll",
+                    || format!("collection={collection_name} filter={filter:?}"),
+                    self.qdrant_read().scroll(request),
+                )
+                .await?;
+            points.extend(response.result);
+            match response.next_page_offset {
+                Some(next) => offset = Some(next),
+                None => break,
+            }
+        }
+        Ok(points)
+    }
+
     pub async fn search_points(
         &self,
         collection_name: impl Into<String>,
@@ -241,13 +1223,18 @@ impl QdrantClient {
         limit: u64,
         filter: Option<Filter>,
     ) -> Result<SearchResponse, QdrantError> {
+        let collection_name = collection_name.into();
         let search_result = self
-            .client
-            .search_points(
-                SearchPointsBuilder::new(collection_name, vector, limit)
-                    .filter(filter.unwrap_or(Filter::default()))
-                    .with_payload(false)
-                    .params(SearchParamsBuilder::default().exact(true)),
+            .metrics
+            .track(
+                "search_points",
+                || format!("collection={collection_name} limit={limit} filter={filter:?}"),
+                self.qdrant_read().search_points(
+                    SearchPointsBuilder::new(&collection_name, vector, limit)
+                        .filter(filter.clone().unwrap_or_default())
+                        .with_payload(false)
+                        .params(SearchParamsBuilder::default().exact(true)),
+                ),
             )
             .await?;
         Ok(search_result)
@@ -264,21 +1251,336 @@ impl QdrantClient {
         let collection_name = collection_name.into();
         for vector in vectors {
             let search = SearchPointsBuilder::new(collection_name.clone(), vector, limit)
-                .filter(filter.clone().unwrap_or(Filter::default()))
+                .filter(filter.clone().unwrap_or_default())
                 .build();
             searches.push(search);
         }
         let results = self
-            .client
+            .qdrant()
             .search_batch_points(SearchBatchPointsBuilder::new(collection_name, searches))
             .await?;
         Ok(results)
     }
 }
 
+impl VectorStore for QdrantClient {
+    async fn upsert(
+        &self,
+        collection: &str,
+        points: Vec<VectorPoint>,
+    ) -> Result<(), VectorStoreError> {
+        let mut ids = Vec::with_capacity(points.len());
+        let mut vectors = Vec::with_capacity(points.len());
+        let mut payloads = Vec::with_capacity(points.len());
+        for point in points {
+            ids.push(point.id);
+            vectors.push(point.vector);
+            payloads.push(Payload::from(point.payload));
+        }
+        self.upsert_points_with_ids(collection, ids, vectors, payloads)
+            .await?;
+        Ok(())
+    }
+
+    async fn query_filtered(
+        &self,
+        collection: &str,
+        vector: Vec<f32>,
+        limit: u64,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<SearchHit>, VectorStoreError> {
+        let mut query = QueryPointsBuilder::new(collection)
+            .query(vector)
+            .limit(limit)
+            .with_payload(true);
+        if let Some(filter) = filter {
+            query = query.filter(Filter::from(filter));
+        }
+        let response = self
+            .metrics
+            .track(
+                "query",
+                || format!("collection={collection} limit={limit} filter={filter:?}"),
+                self.query_read(query),
+            )
+            .await?;
+        Ok(response.result.into_iter().map(search_hit).collect())
+    }
+
+    async fn delete(&self, collection: &str, ids: Vec<String>) -> Result<(), VectorStoreError> {
+        self.delete_points(collection, ids).await?;
+        Ok(())
+    }
+
+    async fn delete_matching(
+        &self,
+        collection: &str,
+        filter: &MetadataFilter,
+    ) -> Result<u64, VectorStoreError> {
+        Ok(self
+            .delete_points_matching(collection, Filter::from(filter))
+            .await?)
+    }
+
+    async fn set_payload(
+        &self,
+        collection: &str,
+        ids: Vec<String>,
+        fields: Map<String, JsonValue>,
+    ) -> Result<(), VectorStoreError> {
+        QdrantClient::set_payload(self, collection, ids, Payload::from(fields)).await?;
+        Ok(())
+    }
+
+    async fn delete_payload(
+        &self,
+        collection: &str,
+        ids: Vec<String>,
+        keys: Vec<String>,
+    ) -> Result<(), VectorStoreError> {
+        QdrantClient::delete_payload(self, collection, ids, keys).await?;
+        Ok(())
+    }
+
+    async fn update_named_vector(
+        &self,
+        collection: &str,
+        point_id: &str,
+        vector_name: &str,
+        vector: Vec<f32>,
+    ) -> Result<(), VectorStoreError> {
+        QdrantClient::update_named_vector(self, collection, point_id, vector_name, vector).await?;
+        Ok(())
+    }
+
+    async fn upsert_multi(
+        &self,
+        collection: &str,
+        points: Vec<MultiVectorPoint>,
+    ) -> Result<(), VectorStoreError> {
+        let points: Vec<PointStruct> = points
+            .into_iter()
+            .map(|point| {
+                let mut vectors = NamedVectors::default();
+                for (name, vector) in point.vectors {
+                    vectors = vectors.add_vector(name, Vector::new_dense(vector));
+                }
+                for (name, sparse) in point.sparse_vectors {
+                    vectors =
+                        vectors.add_vector(name, Vector::new_sparse(sparse.indices, sparse.values));
+                }
+                PointStruct::new(point.id, vectors, Payload::from(point.payload))
+            })
+            .collect();
+        self.validate_points(collection, &points)?;
+        let num_points = points.len();
+        self.write(
+            collection,
+            self.metrics.track(
+                "upsert_multi",
+                || format!("collection={collection} points={num_points}"),
+                self.qdrant()
+                    .upsert_points(UpsertPointsBuilder::new(collection, points).wait(true)),
+            ),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn query_named(
+        &self,
+        collection: &str,
+        vector_name: &str,
+        vector: Vec<f32>,
+        limit: u64,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<SearchHit>, VectorStoreError> {
+        let mut query = QueryPointsBuilder::new(collection)
+            .query(vector)
+            .using(vector_name)
+            .limit(limit)
+            .with_payload(true);
+        if let Some(filter) = filter {
+            query = query.filter(Filter::from(filter));
+        }
+        let response = self
+            .metrics
+            .track(
+                "query_named",
+                || format!("collection={collection} using={vector_name} limit={limit}"),
+                self.query_read(query),
+            )
+            .await?;
+        Ok(response.result.into_iter().map(search_hit).collect())
+    }
+
+    async fn delete_multi(
+        &self,
+        collection: &str,
+        _vector_names: &[&str],
+        ids: Vec<String>,
+    ) -> Result<(), VectorStoreError> {
+        self.delete(collection, ids).await
+    }
+
+    async fn scroll(
+        &self,
+        collection: &str,
+        offset: Option<String>,
+        limit: u64,
+    ) -> Result<(Vec<VectorPoint>, Option<String>), VectorStoreError> {
+        let mut request = ScrollPointsBuilder::new(collection)
+            .limit(limit as u32)
+            .with_payload(true)
+            .with_vectors(true);
+        if let Some(offset) = offset {
+            request = request.offset(parse_point_id(&offset));
+        }
+        let response = self
+            .metrics
+            .track(
+                "scroll",
+                || format!("collection={collection} limit={limit}"),
+                self.qdrant_read().scroll(request),
+            )
+            .await?;
+        let points = response
+            .result
+            .into_iter()
+            .map(vector_point)
+            .collect::<Result<_, QdrantError>>()?;
+        let next = response.next_page_offset.as_ref().map(point_id_to_string);
+        Ok((points, next))
+    }
+
+    async fn scroll_multi(
+        &self,
+        collection: &str,
+        _vector_names: &[&str],
+        offset: Option<String>,
+        limit: u64,
+    ) -> Result<(Vec<MultiVectorPoint>, Option<String>), VectorStoreError> {
+        let mut request = ScrollPointsBuilder::new(collection)
+            .limit(limit as u32)
+            .with_payload(true)
+            .with_vectors(true);
+        if let Some(offset) = offset {
+            request = request.offset(parse_point_id(&offset));
+        }
+        let response = self
+            .metrics
+            .track(
+                "scroll_multi",
+                || format!("collection={collection} limit={limit}"),
+                self.qdrant_read().scroll(request),
+            )
+            .await?;
+        let points = response
+            .result
+            .into_iter()
+            .map(multi_vector_point)
+            .collect::<Result<_, QdrantError>>()?;
+        let next = response.next_page_offset.as_ref().map(point_id_to_string);
+        Ok((points, next))
+    }
+}
+
+// Points with named or sparse vectors have no single dense vector and are rejected, see
+// `multi_vector_point`
+fn vector_point(point: RetrievedPoint) -> Result<VectorPoint, QdrantError> {
+    let id = point
+        .id
+        .as_ref()
+        .map(point_id_to_string)
+        .unwrap_or_default();
+    let vector = match point
+        .vectors
+        .as_ref()
+        .and_then(|vectors| vectors.get_vector())
+    {
+        Some(vector_output::Vector::Dense(dense)) => dense.data,
+        _ => {
+            return Err(QdrantError::ConversionError(format!(
+                "point {id} has no unnamed dense vector"
+            )))
+        }
+    };
+    let payload = point
+        .payload
+        .into_iter()
+        .map(|(key, value)| (key, value.into_json()))
+        .collect();
+    Ok(VectorPoint::new(id, vector, payload))
+}
+
+// Points with an unnamed vector, or a multi-dense one, are rejected
+fn multi_vector_point(point: RetrievedPoint) -> Result<MultiVectorPoint, QdrantError> {
+    let id = point
+        .id
+        .as_ref()
+        .map(point_id_to_string)
+        .unwrap_or_default();
+    let payload = point
+        .payload
+        .into_iter()
+        .map(|(key, value)| (key, value.into_json()))
+        .collect();
+    let mut multi = MultiVectorPoint::new(id, payload);
+    let named = match point.vectors.and_then(|vectors| vectors.vectors_options) {
+        Some(vectors_output::VectorsOptions::Vectors(named)) => named.vectors,
+        _ => {
+            return Err(QdrantError::ConversionError(format!(
+                "point {} has no named vectors",
+                multi.id
+            )))
+        }
+    };
+    for (name, vector) in named {
+        match vector.into_vector() {
+            vector_output::Vector::Dense(dense) => {
+                multi.vectors.insert(name, dense.data);
+            }
+            vector_output::Vector::Sparse(sparse) => {
+                multi.sparse_vectors.insert(
+                    name,
+                    SparseEmbedding {
+                        indices: sparse.indices,
+                        values: sparse.values,
+                    },
+                );
+            }
+            _ => {
+                return Err(QdrantError::ConversionError(format!(
+                    "vector {name} of point {} is neither dense nor sparse",
+                    multi.id
+                )))
+            }
+        }
+    }
+    Ok(multi)
+}
+
+fn search_hit(point: ScoredPoint) -> SearchHit {
+    SearchHit {
+        id: point
+            .id
+            .as_ref()
+            .map(point_id_to_string)
+            .unwrap_or_default(),
+        score: point.score,
+        payload: point
+            .payload
+            .into_iter()
+            .map(|(key, value)| (key, value.into_json()))
+            .collect(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use qdrant_client::qdrant::Vectors;
+    use serde_json::json;
 
     async fn setup() -> String {
         let client = QdrantClient::new("http://localhost:6334");
@@ -347,10 +1649,86 @@ mod tests {
         let point = vec![0.1, 0.2, 0.3, 0.4, 0.5];
         let vectors = Some(Vectors::from(point.clone()));
         let payload = Payload::try_from(serde_json::json!({"text": "Hello World"})).unwrap();
-        let point_struct = to_point_struct(point, payload);
+        let point_struct = to_point_struct("1".to_string(), point, payload);
         assert_eq!(point_struct.vectors, vectors);
     }
 
+    #[test]
+    fn test_point_id_to_string() {
+        assert_eq!(point_id_to_string(&PointId::from(42)), "42");
+        let uuid = Uuid::new_v4().to_string();
+        assert_eq!(point_id_to_string(&PointId::from(uuid.clone())), uuid);
+    }
+
+    #[test]
+    fn test_point_id_from_key() {
+        assert_eq!(point_id_from_key("events:1"), point_id_from_key("events:1"));
+        assert_ne!(point_id_from_key("events:1"), point_id_from_key("events:2"));
+    }
+
+    #[test]
+    fn test_payload_point_id() {
+        let client = QdrantClient::new("http://127.0.0.1:1");
+        client.set_id_strategy("memories", IdStrategy::ContentHash);
+        let id_for = |payload: JsonValue| {
+            let payload = Payload::try_from(payload).unwrap();
+            let (id, kept) = client
+                .payload_point_id("memories", payload.clone())
+                .unwrap();
+            assert_eq!(kept, payload);
+            id
+        };
+        assert_eq!(
+            id_for(json!({"text": "boots", "source": "a.md", "page": 1})),
+            id_for(json!({"text": "boots", "source": "a.md", "page": 2}))
+        );
+        assert_ne!(
+            id_for(json!({"text": "boots", "source": "a.md"})),
+            id_for(json!({"text": "boots", "source": "b.md"}))
+        );
+        assert_eq!(id_for(json!({"n": 1})), id_for(json!({"n": 1})));
+        assert_ne!(id_for(json!({"n": 1})), id_for(json!({"n": 2})));
+
+        client.set_id_strategy("memories", IdStrategy::CallerProvided);
+        assert!(client.payload_point_id("memories", Payload::new()).is_err());
+    }
+
+    #[test]
+    fn test_read_replica_routing() {
+        let client = QdrantClient::new("http://127.0.0.1:6334")
+            .with_read_replicas(&["http://127.0.0.1:6335", "http://127.0.0.1:6336"])
+            .unwrap();
+        let read_uris: Vec<String> = (0..3)
+            .map(|_| client.qdrant_read().config.uri.clone())
+            .collect();
+        assert_eq!(
+            read_uris,
+            [
+                "http://127.0.0.1:6335",
+                "http://127.0.0.1:6336",
+                "http://127.0.0.1:6335"
+            ]
+        );
+        // Writes stay on the primary
+        assert_eq!(client.qdrant().config.uri, "http://127.0.0.1:6334");
+
+        let invalid = QdrantClient::new("http://127.0.0.1:6334").with_read_replicas(&["not a url"]);
+        assert!(invalid.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_write_generation() {
+        // Nothing listens there, but a failed write may still have been applied
+        let client = QdrantClient::new("http://127.0.0.1:1");
+        assert_eq!(client.write_generation("memories"), 0);
+        let deleted = client
+            .delete_points("memories", vec!["1".to_string()])
+            .await;
+        assert!(deleted.is_err());
+        assert_eq!(client.write_generation("memories"), 1);
+        assert_eq!(client.write_generation("products"), 0);
+    }
+
     #[test]
     fn test_texts_to_payload() {
         let texts = vec!["Hello World".to_string(), "Ola Mundo".to_string()];
@@ -0,0 +1,281 @@
+use crate::embeddings::sparse::{Bm25Encoder, SparseEncoding};
+use crate::embeddings::text_embedding_inference::{InputKind, TextEmbeddingInference};
+use crate::utils::load_image_as_base64;
+use crate::vectorstore::ingestion::IngestReport;
+use crate::vectorstore::qdrant_client::QdrantClient;
+use anyhow::{anyhow, Result};
+use qdrant_client::Payload;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as JsonValue};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// What a failed item embeds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RetryContent {
+    Text { text: String },
+    Image { image_path: String },
+}
+
+/// A point that could not be embedded or written, with everything needed to write it
+/// later under the same id.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FailedItem {
+    pub collection: String,
+    pub id: String,
+    #[serde(flatten)]
+    pub content: RetryContent,
+    pub embedding_url: String,
+    #[serde(default)]
+    pub sparse: Option<SparseEncoding>,
+    pub payload: Map<String, JsonValue>,
+    pub error: String,
+    pub attempts: u32,
+}
+
+/// Failed ingestion items, persisted as a JSON Lines file so they survive the process and
+/// can be retried with [`retry_failed`] once the embedding server or Qdrant is fixed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryQueue {
+    path: PathBuf,
+}
+
+impl RetryQueue {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends items to the file, creating it if needed.
+    pub fn push(&self, items: &[FailedItem]) -> Result<()> {
+        if items.is_empty() {
+            return Ok(());
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        for item in items {
+            writeln!(file, "{}", serde_json::to_string(item)?)?;
+        }
+        Ok(())
+    }
+
+    /// Queued items, none if the file does not exist.
+    pub fn load(&self) -> Result<Vec<FailedItem>> {
+        let data = match std::fs::read_to_string(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        data.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Ok(serde_json::from_str(line)?))
+            .collect()
+    }
+
+    /// Replaces the queued items, removing the file once none are left. The new items
+    /// are written to a temporary file next to the queue and renamed over it, so a
+    /// failure part way leaves the previous items queued.
+    pub fn replace(&self, items: &[FailedItem]) -> Result<()> {
+        if items.is_empty() {
+            return match std::fs::remove_file(&self.path) {
+                Ok(()) => Ok(()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                Err(e) => Err(e.into()),
+            };
+        }
+
+        let file_name = self
+            .path
+            .file_name()
+            .ok_or_else(|| anyhow!("Invalid retry queue path {}", self.path.display()))?;
+        let tmp_path = self.path.with_file_name(format!(
+            ".{}.{}.tmp",
+            file_name.to_string_lossy(),
+            Uuid::new_v4()
+        ));
+        let written = Self::write_file(&tmp_path, items)
+            .and_then(|()| Ok(std::fs::rename(&tmp_path, &self.path)?));
+        if written.is_err() {
+            let _ = std::fs::remove_file(&tmp_path);
+        }
+        written
+    }
+
+    fn write_file(path: &Path, items: &[FailedItem]) -> Result<()> {
+        let mut file = std::fs::File::create(path)?;
+        for item in items {
+            writeln!(file, "{}", serde_json::to_string(item)?)?;
+        }
+        file.sync_all()?;
+        Ok(())
+    }
+}
+
+async fn write_item(item: &FailedItem, client: &QdrantClient) -> Result<u64> {
+    let (input, input_kind) = match &item.content {
+        RetryContent::Text { text } => (text.clone(), InputKind::Text),
+        RetryContent::Image { image_path } => (
+            load_image_as_base64(image_path).await?,
+            InputKind::ImageBase64,
+        ),
+    };
+    let embedding = TextEmbeddingInference::new(Some(&item.embedding_url))
+        .embed_as(vec![input], input_kind)
+        .await
+        .map_err(|e| anyhow!("Embedding failed: {e}"))?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("Embedding server returned no embedding"))?;
+    let dimensions = embedding.len() as u64;
+    let payload = Payload::from(item.payload.clone());
+
+    match (&item.sparse, &item.content) {
+        (Some(encoding), RetryContent::Text { text }) => {
+            let sparse = match encoding {
+                SparseEncoding::Bm25 => Bm25Encoder::new().encode(text),
+                SparseEncoding::Splade { url } => TextEmbeddingInference::new(Some(url))
+                    .embed_sparse(vec![text.clone()])
+                    .await
+                    .map_err(|e| anyhow!("Sparse embedding failed: {e}"))?
+                    .into_iter()
+                    .next()
+                    .unwrap_or_default(),
+            };
+            client
+                .upsert_points_hybrid(
+                    &item.collection,
+                    [item.id.clone()],
+                    [(embedding, sparse)],
+                    [payload],
+                )
+                .await?;
+        }
+        _ => {
+            client
+                .upsert_points_with_ids(&item.collection, [item.id.clone()], [embedding], [payload])
+                .await?;
+        }
+    }
+    Ok(dimensions)
+}
+
+/// Writes the items queued by the ingestion run of `report` again, one by one. Items that
+/// fail again stay queued with the new error and one more attempt; the returned report
+/// counts the retried items as documents.
+pub async fn retry_failed(report: &IngestReport, client: &QdrantClient) -> Result<IngestReport> {
+    let Some(path) = &report.retry_queue else {
+        return Ok(IngestReport::default());
+    };
+    let queue = RetryQueue::new(path);
+    let items = queue.load()?;
+    let mut retried = IngestReport {
+        documents: items.len(),
+        retry_queue: Some(path.clone()),
+        ..Default::default()
+    };
+
+    let mut still_failing = Vec::new();
+    for mut item in items {
+        retried.embedding_calls += 1;
+        match write_item(&item, client).await {
+            Ok(dimensions) => {
+                retried.points += 1;
+                retried.vector_dimensions = Some(dimensions);
+            }
+            Err(e) => {
+                log::warn!("Retry of point {} failed: {e}", item.id);
+                item.error = e.to_string();
+                item.attempts += 1;
+                still_failing.push(item);
+            }
+        }
+    }
+    retried.failed = still_failing.len();
+    queue.replace(&still_failing)?;
+    Ok(retried)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(text: &str) -> FailedItem {
+        FailedItem {
+            collection: "faq".to_string(),
+            id: Uuid::new_v4().to_string(),
+            content: RetryContent::Text {
+                text: text.to_string(),
+            },
+            embedding_url: "http://localhost:8080".to_string(),
+            sparse: Some(SparseEncoding::Bm25),
+            payload: Map::new(),
+            error: "connection refused".to_string(),
+            attempts: 1,
+        }
+    }
+
+    #[test]
+    fn test_retry_queue_persists_items() {
+        let path = std::env::temp_dir().join(format!("retry-{}.jsonl", Uuid::new_v4()));
+        let queue = RetryQueue::new(&path);
+        assert!(queue.load().unwrap().is_empty());
+
+        let items = vec![item("Boots are waterproof."), item("Sandals are not.")];
+        queue.push(&items[..1]).unwrap();
+        queue.push(&items[1..]).unwrap();
+        assert_eq!(queue.load().unwrap(), items);
+
+        queue.replace(&items[1..]).unwrap();
+        assert_eq!(queue.load().unwrap(), items[1..]);
+        // No temporary file is left behind
+        let leftovers = std::fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                name.ends_with(".tmp")
+                    && name.contains(&*path.file_name().unwrap().to_string_lossy())
+            })
+            .count();
+        assert_eq!(leftovers, 0);
+        queue.replace(&[]).unwrap();
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_retry_failed_requeues_failures() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/embed")
+            .with_status(503)
+            .create_async()
+            .await;
+        let path = std::env::temp_dir().join(format!("retry-{}.jsonl", Uuid::new_v4()));
+        let mut queued = item("Boots are waterproof.");
+        queued.embedding_url = server.url();
+        RetryQueue::new(&path).push(&[queued]).unwrap();
+
+        let report = IngestReport {
+            retry_queue: Some(path.clone()),
+            ..Default::default()
+        };
+        let client = QdrantClient::new("http://localhost:6334");
+        let retried = retry_failed(&report, &client).await.unwrap();
+        assert_eq!(
+            (retried.documents, retried.points, retried.failed),
+            (1, 0, 1)
+        );
+
+        let items = RetryQueue::new(&path).load().unwrap();
+        assert_eq!(items[0].attempts, 2);
+        assert!(items[0].error.contains("503"));
+        std::fs::remove_file(path).unwrap();
+    }
+}
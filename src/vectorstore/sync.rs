@@ -0,0 +1,520 @@
+use crate::embeddings::text_embedding_inference::TextEmbeddingInference;
+use crate::utils::html_to_text;
+use crate::utils::http_fetch::HttpFetcher;
+use crate::vectorstore::qdrant_client::{point_id_from_key, QdrantClient, SOURCE_FIELD};
+use anyhow::{anyhow, Result};
+use qdrant_client::Payload;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde_json::{json, Map};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceKind {
+    /// A single web page.
+    Url,
+    /// An RSS or Atom feed; each entry is ingested from its feed content.
+    Feed,
+    /// A sitemap; each listed page is fetched and ingested.
+    Sitemap,
+}
+
+#[derive(Debug, Clone)]
+pub struct SyncSource {
+    pub url: String,
+    pub kind: SourceKind,
+}
+
+impl SyncSource {
+    pub fn new(url: impl Into<String>, kind: SourceKind) -> Self {
+        Self {
+            url: url.into(),
+            kind,
+        }
+    }
+}
+
+/// An entry of a feed or sitemap. `version` is whatever the source exposes to detect
+/// changes cheaply (`lastmod`, `updated`), if anything.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SourceEntry {
+    pub link: String,
+    pub title: Option<String>,
+    pub content: Option<String>,
+    pub version: Option<String>,
+}
+
+/// Parses RSS `<item>` and Atom `<entry>` elements.
+pub fn parse_feed(xml: &str) -> Result<Vec<SourceEntry>> {
+    let records = parse_records(xml, &["item", "entry"])?;
+    Ok(records
+        .into_iter()
+        .filter_map(|mut record| {
+            // RSS `guid` or Atom `id` identify the entry better than the link
+            let link = record
+                .remove("link")
+                .or_else(|| record.remove("guid"))
+                .or_else(|| record.remove("id"))?;
+            Some(SourceEntry {
+                link,
+                title: record.remove("title"),
+                content: record
+                    .remove("encoded")
+                    .or_else(|| record.remove("content"))
+                    .or_else(|| record.remove("description"))
+                    .or_else(|| record.remove("summary")),
+                version: record.remove("updated"),
+            })
+        })
+        .collect())
+}
+
+/// Parses sitemap `<url>` elements.
+pub fn parse_sitemap(xml: &str) -> Result<Vec<SourceEntry>> {
+    let records = parse_records(xml, &["url"])?;
+    Ok(records
+        .into_iter()
+        .filter_map(|mut record| {
+            Some(SourceEntry {
+                link: record.remove("loc")?,
+                version: record.remove("lastmod"),
+                ..Default::default()
+            })
+        })
+        .collect())
+}
+
+/// Collects the text of the direct children of every `record_tags` element, keyed by
+/// local name. Atom `<link href=".."/>` is read from its attribute.
+fn parse_records(xml: &str, record_tags: &[&str]) -> Result<Vec<HashMap<String, String>>> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut records = Vec::new();
+    let mut record: Option<HashMap<String, String>> = None;
+    let mut field: Option<String> = None;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).to_string();
+                if record_tags.contains(&name.as_str()) {
+                    record = Some(HashMap::new());
+                } else if record.is_some() {
+                    field = Some(name);
+                }
+            }
+            Event::Empty(e) if e.local_name().as_ref() == b"link" => {
+                if let (Some(record), Some(href)) = (record.as_mut(), e.try_get_attribute("href")?)
+                {
+                    record
+                        .entry("link".to_string())
+                        .or_insert(href.unescape_value()?.to_string());
+                }
+            }
+            Event::Text(e) => {
+                if let (Some(record), Some(field)) = (record.as_mut(), field.as_ref()) {
+                    record.insert(field.clone(), e.unescape()?.to_string());
+                }
+            }
+            Event::CData(e) => {
+                if let (Some(record), Some(field)) = (record.as_mut(), field.as_ref()) {
+                    record.insert(field.clone(), e.decode()?.to_string());
+                }
+            }
+            Event::End(e) => {
+                let name = e.local_name();
+                if record_tags
+                    .iter()
+                    .any(|tag| tag.as_bytes() == name.as_ref())
+                {
+                    records.extend(record.take());
+                }
+                field = None;
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(records)
+}
+
+fn content_hash(text: &str) -> String {
+    Uuid::new_v5(&Uuid::NAMESPACE_OID, text.as_bytes()).to_string()
+}
+
+/// Last ingested version of every synced item, persisted as a JSON file.
+#[derive(Debug, Default)]
+pub struct SyncStateStore {
+    path: Option<PathBuf>,
+    versions: HashMap<String, String>,
+}
+
+impl SyncStateStore {
+    /// In-memory state, lost on restart.
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Loads the state from `path`, starting empty if the file does not exist yet.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let versions = match std::fs::read_to_string(&path) {
+            Ok(data) => serde_json::from_str(&data)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            path: Some(path),
+            versions,
+        })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(path) = &self.path {
+            std::fs::write(path, serde_json::to_string_pretty(&self.versions)?)?;
+        }
+        Ok(())
+    }
+
+    pub fn is_current(&self, key: &str, version: &str) -> bool {
+        self.versions.get(key).is_some_and(|v| v == version)
+    }
+
+    pub fn set(&mut self, key: impl Into<String>, version: impl Into<String>) {
+        self.versions.insert(key.into(), version.into());
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SyncReport {
+    pub ingested: usize,
+    pub unchanged: usize,
+    pub failed_sources: Vec<String>,
+    /// Links of entries that could not be fetched, embedded or written; they are retried
+    /// on the next pass.
+    pub failed_entries: Vec<String>,
+}
+
+struct PendingItem {
+    key: String,
+    source: String,
+    title: Option<String>,
+    text: String,
+    version: String,
+}
+
+/// Periodically re-crawls sources and ingests only new or changed items.
+///
+/// Items are keyed by their link, so a changed item overwrites its previous point instead
+/// of adding a new one. Pages are embedded whole; the embedding server should be started
+/// with truncation enabled for long pages.
+pub struct SyncScheduler {
+    sources: Vec<SyncSource>,
+    interval: Duration,
+    collection_name: String,
+    state: SyncStateStore,
+    http: HttpFetcher,
+}
+
+impl SyncScheduler {
+    pub fn new(
+        sources: Vec<SyncSource>,
+        interval: Duration,
+        collection_name: impl Into<String>,
+        state: SyncStateStore,
+    ) -> Self {
+        Self {
+            sources,
+            interval,
+            collection_name: collection_name.into(),
+            state,
+            http: HttpFetcher::default(),
+        }
+    }
+
+    /// Replaces the default fetcher, which waits a second between requests to a host.
+    pub fn with_fetcher(mut self, fetcher: HttpFetcher) -> Self {
+        self.http = fetcher;
+        self
+    }
+
+    async fn fetch(&self, url: &str) -> Result<String> {
+        Ok(self.http.fetch_text(url).await?.0)
+    }
+
+    /// Keeps `entry` if it is new or changed. Entries without content are fetched, unless
+    /// the version advertised by the source is unchanged.
+    async fn pending_item(
+        &self,
+        source: &SyncSource,
+        entry: SourceEntry,
+    ) -> Result<Option<PendingItem>> {
+        if let Some(version) = &entry.version {
+            if self.state.is_current(&entry.link, version) {
+                return Ok(None);
+            }
+        }
+
+        let text = match entry.content {
+            Some(content) => html_to_text(&content),
+            // Pages unchanged since the last pass are revalidated, not downloaded again,
+            // and then skipped below by their content hash
+            None => html_to_text(&self.fetch(&entry.link).await?),
+        };
+        let text = match &entry.title {
+            Some(title) => format!("{title}\n\n{text}"),
+            None => text,
+        };
+        let version = entry.version.unwrap_or_else(|| content_hash(&text));
+        if text.trim().is_empty() || self.state.is_current(&entry.link, &version) {
+            return Ok(None);
+        }
+
+        Ok(Some(PendingItem {
+            key: entry.link,
+            source: source.url.clone(),
+            title: entry.title,
+            text,
+            version,
+        }))
+    }
+
+    async fn sync_source(
+        &self,
+        source: &SyncSource,
+        report: &mut SyncReport,
+    ) -> Result<Vec<PendingItem>> {
+        let entries = match source.kind {
+            SourceKind::Url => vec![SourceEntry {
+                link: source.url.clone(),
+                ..Default::default()
+            }],
+            SourceKind::Feed => parse_feed(&self.fetch(&source.url).await?)?,
+            SourceKind::Sitemap => parse_sitemap(&self.fetch(&source.url).await?)?,
+        };
+
+        // One broken page doesn't keep the rest of its source from syncing
+        let mut pending = Vec::new();
+        for entry in entries {
+            let link = entry.link.clone();
+            match self.pending_item(source, entry).await {
+                Ok(Some(item)) => pending.push(item),
+                Ok(None) => report.unchanged += 1,
+                Err(e) => {
+                    log::warn!("Failed to sync {link}: {e}");
+                    report.failed_entries.push(link);
+                }
+            }
+        }
+        Ok(pending)
+    }
+
+    /// Runs one pass over all sources. A failing source or entry is reported and skipped;
+    /// the state is only updated for items that were upserted.
+    pub async fn sync_once(
+        &mut self,
+        text_embedding_client: &TextEmbeddingInference,
+        client: &QdrantClient,
+    ) -> Result<SyncReport> {
+        let mut report = SyncReport::default();
+        let mut pending = Vec::new();
+        for source in &self.sources {
+            match self.sync_source(source, &mut report).await {
+                Ok(items) => pending.extend(items),
+                Err(e) => {
+                    log::warn!("Failed to sync {}: {e}", source.url);
+                    report.failed_sources.push(source.url.clone());
+                }
+            }
+        }
+        if pending.is_empty() {
+            return Ok(report);
+        }
+
+        // Embedded and written batch by batch, so a failed batch only costs its own items
+        let batch_size = client.embedding_batcher().batch_size();
+        for batch in pending.chunks(batch_size) {
+            match self.write_batch(batch, text_embedding_client, client).await {
+                Ok(()) => {
+                    report.ingested += batch.len();
+                    for item in batch {
+                        self.state.set(item.key.clone(), item.version.clone());
+                    }
+                }
+                Err(e) => {
+                    log::warn!("Failed to write {} synced items: {e}", batch.len());
+                    report
+                        .failed_entries
+                        .extend(batch.iter().map(|item| item.key.clone()));
+                }
+            }
+        }
+        self.state.save()?;
+        Ok(report)
+    }
+
+    async fn write_batch(
+        &self,
+        batch: &[PendingItem],
+        text_embedding_client: &TextEmbeddingInference,
+        client: &QdrantClient,
+    ) -> Result<()> {
+        let texts: Vec<String> = batch.iter().map(|item| item.text.clone()).collect();
+        let embeddings = client
+            .embedding_batcher()
+            .embed(text_embedding_client, texts)
+            .await
+            .map_err(|e| anyhow!(e.to_string()))?;
+        let ids: Vec<String> = batch
+            .iter()
+            .map(|item| point_id_from_key(&item.key))
+            .collect();
+        let payloads: Vec<Payload> = batch
+            .iter()
+            .map(|item| {
+                let mut payload = Map::new();
+                client
+                    .payload_fields()
+                    .insert_text(&mut payload, &item.text);
+                payload.insert("title".to_string(), json!(item.title));
+                payload.insert(SOURCE_FIELD.to_string(), json!(item.key));
+                payload.insert("sync_source".to_string(), json!(item.source));
+                Payload::from(payload)
+            })
+            .collect();
+        client
+            .upsert_points_with_ids(&self.collection_name, ids, embeddings, payloads)
+            .await?;
+        Ok(())
+    }
+
+    /// Syncs every `interval` forever. Errors of a pass are logged and retried on the next tick.
+    pub async fn run(
+        &mut self,
+        text_embedding_client: &TextEmbeddingInference,
+        client: &QdrantClient,
+    ) {
+        let mut interval = tokio::time::interval(self.interval);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.sync_once(text_embedding_client, client).await {
+                log::error!("Sync failed: {e}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rss() {
+        let xml = r#"<?xml version="1.0"?>
+            <rss version="2.0"><channel><title>Blog</title>
+                <item>
+                    <title>New boots</title>
+                    <link>https://example.com/boots</link>
+                    <description><![CDATA[<p>Suede <b>ankle</b> boots</p>]]></description>
+                </item>
+                <item><guid>urn:post:2</guid><title>Second</title></item>
+            </channel></rss>"#;
+        let entries = parse_feed(xml).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].link, "https://example.com/boots");
+        assert_eq!(entries[0].title.as_deref(), Some("New boots"));
+        assert_eq!(
+            entries[0].content.as_deref(),
+            Some("<p>Suede <b>ankle</b> boots</p>")
+        );
+        assert_eq!(entries[1].link, "urn:post:2");
+    }
+
+    #[test]
+    fn test_parse_atom() {
+        let xml = r#"<feed xmlns="http://www.w3.org/2005/Atom">
+                <entry>
+                    <title>Release</title>
+                    <link href="https://example.com/release"/>
+                    <updated>2024-01-01T00:00:00Z</updated>
+                    <summary>Notes</summary>
+                </entry>
+            </feed>"#;
+        let entries = parse_feed(xml).unwrap();
+        assert_eq!(entries[0].link, "https://example.com/release");
+        assert_eq!(entries[0].version.as_deref(), Some("2024-01-01T00:00:00Z"));
+        assert_eq!(entries[0].content.as_deref(), Some("Notes"));
+    }
+
+    #[test]
+    fn test_parse_sitemap() {
+        let xml = r#"<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+                <url><loc>https://example.com/a</loc><lastmod>2024-05-01</lastmod></url>
+                <url><loc>https://example.com/b</loc></url>
+            </urlset>"#;
+        let entries = parse_sitemap(xml).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].version.as_deref(), Some("2024-05-01"));
+        assert_eq!(entries[1].version, None);
+    }
+
+    #[test]
+    fn test_sync_state_store() {
+        let path = std::env::temp_dir().join(format!("sync-state-{}.json", Uuid::new_v4()));
+        let mut state = SyncStateStore::load(&path).unwrap();
+        assert!(!state.is_current("https://example.com/a", "v1"));
+
+        state.set("https://example.com/a", "v1");
+        state.save().unwrap();
+
+        let state = SyncStateStore::load(&path).unwrap();
+        assert!(state.is_current("https://example.com/a", "v1"));
+        assert!(!state.is_current("https://example.com/a", "v2"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sync_source_skips_failed_entries() {
+        let mut server = mockito::Server::new_async().await;
+        let sitemap = format!(
+            "<urlset><url><loc>{0}/a</loc></url><url><loc>{0}/b</loc></url></urlset>",
+            server.url()
+        );
+        server
+            .mock("GET", "/sitemap.xml")
+            .with_header("content-type", "application/xml")
+            .with_body(sitemap)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/a")
+            .with_status(500)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/b")
+            .with_header("content-type", "text/html")
+            .with_body("<p>Suede boots</p>")
+            .create_async()
+            .await;
+
+        let scheduler = SyncScheduler::new(
+            Vec::new(),
+            Duration::from_secs(60),
+            "pages",
+            SyncStateStore::in_memory(),
+        )
+        .with_fetcher(HttpFetcher::new(Duration::ZERO));
+        let source = SyncSource::new(format!("{}/sitemap.xml", server.url()), SourceKind::Sitemap);
+        let mut report = SyncReport::default();
+        let pending = scheduler.sync_source(&source, &mut report).await.unwrap();
+
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].key, format!("{}/b", server.url()));
+        assert_eq!(report.failed_entries, vec![format!("{}/a", server.url())]);
+    }
+}
@@ -0,0 +1,438 @@
+use crate::circuit_breaker::TransientError;
+use crate::embeddings::sparse::SparseEmbedding;
+use crate::vectorstore::filter::MetadataFilter;
+use qdrant_client::QdrantError;
+use serde::Serialize;
+use serde_json::{Map, Value as JsonValue};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum VectorStoreError {
+    #[error("Qdrant Error: {0}")]
+    QdrantError(#[from] QdrantError),
+    #[error("Collection not found: {0}")]
+    CollectionNotFound(String),
+    #[error("Point not found: {0}")]
+    PointNotFound(String),
+    #[error("Vector dimension mismatch: expected {expected}, got {actual}")]
+    DimensionMismatch { expected: usize, actual: usize },
+    #[error("Empty vector for point {0}")]
+    EmptyVector(String),
+    #[error("Embedding Error: {0}")]
+    EmbeddingError(String),
+    #[error("Not supported by this backend: {0}")]
+    Unsupported(String),
+    #[error("IO Error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Serialization Error: {0}")]
+    SerializationError(#[from] serde_json::Error),
+}
+
+impl TransientError for VectorStoreError {
+    fn is_transient(&self) -> bool {
+        match self {
+            VectorStoreError::QdrantError(e) => e.is_transient(),
+            VectorStoreError::IoError(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// A point as stored by any [`VectorStore`] backend.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VectorPoint {
+    pub id: String,
+    pub vector: Vec<f32>,
+    pub payload: Map<String, JsonValue>,
+}
+
+impl VectorPoint {
+    pub fn new(id: impl Into<String>, vector: Vec<f32>, payload: Map<String, JsonValue>) -> Self {
+        Self {
+            id: id.into(),
+            vector,
+            payload,
+        }
+    }
+}
+
+/// A point with several named vectors, e.g. `image` and `text` embeddings of one product.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MultiVectorPoint {
+    pub id: String,
+    pub vectors: BTreeMap<String, Vec<f32>>,
+    /// Named sparse vectors, e.g. of a hybrid collection. Only Qdrant stores them.
+    pub sparse_vectors: BTreeMap<String, SparseEmbedding>,
+    pub payload: Map<String, JsonValue>,
+}
+
+impl MultiVectorPoint {
+    pub fn new(id: impl Into<String>, payload: Map<String, JsonValue>) -> Self {
+        Self {
+            id: id.into(),
+            vectors: BTreeMap::new(),
+            sparse_vectors: BTreeMap::new(),
+            payload,
+        }
+    }
+
+    pub fn with_vector(mut self, name: impl Into<String>, vector: Vec<f32>) -> Self {
+        self.vectors.insert(name.into(), vector);
+        self
+    }
+
+    pub fn with_sparse_vector(mut self, name: impl Into<String>, vector: SparseEmbedding) -> Self {
+        self.sparse_vectors.insert(name.into(), vector);
+        self
+    }
+}
+
+/// Collection holding the `vector_name` vectors of `collection` on backends without native
+/// named vectors.
+pub fn named_vector_collection(collection: &str, vector_name: &str) -> String {
+    format!("{collection}.{vector_name}")
+}
+
+/// A query result. `score` is the cosine similarity to the query.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchHit {
+    pub id: String,
+    pub score: f32,
+    pub payload: Map<String, JsonValue>,
+}
+
+// Points copied per page by `copy_collection`
+const COPY_PAGE_SIZE: u64 = 256;
+
+/// Copies every point of `collection` from one backend to another page by page, keeping
+/// ids, vectors and payloads, e.g. to migrate off Qdrant without re-embedding. Returns
+/// the number of points copied. Backends that do not create collections on write
+/// (Qdrant) need the destination collection created first. Collections with named
+/// vectors are copied with [`copy_multi_collection`].
+pub async fn copy_collection(
+    src: &impl VectorStore,
+    dst: &impl VectorStore,
+    collection: &str,
+) -> Result<u64, VectorStoreError> {
+    let mut copied = 0;
+    let mut offset = None;
+    loop {
+        let (points, next) = src.scroll(collection, offset, COPY_PAGE_SIZE).await?;
+        copied += points.len() as u64;
+        dst.upsert(collection, points).await?;
+        match next {
+            Some(next) => offset = Some(next),
+            None => return Ok(copied),
+        }
+    }
+}
+
+/// Like [`copy_collection`], for a collection written with [`VectorStore::upsert_multi`],
+/// e.g. a multivector or hybrid one, keeping every named vector. Emulating backends need
+/// the vector names to find every copy.
+pub async fn copy_multi_collection(
+    src: &impl VectorStore,
+    dst: &impl VectorStore,
+    collection: &str,
+    vector_names: &[&str],
+) -> Result<u64, VectorStoreError> {
+    let mut copied = 0;
+    let mut offset = None;
+    loop {
+        let (points, next) = src
+            .scroll_multi(collection, vector_names, offset, COPY_PAGE_SIZE)
+            .await?;
+        copied += points.len() as u64;
+        dst.upsert_multi(collection, points).await?;
+        match next {
+            Some(next) => offset = Some(next),
+            None => return Ok(copied),
+        }
+    }
+}
+
+/// Backend-agnostic vector storage, implemented by Qdrant, the in-memory and the
+/// memory-mapped store.
+#[allow(async_fn_in_trait)]
+pub trait VectorStore {
+    /// Inserts or replaces points by id. Backends may create the collection on first write.
+    async fn upsert(
+        &self,
+        collection: &str,
+        points: Vec<VectorPoint>,
+    ) -> Result<(), VectorStoreError>;
+
+    /// Returns the `limit` points most similar to `vector`, best first.
+    async fn query(
+        &self,
+        collection: &str,
+        vector: Vec<f32>,
+        limit: u64,
+    ) -> Result<Vec<SearchHit>, VectorStoreError> {
+        self.query_filtered(collection, vector, limit, None).await
+    }
+
+    /// Like [`VectorStore::query`], restricted to points whose payload matches `filter`.
+    /// The filter is applied before ranking, so up to `limit` matching points are returned.
+    async fn query_filtered(
+        &self,
+        collection: &str,
+        vector: Vec<f32>,
+        limit: u64,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<SearchHit>, VectorStoreError>;
+
+    async fn delete(&self, collection: &str, ids: Vec<String>) -> Result<(), VectorStoreError>;
+
+    /// Deletes every point whose payload matches `filter` and returns how many there were.
+    async fn delete_matching(
+        &self,
+        collection: &str,
+        filter: &MetadataFilter,
+    ) -> Result<u64, VectorStoreError>;
+
+    /// Merges `fields` into the payload of the given points, leaving other fields
+    /// untouched. Fails with [`VectorStoreError::PointNotFound`] without changing anything
+    /// if a point does not exist.
+    async fn set_payload(
+        &self,
+        collection: &str,
+        ids: Vec<String>,
+        fields: Map<String, JsonValue>,
+    ) -> Result<(), VectorStoreError>;
+
+    /// Removes `keys` from the payload of the given points, failing like
+    /// [`VectorStore::set_payload`].
+    async fn delete_payload(
+        &self,
+        collection: &str,
+        ids: Vec<String>,
+        keys: Vec<String>,
+    ) -> Result<(), VectorStoreError>;
+
+    /// Up to `limit` points of the collection in id order, starting at `offset`, with the
+    /// offset of the next page or `None` after the last one.
+    async fn scroll(
+        &self,
+        collection: &str,
+        offset: Option<String>,
+        limit: u64,
+    ) -> Result<(Vec<VectorPoint>, Option<String>), VectorStoreError>;
+
+    /// Inserts or replaces points with named vectors. Backends without native support keep
+    /// each name in its own collection (see [`named_vector_collection`]), with a copy of
+    /// the payload, so there a name missing from a replaced point keeps its old vector.
+    /// They reject sparse vectors with [`VectorStoreError::Unsupported`].
+    async fn upsert_multi(
+        &self,
+        collection: &str,
+        points: Vec<MultiVectorPoint>,
+    ) -> Result<(), VectorStoreError> {
+        if let Some(point) = points.iter().find(|point| !point.sparse_vectors.is_empty()) {
+            return Err(VectorStoreError::Unsupported(format!(
+                "sparse vectors of point {}",
+                point.id
+            )));
+        }
+        let mut by_name: BTreeMap<String, Vec<VectorPoint>> = BTreeMap::new();
+        for point in points {
+            for (name, vector) in point.vectors {
+                by_name.entry(name).or_default().push(VectorPoint::new(
+                    point.id.clone(),
+                    vector,
+                    point.payload.clone(),
+                ));
+            }
+        }
+        for (name, points) in by_name {
+            self.upsert(&named_vector_collection(collection, &name), points)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Like [`VectorStore::scroll`], for points written with [`VectorStore::upsert_multi`].
+    /// Emulating backends need the vector names to find every copy and page through them
+    /// together, which relies on their ids scrolling in string order.
+    async fn scroll_multi(
+        &self,
+        collection: &str,
+        vector_names: &[&str],
+        offset: Option<String>,
+        limit: u64,
+    ) -> Result<(Vec<MultiVectorPoint>, Option<String>), VectorStoreError> {
+        let mut points: BTreeMap<String, MultiVectorPoint> = BTreeMap::new();
+        let mut next: Option<String> = None;
+        for name in vector_names {
+            let (page, page_next) = match self
+                .scroll(
+                    &named_vector_collection(collection, name),
+                    offset.clone(),
+                    limit,
+                )
+                .await
+            {
+                Ok(page) => page,
+                Err(VectorStoreError::CollectionNotFound(_)) => continue,
+                Err(e) => return Err(e),
+            };
+            for point in page {
+                points
+                    .entry(point.id.clone())
+                    .or_insert_with(|| MultiVectorPoint::new(point.id, point.payload))
+                    .vectors
+                    .insert(name.to_string(), point.vector);
+            }
+            // Past the end of this name's page its vectors are not known yet
+            next = match (next, page_next) {
+                (Some(next), Some(page_next)) => Some(next.min(page_next)),
+                (next, page_next) => next.or(page_next),
+            };
+        }
+        if let Some(next) = &next {
+            points.split_off(next);
+        }
+        Ok((points.into_values().collect(), next))
+    }
+
+    /// Replaces the `vector_name` vector of a point written with
+    /// [`VectorStore::upsert_multi`], leaving its other vectors and payload untouched, e.g.
+    /// to re-embed an improved caption without re-upserting the image vector.
+    async fn update_named_vector(
+        &self,
+        collection: &str,
+        point_id: &str,
+        vector_name: &str,
+        vector: Vec<f32>,
+    ) -> Result<(), VectorStoreError>;
+
+    /// Like [`VectorStore::query_filtered`], ranking by the `vector_name` vectors of points
+    /// written with [`VectorStore::upsert_multi`].
+    async fn query_named(
+        &self,
+        collection: &str,
+        vector_name: &str,
+        vector: Vec<f32>,
+        limit: u64,
+        filter: Option<&MetadataFilter>,
+    ) -> Result<Vec<SearchHit>, VectorStoreError> {
+        self.query_filtered(
+            &named_vector_collection(collection, vector_name),
+            vector,
+            limit,
+            filter,
+        )
+        .await
+    }
+
+    /// Deletes points written with [`VectorStore::upsert_multi`]. Emulating backends need
+    /// the vector names to find every copy.
+    async fn delete_multi(
+        &self,
+        collection: &str,
+        vector_names: &[&str],
+        ids: Vec<String>,
+    ) -> Result<(), VectorStoreError> {
+        for name in vector_names {
+            match self
+                .delete(&named_vector_collection(collection, name), ids.clone())
+                .await
+            {
+                Ok(()) | Err(VectorStoreError::CollectionNotFound(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vectorstore::in_memory::InMemoryVectorStore;
+    use crate::vectorstore::mmap_store::MmapVectorStore;
+    use serde_json::json;
+
+    fn payload(text: &str) -> Map<String, JsonValue> {
+        json!({"text": text}).as_object().unwrap().clone()
+    }
+
+    #[tokio::test]
+    async fn test_copy_collection() {
+        let src = InMemoryVectorStore::new();
+        let points: Vec<VectorPoint> = (0..300)
+            .map(|idx| {
+                VectorPoint::new(format!("{idx:03}"), vec![1.0, idx as f32], payload("boots"))
+            })
+            .collect();
+        src.upsert("memories", points).await.unwrap();
+
+        let (page, next) = src.scroll("memories", None, 2).await.unwrap();
+        assert_eq!(page[1].id, "001");
+        assert_eq!(next.as_deref(), Some("002"));
+
+        let dir = std::env::temp_dir().join(format!("liquid-memory-{}", uuid::Uuid::new_v4()));
+        let dst = MmapVectorStore::open(&dir).unwrap();
+        assert_eq!(copy_collection(&src, &dst, "memories").await.unwrap(), 300);
+        assert_eq!(dst.len("memories"), 300);
+        assert_eq!(
+            dst.get("memories", "299").unwrap(),
+            src.get("memories", "299")
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_copy_multi_collection() {
+        let src = InMemoryVectorStore::new();
+        // Every third product has no text vector, so the names' pages end at different ids
+        let points: Vec<MultiVectorPoint> = (0..300)
+            .map(|idx| {
+                let point = MultiVectorPoint::new(format!("{idx:03}"), payload("boots"))
+                    .with_vector("image", vec![1.0, idx as f32]);
+                match idx % 3 {
+                    0 => point,
+                    _ => point.with_vector("text", vec![idx as f32, 1.0, 0.0]),
+                }
+            })
+            .collect();
+        src.upsert_multi("products", points.clone()).await.unwrap();
+
+        let names = ["image", "text"];
+        // The text page ends past the image page: its vectors from there on are left for
+        // the next page
+        let (page, next) = src.scroll_multi("products", &names, None, 4).await.unwrap();
+        assert_eq!(page, points[..4].to_vec());
+        assert_eq!(next.as_deref(), Some("004"));
+        let (page, next) = src
+            .scroll_multi("products", &names, Some("297".to_string()), 4)
+            .await
+            .unwrap();
+        assert_eq!(page, points[297..].to_vec());
+        assert_eq!(next, None);
+
+        let dst = InMemoryVectorStore::new();
+        assert_eq!(
+            copy_multi_collection(&src, &dst, "products", &names)
+                .await
+                .unwrap(),
+            300
+        );
+        let (copied, _) = dst
+            .scroll_multi("products", &names, None, 300)
+            .await
+            .unwrap();
+        assert_eq!(copied, points);
+
+        let hybrid = MultiVectorPoint::new("boot", payload("boots"))
+            .with_vector("dense", vec![1.0, 0.0])
+            .with_sparse_vector("sparse", SparseEmbedding::default());
+        assert!(matches!(
+            dst.upsert_multi("hybrid", vec![hybrid]).await,
+            Err(VectorStoreError::Unsupported(_))
+        ));
+        assert_eq!(dst.len("hybrid.dense"), 0);
+    }
+}
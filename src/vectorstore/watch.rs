@@ -0,0 +1,178 @@
+use crate::utils::fnv1a;
+use crate::vectorstore::qdrant_client::{point_id_to_string, QdrantClient};
+use futures::stream::{self, Stream};
+use qdrant_client::QdrantError;
+use serde::Serialize;
+use serde_json::{Map, Value as JsonValue};
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+/// A point written or deleted since the previous poll of [`QdrantClient::watch`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum CollectionChange {
+    Upserted {
+        id: String,
+        payload: Map<String, JsonValue>,
+    },
+    Deleted {
+        id: String,
+    },
+}
+
+/// Payload hashes of every point of a collection, diffed poll after poll.
+#[derive(Debug, Clone, Default)]
+pub struct CollectionSnapshot {
+    hashes: HashMap<String, u64>,
+}
+
+impl CollectionSnapshot {
+    /// Replaces the snapshot with `points` and returns what changed, upserts in the order
+    /// of `points` and then deletes sorted by id.
+    pub fn update(
+        &mut self,
+        points: Vec<(String, Map<String, JsonValue>)>,
+    ) -> Vec<CollectionChange> {
+        let mut changes = Vec::new();
+        let mut hashes = HashMap::with_capacity(points.len());
+        for (id, payload) in points {
+            // Maps are sorted by key, so equal payloads serialize to the same bytes
+            let hash = fnv1a(JsonValue::Object(payload.clone()).to_string().as_bytes());
+            if self.hashes.get(&id) != Some(&hash) {
+                changes.push(CollectionChange::Upserted {
+                    id: id.clone(),
+                    payload,
+                });
+            }
+            hashes.insert(id, hash);
+        }
+        let mut deleted: Vec<&String> = self
+            .hashes
+            .keys()
+            .filter(|id| !hashes.contains_key(*id))
+            .collect();
+        deleted.sort();
+        changes.extend(
+            deleted
+                .into_iter()
+                .map(|id| CollectionChange::Deleted { id: id.clone() }),
+        );
+        self.hashes = hashes;
+        changes
+    }
+}
+
+struct WatchState<'a> {
+    client: &'a QdrantClient,
+    collection_name: String,
+    interval: Duration,
+    snapshot: Option<CollectionSnapshot>,
+    pending: VecDeque<CollectionChange>,
+    polled: bool,
+}
+
+impl QdrantClient {
+    /// Stream of the points upserted or deleted in a collection, polled every `interval`
+    /// by scrolling the collection and diffing payload hashes, so it sees writes from any
+    /// client. The first poll only takes the baseline; updates that change vectors but
+    /// not payloads are not seen. For writes through a
+    /// [`MemoryStore`](crate::memory::memory_store::MemoryStore), its event bus is
+    /// cheaper, see [`MemoryStore::watch`](crate::memory::memory_store::MemoryStore::watch).
+    pub fn watch(
+        &self,
+        collection_name: impl Into<String>,
+        interval: Duration,
+    ) -> impl Stream<Item = Result<CollectionChange, QdrantError>> + '_ {
+        let state = WatchState {
+            client: self,
+            collection_name: collection_name.into(),
+            interval,
+            snapshot: None,
+            pending: VecDeque::new(),
+            polled: false,
+        };
+        stream::unfold(state, |mut state| async move {
+            loop {
+                if let Some(change) = state.pending.pop_front() {
+                    return Some((Ok(change), state));
+                }
+                if state.polled {
+                    tokio::time::sleep(state.interval).await;
+                }
+                state.polled = true;
+                let points = match state
+                    .client
+                    .scroll_all(&state.collection_name, None, false)
+                    .await
+                {
+                    Ok(points) => points,
+                    Err(e) => return Some((Err(e), state)),
+                };
+                let points = points
+                    .into_iter()
+                    .filter_map(|point| {
+                        let id = point.id.as_ref().map(point_id_to_string)?;
+                        let payload = point
+                            .payload
+                            .into_iter()
+                            .map(|(key, value)| (key, value.into_json()))
+                            .collect();
+                        Some((id, payload))
+                    })
+                    .collect();
+                match &mut state.snapshot {
+                    Some(snapshot) => state.pending.extend(snapshot.update(points)),
+                    None => {
+                        let mut snapshot = CollectionSnapshot::default();
+                        snapshot.update(points);
+                        state.snapshot = Some(snapshot);
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn point(id: &str, text: &str) -> (String, Map<String, JsonValue>) {
+        let mut payload = Map::new();
+        payload.insert("text".to_string(), json!(text));
+        (id.to_string(), payload)
+    }
+
+    #[test]
+    fn test_snapshot_update() {
+        let mut snapshot = CollectionSnapshot::default();
+        assert_eq!(
+            snapshot
+                .update(vec![point("a", "Boots"), point("b", "Sandals")])
+                .len(),
+            2
+        );
+        assert!(snapshot
+            .update(vec![point("a", "Boots"), point("b", "Sandals")])
+            .is_empty());
+
+        let changes = snapshot.update(vec![point("a", "Waterproof boots"), point("c", "Clogs")]);
+        assert_eq!(
+            changes,
+            vec![
+                CollectionChange::Upserted {
+                    id: "a".to_string(),
+                    payload: point("a", "Waterproof boots").1,
+                },
+                CollectionChange::Upserted {
+                    id: "c".to_string(),
+                    payload: point("c", "Clogs").1,
+                },
+                CollectionChange::Deleted {
+                    id: "b".to_string()
+                },
+            ]
+        );
+    }
+}